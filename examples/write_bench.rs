@@ -0,0 +1,32 @@
+//! Compare write settings against a server:
+//!
+//! ```sh
+//! INFLUXDB_HOST=http://localhost:8086 INFLUXDB_ORG=org INFLUXDB_TOKEN=token \
+//!     INFLUXDB_BUCKET=bench cargo run --release --example write_bench --features bench
+//! ```
+
+use influxdb2::bench::{self, BenchConfig};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let host = std::env::var("INFLUXDB_HOST")?;
+    let org = std::env::var("INFLUXDB_ORG")?;
+    let token = std::env::var("INFLUXDB_TOKEN")?;
+    let bucket = std::env::var("INFLUXDB_BUCKET").unwrap_or_else(|_| "bench".to_string());
+    let points = match std::env::var("BENCH_POINTS") {
+        Ok(points) => points.parse()?,
+        Err(_) => 200_000,
+    };
+
+    let client = influxdb2::Client::new(host, org, token);
+    for batch in [1000, 5000, 20_000] {
+        for concurrency in [1, 4] {
+            let config = BenchConfig::new(points)
+                .batch(batch)
+                .concurrency(concurrency);
+            println!("{}", bench::run(&client, &bucket, &config).await?);
+        }
+    }
+
+    Ok(())
+}
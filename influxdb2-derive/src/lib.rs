@@ -7,13 +7,83 @@ extern crate proc_macro;
 use itertools::izip;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{Data, DeriveInput, Ident};
+use syn::{Data, DeriveInput, Field, Ident, Lit, Meta, MetaNameValue, NestedMeta};
+
+/// The options of a field given in `#[influxdb(...)]` attributes
+#[derive(Default)]
+struct FieldOptions {
+    /// `flatten`: the field is read through its own `FromMap` implementation
+    flatten: bool,
+    /// `tags`: the field collects the string columns of no other field
+    tags: bool,
+    /// `rename = "..."`: the column the field is read from
+    rename: Option<String>,
+    /// `deserialize_with = "..."`: the function reading the field from the
+    /// value of its column
+    deserialize_with: Option<syn::ExprPath>,
+}
+
+fn field_options(field: &Field) -> FieldOptions {
+    let mut options = FieldOptions::default();
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("influxdb")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => panic!("Unsupported influxdb attribute: {}", quote! {#attr}),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                    options.flatten = true
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("tags") => options.tags = true,
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(column),
+                    ..
+                })) if path.is_ident("rename") => options.rename = Some(column.value()),
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(function),
+                    ..
+                })) if path.is_ident("deserialize_with") => {
+                    let function = function.parse().unwrap_or_else(|_| {
+                        panic!("Invalid deserialize_with path: {}", function.value())
+                    });
+                    options.deserialize_with = Some(function);
+                }
+                _ => panic!("Unsupported influxdb option: {}", quote! {#nested}),
+            }
+        }
+    }
+    options
+}
 
 /// Implements the functionality for converting entries in a BTreeMap into 
 /// attributes and values of a struct. It will consume a tokenized version of 
 /// the initial struct declaration, and use code generation to implement the 
 /// `FromMap` trait for instantiating the contents of the struct.
-#[proc_macro_derive(FromDataPoint )]
+///
+/// A field marked `#[influxdb(flatten)]` is built from the same columns
+/// through its own `FromMap` implementation, so columns shared by several
+/// structs can be grouped in one struct instead of repeated.
+///
+/// A map field marked `#[influxdb(tags)]` receives every string column that
+/// isn't read into another field, except `result` and the columns starting
+/// with `_`, such as the tags of measurements whose tag sets vary.
+///
+/// A field is read from the column of its name, or of its name prefixed
+/// with `_`, such as `_start` for `start`. `#[influxdb(rename = "_start")]`
+/// reads it from a column of another name.
+///
+/// `#[influxdb(deserialize_with = "path")]` reads a field of any type by
+/// calling the function at `path` with the `Value` of its column, e.g. to
+/// parse a string column holding JSON into a nested type. The function
+/// returns a `Result` whose error implements `Display`, and an error panics.
+///
+/// `i64` and `u64` fields read both `long` and `unsignedLong` columns. A
+/// value out of their range is a `FromMapError` of `try_from_genericmap`,
+/// and panics in `from_genericmap`, instead of wrapping.
+#[proc_macro_derive(FromDataPoint, attributes(influxdb))]
 pub fn from_data_point(input: TokenStream) -> TokenStream {
     let ast = syn::parse_macro_input!(input as DeriveInput);
 
@@ -46,6 +116,9 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
         .map(|ident| ident.to_string())
         .collect::<Vec<String>>();
 
+    // Options: i.e. `#[influxdb(flatten)]`
+    let options = fields.iter().map(field_options).collect::<Vec<FieldOptions>>();
+
     // Typenames: i.e. "String", "f64", "i64"
     let typenames = fields
         .iter()
@@ -63,8 +136,59 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
     let datetime_re = regex::Regex::new(r"DateTime").unwrap();
     let duration_re = regex::Regex::new(r"Duration").unwrap();
     let base64_re = regex::Regex::new(r"Vec").unwrap();
+    let tys = fields.iter().map(|field| &field.ty);
     let mut assignments = Vec::new();
-    for (key, typename, ident) in izip!(keys, typenames, idents) {
+    let mut columns = Vec::new();
+    let mut tags = Vec::new();
+    for (key, typename, ident, ty, options) in izip!(keys, typenames, idents, tys, options) {
+        if options.flatten {
+            assignments.push(quote! {
+                settings.#ident = influxdb2_structmap::FromMap::try_from_genericmap(hashmap.clone())?;
+            });
+            columns.push(quote! {
+                <#ty as influxdb2_structmap::FromMap>::columns()
+            });
+            continue;
+        }
+        if options.tags {
+            // Filled in last, once the columns of the other fields are known
+            tags.push(quote! {
+                settings.#ident = hashmap
+                    .iter()
+                    .filter(|(key, _)| {
+                        !key.starts_with('_') && *key != "result" && !columns.contains(key)
+                    })
+                    .filter_map(|(key, value)| match value {
+                        influxdb2_structmap::value::Value::String(v) => Some((key.clone(), v.clone())),
+                        _ => None,
+                    })
+                    .collect();
+            });
+            continue;
+        }
+        let key = options.rename.unwrap_or(key);
+        columns.push(quote! {
+            vec![String::from(#key)]
+        });
+
+        if let Some(function) = options.deserialize_with {
+            assignments.push(quote! {
+                let mut key = String::from(#key);
+                if !hashmap.contains_key(&key) {
+                    key = format!("_{}", key);
+                }
+                match hashmap.get(&key) {
+                    Some(value) => {
+                        settings.#ident = #function(value).unwrap_or_else(|e| {
+                            panic!("Cannot deserialize map entry, key: {}, error: {}", key, e)
+                        });
+                    },
+                    None => panic!("Cannot parse out map entry, key: {}", key),
+                }
+            });
+            continue;
+        }
+
         match &typename[..] {
             "f64" => {
                 assignments.push(quote! {
@@ -90,8 +214,34 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
                     }
                     match hashmap.entry(key.clone()) {
                         ::std::collections::btree_map::Entry::Occupied(entry) => {
-                            if let influxdb2_structmap::value::Value::Long(v) = entry.get() {
-                                settings.#ident = *v;
+                            match entry.get() {
+                                influxdb2_structmap::value::Value::Long(v) => settings.#ident = *v,
+                                influxdb2_structmap::value::Value::UnsignedLong(v) => {
+                                    settings.#ident = match ::std::convert::TryFrom::try_from(*v) {
+                                        Ok(v) => v,
+                                        Err(_) => {
+                                            return Err(influxdb2_structmap::FromMapError::OutOfRange {
+                                                key,
+                                                value: v.to_string(),
+                                                ty: "i64",
+                                            })
+                                        }
+                                    }
+                                }
+                                // The nanoseconds of a `duration` column
+                                influxdb2_structmap::value::Value::Duration(v) => {
+                                    settings.#ident = match v.num_nanoseconds() {
+                                        Some(v) => v,
+                                        None => {
+                                            return Err(influxdb2_structmap::FromMapError::OutOfRange {
+                                                key,
+                                                value: v.to_string(),
+                                                ty: "i64",
+                                            })
+                                        }
+                                    }
+                                }
+                                _ => {}
                             }
                         },
                         _ => panic!("Cannot parse out map entry, key: {}", key),
@@ -106,8 +256,21 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
                     }
                     match hashmap.entry(key.clone()) {
                         ::std::collections::btree_map::Entry::Occupied(entry) => {
-                            if let influxdb2_structmap::value::Value::UnsignedLong(v) = entry.get() {
-                                settings.#ident = *v;
+                            match entry.get() {
+                                influxdb2_structmap::value::Value::UnsignedLong(v) => settings.#ident = *v,
+                                influxdb2_structmap::value::Value::Long(v) => {
+                                    settings.#ident = match ::std::convert::TryFrom::try_from(*v) {
+                                        Ok(v) => v,
+                                        Err(_) => {
+                                            return Err(influxdb2_structmap::FromMapError::OutOfRange {
+                                                key,
+                                                value: v.to_string(),
+                                                ty: "u64",
+                                            })
+                                        }
+                                    }
+                                }
+                                _ => {}
                             }
                         },
                         _ => panic!("Cannot parse out map entry, key: {}", key),
@@ -155,7 +318,7 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
                     match hashmap.entry(key.clone()) {
                         ::std::collections::btree_map::Entry::Occupied(entry) => {
                             if let influxdb2_structmap::value::Value::Duration(v) = entry.get() {
-                                settings.#ident = *v;
+                                settings.#ident = influxdb2_structmap::value::FromDuration::from_duration(&key, *v)?;
                             }
                         },
                         _ => panic!("Cannot parse out map entry, key: {}", key),
@@ -187,7 +350,7 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
                     match hashmap.entry(key.clone()) {
                         ::std::collections::btree_map::Entry::Occupied(entry) => {
                             if let influxdb2_structmap::value::Value::Base64Binary(v) = entry.get() {
-                                settings.#ident = *v;
+                                settings.#ident = v.clone();
                             }
                         },
                         _ => panic!("Cannot parse out map entry, key: {}", key),
@@ -200,18 +363,41 @@ pub fn from_data_point(input: TokenStream) -> TokenStream {
         }
     }
 
+    let tags = match &tags[..] {
+        [] => quote! {},
+        _ => quote! {
+            let columns = <Self as influxdb2_structmap::FromMap>::columns();
+            #(
+                #tags
+            )*
+        },
+    };
+
     // start codegen of a generic or non-generic impl for the given struct using quasi-quoting
     let tokens = quote! {
         impl #impl_generics influxdb2_structmap::FromMap for #name #ty_generics #where_clause {
 
-            fn from_genericmap(mut hashmap: influxdb2_structmap::GenericMap) -> #name {
+            fn from_genericmap(hashmap: influxdb2_structmap::GenericMap) -> #name {
+                Self::try_from_genericmap(hashmap).unwrap_or_else(|e| panic!("{}", e))
+            }
+
+            fn try_from_genericmap(
+                mut hashmap: influxdb2_structmap::GenericMap,
+            ) -> ::std::result::Result<#name, influxdb2_structmap::FromMapError> {
                 let mut settings = #name::default();
 
                 #(
                     #assignments
                 )*
 
-                settings
+                #tags
+
+                Ok(settings)
+            }
+
+            fn columns() -> Vec<String> {
+                let columns: Vec<Vec<String>> = vec![#(#columns),*];
+                columns.concat()
             }
 
         }
@@ -226,6 +412,9 @@ mod tests {
         let t = trybuild::TestCases::new();
         t.pass("tests/struct.rs");
         t.pass("tests/multistruct.rs");
+        t.pass("tests/flatten.rs");
+        t.pass("tests/tags.rs");
+        t.pass("tests/deserialize_with.rs");
     }
 }
 
@@ -0,0 +1,24 @@
+use influxdb2_derive::FromDataPoint;
+use influxdb2_structmap::value::Value;
+
+#[derive(Default)]
+struct Pair(String, String);
+
+fn pair(value: &Value) -> Result<Pair, String> {
+    match value {
+        Value::String(text) => match text.split_once(',') {
+            Some((a, b)) => Ok(Pair(a.to_string(), b.to_string())),
+            None => Err(format!("not a pair: {}", text)),
+        },
+        _ => Err("not a string".to_string()),
+    }
+}
+
+#[derive(Default, FromDataPoint)]
+struct Point {
+    value: f64,
+    #[influxdb(deserialize_with = "pair")]
+    pair: Pair,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use influxdb2_derive::FromDataPoint;
+
+#[derive(Default, FromDataPoint)]
+struct Host {
+    host: String,
+    region: String,
+}
+
+#[derive(FromDataPoint)]
+struct CpuUsage {
+    #[influxdb(flatten)]
+    host: Host,
+    usage: f64,
+    time: chrono::DateTime<chrono::FixedOffset>,
+}
+
+impl Default for CpuUsage {
+    fn default() -> Self {
+        Self {
+            host: Host::default(),
+            usage: 0.0,
+            time: chrono::DateTime::parse_from_rfc3339("2020-02-17T22:19:49.747562847Z").unwrap(),
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use std::collections::HashMap;
+
+use influxdb2_derive::FromDataPoint;
+
+#[derive(Default, FromDataPoint)]
+struct Point {
+    measurement: String,
+    value: f64,
+    #[influxdb(tags)]
+    tags: HashMap<String, String>,
+}
+
+fn main() {}
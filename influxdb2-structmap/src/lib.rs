@@ -4,14 +4,55 @@
 pub mod value;
 
 use std::collections::BTreeMap;
+use std::fmt;
 
 // Alias for BTreeMap with String keys and generic values
 pub type GenericMap = BTreeMap<String, value::Value>;
 
+/// An error converting a `GenericMap` into a structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromMapError {
+    /// An integer entry that doesn't fit the integer type of its field.
+    OutOfRange {
+        /// The key of the entry
+        key: String,
+        /// The value of the entry
+        value: String,
+        /// The type of the field
+        ty: &'static str,
+    },
+}
+
+impl fmt::Display for FromMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange { key, value, ty } => write!(
+                f,
+                "Map entry out of range for {}, key: {}, value: {}",
+                ty, key, value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FromMapError {}
+
 pub trait FromMap: Default {
     /// Converts a `GenericMap` back into a structure.
     /// __Constraints__: assumes that value types conform to the original types of the struct.
     fn from_genericmap(map: GenericMap) -> Self;
+
+    /// Converts a `GenericMap` back into a structure, returning an error
+    /// instead of panicking on the values it checks.
+    fn try_from_genericmap(map: GenericMap) -> Result<Self, FromMapError> {
+        Ok(Self::from_genericmap(map))
+    }
+
+    /// The columns `from_genericmap` reads into fields, which a catch-all
+    /// tags field of an enclosing struct leaves out.
+    fn columns() -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub trait ToMap: Default {
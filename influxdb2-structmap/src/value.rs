@@ -5,6 +5,8 @@ use chrono::{DateTime, FixedOffset};
 use num_traits::cast::ToPrimitive;
 use ordered_float::OrderedFloat;
 
+use crate::FromMapError;
+
 /// Represents primitive types that are supported for conversion into a BTreeMap that can support
 /// heterogeneous values. Inspired by `serde_json::Value`s.
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
@@ -20,6 +22,31 @@ pub enum Value {
     TimeRFC(DateTime<FixedOffset>),
 }
 
+/// The field types a `duration` column can be read into
+pub trait FromDuration: Sized {
+    /// Converts the duration of the column `key`, failing if the field type
+    /// can't hold it.
+    fn from_duration(key: &str, duration: chrono::Duration) -> Result<Self, FromMapError>;
+}
+
+impl FromDuration for chrono::Duration {
+    fn from_duration(_key: &str, duration: chrono::Duration) -> Result<Self, FromMapError> {
+        Ok(duration)
+    }
+}
+
+impl FromDuration for std::time::Duration {
+    /// Negative durations, which `std::time::Duration` can't hold, are out of
+    /// range.
+    fn from_duration(key: &str, duration: chrono::Duration) -> Result<Self, FromMapError> {
+        duration.to_std().map_err(|_| FromMapError::OutOfRange {
+            key: key.to_string(),
+            value: duration.to_string(),
+            ty: "Duration",
+        })
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self)
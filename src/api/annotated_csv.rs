@@ -0,0 +1,368 @@
+//! Annotated CSV
+//!
+//! Write query rows back as annotated CSV, the format InfluxDB answers
+//! queries with, e.g. for golden-file tests, proxies, or tools feeding
+//! results to other Influx clients:
+//!
+//! ```
+//! use influxdb2::api::annotated_csv::to_annotated_csv;
+//! use influxdb2_structmap::value::Value;
+//! use influxdb2_structmap::GenericMap;
+//!
+//! let mut row = GenericMap::new();
+//! row.insert("_measurement".to_string(), Value::String("cpu".to_string()));
+//! row.insert("_value".to_string(), Value::Long(3));
+//!
+//! assert_eq!(
+//!     to_annotated_csv(&[row]),
+//!     "#datatype,string,long,long,string
+//! #group,false,false,false,true
+//! #default,_result,,,
+//! ,result,table,_value,_measurement
+//! ,,0,3,cpu
+//! "
+//! );
+//! ```
+//!
+//! Rows are the records of `query_raw`, or maps built the same way. The
+//! rows of a table follow each other and share its `table` value and its
+//! columns, which start with `result`, `table`, `_start`, `_stop`, `_time`,
+//! `_value`, `_field` and `_measurement`, then the others by name. Rows
+//! without a `table` column are numbered into tables as their columns
+//! change, and the `result` column defaults to `_result`.
+//!
+//! Each column is typed after its first value in the table. The group key
+//! isn't part of a row, so a table groups by the columns holding the same
+//! value in each of its rows, but for `result`, `table`, `_time` and `_value`.
+//! Annotations are written again, after an empty line, whenever a table's
+//! columns or group key differ from those of the table before it. Lines end
+//! with `\n`, and times are written in RFC3339 with as many fractional digits
+//! as they need, durations as Go durations such as `1h30m0s`, and binary
+//! values in base64.
+
+use std::io::{self, Write};
+
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+
+/// The columns written first, in this order
+const LEADING: &[&str] = &[
+    "result",
+    "table",
+    "_start",
+    "_stop",
+    "_time",
+    "_value",
+    "_field",
+    "_measurement",
+];
+
+/// The columns that are never in a group key
+const NOT_GROUPED: &[&str] = &["result", "table", "_time", "_value"];
+
+/// The annotations and header of a table
+#[derive(PartialEq)]
+struct Header {
+    columns: Vec<String>,
+    data_types: Vec<&'static str>,
+    group: Vec<bool>,
+    result: String,
+}
+
+/// Write `rows` as annotated CSV.
+pub fn to_annotated_csv(rows: &[GenericMap]) -> String {
+    let mut csv = Vec::new();
+    write_annotated_csv(rows, &mut csv).expect("writing to a Vec can't fail");
+    String::from_utf8(csv).expect("the values are UTF-8")
+}
+
+/// Write `rows` as annotated CSV to `writer`.
+pub fn write_annotated_csv(rows: &[GenericMap], mut writer: impl Write) -> io::Result<()> {
+    let mut previous: Option<Header> = None;
+    for (position, table) in tables(rows).into_iter().enumerate() {
+        let header = header(&table);
+        if previous.as_ref() != Some(&header) {
+            if previous.is_some() {
+                writer.write_all(b"\n")?;
+            }
+            write_header(&mut writer, &header)?;
+        }
+
+        for row in table {
+            let mut record = vec![String::new()];
+            for column in &header.columns {
+                let cell = match (column.as_str(), row.get(column)) {
+                    ("result", Some(Value::String(result))) if *result == header.result => {
+                        String::new()
+                    }
+                    ("result", None) => String::new(),
+                    ("table", None) => position.to_string(),
+                    (_, Some(value)) => cell(value),
+                    (_, None) => String::new(),
+                };
+                record.push(cell);
+            }
+            write_record(&mut writer, &record)?;
+        }
+        previous = Some(header);
+    }
+    writer.flush()
+}
+
+/// Write `cells` as a line, quoting those that need it
+fn write_record<S: AsRef<str>>(writer: &mut impl Write, cells: &[S]) -> io::Result<()> {
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        let cell = cell.as_ref();
+        if cell.contains(&[',', '"', '\n', '\r'][..]) {
+            write!(writer, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(cell.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+/// Split `rows` into the rows of each table
+fn tables(rows: &[GenericMap]) -> Vec<Vec<&GenericMap>> {
+    let mut tables: Vec<Vec<&GenericMap>> = Vec::new();
+    for row in rows {
+        match tables.last_mut() {
+            Some(table) if same_table(table[0], row) => table.push(row),
+            _ => tables.push(vec![row]),
+        }
+    }
+    tables
+}
+
+fn same_table(a: &GenericMap, b: &GenericMap) -> bool {
+    if !a.keys().eq(b.keys()) {
+        return false;
+    }
+    match (a.get("table"), b.get("table")) {
+        (Some(_), Some(_)) => {
+            a.get("table") == b.get("table") && a.get("result") == b.get("result")
+        }
+        _ => a
+            .values()
+            .zip(b.values())
+            .all(|(a, b)| data_type(a) == data_type(b)),
+    }
+}
+
+fn header(table: &[&GenericMap]) -> Header {
+    let mut columns: Vec<String> = LEADING
+        .iter()
+        .filter(|column| matches!(**column, "result" | "table") || table[0].contains_key(**column))
+        .map(|column| column.to_string())
+        .collect();
+    columns.extend(
+        table[0]
+            .keys()
+            .filter(|column| !LEADING.contains(&column.as_str()))
+            .cloned(),
+    );
+
+    let data_types = columns
+        .iter()
+        .map(|column| match column.as_str() {
+            "result" => "string",
+            "table" => "long",
+            _ => table
+                .iter()
+                .filter_map(|row| row.get(column).and_then(data_type))
+                .next()
+                .unwrap_or("string"),
+        })
+        .collect();
+    let group = columns
+        .iter()
+        .map(|column| {
+            !NOT_GROUPED.contains(&column.as_str())
+                && table
+                    .iter()
+                    .all(|row| row.get(column) == table[0].get(column))
+        })
+        .collect();
+    let result = match table[0].get("result") {
+        Some(Value::String(result)) => result.clone(),
+        _ => "_result".to_string(),
+    };
+
+    Header {
+        columns,
+        data_types,
+        group,
+        result,
+    }
+}
+
+fn write_header(writer: &mut impl Write, header: &Header) -> io::Result<()> {
+    let mut data_types = vec!["#datatype"];
+    data_types.extend(&header.data_types);
+    write_record(writer, &data_types)?;
+
+    let mut group = vec!["#group"];
+    group.extend(
+        header
+            .group
+            .iter()
+            .map(|group| if *group { "true" } else { "false" }),
+    );
+    write_record(writer, &group)?;
+
+    let mut defaults = vec!["#default"];
+    defaults.extend(header.columns.iter().map(|column| match column.as_str() {
+        "result" => header.result.as_str(),
+        _ => "",
+    }));
+    write_record(writer, &defaults)?;
+
+    let mut names = vec![""];
+    names.extend(header.columns.iter().map(String::as_str));
+    write_record(writer, &names)
+}
+
+/// The annotated CSV datatype of `value`, or `None` if it has none
+pub(crate) fn data_type(value: &Value) -> Option<&'static str> {
+    Some(match value {
+        Value::Unknown => return None,
+        Value::String(_) => "string",
+        Value::Double(_) => "double",
+        Value::Bool(_) => "boolean",
+        Value::Long(_) => "long",
+        Value::UnsignedLong(_) => "unsignedLong",
+        Value::Duration(_) => "duration",
+        Value::Base64Binary(_) => "base64Binary",
+        Value::TimeRFC(_) => "dateTime:RFC3339",
+    })
+}
+
+pub(crate) fn cell(value: &Value) -> String {
+    match value {
+        Value::Unknown => String::new(),
+        Value::String(v) => v.clone(),
+        Value::Double(v) => match v.into_inner() {
+            v if v == f64::INFINITY => "+Inf".to_string(),
+            v if v == f64::NEG_INFINITY => "-Inf".to_string(),
+            v if v.is_nan() => "NaN".to_string(),
+            v => v.to_string(),
+        },
+        Value::Bool(v) => v.to_string(),
+        Value::Long(v) => v.to_string(),
+        Value::UnsignedLong(v) => v.to_string(),
+        Value::Duration(v) => match v.num_nanoseconds() {
+            Some(nanos) => go_duration(nanos),
+            None => format!("{}s", v.num_seconds()),
+        },
+        Value::Base64Binary(v) => base64::encode(v),
+        Value::TimeRFC(v) => v.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+    }
+}
+
+/// Format `nanos` the way Go formats a `time.Duration`, e.g. `1h2m3.5s`,
+/// `1.5ms` or `0s`
+fn go_duration(nanos: i64) -> String {
+    if nanos == 0 {
+        return "0s".to_string();
+    }
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+
+    let (whole, fraction, unit) = match nanos {
+        0..=999 => return format!("{}{}ns", sign, nanos),
+        1_000..=999_999 => (nanos / 1_000, nanos % 1_000, "µs"),
+        1_000_000..=999_999_999 => (nanos / 1_000_000, nanos % 1_000_000, "ms"),
+        _ => {
+            let seconds = nanos / 1_000_000_000;
+            let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+            let fraction = decimals(nanos % 1_000_000_000, 9);
+            let seconds = format!("{}{}s", seconds % 60, fraction);
+            return match (hours, minutes) {
+                (0, 0) => format!("{}{}", sign, seconds),
+                (0, _) => format!("{}{}m{}", sign, minutes, seconds),
+                _ => format!("{}{}h{}m{}", sign, hours, minutes, seconds),
+            };
+        }
+    };
+    let digits = if unit == "µs" { 3 } else { 6 };
+    format!("{}{}{}{}", sign, whole, decimals(fraction, digits), unit)
+}
+
+/// The fractional part `fraction` of `digits` digits as `.5`, or nothing if
+/// it's zero
+fn decimals(fraction: u64, digits: usize) -> String {
+    if fraction == 0 {
+        return String::new();
+    }
+    let fraction = format!("{:0width$}", fraction, width = digits);
+    format!(".{}", fraction.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::query::parse_records;
+
+    #[test]
+    fn writes_back_what_was_parsed() {
+        let text = r#"#datatype,string,long,dateTime:RFC3339,dateTime:RFC3339,dateTime:RFC3339,double,string,string,string
+#group,false,false,true,true,false,false,true,true,true
+#default,_result,,,,,,,,
+,result,table,_start,_stop,_time,_value,_field,_measurement,host
+,,0,2020-02-17T22:19:49.747562847Z,2020-02-18T22:19:49Z,2020-02-18T10:34:08.100Z,1.4,usage,cpu,a
+,,0,2020-02-17T22:19:49.747562847Z,2020-02-18T22:19:49Z,2020-02-18T22:08:44Z,+Inf,usage,cpu,a
+,,1,2020-02-17T22:19:49.747562847Z,2020-02-18T22:19:49Z,2020-02-18T10:34:08Z,-6,usage,cpu,"b,""c"""
+
+#datatype,string,long,dateTime:RFC3339,long,string,string,base64Binary,unsignedLong,boolean,duration
+#group,false,false,false,false,true,true,true,true,true,true
+#default,other,,,,,,,,,
+,result,table,_time,_value,_field,_measurement,blob,bytes,ok,took
+,,2,2020-02-18T10:34:08Z,3,count,disk,aGk=,18446744073709551615,true,1h2m3.5s
+,,2,2020-02-18T10:34:09Z,4,count,disk,aGk=,18446744073709551615,true,1h2m3.5s
+"#;
+
+        let rows = parse_records(text).unwrap();
+        assert_eq!(to_annotated_csv(&rows), text);
+    }
+
+    #[test]
+    fn numbers_tables_as_columns_change() {
+        let row = |value: Value| {
+            let mut row = GenericMap::new();
+            row.insert("_value".to_string(), value);
+            row
+        };
+        let rows = [
+            row(Value::Long(1)),
+            row(Value::Long(2)),
+            row(Value::Bool(false)),
+        ];
+
+        let csv = to_annotated_csv(&rows);
+        assert!(csv.contains(",,0,1\n,,0,2\n\n"));
+        assert!(csv.ends_with(",,1,false\n"));
+
+        let parsed = parse_records(&csv).unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[2]["_value"], Value::Bool(false));
+        assert_eq!(parsed[2]["result"], Value::String("_result".to_string()));
+    }
+
+    #[test]
+    fn formats_durations_like_go() {
+        let cases = [
+            (0, "0s"),
+            (999, "999ns"),
+            (1_500, "1.5µs"),
+            (-2_000_000, "-2ms"),
+            (61_000_000_000, "1m1s"),
+            (3_600_000_000_001, "1h0m0.000000001s"),
+        ];
+        for (nanos, text) in cases {
+            assert_eq!(go_duration(nanos), text);
+        }
+    }
+}
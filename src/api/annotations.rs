@@ -0,0 +1,285 @@
+//! Event annotations
+//!
+//! Deployments, incidents and other events are worth marking on the charts
+//! of the metrics they affect. `Client::annotate` writes them the way Grafana
+//! and Chronograf read annotations from InfluxDB: points of the `events`
+//! measurement with a `title`, a `text` and comma-separated `tags` fields,
+//! and a `time_end` field with the end of the event in nanoseconds, the time
+//! of the point itself for an instant.
+//!
+//! ```no_run
+//! use chrono::{Duration, Utc};
+//! use influxdb2::models::FluxDuration;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let now = Utc::now();
+//! client.annotate("ops", "Deployed v1.4.2", &["deploy", "api"], now).await?;
+//! client
+//!     .annotate("ops", "Database failover", &["incident"], now - Duration::minutes(20)..now)
+//!     .await?;
+//!
+//! let last_day: FluxDuration = "1d".parse()?;
+//! for event in client.annotations("ops", last_day, &["incident"]).await? {
+//!     println!("{}: {}", event.start, event.title);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::Range;
+
+use chrono::{DateTime, TimeZone, Utc};
+use futures::stream;
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+
+use super::window::TimeRange;
+use crate::models::template::flux_string;
+use crate::models::{DataPoint, Query};
+use crate::{Client, RequestError};
+
+/// The measurement annotations are written to
+pub const ANNOTATION_MEASUREMENT: &str = "events";
+
+/// An event marked on charts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// A short description of the event
+    pub title: String,
+    /// A longer description, empty if there is none
+    pub text: String,
+    /// The tags of the event, e.g. to show only deployments on a chart
+    pub tags: Vec<String>,
+    /// When the event happened, or started
+    pub start: DateTime<Utc>,
+    /// When the event ended, `None` for an instant
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Annotation {
+    /// An annotation titled `title` of the event at `time`.
+    pub fn new(title: impl Into<String>, time: impl Into<EventTime>) -> Self {
+        let time = time.into();
+        Self {
+            title: title.into(),
+            text: String::new(),
+            tags: Vec::new(),
+            start: time.start,
+            end: time.end,
+        }
+    }
+
+    /// Describe the event with `text`.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    /// Tag the event with `tag`.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    fn data_point(&self) -> DataPoint {
+        // Every point has every field, so pivoting them leaves no empty cell
+        DataPoint::builder(ANNOTATION_MEASUREMENT)
+            .field("title", self.title.as_str())
+            .field("text", self.text.as_str())
+            .field("tags", self.tags.join(","))
+            .field("time_end", self.end.unwrap_or(self.start).timestamp_nanos())
+            .timestamp(self.start.timestamp_nanos())
+            .build()
+            .expect("an annotation has fields")
+    }
+
+    fn from_row(row: &GenericMap) -> Option<Self> {
+        let string = |column: &str| match row.get(column) {
+            Some(Value::String(value)) => Some(value.clone()),
+            _ => None,
+        };
+        let start = match row.get("_time") {
+            Some(Value::TimeRFC(time)) => time.with_timezone(&Utc),
+            _ => return None,
+        };
+        let end = match row.get("time_end") {
+            Some(Value::Long(nanos)) => Some(Utc.timestamp_nanos(*nanos)).filter(|end| *end != start),
+            _ => None,
+        };
+
+        Some(Self {
+            title: string("title")?,
+            text: string("text").unwrap_or_default(),
+            tags: string("tags")
+                .map(|tags| {
+                    tags.split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            start,
+            end,
+        })
+    }
+}
+
+/// When an event happened: an instant, or a range of time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EventTime {
+    /// When the event happened, or started
+    pub start: DateTime<Utc>,
+    /// When the event ended, `None` for an instant
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl From<DateTime<Utc>> for EventTime {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self {
+            start: time,
+            end: None,
+        }
+    }
+}
+
+impl From<Range<DateTime<Utc>>> for EventTime {
+    fn from(range: Range<DateTime<Utc>>) -> Self {
+        Self {
+            start: range.start,
+            end: Some(range.end),
+        }
+    }
+}
+
+impl Client {
+    /// Write an annotation titled `title` and tagged with `tags` of the event
+    /// at `time`, an instant or a range, to `bucket`.
+    pub async fn annotate(
+        &self,
+        bucket: &str,
+        title: &str,
+        tags: &[&str],
+        time: impl Into<EventTime>,
+    ) -> Result<(), RequestError> {
+        let annotation = tags
+            .iter()
+            .fold(Annotation::new(title, time), |annotation, tag| annotation.tag(*tag));
+        self.write_annotations(bucket, &[annotation]).await
+    }
+
+    /// Write `annotations` to `bucket`.
+    pub async fn write_annotations(
+        &self,
+        bucket: &str,
+        annotations: &[Annotation],
+    ) -> Result<(), RequestError> {
+        let points: Vec<_> = annotations.iter().map(Annotation::data_point).collect();
+        self.write(bucket, stream::iter(points)).await
+    }
+
+    /// The annotations of `bucket` starting across `range` and tagged with
+    /// every tag of `tags`, in time order.
+    pub async fn annotations(
+        &self,
+        bucket: &str,
+        range: impl Into<TimeRange>,
+        tags: &[&str],
+    ) -> Result<Vec<Annotation>, RequestError> {
+        let flux = annotations_flux(bucket, &range.into());
+        let rows = self.query_records(Query::new(flux)).await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(Annotation::from_row)
+            .filter(|annotation| {
+                tags.iter()
+                    .all(|tag| annotation.tags.iter().any(|t| t == tag))
+            })
+            .collect())
+    }
+}
+
+/// The Flux of `Client::annotations`, one row per annotation
+fn annotations_flux(bucket: &str, range: &TimeRange) -> String {
+    format!(
+        "from(bucket: {})\n  \
+         |> range({})\n  \
+         |> filter(fn: (r) => r._measurement == {})\n  \
+         |> pivot(rowKey: [\"_time\"], columnKey: [\"_field\"], valueColumn: \"_value\")\n  \
+         |> group()\n  \
+         |> sort(columns: [\"_time\"])",
+        flux_string(bucket),
+        range.flux_arguments(),
+        flux_string(ANNOTATION_MEASUREMENT),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FluxDuration;
+    use mockito::{mock, Matcher};
+
+    fn time(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().into()
+    }
+
+    #[tokio::test]
+    async fn annotate() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body(
+                "events tags=\"incident,db\",text=\"\",time_end=2208988860000000000i,\
+                 title=\"Failover\" 2208988800000000000\n",
+            )
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        client
+            .annotate(
+                "b",
+                "Failover",
+                &["incident", "db"],
+                time("2040-01-01T00:00:00Z")..time("2040-01-01T00:01:00Z"),
+            )
+            .await
+            .unwrap();
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn annotations() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r"pivot\(".to_string()))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,string,string,string,long,string
+#group,false,false,false,false,false,false,false,true
+#default,_result,,,,,,,
+,result,table,_time,title,text,tags,time_end,_measurement
+,,0,2040-01-01T00:00:00Z,Deployed,v2,\"deploy,api\",2208988800000000000,events
+,,0,2040-01-01T01:00:00Z,Failover,,incident,2208992460000000000,events
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let incidents = client
+            .annotations("b", FluxDuration::from_secs(86_400), &["incident"])
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(
+            incidents,
+            vec![Annotation::new(
+                "Failover",
+                time("2040-01-01T01:00:00Z")..time("2040-01-01T01:01:00Z")
+            )
+            .tag("incident")]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Arrow export
+//!
+//! Writes query results as an Arrow IPC file, also known as Feather v2, for
+//! tools such as pandas, polars or DuckDB.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, DurationNanosecondArray, Float64Array, Int64Array,
+    RecordBatch, StringArray, TimestampNanosecondArray, UInt64Array,
+};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+use snafu::{ResultExt, Snafu};
+
+use crate::models::Query;
+use crate::{Client, RequestError};
+
+/// Errors that occur while exporting query results
+#[derive(Debug, Snafu)]
+pub enum ExportError {
+    /// The query failed.
+    #[snafu(display("Unable to run the query: {}", source))]
+    Querying {
+        /// The query error
+        source: RequestError,
+    },
+
+    /// The results couldn't be encoded.
+    #[snafu(display("Unable to encode the results: {}", source))]
+    Encoding {
+        /// The Arrow error
+        source: ArrowError,
+    },
+
+    /// The file couldn't be written.
+    #[snafu(display("Unable to write {}: {}", path.display(), source))]
+    Writing {
+        /// The file
+        path: PathBuf,
+        /// The I/O error
+        source: std::io::Error,
+    },
+}
+
+impl Client {
+    /// Run `query` and write its rows to `path` as an Arrow IPC file,
+    /// returning the number of rows.
+    ///
+    /// Every column of the results becomes a nullable column of the file,
+    /// typed after its first value: strings as `Utf8`, doubles as `Float64`,
+    /// longs as `Int64`, unsigned longs as `UInt64`, booleans as `Boolean`,
+    /// times as UTC `Timestamp`s, durations as `Duration`s and binary values
+    /// as `Binary`, all in nanoseconds. Rows missing a column, or holding a
+    /// value of another type, are null in it.
+    pub async fn query_to_arrow_ipc(
+        &self,
+        query: Option<Query>,
+        path: impl AsRef<Path>,
+    ) -> Result<usize, ExportError> {
+        let path = path.as_ref();
+        let rows = self.query_raw(query).await.context(QueryingSnafu)?;
+
+        let mut file = Vec::new();
+        write_arrow_ipc(&rows, &mut file).context(EncodingSnafu)?;
+        std::fs::write(path, file).context(WritingSnafu { path })?;
+
+        Ok(rows.len())
+    }
+}
+
+/// Write `rows` to `writer` as a single record batch of an Arrow IPC file
+pub(crate) fn write_arrow_ipc(rows: &[GenericMap], writer: impl Write) -> Result<(), ArrowError> {
+    let mut columns = BTreeMap::new();
+    for row in rows {
+        for (name, value) in row {
+            let data_type = columns.entry(name.as_str()).or_insert(None);
+            if data_type.is_none() {
+                *data_type = data_type_of(value);
+            }
+        }
+    }
+
+    let fields = columns
+        .iter()
+        .map(|(name, data_type)| {
+            let data_type = data_type.clone().unwrap_or(DataType::Utf8);
+            Field::new(*name, data_type, true)
+        })
+        .collect();
+    let schema = Arc::new(Schema::new(fields));
+    let arrays = schema
+        .fields()
+        .iter()
+        .map(|field| column(rows, field.name(), field.data_type()))
+        .collect();
+
+    let mut writer = FileWriter::try_new(writer, &schema)?;
+    if !rows.is_empty() {
+        writer.write(&RecordBatch::try_new(Arc::clone(&schema), arrays)?)?;
+    }
+    writer.finish()
+}
+
+fn data_type_of(value: &Value) -> Option<DataType> {
+    Some(match value {
+        Value::Unknown => return None,
+        Value::String(_) => DataType::Utf8,
+        Value::Double(_) => DataType::Float64,
+        Value::Bool(_) => DataType::Boolean,
+        Value::Long(_) => DataType::Int64,
+        Value::UnsignedLong(_) => DataType::UInt64,
+        Value::Duration(_) => DataType::Duration(TimeUnit::Nanosecond),
+        Value::Base64Binary(_) => DataType::Binary,
+        Value::TimeRFC(_) => DataType::Timestamp(TimeUnit::Nanosecond, Some(UTC.to_string())),
+    })
+}
+
+const UTC: &str = "+00:00";
+
+/// The values of column `name` as an array of `data_type`
+fn column(rows: &[GenericMap], name: &str, data_type: &DataType) -> ArrayRef {
+    let values = rows.iter().map(|row| row.get(name));
+    match data_type {
+        DataType::Float64 => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::Double(v)) => Some(v.into_inner()),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        DataType::Boolean => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::Bool(v)) => Some(*v),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        DataType::Int64 => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::Long(v)) => Some(*v),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        ),
+        DataType::UInt64 => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::UnsignedLong(v)) => Some(*v),
+                    _ => None,
+                })
+                .collect::<UInt64Array>(),
+        ),
+        DataType::Duration(_) => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::Duration(v)) => v.num_nanoseconds(),
+                    _ => None,
+                })
+                .collect::<DurationNanosecondArray>(),
+        ),
+        DataType::Binary => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::Base64Binary(v)) => Some(v.as_slice()),
+                    _ => None,
+                })
+                .collect::<BinaryArray>(),
+        ),
+        DataType::Timestamp(..) => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::TimeRFC(v)) => Some(v.timestamp_nanos()),
+                    _ => None,
+                })
+                .collect::<TimestampNanosecondArray>()
+                .with_timezone(UTC),
+        ),
+        _ => Arc::new(
+            values
+                .map(|value| match value {
+                    Some(Value::String(v)) => Some(v.as_str()),
+                    _ => None,
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn query_to_arrow_ipc() {
+        let mock_server = mock("POST", "/api/v2/query?org=org")
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,double,string\n\
+                 #group,false,false,false,false,true\n\
+                 #default,_result,,,,\n\
+                 ,result,table,_time,_value,host\n\
+                 ,,0,2023-01-01T00:00:00Z,1.5,a\n\
+                 ,,0,2023-01-01T00:00:01Z,2.5,\n",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.arrow");
+
+        let rows = client
+            .query_to_arrow_ipc(Some(Query::new("from(bucket: \"b\")".into())), &path)
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(rows, 2);
+
+        let mut reader = FileReader::try_new(std::fs::File::open(&path).unwrap(), None).unwrap();
+        let schema = reader.schema();
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, ["_time", "_value", "host", "result", "table"]);
+        assert_eq!(
+            schema.field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Nanosecond, Some(UTC.to_string()))
+        );
+
+        let batch = reader.next().unwrap().unwrap();
+        let values = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(values.values(), &[1.5, 2.5]);
+        let times = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+        assert_eq!(times.value(1), 1_672_531_201_000_000_000);
+        let hosts = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(hosts.value(0), "a");
+        assert_eq!(hosts.null_count(), 0);
+    }
+}
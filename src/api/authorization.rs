@@ -0,0 +1,302 @@
+//! Authorization API
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::models::authorization::Status;
+use crate::models::{Authorization, Authorizations, OrgId, UserId};
+use crate::{Client, RequestError, SerializingSnafu};
+
+impl Client {
+    /// List the authorizations matching `request`.
+    pub async fn list_authorizations(
+        &self,
+        request: ListAuthorizationsRequest,
+    ) -> Result<Authorizations, RequestError> {
+        let qs = serde_qs::to_string(&request).unwrap_or_default();
+        let url = match &qs[..] {
+            "" => self.endpoint_url("/api/v2/authorizations"),
+            _ => self.endpoint_url(&format!("/api/v2/authorizations?{}", qs)),
+        };
+
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Authorizations>().await
+    }
+
+    /// The token with the ID `authorization_id`.
+    pub async fn get_authorization(
+        &self,
+        authorization_id: &str,
+    ) -> Result<Authorization, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/authorizations/{}", authorization_id));
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Authorization>().await
+    }
+
+    /// Activate or deactivate the token with the ID `authorization_id`.
+    pub async fn set_authorization_status(
+        &self,
+        authorization_id: &str,
+        status: Status,
+    ) -> Result<Authorization, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/authorizations/{}", authorization_id));
+        let body = serde_json::json!({ "status": status });
+        let response = self
+            .send(self.request(Method::PATCH, &url).body(body.to_string()))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Authorization>().await
+    }
+
+    /// Replace the token with the ID `old_authorization_id` by a new one with
+    /// the same organization, owner and permissions, described as
+    /// `new_description`, then deactivate the old one. Returns the new
+    /// token, whose `token` is its secret.
+    ///
+    /// If the old token can't be deactivated, the new one is deleted again
+    /// and the error returned, leaving the old token in use as it was.
+    pub async fn rotate_token(
+        &self,
+        old_authorization_id: &str,
+        new_description: &str,
+    ) -> Result<Authorization, RequestError> {
+        let old = self.get_authorization(old_authorization_id).await?;
+        let replacement = Authorization {
+            description: Some(new_description.to_string()),
+            status: Some(Status::Active),
+            user_id: old.user_id,
+            ..Authorization::new(old.org_id, old.permissions)
+        };
+        let new = self.create_authorization(&replacement).await?;
+
+        if let Err(e) = self
+            .set_authorization_status(old_authorization_id, Status::Inactive)
+            .await
+        {
+            if let Some(id) = &new.id {
+                // Best effort: the old token still works, so a failed
+                // cleanup only leaves an unused token behind
+                let _ = self.delete_authorization(id).await;
+            }
+            return Err(e);
+        }
+        Ok(new)
+    }
+
+    /// Create the token of `authorization`, returning it with its ID and
+    /// token value, which the server shows only once.
+    pub async fn create_authorization(
+        &self,
+        authorization: &Authorization,
+    ) -> Result<Authorization, RequestError> {
+        let url = self.endpoint_url("/api/v2/authorizations");
+        let response = self
+            .send(
+                self.request(Method::POST, &url)
+                    .body(serde_json::to_string(authorization).context(SerializingSnafu)?),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Authorization>().await
+    }
+
+    /// Delete the token with the ID `authorization_id`, revoking it.
+    pub async fn delete_authorization(&self, authorization_id: &str) -> Result<(), RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/authorizations/{}", authorization_id));
+        let response = self.send(self.request(Method::DELETE, &url)).await?;
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+        Ok(())
+    }
+
+    /// List the tokens of the user named `user`.
+    pub async fn find_tokens_by_user(
+        &self,
+        user: &str,
+    ) -> Result<Vec<Authorization>, RequestError> {
+        let request = ListAuthorizationsRequest {
+            user: Some(user.to_string()),
+            ..ListAuthorizationsRequest::default()
+        };
+        Ok(self.list_authorizations(request).await?.authorizations)
+    }
+
+    /// The token of the organization named `org` with the description
+    /// `description`, e.g. to rotate it, or `None` if there is none.
+    ///
+    /// The API can't filter by description, so the tokens of the
+    /// organization are listed and the first with that exact description is
+    /// returned.
+    pub async fn find_token_by_description(
+        &self,
+        org: &str,
+        description: &str,
+    ) -> Result<Option<Authorization>, RequestError> {
+        let request = ListAuthorizationsRequest {
+            org: Some(org.to_string()),
+            ..ListAuthorizationsRequest::default()
+        };
+        Ok(self
+            .list_authorizations(request)
+            .await?
+            .authorizations
+            .into_iter()
+            .find(|authorization| authorization.description.as_deref() == Some(description)))
+    }
+}
+
+/// Request for list authorizations API
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListAuthorizationsRequest {
+    /// Filter by user ID.
+    #[serde(rename = "userID")]
+    pub user_id: Option<UserId>,
+    /// Filter by user name.
+    pub user: Option<String>,
+    /// Filter by organization ID.
+    #[serde(rename = "orgID")]
+    pub org_id: Option<OrgId>,
+    /// Filter by organization name.
+    pub org: Option<String>,
+}
+
+impl ListAuthorizationsRequest {
+    /// Create a new request for list authorizations API
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn finds_tokens_by_user() {
+        let mock_server = mock("GET", "/api/v2/authorizations?user=alice")
+            .with_body(
+                r#"{"authorizations":[
+                    {"id":"1","orgID":"0000000000000001","permissions":[],"user":"alice"}
+                ]}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let tokens = client.find_tokens_by_user("alice").await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].user.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn finds_token_by_description() {
+        let mock_server = mock("GET", "/api/v2/authorizations?org=org")
+            .with_body(
+                r#"{"authorizations":[
+                    {"id":"1","orgID":"0000000000000001","permissions":[],"description":"ci"},
+                    {"id":"2","orgID":"0000000000000001","permissions":[],"description":"telegraf"}
+                ]}"#,
+            )
+            .expect(2)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let token = client
+            .find_token_by_description("org", "telegraf")
+            .await
+            .unwrap();
+        let missing = client
+            .find_token_by_description("org", "grafana")
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(token.unwrap().id.as_deref(), Some("2"));
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn rotates_tokens() {
+        let old = mock("GET", "/api/v2/authorizations/old")
+            .with_body(
+                r#"{"id":"old","orgID":"0000000000000001","userID":"0000000000000002",
+                    "description":"ci","status":"active","token":"secret",
+                    "permissions":[{"action":"read","resource":{"type":"buckets"}}]}"#,
+            )
+            .create();
+        let create = mock("POST", "/api/v2/authorizations")
+            .match_body(Matcher::Json(serde_json::json!({
+                "orgID": "0000000000000001",
+                "userID": "0000000000000002",
+                "description": "ci 2040",
+                "status": "active",
+                "permissions": [{"action": "read", "resource": {"type": "buckets"}}],
+            })))
+            .with_status(201)
+            .with_body(
+                r#"{"id":"new","orgID":"0000000000000001","description":"ci 2040",
+                    "status":"active","token":"new-secret","permissions":[]}"#,
+            )
+            .create();
+        let deactivate = mock("PATCH", "/api/v2/authorizations/old")
+            .match_body(r#"{"status":"inactive"}"#)
+            .with_body(
+                r#"{"id":"old","orgID":"0000000000000001","status":"inactive","permissions":[]}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let new = client.rotate_token("old", "ci 2040").await.unwrap();
+
+        old.assert();
+        create.assert();
+        deactivate.assert();
+        assert_eq!(new.id.as_deref(), Some("new"));
+        assert_eq!(new.token.as_deref(), Some("new-secret"));
+    }
+
+    #[tokio::test]
+    async fn rotation_keeps_the_old_token_when_deactivation_fails() {
+        let _old = mock("GET", "/api/v2/authorizations/stuck")
+            .with_body(r#"{"id":"stuck","orgID":"0000000000000001","permissions":[]}"#)
+            .create();
+        let _create = mock("POST", "/api/v2/authorizations")
+            .with_status(201)
+            .with_body(r#"{"id":"unused","orgID":"0000000000000001","permissions":[]}"#)
+            .create();
+        let _deactivate = mock("PATCH", "/api/v2/authorizations/stuck")
+            .with_status(500)
+            .create();
+        let cleanup = mock("DELETE", "/api/v2/authorizations/unused")
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let result = client.rotate_token("stuck", "ci 2040").await;
+
+        assert!(result.is_err());
+        cleanup.assert();
+    }
+}
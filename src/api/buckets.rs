@@ -4,8 +4,12 @@ use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 
-use crate::models::{Buckets, PostBucketRequest};
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu, SerializingSnafu};
+use crate::models::retention_rule::Type;
+use crate::models::{
+    Bucket, BucketId, Buckets, FluxDuration, OrgId, PatchBucketRequest, PostBucketRequest,
+    RetentionRule,
+};
+use crate::{Client, RequestError, SerializingSnafu, Target};
 
 impl Client {
     /// List all buckets matching specified parameters
@@ -15,27 +19,17 @@ impl Client {
     ) -> Result<Buckets, RequestError> {
         let qs = serde_qs::to_string(&request).unwrap();
         let url = match &qs[..] {
-            "" => format!("{}/api/v2/buckets", self.url),
-            _ => format!("{}/api/v2/buckets?{}", self.url, qs),
+            "" => self.endpoint_url("/api/v2/buckets"),
+            _ => self.endpoint_url(&format!("/api/v2/buckets?{}", qs)),
         };
 
-        let response = self
-            .request(Method::GET, &url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let response = self.send(self.request(Method::GET, &url)).await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            let res = HttpSnafu { status, text }.fail();
-            return res;
+            return Err(response.error().await);
         }
 
-        let res = response
-            .json::<Buckets>()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let res = response.json::<Buckets>().await?;
 
         Ok(res)
     }
@@ -46,44 +40,146 @@ impl Client {
         &self,
         post_bucket_request: Option<PostBucketRequest>,
     ) -> Result<(), RequestError> {
-        let create_bucket_url = format!("{}/api/v2/buckets", self.url);
+        let create_bucket_url = self.endpoint_url("/api/v2/buckets");
 
         let response = self
-            .request(Method::POST, &create_bucket_url)
-            .body(
-                serde_json::to_string(&post_bucket_request.unwrap_or_default())
-                    .context(SerializingSnafu)?,
+            .send(
+                self.request(Method::POST, &create_bucket_url)
+                    .body(
+                        serde_json::to_string(&post_bucket_request.unwrap_or_default())
+                            .context(SerializingSnafu)?,
+                    ),
             )
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            HttpSnafu { status, text }.fail()?;
+            return Err(response.error().await);
         }
 
         Ok(())
     }
 
-    /// Delete a bucket specified by bucket id.
-    pub async fn delete_bucket(&self, bucket_id: &str) -> Result<(), RequestError> {
-        let url = format!("{}/api/v2/buckets/{}", self.url, bucket_id);
+    /// Update the bucket specified by bucket id, returning it as updated.
+    pub async fn update_bucket(
+        &self,
+        bucket_id: &BucketId,
+        request: &PatchBucketRequest,
+    ) -> Result<Bucket, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/buckets/{}", bucket_id));
         let response = self
-            .request(Method::DELETE, &url)
-            .send()
+            .send(
+                self.request(Method::PATCH, &url)
+                    .body(serde_json::to_string(request).context(SerializingSnafu)?),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Bucket>().await
+    }
+
+    /// Make sure the organization named `org` has a bucket named `name`
+    /// keeping data for `retention`, zero meaning forever: create the bucket
+    /// if it doesn't exist, or update its retention if it differs. Either
+    /// way, return the bucket.
+    pub async fn ensure_bucket(
+        &self,
+        org: &str,
+        name: &str,
+        retention: FluxDuration,
+    ) -> Result<Bucket, RequestError> {
+        self.ensure_bucket_to(Target::org(org).with_bucket(name), retention)
             .await
-            .context(ReqwestProcessingSnafu)?;
+    }
+
+    /// Make sure the bucket of `target` exists and keeps data for
+    /// `retention`, like `ensure_bucket`, resolving the parts `target` leaves
+    /// unset against the defaults of the client.
+    pub async fn ensure_bucket_to(
+        &self,
+        target: impl Into<Target>,
+        retention: FluxDuration,
+    ) -> Result<Bucket, RequestError> {
+        let target = target.into();
+        let (org, name) = self.resolve(&target)?;
+        let org_id = self.org_id(org).await?;
+        let request = ListBucketsRequest {
+            name: Some(name.to_string()),
+            org_id: Some(org_id.clone()),
+            ..ListBucketsRequest::default()
+        };
+        let existing = self
+            .list_buckets(Some(request))
+            .await?
+            .buckets
+            .into_iter()
+            .find(|bucket| bucket.name == name);
+        let rules = vec![RetentionRule::new(Type::Expire, retention)];
+
+        match existing {
+            Some(bucket) if retention_of(&bucket) == retention => Ok(bucket),
+            Some(bucket) => {
+                let request = PatchBucketRequest {
+                    retention_rules: Some(rules),
+                    ..PatchBucketRequest::default()
+                };
+                let id = bucket
+                    .id
+                    .ok_or_else(|| RequestError::missing_id("bucket", name))?;
+                self.update_bucket(&id, &request).await
+            }
+            None => {
+                let request = PostBucketRequest {
+                    retention_rules: rules,
+                    ..PostBucketRequest::new(org_id, name.to_string())
+                };
+                self.post_bucket(&request).await
+            }
+        }
+    }
+
+    /// Create the bucket of `request`, returning it
+    pub(crate) async fn post_bucket(
+        &self,
+        request: &PostBucketRequest,
+    ) -> Result<Bucket, RequestError> {
+        let url = self.endpoint_url("/api/v2/buckets");
+        let response = self
+            .send(
+                self.request(Method::POST, &url)
+                    .body(serde_json::to_string(request).context(SerializingSnafu)?),
+            )
+            .await?;
+
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            HttpSnafu { status, text }.fail()?;
+            return Err(response.error().await);
+        }
+
+        response.json::<Bucket>().await
+    }
+
+    /// Delete a bucket specified by bucket id.
+    pub async fn delete_bucket(&self, bucket_id: &BucketId) -> Result<(), RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/buckets/{}", bucket_id));
+        let response = self.send(self.request(Method::DELETE, &url)).await?;
+        if !response.status().is_success() {
+            return Err(response.error().await);
         }
         Ok(())
     }
 }
 
+/// How long `bucket` keeps data, zero meaning forever
+pub(crate) fn retention_of(bucket: &Bucket) -> FluxDuration {
+    bucket
+        .retention_rules
+        .iter()
+        .find(|rule| rule.r#type == Type::Expire)
+        .map_or(FluxDuration::ZERO, |rule| rule.every_seconds)
+}
+
 /// Request for list buckets API
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -103,7 +199,7 @@ pub struct ListBucketsRequest {
     pub org: Option<String>,
     #[serde(rename = "orgID")]
     /// The organization ID.
-    pub org_id: Option<String>,
+    pub org_id: Option<OrgId>,
 }
 
 #[cfg(test)]
@@ -128,15 +224,136 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), &org_id, token);
+        let client = Client::new(mockito::server_url(), &org_id, token);
 
         let _result = client
-            .create_bucket(Some(PostBucketRequest::new(org_id, bucket)))
+            .create_bucket(Some(PostBucketRequest::new(org_id.into(), bucket)))
             .await;
 
         mock_server.assert();
     }
 
+    #[tokio::test]
+    async fn ensure_bucket() {
+        let orgs = mock("GET", "/api/v2/orgs?org=some-org")
+            .with_body(r#"{"orgs":[{"id":"0000111100001111","name":"some-org"}]}"#)
+            .create();
+        let list = |name: &str, body: &str| {
+            mock(
+                "GET",
+                format!("/api/v2/buckets?name={}&orgID=0000111100001111", name).as_str(),
+            )
+            .with_body(body)
+            .create()
+        };
+        let up_to_date = list(
+            "kept",
+            r#"{"buckets":[{"id":"0000000000000001","name":"kept",
+                "retentionRules":[{"type":"expire","everySeconds":3600}]}]}"#,
+        );
+        let drifted = list(
+            "drifted",
+            r#"{"buckets":[{"id":"0000000000000002","name":"drifted",
+                "retentionRules":[{"type":"expire","everySeconds":60}]}]}"#,
+        );
+        let absent = list("absent", r#"{"buckets":[]}"#);
+        let update = mock("PATCH", "/api/v2/buckets/0000000000000002")
+            .match_body(r#"{"retentionRules":[{"type":"expire","everySeconds":3600}]}"#)
+            .with_body(
+                r#"{"id":"0000000000000002","name":"drifted",
+                    "retentionRules":[{"type":"expire","everySeconds":3600}]}"#,
+            )
+            .create();
+        let create = mock("POST", "/api/v2/buckets")
+            .match_body(
+                r#"{"orgID":"0000111100001111","name":"absent","retentionRules":[{"type":"expire","everySeconds":3600}]}"#,
+            )
+            .with_status(201)
+            .with_body(
+                r#"{"id":"0000000000000003","name":"absent",
+                    "retentionRules":[{"type":"expire","everySeconds":3600}]}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "some-org", "");
+        let hour = FluxDuration::from_secs(3600);
+        for (name, id) in &[
+            ("kept", "0000000000000001"),
+            ("drifted", "0000000000000002"),
+            ("absent", "0000000000000003"),
+        ] {
+            let bucket = client.ensure_bucket("some-org", name, hour).await.unwrap();
+            assert_eq!(bucket.id, Some(BucketId::from(*id)));
+            assert_eq!(retention_of(&bucket), hour);
+        }
+
+        orgs.assert();
+        up_to_date.assert();
+        drifted.assert();
+        absent.assert();
+        update.assert();
+        create.assert();
+    }
+
+    #[tokio::test]
+    async fn ensure_default_bucket_of_other_org() {
+        let orgs = mock("GET", "/api/v2/orgs?org=bucket-other-org")
+            .with_body(r#"{"orgs":[{"id":"0000222200002222","name":"bucket-other-org"}]}"#)
+            .create();
+        let list = mock(
+            "GET",
+            "/api/v2/buckets?name=default-bucket&orgID=0000222200002222",
+        )
+        .with_body(
+            r#"{"buckets":[{"id":"0000000000000004","name":"default-bucket",
+                "retentionRules":[{"type":"expire","everySeconds":0}]}]}"#,
+        )
+        .create();
+
+        let client = Client::builder(mockito::server_url(), "some-org", "")
+            .with_bucket("default-bucket")
+            .build()
+            .unwrap();
+        let bucket = client
+            .ensure_bucket_to(Target::org("bucket-other-org"), FluxDuration::ZERO)
+            .await
+            .unwrap();
+
+        assert_eq!(bucket.id, Some(BucketId::from("0000000000000004")));
+        orgs.assert();
+        list.assert();
+    }
+
+    #[tokio::test]
+    async fn ensure_bucket_without_id() {
+        let orgs = mock("GET", "/api/v2/orgs?org=idless-bucket-org")
+            .with_body(r#"{"orgs":[{"id":"0000333300003333","name":"idless-bucket-org"}]}"#)
+            .create();
+        let list = mock("GET", "/api/v2/buckets?name=idless&orgID=0000333300003333")
+            .with_body(r#"{"buckets":[{"name":"idless","retentionRules":[]}]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "idless-bucket-org", "");
+        let result = client
+            .ensure_bucket("idless-bucket-org", "idless", FluxDuration::from_secs(3600))
+            .await;
+
+        orgs.assert();
+        list.assert();
+        assert!(matches!(result, Err(RequestError::Deserializing { .. })), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn ensure_bucket_without_bucket() {
+        let client = Client::new(mockito::server_url(), "some-org", "");
+
+        let result = client
+            .ensure_bucket_to(Target::new(), FluxDuration::ZERO)
+            .await;
+
+        assert!(matches!(result, Err(RequestError::MissingBucket)));
+    }
+
     #[test]
     fn serialize_empty_list_buckets_request() {
         let request: Option<ListBucketsRequest> = None;
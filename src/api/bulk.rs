@@ -0,0 +1,180 @@
+//! Bulk operations
+//!
+//! Run a single-item call for many IDs with a bounded number of calls in
+//! flight, collecting the result of every item instead of stopping at the
+//! first error:
+//!
+//! ```no_run
+//! use influxdb2::models::LabelId;
+//!
+//! # async fn example(client: influxdb2::Client, ids: Vec<LabelId>) {
+//! for (id, result) in client.delete_labels(ids, 8).await {
+//!     if let Err(e) = result {
+//!         eprintln!("couldn't delete label {}: {}", id, e);
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::future::Future;
+
+use futures::stream::{self, StreamExt};
+
+use crate::models::{BucketId, LabelId, Task, TaskId};
+use crate::{Client, RequestError};
+
+/// The result of a bulk operation for each item, in the order of the items
+pub type BulkResults<K, T = ()> = Vec<(K, Result<T, RequestError>)>;
+
+/// Call `f` for every item of `items`, with at most `concurrency` calls in
+/// flight, and collect the results in the order of the items.
+///
+/// A `concurrency` of 0 is treated as 1.
+// The future is `Send` whenever `K`, `T`, `F` and `Fut` are, which the lint
+// can't see through the generics
+#[allow(clippy::future_not_send)]
+pub async fn run<K, T, F, Fut>(
+    items: impl IntoIterator<Item = K>,
+    concurrency: usize,
+    f: F,
+) -> BulkResults<K, T>
+where
+    K: Clone,
+    F: Fn(K) -> Fut,
+    Fut: Future<Output = Result<T, RequestError>>,
+{
+    stream::iter(items)
+        .map(|item| {
+            let call = f(item.clone());
+            async move { (item, call.await) }
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+impl Client {
+    /// Delete the labels with the given IDs, `concurrency` at a time.
+    pub async fn delete_labels(
+        &self,
+        ids: impl IntoIterator<Item = LabelId>,
+        concurrency: usize,
+    ) -> BulkResults<LabelId> {
+        run(ids, concurrency, |id| async move { self.delete_label(&id).await }).await
+    }
+
+    /// Delete the tasks with the given IDs, `concurrency` at a time.
+    pub async fn delete_tasks(
+        &self,
+        ids: impl IntoIterator<Item = TaskId>,
+        concurrency: usize,
+    ) -> BulkResults<TaskId> {
+        run(ids, concurrency, |id| async move { self.delete_task(&id).await }).await
+    }
+
+    /// Activate the tasks with the given IDs, `concurrency` at a time.
+    pub async fn activate_tasks(
+        &self,
+        ids: impl IntoIterator<Item = TaskId>,
+        concurrency: usize,
+    ) -> BulkResults<TaskId, Task> {
+        run(ids, concurrency, |id| async move { self.activate_task(&id).await }).await
+    }
+
+    /// Deactivate the tasks with the given IDs, `concurrency` at a time.
+    pub async fn deactivate_tasks(
+        &self,
+        ids: impl IntoIterator<Item = TaskId>,
+        concurrency: usize,
+    ) -> BulkResults<TaskId, Task> {
+        run(ids, concurrency, |id| async move { self.deactivate_task(&id).await }).await
+    }
+
+    /// Delete the buckets with the given IDs, `concurrency` at a time.
+    pub async fn delete_buckets(
+        &self,
+        ids: impl IntoIterator<Item = BucketId>,
+        concurrency: usize,
+    ) -> BulkResults<BucketId> {
+        run(ids, concurrency, |id| async move { self.delete_bucket(&id).await }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskStatusType;
+    use mockito::{mock, Matcher};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn collects_every_result() {
+        let deleted = mock("DELETE", "/api/v2/labels/0000000000000001")
+            .with_status(204)
+            .create();
+        let missing = mock("DELETE", "/api/v2/labels/0000000000000002")
+            .with_status(404)
+            .create();
+        let also_deleted = mock("DELETE", "/api/v2/labels/0000000000000003")
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let ids = ["0000000000000001", "0000000000000002", "0000000000000003"];
+        let results = client
+            .delete_labels(ids.iter().map(|&id| LabelId::from(id)), 2)
+            .await;
+
+        deleted.assert();
+        missing.assert();
+        also_deleted.assert();
+        let ids: Vec<_> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(
+            ids,
+            ["0000000000000001", "0000000000000002", "0000000000000003"]
+        );
+        assert!(results[0].1.is_ok());
+        assert!(results[1].1.is_err());
+        assert!(results[2].1.is_ok());
+    }
+
+    #[tokio::test]
+    async fn bounds_the_calls_in_flight() {
+        let (in_flight, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let results = run(0..10, 3, |_| async {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .await;
+
+        assert_eq!(results.len(), 10);
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn deactivates_tasks() {
+        let path = Matcher::Regex("^/api/v2/tasks/000000000000000[12]$".to_string());
+        let mock_server = mock("PATCH", path)
+            .match_body(r#"{"status":"inactive"}"#)
+            .with_body(
+                r#"{"id":"0000000000000001","name":"t","orgID":"o","flux":"","status":"inactive"}"#,
+            )
+            .expect(2)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let ids = ["0000000000000001", "0000000000000002"];
+        let results = client
+            .deactivate_tasks(ids.iter().map(|&id| TaskId::from(id)), 2)
+            .await;
+
+        mock_server.assert();
+        assert_eq!(results.len(), 2);
+        for (_, result) in results {
+            assert_eq!(result.unwrap().status, Some(TaskStatusType::Inactive));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Capabilities
+//!
+//! Detects which API families a server offers, so generic tools can degrade
+//! gracefully between InfluxDB OSS, Cloud and the 3.x editions.
+
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::request::Response;
+use crate::{Client, RequestError};
+
+impl Client {
+    /// Probe the server for its build, version and the API families it
+    /// offers.
+    ///
+    /// Each family is probed with a `GET` request to one of its routes that
+    /// has no side effects. A probe that fails, `/ping` included, is reported
+    /// as [`Availability::Unknown`] for its family instead of failing the
+    /// call.
+    pub async fn capabilities(&self) -> Result<Capabilities, RequestError> {
+        let ping = self.probe("/ping").await;
+        let header = |name: &str| {
+            ping.as_ref()
+                .ok()?
+                .headers()
+                .get(name)?
+                .to_str()
+                .ok()
+                .map(ToString::to_string)
+        };
+
+        Ok(Capabilities {
+            build: header("X-Influxdb-Build"),
+            version: header("X-Influxdb-Version"),
+            ping: Availability::of(&ping),
+            flux: Availability::of(&self.probe("/api/v2/query/suggestions").await),
+            // Without a database and a query, only fails with `400`
+            sql: Availability::of(&self.probe("/api/v3/query_sql").await),
+            scripts: Availability::of(&self.probe("/api/v2/scripts").await),
+            replications: Availability::of(&self.probe("/api/v2/replications").await),
+        })
+    }
+
+    async fn probe(&self, path: &str) -> Result<Response, RequestError> {
+        let url = self.endpoint_url(path);
+        self.send(self.request(Method::GET, &url)).await
+    }
+}
+
+/// The build, version and API families of a server
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// The build reported by the server, e.g. `OSS` or `Cloud`
+    pub build: Option<String>,
+    /// The version reported by the server
+    pub version: Option<String>,
+    /// Health pings through `/ping`, which report the build and version
+    pub ping: Availability,
+    /// Flux queries through `/api/v2/query`
+    pub flux: Availability,
+    /// SQL queries through `/api/v3/query_sql`
+    pub sql: Availability,
+    /// Invokable scripts
+    pub scripts: Availability,
+    /// Replications to remote servers
+    pub replications: Availability,
+}
+
+/// Whether a server offers an API family, as told by the answer to a probe
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Availability {
+    /// The server answered with a success or `400 Bad Request`, so it knows
+    /// the route
+    Available,
+    /// The server answered with another status, such as `404 Not Found`
+    Unavailable,
+    /// The probe failed, or the server refused to tell, e.g. with `401` or a
+    /// 5xx status
+    Unknown {
+        /// The error or status of the probe
+        reason: String,
+    },
+}
+
+impl Availability {
+    /// Whether the family is known to be available.
+    pub fn is_available(&self) -> bool {
+        *self == Self::Available
+    }
+
+    fn of(probe: &Result<Response, RequestError>) -> Self {
+        let status = match probe {
+            Ok(response) => response.status(),
+            Err(e) => {
+                return Self::Unknown {
+                    reason: e.to_string(),
+                }
+            }
+        };
+        match status {
+            status if status.is_success() || status == StatusCode::BAD_REQUEST => Self::Available,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS => {
+                Self::Unknown {
+                    reason: status.to_string(),
+                }
+            }
+            status if status.is_server_error() => Self::Unknown {
+                reason: status.to_string(),
+            },
+            _ => Self::Unavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn capabilities() {
+        let ping = mock("GET", "/ping")
+            .with_status(204)
+            .with_header("X-Influxdb-Build", "OSS")
+            .with_header("X-Influxdb-Version", "v2.7.1")
+            .create();
+        let flux = mock("GET", "/api/v2/query/suggestions").create();
+        let sql = mock("GET", "/api/v3/query_sql").with_status(404).create();
+        let scripts = mock("GET", "/api/v2/scripts").with_status(401).create();
+        let replications = mock("GET", "/api/v2/replications")
+            .with_status(400)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+
+        let capabilities = client.capabilities().await.unwrap();
+
+        ping.assert();
+        flux.assert();
+        sql.assert();
+        scripts.assert();
+        replications.assert();
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                build: Some("OSS".to_string()),
+                version: Some("v2.7.1".to_string()),
+                ping: Availability::Available,
+                flux: Availability::Available,
+                sql: Availability::Unavailable,
+                scripts: Availability::Unknown {
+                    reason: "401 Unauthorized".to_string()
+                },
+                replications: Availability::Available,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_failed_probes() {
+        let client = Client::new("http://127.0.0.1:1", "org", "");
+
+        let capabilities = client.capabilities().await.unwrap();
+
+        assert_eq!(capabilities.build, None);
+        for availability in [
+            capabilities.ping,
+            capabilities.flux,
+            capabilities.sql,
+            capabilities.scripts,
+            capabilities.replications,
+        ] {
+            assert!(
+                matches!(availability, Availability::Unknown { .. }),
+                "{:?}",
+                availability
+            );
+        }
+    }
+}
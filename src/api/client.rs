@@ -0,0 +1,451 @@
+//! Client trait
+//!
+//! The public operations of [`Client`] as an object-safe trait, so code can be
+//! written against `&dyn InfluxClient` and exercised with
+//! [`MockClient`](crate::mock::MockClient) in tests.
+
+#[cfg(feature = "management")]
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+use chrono::NaiveDateTime;
+use futures::stream;
+use influxdb2_structmap::{FromMap, GenericMap};
+use reqwest::Body;
+
+#[cfg(feature = "management")]
+use crate::api::buckets::ListBucketsRequest;
+use crate::api::capabilities::Capabilities;
+#[cfg(feature = "management")]
+use crate::api::organization::ListOrganizationRequest;
+#[cfg(feature = "management")]
+use crate::api::task::{CreateTaskRequest, ListTasksRequest};
+use crate::models::{
+    AnalyzeQueryResponse, AstResponse, DataPoint, FluxSuggestion, FluxSuggestions, HealthCheck,
+    LanguageRequest, Query,
+};
+#[cfg(feature = "management")]
+use crate::models::{
+    BucketId, Buckets, FluxDuration, LabelId, LabelResponse, LabelsResponse, OnboardingResponse,
+    OrgId, Organizations, PostBucketRequest, TaskId, Tasks,
+};
+use crate::{Client, RequestError, Target};
+
+/// A boxed future returned by the methods of [`InfluxClient`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A boxed future returned by the methods of [`InfluxClient`].
+#[cfg(target_arch = "wasm32")]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// The operations supported by a client to a server supporting the
+/// InfluxData 2.0 API.
+///
+/// All methods but [`query`](Self::query) are object safe; generic callers
+/// can use `query` while trait objects fall back to
+/// [`query_raw`](Self::query_raw).
+pub trait InfluxClient {
+    #[cfg(feature = "management")]
+    /// List all buckets matching specified parameters
+    fn list_buckets(
+        &self,
+        request: Option<ListBucketsRequest>,
+    ) -> BoxFuture<'_, Result<Buckets, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Create a new bucket
+    fn create_bucket(
+        &self,
+        post_bucket_request: Option<PostBucketRequest>,
+    ) -> BoxFuture<'_, Result<(), RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Delete a bucket specified by bucket id.
+    fn delete_bucket<'a>(&'a self, bucket_id: &'a BucketId) -> BoxFuture<'a, Result<(), RequestError>>;
+
+    /// Probe the server for its build, version and the API families it offers
+    fn capabilities(&self) -> BoxFuture<'_, Result<Capabilities, RequestError>>;
+
+    /// Delete data points from a bucket matching specified parameters.
+    fn delete(
+        &self,
+        target: Target,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        predicate: Option<String>,
+    ) -> BoxFuture<'_, Result<(), RequestError>>;
+
+    /// Get health of an instance
+    fn health(&self) -> BoxFuture<'_, Result<HealthCheck, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// List all Labels
+    fn labels(&self) -> BoxFuture<'_, Result<LabelsResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// List all Labels by organization ID
+    fn labels_by_org<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+    ) -> BoxFuture<'a, Result<LabelsResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Retrieve a label by ID
+    fn find_label<'a>(&'a self, label_id: &'a LabelId)
+        -> BoxFuture<'a, Result<LabelResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Create a Label
+    fn create_label<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+        name: &'a str,
+        properties: Option<HashMap<String, String>>,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Update a Label
+    fn update_label<'a>(
+        &'a self,
+        name: Option<String>,
+        properties: Option<HashMap<String, String>>,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Delete a Label
+    fn delete_label<'a>(&'a self, label_id: &'a LabelId) -> BoxFuture<'a, Result<(), RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// List all organizations.
+    fn list_organizations(
+        &self,
+        request: ListOrganizationRequest,
+    ) -> BoxFuture<'_, Result<Organizations, RequestError>>;
+
+    /// Get Query Suggestions
+    fn query_suggestions(&self) -> BoxFuture<'_, Result<FluxSuggestions, RequestError>>;
+
+    /// Query Suggestions with name
+    fn query_suggestions_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<FluxSuggestion, RequestError>>;
+
+    /// Query, returning each row as a `GenericMap`
+    fn query_raw(&self, query: Option<Query>)
+        -> BoxFuture<'_, Result<Vec<GenericMap>, RequestError>>;
+
+    /// Query
+    fn query<T: FromMap + Send + 'static>(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<Vec<T>, RequestError>>
+    where
+        Self: Sized + Sync,
+    {
+        Box::pin(async move {
+            let items = self.query_raw(query).await?;
+            items
+                .into_iter()
+                .map(|item| {
+                    T::try_from_genericmap(item)
+                        .map_err(|e| RequestError::deserializing(e.to_string()))
+                })
+                .collect()
+        })
+    }
+
+    /// Analyze Query
+    fn query_analyze(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<AnalyzeQueryResponse, RequestError>>;
+
+    /// Get Query AST Repsonse
+    fn query_ast(
+        &self,
+        language_request: Option<LanguageRequest>,
+    ) -> BoxFuture<'_, Result<AstResponse, RequestError>>;
+
+    /// Get the readiness of an instance at startup
+    fn ready(&self) -> BoxFuture<'_, Result<bool, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Check if database has default user, org, bucket
+    fn is_onboarding_allowed(&self) -> BoxFuture<'_, Result<bool, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Set up initial user, org and bucket
+    #[allow(clippy::too_many_arguments)]
+    fn onboarding<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Set up a new user, org and bucket
+    #[allow(clippy::too_many_arguments)]
+    fn post_setup_user<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// List all tasks.
+    fn list_tasks(&self, request: ListTasksRequest) -> BoxFuture<'_, Result<Tasks, RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Create a new task.
+    fn create_task(&self, request: CreateTaskRequest) -> BoxFuture<'_, Result<(), RequestError>>;
+
+    #[cfg(feature = "management")]
+    /// Delete a task specified by task_id.
+    fn delete_task<'a>(&'a self, task_id: &'a TaskId) -> BoxFuture<'a, Result<(), RequestError>>;
+
+    /// Write line protocol data to the organization and bucket of `target`.
+    fn write_line_protocol_to(
+        &self,
+        target: Target,
+        body: Body,
+    ) -> BoxFuture<'_, Result<(), RequestError>>;
+
+    /// Write line protocol data to the specified organization and bucket.
+    #[deprecated(note = "use `write_line_protocol_to`, which takes a `Target`")]
+    fn write_line_protocol<'a>(
+        &'a self,
+        org: &'a str,
+        bucket: &'a str,
+        body: Body,
+    ) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.write_line_protocol_to(Target::org(org).with_bucket(bucket), body)
+    }
+
+    /// Write `DataPoint`s to the organization and bucket of `target`.
+    fn write(
+        &self,
+        target: Target,
+        points: Vec<DataPoint>,
+    ) -> BoxFuture<'_, Result<(), RequestError>>;
+}
+
+impl InfluxClient for Client {
+    #[cfg(feature = "management")]
+    fn list_buckets(
+        &self,
+        request: Option<ListBucketsRequest>,
+    ) -> BoxFuture<'_, Result<Buckets, RequestError>> {
+        Box::pin(Self::list_buckets(self, request))
+    }
+
+    #[cfg(feature = "management")]
+    fn create_bucket(
+        &self,
+        post_bucket_request: Option<PostBucketRequest>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        Box::pin(Self::create_bucket(self, post_bucket_request))
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_bucket<'a>(&'a self, bucket_id: &'a BucketId) -> BoxFuture<'a, Result<(), RequestError>> {
+        Box::pin(Self::delete_bucket(self, bucket_id))
+    }
+
+    fn capabilities(&self) -> BoxFuture<'_, Result<Capabilities, RequestError>> {
+        Box::pin(Self::capabilities(self))
+    }
+
+    fn delete(
+        &self,
+        target: Target,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        predicate: Option<String>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        Box::pin(Self::delete(self, target, start, stop, predicate))
+    }
+
+    fn health(&self) -> BoxFuture<'_, Result<HealthCheck, RequestError>> {
+        Box::pin(Self::health(self))
+    }
+
+    #[cfg(feature = "management")]
+    fn labels(&self) -> BoxFuture<'_, Result<LabelsResponse, RequestError>> {
+        Box::pin(Self::labels(self))
+    }
+
+    #[cfg(feature = "management")]
+    fn labels_by_org<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+    ) -> BoxFuture<'a, Result<LabelsResponse, RequestError>> {
+        Box::pin(Self::labels_by_org(self, org_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn find_label<'a>(
+        &'a self,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        Box::pin(Self::find_label(self, label_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn create_label<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+        name: &'a str,
+        properties: Option<HashMap<String, String>>,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        Box::pin(Self::create_label(self, org_id, name, properties))
+    }
+
+    #[cfg(feature = "management")]
+    fn update_label<'a>(
+        &'a self,
+        name: Option<String>,
+        properties: Option<HashMap<String, String>>,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        Box::pin(Self::update_label(self, name, properties, label_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_label<'a>(&'a self, label_id: &'a LabelId) -> BoxFuture<'a, Result<(), RequestError>> {
+        Box::pin(Self::delete_label(self, label_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn list_organizations(
+        &self,
+        request: ListOrganizationRequest,
+    ) -> BoxFuture<'_, Result<Organizations, RequestError>> {
+        Box::pin(Self::list_organizations(self, request))
+    }
+
+    fn query_suggestions(&self) -> BoxFuture<'_, Result<FluxSuggestions, RequestError>> {
+        Box::pin(Self::query_suggestions(self))
+    }
+
+    fn query_suggestions_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<FluxSuggestion, RequestError>> {
+        Box::pin(Self::query_suggestions_name(self, name))
+    }
+
+    fn query_raw(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<Vec<GenericMap>, RequestError>> {
+        Box::pin(Self::query_raw(self, query))
+    }
+
+    fn query_analyze(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<AnalyzeQueryResponse, RequestError>> {
+        Box::pin(Self::query_analyze(self, query))
+    }
+
+    fn query_ast(
+        &self,
+        language_request: Option<LanguageRequest>,
+    ) -> BoxFuture<'_, Result<AstResponse, RequestError>> {
+        Box::pin(Self::query_ast(self, language_request))
+    }
+
+    fn ready(&self) -> BoxFuture<'_, Result<bool, RequestError>> {
+        Box::pin(Self::ready(self))
+    }
+
+    #[cfg(feature = "management")]
+    fn is_onboarding_allowed(&self) -> BoxFuture<'_, Result<bool, RequestError>> {
+        Box::pin(Self::is_onboarding_allowed(self))
+    }
+
+    #[cfg(feature = "management")]
+    fn onboarding<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>> {
+        Box::pin(Self::onboarding(
+            self,
+            username,
+            org,
+            bucket,
+            password,
+            retention_period_hrs,
+            retention_period_seconds,
+        ))
+    }
+
+    #[cfg(feature = "management")]
+    fn post_setup_user<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>> {
+        Box::pin(Self::post_setup_user(
+            self,
+            username,
+            org,
+            bucket,
+            password,
+            retention_period_hrs,
+            retention_period_seconds,
+        ))
+    }
+
+    #[cfg(feature = "management")]
+    fn list_tasks(&self, request: ListTasksRequest) -> BoxFuture<'_, Result<Tasks, RequestError>> {
+        Box::pin(Self::list_tasks(self, request))
+    }
+
+    #[cfg(feature = "management")]
+    fn create_task(&self, request: CreateTaskRequest) -> BoxFuture<'_, Result<(), RequestError>> {
+        Box::pin(Self::create_task(self, request))
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_task<'a>(&'a self, task_id: &'a TaskId) -> BoxFuture<'a, Result<(), RequestError>> {
+        Box::pin(Self::delete_task(self, task_id))
+    }
+
+    fn write_line_protocol_to(
+        &self,
+        target: Target,
+        body: Body,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        Box::pin(Self::write_line_protocol_to(self, target, body))
+    }
+
+    fn write(
+        &self,
+        target: Target,
+        points: Vec<DataPoint>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        Box::pin(Self::write(self, target, stream::iter(points)))
+    }
+}
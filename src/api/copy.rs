@@ -0,0 +1,356 @@
+//! Bucket copy
+//!
+//! Copy the points of a time range from one bucket to another, a batch at a
+//! time, e.g. to move data into a bucket with another retention period:
+//!
+//! ```no_run
+//! use chrono::NaiveDate;
+//! use futures::TryStreamExt;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let stop = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let progress = client.copy_bucket_range("raw", "archive", start, stop, 5000);
+//! futures::pin_mut!(progress);
+//! while let Some(progress) = progress.try_next().await? {
+//!     println!("{} points copied up to {}", progress.points, progress.copied_until);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+
+use chrono::{DateTime, Duration, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use futures::stream::{self, Stream};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+
+use crate::models::template::flux_string;
+use crate::models::{DataPoint, FieldValue, Query};
+use crate::{new_idempotency_key, Client, RequestError};
+
+/// How far a copy got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// The number of points written to the destination so far
+    pub points: u64,
+    /// Every point before this time is copied
+    pub copied_until: NaiveDateTime,
+}
+
+/// What a copy remembers between batches
+pub(crate) struct BucketCopy {
+    /// The client querying the source bucket
+    source: Client,
+    source_bucket: String,
+    /// The client writing to the destination bucket
+    destination: Client,
+    destination_bucket: String,
+    /// Where the next batch starts
+    cursor: DateTime<Utc>,
+    stop: DateTime<Utc>,
+    batch: usize,
+    points: u64,
+    /// The idempotency key of the batch being written, kept until it is
+    /// written
+    batch_key: Option<String>,
+}
+
+/// The columns of a record that aren't tags
+const NOT_TAGS: &[&str] = &[
+    "result",
+    "table",
+    "_start",
+    "_stop",
+    "_time",
+    "_measurement",
+    "_field",
+    "_value",
+];
+
+impl Client {
+    /// Copy the points of `source` from `start` to `stop`, both in UTC, to
+    /// `destination`, reading and writing up to `batch` points at a time.
+    ///
+    /// Nothing is copied until the stream is polled. It yields the progress
+    /// after each batch is written and ends once the range is copied. A
+    /// batch that fails yields its error, and polling the stream again
+    /// retries it, so a copy can be resumed after a failure. Points are
+    /// copied by time, with their measurement, tags, field and value. The
+    /// writes of a batch carry the same idempotency key when it is retried.
+    pub fn copy_bucket_range(
+        &self,
+        source: &str,
+        destination: &str,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        batch: usize,
+    ) -> impl Stream<Item = Result<CopyProgress, RequestError>> {
+        let copy = BucketCopy::new(self, source, self, destination, start, stop, batch);
+
+        stream::unfold(copy, |mut copy| async move {
+            if copy.is_done() {
+                return None;
+            }
+            let result = copy.next_batch().await;
+            Some((result, copy))
+        })
+    }
+}
+
+impl BucketCopy {
+    /// A copy of the range from `start` to `stop` of `source_bucket`, read
+    /// by `source`, to `destination_bucket`, written by `destination`.
+    pub(crate) fn new(
+        source: &Client,
+        source_bucket: &str,
+        destination: &Client,
+        destination_bucket: &str,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        batch: usize,
+    ) -> Self {
+        Self {
+            source: source.clone(),
+            source_bucket: flux_string(source_bucket),
+            destination: destination.clone(),
+            destination_bucket: destination_bucket.to_string(),
+            cursor: Utc.from_utc_datetime(&start),
+            stop: Utc.from_utc_datetime(&stop),
+            batch: batch.max(1),
+            points: 0,
+            batch_key: None,
+        }
+    }
+
+    /// Whether the whole range is copied.
+    pub(crate) fn is_done(&self) -> bool {
+        self.cursor >= self.stop
+    }
+
+    /// Copy the next batch, moving the cursor past it, and return the
+    /// progress. Nothing moves if it fails, so calling it again retries the
+    /// batch.
+    pub(crate) async fn next_batch(&mut self) -> Result<CopyProgress, RequestError> {
+        let mut records = self.query(self.cursor, self.stop, Some(self.batch)).await?;
+        let cursor = match records.last().and_then(time) {
+            Some(last) if records.len() >= self.batch => {
+                if records.first().and_then(time) == Some(last) {
+                    // The whole batch shares one time, so copy every point of
+                    // that time, however many there are
+                    let next = last + Duration::nanoseconds(1);
+                    records = self.query(last, next, None).await?;
+                    next
+                } else {
+                    // The batch may have cut the points of its last time in
+                    // two, so leave them to the next one
+                    records.retain(|record| time(record) != Some(last));
+                    last
+                }
+            }
+            _ => self.stop,
+        };
+
+        let points: Vec<_> = records.iter().filter_map(data_point).collect();
+        let count = points.len() as u64;
+        if !points.is_empty() {
+            let key = self.batch_key.get_or_insert_with(new_idempotency_key);
+            self.destination
+                .with_idempotency_key(key)
+                .expect("a UUID is a valid header value")
+                .write(self.destination_bucket.as_str(), stream::iter(points))
+                .await?;
+        }
+        self.points += count;
+        self.cursor = cursor;
+        self.batch_key = None;
+        Ok(CopyProgress {
+            points: self.points,
+            copied_until: self.cursor.naive_utc(),
+        })
+    }
+
+    async fn query(
+        &self,
+        start: DateTime<Utc>,
+        stop: DateTime<Utc>,
+        limit: Option<usize>,
+    ) -> Result<Vec<GenericMap>, RequestError> {
+        let mut flux = format!(
+            "from(bucket: {}) |> range(start: {}, stop: {}) \
+             |> group() |> sort(columns: [\"_time\"])",
+            self.source_bucket,
+            start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        );
+        if let Some(n) = limit {
+            flux.push_str(&format!(" |> limit(n: {})", n));
+        }
+        self.source.query_records(Query::new(flux)).await
+    }
+}
+
+fn time(record: &GenericMap) -> Option<DateTime<Utc>> {
+    match record.get("_time") {
+        Some(Value::TimeRFC(time)) => Some(time.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+/// The point of a record, or `None` if it isn't one
+fn data_point(record: &GenericMap) -> Option<DataPoint> {
+    let (measurement, field) = match (record.get("_measurement"), record.get("_field")) {
+        (Some(Value::String(measurement)), Some(Value::String(field))) => (measurement, field),
+        _ => return None,
+    };
+    let value = match record.get("_value")? {
+        Value::String(value) => FieldValue::String(value.clone()),
+        Value::Double(value) => FieldValue::F64(value.into_inner()),
+        Value::Bool(value) => FieldValue::Bool(*value),
+        Value::Long(value) => FieldValue::I64(*value),
+        Value::UnsignedLong(value) => FieldValue::U64(*value),
+        _ => return None,
+    };
+
+    let mut point = DataPoint::builder(measurement.as_str());
+    for (column, value) in record {
+        match value {
+            Value::String(tag) if !tag.is_empty() && !NOT_TAGS.contains(&column.as_str()) => {
+                point = point.tag(column.as_str(), tag.as_str());
+            }
+            _ => {}
+        }
+    }
+    point
+        .field(field.as_str(), value)
+        .timestamp(time(record)?.timestamp_nanos())
+        .build()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use futures::TryStreamExt;
+    use mockito::{mock, Matcher};
+
+    fn csv(rows: &[(&str, &str, f64)]) -> String {
+        let mut csv = "#datatype,string,long,dateTime:RFC3339,string,string,string,double
+#group,false,false,false,false,false,false,false
+#default,_result,,,,,,
+,result,table,_time,_measurement,host,_field,_value
+"
+        .to_string();
+        for (time, host, value) in rows {
+            csv.push_str(&format!(",,0,{},cpu,{},usage,{}\n", time, host, value));
+        }
+        csv
+    }
+
+    fn query(range: &str) -> Matcher {
+        Matcher::Regex(format!(r"range\({}\)", range))
+    }
+
+    #[tokio::test]
+    async fn copies_in_batches() {
+        let first = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::AllOf(vec![
+                query("start: 2040-01-01T00:00:00Z, stop: 2040-01-02T00:00:00Z"),
+                Matcher::Regex(r"limit\(n: 3\)".to_string()),
+            ]))
+            .with_body(csv(&[
+                ("2040-01-01T00:00:01Z", "a", 1.0),
+                ("2040-01-01T00:00:02Z", "a", 2.0),
+                ("2040-01-01T00:00:02Z", "b", 3.0),
+            ]))
+            .create();
+        let first_write = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "dst".into()))
+            .match_body("cpu,host=a usage=1 2208988801000000000\n")
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let day = NaiveDate::from_ymd_opt(2040, 1, 1).unwrap();
+        let progress = client.copy_bucket_range(
+            "src",
+            "dst",
+            day.and_hms_opt(0, 0, 0).unwrap(),
+            day.and_hms_opt(0, 0, 0).unwrap() + Duration::days(1),
+            3,
+        );
+        futures::pin_mut!(progress);
+
+        assert_eq!(
+            progress.try_next().await.unwrap(),
+            Some(CopyProgress {
+                points: 1,
+                copied_until: day.and_hms_opt(0, 0, 2).unwrap(),
+            })
+        );
+        first.assert();
+        first_write.assert();
+
+        let _second = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(query(
+                "start: 2040-01-01T00:00:02Z, stop: 2040-01-02T00:00:00Z",
+            ))
+            .with_body(csv(&[
+                ("2040-01-01T00:00:02Z", "a", 2.0),
+                ("2040-01-01T00:00:02Z", "b", 3.0),
+            ]))
+            .create();
+        let second_write = mock("POST", "/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body(
+                "cpu,host=a usage=2 2208988802000000000\n\
+                 cpu,host=b usage=3 2208988802000000000\n",
+            )
+            .with_status(204)
+            .create();
+
+        assert_eq!(
+            progress.try_next().await.unwrap().map(|p| p.points),
+            Some(3)
+        );
+        second_write.assert();
+        assert_eq!(progress.try_next().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn copies_batches_sharing_one_time_whole() {
+        let time = "2040-01-01T00:00:05Z";
+        let _limited = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r"limit\(n: 1\)".to_string()))
+            .with_body(csv(&[(time, "a", 1.0)]))
+            .create();
+        let _whole = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(query(
+                "start: 2040-01-01T00:00:05Z, stop: 2040-01-01T00:00:05.000000001Z",
+            ))
+            .with_body(csv(&[(time, "a", 1.0), (time, "b", 2.0)]))
+            .create();
+        let _write = mock("POST", "/api/v2/write")
+            .match_query(Matcher::Any)
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let start = NaiveDate::from_ymd_opt(2040, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 5)
+            .unwrap();
+        let progress =
+            client.copy_bucket_range("src", "dst", start, start + Duration::seconds(1), 1);
+        futures::pin_mut!(progress);
+
+        let progress = progress.try_next().await.unwrap().unwrap();
+        assert_eq!(progress.points, 2);
+        assert_eq!(progress.copied_until, start + Duration::nanoseconds(1));
+    }
+}
@@ -0,0 +1,155 @@
+//! DBRP mappings API
+//!
+//! The 1.x compatibility API addresses buckets by database and retention
+//! policy through these mappings. `databases` and `retention_policies` list
+//! them the way `SHOW DATABASES` and `SHOW RETENTION POLICIES` would, so code
+//! written against 1.x can discover where to write:
+//!
+//! ```no_run
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! for db in client.databases().await? {
+//!     for rp in client.retention_policies(&db).await? {
+//!         println!("{}.{} is bucket {}", db, rp.retention_policy, rp.bucket_id);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{BucketId, Dbrp, DbrpId, Dbrps, OrgId};
+use crate::{Client, RequestError};
+
+impl Client {
+    /// List the DBRP mappings matching `request`, of the organization of
+    /// this client if `request` names none.
+    pub async fn list_dbrps(&self, mut request: ListDbrpsRequest) -> Result<Dbrps, RequestError> {
+        if request.org.is_none() && request.org_id.is_none() {
+            request.org = Some(self.org.clone());
+        }
+        let qs = serde_qs::to_string(&request).unwrap_or_default();
+        let url = self.endpoint_url(&format!("/api/v2/dbrps?{}", qs));
+
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Dbrps>().await
+    }
+
+    /// The names of the databases mapped in the organization of this
+    /// client, sorted.
+    pub async fn databases(&self) -> Result<Vec<String>, RequestError> {
+        let mut databases: Vec<_> = self
+            .list_dbrps(ListDbrpsRequest::default())
+            .await?
+            .content
+            .into_iter()
+            .map(|dbrp| dbrp.database)
+            .collect();
+        databases.sort();
+        databases.dedup();
+        Ok(databases)
+    }
+
+    /// The mappings of the retention policies of the database `db` in the
+    /// organization of this client, the default one first and the others
+    /// sorted by name. Empty if no such database is mapped.
+    pub async fn retention_policies(&self, db: &str) -> Result<Vec<Dbrp>, RequestError> {
+        let request = ListDbrpsRequest {
+            db: Some(db.to_string()),
+            ..ListDbrpsRequest::default()
+        };
+        let mut policies = self.list_dbrps(request).await?.content;
+        policies.sort_by(|a, b| {
+            b.default
+                .cmp(&a.default)
+                .then_with(|| a.retention_policy.cmp(&b.retention_policy))
+        });
+        Ok(policies)
+    }
+}
+
+/// Request for list DBRP mappings API
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListDbrpsRequest {
+    /// Filter by organization name.
+    pub org: Option<String>,
+    /// Filter by organization ID.
+    #[serde(rename = "orgID")]
+    pub org_id: Option<OrgId>,
+    /// Filter by mapping ID.
+    pub id: Option<DbrpId>,
+    /// Filter by the ID of the bucket mapped to.
+    #[serde(rename = "bucketID")]
+    pub bucket_id: Option<BucketId>,
+    /// Filter by whether the retention policy is the default one.
+    pub default: Option<bool>,
+    /// Filter by database name.
+    pub db: Option<String>,
+    /// Filter by retention policy name.
+    pub rp: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    fn dbrp(id: &str, db: &str, rp: &str, default: bool) -> String {
+        format!(
+            r#"{{"id":"{}","orgID":"0000000000000001","bucketID":"000000000000000{}",
+                "database":"{}","retention_policy":"{}","default":{},"virtual":false}}"#,
+            id,
+            &id[id.len() - 1..],
+            db,
+            rp,
+            default
+        )
+    }
+
+    #[tokio::test]
+    async fn lists_databases() {
+        let mock_server = mock("GET", "/api/v2/dbrps?org=org")
+            .with_body(format!(
+                r#"{{"content":[{},{},{}]}}"#,
+                dbrp("0000000000000003", "telegraf", "autogen", true),
+                dbrp("0000000000000004", "app", "autogen", true),
+                dbrp("0000000000000005", "telegraf", "a_year", false)
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let databases = client.databases().await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(databases, ["app", "telegraf"]);
+    }
+
+    #[tokio::test]
+    async fn lists_retention_policies_default_first() {
+        let mock_server = mock("GET", "/api/v2/dbrps?org=org&db=telegraf")
+            .with_body(format!(
+                r#"{{"content":[{},{},{}]}}"#,
+                dbrp("0000000000000006", "telegraf", "b_week", false),
+                dbrp("0000000000000007", "telegraf", "autogen", true),
+                dbrp("0000000000000008", "telegraf", "a_year", false)
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let policies = client.retention_policies("telegraf").await.unwrap();
+
+        mock_server.assert();
+        let names: Vec<_> = policies
+            .iter()
+            .map(|p| p.retention_policy.as_str())
+            .collect();
+        assert_eq!(names, ["autogen", "a_year", "b_week"]);
+        assert_eq!(policies[0].bucket_id, BucketId::from("0000000000000007"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Query result deduplication
+//!
+//! Results stitched together from several queries contain the rows of any
+//! overlap between their ranges twice, e.g. when a long range is queried in
+//! chunks whose bounds overlap slightly, or when a query is resumed from a
+//! checkpoint taken before its last rows. A [`Dedup`] stage drops the rows
+//! already seen, keyed on their series and `_time`:
+//!
+//! ```no_run
+//! use influxdb2::api::dedup::Dedup;
+//! use influxdb2::models::Query;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let mut dedup = Dedup::new().series_columns(["_measurement", "host"]);
+//! let mut rows = Vec::new();
+//! for (start, stop) in [("-2h", "-59m"), ("-61m", "now()")] {
+//!     let flux = format!(r#"from(bucket: "telegraf") |> range(start: {}, stop: {})"#, start, stop);
+//!     let mut chunk = client.query_raw(Some(Query::new(flux))).await?;
+//!     dedup.retain(&mut chunk);
+//!     rows.append(&mut chunk);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! By default the series of a row is every column but `result`, `table`,
+//! `_start`, `_stop`, `_time` and `_value`, which suits rows with `_field`
+//! and `_value` columns, such as those of `Client::query_spooled`. Rows with
+//! a column per field, such as those of `Client::query_raw`, need their
+//! series columns named with [`Dedup::series_columns`].
+
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Duration, FixedOffset};
+use futures::future;
+use futures::stream::{Stream, TryStreamExt};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+
+/// The columns of a record that aren't part of its series
+const NOT_SERIES: &[&str] = &["result", "table", "_start", "_stop", "_time", "_value"];
+
+/// A dedup stage, remembering the rows it let through
+#[derive(Clone, Debug, Default)]
+pub struct Dedup {
+    /// The columns identifying a series, or every column but `NOT_SERIES`
+    series_columns: Option<Vec<String>>,
+    /// How long the rows older than the latest one are remembered
+    horizon: Option<Duration>,
+    /// The series seen, by time
+    seen: BTreeMap<DateTime<FixedOffset>, HashSet<GenericMap>>,
+    /// The series seen in rows without a time
+    untimed: HashSet<GenericMap>,
+}
+
+impl Dedup {
+    /// A stage letting through the first row of each series and time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Identify the series of a row by `columns` only.
+    pub fn series_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.series_columns = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Forget the rows older than `horizon` before the latest row seen, so
+    /// memory stays bounded over a long stream. Rows are then only
+    /// deduplicated when their windows are stitched in time order and
+    /// overlap by less than `horizon`.
+    pub fn horizon(mut self, horizon: Duration) -> Self {
+        self.horizon = Some(horizon);
+        self
+    }
+
+    /// Remember `row`, returning whether it wasn't seen before.
+    pub fn insert(&mut self, row: &GenericMap) -> bool {
+        let series = self.series(row);
+        let time = match row.get("_time") {
+            Some(Value::TimeRFC(time)) => *time,
+            _ => return self.untimed.insert(series),
+        };
+        if let Some(cutoff) = self.cutoff() {
+            if time < cutoff {
+                // Older than anything remembered, so it can't be told apart
+                return true;
+            }
+        }
+
+        let inserted = self.seen.entry(time).or_default().insert(series);
+        if let Some(cutoff) = self.cutoff() {
+            self.seen = self.seen.split_off(&cutoff);
+        }
+        inserted
+    }
+
+    /// Keep the rows of `rows` that weren't seen before, in their order.
+    pub fn retain(&mut self, rows: &mut Vec<GenericMap>) {
+        rows.retain(|row| self.insert(row));
+    }
+
+    /// Drop the rows of `rows` that were seen before, passing errors on.
+    pub fn stream<S, E>(mut self, rows: S) -> impl Stream<Item = Result<GenericMap, E>>
+    where
+        S: Stream<Item = Result<GenericMap, E>>,
+    {
+        rows.try_filter(move |row| future::ready(self.insert(row)))
+    }
+
+    /// The time before which rows are forgotten
+    fn cutoff(&self) -> Option<DateTime<FixedOffset>> {
+        let latest = *self.seen.keys().next_back()?;
+        Some(latest - self.horizon?)
+    }
+
+    /// The columns of `row` identifying its series
+    fn series(&self, row: &GenericMap) -> GenericMap {
+        match &self.series_columns {
+            Some(columns) => row
+                .iter()
+                .filter(|(column, _)| columns.contains(column))
+                .map(|(column, value)| (column.clone(), value.clone()))
+                .collect(),
+            None => row
+                .iter()
+                .filter(|(column, _)| !NOT_SERIES.contains(&column.as_str()))
+                .map(|(column, value)| (column.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream::{self, StreamExt};
+
+    fn row(time: &str, host: &str, value: f64) -> GenericMap {
+        let mut row = GenericMap::new();
+        row.insert("result".to_string(), Value::String("_result".to_string()));
+        row.insert("_measurement".to_string(), Value::String("cpu".to_string()));
+        row.insert("_field".to_string(), Value::String("usage".to_string()));
+        row.insert("host".to_string(), Value::String(host.to_string()));
+        row.insert(
+            "_time".to_string(),
+            Value::TimeRFC(DateTime::parse_from_rfc3339(time).unwrap()),
+        );
+        row.insert("_value".to_string(), Value::Double(value.into()));
+        row
+    }
+
+    #[test]
+    fn drops_rows_of_overlapping_windows() {
+        let mut dedup = Dedup::new();
+        let mut first = vec![
+            row("2040-01-01T00:00:00Z", "a", 1.0),
+            row("2040-01-01T00:00:01Z", "a", 2.0),
+            row("2040-01-01T00:00:01Z", "b", 3.0),
+        ];
+        let mut second = vec![
+            row("2040-01-01T00:00:01Z", "a", 2.0),
+            // A point rewritten between the queries is still a duplicate
+            row("2040-01-01T00:00:01Z", "b", 3.5),
+            row("2040-01-01T00:00:02Z", "a", 4.0),
+        ];
+        second[0].insert("table".to_string(), Value::Long(1));
+
+        dedup.retain(&mut first);
+        dedup.retain(&mut second);
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(second, vec![row("2040-01-01T00:00:02Z", "a", 4.0)]);
+    }
+
+    #[test]
+    fn keys_on_the_series_columns_given() {
+        let mut dedup = Dedup::new().series_columns(["_measurement"]);
+        assert!(dedup.insert(&row("2040-01-01T00:00:00Z", "a", 1.0)));
+        assert!(!dedup.insert(&row("2040-01-01T00:00:00Z", "b", 2.0)));
+    }
+
+    #[test]
+    fn forgets_rows_past_the_horizon() {
+        let mut dedup = Dedup::new().horizon(Duration::seconds(10));
+        assert!(dedup.insert(&row("2040-01-01T00:00:00Z", "a", 1.0)));
+        assert!(dedup.insert(&row("2040-01-01T00:00:05Z", "a", 1.0)));
+        assert!(!dedup.insert(&row("2040-01-01T00:00:00Z", "a", 1.0)));
+        assert!(dedup.insert(&row("2040-01-01T00:00:30Z", "a", 1.0)));
+        assert_eq!(dedup.seen.len(), 1);
+        assert!(dedup.insert(&row("2040-01-01T00:00:05Z", "a", 1.0)));
+    }
+
+    #[tokio::test]
+    async fn dedups_streams() {
+        let rows = stream::iter(vec![
+            Ok(row("2040-01-01T00:00:00Z", "a", 1.0)),
+            Err("failed"),
+            Ok(row("2040-01-01T00:00:00Z", "a", 1.0)),
+            Ok(row("2040-01-01T00:00:00Z", "b", 1.0)),
+        ]);
+        let rows: Vec<_> = Dedup::new().stream(rows).collect::<Vec<_>>().await;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], Err("failed"));
+    }
+}
@@ -2,12 +2,13 @@
 
 use chrono::NaiveDateTime;
 use reqwest::Method;
-use snafu::ResultExt;
 
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu};
+use crate::request::RequestKind;
+use crate::{Client, RequestError, Target};
 
 impl Client {
-    /// Delete data points from a bucket matching specified parameters.
+    /// Delete data points matching specified parameters from the bucket of
+    /// `target`.
     ///
     /// Usage:
     ///
@@ -26,12 +27,14 @@ impl Client {
     ///
     pub async fn delete(
         &self,
-        bucket: &str,
+        target: impl Into<Target>,
         start: NaiveDateTime,
         stop: NaiveDateTime,
         predicate: Option<String>,
     ) -> Result<(), RequestError> {
-        let delete_url = format!("{}/api/v2/delete", self.url);
+        let target = target.into();
+        let (org, bucket) = self.resolve(&target)?;
+        let delete_url = self.endpoint_url("/api/v2/delete");
         
         let body = serde_json::json!({
             "start": start.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
@@ -40,17 +43,15 @@ impl Client {
         }).to_string();
         
         let response = self
-            .request(Method::POST, &delete_url)
-            .query(&[("bucket", bucket), ("org", &self.org)])
-            .body(body)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request_as(RequestKind::Write, Method::POST, &delete_url)
+                    .query(&[("bucket", bucket), ("org", org)])
+                    .body(body),
+            )
+            .await?;
         
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            HttpSnafu { status, text }.fail()?;
+            return Err(response.error().await);
         }
         
         Ok(())
@@ -79,10 +80,16 @@ mod tests {
             )
             .create();
         
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
         
-        let start = NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
-        let stop = NaiveDate::from_ymd(2021, 1, 1).and_hms(0, 0, 0);
+        let start = NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let stop = NaiveDate::from_ymd_opt(2021, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
         let _result = client.delete(bucket, start, stop, None).await;
         
         mock_server.assert();
@@ -0,0 +1,165 @@
+//! Downsampling tasks
+//!
+//! The most common task aggregates the recent points of a bucket into
+//! windows and writes them to another bucket. A `DownsampleSpec` generates
+//! that task instead of copying the `aggregateWindow` boilerplate around:
+//!
+//! ```
+//! use influxdb2::api::downsample::DownsampleSpec;
+//! use influxdb2::models::FluxDuration;
+//!
+//! let spec = DownsampleSpec::new("raw", "hourly", FluxDuration::from_secs(3600))
+//!     .aggregate("max")
+//!     .fields(["usage_user", "usage_system"]);
+//! assert_eq!(
+//!     spec.flux(),
+//!     "from(bucket: \"raw\")\n    \
+//!      |> range(start: -task.every)\n    \
+//!      |> filter(fn: (r) => r._field == \"usage_user\" or r._field == \"usage_system\")\n    \
+//!      |> aggregateWindow(every: task.every, fn: max, createEmpty: false)\n    \
+//!      |> to(bucket: \"hourly\")"
+//! );
+//! ```
+
+use crate::api::task::TaskBuilder;
+use crate::models::template::flux_string;
+use crate::models::FluxDuration;
+use crate::{Client, RequestError};
+
+/// A task aggregating the points of a bucket into windows of `every` and
+/// writing them to another bucket
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownsampleSpec {
+    /// The bucket read from
+    pub source_bucket: String,
+    /// The bucket written to
+    pub dest_bucket: String,
+    /// How often the task runs, which is also the length of the windows
+    pub every: FluxDuration,
+    /// The Flux function aggregating each window, such as `mean` or `last`
+    pub aggregate: String,
+    /// The fields to downsample, or every field if empty
+    pub fields: Vec<String>,
+    /// The measurement to downsample, or every measurement if `None`
+    pub measurement: Option<String>,
+}
+
+impl DownsampleSpec {
+    /// Downsample `source_bucket` into `dest_bucket` every `every`, taking
+    /// the mean of every field.
+    pub fn new(
+        source_bucket: impl Into<String>,
+        dest_bucket: impl Into<String>,
+        every: FluxDuration,
+    ) -> Self {
+        Self {
+            source_bucket: source_bucket.into(),
+            dest_bucket: dest_bucket.into(),
+            every,
+            aggregate: "mean".to_string(),
+            fields: Vec::new(),
+            measurement: None,
+        }
+    }
+
+    /// Aggregate each window with the Flux function `aggregate`.
+    pub fn aggregate(mut self, aggregate: impl Into<String>) -> Self {
+        self.aggregate = aggregate.into();
+        self
+    }
+
+    /// Downsample only `fields`.
+    pub fn fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Downsample only `measurement`.
+    pub fn measurement(mut self, measurement: impl Into<String>) -> Self {
+        self.measurement = Some(measurement.into());
+        self
+    }
+
+    /// The name of the task, `downsample <source> to <dest>`.
+    pub fn task_name(&self) -> String {
+        format!("downsample {} to {}", self.source_bucket, self.dest_bucket)
+    }
+
+    /// The body of the script, which follows the `option task` block.
+    pub fn flux(&self) -> String {
+        let mut steps = vec![
+            format!("from(bucket: {})", flux_string(&self.source_bucket)),
+            "range(start: -task.every)".to_string(),
+        ];
+        if let Some(measurement) = &self.measurement {
+            steps.push(format!(
+                "filter(fn: (r) => r._measurement == {})",
+                flux_string(measurement)
+            ));
+        }
+        if !self.fields.is_empty() {
+            let fields: Vec<_> = self
+                .fields
+                .iter()
+                .map(|field| format!("r._field == {}", flux_string(field)))
+                .collect();
+            steps.push(format!("filter(fn: (r) => {})", fields.join(" or ")));
+        }
+        steps.push(format!(
+            "aggregateWindow(every: task.every, fn: {}, createEmpty: false)",
+            self.aggregate
+        ));
+        steps.push(format!("to(bucket: {})", flux_string(&self.dest_bucket)));
+        steps.join("\n    |> ")
+    }
+
+    /// A builder for the task, with its name, schedule and script set, to
+    /// set the organization, description or offset before building it.
+    pub fn task(&self) -> TaskBuilder {
+        TaskBuilder::new(self.task_name())
+            .every(self.every.to_string())
+            .flux(self.flux())
+    }
+}
+
+impl Client {
+    /// Create the downsampling task of `spec` in the organization of this
+    /// client.
+    pub async fn create_downsample_task(&self, spec: &DownsampleSpec) -> Result<(), RequestError> {
+        self.create_task(spec.task().org(self.org.as_str()).build())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn creates_downsample_tasks() {
+        let spec =
+            DownsampleSpec::new("raw", "daily", FluxDuration::from_secs(86_400)).measurement("cpu");
+        let flux = "option task = {name: \"downsample raw to daily\", every: 1d}\n\n\
+                    from(bucket: \"raw\")\n    \
+                    |> range(start: -task.every)\n    \
+                    |> filter(fn: (r) => r._measurement == \"cpu\")\n    \
+                    |> aggregateWindow(every: task.every, fn: mean, createEmpty: false)\n    \
+                    |> to(bucket: \"daily\")";
+        let mock_server = mock("POST", "/api/v2/tasks")
+            .match_body(Matcher::Json(
+                serde_json::json!({ "flux": flux, "org": "org" }),
+            ))
+            .with_status(201)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        client.create_downsample_task(&spec).await.unwrap();
+
+        mock_server.assert();
+    }
+}
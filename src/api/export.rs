@@ -0,0 +1,386 @@
+//! Downsampled export
+//!
+//! Archival jobs mostly do the same thing: read a bucket over a long range,
+//! aggregate it into coarser windows and store the result outside InfluxDB.
+//! `Client::export` runs that pipeline in one call. It splits the range into
+//! chunks, aggregates each one with `aggregateWindow` and hands its rows to an
+//! [`ExportSink`], such as annotated CSV with [`CsvSink`]:
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufWriter;
+//! use influxdb2::api::export::CsvSink;
+//! use influxdb2::api::window::{Aggregate, TimeRange};
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut sink = CsvSink::new(BufWriter::new(File::create("telegraf-2039.csv")?));
+//! let range = TimeRange::between("2039-01-01T00:00:00Z".parse()?, "2040-01-01T00:00:00Z".parse()?);
+//! let rows = client
+//!     .export("telegraf", range, "1h".parse()?, Aggregate::Mean, &mut sink)
+//!     .await?;
+//! println!("{} rows archived", rows);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Chunks end on multiples of the window duration, so every window is
+//! aggregated within one chunk and the rows are the same as those of a single
+//! query over the whole range. Aggregates that need numbers, all but `Count`,
+//! `First` and `Last`, skip the points of string and boolean fields.
+
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+use influxdb2_structmap::GenericMap;
+use snafu::{ResultExt, Snafu};
+
+use super::annotated_csv::write_annotated_csv;
+use super::window::{Aggregate, TimeRange};
+use crate::models::template::flux_string;
+use crate::models::{FluxDuration, Query};
+use crate::{Client, RequestError};
+
+/// How much of the range `Client::export` queries at once
+pub const EXPORT_CHUNK: FluxDuration = FluxDuration::from_secs(86_400);
+
+/// Errors that occur while exporting
+#[derive(Debug, Snafu)]
+pub enum ExportError {
+    /// The query of a chunk failed.
+    #[snafu(display("Unable to query the chunk from {}: {}", start, source))]
+    Querying {
+        /// The start of the chunk
+        start: DateTime<Utc>,
+        /// The query error
+        #[snafu(source(from(RequestError, Box::new)))]
+        source: Box<RequestError>,
+    },
+
+    /// The sink failed to take the rows.
+    #[snafu(display("Unable to write the export: {}", source))]
+    Writing {
+        /// The I/O error
+        source: io::Error,
+    },
+}
+
+/// Where the rows of an export go
+///
+/// Implemented for closures taking the rows of a chunk.
+pub trait ExportSink {
+    /// Take the rows of the next chunk, in time order of the chunks.
+    fn write_rows(&mut self, rows: &[GenericMap]) -> io::Result<()>;
+
+    /// Complete the export once every chunk is written.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> ExportSink for F
+where
+    F: FnMut(&[GenericMap]) -> io::Result<()>,
+{
+    fn write_rows(&mut self, rows: &[GenericMap]) -> io::Result<()> {
+        self(rows)
+    }
+}
+
+/// Writes the rows as annotated CSV, the tables of each chunk after those of
+/// the one before
+#[derive(Debug)]
+pub struct CsvSink<W> {
+    writer: W,
+    written: bool,
+}
+
+impl<W: Write> CsvSink<W> {
+    /// Write the rows to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            written: false,
+        }
+    }
+
+    /// The writer, once the export is done.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> ExportSink for CsvSink<W> {
+    fn write_rows(&mut self, rows: &[GenericMap]) -> io::Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        if self.written {
+            self.writer.write_all(b"\n")?;
+        }
+        write_annotated_csv(rows, &mut self.writer)?;
+        self.written = true;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes the rows as an Arrow IPC file, typed as by
+/// `Client::query_to_arrow_ipc`
+///
+/// A file has a single schema, so the rows are kept until the export
+/// finishes and written then, as one record batch.
+#[cfg(all(feature = "arrow", not(target_arch = "wasm32")))]
+#[derive(Debug)]
+pub struct ArrowIpcSink<W> {
+    writer: W,
+    rows: Vec<GenericMap>,
+}
+
+#[cfg(all(feature = "arrow", not(target_arch = "wasm32")))]
+impl<W: Write> ArrowIpcSink<W> {
+    /// Write the file to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            rows: Vec::new(),
+        }
+    }
+
+    /// The writer, once the export is done.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(all(feature = "arrow", not(target_arch = "wasm32")))]
+impl<W: Write> ExportSink for ArrowIpcSink<W> {
+    fn write_rows(&mut self, rows: &[GenericMap]) -> io::Result<()> {
+        self.rows.extend_from_slice(rows);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let rows = std::mem::take(&mut self.rows);
+        super::arrow::write_arrow_ipc(&rows, &mut self.writer)
+            .map_err(io::Error::other)?;
+        self.writer.flush()
+    }
+}
+
+impl Client {
+    /// Aggregate every field of `bucket` with `aggregate` over windows of
+    /// `every` across `range`, [`EXPORT_CHUNK`] at a time, and
+    /// write the rows to `sink`, returning their number.
+    ///
+    /// A chunk that fails stops the export; the rows of the chunks before it
+    /// are in the sink, which isn't finished.
+    pub async fn export<S>(
+        &self,
+        bucket: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+        aggregate: Aggregate,
+        sink: &mut S,
+    ) -> Result<usize, ExportError>
+    where
+        S: ExportSink + ?Sized,
+    {
+        self.export_chunked(bucket, range, every, aggregate, EXPORT_CHUNK, sink)
+            .await
+    }
+
+    /// Like [`Client::export`], querying `chunk` of the range at a time,
+    /// rounded down to a multiple of `every`.
+    pub async fn export_chunked<S>(
+        &self,
+        bucket: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+        aggregate: Aggregate,
+        chunk: FluxDuration,
+        sink: &mut S,
+    ) -> Result<usize, ExportError>
+    where
+        S: ExportSink + ?Sized,
+    {
+        let (start, stop) = match range.into() {
+            TimeRange::Last(duration) => {
+                let now = self.clock.now();
+                (now - duration_of(duration), now)
+            }
+            TimeRange::Between(start, stop) => (start, stop),
+        };
+
+        let mut rows = 0;
+        for (start, stop) in chunks(start, stop, every, chunk) {
+            let flux = export_flux(bucket, start, stop, every, aggregate);
+            let records = self
+                .query_records(Query::new(flux))
+                .await
+                .context(QueryingSnafu { start })?;
+            sink.write_rows(&records).context(WritingSnafu)?;
+            rows += records.len();
+        }
+        sink.finish().context(WritingSnafu)?;
+
+        Ok(rows)
+    }
+}
+
+fn duration_of(duration: FluxDuration) -> Duration {
+    Duration::from_std(duration.as_duration()).unwrap_or_else(|_| Duration::max_value())
+}
+
+/// The ranges the chunks of an export from `start` to `stop` cover, each
+/// ending on a multiple of `chunk` since the epoch, itself a multiple of
+/// `every`, but for the last
+fn chunks(
+    start: DateTime<Utc>,
+    stop: DateTime<Utc>,
+    every: FluxDuration,
+    chunk: FluxDuration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let nanos = |duration: FluxDuration| i64::try_from(duration.as_duration().as_nanos()).ok();
+    let step = match (nanos(every), nanos(chunk)) {
+        (Some(every), Some(chunk)) if every > 0 => (chunk / every).max(1).saturating_mul(every),
+        _ => return vec![(start, stop)],
+    };
+
+    let mut chunks = Vec::new();
+    let mut cursor = start;
+    while cursor < stop {
+        let end = (cursor.timestamp_nanos().div_euclid(step) + 1)
+            .checked_mul(step)
+            .map(|t| Utc.timestamp_nanos(t))
+            .map_or(stop, |end| end.min(stop));
+        chunks.push((cursor, end));
+        cursor = end;
+    }
+    chunks
+}
+
+/// The Flux of a chunk of `Client::export`
+fn export_flux(
+    bucket: &str,
+    start: DateTime<Utc>,
+    stop: DateTime<Utc>,
+    every: FluxDuration,
+    aggregate: Aggregate,
+) -> String {
+    let numeric = !matches!(aggregate, Aggregate::Count | Aggregate::First | Aggregate::Last);
+    format!(
+        "{}from(bucket: {})\n  \
+         |> range(start: {}, stop: {})\n  \
+         {}|> aggregateWindow(every: {}, fn: {}, createEmpty: false)",
+        if numeric { "import \"types\"\n\n" } else { "" },
+        flux_string(bucket),
+        start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        stop.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        if numeric {
+            "|> filter(fn: (r) => types.isNumeric(v: r._value))\n  "
+        } else {
+            ""
+        },
+        every,
+        aggregate.as_str(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn time(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().into()
+    }
+
+    fn every() -> FluxDuration {
+        FluxDuration::from_secs(3600)
+    }
+
+    #[test]
+    fn splits_ranges_on_window_boundaries() {
+        let ranges = chunks(
+            time("2040-01-01T10:20:00Z"),
+            time("2040-01-03T05:00:00Z"),
+            FluxDuration::from_secs(7 * 3600),
+            EXPORT_CHUNK,
+        );
+
+        // A day rounds down to three windows of seven hours, 21h since the epoch
+        assert_eq!(
+            ranges,
+            vec![
+                (time("2040-01-01T10:20:00Z"), time("2040-01-01T12:00:00Z")),
+                (time("2040-01-01T12:00:00Z"), time("2040-01-02T09:00:00Z")),
+                (time("2040-01-02T09:00:00Z"), time("2040-01-03T05:00:00Z")),
+            ]
+        );
+        let start = time("2040-01-01T00:00:00Z");
+        assert_eq!(chunks(start, start, every(), EXPORT_CHUNK), vec![]);
+    }
+
+    #[test]
+    fn builds_export_queries() {
+        let start = time("2040-01-01T00:00:00Z");
+        let stop = time("2040-01-02T00:00:00Z");
+
+        assert_eq!(
+            export_flux("b", start, stop, every(), Aggregate::Mean),
+            "import \"types\"\n\n\
+             from(bucket: \"b\")\n  \
+             |> range(start: 2040-01-01T00:00:00Z, stop: 2040-01-02T00:00:00Z)\n  \
+             |> filter(fn: (r) => types.isNumeric(v: r._value))\n  \
+             |> aggregateWindow(every: 1h, fn: mean, createEmpty: false)"
+        );
+        assert_eq!(
+            export_flux("b", start, stop, every(), Aggregate::Last),
+            "from(bucket: \"b\")\n  \
+             |> range(start: 2040-01-01T00:00:00Z, stop: 2040-01-02T00:00:00Z)\n  \
+             |> aggregateWindow(every: 1h, fn: last, createEmpty: false)"
+        );
+    }
+
+    #[tokio::test]
+    async fn exports_chunks_as_csv() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r"fn: max".to_string()))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,double,string,string
+#group,false,false,false,false,true,true
+#default,_result,,,,,
+,result,table,_time,_value,_field,_measurement
+,,0,2040-01-01T01:00:00Z,0.5,usage,cpu
+",
+            )
+            .expect(2)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let range = TimeRange::between(time("2040-01-01T00:00:00Z"), time("2040-01-03T00:00:00Z"));
+        let mut sink = CsvSink::new(Vec::new());
+        let rows = client
+            .export("b", range, every(), Aggregate::Max, &mut sink)
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(rows, 2);
+        let table = "#datatype,string,long,dateTime:RFC3339,double,string,string
+#group,false,false,false,false,true,true
+#default,_result,,,,,
+,result,table,_time,_value,_field,_measurement
+,,0,2040-01-01T01:00:00Z,0.5,usage,cpu
+";
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            format!("{}\n{}", table, table)
+        );
+    }
+}
@@ -3,33 +3,19 @@
 //! Get health of an InfluxDB instance
 
 use crate::models::HealthCheck;
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu};
+use crate::{Client, RequestError};
 use reqwest::{Method, StatusCode};
-use snafu::ResultExt;
 
 impl Client {
     /// Get health of an instance
     pub async fn health(&self) -> Result<HealthCheck, RequestError> {
-        let health_url = format!("{}/health", self.url);
-        let response = self
-            .request(Method::GET, &health_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let health_url = self.endpoint_url("/health");
+        let response = self.send(self.request(Method::GET, &health_url)).await?;
 
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<HealthCheck>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            StatusCode::SERVICE_UNAVAILABLE => Ok(response
-                .json::<HealthCheck>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<HealthCheck>().await,
+            StatusCode::SERVICE_UNAVAILABLE => response.json::<HealthCheck>().await,
+            _ => Err(response.error().await),
         }
     }
 }
@@ -43,7 +29,7 @@ mod tests {
     async fn health() {
         let mock_server = mock("GET", "/health").create();
 
-        let client = Client::new(&mockito::server_url(), "", "");
+        let client = Client::new(mockito::server_url(), "", "");
 
         let _result = client.health().await;
 
@@ -0,0 +1,223 @@
+//! Query result joins
+//!
+//! Enriching the rows of one query with those of another, e.g. CPU usage
+//! with the rack of each host kept in a small lookup bucket, is slow and
+//! awkward with the Flux `join` functions. A [`Join`] combines two query
+//! results on the client instead, matching rows on `_time` and the columns
+//! given:
+//!
+//! ```no_run
+//! use influxdb2::api::join::Join;
+//! use influxdb2::models::Query;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let usage = client
+//!     .query_raw(Some(Query::new(r#"from(bucket: "telegraf") |> range(start: -1h)"#.to_string())))
+//!     .await?;
+//! let racks = client
+//!     .query_raw(Some(Query::new(r#"from(bucket: "inventory") |> range(start: -30d) |> last()"#.to_string())))
+//!     .await?;
+//! let rows = Join::on(["host"]).without_time().left().join(&usage, &racks);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A combined row holds the columns of both rows. The columns of the right
+//! row that the left row has too, other than the join columns, are renamed
+//! with a suffix, `_right` by default; the `result` and `table` columns of the
+//! right row are dropped.
+
+use std::collections::HashMap;
+
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+
+/// The columns of the right row that are left out of combined rows
+const BOOKKEEPING: &[&str] = &["result", "table"];
+
+/// Which rows of the left result a join keeps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum JoinKind {
+    /// Only the rows matching a row of the right result
+    #[default]
+    Inner,
+    /// Every row, as it is when no row of the right result matches
+    Left,
+}
+
+/// A join of two query results
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Join {
+    /// The columns rows match on, besides `_time` when `on_time` is set
+    columns: Vec<String>,
+    on_time: bool,
+    kind: JoinKind,
+    suffix: String,
+}
+
+impl Join {
+    /// An inner join on `_time` and `columns`.
+    pub fn on<I, S>(columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            columns: columns.into_iter().map(Into::into).collect(),
+            on_time: true,
+            kind: JoinKind::Inner,
+            suffix: "_right".to_string(),
+        }
+    }
+
+    /// Match rows on the join columns only, ignoring `_time`, e.g. to look
+    /// up the rows of a small set of metadata.
+    pub fn without_time(mut self) -> Self {
+        self.on_time = false;
+        self
+    }
+
+    /// Keep the rows of the left result that match no row of the right one.
+    pub fn left(mut self) -> Self {
+        self.kind = JoinKind::Left;
+        self
+    }
+
+    /// Set which rows of the left result are kept.
+    pub fn kind(mut self, kind: JoinKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Rename the conflicting columns of the right row with `suffix`.
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+
+    /// Combine every row of `left` with each row of `right` it matches, in
+    /// the order of `left`, then of `right`.
+    pub fn join(&self, left: &[GenericMap], right: &[GenericMap]) -> Vec<GenericMap> {
+        let mut lookup: HashMap<Vec<&Value>, Vec<&GenericMap>> = HashMap::new();
+        for row in right {
+            if let Some(key) = self.key(row) {
+                lookup.entry(key).or_default().push(row);
+            }
+        }
+
+        let mut rows = Vec::with_capacity(left.len());
+        for row in left {
+            let matches = self.key(row).and_then(|key| lookup.get(&key));
+            match matches {
+                Some(matches) => rows.extend(matches.iter().map(|other| self.combine(row, other))),
+                None if self.kind == JoinKind::Left => rows.push(row.clone()),
+                None => {}
+            }
+        }
+        rows
+    }
+
+    /// The values of the join columns of `row`, `None` if one is missing
+    fn key<'a>(&self, row: &'a GenericMap) -> Option<Vec<&'a Value>> {
+        let time = if self.on_time { Some("_time") } else { None };
+        time.into_iter()
+            .chain(self.columns.iter().map(String::as_str))
+            .map(|column| row.get(column))
+            .collect()
+    }
+
+    fn combine(&self, left: &GenericMap, right: &GenericMap) -> GenericMap {
+        let mut row = left.clone();
+        for (column, value) in right {
+            if BOOKKEEPING.contains(&column.as_str()) || self.is_key(column) {
+                continue;
+            }
+            let column = if left.contains_key(column) {
+                format!("{}{}", column, self.suffix)
+            } else {
+                column.clone()
+            };
+            row.insert(column, value.clone());
+        }
+        row
+    }
+
+    fn is_key(&self, column: &str) -> bool {
+        (self.on_time && column == "_time") || self.columns.iter().any(|c| c == column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    /// A row of `host` at `second` past 2040, with the `extra` columns
+    fn row(second: u32, host: Option<&str>, extra: &[(&str, Value)]) -> GenericMap {
+        let time = format!("2040-01-01T00:00:{:02}Z", second);
+        let mut row = GenericMap::new();
+        row.insert(
+            "_time".to_string(),
+            Value::TimeRFC(DateTime::parse_from_rfc3339(&time).unwrap()),
+        );
+        if let Some(host) = host {
+            row.insert("host".to_string(), Value::String(host.to_string()));
+        }
+        for (column, value) in extra {
+            row.insert(column.to_string(), value.clone());
+        }
+        row
+    }
+
+    #[test]
+    fn joins_on_time_and_columns() {
+        let left = vec![
+            row(0, Some("a"), &[("_value", Value::Long(1))]),
+            row(1, Some("a"), &[("_value", Value::Long(2))]),
+            row(0, Some("b"), &[("_value", Value::Long(3))]),
+        ];
+        let right = vec![
+            row(0, Some("a"), &[("_value", Value::Long(10)), ("table", Value::Long(4))]),
+            row(0, Some("b"), &[("_value", Value::Long(30)), ("zone", Value::Long(5))]),
+        ];
+
+        let rows = Join::on(["host"]).join(&left, &right);
+
+        assert_eq!(
+            rows,
+            vec![
+                row(0, Some("a"), &[("_value", Value::Long(1)), ("_value_right", Value::Long(10))]),
+                row(
+                    0,
+                    Some("b"),
+                    &[
+                        ("_value", Value::Long(3)),
+                        ("_value_right", Value::Long(30)),
+                        ("zone", Value::Long(5)),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn looks_up_rows_without_time() {
+        let left = vec![row(1, Some("a"), &[]), row(2, Some("c"), &[]), row(3, None, &[])];
+        let right = vec![row(0, Some("a"), &[("rack", Value::Long(1))])];
+
+        let rows = Join::on(["host"])
+            .without_time()
+            .left()
+            .suffix("_meta")
+            .join(&left, &right);
+
+        assert_eq!(
+            rows,
+            vec![
+                row(1, Some("a"), &[("rack", Value::Long(1)), ("_time_meta", right[0]["_time"].clone())]),
+                left[1].clone(),
+                left[2].clone(),
+            ]
+        );
+    }
+}
@@ -1,91 +1,79 @@
 //! Labels
 
-use crate::models::{LabelCreateRequest, LabelResponse, LabelUpdate, LabelsResponse};
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu, SerializingSnafu};
+use crate::models::{
+    Label, LabelCreateRequest, LabelId, LabelResponse, LabelSpec, LabelUpdate, LabelsResponse,
+    OrgId,
+};
+use crate::{Client, LabelNotFoundSnafu, RequestError, SerializingSnafu, Target};
+use parking_lot::Mutex;
 use reqwest::{Method, StatusCode};
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt};
 use std::collections::HashMap;
 
 impl Client {
     /// List all Labels
     pub async fn labels(&self) -> Result<LabelsResponse, RequestError> {
-        Ok(self.get_labels(None).await?)
+        self.get_labels(None).await
     }
 
     /// List all Labels by organization ID
-    pub async fn labels_by_org(&self, org_id: &str) -> Result<LabelsResponse, RequestError> {
-        Ok(self.get_labels(Some(org_id)).await?)
+    pub async fn labels_by_org(&self, org_id: &OrgId) -> Result<LabelsResponse, RequestError> {
+        self.get_labels(Some(org_id)).await
     }
 
-    async fn get_labels(&self, org_id: Option<&str>) -> Result<LabelsResponse, RequestError> {
-        let labels_url = format!("{}/api/v2/labels", self.url);
+    async fn get_labels(&self, org_id: Option<&OrgId>) -> Result<LabelsResponse, RequestError> {
+        let labels_url = self.endpoint_url("/api/v2/labels");
         let mut request = self.request(Method::GET, &labels_url);
 
         if let Some(id) = org_id {
-            request = request.query(&[("orgID", id)]);
+            request = request.query(&[("orgID", id.as_str())]);
         }
 
-        let response = request.send().await.context(ReqwestProcessingSnafu)?;
+        let response = self.send(request).await?;
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<LabelsResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<LabelsResponse>().await,
+            _ => Err(response.error().await),
         }
     }
 
     /// Retrieve a label by ID
-    pub async fn find_label(&self, label_id: &str) -> Result<LabelResponse, RequestError> {
-        let labels_by_id_url = format!("{}/api/v2/labels/{}", self.url, label_id);
-        let response = self
-            .request(Method::GET, &labels_by_id_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+    pub async fn find_label(&self, label_id: &LabelId) -> Result<LabelResponse, RequestError> {
+        let labels_by_id_url = self.endpoint_url(&format!("/api/v2/labels/{}", label_id));
+        let response = self.send(self.request(Method::GET, &labels_by_id_url)).await?;
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<LabelResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<LabelResponse>().await,
+            _ => Err(response.error().await),
         }
     }
 
     /// Create a Label
     pub async fn create_label(
         &self,
-        org_id: &str,
+        org_id: &OrgId,
         name: &str,
         properties: Option<HashMap<String, String>>,
     ) -> Result<LabelResponse, RequestError> {
-        let create_label_url = format!("{}/api/v2/labels", self.url);
+        let create_label_url = self.endpoint_url("/api/v2/labels");
         let body = LabelCreateRequest {
-            org_id: org_id.into(),
+            org_id: org_id.clone(),
             name: name.into(),
             properties,
         };
         let response = self
-            .request(Method::POST, &create_label_url)
-            .body(serde_json::to_string(&body).context(SerializingSnafu)?)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request(Method::POST, &create_label_url)
+                    .body(serde_json::to_string(&body).context(SerializingSnafu)?),
+            )
+            .await?;
         match response.status() {
-            StatusCode::CREATED => Ok(response
-                .json::<LabelResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
+            StatusCode::CREATED => {
+                let created = response.json::<LabelResponse>().await?;
+                if let Some(id) = created.label.as_ref().and_then(|label| label.id.clone()) {
+                    self.label_ids.insert(org_id, name, id);
+                }
+                Ok(created)
             }
+            _ => Err(response.error().await),
         }
     }
 
@@ -94,44 +82,173 @@ impl Client {
         &self,
         name: Option<String>,
         properties: Option<HashMap<String, String>>,
-        label_id: &str,
+        label_id: &LabelId,
     ) -> Result<LabelResponse, RequestError> {
-        let update_label_url = format!("{}/api/v2/labels/{}", &self.url, label_id);
+        let update_label_url = self.endpoint_url(&format!("/api/v2/labels/{}", label_id));
         let body = LabelUpdate { name, properties };
         let response = self
-            .request(Method::PATCH, &update_label_url)
-            .body(serde_json::to_string(&body).context(SerializingSnafu)?)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request(Method::PATCH, &update_label_url)
+                    .body(serde_json::to_string(&body).context(SerializingSnafu)?),
+            )
+            .await?;
+        self.label_ids.remove(label_id);
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<LabelResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<LabelResponse>().await,
+            _ => Err(response.error().await),
         }
     }
 
     /// Delete a Label
-    pub async fn delete_label(&self, label_id: &str) -> Result<(), RequestError> {
-        let delete_label_url = format!("{}/api/v2/labels/{}", &self.url, label_id);
-        let response = self
-            .request(Method::DELETE, &delete_label_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+    pub async fn delete_label(&self, label_id: &LabelId) -> Result<(), RequestError> {
+        let delete_label_url = self.endpoint_url(&format!("/api/v2/labels/{}", label_id));
+        let response = self.send(self.request(Method::DELETE, &delete_label_url)).await?;
         match response.status() {
-            StatusCode::NO_CONTENT => Ok(()),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
+            StatusCode::NO_CONTENT => {
+                self.label_ids.remove(label_id);
+                Ok(())
             }
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// The ID of the label named `name` in the organization named `org`.
+    ///
+    /// The labels of an organization are listed once and reused by this
+    /// client and its clones, which keep them up to date when they create,
+    /// rename or delete labels. A name that isn't cached lists the labels
+    /// again; `clear_label_ids` forgets labels changed by other clients.
+    pub async fn label_id_by_name(&self, org: &str, name: &str) -> Result<LabelId, RequestError> {
+        self.label_id_by_name_to(Target::org(org), name).await
+    }
+
+    /// The ID of the label named `name` in the organization of `target`,
+    /// cached like `label_id_by_name`.
+    pub async fn label_id_by_name_to(
+        &self,
+        target: impl Into<Target>,
+        name: &str,
+    ) -> Result<LabelId, RequestError> {
+        let target = target.into();
+        let org = self.resolve_org(&target);
+        let org_id = self.org_id(org).await?;
+        if let Some(id) = self.label_ids.get(&org_id, name) {
+            return Ok(id);
+        }
+
+        let labels = self.labels_by_org(&org_id).await?.labels;
+        self.label_ids.replace(
+            &org_id,
+            labels
+                .into_iter()
+                .filter_map(|label| Some((label.name?, label.id?)))
+                .collect(),
+        );
+        self.label_ids
+            .get(&org_id, name)
+            .context(LabelNotFoundSnafu { org, name })
+    }
+
+    /// Converge the labels of the organization named `org` to `desired`:
+    /// create the missing labels, update the properties that differ and
+    /// delete the labels not in `desired`. Returns the labels of `desired`
+    /// as they are now.
+    pub async fn ensure_labels(
+        &self,
+        org: &str,
+        desired: Vec<LabelSpec>,
+    ) -> Result<Vec<Label>, RequestError> {
+        self.ensure_labels_to(Target::org(org), desired).await
+    }
+
+    /// Converge the labels of the organization of `target` to `desired`,
+    /// like `ensure_labels`.
+    pub async fn ensure_labels_to(
+        &self,
+        target: impl Into<Target>,
+        desired: Vec<LabelSpec>,
+    ) -> Result<Vec<Label>, RequestError> {
+        let target = target.into();
+        let org = self.resolve_org(&target);
+        let org_id = self.org_id(org).await?;
+        let mut existing: HashMap<String, Label> = self
+            .labels_by_org(&org_id)
+            .await?
+            .labels
+            .into_iter()
+            .filter_map(|label| Some((label.name.clone()?, label)))
+            .collect();
+
+        let mut labels = Vec::with_capacity(desired.len());
+        for spec in desired {
+            let label = match existing.remove(&spec.name) {
+                None => {
+                    self.create_label(&org_id, &spec.name, Some(spec.properties))
+                        .await?
+                        .label
+                }
+                Some(label) if label.properties.clone().unwrap_or_default() == spec.properties => {
+                    Some(label)
+                }
+                Some(label) => {
+                    let id = label
+                        .id
+                        .clone()
+                        .ok_or_else(|| RequestError::missing_id("label", &spec.name))?;
+                    // Properties are removed by sending them with an empty value
+                    let mut properties = spec.properties;
+                    for key in label.properties.iter().flat_map(HashMap::keys) {
+                        properties.entry(key.clone()).or_default();
+                    }
+                    self.update_label(None, Some(properties), &id).await?.label
+                }
+            };
+            labels.extend(label);
+        }
+
+        for id in existing.into_values().filter_map(|label| label.id) {
+            self.delete_label(&id).await?;
+        }
+        Ok(labels)
+    }
+
+    /// Forget every cached label ID.
+    pub fn clear_label_ids(&self) {
+        self.label_ids.clear();
+    }
+}
+
+/// Label IDs by organization ID and label name
+#[derive(Debug, Default)]
+pub(crate) struct LabelIdCache {
+    entries: Mutex<HashMap<OrgId, HashMap<String, LabelId>>>,
+}
+
+impl LabelIdCache {
+    fn get(&self, org_id: &OrgId, name: &str) -> Option<LabelId> {
+        self.entries.lock().get(org_id)?.get(name).cloned()
+    }
+
+    fn replace(&self, org_id: &OrgId, labels: HashMap<String, LabelId>) {
+        self.entries.lock().insert(org_id.clone(), labels);
+    }
+
+    /// Record a label, if the labels of its organization are cached
+    fn insert(&self, org_id: &OrgId, name: &str, id: LabelId) {
+        if let Some(labels) = self.entries.lock().get_mut(org_id) {
+            labels.insert(name.to_string(), id);
+        }
+    }
+
+    fn remove(&self, id: &LabelId) {
+        for labels in self.entries.lock().values_mut() {
+            labels.retain(|_, cached| cached != id);
         }
     }
+
+    fn clear(&self) {
+        self.entries.lock().clear();
+    }
 }
 
 #[cfg(test)]
@@ -149,7 +266,7 @@ mod tests {
             .match_header("Authorization", format!("Token {}", token).as_str())
             .create();
 
-        let client = Client::new(&mockito::server_url(), "", token);
+        let client = Client::new(mockito::server_url(), "", token);
 
         let _result = client.labels().await;
 
@@ -165,9 +282,9 @@ mod tests {
             .match_header("Authorization", format!("Token {}", token).as_str())
             .create();
 
-        let client = Client::new(&mockito::server_url(), "", token);
+        let client = Client::new(mockito::server_url(), "", token);
 
-        let _result = client.labels_by_org(org_id).await;
+        let _result = client.labels_by_org(&org_id.into()).await;
 
         mock_server.assert();
     }
@@ -181,9 +298,9 @@ mod tests {
             .match_header("Authorization", format!("Token {}", token).as_str())
             .create();
 
-        let client = Client::new(&mockito::server_url(), "", token);
+        let client = Client::new(mockito::server_url(), "", token);
 
-        let _result = client.find_label(label_id).await;
+        let _result = client.find_label(&label_id.into()).await;
 
         mock_server.assert();
     }
@@ -207,9 +324,9 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org_id, token);
+        let client = Client::new(mockito::server_url(), org_id, token);
 
-        let _result = client.create_label(org_id, name, Some(properties)).await;
+        let _result = client.create_label(&org_id.into(), name, Some(properties)).await;
 
         mock_server.assert();
     }
@@ -225,9 +342,9 @@ mod tests {
             .match_body(format!(r#"{{"orgID":"{}","name":"{}"}}"#, org_id, name).as_str())
             .create();
 
-        let client = Client::new(&mockito::server_url(), org_id, token);
+        let client = Client::new(mockito::server_url(), org_id, token);
 
-        let _result = client.create_label(org_id, name, None).await;
+        let _result = client.create_label(&org_id.into(), name, None).await;
 
         mock_server.assert();
     }
@@ -251,10 +368,10 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), "", token);
+        let client = Client::new(mockito::server_url(), "", token);
 
         let _result = client
-            .update_label(Some(name.to_string()), Some(properties), label_id)
+            .update_label(Some(name.to_string()), Some(properties), &label_id.into())
             .await;
 
         mock_server.assert();
@@ -270,9 +387,9 @@ mod tests {
             .match_body("{}")
             .create();
 
-        let client = Client::new(&mockito::server_url(), "", token);
+        let client = Client::new(mockito::server_url(), "", token);
 
-        let _result = client.update_label(None, None, label_id).await;
+        let _result = client.update_label(None, None, &label_id.into()).await;
 
         mock_server.assert();
     }
@@ -286,10 +403,118 @@ mod tests {
             .match_header("Authorization", format!("Token {}", token).as_str())
             .create();
 
-        let client = Client::new(&mockito::server_url(), "", token);
+        let client = Client::new(mockito::server_url(), "", token);
 
-        let _result = client.delete_label(label_id).await;
+        let _result = client.delete_label(&label_id.into()).await;
 
         mock_server.assert();
     }
+
+    #[tokio::test]
+    async fn caches_label_ids() {
+        let orgs = mock("GET", "/api/v2/orgs?org=org")
+            .with_body(r#"{"orgs":[{"id":"0000000000000001","name":"org"}]}"#)
+            .create();
+        let labels = mock("GET", "/api/v2/labels?orgID=0000000000000001")
+            .with_body(r#"{"labels":[{"id":"00000000000000aa","name":"prod"}]}"#)
+            .expect(3)
+            .create();
+        let deleted = mock("DELETE", "/api/v2/labels/00000000000000aa")
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let id = client.label_id_by_name("org", "prod").await.unwrap();
+        assert_eq!(id.as_str(), "00000000000000aa");
+        client.label_id_by_name("org", "prod").await.unwrap();
+
+        client.delete_label(&id).await.unwrap();
+        client.label_id_by_name("org", "prod").await.unwrap();
+        let missing = client.label_id_by_name("org", "staging").await;
+
+        orgs.assert();
+        labels.assert();
+        deleted.assert();
+        assert!(matches!(missing, Err(RequestError::LabelNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn label_id_in_default_org() {
+        let orgs = mock("GET", "/api/v2/orgs?org=label-org")
+            .with_body(r#"{"orgs":[{"id":"0000000000000002","name":"label-org"}]}"#)
+            .create();
+        let labels = mock("GET", "/api/v2/labels?orgID=0000000000000002")
+            .with_body(r#"{"labels":[{"id":"00000000000000dd","name":"dev"}]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "label-org", "");
+        let id = client.label_id_by_name_to(Target::new(), "dev").await.unwrap();
+
+        orgs.assert();
+        labels.assert();
+        assert_eq!(id.as_str(), "00000000000000dd");
+    }
+
+    #[tokio::test]
+    async fn ensure_labels_without_id() {
+        let orgs = mock("GET", "/api/v2/orgs?org=idless-label-org")
+            .with_body(r#"{"orgs":[{"id":"0000000000000003","name":"idless-label-org"}]}"#)
+            .create();
+        let labels = mock("GET", "/api/v2/labels?orgID=0000000000000003")
+            .with_body(r#"{"labels":[{"name":"a","properties":{"color":"red"}}]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let desired = vec![LabelSpec::new("a").property("color", "blue")];
+        let result = client.ensure_labels("idless-label-org", desired).await;
+
+        orgs.assert();
+        labels.assert();
+        assert!(matches!(result, Err(RequestError::Deserializing { .. })), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn ensures_labels() {
+        let orgs = mock("GET", "/api/v2/orgs?org=org")
+            .with_body(r#"{"orgs":[{"id":"0000000000000001","name":"org"}]}"#)
+            .create();
+        let labels = mock("GET", "/api/v2/labels?orgID=0000000000000001")
+            .with_body(
+                r#"{"labels":[
+                    {"id":"00000000000000aa","name":"a","properties":{"color":"red","x":"1"}},
+                    {"id":"00000000000000bb","name":"b"}
+                ]}"#,
+            )
+            .create();
+        let updated = mock("PATCH", "/api/v2/labels/00000000000000aa")
+            .match_body(mockito::Matcher::Json(
+                serde_json::json!({"properties": {"color": "blue", "x": ""}}),
+            ))
+            .with_body(r#"{"label":{"id":"00000000000000aa","name":"a"}}"#)
+            .create();
+        let created = mock("POST", "/api/v2/labels")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "orgID": "0000000000000001",
+                "name": "c",
+                "properties": {}
+            })))
+            .with_status(201)
+            .with_body(r#"{"label":{"id":"00000000000000cc","name":"c"}}"#)
+            .create();
+        let deleted = mock("DELETE", "/api/v2/labels/00000000000000bb")
+            .with_status(204)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let desired = vec![LabelSpec::new("a").property("color", "blue"), LabelSpec::new("c")];
+        let labels_now = client.ensure_labels("org", desired).await.unwrap();
+
+        orgs.assert();
+        labels.assert();
+        updated.assert();
+        created.assert();
+        deleted.assert();
+        let names: Vec<_> = labels_now.iter().filter_map(|l| l.name.as_deref()).collect();
+        assert_eq!(names, ["a", "c"]);
+    }
 }
@@ -0,0 +1,165 @@
+//! Latest values
+//!
+//! Status pages and device dashboards mostly show the latest value of each
+//! series. `Client::last_value` finds it with a `filter() |> last()` query,
+//! which the server answers from its index instead of reading the whole
+//! range:
+//!
+//! ```no_run
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! for latest in client.last_value("devices", "battery", "level", &[("site", "lab")]).await? {
+//!     println!("{:?} at {}: {:?}", latest.tags, latest.time, latest.value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Series without a point in the last [`LAST_VALUE_LOOKBACK`] are left out;
+//! `Client::last_value_within` looks further back.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset};
+use influxdb2_structmap::value::Value;
+
+use super::window::NOT_TAGS;
+use crate::models::template::flux_string;
+use crate::models::{FluxDuration, Query};
+use crate::{Client, RequestError};
+
+/// How far back `Client::last_value` looks for points
+pub const LAST_VALUE_LOOKBACK: FluxDuration = FluxDuration::from_secs(30 * 86_400);
+
+/// The latest point of a series
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatestValue {
+    /// The tags of the series
+    pub tags: BTreeMap<String, String>,
+    /// The time of the point
+    pub time: DateTime<FixedOffset>,
+    /// The value of the field
+    pub value: Value,
+}
+
+impl Client {
+    /// The latest value of `field` of each series of `measurement` in
+    /// `bucket` whose tags equal `tag_filters`, within the last
+    /// [`LAST_VALUE_LOOKBACK`].
+    pub async fn last_value(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        tag_filters: &[(&str, &str)],
+    ) -> Result<Vec<LatestValue>, RequestError> {
+        self.last_value_within(bucket, measurement, field, tag_filters, LAST_VALUE_LOOKBACK)
+            .await
+    }
+
+    /// Like [`Client::last_value`], looking back `lookback` for points.
+    pub async fn last_value_within(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        tag_filters: &[(&str, &str)],
+        lookback: FluxDuration,
+    ) -> Result<Vec<LatestValue>, RequestError> {
+        let flux = last_value_flux(bucket, measurement, field, tag_filters, lookback);
+        let rows = self.query_records(Query::new(flux)).await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|mut row| {
+                let time = match row.remove("_time") {
+                    Some(Value::TimeRFC(time)) => time,
+                    _ => return None,
+                };
+                let value = row.remove("_value")?;
+                let tags = row
+                    .into_iter()
+                    .filter(|(column, _)| !NOT_TAGS.contains(&column.as_str()))
+                    .filter_map(|(column, value)| match value {
+                        Value::String(value) => Some((column, value)),
+                        _ => None,
+                    })
+                    .collect();
+                Some(LatestValue { tags, time, value })
+            })
+            .collect())
+    }
+}
+
+/// The Flux of `Client::last_value_within`
+fn last_value_flux(
+    bucket: &str,
+    measurement: &str,
+    field: &str,
+    tag_filters: &[(&str, &str)],
+    lookback: FluxDuration,
+) -> String {
+    let mut predicate = format!(
+        "r._measurement == {} and r._field == {}",
+        flux_string(measurement),
+        flux_string(field),
+    );
+    for (tag, value) in tag_filters {
+        predicate.push_str(&format!(" and r[{}] == {}", flux_string(tag), flux_string(value)));
+    }
+    format!(
+        "from(bucket: {})\n  \
+         |> range(start: -{})\n  \
+         |> filter(fn: (r) => {})\n  \
+         |> last()",
+        flux_string(bucket),
+        lookback,
+        predicate,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn builds_last_value_queries() {
+        assert_eq!(
+            last_value_flux("b", "battery", "level", &[("site", "lab \"2\"")], LAST_VALUE_LOOKBACK),
+            "from(bucket: \"b\")\n  \
+             |> range(start: -30d)\n  \
+             |> filter(fn: (r) => r._measurement == \"battery\" and r._field == \"level\" \
+             and r[\"site\"] == \"lab \\\"2\\\"\")\n  \
+             |> last()"
+        );
+    }
+
+    #[tokio::test]
+    async fn last_value() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r"\|> last\(\)".to_string()))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,long,string,string,string
+#group,false,false,false,false,true,true,true
+#default,_result,,,,,,
+,result,table,_time,_value,_field,_measurement,device
+,,0,2040-01-01T00:05:00Z,87,level,battery,a
+,,1,2040-01-01T00:04:00Z,12,level,battery,b
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let latest = client.last_value("b", "battery", "level", &[]).await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].tags["device"], "a");
+        assert_eq!(latest[0].value, Value::Long(87));
+        assert_eq!(
+            latest[1].time,
+            DateTime::parse_from_rfc3339("2040-01-01T00:04:00Z").unwrap()
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Cross-server migration
+//!
+//! Copy the points of a bucket on one server into a bucket on another, e.g.
+//! when moving from InfluxDB OSS to Cloud. The copy goes batch by batch from
+//! the oldest points, retrying the batches that fail on transient errors,
+//! and reports how far it got after each batch. That time is the checkpoint
+//! to resume from if the migration is interrupted. Requires the `migration`
+//! feature and a tokio runtime.
+//!
+//! ```no_run
+//! use chrono::NaiveDate;
+//! use futures::TryStreamExt;
+//! use influxdb2::api::migrate::{migrate, MigrationSpec};
+//! use influxdb2::Client;
+//!
+//! # async fn example() -> Result<(), influxdb2::RequestError> {
+//! let oss = Client::new("http://localhost:8086", "org", "oss-token");
+//! let cloud = Client::new("https://cloud2.influxdata.com", "org", "cloud-token");
+//! let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let stop = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//!
+//! let spec = MigrationSpec::new("telegraf", "telegraf", start, stop).batch(10_000);
+//! let progress = migrate(&oss, &cloud, &spec);
+//! futures::pin_mut!(progress);
+//! while let Some(progress) = progress.try_next().await? {
+//!     // Save the checkpoint somewhere to resume with `resume_from`
+//!     println!("copied up to {}", progress.copied_until);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use futures::stream::{self, Stream};
+use reqwest::StatusCode;
+
+use crate::api::copy::{BucketCopy, CopyProgress};
+use crate::{Client, RequestError};
+
+/// What to migrate and how
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationSpec {
+    /// The bucket read from on the source server
+    pub source_bucket: String,
+    /// The bucket written to on the destination server
+    pub dest_bucket: String,
+    /// The start of the range to copy, in UTC
+    pub start: NaiveDateTime,
+    /// The end of the range to copy, in UTC
+    pub stop: NaiveDateTime,
+    /// The number of points read and written at a time
+    pub batch: usize,
+    /// How many times a failed batch is retried before its error is yielded
+    pub retries: u32,
+    /// How long to wait before the first retry, doubling for each retry
+    pub retry_delay: Duration,
+}
+
+impl MigrationSpec {
+    /// Migrate the points of `source_bucket` from `start` to `stop` into
+    /// `dest_bucket`, 5000 at a time, retrying a failed batch three times
+    /// after waiting 1, 2 and 4 seconds.
+    pub fn new(
+        source_bucket: impl Into<String>,
+        dest_bucket: impl Into<String>,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+    ) -> Self {
+        Self {
+            source_bucket: source_bucket.into(),
+            dest_bucket: dest_bucket.into(),
+            start,
+            stop,
+            batch: 5000,
+            retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Read and write `batch` points at a time.
+    pub fn batch(mut self, batch: usize) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Retry a failed batch `retries` times, waiting `delay` before the
+    /// first retry and twice as long before each following one.
+    pub fn retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Resume an interrupted migration from `checkpoint`, the `copied_until`
+    /// of the last progress it reported.
+    pub fn resume_from(mut self, checkpoint: NaiveDateTime) -> Self {
+        self.start = self.start.max(checkpoint);
+        self
+    }
+}
+
+/// Copy the points of `spec` from the server of `source` to the server of
+/// `destination`.
+///
+/// Nothing is copied until the stream is polled. It yields the progress
+/// after each batch is written and ends once the range is copied. A batch
+/// failing because a server can't be reached, times out, is overloaded or
+/// answers with a 5xx status is retried as `spec` says; other errors, and
+/// the last error of a batch out of retries, are yielded, and polling the
+/// stream again retries the batch.
+///
+/// # Panics
+///
+/// Panics if polled outside of a tokio runtime and a batch needs a retry.
+pub fn migrate(
+    source: &Client,
+    destination: &Client,
+    spec: &MigrationSpec,
+) -> impl Stream<Item = Result<CopyProgress, RequestError>> {
+    let copy = BucketCopy::new(
+        source,
+        &spec.source_bucket,
+        destination,
+        &spec.dest_bucket,
+        spec.start,
+        spec.stop,
+        spec.batch,
+    );
+    let (retries, retry_delay) = (spec.retries, spec.retry_delay);
+    let clock = Arc::clone(&source.clock);
+
+    stream::unfold(copy, move |mut copy| {
+        let clock = Arc::clone(&clock);
+        async move {
+            if copy.is_done() {
+                return None;
+            }
+            let mut delay = retry_delay;
+            let mut result = copy.next_batch().await;
+            for _ in 0..retries {
+                match &result {
+                    Err(e) if is_transient(e) => {}
+                    _ => break,
+                }
+                clock.sleep(delay).await;
+                delay = delay.saturating_mul(2);
+                result = copy.next_batch().await;
+            }
+            Some((result, copy))
+        }
+    })
+}
+
+/// Whether `error` may not happen again on a retry
+fn is_transient(error: &RequestError) -> bool {
+    match error {
+        RequestError::ReqwestProcessing { source, .. } => {
+            source.is_connect() || source.is_timeout()
+        }
+        RequestError::Http { status, .. } => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use futures::TryStreamExt;
+    use mockito::{mock, Matcher};
+
+    const CSV: &str = "#datatype,string,long,dateTime:RFC3339,string,string,double
+#group,false,false,false,false,false,false
+#default,_result,,,,,
+,result,table,_time,_measurement,_field,_value
+,,0,2040-01-01T00:00:01Z,cpu,usage,1
+";
+
+    #[tokio::test]
+    async fn retries_transient_errors_and_resumes_from_checkpoints() {
+        let day = NaiveDate::from_ymd_opt(2040, 1, 1).unwrap();
+        let spec = MigrationSpec::new(
+            "src",
+            "migrated",
+            day.and_hms_opt(0, 0, 0).unwrap(),
+            day.and_hms_opt(1, 0, 0).unwrap(),
+        )
+        .retries(1, Duration::from_millis(1))
+        .resume_from(day.and_hms_opt(0, 0, 1).unwrap());
+
+        let query = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(
+                r"range\(start: 2040-01-01T00:00:01Z, stop: 2040-01-01T01:00:00Z\)".to_string(),
+            ))
+            .with_body(CSV)
+            .expect(2)
+            .create();
+        let unavailable = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "migrated".into()))
+            .with_status(503)
+            .expect(2)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let progress = migrate(&client, &client, &spec);
+        futures::pin_mut!(progress);
+
+        // The only retry fails too, so the error is yielded
+        assert!(progress.try_next().await.is_err());
+        query.assert();
+        unavailable.assert();
+        drop(unavailable);
+
+        let _write = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "migrated".into()))
+            .match_body("cpu usage=1 2208988801000000000\n")
+            .with_status(204)
+            .create();
+        let progress = progress.try_next().await.unwrap().unwrap();
+        assert_eq!(progress.points, 1);
+        assert_eq!(progress.copied_until, day.and_hms_opt(1, 0, 0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let day = NaiveDate::from_ymd_opt(2041, 1, 1).unwrap();
+        let spec = MigrationSpec::new(
+            "missing",
+            "dst",
+            day.and_hms_opt(0, 0, 0).unwrap(),
+            day.and_hms_opt(1, 0, 0).unwrap(),
+        )
+        .retries(5, Duration::from_millis(1));
+        let query = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r#"\\"missing\\""#.to_string()))
+            .with_status(404)
+            .with_body(r#"{"code":"not found","message":"bucket not found"}"#)
+            .expect(1)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let progress = migrate(&client, &client, &spec);
+        futures::pin_mut!(progress);
+
+        assert!(progress.try_next().await.is_err());
+        query.assert();
+    }
+}
@@ -1,11 +1,60 @@
 //! InfluxDB v2.0 Client API
+pub mod annotated_csv;
+pub mod annotations;
+#[cfg(all(feature = "arrow", not(target_arch = "wasm32")))]
+pub mod arrow;
+#[cfg(feature = "management")]
+pub mod authorization;
+#[cfg(feature = "management")]
 pub mod buckets;
+#[cfg(feature = "management")]
+pub mod bulk;
+pub mod capabilities;
+pub mod client;
+pub mod copy;
+#[cfg(feature = "management")]
+pub mod dbrp;
+pub mod dedup;
+pub mod export;
 pub mod delete;
+#[cfg(feature = "management")]
+pub mod downsample;
 pub mod health;
+pub mod join;
+pub mod latest;
+#[cfg(feature = "management")]
 pub mod label;
+#[cfg(all(feature = "migration", not(target_arch = "wasm32")))]
+pub mod migrate;
+pub mod monitoring;
+#[cfg(feature = "management")]
+pub mod operation_logs;
+#[cfg(feature = "management")]
 pub mod organization;
+#[cfg(feature = "management")]
+pub mod pagination;
+#[cfg(feature = "management")]
+pub mod provision;
 pub mod query;
 pub mod ready;
+pub mod schema;
+#[cfg(all(feature = "spool", not(target_arch = "wasm32")))]
+pub mod spool;
+#[cfg(all(feature = "live-tail", not(target_arch = "wasm32")))]
+pub mod tail;
+#[cfg(feature = "management")]
 pub mod setup;
+#[cfg(feature = "management")]
 pub mod task;
+#[cfg(feature = "management")]
+pub mod telegraf;
+#[cfg(feature = "management")]
+pub mod templates;
+#[cfg(feature = "management")]
+pub mod usage;
+#[cfg(feature = "management")]
+pub mod users;
+#[cfg(feature = "management")]
+pub mod variable;
+pub mod window;
 pub mod write;
@@ -0,0 +1,333 @@
+//! Check statuses
+//!
+//! Checks write their statuses, and notification rules the notifications
+//! they send, to the `_monitoring` bucket. These calls read them back as
+//! typed records, e.g. to show the current alert state in an application
+//! without writing the Flux of `monitor.from()` and `monitor.notifications()`:
+//!
+//! ```no_run
+//! use influxdb2::api::monitoring::CheckLevel;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! for status in client.current_check_statuses().await? {
+//!     if status.level >= CheckLevel::Warn {
+//!         println!("{} is {}: {}", status.check_name, status.level, status.message);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+
+use super::latest::LAST_VALUE_LOOKBACK;
+use super::window::TimeRange;
+use crate::models::template::flux_string;
+use crate::models::Query;
+use crate::{Client, RequestError};
+
+/// The bucket checks and notification rules write to
+pub const MONITORING_BUCKET: &str = "_monitoring";
+
+/// The level of a check status, from the least to the most severe
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CheckLevel {
+    /// The check has no data
+    Unknown,
+    /// Everything is fine
+    Ok,
+    /// Worth knowing
+    Info,
+    /// A warning
+    Warn,
+    /// Critical
+    Crit,
+}
+
+impl CheckLevel {
+    /// The level as written to `_monitoring`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Ok => "ok",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Crit => "crit",
+        }
+    }
+
+    fn parse(level: &str) -> Self {
+        match level {
+            "ok" => Self::Ok,
+            "info" => Self::Info,
+            "warn" => Self::Warn,
+            "crit" => Self::Crit,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for CheckLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A status written by a check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckStatus {
+    /// When the check ran
+    pub time: DateTime<FixedOffset>,
+    /// The ID of the check
+    pub check_id: String,
+    /// The name of the check
+    pub check_name: String,
+    /// The type of the check, such as `threshold` or `deadman`
+    pub check_type: String,
+    /// The level the check found
+    pub level: CheckLevel,
+    /// The message of the check
+    pub message: String,
+    /// The measurement the check queried
+    pub source_measurement: Option<String>,
+    /// The tags of the series the status is about
+    pub tags: BTreeMap<String, String>,
+}
+
+/// A notification sent, or attempted, by a notification rule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    /// When the rule ran
+    pub time: DateTime<FixedOffset>,
+    /// The ID of the check whose status was notified
+    pub check_id: String,
+    /// The name of the check whose status was notified
+    pub check_name: String,
+    /// The level of the status
+    pub level: CheckLevel,
+    /// The message of the status
+    pub message: String,
+    /// The ID of the notification rule
+    pub rule_id: String,
+    /// The name of the notification rule
+    pub rule_name: String,
+    /// The ID of the endpoint notified
+    pub endpoint_id: String,
+    /// The name of the endpoint notified
+    pub endpoint_name: String,
+    /// Whether the endpoint accepted the notification
+    pub sent: bool,
+    /// The tags of the series the status is about
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Client {
+    /// The statuses written by checks across `range`, in time order.
+    pub async fn check_statuses(
+        &self,
+        range: impl Into<TimeRange>,
+    ) -> Result<Vec<CheckStatus>, RequestError> {
+        let flux = format!(
+            "{}\n  |> sort(columns: [\"_time\"])",
+            monitoring_flux("statuses", &range.into())
+        );
+        self.monitoring_records(flux, check_status).await
+    }
+
+    /// The latest status of each check, within the last
+    /// [`LAST_VALUE_LOOKBACK`], ordered by check name.
+    pub async fn current_check_statuses(&self) -> Result<Vec<CheckStatus>, RequestError> {
+        let flux = format!(
+            "{}\n  \
+             |> group(columns: [\"_check_id\"])\n  \
+             |> sort(columns: [\"_time\"])\n  \
+             |> last()",
+            monitoring_flux("statuses", &LAST_VALUE_LOOKBACK.into())
+        );
+        let mut statuses = self.monitoring_records(flux, check_status).await?;
+        statuses.sort_by(|a, b| a.check_name.cmp(&b.check_name));
+        Ok(statuses)
+    }
+
+    /// The notifications sent by notification rules across `range`, in time
+    /// order.
+    pub async fn notifications(
+        &self,
+        range: impl Into<TimeRange>,
+    ) -> Result<Vec<Notification>, RequestError> {
+        let flux = format!(
+            "{}\n  |> sort(columns: [\"_time\"])",
+            monitoring_flux("notifications", &range.into())
+        );
+        self.monitoring_records(flux, notification).await
+    }
+
+    async fn monitoring_records<T>(
+        &self,
+        flux: String,
+        parse: fn(GenericMap) -> Option<T>,
+    ) -> Result<Vec<T>, RequestError> {
+        let rows = self.query_records(Query::new(flux)).await?;
+        Ok(rows.into_iter().filter_map(parse).collect())
+    }
+}
+
+/// The Flux reading the messages of `measurement` of the monitoring bucket,
+/// one row per status or notification, across `range`, merged into one table
+fn monitoring_flux(measurement: &str, range: &TimeRange) -> String {
+    format!(
+        "from(bucket: {})\n  \
+         |> range({})\n  \
+         |> filter(fn: (r) => r._measurement == {} and r._field == \"_message\")\n  \
+         |> group()",
+        flux_string(MONITORING_BUCKET),
+        range.flux_arguments(),
+        flux_string(measurement),
+    )
+}
+
+fn check_status(mut row: GenericMap) -> Option<CheckStatus> {
+    Some(CheckStatus {
+        time: time(&mut row)?,
+        check_id: string(&mut row, "_check_id")?,
+        check_name: string(&mut row, "_check_name").unwrap_or_default(),
+        check_type: string(&mut row, "_type").unwrap_or_default(),
+        level: CheckLevel::parse(&string(&mut row, "_level")?),
+        message: string(&mut row, "_value").unwrap_or_default(),
+        source_measurement: string(&mut row, "_source_measurement"),
+        tags: tags(row),
+    })
+}
+
+fn notification(mut row: GenericMap) -> Option<Notification> {
+    Some(Notification {
+        time: time(&mut row)?,
+        check_id: string(&mut row, "_check_id")?,
+        check_name: string(&mut row, "_check_name").unwrap_or_default(),
+        level: CheckLevel::parse(&string(&mut row, "_level")?),
+        message: string(&mut row, "_value").unwrap_or_default(),
+        rule_id: string(&mut row, "_notification_rule_id").unwrap_or_default(),
+        rule_name: string(&mut row, "_notification_rule_name").unwrap_or_default(),
+        endpoint_id: string(&mut row, "_notification_endpoint_id").unwrap_or_default(),
+        endpoint_name: string(&mut row, "_notification_endpoint_name").unwrap_or_default(),
+        sent: string(&mut row, "_sent").as_deref() == Some("true"),
+        tags: tags(row),
+    })
+}
+
+fn time(row: &mut GenericMap) -> Option<DateTime<FixedOffset>> {
+    match row.remove("_time") {
+        Some(Value::TimeRFC(time)) => Some(time),
+        _ => None,
+    }
+}
+
+fn string(row: &mut GenericMap, column: &str) -> Option<String> {
+    match row.remove(column) {
+        Some(Value::String(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// The tags of the series a row is about: its string columns, but for those
+/// of the monitoring system, which start with `_`
+fn tags(row: GenericMap) -> BTreeMap<String, String> {
+    row.into_iter()
+        .filter(|(column, _)| !column.starts_with('_') && column != "result" && column != "table")
+        .filter_map(|(column, value)| match value {
+            Value::String(value) => Some((column, value)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FluxDuration;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn builds_monitoring_queries() {
+        assert_eq!(
+            monitoring_flux("statuses", &FluxDuration::from_secs(3600).into()),
+            "from(bucket: \"_monitoring\")\n  \
+             |> range(start: -1h)\n  \
+             |> filter(fn: (r) => r._measurement == \"statuses\" and r._field == \"_message\")\n  \
+             |> group()"
+        );
+    }
+
+    #[tokio::test]
+    async fn current_check_statuses() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r"\|> last\(\)".to_string()))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,string,string,string,string,string,string,string,string,string
+#group,false,false,false,false,true,true,false,false,false,false,false,false
+#default,_result,,,,,,,,,,,
+,result,table,_time,_value,_field,_measurement,_check_id,_check_name,_level,_source_measurement,_type,host
+,,0,2040-01-01T00:05:00Z,disk is full,_message,statuses,0a,Disk,crit,disk,threshold,a
+,,1,2040-01-01T00:04:00Z,cpu is fine,_message,statuses,0b,CPU,ok,cpu,threshold,b
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let statuses = client.current_check_statuses().await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].check_name, "CPU");
+        assert_eq!(statuses[0].level, CheckLevel::Ok);
+        assert_eq!(
+            statuses[1],
+            CheckStatus {
+                time: DateTime::parse_from_rfc3339("2040-01-01T00:05:00Z").unwrap(),
+                check_id: "0a".to_string(),
+                check_name: "Disk".to_string(),
+                check_type: "threshold".to_string(),
+                level: CheckLevel::Crit,
+                message: "disk is full".to_string(),
+                source_measurement: Some("disk".to_string()),
+                tags: vec![("host".to_string(), "a".to_string())].into_iter().collect(),
+            }
+        );
+        assert!(statuses[1].level > CheckLevel::Warn);
+    }
+
+    #[tokio::test]
+    async fn notifications() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r#"\\"notifications\\""#.to_string()))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,string,string,string,string,string,string,string,string,string,string,string
+#group,false,false,false,false,true,true,false,false,false,false,false,false,false,false
+#default,_result,,,,,,,,,,,,,
+,result,table,_time,_value,_field,_measurement,_check_id,_check_name,_level,_notification_rule_id,_notification_rule_name,_notification_endpoint_id,_notification_endpoint_name,_sent
+,,0,2040-01-01T00:05:00Z,disk is full,_message,notifications,0a,Disk,crit,0c,Pager,0d,Slack,true
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let notifications = client
+            .notifications(FluxDuration::from_secs(86_400))
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].rule_name, "Pager");
+        assert_eq!(notifications[0].endpoint_name, "Slack");
+        assert!(notifications[0].sent);
+        assert!(notifications[0].tags.is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+//! Operation logs
+//!
+//! Some InfluxDB 2 servers log who changed a bucket, dashboard, organization
+//! or user, when, and how, at `/api/v2/{resource}/{id}/logs`.
+//! `Client::operation_logs` reads a page of those logs and
+//! `Client::stream_operation_logs` all of them, so admin tools can audit a
+//! resource. Servers without the endpoint answer `404 Not Found`.
+//!
+//! ```no_run
+//! use futures::TryStreamExt;
+//! use influxdb2::api::operation_logs::ListOperationLogsRequest;
+//! use influxdb2::models::{BucketId, OperationLog};
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let logs: Vec<OperationLog> = client
+//!     .stream_operation_logs(BucketId::new("0000000000000001"), ListOperationLogsRequest::default())
+//!     .try_collect()
+//!     .await?;
+//! for log in logs {
+//!     println!("{} {:?}: {}", log.time, log.user_id, log.description);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{BucketId, DashboardId, OperationLogs, OrgId, UserId};
+use crate::{Client, RequestError};
+
+/// A resource with an operation log
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LoggedResource {
+    /// A bucket
+    Bucket(BucketId),
+    /// A dashboard
+    Dashboard(DashboardId),
+    /// An organization
+    Org(OrgId),
+    /// A user
+    User(UserId),
+}
+
+impl LoggedResource {
+    /// The path of the operation log of the resource.
+    pub fn path(&self) -> String {
+        match self {
+            Self::Bucket(id) => format!("/api/v2/buckets/{}/logs", id),
+            Self::Dashboard(id) => format!("/api/v2/dashboards/{}/logs", id),
+            Self::Org(id) => format!("/api/v2/orgs/{}/logs", id),
+            Self::User(id) => format!("/api/v2/users/{}/logs", id),
+        }
+    }
+}
+
+impl From<BucketId> for LoggedResource {
+    fn from(id: BucketId) -> Self {
+        Self::Bucket(id)
+    }
+}
+
+impl From<DashboardId> for LoggedResource {
+    fn from(id: DashboardId) -> Self {
+        Self::Dashboard(id)
+    }
+}
+
+impl From<OrgId> for LoggedResource {
+    fn from(id: OrgId) -> Self {
+        Self::Org(id)
+    }
+}
+
+impl From<UserId> for LoggedResource {
+    fn from(id: UserId) -> Self {
+        Self::User(id)
+    }
+}
+
+/// Request for the operation log API
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListOperationLogsRequest {
+    /// Offset from which to return logs.
+    pub offset: Option<u64>,
+    /// Number of logs to return. Default: 20. Valid values: [1..100]
+    pub limit: Option<u8>,
+    /// Whether to return the latest logs first.
+    pub descending: Option<bool>,
+}
+
+impl Client {
+    /// A page of the operation log of `resource`.
+    pub async fn operation_logs(
+        &self,
+        resource: impl Into<LoggedResource>,
+        request: ListOperationLogsRequest,
+    ) -> Result<OperationLogs, RequestError> {
+        let path = resource.into().path();
+        let qs = serde_qs::to_string(&request).unwrap();
+        let url = match &qs[..] {
+            "" => self.endpoint_url(&path),
+            _ => self.endpoint_url(&format!("{}?{}", path, qs)),
+        };
+
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<OperationLogs>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn reads_typed_operation_logs() {
+        let mock_server = mock("GET", "/api/v2/dashboards/0000000000000abc/logs?limit=2&descending=true")
+            .with_body(
+                r#"{"links":{"self":"/api/v2/dashboards/0000000000000abc/logs"},
+                    "logs":[{"description":"Dashboard Updated","time":"2040-01-02T00:00:00Z",
+                             "userID":"0000000000000001","links":{"user":"/api/v2/users/0000000000000001"}},
+                            {"description":"Dashboard Created","time":"2040-01-01T00:00:00Z"}]}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let request = ListOperationLogsRequest {
+            limit: Some(2),
+            descending: Some(true),
+            ..ListOperationLogsRequest::default()
+        };
+        let logs = client
+            .operation_logs(DashboardId::new("0000000000000abc"), request)
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(logs.logs.len(), 2);
+        assert_eq!(logs.logs[0].description, "Dashboard Updated");
+        assert_eq!(logs.logs[0].user_id, Some(UserId::new("0000000000000001")));
+        assert_eq!(logs.logs[0].time.to_rfc3339(), "2040-01-02T00:00:00+00:00");
+        assert_eq!(logs.logs[1].user_id, None);
+    }
+
+    #[tokio::test]
+    async fn streams_every_page() {
+        let first = mock("GET", "/api/v2/buckets/0000000000000abc/logs?limit=1")
+            .with_body(
+                r#"{"links":{"self":"/api/v2/buckets/0000000000000abc/logs?limit=1",
+                             "next":"/api/v2/buckets/0000000000000abc/logs?offset=1&limit=1"},
+                    "logs":[{"description":"Bucket Created","time":"2040-01-01T00:00:00Z"}]}"#,
+            )
+            .create();
+        let second = mock(
+            "GET",
+            "/api/v2/buckets/0000000000000abc/logs?offset=1&limit=1",
+        )
+        .with_body(
+            r#"{"links":{"self":"/api/v2/buckets/0000000000000abc/logs?offset=1&limit=1"},
+                    "logs":[{"description":"Bucket Updated","time":"2040-01-02T00:00:00Z"}]}"#,
+        )
+        .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let request = ListOperationLogsRequest {
+            limit: Some(1),
+            ..ListOperationLogsRequest::default()
+        };
+        let logs: Vec<_> = client
+            .stream_operation_logs(BucketId::new("0000000000000abc"), request)
+            .try_collect()
+            .await
+            .unwrap();
+
+        first.assert();
+        second.assert();
+        let descriptions: Vec<_> = logs.iter().map(|log| log.description.as_str()).collect();
+        assert_eq!(descriptions, ["Bucket Created", "Bucket Updated"]);
+    }
+}
@@ -1,11 +1,20 @@
 //! Organization API
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt};
+
+use crate::clock::Clock;
+use crate::{Client, OrgNotFoundSnafu, RequestError, SerializingSnafu};
+use crate::models::{OrgId, Organization, Organizations, UserId};
 
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu};
-use crate::models::Organizations;
+/// How long a resolved organization ID is reused by default
+pub const DEFAULT_ORG_ID_TTL: std::time::Duration = std::time::Duration::from_secs(300);
 
 impl Client {
     /// List all organizations.
@@ -15,29 +24,135 @@ impl Client {
     ) -> Result<Organizations, RequestError> {
         let qs = serde_qs::to_string(&request).unwrap();
         let url = match &qs[..] {
-            "" => format!("{}/api/v2/orgs", self.url),
-            _  => format!("{}/api/v2/orgs?{}", self.url, qs),
+            "" => self.endpoint_url("/api/v2/orgs"),
+            _  => self.endpoint_url(&format!("/api/v2/orgs?{}", qs)),
         };
         
-        let response = self
-            .request(Method::GET, &url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let response = self.send(self.request(Method::GET, &url)).await?;
         
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            let res = HttpSnafu { status, text }.fail();
-            return res;
+            return Err(response.error().await);
         }
         
-        let res = response
-            .json::<Organizations>()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let res = response.json::<Organizations>().await?;
         Ok(res)
     }
+
+    /// Create an organization named `name`, returning it.
+    pub async fn create_organization(
+        &self,
+        name: &str,
+        description: Option<String>,
+    ) -> Result<Organization, RequestError> {
+        let url = self.endpoint_url("/api/v2/orgs");
+        let request = Organization {
+            description,
+            ..Organization::new(name.to_string())
+        };
+        let response = self
+            .send(
+                self.request(Method::POST, &url)
+                    .body(serde_json::to_string(&request).context(SerializingSnafu)?),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        let org = response.json::<Organization>().await?;
+        if let Some(id) = &org.id {
+            self.org_ids.insert(name, id.clone());
+        }
+        Ok(org)
+    }
+
+    /// The ID of the organization named `name`.
+    ///
+    /// The ID is looked up once and reused for the time set with
+    /// `ClientBuilder::with_org_id_ttl`, by this client and its clones.
+    pub async fn org_id(&self, name: &str) -> Result<OrgId, RequestError> {
+        if let Some(id) = self.org_ids.get(name) {
+            return Ok(id);
+        }
+
+        let request = ListOrganizationRequest {
+            org: Some(name.to_string()),
+            ..ListOrganizationRequest::default()
+        };
+        let id = self
+            .list_organizations(request)
+            .await?
+            .orgs
+            .into_iter()
+            .find(|org| org.name == name)
+            .and_then(|org| org.id)
+            .context(OrgNotFoundSnafu { name })?;
+        self.org_ids.insert(name, id.clone());
+        Ok(id)
+    }
+
+    /// Forget the cached ID of the organization named `name`, e.g. after
+    /// it was deleted and recreated.
+    pub fn invalidate_org_id(&self, name: &str) {
+        self.org_ids.remove(name);
+    }
+
+    /// Forget every cached organization ID.
+    pub fn clear_org_ids(&self) {
+        self.org_ids.clear();
+    }
+}
+
+/// Values resolved by name, such as organization IDs, with the time they
+/// expire
+#[derive(Debug)]
+pub(crate) struct NameCache<V> {
+    ttl: chrono::Duration,
+    clock: Arc<dyn Clock>,
+    entries: Mutex<HashMap<String, (V, DateTime<Utc>)>>,
+}
+
+/// Organization IDs resolved by name
+pub(crate) type OrgIdCache = NameCache<OrgId>;
+
+impl<V: Clone> NameCache<V> {
+    pub(crate) fn new(ttl: std::time::Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            ttl: chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::max_value()),
+            clock,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<V> {
+        let mut entries = self.entries.lock();
+        match entries.get(name) {
+            Some((value, expires)) if *expires > self.clock.now() => Some(value.clone()),
+            Some(_) => {
+                entries.remove(name);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, name: &str, value: V) {
+        if self.ttl <= chrono::Duration::zero() {
+            return;
+        }
+        if let Some(expires) = self.clock.now().checked_add_signed(self.ttl) {
+            self.entries.lock().insert(name.to_string(), (value, expires));
+        }
+    }
+
+    pub(crate) fn remove(&self, name: &str) {
+        self.entries.lock().remove(name);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries.lock().clear();
+    }
 }
 
 /// Request for list organization API
@@ -54,10 +169,10 @@ pub struct ListOrganizationRequest {
     pub org: Option<String>,
     /// Filter by organization ID.
     #[serde(rename = "orgID")]
-    pub org_id: Option<String>,
+    pub org_id: Option<OrgId>,
     /// Filter by specific user ID.
     #[serde(rename = "userID")]
-    pub user_id: Option<String>,
+    pub user_id: Option<UserId>,
 }
 
 impl ListOrganizationRequest {
@@ -70,7 +185,9 @@ impl ListOrganizationRequest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::clock::MockClock;
+    use mockito::mock;
+
     #[test]
     fn serialize_list_organization_request() {
         let req = ListOrganizationRequest::new();
@@ -82,4 +199,56 @@ mod tests {
         let qs = serde_qs::to_string(&req).unwrap();
         assert_eq!(qs, "org=Sahamee");
     }
+
+    #[tokio::test]
+    async fn caches_org_ids() {
+        let lookup = mock("GET", "/api/v2/orgs?org=cached")
+            .with_body(r#"{"orgs":[{"id":"0000111100001111","name":"cached"}]}"#)
+            .expect(2)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let id = OrgId::from("0000111100001111");
+        assert_eq!(client.org_id("cached").await.unwrap(), id);
+        assert_eq!(client.clone().org_id("cached").await.unwrap(), id);
+        client.invalidate_org_id("cached");
+        assert_eq!(client.org_id("cached").await.unwrap(), id);
+
+        lookup.assert();
+    }
+
+    #[tokio::test]
+    async fn expires_org_ids_by_the_client_clock() {
+        let lookup = mock("GET", "/api/v2/orgs?org=expiring")
+            .with_body(r#"{"orgs":[{"id":"0000111100002222","name":"expiring"}]}"#)
+            .expect(2)
+            .create();
+
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_clock(Arc::clone(&clock))
+            .with_org_id_ttl(std::time::Duration::from_secs(60))
+            .build()
+            .unwrap();
+        client.org_id("expiring").await.unwrap();
+        clock.advance(std::time::Duration::from_secs(59));
+        client.org_id("expiring").await.unwrap();
+        clock.advance(std::time::Duration::from_secs(1));
+        client.org_id("expiring").await.unwrap();
+
+        lookup.assert();
+    }
+
+    #[tokio::test]
+    async fn reports_unknown_orgs() {
+        let lookup = mock("GET", "/api/v2/orgs?org=missing")
+            .with_body(r#"{"orgs":[]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let err = client.org_id("missing").await.unwrap_err();
+
+        lookup.assert();
+        assert!(matches!(err, RequestError::OrgNotFound { name } if name == "missing"));
+    }
 }
@@ -0,0 +1,296 @@
+//! Pagination
+//!
+//! The list endpoints answer one page at a time, linking the next one in
+//! `links.next`, whether they page through `offset` and `limit` like
+//! buckets and organizations or through `after` like tasks. `PageStream`
+//! follows those links and yields the items of every page:
+//!
+//! ```no_run
+//! use futures::TryStreamExt;
+//! use influxdb2::api::buckets::ListBucketsRequest;
+//! use influxdb2::models::Bucket;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let buckets: Vec<Bucket> = client
+//!     .stream_buckets(ListBucketsRequest::default())
+//!     .try_collect()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::stream::{self, Stream};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::buckets::ListBucketsRequest;
+use crate::api::operation_logs::{ListOperationLogsRequest, LoggedResource};
+use crate::api::organization::ListOrganizationRequest;
+use crate::api::task::ListTasksRequest;
+use crate::models::{
+    Bucket, Buckets, Label, LabelsResponse, Links, OperationLog, OperationLogs, Organization,
+    Organizations, Task, Tasks,
+};
+use crate::{Client, RequestError};
+
+/// A page of a list endpoint
+pub trait Paginated: DeserializeOwned {
+    /// The listed resource
+    type Item;
+
+    /// The links of the page, whose `next` is the path of the next page.
+    fn links(&self) -> Option<&Links>;
+
+    /// The items of the page.
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+impl Paginated for Buckets {
+    type Item = Bucket;
+
+    fn links(&self) -> Option<&Links> {
+        self.links.as_ref()
+    }
+
+    fn into_items(self) -> Vec<Bucket> {
+        self.buckets
+    }
+}
+
+impl Paginated for Organizations {
+    type Item = Organization;
+
+    fn links(&self) -> Option<&Links> {
+        self.links.as_ref()
+    }
+
+    fn into_items(self) -> Vec<Organization> {
+        self.orgs
+    }
+}
+
+impl Paginated for Tasks {
+    type Item = Task;
+
+    fn links(&self) -> Option<&Links> {
+        self.links.as_ref()
+    }
+
+    fn into_items(self) -> Vec<Task> {
+        self.tasks
+    }
+}
+
+impl Paginated for LabelsResponse {
+    type Item = Label;
+
+    fn links(&self) -> Option<&Links> {
+        self.links.as_ref()
+    }
+
+    fn into_items(self) -> Vec<Label> {
+        self.labels
+    }
+}
+
+impl Paginated for OperationLogs {
+    type Item = OperationLog;
+
+    fn links(&self) -> Option<&Links> {
+        self.links.as_ref()
+    }
+
+    fn into_items(self) -> Vec<OperationLog> {
+        self.logs
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+
+/// The items of every page of a list endpoint, fetched as the stream is
+/// polled
+///
+/// The stream ends after the last page, or after yielding the error of a
+/// page that couldn't be fetched.
+pub struct PageStream<'a, T> {
+    inner: BoxStream<'a, Result<T, RequestError>>,
+}
+
+impl<T> std::fmt::Debug for PageStream<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PageStream").finish_non_exhaustive()
+    }
+}
+
+impl<T> Stream for PageStream<'_, T> {
+    type Item = Result<T, RequestError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// What is left to yield: the URL of the next page and the items of the
+/// current one
+struct Cursor<T> {
+    next: Option<String>,
+    items: std::vec::IntoIter<T>,
+}
+
+impl Client {
+    /// Stream every bucket matching `request`, following the pages.
+    pub fn stream_buckets(&self, request: ListBucketsRequest) -> PageStream<'_, Bucket> {
+        self.paginate::<Buckets>("/api/v2/buckets", &request)
+    }
+
+    /// Stream every organization matching `request`, following the pages.
+    pub fn stream_organizations(
+        &self,
+        request: ListOrganizationRequest,
+    ) -> PageStream<'_, Organization> {
+        self.paginate::<Organizations>("/api/v2/orgs", &request)
+    }
+
+    /// Stream every task matching `request`, following the pages.
+    pub fn stream_tasks(&self, request: ListTasksRequest) -> PageStream<'_, Task> {
+        self.paginate::<Tasks>("/api/v2/tasks", &request)
+    }
+
+    /// Stream every label, following the pages.
+    pub fn stream_labels(&self) -> PageStream<'_, Label> {
+        self.paginate::<LabelsResponse>("/api/v2/labels", &())
+    }
+
+    /// Stream every change in the operation log of `resource` matching
+    /// `request`, following the pages.
+    pub fn stream_operation_logs(
+        &self,
+        resource: impl Into<LoggedResource>,
+        request: ListOperationLogsRequest,
+    ) -> PageStream<'_, OperationLog> {
+        self.paginate::<OperationLogs>(&resource.into().path(), &request)
+    }
+
+    fn paginate<P>(&self, path: &str, query: &impl Serialize) -> PageStream<'_, P::Item>
+    where
+        P: Paginated + Send + 'static,
+        P::Item: Send,
+    {
+        let qs = serde_qs::to_string(query).unwrap_or_default();
+        let first = match &qs[..] {
+            "" => self.endpoint_url(path),
+            _ => self.endpoint_url(&format!("{}?{}", path, qs)),
+        };
+        let cursor = Cursor {
+            next: Some(first),
+            items: Vec::new().into_iter(),
+        };
+
+        let inner = stream::unfold(cursor, move |mut cursor| async move {
+            loop {
+                if let Some(item) = cursor.items.next() {
+                    return Some((Ok(item), cursor));
+                }
+
+                let url = cursor.next.take()?;
+                let page = match self.page::<P>(&url).await {
+                    Ok(page) => page,
+                    Err(e) => return Some((Err(e), cursor)),
+                };
+                // Stop at an empty page or a link back to the same page, in
+                // case the server keeps linking pages past the end
+                cursor.next = page
+                    .links()
+                    .and_then(|links| links.next.as_deref())
+                    .map(|next| self.endpoint_url(next))
+                    .filter(|next| *next != url);
+                cursor.items = page.into_items().into_iter();
+                if cursor.items.len() == 0 {
+                    return None;
+                }
+            }
+        });
+
+        PageStream {
+            inner: Box::pin(inner),
+        }
+    }
+
+    async fn page<P: DeserializeOwned>(&self, url: &str) -> Result<P, RequestError> {
+        let response = self.send(self.request(Method::GET, url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<P>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{StreamExt, TryStreamExt};
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn follows_next_links() {
+        let first = mock("GET", "/api/v2/buckets?limit=1")
+            .with_body(
+                r#"{"links":{"self":"/api/v2/buckets?limit=1","next":"/api/v2/buckets?limit=1&offset=1"},
+                    "buckets":[{"name":"a","retentionRules":[]}]}"#,
+            )
+            .create();
+        let second = mock("GET", "/api/v2/buckets?limit=1&offset=1")
+            .with_body(
+                r#"{"links":{"self":"/api/v2/buckets?limit=1&offset=1"},
+                    "buckets":[{"name":"b","retentionRules":[]}]}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let request = ListBucketsRequest {
+            limit: Some(1),
+            ..ListBucketsRequest::default()
+        };
+        let buckets: Vec<Bucket> = client.stream_buckets(request).try_collect().await.unwrap();
+
+        first.assert();
+        second.assert();
+        let names: Vec<_> = buckets.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn ends_after_an_error() {
+        let first = mock("GET", "/api/v2/tasks?after=0000000000000001")
+            .with_body(
+                r#"{"links":{"self":"/api/v2/tasks","next":"/api/v2/tasks?after=0000000000000002"},
+                    "tasks":[{"id":"0000000000000002","name":"t","orgID":"o","flux":""}]}"#,
+            )
+            .create();
+        let second = mock("GET", "/api/v2/tasks?after=0000000000000002")
+            .with_status(500)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let request = ListTasksRequest {
+            after: Some("0000000000000001".to_string()),
+            ..ListTasksRequest::default()
+        };
+        let results: Vec<_> = client.stream_tasks(request).collect::<Vec<_>>().await;
+
+        first.assert();
+        second.assert();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().name, "t");
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,1159 @@
+//! Declarative provisioning
+//!
+//! A [`ProvisionSpec`] declares the organizations a server should have, with
+//! their buckets, labels, tasks and tokens. `Client::provision_plan` compares
+//! it with the server and returns the changes converging the server to it,
+//! without changing anything, and `Client::apply_provision` makes them:
+//!
+//! ```no_run
+//! use influxdb2::api::provision::{BucketSpec, OrgSpec, ProvisionSpec, TokenPermission, TokenSpec};
+//! use influxdb2::models::permission::Action;
+//! use influxdb2::models::resource::Type;
+//! use influxdb2::models::FluxDuration;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let spec = ProvisionSpec::new().org(
+//!     OrgSpec::new("acme")
+//!         .bucket(BucketSpec::new("telegraf", FluxDuration::from_secs(30 * 86_400)))
+//!         .token(
+//!             TokenSpec::new("telegraf writer")
+//!                 .permission(TokenPermission::new(Action::Write, Type::Buckets).named("telegraf")),
+//!         ),
+//! );
+//! let plan = client.provision_plan(&spec).await?;
+//! println!("{}", plan);
+//! let provisioned = client.apply_provision(plan).await?;
+//! println!("{:?}", provisioned.tokens.get("telegraf writer"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Specs deserialize with serde, with retention periods as Flux duration
+//! literals such as `"30d"`, so they can be kept in files next to the code.
+//! Organizations, buckets, labels and tasks are matched by name, tokens by
+//! description. Resources missing from the spec are kept, unless `prune` is
+//! set: the buckets, labels and tasks of the organizations of the spec that
+//! it doesn't list are then deleted. Buckets whose names start with an
+//! underscore, such as `_monitoring`, and tokens are never pruned. The
+//! permissions of a token can't be changed in place, so a token whose
+//! permissions differ is replaced by a new one, with a new value.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use futures::TryStreamExt;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::api::buckets::{retention_of, ListBucketsRequest};
+use crate::api::organization::ListOrganizationRequest;
+use crate::api::task::{CreateTaskRequest, ListTasksRequest, UpdateTaskRequest};
+use crate::models::permission::Action;
+use crate::models::resource::{self, Resource};
+use crate::models::retention_rule::{self, RetentionRule};
+use crate::models::schedule::validate_task_options;
+use crate::models::{
+    Authorization, BucketId, FluxDuration, Label, LabelId, LabelSpec, OrgId, PatchBucketRequest,
+    Permission, PostBucketRequest, TaskId, TaskStatusType,
+};
+use crate::{Client, InvalidScheduleSnafu, RequestError};
+
+/// The desired state of a server
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProvisionSpec {
+    /// The organizations, with their resources
+    #[serde(default)]
+    pub orgs: Vec<OrgSpec>,
+    /// Whether to delete the buckets, labels and tasks of these
+    /// organizations that the spec doesn't list
+    #[serde(default)]
+    pub prune: bool,
+}
+
+/// The desired state of an organization
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrgSpec {
+    /// The name of the organization
+    pub name: String,
+    /// The description given to the organization if it is created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The buckets of the organization
+    #[serde(default)]
+    pub buckets: Vec<BucketSpec>,
+    /// The labels of the organization
+    #[serde(default)]
+    pub labels: Vec<LabelSpec>,
+    /// The tasks of the organization
+    #[serde(default)]
+    pub tasks: Vec<TaskSpec>,
+    /// The tokens of the organization
+    #[serde(default)]
+    pub tokens: Vec<TokenSpec>,
+}
+
+/// The desired state of a bucket
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BucketSpec {
+    /// The name of the bucket
+    pub name: String,
+    /// How long the bucket keeps data, zero meaning forever
+    #[serde(default, with = "duration_literal")]
+    pub retention: FluxDuration,
+    /// The description of the bucket, left as it is if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The desired state of a task
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TaskSpec {
+    /// The name of the task
+    pub name: String,
+    /// The script of the task, whose `option task` block names it `name`
+    pub flux: String,
+    /// The description of the task, left as it is if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The status of the task, left as it is if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatusType>,
+}
+
+/// The desired state of a token
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenSpec {
+    /// The description identifying the token
+    pub description: String,
+    /// What the token may do
+    #[serde(default)]
+    pub permissions: Vec<TokenPermission>,
+}
+
+/// A permission of a token, within the organization of the token
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenPermission {
+    /// Read or write
+    pub action: Action,
+    /// The type of resource
+    pub resource: resource::Type,
+    /// The name of the one bucket, label or task the permission is for, or
+    /// `None` for all resources of the type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl ProvisionSpec {
+    /// An empty spec.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the organization `org`.
+    pub fn org(mut self, org: OrgSpec) -> Self {
+        self.orgs.push(org);
+        self
+    }
+
+    /// Delete the resources missing from the spec, or not.
+    pub fn prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+}
+
+impl OrgSpec {
+    /// An organization named `name`, without resources.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Add the bucket `bucket`.
+    pub fn bucket(mut self, bucket: BucketSpec) -> Self {
+        self.buckets.push(bucket);
+        self
+    }
+
+    /// Add the label `label`.
+    pub fn label(mut self, label: LabelSpec) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    /// Add the task `task`.
+    pub fn task(mut self, task: TaskSpec) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Add the token `token`.
+    pub fn token(mut self, token: TokenSpec) -> Self {
+        self.tokens.push(token);
+        self
+    }
+}
+
+impl BucketSpec {
+    /// A bucket named `name` keeping data for `retention`.
+    pub fn new(name: impl Into<String>, retention: FluxDuration) -> Self {
+        Self {
+            name: name.into(),
+            retention,
+            description: None,
+        }
+    }
+
+    /// Describe the bucket.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl TaskSpec {
+    /// A task named `name` running `flux`.
+    pub fn new(name: impl Into<String>, flux: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            flux: flux.into(),
+            description: None,
+            status: None,
+        }
+    }
+
+    /// Describe the task.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the status of the task.
+    pub fn status(mut self, status: TaskStatusType) -> Self {
+        self.status = Some(status);
+        self
+    }
+}
+
+impl TokenSpec {
+    /// A token described as `description`, without permissions.
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            permissions: Vec::new(),
+        }
+    }
+
+    /// Add the permission `permission`.
+    pub fn permission(mut self, permission: TokenPermission) -> Self {
+        self.permissions.push(permission);
+        self
+    }
+}
+
+impl TokenPermission {
+    /// Permission to `action` all resources of type `resource`.
+    pub fn new(action: Action, resource: resource::Type) -> Self {
+        Self {
+            action,
+            resource,
+            name: None,
+        }
+    }
+
+    /// Restrict the permission to the resource named `name`.
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+}
+
+/// A change to a resource of the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// What happens to the resource
+    pub kind: ChangeKind,
+    /// The type of the resource
+    pub resource: ResourceKind,
+    /// The organization of the resource
+    pub org: String,
+    /// The name of the resource, or the description of a token
+    pub name: String,
+    /// What changes, e.g. `retention 7d -> 30d`
+    pub details: Vec<String>,
+}
+
+/// What happens to a resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The resource is created
+    Create,
+    /// The resource is updated in place
+    Update,
+    /// The resource is deleted and created again
+    Replace,
+    /// The resource is deleted
+    Delete,
+}
+
+/// The type of a provisioned resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// An organization
+    Org,
+    /// A bucket
+    Bucket,
+    /// A label
+    Label,
+    /// A task
+    Task,
+    /// A token
+    Token,
+}
+
+/// The changes converging a server to a spec, see `Client::provision_plan`
+#[derive(Clone, Debug)]
+pub struct Plan {
+    changes: Vec<Change>,
+    /// How to make each change
+    steps: Vec<Step>,
+    org_ids: HashMap<String, OrgId>,
+    /// The IDs of the resources a token permission may name, by
+    /// organization, type and name
+    ids: HashMap<(String, resource::Type), HashMap<String, String>>,
+}
+
+/// The outcome of `Client::apply_provision`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provisioned {
+    /// The changes made
+    pub changes: Vec<Change>,
+    /// The values of the tokens created, by description, which the server
+    /// shows only once
+    pub tokens: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug)]
+enum Step {
+    CreateOrg(Option<String>),
+    CreateBucket(BucketSpec),
+    UpdateBucket(BucketId, PatchBucketRequest),
+    DeleteBucket(BucketId),
+    CreateLabel(LabelSpec),
+    UpdateLabel(LabelId, HashMap<String, String>),
+    DeleteLabel(LabelId),
+    CreateTask(TaskSpec),
+    UpdateTask(TaskId, UpdateTaskRequest),
+    DeleteTask(TaskId),
+    CreateToken {
+        spec: TokenSpec,
+        /// The ID of the token it replaces
+        replaces: Option<String>,
+    },
+}
+
+impl Client {
+    /// Compare the server with `spec` and return the changes that would
+    /// converge it, without making them.
+    ///
+    /// The task scripts of `spec` are checked like in `create_task`.
+    pub async fn provision_plan(&self, spec: &ProvisionSpec) -> Result<Plan, RequestError> {
+        let mut plan = Plan {
+            changes: Vec::new(),
+            steps: Vec::new(),
+            org_ids: HashMap::new(),
+            ids: HashMap::new(),
+        };
+        for org in &spec.orgs {
+            for task in &org.tasks {
+                validate_task_options(&task.flux).context(InvalidScheduleSnafu)?;
+            }
+            self.plan_org(&mut plan, org, spec.prune).await?;
+        }
+        Ok(plan)
+    }
+
+    /// Make the changes of `plan`, in order, returning them along with the
+    /// values of the tokens created.
+    ///
+    /// A failed change aborts the changes after it; planning again from the
+    /// same spec picks up where it stopped.
+    pub async fn apply_provision(&self, plan: Plan) -> Result<Provisioned, RequestError> {
+        let Plan {
+            changes,
+            steps,
+            mut org_ids,
+            mut ids,
+        } = plan;
+        let mut tokens = BTreeMap::new();
+
+        for (change, step) in changes.iter().zip(steps) {
+            let org = change.org.as_str();
+            let org_id = || {
+                org_ids
+                    .get(org)
+                    .cloned()
+                    .ok_or_else(|| RequestError::missing_id("organization", org))
+            };
+            match step {
+                Step::CreateOrg(description) => {
+                    let created = self.create_organization(org, description).await?;
+                    let id = created
+                        .id
+                        .ok_or_else(|| RequestError::missing_id("organization", org))?;
+                    org_ids.insert(org.to_string(), id);
+                }
+                Step::CreateBucket(spec) => {
+                    let request = PostBucketRequest {
+                        description: spec.description,
+                        retention_rules: retention_rules(spec.retention),
+                        ..PostBucketRequest::new(org_id()?, spec.name)
+                    };
+                    let bucket = self.post_bucket(&request).await?;
+                    if let Some(id) = bucket.id {
+                        ids.entry((org.to_string(), resource::Type::Buckets))
+                            .or_default()
+                            .insert(bucket.name, id.to_string());
+                    }
+                }
+                Step::UpdateBucket(id, request) => {
+                    self.update_bucket(&id, &request).await?;
+                }
+                Step::DeleteBucket(id) => self.delete_bucket(&id).await?,
+                Step::CreateLabel(spec) => {
+                    let created = self
+                        .create_label(&org_id()?, &spec.name, Some(spec.properties))
+                        .await?;
+                    if let Some(id) = created.label.and_then(|label| label.id) {
+                        ids.entry((org.to_string(), resource::Type::Labels))
+                            .or_default()
+                            .insert(spec.name, id.to_string());
+                    }
+                }
+                Step::UpdateLabel(id, properties) => {
+                    self.update_label(None, Some(properties), &id).await?;
+                }
+                Step::DeleteLabel(id) => self.delete_label(&id).await?,
+                Step::CreateTask(spec) => {
+                    let request = CreateTaskRequest {
+                        description: spec.description,
+                        org_id: Some(org_id()?),
+                        status: spec.status,
+                        ..CreateTaskRequest::new(spec.flux)
+                    };
+                    let task = self.post_task(&request).await?;
+                    ids.entry((org.to_string(), resource::Type::Tasks))
+                        .or_default()
+                        .insert(spec.name, task.id.to_string());
+                }
+                Step::UpdateTask(id, request) => {
+                    self.update_task(&id, &request).await?;
+                }
+                Step::DeleteTask(id) => self.delete_task(&id).await?,
+                Step::CreateToken { spec, replaces } => {
+                    let org_id = org_id()?;
+                    let permissions = spec
+                        .permissions
+                        .iter()
+                        .map(|permission| {
+                            let resource = Resource {
+                                id: resolve(&ids, org, permission),
+                                name: permission.name.clone(),
+                                org_id: Some(org_id.clone()),
+                                ..Resource::new(permission.resource)
+                            };
+                            Permission::new(permission.action, resource)
+                        })
+                        .collect();
+                    let authorization = Authorization {
+                        description: Some(spec.description.clone()),
+                        ..Authorization::new(org_id, permissions)
+                    };
+                    let created = self.create_authorization(&authorization).await?;
+                    if let Some(id) = replaces {
+                        self.delete_authorization(&id).await?;
+                    }
+                    if let Some(token) = created.token {
+                        tokens.insert(spec.description, token);
+                    }
+                }
+            }
+        }
+
+        Ok(Provisioned { changes, tokens })
+    }
+
+    /// Plan and apply `spec`, see `provision_plan` and `apply_provision`.
+    pub async fn provision(&self, spec: &ProvisionSpec) -> Result<Provisioned, RequestError> {
+        let plan = self.provision_plan(spec).await?;
+        self.apply_provision(plan).await
+    }
+
+    async fn plan_org(
+        &self,
+        plan: &mut Plan,
+        spec: &OrgSpec,
+        prune: bool,
+    ) -> Result<(), RequestError> {
+        let org = spec.name.as_str();
+        let org_id = match self.find_org_id(org).await? {
+            Some(id) => id,
+            None => {
+                plan.push(ChangeKind::Create, ResourceKind::Org, org, org, Vec::new());
+                plan.steps.push(Step::CreateOrg(spec.description.clone()));
+                for bucket in &spec.buckets {
+                    plan.create_bucket(org, bucket);
+                }
+                for label in &spec.labels {
+                    plan.create_label(org, label);
+                }
+                for task in &spec.tasks {
+                    plan.create_task(org, task);
+                }
+                for token in &spec.tokens {
+                    plan.create_token(org, token, None, Vec::new());
+                }
+                return Ok(());
+            }
+        };
+        plan.org_ids.insert(org.to_string(), org_id.clone());
+
+        let buckets_request = ListBucketsRequest {
+            org_id: Some(org_id.clone()),
+            ..ListBucketsRequest::default()
+        };
+        let mut buckets: BTreeMap<_, _> = self
+            .stream_buckets(buckets_request)
+            .map_ok(|bucket| (bucket.name.clone(), bucket))
+            .try_collect()
+            .await?;
+        let mut labels: BTreeMap<_, _> = self
+            .labels_by_org(&org_id)
+            .await?
+            .labels
+            .into_iter()
+            .filter_map(|label| Some((label.name.clone()?, label)))
+            .collect();
+        let tasks_request = ListTasksRequest {
+            org_id: Some(org_id.clone()),
+            ..ListTasksRequest::default()
+        };
+        let mut tasks: BTreeMap<_, _> = self
+            .stream_tasks(tasks_request)
+            .map_ok(|task| (task.name.clone(), task))
+            .try_collect()
+            .await?;
+        let tokens_request = crate::api::authorization::ListAuthorizationsRequest {
+            org_id: Some(org_id.clone()),
+            ..Default::default()
+        };
+        let tokens = self
+            .list_authorizations(tokens_request)
+            .await?
+            .authorizations;
+
+        for (name, bucket) in &buckets {
+            if let Some(id) = &bucket.id {
+                plan.remember(org, resource::Type::Buckets, name, id.to_string());
+            }
+        }
+        for (name, label) in &labels {
+            if let Some(id) = &label.id {
+                plan.remember(org, resource::Type::Labels, name, id.to_string());
+            }
+        }
+        for (name, task) in &tasks {
+            plan.remember(org, resource::Type::Tasks, name, task.id.to_string());
+        }
+
+        for spec in &spec.buckets {
+            let bucket = match buckets.remove(&spec.name) {
+                Some(bucket) => bucket,
+                None => {
+                    plan.create_bucket(org, spec);
+                    continue;
+                }
+            };
+            let mut details = Vec::new();
+            let mut request = PatchBucketRequest::default();
+            let retention = retention_of(&bucket);
+            if retention != spec.retention {
+                details.push(format!(
+                    "retention {} -> {}",
+                    display_retention(retention),
+                    display_retention(spec.retention)
+                ));
+                request.retention_rules = Some(retention_rules(spec.retention));
+            }
+            if spec.description.is_some() && spec.description != bucket.description {
+                details.push("description".to_string());
+                request.description = spec.description.clone();
+            }
+            if !details.is_empty() {
+                plan.push(
+                    ChangeKind::Update,
+                    ResourceKind::Bucket,
+                    org,
+                    &spec.name,
+                    details,
+                );
+                let id = bucket
+                    .id
+                    .ok_or_else(|| RequestError::missing_id("bucket", &spec.name))?;
+                plan.steps.push(Step::UpdateBucket(id, request));
+            }
+        }
+
+        for spec in &spec.labels {
+            let label = match labels.remove(&spec.name) {
+                Some(label) => label,
+                None => {
+                    plan.create_label(org, spec);
+                    continue;
+                }
+            };
+            if let Some(properties) = changed_properties(&label, spec) {
+                let details = vec!["properties".to_string()];
+                plan.push(
+                    ChangeKind::Update,
+                    ResourceKind::Label,
+                    org,
+                    &spec.name,
+                    details,
+                );
+                let id = label
+                    .id
+                    .ok_or_else(|| RequestError::missing_id("label", &spec.name))?;
+                plan.steps.push(Step::UpdateLabel(id, properties));
+            }
+        }
+
+        for spec in &spec.tasks {
+            let task = match tasks.remove(&spec.name) {
+                Some(task) => task,
+                None => {
+                    plan.create_task(org, spec);
+                    continue;
+                }
+            };
+            let request = UpdateTaskRequest {
+                flux: Some(spec.flux.clone()).filter(|flux| *flux != task.flux),
+                description: spec
+                    .description
+                    .clone()
+                    .filter(|description| task.description.as_ref() != Some(description)),
+                status: spec.status.filter(|status| task.status != Some(*status)),
+            };
+            let mut details = Vec::new();
+            if request.flux.is_some() {
+                details.push("script".to_string());
+            }
+            if request.description.is_some() {
+                details.push("description".to_string());
+            }
+            if let Some(status) = request.status {
+                details.push(format!("status {:?}", status).to_lowercase());
+            }
+            if !details.is_empty() {
+                plan.push(
+                    ChangeKind::Update,
+                    ResourceKind::Task,
+                    org,
+                    &spec.name,
+                    details,
+                );
+                plan.steps.push(Step::UpdateTask(task.id, request));
+            }
+        }
+
+        for spec in &spec.tokens {
+            let token = tokens
+                .iter()
+                .find(|token| token.description.as_deref() == Some(spec.description.as_str()));
+            let token = match token {
+                Some(token) => token,
+                None => {
+                    plan.create_token(org, spec, None, Vec::new());
+                    continue;
+                }
+            };
+            let mut existing: Vec<_> = token
+                .permissions
+                .iter()
+                .map(|permission| {
+                    let resource = &permission.resource;
+                    (permission.action, resource.r#type, resource.id.clone())
+                })
+                .collect();
+            let mut desired: Vec<_> = spec
+                .permissions
+                .iter()
+                .map(|permission| {
+                    let id = resolve(&plan.ids, org, permission);
+                    let resolved = permission.name.is_none() || id.is_some();
+                    (permission.action, permission.resource, id, resolved)
+                })
+                .collect();
+            existing.sort();
+            desired.sort();
+            let unchanged = desired.iter().all(|(_, _, _, resolved)| *resolved)
+                && desired
+                    .iter()
+                    .map(|(action, r#type, id, _)| (*action, *r#type, id.clone()))
+                    .eq(existing);
+            if !unchanged {
+                plan.create_token(org, spec, token.id.clone(), vec!["permissions".to_string()]);
+            }
+        }
+
+        if prune {
+            for (name, bucket) in buckets {
+                if !name.starts_with('_') {
+                    plan.push(
+                        ChangeKind::Delete,
+                        ResourceKind::Bucket,
+                        org,
+                        &name,
+                        Vec::new(),
+                    );
+                    let id = bucket
+                        .id
+                        .ok_or_else(|| RequestError::missing_id("bucket", &name))?;
+                    plan.steps.push(Step::DeleteBucket(id));
+                }
+            }
+            for (name, label) in labels {
+                plan.push(
+                    ChangeKind::Delete,
+                    ResourceKind::Label,
+                    org,
+                    &name,
+                    Vec::new(),
+                );
+                let id = label
+                    .id
+                    .ok_or_else(|| RequestError::missing_id("label", &name))?;
+                plan.steps.push(Step::DeleteLabel(id));
+            }
+            for (name, task) in tasks {
+                plan.push(
+                    ChangeKind::Delete,
+                    ResourceKind::Task,
+                    org,
+                    &name,
+                    Vec::new(),
+                );
+                plan.steps.push(Step::DeleteTask(task.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// The ID of the organization named `name`, or `None` if there is none
+    async fn find_org_id(&self, name: &str) -> Result<Option<OrgId>, RequestError> {
+        let request = ListOrganizationRequest {
+            org: Some(name.to_string()),
+            ..ListOrganizationRequest::default()
+        };
+        match self.list_organizations(request).await {
+            Ok(orgs) => Ok(orgs
+                .orgs
+                .into_iter()
+                .find(|org| org.name == name)
+                .and_then(|org| org.id)),
+            Err(RequestError::Http { status, .. }) if status == StatusCode::NOT_FOUND => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Plan {
+    /// The changes, in the order they are made.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Whether the server already matches the spec.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn push(
+        &mut self,
+        kind: ChangeKind,
+        resource: ResourceKind,
+        org: &str,
+        name: &str,
+        details: Vec<String>,
+    ) {
+        self.changes.push(Change {
+            kind,
+            resource,
+            org: org.to_string(),
+            name: name.to_string(),
+            details,
+        });
+    }
+
+    fn remember(&mut self, org: &str, r#type: resource::Type, name: &str, id: String) {
+        self.ids
+            .entry((org.to_string(), r#type))
+            .or_default()
+            .insert(name.to_string(), id);
+    }
+
+    fn create_bucket(&mut self, org: &str, spec: &BucketSpec) {
+        let details = vec![format!("retention {}", display_retention(spec.retention))];
+        self.push(
+            ChangeKind::Create,
+            ResourceKind::Bucket,
+            org,
+            &spec.name,
+            details,
+        );
+        self.steps.push(Step::CreateBucket(spec.clone()));
+    }
+
+    fn create_label(&mut self, org: &str, spec: &LabelSpec) {
+        self.push(
+            ChangeKind::Create,
+            ResourceKind::Label,
+            org,
+            &spec.name,
+            Vec::new(),
+        );
+        self.steps.push(Step::CreateLabel(spec.clone()));
+    }
+
+    fn create_task(&mut self, org: &str, spec: &TaskSpec) {
+        self.push(
+            ChangeKind::Create,
+            ResourceKind::Task,
+            org,
+            &spec.name,
+            Vec::new(),
+        );
+        self.steps.push(Step::CreateTask(spec.clone()));
+    }
+
+    fn create_token(
+        &mut self,
+        org: &str,
+        spec: &TokenSpec,
+        replaces: Option<String>,
+        details: Vec<String>,
+    ) {
+        let kind = match replaces {
+            Some(_) => ChangeKind::Replace,
+            None => ChangeKind::Create,
+        };
+        self.push(kind, ResourceKind::Token, org, &spec.description, details);
+        self.steps.push(Step::CreateToken {
+            spec: spec.clone(),
+            replaces,
+        });
+    }
+}
+
+impl fmt::Display for Plan {
+    /// Writes a change per line, or `no changes`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return f.write_str("no changes");
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Change {
+    /// Writes e.g. `~ bucket acme/telegraf: retention 7d -> 30d`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self.kind {
+            ChangeKind::Create => "+",
+            ChangeKind::Update => "~",
+            ChangeKind::Replace => "-/+",
+            ChangeKind::Delete => "-",
+        };
+        match self.resource {
+            ResourceKind::Org => write!(f, "{} org {}", symbol, self.name)?,
+            resource => write!(f, "{} {} {}/{}", symbol, resource, self.org, self.name)?,
+        }
+        if !self.details.is_empty() {
+            write!(f, ": {}", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Org => "org",
+            Self::Bucket => "bucket",
+            Self::Label => "label",
+            Self::Task => "task",
+            Self::Token => "token",
+        })
+    }
+}
+
+/// The ID of the resource a permission names, if it doesn't name one or it
+/// is known
+fn resolve(
+    ids: &HashMap<(String, resource::Type), HashMap<String, String>>,
+    org: &str,
+    permission: &TokenPermission,
+) -> Option<String> {
+    let name = permission.name.as_ref()?;
+    ids.get(&(org.to_string(), permission.resource))?
+        .get(name)
+        .cloned()
+}
+
+/// The properties to send to give `label` those of `spec`, if they differ
+fn changed_properties(label: &Label, spec: &LabelSpec) -> Option<HashMap<String, String>> {
+    let current = label.properties.clone().unwrap_or_default();
+    if current == spec.properties {
+        return None;
+    }
+    // Properties are removed by sending them with an empty value
+    let mut properties = spec.properties.clone();
+    for key in current.into_keys() {
+        properties.entry(key).or_default();
+    }
+    Some(properties)
+}
+
+fn retention_rules(retention: FluxDuration) -> Vec<RetentionRule> {
+    vec![RetentionRule::new(retention_rule::Type::Expire, retention)]
+}
+
+fn display_retention(retention: FluxDuration) -> String {
+    if retention == FluxDuration::ZERO {
+        "forever".to_string()
+    } else {
+        retention.to_string()
+    }
+}
+
+/// Serde for durations as Flux literals, or as seconds
+mod duration_literal {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::models::FluxDuration;
+
+    pub(super) fn serialize<S: Serializer>(
+        duration: &FluxDuration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(duration)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FluxDuration, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Literal(String),
+            Seconds(u64),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Literal(text) => text.parse().map_err(de::Error::custom),
+            Repr::Seconds(secs) => Ok(FluxDuration::from_secs(secs)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    const ORG_ID: &str = "00000000000000aa";
+
+    fn org_query(id: &str) -> Matcher {
+        Matcher::UrlEncoded("orgID".into(), id.into())
+    }
+
+    #[tokio::test]
+    async fn plans_and_applies_changes() {
+        let _org = mock("GET", "/api/v2/orgs")
+            .match_query(Matcher::UrlEncoded("org".into(), "provisioned".into()))
+            .with_body(format!(
+                r#"{{"orgs":[{{"id":"{}","name":"provisioned"}}]}}"#,
+                ORG_ID
+            ))
+            .create();
+        let _buckets = mock("GET", "/api/v2/buckets")
+            .match_query(org_query(ORG_ID))
+            .with_body(
+                r#"{"buckets":[
+                    {"id":"b1","name":"cpu","retentionRules":[{"type":"expire","everySeconds":604800}]},
+                    {"id":"b2","name":"old","retentionRules":[]},
+                    {"id":"b3","name":"_monitoring","retentionRules":[]}
+                ]}"#,
+            )
+            .create();
+        let _labels = mock("GET", "/api/v2/labels")
+            .match_query(org_query(ORG_ID))
+            .with_body(r#"{"labels":[{"id":"l1","name":"prod","properties":{"color":"red"}}]}"#)
+            .create();
+        let _tasks = mock("GET", "/api/v2/tasks")
+            .match_query(org_query(ORG_ID))
+            .with_body(r#"{"tasks":[]}"#)
+            .create();
+        let _tokens = mock("GET", "/api/v2/authorizations")
+            .match_query(org_query(ORG_ID))
+            .with_body(format!(
+                r#"{{"authorizations":[{{"id":"t1","orgID":"{}","description":"writer",
+                    "permissions":[{{"action":"read","resource":{{"type":"buckets","id":"b1"}}}}]}}]}}"#,
+                ORG_ID
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let spec = ProvisionSpec::new().prune(true).org(
+            OrgSpec::new("provisioned")
+                .bucket(BucketSpec::new("cpu", FluxDuration::from_secs(30 * 86_400)))
+                .bucket(BucketSpec::new("fresh", FluxDuration::ZERO))
+                .label(LabelSpec::new("prod").property("color", "red"))
+                .token(TokenSpec::new("writer").permission(
+                    TokenPermission::new(Action::Write, resource::Type::Buckets).named("cpu"),
+                )),
+        );
+        let plan = client.provision_plan(&spec).await.unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "~ bucket provisioned/cpu: retention 7d -> 30d\n\
+             + bucket provisioned/fresh: retention forever\n\
+             -/+ token provisioned/writer: permissions\n\
+             - bucket provisioned/old"
+        );
+
+        let update = mock("PATCH", "/api/v2/buckets/b1")
+            .match_body(r#"{"retentionRules":[{"type":"expire","everySeconds":2592000}]}"#)
+            .with_body(r#"{"id":"b1","name":"cpu","retentionRules":[]}"#)
+            .create();
+        let create = mock("POST", "/api/v2/buckets")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{"orgID":"{}","name":"fresh"}}"#,
+                ORG_ID
+            )))
+            .with_status(201)
+            .with_body(r#"{"id":"b4","name":"fresh","retentionRules":[]}"#)
+            .create();
+        let token = mock("POST", "/api/v2/authorizations")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{"description":"writer","permissions":[{{"action":"write",
+                    "resource":{{"type":"buckets","id":"b1","name":"cpu","orgID":"{}"}}}}]}}"#,
+                ORG_ID
+            )))
+            .with_status(201)
+            .with_body(format!(
+                r#"{{"id":"t2","orgID":"{}","permissions":[],"token":"secret"}}"#,
+                ORG_ID
+            ))
+            .create();
+        let revoke = mock("DELETE", "/api/v2/authorizations/t1")
+            .with_status(204)
+            .create();
+        let prune = mock("DELETE", "/api/v2/buckets/b2")
+            .with_status(204)
+            .create();
+
+        let provisioned = client.apply_provision(plan).await.unwrap();
+        update.assert();
+        create.assert();
+        token.assert();
+        revoke.assert();
+        prune.assert();
+        assert_eq!(provisioned.changes.len(), 4);
+        assert_eq!(provisioned.tokens["writer"], "secret");
+    }
+
+    #[tokio::test]
+    async fn refuses_to_prune_resources_without_ids() {
+        let org_id = "00000000000000bb";
+        let _org = mock("GET", "/api/v2/orgs")
+            .match_query(Matcher::UrlEncoded("org".into(), "idless".into()))
+            .with_body(format!(r#"{{"orgs":[{{"id":"{}","name":"idless"}}]}}"#, org_id))
+            .create();
+        let _buckets = mock("GET", "/api/v2/buckets")
+            .match_query(org_query(org_id))
+            .with_body(r#"{"buckets":[]}"#)
+            .create();
+        let _labels = mock("GET", "/api/v2/labels")
+            .match_query(org_query(org_id))
+            .with_body(r#"{"labels":[{"name":"stale"}]}"#)
+            .create();
+        let _tasks = mock("GET", "/api/v2/tasks")
+            .match_query(org_query(org_id))
+            .with_body(r#"{"tasks":[]}"#)
+            .create();
+        let _tokens = mock("GET", "/api/v2/authorizations")
+            .match_query(org_query(org_id))
+            .with_body(r#"{"authorizations":[]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let spec = ProvisionSpec::new().prune(true).org(OrgSpec::new("idless"));
+        let err = client.provision_plan(&spec).await.unwrap_err();
+
+        assert!(
+            matches!(err, RequestError::Deserializing { ref text, .. } if text.contains("\"stale\" has no ID")),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn plans_missing_orgs_from_scratch() {
+        let _org = mock("GET", "/api/v2/orgs")
+            .match_query(Matcher::UrlEncoded("org".into(), "unprovisioned".into()))
+            .with_status(404)
+            .with_body(
+                r#"{"code":"not found","message":"organization name \"unprovisioned\" not found"}"#,
+            )
+            .create();
+
+        let spec: ProvisionSpec = serde_json::from_str(
+            r#"{"orgs":[{
+                "name":"unprovisioned",
+                "buckets":[{"name":"metrics","retention":"90d"},{"name":"logs","retention":3600}],
+                "labels":[{"name":"team"}],
+                "tokens":[{"description":"reader","permissions":[{"action":"read","resource":"buckets"}]}]
+            }]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            spec.orgs[0].buckets[1].retention,
+            FluxDuration::from_secs(3600)
+        );
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let plan = client.provision_plan(&spec).await.unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "+ org unprovisioned\n\
+             + bucket unprovisioned/metrics: retention 90d\n\
+             + bucket unprovisioned/logs: retention 1h\n\
+             + label unprovisioned/team\n\
+             + token unprovisioned/reader"
+        );
+        assert!(plan
+            .changes()
+            .iter()
+            .all(|change| change.kind == ChangeKind::Create));
+    }
+}
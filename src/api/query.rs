@@ -2,10 +2,13 @@
 //!
 //! Query InfluxDB using InfluxQL or Flux Query
 
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
 use std::str::FromStr;
 
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu, SerializingSnafu};
+use crate::request::{RequestKind, Response};
+use crate::{Client, RequestError, SerializingSnafu, Target};
 
 use base64::decode;
 use chrono::DateTime;
@@ -15,58 +18,41 @@ use go_parse_duration::parse_duration;
 use influxdb2_structmap::value::Value;
 use influxdb2_structmap::{FromMap, GenericMap};
 use ordered_float::OrderedFloat;
+use reqwest::header::CONTENT_TYPE;
 use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
 use snafu::ResultExt;
 
+use crate::models::ast::format::format_package;
 use crate::models::{
-    AnalyzeQueryResponse, AstResponse, FluxSuggestion, FluxSuggestions, LanguageRequest, Query,
+    AnalyzeQueryResponse, AstResponse, FluxDuration, FluxSuggestion, FluxSuggestions,
+    LanguageRequest, Query,
 };
 
 impl Client {
     /// Get Query Suggestions
     pub async fn query_suggestions(&self) -> Result<FluxSuggestions, RequestError> {
-        let req_url = format!("{}/api/v2/query/suggestions", self.url);
-        let response = self
-            .request(Method::GET, &req_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let req_url = self.endpoint_url("/api/v2/query/suggestions");
+        let response = self.send(self.request(Method::GET, &req_url)).await?;
 
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<FluxSuggestions>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<FluxSuggestions>().await,
+            _ => Err(response.error().await),
         }
     }
 
     /// Query Suggestions with name
     pub async fn query_suggestions_name(&self, name: &str) -> Result<FluxSuggestion, RequestError> {
-        let req_url = format!(
-            "{}/api/v2/query/suggestions/{name}",
-            self.url,
+        let req_url = self.endpoint_url(&format!(
+            "/api/v2/query/suggestions/{name}",
             name = crate::common::urlencode(name),
-        );
+        ));
 
-        let response = self
-            .request(Method::GET, &req_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let response = self.send(self.request(Method::GET, &req_url)).await?;
 
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<FluxSuggestion>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<FluxSuggestion>().await,
+            _ => Err(response.error().await),
         }
     }
 
@@ -75,61 +61,157 @@ impl Client {
         &self, 
         query: Option<Query>
     ) -> Result<Vec<T>, RequestError> {
-        let req_url = format!("{}/api/v2/query", self.url);
-        let body = serde_json::to_string(&query.unwrap_or_default()).context(SerializingSnafu)?;
+        self.query_to(Target::new(), query).await
+    }
 
-        let response = self
-            .request(Method::POST, &req_url)
-            .header("Accepting-Encoding", "identity")
-            .header("Content-Type", "application/json")
-            .query(&[("org", &self.org)])
-            .body(body)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+    /// Query in the organization of `target`. The script names its buckets,
+    /// so the bucket of `target` is not used.
+    pub async fn query_to<T: FromMap>(
+        &self,
+        target: impl Into<Target>,
+        query: Option<Query>,
+    ) -> Result<Vec<T>, RequestError> {
+        let items = self.query_raw_to(target, query).await?;
+        items
+            .into_iter()
+            .map(|item| {
+                T::try_from_genericmap(item).map_err(|e| RequestError::deserializing(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Query, returning each row as a `GenericMap` of column name to value
+    /// instead of deserializing it into a struct.
+    pub async fn query_raw(&self, query: Option<Query>) -> Result<Vec<GenericMap>, RequestError> {
+        self.query_raw_to(Target::new(), query).await
+    }
+
+    /// Query like `query_raw`, in the organization of `target`.
+    pub async fn query_raw_to(
+        &self,
+        target: impl Into<Target>,
+        query: Option<Query>,
+    ) -> Result<Vec<GenericMap>, RequestError> {
+        let response = self.post_query(&target.into(), query, "text/csv").await?;
 
         match response.status() {
-            StatusCode::OK => {
-                let text = response.text().await.unwrap();
-                let qtr = QueryTableResult::new(&text[..]);
+            StatusCode::OK => response.parse(|text| {
+                let qtr = QueryTableResult::new(text);
                 let qr = QueryResult::new(qtr)?;
-                let mut res = vec![];
-                for item in qr.items {
-                    res.push(T::from_genericmap(item));
-                }
-                Ok(res)
-            },
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+                Ok(qr.items)
+            }).await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Query, returning every record as it is, where `query_raw` merges the
+    /// `_field` and `_value` columns of the records of a series into one row.
+    pub(crate) async fn query_records(&self, query: Query) -> Result<Vec<GenericMap>, RequestError> {
+        let response = self.post_query(&Target::new(), Some(query), "text/csv").await?;
+
+        match response.status() {
+            StatusCode::OK => response.parse(parse_records).await,
+            _ => Err(response.error().await),
         }
     }
 
+    /// Query, asking for a JSON response and deserializing each row into `T`
+    /// through serde.
+    ///
+    /// Servers that answer with `application/json` send an array of row
+    /// objects. Others, such as InfluxDB OSS, answer with annotated CSV, whose
+    /// rows are turned into the same objects, with times in RFC3339 format and
+    /// durations in nanoseconds.
+    pub async fn query_json<T: DeserializeOwned>(
+        &self,
+        query: Option<Query>,
+    ) -> Result<Vec<T>, RequestError> {
+        self.query_json_to(Target::new(), query).await
+    }
+
+    /// Query like `query_json`, in the organization of `target`.
+    pub async fn query_json_to<T: DeserializeOwned>(
+        &self,
+        target: impl Into<Target>,
+        query: Option<Query>,
+    ) -> Result<Vec<T>, RequestError> {
+        let response = self
+            .post_query(&target.into(), query, "application/json")
+            .await?;
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+
+        match response.status() {
+            StatusCode::OK if is_json => response.json::<Vec<T>>().await,
+            StatusCode::OK => response.parse(|text| {
+                let qr = QueryResult::new(QueryTableResult::new(text))?;
+                qr.items
+                    .iter()
+                    .map(|row| {
+                        let row = row
+                            .iter()
+                            .map(|(column, value)| (column.clone(), json_value(value)))
+                            .collect();
+                        serde_json::from_value(serde_json::Value::Object(row))
+                            .map_err(|e| RequestError::deserializing(e.to_string()))
+                    })
+                    .collect()
+            }).await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Post `query`, with the options of the request it carries.
+    pub(crate) async fn post_query(
+        &self,
+        target: &Target,
+        query: Option<Query>,
+        accept: &str,
+    ) -> Result<Response, RequestError> {
+        let req_url = self.endpoint_url("/api/v2/query");
+        let mut query = query.unwrap_or_default();
+        if let Cow::Owned(script) = query.script() {
+            query.query = script;
+        }
+        let body = serde_json::to_string(&query).context(SerializingSnafu)?;
+
+        let mut request = self
+            .request_as(RequestKind::Query, Method::POST, &req_url)
+            .header("Accepting-Encoding", "identity")
+            .header("Accept", accept)
+            .header("Content-Type", "application/json")
+            .query(&[("org", self.resolve_org(target))]);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = query.timeout {
+            request = request.timeout(timeout);
+        }
+        self.send_retrying(request.body(body)).await
+    }
+
     /// Analyze Query
     pub async fn query_analyze(
         &self,
         query: Option<Query>,
     ) -> Result<AnalyzeQueryResponse, RequestError> {
-        let req_url = format!("{}/api/v2/query/analyze", self.url);
+        let req_url = self.endpoint_url("/api/v2/query/analyze");
 
         let response = self
-            .request(Method::POST, &req_url)
-            .header("Content-Type", "application/json")
-            .body(serde_json::to_string(&query.unwrap_or_default()).context(SerializingSnafu)?)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request(Method::POST, &req_url)
+                    .header("Content-Type", "application/json")
+                    .body(
+                        serde_json::to_string(&query.unwrap_or_default())
+                            .context(SerializingSnafu)?,
+                    ),
+            )
+            .await?;
 
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<AnalyzeQueryResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<AnalyzeQueryResponse>().await,
+            _ => Err(response.error().await),
         }
     }
 
@@ -138,34 +220,41 @@ impl Client {
         &self,
         language_request: Option<LanguageRequest>,
     ) -> Result<AstResponse, RequestError> {
-        let req_url = format!("{}/api/v2/query/ast", self.url);
+        let req_url = self.endpoint_url("/api/v2/query/ast");
 
         let response = self
-            .request(Method::POST, &req_url)
-            .header("Content-Type", "application/json")
-            .body(
-                serde_json::to_string(&language_request.unwrap_or_default())
-                    .context(SerializingSnafu)?,
+            .send(
+                self.request(Method::POST, &req_url)
+                    .header("Content-Type", "application/json")
+                    .body(
+                        serde_json::to_string(&language_request.unwrap_or_default())
+                            .context(SerializingSnafu)?,
+                    ),
             )
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .await?;
 
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<AstResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => response.json::<AstResponse>().await,
+            _ => Err(response.error().await),
         }
     }
+
+    /// Format `query` in the canonical layout of
+    /// [`format_package`](crate::models::ast::format::format_package), from
+    /// the AST the server parses it into.
+    pub async fn format_query(&self, query: &str) -> Result<String, RequestError> {
+        let response = self
+            .query_ast(Some(LanguageRequest::new(query.to_string())))
+            .await?;
+        let package = response
+            .ast
+            .ok_or_else(|| RequestError::deserializing("The response has no AST"))?;
+        Ok(format_package(&package))
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-enum DataType {
+pub(crate) enum DataType {
     String,
     Double,
     Bool,
@@ -179,20 +268,18 @@ enum DataType {
 impl FromStr for DataType {
     type Err = RequestError;
 
-    fn from_str(input: &str) -> Result<DataType, RequestError> {
+    fn from_str(input: &str) -> Result<Self, RequestError> {
         match input {
-            "string"                => Ok(DataType::String),
-            "double"                => Ok(DataType::Double),
-            "boolean"               => Ok(DataType::Bool),
-            "long"                  => Ok(DataType::Long),
-            "unsignedLong"          => Ok(DataType::UnsignedLong),
-            "duration"              => Ok(DataType::Duration),
-            "base64Binary"          => Ok(DataType::Base64Binary),
-            "dateTime:RFC3339"      => Ok(DataType::TimeRFC),
-            "dateTime:RFC3339Nano"  => Ok(DataType::TimeRFC),
-            _ => Err(RequestError::Deserializing { 
-                text: format!("unknown datatype: {}", input)
-            })
+            "string"                => Ok(Self::String),
+            "double"                => Ok(Self::Double),
+            "boolean"               => Ok(Self::Bool),
+            "long"                  => Ok(Self::Long),
+            "unsignedLong"          => Ok(Self::UnsignedLong),
+            "duration"              => Ok(Self::Duration),
+            "base64Binary"          => Ok(Self::Base64Binary),
+            "dateTime:RFC3339"      => Ok(Self::TimeRFC),
+            "dateTime:RFC3339Nano"  => Ok(Self::TimeRFC),
+            _ => Err(RequestError::deserializing(format!("unknown datatype: {}", input)))
         }
     }
 }
@@ -208,7 +295,7 @@ struct FluxColumn {
 #[derive(Clone, Debug, PartialEq)]
 pub struct FluxRecord {
     table:  i32,
-    values: GenericMap,
+    pub(crate) values: GenericMap,
 }
 
 struct FluxTableMetadata {
@@ -216,8 +303,8 @@ struct FluxTableMetadata {
     columns:    Vec<FluxColumn>,
 }
 
-struct QueryTableResult<'a> {
-    csv_reader:     csv::Reader<&'a [u8]>,
+pub(crate) struct QueryTableResult<R> {
+    csv_reader:     csv::Reader<R>,
     table_position: i32,
     table_changed:  bool,
     table:          Option<FluxTableMetadata>,
@@ -230,11 +317,19 @@ enum ParsingState {
 	Error,
 }
 
-impl<'a> QueryTableResult<'a> {
+impl<'a> QueryTableResult<&'a [u8]> {
     fn new(text: &'a str) -> Self {
+        Self::from_reader(text.as_bytes())
+    }
+}
+
+impl<R: std::io::Read> QueryTableResult<R> {
+    /// Parse the records of the annotated CSV read from `reader`
+    pub(crate) fn from_reader(reader: R) -> Self {
         let reader = csv::ReaderBuilder::new()
             .has_headers(false)
-            .from_reader(text.as_bytes());
+            .flexible(true)
+            .from_reader(reader);
         Self {
             csv_reader: reader,
             table_position: 0,
@@ -244,7 +339,7 @@ impl<'a> QueryTableResult<'a> {
     }
 }
 
-impl<'a> FallibleIterator for QueryTableResult<'a> {
+impl<R: std::io::Read> FallibleIterator for QueryTableResult<R> {
     type Item = FluxRecord;
     type Error = RequestError;
 
@@ -257,7 +352,11 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
         let mut parsing_state = ParsingState::Normal;
         let mut data_type_annotation_found = false;
         loop {
-            if !self.csv_reader.read_record(&mut row).unwrap() {
+            let read = self
+                .csv_reader
+                .read_record(&mut row)
+                .map_err(|e| RequestError::deserializing(e.to_string()))?;
+            if !read {
                 // EOF
                 return Ok(None)
             }
@@ -265,7 +364,7 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
                 continue
             }
             if let Some(s) = row.get(0) {
-                if s.len() > 0 && s.chars().nth(0).unwrap() == '#' {
+                if s.starts_with('#') {
                     // Finding new table, prepare for annotation parsing
                     if parsing_state == ParsingState::Normal {
                         self.table = Some(FluxTableMetadata { 
@@ -287,18 +386,14 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
                 }
             }
             if self.table.is_none() {
-                return Err(RequestError::Deserializing { 
-                    text: String::from("annotations not found") 
-                })
+                return Err(RequestError::deserializing("annotations not found"))
             }
             if row.len()-1 != self.table.as_ref().unwrap().columns.len() {
-                return Err(RequestError::Deserializing {
-                    text: format!(
-                        "row has different number of columns than the table: {} vs {}", 
-                        row.len() - 1, 
-                        self.table.as_ref().unwrap().columns.len(),
-                    )
-                })
+                return Err(RequestError::deserializing(format!(
+                    "row has different number of columns than the table: {} vs {}", 
+                    row.len() - 1, 
+                    self.table.as_ref().unwrap().columns.len(),
+                )))
             }
             if let Some(s) = row.get(0) {
                 match s {
@@ -307,9 +402,9 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
                             ParsingState::Annotation => {
                                 // Parse column name (csv header)
                                 if !data_type_annotation_found {
-                                    return Err(RequestError::Deserializing {
-                                        text: String::from("datatype annotation not found")
-                                    })
+                                    return Err(RequestError::deserializing(
+                                        "datatype annotation not found"
+                                    ))
                                 }
                                 if row.get(1).unwrap() == "error" {
                                     parsing_state = ParsingState::Error;
@@ -323,19 +418,17 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
                                 continue;
                             }
                             ParsingState::Error => {
-                                let msg = if row.len() > 1 && row.get(1).unwrap().len() > 0 {
+                                let msg = if row.len() > 1 && !row.get(1).unwrap().is_empty() {
                                     row.get(1).unwrap()
                                 } else {
                                     "unknown query error"
                                 };
                                 let mut reference = String::from("");
-                                if row.len() > 2 && row.get(2).unwrap().len() > 0 {
+                                if row.len() > 2 && !row.get(2).unwrap().is_empty() {
                                     let s = row.get(2).unwrap();
                                     reference = format!(",{}", s);
                                 }
-                                return Err(RequestError::Deserializing {
-                                    text: format!("{}{}", msg, reference)
-                                });
+                                return Err(RequestError::deserializing(format!("{}{}", msg, reference)));
                             }
                             _ => {}
                         }
@@ -343,7 +436,7 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
                         for i in 1..row.len() {
                             let column = &self.table.as_mut().unwrap().columns[i-1];
                             let mut v = row.get(i).unwrap();
-                            if v == "" {
+                            if v.is_empty() {
                                 v = &column.default_value[..];
                             }
                             let value = parse_value(
@@ -380,9 +473,7 @@ impl<'a> FallibleIterator for QueryTableResult<'a> {
                         }
                     }
                     _ => { 
-                        return Err(RequestError::Deserializing {
-                            text: format!("invalid first cell: {}", s)
-                        });
+                        return Err(RequestError::deserializing(format!("invalid first cell: {}", s)));
                     }
                 }
             }
@@ -396,7 +487,7 @@ struct QueryResult {
 }
 
 impl QueryResult {
-    fn new<'a>(qtr: QueryTableResult<'a>) -> Result<Self, RequestError> {
+    fn new<R: std::io::Read>(qtr: QueryTableResult<R>) -> Result<Self, RequestError> {
         // Parse items
         let mut items = vec![];
         let mut build_table = HashMap::<GenericMap, GenericMap>::new();
@@ -456,14 +547,25 @@ impl QueryResult {
     }
 }
 
-fn parse_value(s: &str, t: DataType, name: &str) -> Result<Value, RequestError> {
+/// Parse every record of annotated CSV `text` as it is
+pub(crate) fn parse_records(text: &str) -> Result<Vec<GenericMap>, RequestError> {
+    QueryTableResult::new(text)
+        .map(|record| Ok(record.values))
+        .collect()
+}
+
+pub(crate) fn parse_value(s: &str, t: DataType, name: &str) -> Result<Value, RequestError> {
     match t {
         DataType::String => {
             Ok(Value::String(String::from(s)))
         }
         DataType::Double => {
-            let v = s.parse::<f64>().unwrap();
-            Ok(Value::Double(OrderedFloat::from(v)))
+            match s.parse::<f64>() {
+                Ok(v) => Ok(Value::Double(OrderedFloat::from(v))),
+                Err(_) => Err(RequestError::deserializing(format!(
+                    "invalid double: {}, name: {}", s, name
+                ))),
+            }
         }
         DataType::Bool => {
             if s.to_lowercase() == "false" {
@@ -473,24 +575,36 @@ fn parse_value(s: &str, t: DataType, name: &str) -> Result<Value, RequestError>
             }
         }
         DataType::Long => {
-            let v = s.parse::<i64>().unwrap();
-            Ok(Value::Long(v))
+            match s.parse::<i64>() {
+                Ok(v) => Ok(Value::Long(v)),
+                Err(_) => Err(RequestError::deserializing(format!(
+                    "invalid or out of range long: {}, name: {}", s, name
+                ))),
+            }
         }
         DataType::UnsignedLong => {
-            let v = s.parse::<u64>().unwrap();
-            Ok(Value::UnsignedLong(v))
+            match s.parse::<u64>() {
+                Ok(v) => Ok(Value::UnsignedLong(v)),
+                Err(_) => Err(RequestError::deserializing(format!(
+                    "invalid or out of range unsignedLong: {}, name: {}", s, name
+                ))),
+            }
         }
         DataType::Duration => {
-            match parse_duration(s) {
-                Ok(d) => Ok(Value::Duration(chrono::Duration::nanoseconds(d))),
-                Err(_) => Err(RequestError::Deserializing {
-                    text: format!("invalid duration: {}, name: {}", s, name)
-                }),
+            match parse_duration(s).ok().or_else(|| parse_flux_duration(s)) {
+                Some(d) => Ok(Value::Duration(chrono::Duration::nanoseconds(d))),
+                None => Err(RequestError::deserializing(format!(
+                    "invalid duration: {}, name: {}", s, name
+                ))),
             }
         }
         DataType::Base64Binary => {
-            let b = decode(s).unwrap();
-            Ok(Value::Base64Binary(b))
+            match decode(s) {
+                Ok(b) => Ok(Value::Base64Binary(b)),
+                Err(_) => Err(RequestError::deserializing(format!(
+                    "invalid base64Binary: {}, name: {}", s, name
+                ))),
+            }
         }
         DataType::TimeRFC => {
             let t = DateTime::parse_from_rfc3339(s).unwrap();
@@ -499,19 +613,41 @@ fn parse_value(s: &str, t: DataType, name: &str) -> Result<Value, RequestError>
     }
 }
 
+/// The JSON form of a query value
+pub(crate) fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Unknown => serde_json::Value::Null,
+        Value::String(v) => v.clone().into(),
+        Value::Double(v) => v.into_inner().into(),
+        Value::Bool(v) => (*v).into(),
+        Value::Long(v) => (*v).into(),
+        Value::UnsignedLong(v) => (*v).into(),
+        Value::Duration(v) => v.num_nanoseconds().into(),
+        Value::Base64Binary(v) => base64::encode(v).into(),
+        Value::TimeRFC(v) => v.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true).into(),
+    }
+}
+
+/// Parse the nanoseconds of a Flux duration literal with units Go doesn't
+/// know, such as `1d` or `-2w3d`
+fn parse_flux_duration(s: &str) -> Option<i64> {
+    let (sign, literal) = match s.strip_prefix('-') {
+        Some(literal) => (-1, literal),
+        None => (1, s),
+    };
+    let duration = literal.parse::<FluxDuration>().ok()?.as_duration();
+    i64::try_from(duration.as_nanos()).ok().map(|nanos| sign * nanos)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::FromDataPoint;
+    use crate::models::Profiler;
     use mockito::{mock, Matcher};
 
-    #[derive(FromDataPoint)]
+    #[derive(Default, FromDataPoint)]
     struct Empty { }
-    impl Default for Empty {
-        fn default() -> Self {
-            Self {}
-        }
-    }
 
     #[tokio::test]
     async fn query_suggestions() {
@@ -521,7 +657,7 @@ mod tests {
             .match_header("Authorization", format!("Token {}", token).as_str())
             .create();
 
-        let client = Client::new(&mockito::server_url(), "org", token);
+        let client = Client::new(mockito::server_url(), "org", token);
 
         let _result = client.query_suggestions().await;
 
@@ -544,9 +680,9 @@ mod tests {
         .match_header("Authorization", format!("Token {}", token).as_str())
         .create();
 
-        let client = Client::new(&mockito::server_url(), "org", token);
+        let client = Client::new(mockito::server_url(), "org", token);
 
-        let _result = client.query_suggestions_name(&suggestion_name).await;
+        let _result = client.query_suggestions_name(suggestion_name).await;
 
         mock_server.assert();
     }
@@ -568,7 +704,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
 
         let _result = client.query::<Empty>(query).await;
 
@@ -579,7 +715,6 @@ mod tests {
     async fn query_opt() {
         let token = "some-token";
         let org = "some-org";
-        let query: Option<Query> = None;
 
         let mock_server = mock("POST", "/api/v2/query")
             .match_header("Authorization", format!("Token {}", token).as_str())
@@ -587,19 +722,72 @@ mod tests {
             .match_header("Content-Type", "application/json")
             .match_query(Matcher::UrlEncoded("org".into(), org.into()))
             .match_body(
-                serde_json::to_string(&query.unwrap_or_default())
+                serde_json::to_string(&Query::default())
                     .unwrap()
                     .as_str(),
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
 
         let _result = client.query::<Empty>(None).await;
 
         mock_server.assert();
     }
 
+    #[tokio::test]
+    async fn query_with_options() {
+        let query = Query::new("from(bucket: params.bucket)".to_string())
+            .with_param("bucket", "telegraf")
+            .with_now(DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap())
+            .with_timeout(std::time::Duration::from_secs(5))
+            .with_profilers(&[Profiler::Query, Profiler::Operator]);
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "query": "import \"profiler\"\n\noption profiler.enabledProfilers = [\"query\", \"operator\"]\n\nfrom(bucket: params.bucket)",
+                "params": {"bucket": "telegraf"},
+                "now": "2024-01-01T00:00:00Z",
+            })))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let _result = client.query::<Empty>(Some(query)).await;
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn query_in_other_org() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::UrlEncoded("org".into(), "other-org".into()))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "some-org", "some-token");
+
+        let _result = client
+            .with_target(Target::org("other-org"))
+            .query::<Empty>(None)
+            .await;
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn query_raw_to_other_org() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::UrlEncoded("org".into(), "raw-other-org".into()))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "some-org", "some-token");
+
+        let _result = client
+            .query_raw_to(Target::org("raw-other-org").with_bucket("ignored"), None)
+            .await;
+
+        mock_server.assert();
+    }
+
     #[tokio::test]
     async fn query_analyze() {
         let token = "some-token";
@@ -614,7 +802,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), "org", token);
+        let client = Client::new(mockito::server_url(), "org", token);
 
         let _result = client.query_analyze(query).await;
 
@@ -635,7 +823,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), "org", token);
+        let client = Client::new(mockito::server_url(), "org", token);
 
         let _result = client.query_analyze(query).await;
 
@@ -657,7 +845,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), "org", token);
+        let client = Client::new(mockito::server_url(), "org", token);
 
         let _result = client.query_ast(language_request).await;
 
@@ -678,13 +866,32 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), "org", token);
+        let client = Client::new(mockito::server_url(), "org", token);
 
         let _result = client.query_ast(language_request).await;
 
         mock_server.assert();
     }
 
+    #[tokio::test]
+    async fn format_query() {
+        let mock_server = mock("POST", "/api/v2/query/ast")
+            .with_body(
+                r#"{"ast":{"type":"Package","package":"main","files":[{"type":"File",
+                    "package":null,"imports":null,"body":[{"type":"ExpressionStatement",
+                    "expression":{"type":"BinaryExpression","operator":"+",
+                    "left":{"type":"FloatLiteral","value":1.5},
+                    "right":{"type":"Identifier","name":"x"}}}]}]}}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let source = client.format_query("1.5+x").await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(source, "1.5 + x");
+    }
+
     #[test]
     fn test_query_table_result() {
         let text = "#datatype,string,long,dateTime:RFC3339,dateTime:RFC3339,dateTime:RFC3339,double,string,string,string,string
@@ -727,8 +934,7 @@ mod tests {
                 ].iter().cloned().collect(),
             },
         ];
-        let mut i = 0;
-        for item in qtr.iterator() {
+        for (i, item) in qtr.iterator().enumerate() {
             match item {
                 Ok(record) => {
                     assert_eq!(record, expected[i]);
@@ -737,8 +943,338 @@ mod tests {
                     assert_eq!(format!("{}", e), "");
                 }
             }
-            i += 1;
         }
     }
-}
 
+    #[derive(Default, FromDataPoint)]
+    struct Host {
+        host: String,
+        region: String,
+    }
+
+    #[derive(Default, FromDataPoint)]
+    struct CpuUsage {
+        #[influxdb(flatten)]
+        host: Host,
+        usage: f64,
+    }
+
+    #[test]
+    fn flattens_nested_structs() {
+        let row: GenericMap = [
+            (String::from("host"), Value::String(String::from("server01"))),
+            (String::from("region"), Value::String(String::from("us-west"))),
+            (String::from("usage"), Value::Double(OrderedFloat::from(0.5))),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        let usage = CpuUsage::from_genericmap(row);
+        assert_eq!(usage.host.host, "server01");
+        assert_eq!(usage.host.region, "us-west");
+        assert_eq!(usage.usage, 0.5);
+    }
+
+    #[derive(Default, FromDataPoint)]
+    struct Tagged {
+        #[influxdb(flatten)]
+        host: Host,
+        usage: f64,
+        #[influxdb(tags)]
+        tags: std::collections::HashMap<String, String>,
+    }
+
+    #[test]
+    fn collects_remaining_tags() {
+        let row: GenericMap = [
+            (String::from("result"), Value::String(String::from("_result"))),
+            (String::from("_measurement"), Value::String(String::from("cpu"))),
+            (String::from("host"), Value::String(String::from("server01"))),
+            (String::from("region"), Value::String(String::from("us-west"))),
+            (String::from("cpu"), Value::String(String::from("cpu-total"))),
+            (String::from("core"), Value::Long(0)),
+            (String::from("usage"), Value::Double(OrderedFloat::from(0.5))),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        let tagged = Tagged::from_genericmap(row);
+        assert_eq!(tagged.host.host, "server01");
+        assert_eq!(tagged.tags.len(), 1);
+        assert_eq!(tagged.tags["cpu"], "cpu-total");
+    }
+
+    #[derive(Default, FromDataPoint)]
+    struct Window {
+        result: String,
+        table: i64,
+        #[influxdb(rename = "_start")]
+        window_start: chrono::DateTime<chrono::FixedOffset>,
+        stop: chrono::DateTime<chrono::FixedOffset>,
+        f: f64,
+    }
+
+    #[test]
+    fn binds_window_columns() {
+        let text = "#datatype,string,long,dateTime:RFC3339,dateTime:RFC3339,dateTime:RFC3339,double,string,string
+#group,false,false,true,true,false,false,true,true
+#default,_result,,,,,,,
+,result,table,_start,_stop,_time,_value,_field,_measurement
+,,3,2020-02-17T22:19:49.747562847Z,2020-02-18T22:19:49.747562847Z,2020-02-18T10:34:08.135814545Z,1.4,f,test
+";
+        let items = QueryResult::new(QueryTableResult::new(text)).unwrap().items;
+        let window = Window::from_genericmap(items[0].clone());
+
+        assert_eq!(window.result, "_result");
+        assert_eq!(window.table, 3);
+        assert_eq!(
+            window.window_start,
+            DateTime::parse_from_rfc3339("2020-02-17T22:19:49.747562847Z").unwrap()
+        );
+        assert_eq!(
+            window.stop,
+            DateTime::parse_from_rfc3339("2020-02-18T22:19:49.747562847Z").unwrap()
+        );
+        assert_eq!(window.f, 1.4);
+    }
+
+    #[derive(Debug, Default, PartialEq, serde::Deserialize)]
+    struct Request {
+        path: String,
+        status: u16,
+    }
+
+    fn json<T: DeserializeOwned>(value: &Value) -> Result<T, String> {
+        match value {
+            Value::String(text) => serde_json::from_str(text).map_err(|e| e.to_string()),
+            _ => Err("not a string".to_string()),
+        }
+    }
+
+    #[derive(Default, FromDataPoint)]
+    struct Access {
+        #[influxdb(deserialize_with = "json")]
+        request: Request,
+        #[influxdb(rename = "request", deserialize_with = "json")]
+        raw: serde_json::Value,
+    }
+
+    #[test]
+    fn deserializes_fields_with_functions() {
+        let text = r#"#datatype,string,long,string
+#group,false,false,false
+#default,_result,,
+,result,table,_request
+,,0,"{""path"":""/health"",""status"":200}"
+"#;
+        let items = QueryResult::new(QueryTableResult::new(text)).unwrap().items;
+        let access = Access::from_genericmap(items[0].clone());
+
+        assert_eq!(
+            access.request,
+            Request {
+                path: "/health".to_string(),
+                status: 200,
+            }
+        );
+        assert_eq!(access.raw["status"], 200);
+    }
+
+    #[derive(Default, FromDataPoint)]
+    struct Encoded {
+        elapsed: std::time::Duration,
+        #[influxdb(rename = "elapsed")]
+        elapsed_nanos: i64,
+        lag: std::time::Duration,
+        payload: Vec<u8>,
+    }
+
+    #[test]
+    fn parses_durations_and_binary() {
+        let text = "#datatype,string,long,duration,duration,base64Binary
+#group,false,false,false,false,false
+#default,_result,,,,
+,result,table,elapsed,lag,payload
+,,0,1d2h,1m30s,aGVsbG8=
+";
+        let items = QueryResult::new(QueryTableResult::new(text)).unwrap().items;
+        let encoded = Encoded::from_genericmap(items[0].clone());
+
+        assert_eq!(encoded.elapsed, std::time::Duration::from_secs(26 * 3600));
+        assert_eq!(encoded.elapsed_nanos, 26 * 3600 * 1_000_000_000);
+        assert_eq!(encoded.lag, std::time::Duration::from_secs(90));
+        assert_eq!(
+            parse_value("-2w", DataType::Duration, "d").unwrap(),
+            Value::Duration(chrono::Duration::weeks(-2))
+        );
+        assert_eq!(encoded.payload, b"hello");
+
+        assert!(parse_value("1x", DataType::Duration, "d").is_err());
+        assert!(parse_value("not base64!", DataType::Base64Binary, "b").is_err());
+    }
+
+    #[derive(Debug, Default, FromDataPoint)]
+    struct Lag {
+        lag: std::time::Duration,
+    }
+
+    #[derive(Debug, Default, FromDataPoint)]
+    struct LagNanos {
+        lag: i64,
+    }
+
+    #[test]
+    fn refuses_durations_out_of_range() {
+        let text = "#datatype,string,long,duration
+#group,false,false,false
+#default,_result,,
+,result,table,lag
+,,0,-1m30s
+";
+        let items = QueryResult::new(QueryTableResult::new(text)).unwrap().items;
+
+        assert_eq!(
+            Lag::try_from_genericmap(items[0].clone()).unwrap_err(),
+            influxdb2_structmap::FromMapError::OutOfRange {
+                key: "lag".into(),
+                value: chrono::Duration::seconds(-90).to_string(),
+                ty: "Duration",
+            }
+        );
+        assert_eq!(
+            LagNanos::try_from_genericmap(items[0].clone()).unwrap().lag,
+            -90_000_000_000
+        );
+        let mut beyond_nanos = GenericMap::new();
+        beyond_nanos.insert("lag".into(), Value::Duration(chrono::Duration::max_value()));
+        assert!(matches!(
+            LagNanos::try_from_genericmap(beyond_nanos),
+            Err(influxdb2_structmap::FromMapError::OutOfRange { ty: "i64", .. })
+        ));
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Row {
+        _time: String,
+        _value: f64,
+        host: String,
+    }
+
+    #[tokio::test]
+    async fn query_json() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_header("Accept", "application/json")
+            .match_query(Matcher::UrlEncoded("org".into(), "org".into()))
+            .with_header("Content-Type", "application/json")
+            .with_body(r#"[{"_time":"2020-02-18T10:34:08Z","_value":1.5,"host":"a"}]"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let rows: Vec<Row> = client.query_json(None).await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(
+            rows,
+            [Row {
+                _time: "2020-02-18T10:34:08Z".to_string(),
+                _value: 1.5,
+                host: "a".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn query_json_from_csv() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .with_header("Content-Type", "text/csv; charset=utf-8")
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,double,string,string,string
+#group,false,false,false,false,true,true,true
+#default,_result,,,,,,
+,result,table,_time,_value,_field,_measurement,host
+,,0,2020-02-18T10:34:08Z,1.5,usage,cpu,a
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let rows: Vec<Row> = client.query_json(None).await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]._time, "2020-02-18T10:34:08Z");
+        assert_eq!(rows[0]._value, 1.5);
+        assert_eq!(rows[0].host, "a");
+    }
+
+    #[derive(Default, FromDataPoint)]
+    struct Counters {
+        total: u64,
+        count: u64,
+        small: i64,
+    }
+
+    #[derive(Debug, Default, FromDataPoint)]
+    struct SignedTotal {
+        total: i64,
+    }
+
+    const COUNTERS: &str = "#datatype,string,long,unsignedLong,long,unsignedLong
+#group,false,false,false,false,false
+#default,_result,,,,
+,result,table,total,count,small
+,,0,18446744073709551615,42,7
+";
+
+    #[test]
+    fn reads_unsigned_longs() {
+        let items = QueryResult::new(QueryTableResult::new(COUNTERS)).unwrap().items;
+        let counters = Counters::from_genericmap(items[0].clone());
+
+        assert_eq!(counters.total, u64::MAX);
+        assert_eq!(counters.count, 42);
+        assert_eq!(counters.small, 7);
+
+        assert!(parse_value("18446744073709551616", DataType::UnsignedLong, "u").is_err());
+        assert!(parse_value("9223372036854775808", DataType::Long, "l").is_err());
+    }
+
+    #[test]
+    fn refuses_to_wrap_unsigned_longs() {
+        let items = QueryResult::new(QueryTableResult::new(COUNTERS)).unwrap().items;
+        let err = SignedTotal::try_from_genericmap(items[0].clone()).unwrap_err();
+
+        assert_eq!(
+            err,
+            influxdb2_structmap::FromMapError::OutOfRange {
+                key: "total".into(),
+                value: "18446744073709551615".into(),
+                ty: "i64",
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn query_fails_on_values_out_of_range() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex("signed-total".to_string()))
+            .with_header("Content-Type", "text/csv; charset=utf-8")
+            .with_body(COUNTERS)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let err = client
+            .query::<SignedTotal>(Some(Query::new("signed-total".to_string())))
+            .await
+            .unwrap_err();
+
+        mock_server.assert();
+        assert!(
+            matches!(err, RequestError::Deserializing { ref text, .. } if text.contains("out of range for i64, key: total"))
+        );
+    }
+}
@@ -3,27 +3,18 @@
 //! Check readiness of an InfluxDB instance at startup
 
 use reqwest::{Method, StatusCode};
-use snafu::ResultExt;
 
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu};
+use crate::{Client, RequestError};
 
 impl Client {
     /// Get the readiness of an instance at startup
     pub async fn ready(&self) -> Result<bool, RequestError> {
-        let ready_url = format!("{}/ready", self.url);
-        let response = self
-            .request(Method::GET, &ready_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let ready_url = self.endpoint_url("/ready");
+        let response = self.send(self.request(Method::GET, &ready_url)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(true),
-            _ => {
-                let status = response.status();
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            _ => Err(response.error().await),
         }
     }
 }
@@ -37,7 +28,7 @@ mod tests {
     async fn ready() {
         let mock_server = mock("GET", "/ready").create();
 
-        let client = Client::new(&mockito::server_url(), "org", "");
+        let client = Client::new(mockito::server_url(), "org", "");
 
         let _result = client.ready().await;
 
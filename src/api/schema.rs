@@ -0,0 +1,264 @@
+//! Schema exploration
+//!
+//! Series cardinality and tag value counts, for capacity tooling watching
+//! cardinality grow. Each call looks at the data of the last 30 days, the
+//! default range of the Flux `schema` functions:
+//!
+//! ```no_run
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let series = client.cardinality("telegraf", "cpu").await?;
+//! let hosts = client.tag_value_count("telegraf", "cpu", "host").await?;
+//! println!("{} series over {} hosts", series, hosts);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `Client::bucket_report` gathers the numbers of a bucket a capacity
+//! dashboard shows into one [`BucketReport`].
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset};
+use influxdb2_structmap::value::Value;
+
+use crate::models::template::flux_string;
+use crate::models::{FluxDuration, Query};
+use crate::{Client, RequestError};
+
+/// The range the schema calls look at
+pub const SCHEMA_RANGE: FluxDuration = FluxDuration::from_secs(30 * 86_400);
+
+/// The size of a bucket
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketReport {
+    /// The name of the bucket
+    pub bucket: String,
+    /// The estimated number of series
+    pub series: i64,
+    /// The estimated number of series of each measurement
+    pub measurements: BTreeMap<String, i64>,
+    /// The time of the oldest point, `None` if the bucket is empty
+    pub oldest: Option<DateTime<FixedOffset>>,
+    /// The time of the newest point, `None` if the bucket is empty
+    pub newest: Option<DateTime<FixedOffset>>,
+}
+
+impl Client {
+    /// Report the series of `bucket`, in all and per measurement, over
+    /// [`SCHEMA_RANGE`], and the times of its oldest and newest points.
+    ///
+    /// It runs one query for each measurement, besides four others.
+    pub async fn bucket_report(&self, bucket: &str) -> Result<BucketReport, RequestError> {
+        let series = self.bucket_cardinality(bucket).await?;
+
+        let flux = format!(
+            "import \"influxdata/influxdb/schema\"\n\
+             schema.measurements(bucket: {}, start: -{})",
+            flux_string(bucket),
+            SCHEMA_RANGE,
+        );
+        let mut measurements = BTreeMap::new();
+        for row in self.query_records(Query::new(flux)).await? {
+            if let Some(Value::String(measurement)) = row.get("_value") {
+                let series = self.cardinality(bucket, measurement).await?;
+                measurements.insert(measurement.clone(), series);
+            }
+        }
+
+        Ok(BucketReport {
+            bucket: bucket.to_string(),
+            series,
+            measurements,
+            oldest: self.edge_time(bucket, "first", "min").await?,
+            newest: self.edge_time(bucket, "last", "max").await?,
+        })
+    }
+
+    /// Estimate the number of series of `measurement` in `bucket`.
+    pub async fn cardinality(&self, bucket: &str, measurement: &str) -> Result<i64, RequestError> {
+        let flux = format!(
+            "import \"influxdata/influxdb\"\n\
+             influxdb.cardinality(bucket: {}, start: -{}, predicate: (r) => r._measurement == {})",
+            flux_string(bucket),
+            SCHEMA_RANGE,
+            flux_string(measurement),
+        );
+        self.count(flux).await
+    }
+
+    /// Estimate the number of series in `bucket`.
+    pub async fn bucket_cardinality(&self, bucket: &str) -> Result<i64, RequestError> {
+        let flux = format!(
+            "import \"influxdata/influxdb\"\n\
+             influxdb.cardinality(bucket: {}, start: -{})",
+            flux_string(bucket),
+            SCHEMA_RANGE,
+        );
+        self.count(flux).await
+    }
+
+    /// Count the distinct values of the tag `tag` of `measurement` in
+    /// `bucket`.
+    pub async fn tag_value_count(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        tag: &str,
+    ) -> Result<i64, RequestError> {
+        let flux = format!(
+            "import \"influxdata/influxdb/schema\"\n\
+             schema.measurementTagValues(bucket: {}, measurement: {}, tag: {}, start: -{})\n  \
+             |> count()",
+            flux_string(bucket),
+            flux_string(measurement),
+            flux_string(tag),
+            SCHEMA_RANGE,
+        );
+        self.count(flux).await
+    }
+
+    /// The time of the oldest or newest point of `bucket`, picking a point
+    /// of each series with `selector` and their earliest or latest time with
+    /// `edge`
+    async fn edge_time(
+        &self,
+        bucket: &str,
+        selector: &str,
+        edge: &str,
+    ) -> Result<Option<DateTime<FixedOffset>>, RequestError> {
+        let flux = format!(
+            "from(bucket: {})\n  \
+             |> range(start: 0)\n  \
+             |> {}()\n  \
+             |> group()\n  \
+             |> {}(column: \"_time\")",
+            flux_string(bucket),
+            selector,
+            edge,
+        );
+        let rows = self.query_records(Query::new(flux)).await?;
+        Ok(rows.iter().find_map(|row| match row.get("_time") {
+            Some(Value::TimeRFC(time)) => Some(*time),
+            _ => None,
+        }))
+    }
+
+    /// Run `flux` and add up the `_value` of its rows, one per table
+    async fn count(&self, flux: String) -> Result<i64, RequestError> {
+        let rows = self.query_records(Query::new(flux)).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| match row.get("_value") {
+                Some(Value::Long(count)) => Some(*count),
+                _ => None,
+            })
+            .sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    const COUNT: &str = "#datatype,string,long,long
+#group,false,false,false
+#default,_result,,
+,result,table,_value
+,,0,42
+";
+
+    #[tokio::test]
+    async fn cardinality() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(
+                r#"influxdb.cardinality\(bucket: \\"b\\", start: -30d, predicate: \(r\) => r._measurement == \\"cpu\\"\)"#
+                    .to_string(),
+            ))
+            .with_body(COUNT)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let series = client.cardinality("b", "cpu").await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(series, 42);
+    }
+
+    #[tokio::test]
+    async fn tag_value_count() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(
+                r#"schema.measurementTagValues\(bucket: \\"b\\", measurement: \\"cpu\\", tag: \\"host\\", start: -30d\)"#
+                    .to_string(),
+            ))
+            .with_body(COUNT)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let hosts = client.tag_value_count("b", "cpu", "host").await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(hosts, 42);
+    }
+
+    #[tokio::test]
+    async fn bucket_report() {
+        let count = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex("influxdb.cardinality".to_string()))
+            .with_body(COUNT)
+            .expect(3)
+            .create();
+        let measurements = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex("schema.measurements".to_string()))
+            .with_body(
+                "#datatype,string,long,string
+#group,false,false,false
+#default,_result,,
+,result,table,_value
+,,0,cpu
+,,0,mem
+",
+            )
+            .create();
+        let time = |edge: &str, time: &str| {
+            mock("POST", "/api/v2/query")
+                .match_query(Matcher::Any)
+                .match_body(Matcher::Regex(format!(r#"{}\(column: \\"_time\\"\)"#, edge)))
+                .with_body(format!(
+                    "#datatype,string,long,dateTime:RFC3339,double
+#group,false,false,false,false
+#default,_result,,,
+,result,table,_time,_value
+,,0,{},1
+",
+                    time
+                ))
+                .create()
+        };
+        let oldest = time("min", "2039-01-01T00:00:00Z");
+        let newest = time("max", "2040-01-01T00:00:00Z");
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let report = client.bucket_report("b").await.unwrap();
+
+        for mock in [count, measurements, oldest, newest] {
+            mock.assert();
+        }
+        assert_eq!(report.series, 42);
+        assert_eq!(report.measurements.len(), 2);
+        assert_eq!(report.measurements["mem"], 42);
+        assert_eq!(
+            report.oldest,
+            Some(DateTime::parse_from_rfc3339("2039-01-01T00:00:00Z").unwrap())
+        );
+        assert_eq!(
+            report.newest,
+            Some(DateTime::parse_from_rfc3339("2040-01-01T00:00:00Z").unwrap())
+        );
+    }
+}
@@ -2,32 +2,21 @@
 //!
 //! Initate and start onboarding process of InfluxDB server.
 
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu, SerializingSnafu};
+use crate::{Client, RequestError, SerializingSnafu};
 use reqwest::{Method, StatusCode};
 use snafu::ResultExt;
 
-use crate::models::{IsOnboarding, OnboardingRequest, OnboardingResponse};
+use crate::models::{FluxDuration, IsOnboarding, OnboardingRequest, OnboardingResponse};
 
 impl Client {
     /// Check if database has default user, org, bucket
     pub async fn is_onboarding_allowed(&self) -> Result<bool, RequestError> {
-        let setup_url = format!("{}/api/v2/setup", self.url);
-        let response = self
-            .request(Method::GET, &setup_url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let setup_url = self.endpoint_url("/api/v2/setup");
+        let response = self.send(self.request(Method::GET, &setup_url)).await?;
 
         match response.status() {
-            StatusCode::OK => Ok(response
-                .json::<IsOnboarding>()
-                .await
-                .context(ReqwestProcessingSnafu)?
-                .allowed),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::OK => Ok(response.json::<IsOnboarding>().await?.allowed),
+            _ => Err(response.error().await),
         }
     }
 
@@ -39,9 +28,9 @@ impl Client {
         bucket: &str,
         password: Option<String>,
         retention_period_hrs: Option<i32>,
-        retention_period_seconds: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
     ) -> Result<OnboardingResponse, RequestError> {
-        let setup_init_url = format!("{}/api/v2/setup", self.url);
+        let setup_init_url = self.endpoint_url("/api/v2/setup");
 
         let body = OnboardingRequest {
             username: username.into(),
@@ -53,21 +42,15 @@ impl Client {
         };
 
         let response = self
-            .request(Method::POST, &setup_init_url)
-            .body(serde_json::to_string(&body).context(SerializingSnafu)?)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request(Method::POST, &setup_init_url)
+                    .body(serde_json::to_string(&body).context(SerializingSnafu)?),
+            )
+            .await?;
 
         match response.status() {
-            StatusCode::CREATED => Ok(response
-                .json::<OnboardingResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::CREATED => response.json::<OnboardingResponse>().await,
+            _ => Err(response.error().await),
         }
     }
 
@@ -79,9 +62,9 @@ impl Client {
         bucket: &str,
         password: Option<String>,
         retention_period_hrs: Option<i32>,
-        retention_period_seconds: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
     ) -> Result<OnboardingResponse, RequestError> {
-        let setup_new_url = format!("{}/api/v2/setup/user", self.url);
+        let setup_new_url = self.endpoint_url("/api/v2/setup/user");
 
         let body = OnboardingRequest {
             username: username.into(),
@@ -93,21 +76,15 @@ impl Client {
         };
 
         let response = self
-            .request(Method::POST, &setup_new_url)
-            .body(serde_json::to_string(&body).context(SerializingSnafu)?)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request(Method::POST, &setup_new_url)
+                    .body(serde_json::to_string(&body).context(SerializingSnafu)?),
+            )
+            .await?;
 
         match response.status() {
-            StatusCode::CREATED => Ok(response
-                .json::<OnboardingResponse>()
-                .await
-                .context(ReqwestProcessingSnafu)?),
-            status => {
-                let text = response.text().await.context(ReqwestProcessingSnafu)?;
-                HttpSnafu { status, text }.fail()?
-            }
+            StatusCode::CREATED => response.json::<OnboardingResponse>().await,
+            _ => Err(response.error().await),
         }
     }
 }
@@ -121,7 +98,7 @@ mod tests {
     async fn is_onboarding_allowed() {
         let mock_server = mock("GET", "/api/v2/setup").create();
 
-        let client = Client::new(&mockito::server_url(), "org", "");
+        let client = Client::new(mockito::server_url(), "org", "");
 
         let _result = client.is_onboarding_allowed().await;
 
@@ -146,7 +123,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
 
         let _result = client
             .onboarding(
@@ -181,7 +158,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
 
         let _result = client
             .post_setup_user(
@@ -213,7 +190,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org, "");
+        let client = Client::new(mockito::server_url(), org, "");
 
         let _result = client
             .onboarding(username, org, bucket, None, None, None)
@@ -240,7 +217,7 @@ mod tests {
             )
             .create();
 
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
 
         let _result = client
             .post_setup_user(username, org, bucket, None, None, None)
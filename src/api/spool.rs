@@ -0,0 +1,361 @@
+//! Spooled queries
+//!
+//! Exports of millions of rows outrun the code consuming them, e.g. while it
+//! uploads each batch somewhere. `Client::query_spooled` reads the response
+//! as fast as the server sends it and keeps the rows not consumed yet in
+//! memory up to a limit, then in a temporary file, so memory stays bounded
+//! however far the consumer lags and the server isn't kept waiting. Requires
+//! the `spool` feature and a tokio runtime.
+//!
+//! ```no_run
+//! use futures::TryStreamExt;
+//! use influxdb2::models::Query;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let query = Query::new(r#"from(bucket: "telegraf") |> range(start: -30d)"#.to_string());
+//! let rows = client.query_spooled(Some(query), 100_000).await?;
+//! futures::pin_mut!(rows);
+//! while let Some(row) = rows.try_next().await? {
+//!     // A slow consumer, such as an upload
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use fallible_iterator::FallibleIterator;
+use futures::stream::{self, Stream};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+use parking_lot::Mutex;
+use reqwest::StatusCode;
+use tempfile::NamedTempFile;
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+
+use crate::api::annotated_csv::{cell, data_type};
+use crate::api::query::{parse_value, DataType, QueryTableResult};
+use crate::models::Query;
+use crate::request::Response;
+use crate::{Client, RequestError, Target};
+
+impl Client {
+    /// Query, yielding every record of the response as it is, like the rows
+    /// of its tables, rather than merging the `_field` and `_value` columns
+    /// of a series into one row as `query_raw` does.
+    ///
+    /// The response is read and parsed on a blocking thread, whatever the
+    /// pace of the stream. Up to `memory_rows` unconsumed records are kept in
+    /// memory and the following ones in a temporary file, until the stream
+    /// catches up. Dropping the stream stops reading the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub async fn query_spooled(
+        &self,
+        query: Option<Query>,
+        memory_rows: usize,
+    ) -> Result<impl Stream<Item = Result<GenericMap, RequestError>>, RequestError> {
+        let response = self.post_query(&Target::new(), query, "text/csv").await?;
+        if response.status() != StatusCode::OK {
+            return Err(response.error().await);
+        }
+
+        let spool = Arc::new(Spool {
+            state: Mutex::new(SpoolState {
+                memory_rows,
+                ..SpoolState::default()
+            }),
+            ready: Notify::new(),
+        });
+        let producer = Arc::clone(&spool);
+        let handle = Handle::current();
+        tokio::task::spawn_blocking(move || producer.fill(response, handle));
+
+        let consumer = Consumer(spool);
+        Ok(stream::unfold(consumer, |consumer| async move {
+            loop {
+                match consumer.0.next() {
+                    Next::Row(row) => return Some((row, consumer)),
+                    Next::End => return None,
+                    Next::Wait => consumer.0.ready.notified().await,
+                }
+            }
+        }))
+    }
+}
+
+/// The records read but not consumed yet
+#[derive(Debug)]
+struct Spool {
+    state: Mutex<SpoolState>,
+    /// Notified whenever a record is spooled or the response ends
+    ready: Notify,
+}
+
+#[derive(Debug, Default)]
+struct SpoolState {
+    memory_rows: usize,
+    memory: VecDeque<GenericMap>,
+    /// The records spooled after the memory filled up, all newer than the
+    /// records in memory
+    file: Option<SpoolFile>,
+    /// How the response ended, once it did, until the stream yields it
+    end: Option<Result<(), RequestError>>,
+    /// Whether the stream was dropped
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct SpoolFile {
+    /// Deletes the file when dropped
+    _file: NamedTempFile,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    unread: usize,
+}
+
+enum Next {
+    Row(Result<GenericMap, RequestError>),
+    Wait,
+    End,
+}
+
+/// The consuming end of a spool, closing it when dropped
+struct Consumer(Arc<Spool>);
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        self.0.state.lock().closed = true;
+    }
+}
+
+impl Spool {
+    /// Parse the records of `response` into the spool, until it ends or the
+    /// stream is dropped
+    fn fill(&self, response: Response, handle: Handle) {
+        let error = Arc::new(Mutex::new(None));
+        let body = BodyReader {
+            response,
+            handle,
+            chunk: Bytes::new(),
+            error: Arc::clone(&error),
+        };
+
+        let mut records = QueryTableResult::from_reader(body);
+        let end = loop {
+            match records.next() {
+                Ok(Some(record)) => match self.push(record.values) {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(e) => break Err(e),
+                },
+                Ok(None) => break Ok(()),
+                // An error of the response itself beats the CSV error it
+                // turned into
+                Err(e) => break Err(error.lock().take().unwrap_or(e)),
+            }
+        };
+        self.state.lock().end = Some(end);
+        self.ready.notify_one();
+    }
+
+    /// Spool `row`, returning whether the stream still wants rows
+    fn push(&self, row: GenericMap) -> Result<bool, RequestError> {
+        let mut state = self.state.lock();
+        if state.closed {
+            return Ok(false);
+        }
+        if state.file.is_none() && state.memory.len() < state.memory_rows {
+            state.memory.push_back(row);
+        } else {
+            let file = match &mut state.file {
+                Some(file) => file,
+                None => state.file.insert(SpoolFile::new().map_err(spooling)?),
+            };
+            file.write(&row).map_err(spooling)?;
+        }
+        drop(state);
+        self.ready.notify_one();
+        Ok(true)
+    }
+
+    fn next(&self) -> Next {
+        let mut state = self.state.lock();
+        if let Some(row) = state.memory.pop_front() {
+            return Next::Row(Ok(row));
+        }
+        if let Some(file) = &mut state.file {
+            let row = file.read().map_err(spooling);
+            if file.unread == 0 {
+                state.file = None;
+            }
+            return Next::Row(row);
+        }
+        match state.end.take() {
+            Some(Err(e)) => {
+                state.end = Some(Ok(()));
+                Next::Row(Err(e))
+            }
+            Some(Ok(())) => {
+                state.end = Some(Ok(()));
+                Next::End
+            }
+            None => Next::Wait,
+        }
+    }
+}
+
+impl SpoolFile {
+    fn new() -> io::Result<Self> {
+        let file = NamedTempFile::new()?;
+        Ok(Self {
+            writer: BufWriter::new(file.reopen()?),
+            reader: BufReader::new(file.reopen()?),
+            _file: file,
+            unread: 0,
+        })
+    }
+
+    /// Append `row` as a line of JSON mapping each column to its datatype
+    /// and annotated CSV text, or to nothing if it has no value
+    fn write(&mut self, row: &GenericMap) -> io::Result<()> {
+        let encoded: BTreeMap<_, _> = row
+            .iter()
+            .map(|(column, value)| {
+                let encoded = match data_type(value) {
+                    Some(data_type) => vec![data_type.to_string(), cell(value)],
+                    None => Vec::new(),
+                };
+                (column, encoded)
+            })
+            .collect();
+        serde_json::to_writer(&mut self.writer, &encoded)?;
+        self.writer.write_all(b"\n")?;
+        self.unread += 1;
+        Ok(())
+    }
+
+    /// Read the oldest row written and not read yet
+    fn read(&mut self) -> io::Result<GenericMap> {
+        self.writer.flush()?;
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        self.unread -= 1;
+
+        let encoded: BTreeMap<String, Vec<String>> = serde_json::from_str(&line)?;
+        encoded
+            .into_iter()
+            .map(|(column, encoded)| {
+                let value = match encoded.as_slice() {
+                    [data_type, text] => DataType::from_str(data_type)
+                        .and_then(|data_type| parse_value(text, data_type, &column))
+                        .map_err(io::Error::other)?,
+                    _ => Value::Unknown,
+                };
+                Ok((column, value))
+            })
+            .collect()
+    }
+}
+
+fn spooling(source: io::Error) -> RequestError {
+    RequestError::Spooling { source }
+}
+
+/// The body of a response as a blocking reader
+struct BodyReader {
+    response: Response,
+    handle: Handle,
+    chunk: Bytes,
+    /// The error of the response, if reading it failed
+    error: Arc<Mutex<Option<RequestError>>>,
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.handle.block_on(self.response.chunk()) {
+                Ok(Some(chunk)) => self.chunk = chunk,
+                Ok(None) => return Ok(0),
+                Err(e) => {
+                    let message = e.to_string();
+                    *self.error.lock() = Some(e);
+                    return Err(io::Error::other(message));
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk.split_to(n));
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn spools_rows_past_the_memory_limit() {
+        let mut csv = "#datatype,string,long,dateTime:RFC3339,string,double,duration
+#group,false,false,false,true,false,false
+#default,_result,,,,,
+,result,table,_time,_measurement,_value,took
+"
+        .to_string();
+        for i in 0..50 {
+            csv.push_str(&format!(
+                ",,0,2040-01-01T00:00:{:02}Z,spooled,{}.5,{}s\n",
+                i, i, i
+            ));
+        }
+        let _query = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex("spooled".to_string()))
+            .with_body(&csv)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let query = Query::new("from(bucket: \"spooled\")".to_string());
+        let rows = client.query_spooled(Some(query), 4).await.unwrap();
+        futures::pin_mut!(rows);
+
+        // Let the whole response be read before consuming anything
+        let first = rows.try_next().await.unwrap().unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let rest: Vec<_> = rows.try_collect().await.unwrap();
+
+        assert_eq!(first["_value"], Value::Double(0.5.into()));
+        assert_eq!(rest.len(), 49);
+        for (i, row) in rest.iter().enumerate() {
+            let i = i as i64 + 1;
+            assert_eq!(row["_value"], Value::Double((i as f64 + 0.5).into()));
+            assert_eq!(row["took"], Value::Duration(chrono::Duration::seconds(i)));
+        }
+    }
+
+    #[test]
+    fn round_trips_rows_through_the_file() {
+        let mut row = GenericMap::new();
+        row.insert("blob".to_string(), Value::Base64Binary(vec![0, 1, 2]));
+        row.insert("n".to_string(), Value::UnsignedLong(u64::MAX));
+        row.insert("none".to_string(), Value::Unknown);
+        row.insert("text".to_string(), Value::String("a,\"b\"\nc".to_string()));
+
+        let mut file = SpoolFile::new().unwrap();
+        file.write(&row).unwrap();
+        file.write(&GenericMap::new()).unwrap();
+        assert_eq!(file.read().unwrap(), row);
+        assert_eq!(file.read().unwrap(), GenericMap::new());
+        assert_eq!(file.unread, 0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Live tail
+//!
+//! Follow the points written to a bucket by querying it again and again from
+//! the time of the last point seen, yielding only the rows that weren't
+//! yielded before. Requires the `live-tail` feature and a tokio runtime.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use futures::TryStreamExt;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let rows = client.tail("telegraf", r#"r._measurement == "cpu""#, Duration::from_secs(5));
+//! futures::pin_mut!(rows);
+//! while let Some(row) = rows.try_next().await? {
+//!     println!("{:?}", row.get("_value"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use futures::stream::{self, Stream};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::models::template::flux_string;
+use crate::models::Query;
+use crate::{Client, RequestError};
+
+/// What a tail remembers between polls
+struct Tail {
+    client: Client,
+    bucket: String,
+    filter: String,
+    ticks: Interval,
+    /// The time of the latest row yielded, where the next poll starts
+    last_seen: DateTime<FixedOffset>,
+    /// The rows yielded at `last_seen`, which the next poll returns again
+    boundary: HashSet<GenericMap>,
+    pending: VecDeque<GenericMap>,
+}
+
+impl Client {
+    /// Query `bucket` every `poll_interval` while the stream is polled,
+    /// yielding the rows matching `filter`, the body of a Flux predicate on
+    /// `r`, that were written since the stream was created.
+    ///
+    /// Each poll starts at the time of the latest row seen, so the rows of
+    /// that time are returned again and skipped. Rows written with a time
+    /// older than that are missed. The rows of a poll are yielded by time. A
+    /// poll that fails yields its error, and the stream carries on polling.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn tail(
+        &self,
+        bucket: &str,
+        filter: &str,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<GenericMap, RequestError>> {
+        let mut ticks = tokio::time::interval(poll_interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let tail = Tail {
+            client: self.clone(),
+            bucket: flux_string(bucket),
+            filter: filter.to_string(),
+            ticks,
+            last_seen: self.clock.now().into(),
+            boundary: HashSet::new(),
+            pending: VecDeque::new(),
+        };
+
+        stream::unfold(tail, |mut tail| async move {
+            loop {
+                if let Some(row) = tail.pending.pop_front() {
+                    return Some((Ok(row), tail));
+                }
+
+                tail.ticks.tick().await;
+                let flux = format!(
+                    "from(bucket: {}) |> range(start: {}) |> filter(fn: (r) => {})",
+                    tail.bucket,
+                    tail.last_seen.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                    tail.filter,
+                );
+                match tail.client.query_records(Query::new(flux)).await {
+                    Ok(rows) => tail.push(rows),
+                    Err(e) => return Some((Err(e), tail)),
+                }
+            }
+        })
+    }
+}
+
+impl Tail {
+    /// Queue the rows of a poll that weren't yielded yet.
+    fn push(&mut self, mut rows: Vec<GenericMap>) {
+        rows.sort_by_key(time);
+        for row in rows {
+            let time = match time(&row) {
+                Some(time) if time >= self.last_seen => time,
+                _ => continue,
+            };
+            let key = key(&row);
+            if time > self.last_seen {
+                self.last_seen = time;
+                self.boundary.clear();
+            } else if self.boundary.contains(&key) {
+                continue;
+            }
+            self.boundary.insert(key);
+            self.pending.push_back(row);
+        }
+    }
+}
+
+fn time(row: &GenericMap) -> Option<DateTime<FixedOffset>> {
+    match row.get("_time") {
+        Some(Value::TimeRFC(time)) => Some(*time),
+        _ => None,
+    }
+}
+
+/// A row without the columns that differ between the responses of two polls
+fn key(row: &GenericMap) -> GenericMap {
+    let mut key = row.clone();
+    key.remove("result");
+    key.remove("table");
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use mockito::{mock, Matcher};
+
+    fn csv(rows: &[(&str, i64)]) -> String {
+        let mut csv = "#datatype,string,long,dateTime:RFC3339,long
+#group,false,false,false,false
+#default,_result,,,
+,result,table,_time,_value
+"
+        .to_string();
+        for (time, value) in rows {
+            csv.push_str(&format!(",,0,{},{}\n", time, value));
+        }
+        csv
+    }
+
+    #[tokio::test]
+    async fn skips_rows_seen_at_the_boundary() {
+        let first = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(
+                r#"from\(bucket: \\"tailed\\"\)"#.to_string(),
+            ))
+            .with_body(csv(&[
+                ("2040-01-01T00:00:02Z", 2),
+                ("2040-01-01T00:00:01Z", 1),
+            ]))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let rows = client.tail("tailed", "true", Duration::from_millis(10));
+        futures::pin_mut!(rows);
+        let value = |row: GenericMap| row["_value"].clone();
+
+        assert_eq!(
+            value(rows.try_next().await.unwrap().unwrap()),
+            Value::Long(1)
+        );
+        assert_eq!(
+            value(rows.try_next().await.unwrap().unwrap()),
+            Value::Long(2)
+        );
+        drop(first);
+
+        let _second = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(
+                r"range\(start: 2040-01-01T00:00:02Z\)".to_string(),
+            ))
+            .with_body(csv(&[
+                ("2040-01-01T00:00:02Z", 2),
+                ("2040-01-01T00:00:02Z", 3),
+                ("2040-01-01T00:00:04Z", 4),
+            ]))
+            .create();
+        assert_eq!(
+            value(rows.try_next().await.unwrap().unwrap()),
+            Value::Long(3)
+        );
+        assert_eq!(
+            value(rows.try_next().await.unwrap().unwrap()),
+            Value::Long(4)
+        );
+    }
+}
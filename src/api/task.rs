@@ -1,11 +1,19 @@
 //! Tasks API
 
+use chrono::{DateTime, Utc};
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu, SerializingSnafu};
-use crate::models::{Tasks, TaskStatusType};
+use crate::{
+    Client, InvalidContinuousQuerySnafu, InvalidScheduleSnafu, RequestError, SerializingSnafu,
+    Target,
+};
+use crate::models::schedule::validate_task_options;
+use crate::models::template::flux_string;
+use crate::models::{
+    ContinuousQuery, Logs, OrgId, Run, Runs, Task, TaskId, Tasks, TaskStatusType, UserId,
+};
 
 impl Client {
     /// List all tasks.
@@ -15,70 +23,376 @@ impl Client {
     ) -> Result<Tasks, RequestError> {
         let qs = serde_qs::to_string(&request).unwrap();
         let url = match &qs[..] {
-            "" => format!("{}/api/v2/tasks", self.url),
-            _  => format!("{}/api/v2/tasks?{}", self.url, qs),
+            "" => self.endpoint_url("/api/v2/tasks"),
+            _  => self.endpoint_url(&format!("/api/v2/tasks?{}", qs)),
         };
 
-        let response = self
-            .request(Method::GET, &url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let response = self.send(self.request(Method::GET, &url)).await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            let res = HttpSnafu { status, text }.fail();
-            return res;
+            return Err(response.error().await);
         }
 
-        let res = response
-            .json::<Tasks>()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+        let res = response.json::<Tasks>().await?;
         Ok(res)
     }
 
+    /// List the tasks of the organization of `target` matching `request`,
+    /// unless `request` names an organization itself.
+    pub async fn list_tasks_to(
+        &self,
+        target: impl Into<Target>,
+        mut request: ListTasksRequest,
+    ) -> Result<Tasks, RequestError> {
+        if request.org.is_none() && request.org_id.is_none() {
+            let target = target.into();
+            request.org = Some(self.resolve_org(&target).to_string());
+        }
+        self.list_tasks(request).await
+    }
+
     /// Create a new task.
+    ///
+    /// The `every`, `offset` and `cron` options of the script are checked
+    /// before the task is sent, failing with `RequestError::InvalidSchedule`.
     pub async fn create_task(
         &self,
         request: CreateTaskRequest,
     ) -> Result<(), RequestError> {
-        let url = format!("{}/api/v2/tasks", self.url);
+        validate_task_options(&request.flux).context(InvalidScheduleSnafu)?;
+
+        let url = self.endpoint_url("/api/v2/tasks");
         let response = self
-            .request(Method::POST, &url)
-            .body(
-                serde_json::to_string(&request)
-                    .context(SerializingSnafu)?,
+            .send(
+                self.request(Method::POST, &url)
+                    .body(
+                        serde_json::to_string(&request)
+                            .context(SerializingSnafu)?,
+                    ),
             )
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            HttpSnafu { status, text }.fail()?;
+            return Err(response.error().await);
         }
 
         Ok(())
     }
 
-    /// Delete a task specified by task_id.
-    pub async fn delete_task(&self, task_id: &str) -> Result<(), RequestError> {
-        let url = format!("{}/api/v2/tasks/{}", self.url, task_id);
+    /// Update the task specified by task_id, returning it as updated.
+    ///
+    /// A new script is checked like in `create_task`.
+    pub async fn update_task(
+        &self,
+        task_id: &TaskId,
+        request: &UpdateTaskRequest,
+    ) -> Result<Task, RequestError> {
+        if let Some(flux) = &request.flux {
+            validate_task_options(flux).context(InvalidScheduleSnafu)?;
+        }
+
+        let url = self.endpoint_url(&format!("/api/v2/tasks/{}", task_id));
         let response = self
-            .request(Method::DELETE, &url)
-            .send()
-            .await
-            .context(ReqwestProcessingSnafu)?;
+            .send(
+                self.request(Method::PATCH, &url)
+                    .body(serde_json::to_string(request).context(SerializingSnafu)?),
+            )
+            .await?;
+
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            HttpSnafu { status, text }.fail()?;
+            return Err(response.error().await);
+        }
+
+        response.json::<Task>().await
+    }
+
+    /// Resume the scheduled runs of the task specified by task_id.
+    pub async fn activate_task(&self, task_id: &TaskId) -> Result<Task, RequestError> {
+        self.set_task_status(task_id, TaskStatusType::Active).await
+    }
+
+    /// Pause the scheduled runs of the task specified by task_id, e.g. during
+    /// maintenance.
+    pub async fn deactivate_task(&self, task_id: &TaskId) -> Result<Task, RequestError> {
+        self.set_task_status(task_id, TaskStatusType::Inactive).await
+    }
+
+    async fn set_task_status(
+        &self,
+        task_id: &TaskId,
+        status: TaskStatusType,
+    ) -> Result<Task, RequestError> {
+        let request = UpdateTaskRequest {
+            status: Some(status),
+            ..UpdateTaskRequest::default()
+        };
+        self.update_task(task_id, &request).await
+    }
+
+    /// Make sure a task named `name` runs `spec`: create it if no task of
+    /// the organization of `spec`, or of this client, has that name, or
+    /// update its script, description and status where they differ. Either
+    /// way, return the task.
+    ///
+    /// Since the schedule lives in the `option task` block, a changed
+    /// schedule shows up as a changed script.
+    pub async fn ensure_task(
+        &self,
+        name: &str,
+        spec: CreateTaskRequest,
+    ) -> Result<Task, RequestError> {
+        self.ensure_task_to(Target::new(), name, spec).await
+    }
+
+    /// Make sure a task named `name` runs `spec`, like `ensure_task`, in the
+    /// organization of `target` unless `spec` names one itself.
+    pub async fn ensure_task_to(
+        &self,
+        target: impl Into<Target>,
+        name: &str,
+        mut spec: CreateTaskRequest,
+    ) -> Result<Task, RequestError> {
+        validate_task_options(&spec.flux).context(InvalidScheduleSnafu)?;
+
+        if spec.org.is_none() && spec.org_id.is_none() {
+            let target = target.into();
+            spec.org = Some(self.resolve_org(&target).to_string());
+        }
+        let request = ListTasksRequest {
+            name: Some(name.to_string()),
+            org: spec.org.clone(),
+            org_id: spec.org_id.clone(),
+            ..ListTasksRequest::default()
+        };
+        let existing = self
+            .list_tasks(request)
+            .await?
+            .tasks
+            .into_iter()
+            .find(|task| task.name == name);
+
+        let task = match existing {
+            Some(task) => task,
+            None => return self.post_task(&spec).await,
+        };
+
+        let update = UpdateTaskRequest {
+            flux: Some(spec.flux).filter(|flux| *flux != task.flux),
+            description: spec
+                .description
+                .filter(|description| task.description.as_ref() != Some(description)),
+            status: spec.status.filter(|status| task.status != Some(*status)),
+        };
+        if update == UpdateTaskRequest::default() {
+            return Ok(task);
+        }
+        self.update_task(&task.id, &update).await
+    }
+
+    /// Create the task of `spec`, returning it
+    pub(crate) async fn post_task(&self, spec: &CreateTaskRequest) -> Result<Task, RequestError> {
+        let url = self.endpoint_url("/api/v2/tasks");
+        let response = self
+            .send(
+                self.request(Method::POST, &url)
+                    .body(serde_json::to_string(spec).context(SerializingSnafu)?),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Task>().await
+    }
+
+    /// Delete a task specified by task_id.
+    pub async fn delete_task(&self, task_id: &TaskId) -> Result<(), RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/tasks/{}", task_id));
+        let response = self.send(self.request(Method::DELETE, &url)).await?;
+        if !response.status().is_success() {
+            return Err(response.error().await);
         }
         Ok(())
     }
+
+    /// List the runs of the task specified by task_id.
+    pub async fn list_task_runs(
+        &self,
+        task_id: &TaskId,
+        request: ListRunsRequest,
+    ) -> Result<Runs, RequestError> {
+        let qs = serde_qs::to_string(&request).unwrap_or_default();
+        let url = match &qs[..] {
+            "" => self.endpoint_url(&format!("/api/v2/tasks/{}/runs", task_id)),
+            _ => self.endpoint_url(&format!("/api/v2/tasks/{}/runs?{}", task_id, qs)),
+        };
+
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Runs>().await
+    }
+
+    /// Start a run of the task specified by task_id now, outside of its
+    /// schedule, returning the scheduled run.
+    pub async fn run_task(&self, task_id: &TaskId) -> Result<Run, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/tasks/{}/runs", task_id));
+        let response = self.send(self.request(Method::POST, &url).body("{}")).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Run>().await
+    }
+
+    /// Get the run specified by run_id of the task specified by task_id.
+    pub async fn get_task_run(&self, task_id: &TaskId, run_id: &str) -> Result<Run, RequestError> {
+        let url = self.endpoint_url(&format!(
+            "/api/v2/tasks/{}/runs/{}",
+            task_id,
+            crate::common::urlencode(run_id)
+        ));
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Run>().await
+    }
+
+    /// List the log events of the task specified by task_id, over all its
+    /// runs.
+    pub async fn list_task_logs(&self, task_id: &TaskId) -> Result<Logs, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/tasks/{}/logs", task_id));
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Logs>().await
+    }
+
+    /// List the log events of the run specified by run_id of the task
+    /// specified by task_id.
+    pub async fn list_run_logs(&self, task_id: &TaskId, run_id: &str) -> Result<Logs, RequestError> {
+        let url = self.endpoint_url(&format!(
+            "/api/v2/tasks/{}/runs/{}/logs",
+            task_id,
+            crate::common::urlencode(run_id)
+        ));
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Logs>().await
+    }
+
+    /// Create the task equivalent to the InfluxDB 1.x continuous query
+    /// `influxql`, a `CREATE CONTINUOUS QUERY` statement, in the
+    /// organization of this client.
+    ///
+    /// The task is named after the query and reads from and writes to the
+    /// `db/rp` buckets its measurements map to. Statements with no
+    /// equivalent task fail with `RequestError::InvalidContinuousQuery`
+    /// before anything is sent.
+    pub async fn migrate_continuous_query(&self, influxql: &str) -> Result<(), RequestError> {
+        let cq: ContinuousQuery = influxql.parse().context(InvalidContinuousQuerySnafu)?;
+        let request = TaskBuilder::new(cq.name.as_str())
+            .every(cq.every().to_string())
+            .description(format!("Migrated continuous query {} on {}", cq.name, cq.database))
+            .org(self.org.as_str())
+            .flux(cq.flux())
+            .build();
+        self.create_task(request).await
+    }
+}
+
+#[cfg(all(feature = "task-watcher", not(target_arch = "wasm32")))]
+mod watch {
+    use std::collections::{HashMap, VecDeque};
+    use std::time::Duration;
+
+    use futures::stream::{self, Stream};
+    use tokio::time::{Interval, MissedTickBehavior};
+
+    use super::ListRunsRequest;
+    use crate::models::{Run, TaskId};
+    use crate::{Client, RequestError};
+
+    /// What a run watcher remembers between polls
+    struct Watch {
+        client: Client,
+        task_id: TaskId,
+        ticks: Interval,
+        seen: HashMap<String, Run>,
+        pending: VecDeque<Run>,
+    }
+
+    impl Client {
+        /// Poll the runs of the task specified by task_id every `interval`
+        /// while the stream is polled, yielding the runs of the first poll
+        /// and then every run that is new or changed, e.g. from
+        /// `RunStatus::Started` to `RunStatus::Success`.
+        ///
+        /// A poll that fails yields its error, and the stream carries on
+        /// polling.
+        ///
+        /// # Panics
+        ///
+        /// Panics if called outside of a tokio runtime.
+        pub fn watch_task_runs(
+            &self,
+            task_id: &TaskId,
+            interval: Duration,
+        ) -> impl Stream<Item = Result<Run, RequestError>> {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let watch = Watch {
+                client: self.clone(),
+                task_id: task_id.clone(),
+                ticks,
+                seen: HashMap::new(),
+                pending: VecDeque::new(),
+            };
+
+            stream::unfold(watch, |mut watch| async move {
+                loop {
+                    if let Some(run) = watch.pending.pop_front() {
+                        return Some((Ok(run), watch));
+                    }
+
+                    watch.ticks.tick().await;
+                    let runs = match watch
+                        .client
+                        .list_task_runs(&watch.task_id, ListRunsRequest::default())
+                        .await
+                    {
+                        Ok(runs) => runs.runs,
+                        Err(e) => return Some((Err(e), watch)),
+                    };
+                    for run in runs {
+                        let id = match &run.id {
+                            Some(id) => id.clone(),
+                            None => continue,
+                        };
+                        if watch.seen.get(&id) != Some(&run) {
+                            watch.seen.insert(id, run.clone());
+                            watch.pending.push_back(run);
+                        }
+                    }
+                }
+            })
+        }
+    }
 }
 
 /// Request for list tasks api
@@ -94,14 +408,28 @@ pub struct ListTasksRequest {
     pub org: Option<String>,
     /// Filter tasks to a specific organization ID.
     #[serde(rename = "orgID")]
-    pub org_id: Option<String>,
+    pub org_id: Option<OrgId>,
     /// Filter tasks by status, either "inactive" or "active".
     pub status: Option<String>,
     /// Filter task by type. Default: "". Valid values: ["basic", "system"].
     #[serde(rename = "type")]
     pub type_: Option<TaskStatusType>,
     /// Filter tasks to a specific user ID.
-    pub user: Option<String>,
+    pub user: Option<UserId>,
+}
+
+/// Request for list runs api
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRunsRequest {
+    /// Return runs after a specified run ID.
+    pub after: Option<String>,
+    /// The number of runs to return. Default: 100. Valid values [1..500].
+    pub limit: Option<u16>,
+    /// Return runs scheduled after this time.
+    pub after_time: Option<DateTime<Utc>>,
+    /// Return runs scheduled before this time.
+    pub before_time: Option<DateTime<Utc>>,
 }
 
 /// Encapsulates task data that is sent on POST via the task API.
@@ -118,12 +446,28 @@ pub struct CreateTaskRequest {
     pub org: Option<String>,
     /// The ID of the organization that owns this task
     #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
-    pub org_id: Option<String>,
+    pub org_id: Option<OrgId>,
     /// Task status
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<TaskStatusType>,
 }
 
+/// Encapsulates the changes to a task that are sent on PATCH via the task
+/// API. The fields left `None` are kept as they are.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTaskRequest {
+    /// The new flux script of the task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flux: Option<String>,
+    /// The new description of the task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The new status of the task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatusType>,
+}
+
 impl CreateTaskRequest {
     /// Returns instance of PostTaskRequest
     pub fn new(flux: String) -> Self {
@@ -135,5 +479,361 @@ impl CreateTaskRequest {
             status: None,
         }
     }
+
+    /// Create a builder generating the `option task` block of the script.
+    pub fn builder(name: impl Into<String>) -> TaskBuilder {
+        TaskBuilder::new(name)
+    }
+}
+
+/// Builds a `CreateTaskRequest` whose script starts with an
+/// `option task = {...}` block generated from the task's name and schedule.
+///
+/// ```
+/// use influxdb2::api::task::TaskBuilder;
+///
+/// let request = TaskBuilder::new("downsample")
+///     .every("1h")
+///     .offset("5m")
+///     .flux("from(bucket: \"raw\") |> range(start: -task.every)")
+///     .build();
+/// assert_eq!(
+///     request.flux,
+///     "option task = {name: \"downsample\", every: 1h, offset: 5m}\n\n\
+///      from(bucket: \"raw\") |> range(start: -task.every)"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskBuilder {
+    name: String,
+    every: Option<String>,
+    cron: Option<String>,
+    offset: Option<String>,
+    flux: String,
+    request: CreateTaskRequest,
 }
 
+impl TaskBuilder {
+    /// Create a builder for the task `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Run the task at every `duration`, a Flux duration literal such as
+    /// `1h`.
+    pub fn every(mut self, duration: impl Into<String>) -> Self {
+        self.every = Some(duration.into());
+        self
+    }
+
+    /// Run the task on the cron schedule `expression`, such as
+    /// `0 * * * *`.
+    pub fn cron(mut self, expression: impl Into<String>) -> Self {
+        self.cron = Some(expression.into());
+        self
+    }
+
+    /// Delay every run by `duration`, a Flux duration literal such as `5m`.
+    pub fn offset(mut self, duration: impl Into<String>) -> Self {
+        self.offset = Some(duration.into());
+        self
+    }
+
+    /// Set the body of the script, which follows the `option task` block.
+    pub fn flux(mut self, body: impl Into<String>) -> Self {
+        self.flux = body.into();
+        self
+    }
+
+    /// Set the description of the task.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.request.description = Some(description.into());
+        self
+    }
+
+    /// Set the name of the organization owning the task.
+    pub fn org(mut self, org: impl Into<String>) -> Self {
+        self.request.org = Some(org.into());
+        self
+    }
+
+    /// Set the ID of the organization owning the task.
+    pub fn org_id(mut self, org_id: impl Into<OrgId>) -> Self {
+        self.request.org_id = Some(org_id.into());
+        self
+    }
+
+    /// Set the status the task is created with.
+    pub fn status(mut self, status: TaskStatusType) -> Self {
+        self.request.status = Some(status);
+        self
+    }
+
+    /// Build the request.
+    pub fn build(self) -> CreateTaskRequest {
+        let mut options = vec![format!("name: {}", flux_string(&self.name))];
+        if let Some(every) = &self.every {
+            options.push(format!("every: {}", every));
+        }
+        if let Some(cron) = &self.cron {
+            options.push(format!("cron: {}", flux_string(cron)));
+        }
+        if let Some(offset) = &self.offset {
+            options.push(format!("offset: {}", offset));
+        }
+
+        CreateTaskRequest {
+            flux: format!("option task = {{{}}}\n\n{}", options.join(", "), self.flux),
+            ..self.request
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ScheduleError;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn builds_the_option_block() {
+        let request = TaskBuilder::new("say \"hi\" for ${you}")
+            .cron("0 * * * *")
+            .description("hourly")
+            .org("org")
+            .flux("from(bucket: \"b\")")
+            .build();
+
+        assert_eq!(
+            request.flux,
+            "option task = {name: \"say \\\"hi\\\" for \\${you}\", cron: \"0 * * * *\"}\n\n\
+             from(bucket: \"b\")"
+        );
+        assert_eq!(request.description.as_deref(), Some("hourly"));
+        assert_eq!(request.org.as_deref(), Some("org"));
+        assert_eq!(request.status, None);
+    }
+
+    #[tokio::test]
+    async fn create_task_from_builder() {
+        let request = CreateTaskRequest::builder("t")
+            .every("10m")
+            .org_id("1234")
+            .flux("x")
+            .build();
+        let mock_server = mock("POST", "/api/v2/tasks")
+            .match_body(Matcher::Json(serde_json::json!({
+                "flux": "option task = {name: \"t\", every: 10m}\n\nx",
+                "orgID": "1234",
+            })))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let _result = client.create_task(request).await;
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn ensure_task() {
+        let task = |id: &str, flux: &str| {
+            format!(
+                r#"{{"id":"{}","name":"t","orgID":"0000111100001111","flux":{:?}}}"#,
+                id, flux
+            )
+        };
+        let spec = |every: &str| {
+            TaskBuilder::new("t")
+                .every(every)
+                .org_id("0000111100001111")
+                .flux("x")
+                .build()
+        };
+        let hourly = spec("1h").flux;
+
+        let list = mock("GET", "/api/v2/tasks?name=t&orgID=0000111100001111")
+            .with_body(format!(r#"{{"tasks":[{}]}}"#, task("0000000000000001", &hourly)))
+            .expect(2)
+            .create();
+        let update = mock("PATCH", "/api/v2/tasks/0000000000000001")
+            .match_body(Matcher::Json(serde_json::json!({
+                "flux": "option task = {name: \"t\", every: 10m}\n\nx",
+            })))
+            .with_body(task("0000000000000001", &spec("10m").flux))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let unchanged = client.ensure_task("t", spec("1h")).await.unwrap();
+        assert_eq!(unchanged.flux, hourly);
+        let updated = client.ensure_task("t", spec("10m")).await.unwrap();
+        assert_eq!(updated.flux, spec("10m").flux);
+        list.assert();
+        update.assert();
+
+        let list = mock("GET", "/api/v2/tasks?name=new&org=org")
+            .with_body(r#"{"tasks":[]}"#)
+            .create();
+        let create = mock("POST", "/api/v2/tasks")
+            .match_body(Matcher::Json(serde_json::json!({ "flux": "x", "org": "org" })))
+            .with_status(201)
+            .with_body(task("0000000000000002", "x"))
+            .create();
+
+        let created = client
+            .ensure_task("new", CreateTaskRequest::new("x".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(created.id, TaskId::from("0000000000000002"));
+        list.assert();
+        create.assert();
+    }
+
+    #[tokio::test]
+    async fn list_tasks_of_other_org() {
+        let mock_server = mock("GET", "/api/v2/tasks?org=task-other-org")
+            .with_body(r#"{"tasks":[]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let tasks = client
+            .list_tasks_to(Target::org("task-other-org"), ListTasksRequest::default())
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert!(tasks.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_run_logs() {
+        let mock_server = mock("GET", "/api/v2/tasks/0000000000000009/runs/0000000000000001/logs")
+            .with_body(
+                r#"{"events":[{"runID":"0000000000000001","time":"2023-01-02T03:04:05Z",
+                               "message":"Completed(success)"}]}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let logs = client
+            .list_run_logs(&TaskId::from("0000000000000009"), "0000000000000001")
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(logs.events.len(), 1);
+        assert_eq!(
+            logs.events[0].time,
+            Some(DateTime::parse_from_rfc3339("2023-01-02T03:04:05Z").unwrap().into())
+        );
+    }
+
+    #[tokio::test]
+    async fn create_task_rejects_invalid_schedules() {
+        let request = TaskBuilder::new("t").cron("0 0 * * 8").flux("x").build();
+
+        let client = Client::new("http://localhost:1", "org", "");
+        let err = client.create_task(request).await.unwrap_err();
+
+        assert!(
+            matches!(
+                &err,
+                RequestError::InvalidSchedule {
+                    source: ScheduleError::InvalidCron { token, .. }
+                } if token == "8"
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn migrates_continuous_queries() {
+        let flux = "option task = {name: \"cq_1h\", every: 1h}\n\n\
+                    data = from(bucket: \"telegraf/autogen\")\n    \
+                    |> range(start: -1h)\n    \
+                    |> filter(fn: (r) => r._measurement == \"cpu\")\n    \
+                    |> group(columns: [\"_measurement\", \"_field\", \"host\"])\n\n\
+                    data\n    \
+                    |> filter(fn: (r) => r._field == \"usage_idle\")\n    \
+                    |> aggregateWindow(every: 1h, fn: last, timeSrc: \"_start\", createEmpty: false)\n    \
+                    |> set(key: \"_field\", value: \"last\")\n    \
+                    |> set(key: \"_measurement\", value: \"cpu_1h\")\n    \
+                    |> to(bucket: \"telegraf/autogen\")\n";
+        let mock_server = mock("POST", "/api/v2/tasks")
+            .match_body(Matcher::Json(serde_json::json!({
+                "flux": flux,
+                "org": "org",
+                "description": "Migrated continuous query cq_1h on telegraf"
+            })))
+            .with_status(201)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        client
+            .migrate_continuous_query(
+                "CREATE CONTINUOUS QUERY cq_1h ON telegraf BEGIN SELECT last(usage_idle) \
+                 INTO cpu_1h FROM cpu GROUP BY time(1h), host END",
+            )
+            .await
+            .unwrap();
+        mock_server.assert();
+
+        let err = client
+            .migrate_continuous_query("SELECT mean(v) FROM cpu")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, RequestError::InvalidContinuousQuery { .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[cfg(feature = "task-watcher")]
+    #[tokio::test]
+    async fn watches_new_and_changed_runs() {
+        use crate::models::RunStatus;
+        use futures::StreamExt;
+        use std::time::Duration;
+
+        let run = |id: &str, status: &str| {
+            format!(r#"{{"id":"{}","taskID":"0000000000000009","status":"{}"}}"#, id, status)
+        };
+        let path = "/api/v2/tasks/0000000000000009/runs";
+        let first = mock("GET", path)
+            .with_body(format!(
+                r#"{{"runs":[{},{}]}}"#,
+                run("a", "started"),
+                run("b", "success")
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let runs = client.watch_task_runs(&TaskId::from("0000000000000009"), Duration::from_millis(10));
+        futures::pin_mut!(runs);
+
+        let a = runs.next().await.unwrap().unwrap();
+        let b = runs.next().await.unwrap().unwrap();
+        assert_eq!(a.id.as_deref(), Some("a"));
+        assert_eq!(b.id.as_deref(), Some("b"));
+        drop(first);
+
+        let _second = mock("GET", path)
+            .with_body(format!(
+                r#"{{"runs":[{},{},{}]}}"#,
+                run("a", "success"),
+                run("b", "success"),
+                run("c", "scheduled")
+            ))
+            .create();
+        let a = runs.next().await.unwrap().unwrap();
+        assert_eq!(a.id.as_deref(), Some("a"));
+        assert!(a.status.unwrap().is_finished());
+        let c = runs.next().await.unwrap().unwrap();
+        assert_eq!(c.id.as_deref(), Some("c"));
+        assert_eq!(c.status, Some(RunStatus::Scheduled));
+    }
+}
@@ -0,0 +1,234 @@
+//! Telegraf configurations API
+//!
+//! With the `telegraf-toml` feature, configurations can be parsed and
+//! patched as TOML documents, e.g. to roll a plugin change out to many
+//! configurations:
+//!
+//! ```no_run
+//! # #[cfg(feature = "telegraf-toml")]
+//! # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! use influxdb2::models::TelegrafId;
+//!
+//! let id = TelegrafId::from("0000000000000001");
+//! client
+//!     .patch_telegraf_config(&id, |config| {
+//!         config.plugins_mut("inputs", "mem").push(toml::Value::Table(Default::default()));
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use reqwest::{Method, StatusCode};
+use snafu::ResultExt;
+
+use crate::models::{OrgId, Telegraf, TelegrafId, TelegrafRequest, Telegrafs};
+use crate::{Client, RequestError, SerializingSnafu};
+
+impl Client {
+    /// List the Telegraf configurations, of the organization `org_id` if
+    /// given.
+    pub async fn list_telegrafs(&self, org_id: Option<&OrgId>) -> Result<Telegrafs, RequestError> {
+        let url = self.endpoint_url("/api/v2/telegrafs");
+        let mut request = self.request(Method::GET, &url);
+
+        if let Some(id) = org_id {
+            request = request.query(&[("orgID", id.as_str())]);
+        }
+
+        let response = self.send(request).await?;
+        match response.status() {
+            StatusCode::OK => response.json::<Telegrafs>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Retrieve a Telegraf configuration by ID
+    pub async fn find_telegraf(&self, id: &TelegrafId) -> Result<Telegraf, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/telegrafs/{}", id));
+        // Without this the configuration is sent as bare TOML
+        let request = self
+            .request(Method::GET, &url)
+            .header("Accept", "application/json");
+
+        let response = self.send(request).await?;
+        match response.status() {
+            StatusCode::OK => response.json::<Telegraf>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Create a Telegraf configuration
+    pub async fn create_telegraf(
+        &self,
+        request: TelegrafRequest,
+    ) -> Result<Telegraf, RequestError> {
+        let url = self.endpoint_url("/api/v2/telegrafs");
+        let response = self
+            .send(
+                self.request(Method::POST, &url)
+                    .body(serde_json::to_string(&request).context(SerializingSnafu)?),
+            )
+            .await?;
+        match response.status() {
+            StatusCode::CREATED => response.json::<Telegraf>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Replace the Telegraf configuration `id` with `request`
+    pub async fn update_telegraf(
+        &self,
+        id: &TelegrafId,
+        request: TelegrafRequest,
+    ) -> Result<Telegraf, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/telegrafs/{}", id));
+        let response = self
+            .send(
+                self.request(Method::PUT, &url)
+                    .body(serde_json::to_string(&request).context(SerializingSnafu)?),
+            )
+            .await?;
+        match response.status() {
+            StatusCode::OK => response.json::<Telegraf>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Delete a Telegraf configuration
+    pub async fn delete_telegraf(&self, id: &TelegrafId) -> Result<(), RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/telegrafs/{}", id));
+        let response = self.send(self.request(Method::DELETE, &url)).await?;
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(response.error().await),
+        }
+    }
+}
+
+#[cfg(feature = "telegraf-toml")]
+pub use self::config::TelegrafConfigError;
+
+#[cfg(feature = "telegraf-toml")]
+mod config {
+    use snafu::{ResultExt, Snafu};
+
+    use crate::models::{Telegraf, TelegrafConfig, TelegrafId};
+    use crate::{Client, RequestError};
+
+    /// Errors that occur while fetching or patching a Telegraf configuration
+    /// as TOML
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(crate)))]
+    pub enum TelegrafConfigError {
+        /// The configuration couldn't be fetched or updated.
+        #[snafu(display("{}", source))]
+        Request {
+            /// The underlying request error
+            #[snafu(source(from(RequestError, Box::new)))]
+            source: Box<RequestError>,
+        },
+
+        /// The configuration isn't valid TOML.
+        #[snafu(display("Invalid Telegraf configuration: {}", source))]
+        Parse {
+            /// The underlying error from `toml`
+            source: toml::de::Error,
+        },
+
+        /// The patched configuration can't be written as TOML.
+        #[snafu(display("Can't write the Telegraf configuration: {}", source))]
+        Serialize {
+            /// The underlying error from `toml`
+            source: toml::ser::Error,
+        },
+    }
+
+    impl Client {
+        /// Retrieve a Telegraf configuration by ID, along with its
+        /// configuration parsed as TOML.
+        pub async fn find_telegraf_config(
+            &self,
+            id: &TelegrafId,
+        ) -> Result<(Telegraf, TelegrafConfig), TelegrafConfigError> {
+            let telegraf = self.find_telegraf(id).await.context(RequestSnafu)?;
+            let config = telegraf.parse_config().context(ParseSnafu)?;
+            Ok((telegraf, config))
+        }
+
+        /// Fetch the Telegraf configuration `id`, let `patch` change it as a
+        /// TOML document and write it back, returning the updated
+        /// configuration.
+        pub async fn patch_telegraf_config(
+            &self,
+            id: &TelegrafId,
+            patch: impl FnOnce(&mut TelegrafConfig),
+        ) -> Result<Telegraf, TelegrafConfigError> {
+            let (mut telegraf, mut config) = self.find_telegraf_config(id).await?;
+            patch(&mut config);
+            telegraf.set_config(&config).context(SerializeSnafu)?;
+            self.update_telegraf(id, telegraf.into())
+                .await
+                .context(RequestSnafu)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn finds_telegrafs_as_json() {
+        let mock_server = mock("GET", "/api/v2/telegrafs/0000000000000001")
+            .match_header("Accept", "application/json")
+            .with_body(
+                r#"{"id":"0000000000000001","orgID":"0000000000000002","name":"t",
+                    "metadata":{"buckets":["b"]},"config":"[agent]\n"}"#,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let telegraf = client
+            .find_telegraf(&TelegrafId::from("0000000000000001"))
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(telegraf.name, "t");
+        assert_eq!(telegraf.metadata.unwrap().buckets, ["b"]);
+        assert_eq!(telegraf.config, "[agent]\n");
+    }
+
+    #[cfg(feature = "telegraf-toml")]
+    #[tokio::test]
+    async fn patches_telegraf_configs() {
+        let find = mock("GET", "/api/v2/telegrafs/0000000000000003")
+            .with_body(
+                r#"{"id":"0000000000000003","orgID":"0000000000000002","name":"t",
+                    "config":"[[inputs.cpu]]\npercpu = true\n"}"#,
+            )
+            .create();
+        let update = mock("PUT", "/api/v2/telegrafs/0000000000000003")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"name":"t","orgID":"0000000000000002","config":"[[inputs.cpu]]\npercpu = true\n\n[[inputs.mem]]\n"}"#
+                    .to_string(),
+            ))
+            .with_body(r#"{"id":"0000000000000003","name":"t","config":""}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        client
+            .patch_telegraf_config(&TelegrafId::from("0000000000000003"), |config| {
+                config
+                    .plugins_mut("inputs", "mem")
+                    .push(toml::Value::Table(Default::default()));
+            })
+            .await
+            .unwrap();
+
+        find.assert();
+        update.assert();
+    }
+}
@@ -0,0 +1,88 @@
+//! Templates API
+//!
+//! Export resources as templates, e.g. to keep a hand-built dashboard in
+//! version control:
+//!
+//! ```no_run
+//! use influxdb2::models::DashboardId;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let template = client.export_dashboard(&DashboardId::from("0000000000000001")).await?;
+//! std::fs::write("dashboard.json", serde_json::to_string_pretty(&template)?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use reqwest::{Method, StatusCode};
+use snafu::ResultExt;
+
+use crate::models::{DashboardId, Template, TemplateExportRequest, TemplateExportResource};
+use crate::{Client, RequestError, SerializingSnafu};
+
+impl Client {
+    /// Export the resources of `request` as a template.
+    pub async fn export_template(
+        &self,
+        request: TemplateExportRequest,
+    ) -> Result<Template, RequestError> {
+        let url = self.endpoint_url("/api/v2/templates/export");
+        let response = self
+            .send(
+                self.request(Method::POST, &url)
+                    .header("Accept", "application/json")
+                    .body(serde_json::to_string(&request).context(SerializingSnafu)?),
+            )
+            .await?;
+        match response.status() {
+            StatusCode::OK => response.json::<Template>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Export the dashboard `id` as a template, along with the labels and
+    /// variables it depends on.
+    pub async fn export_dashboard(&self, id: &DashboardId) -> Result<Template, RequestError> {
+        let request = TemplateExportRequest {
+            resources: vec![TemplateExportResource::new("Dashboard", id.as_str())],
+            ..TemplateExportRequest::default()
+        };
+        self.export_template(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn exports_dashboards() {
+        let mock_server = mock("POST", "/api/v2/templates/export")
+            .match_body(Matcher::Json(serde_json::json!({
+                "resources": [{"kind": "Dashboard", "id": "0000000000000001"}]
+            })))
+            .with_body(
+                r##"[{"apiVersion":"influxdata.com/v2alpha1","kind":"Label",
+                     "metadata":{"name":"prod"},"spec":{"name":"prod","color":"#326BBA"}},
+                    {"apiVersion":"influxdata.com/v2alpha1","kind":"Dashboard",
+                     "metadata":{"name":"cpu"},"spec":{"name":"CPU","charts":[]}}]"##,
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let template = client
+            .export_dashboard(&DashboardId::from("0000000000000001"))
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(template.objects.len(), 2);
+        let dashboard = template.objects_of_kind("Dashboard").next().unwrap();
+        assert_eq!(dashboard.metadata.name, "cpu");
+        assert_eq!(dashboard.spec["name"], "CPU");
+        assert_eq!(
+            serde_json::to_value(&template).unwrap()[0]["spec"]["color"],
+            "#326BBA"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Usage API
+//!
+//! InfluxDB Cloud meters what each organization writes, queries and stores.
+//! `Client::usage` reads those measurements from `/api/v2/orgs/{orgID}/usage`
+//! as typed series, so billing-aware applications can watch their
+//! consumption. Servers without the endpoint, such as InfluxDB OSS, answer
+//! `404 Not Found`.
+//!
+//! ```no_run
+//! use chrono::{Duration, Utc};
+//! use influxdb2::api::usage::UsageMetric;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let org_id = client.org_id("acme").await?;
+//! let usage = client.usage(&org_id, Utc::now() - Duration::days(1), None).await?;
+//! println!("{} bytes written today", usage.total(UsageMetric::WriteBytes));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use influxdb2_structmap::value::Value;
+use influxdb2_structmap::GenericMap;
+use reqwest::{Method, StatusCode};
+
+use crate::api::query::parse_records;
+use crate::models::OrgId;
+use crate::{Client, RequestError};
+
+/// The columns of a usage record that aren't tags
+const NOT_TAGS: &[&str] = &[
+    "result",
+    "table",
+    "_start",
+    "_stop",
+    "_time",
+    "_value",
+    "_field",
+    "_measurement",
+];
+
+/// What a usage series measures
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UsageMetric {
+    /// The bytes of the write requests, the `req_bytes` field of the
+    /// `http_request` measurement for the `/api/v2/write` endpoint
+    WriteBytes,
+    /// The number of queries, the `query_count` measurement
+    QueryCount,
+    /// The bytes stored, the `storage_usage_bucket_bytes` measurement
+    StorageBytes,
+    /// Another measurement of the usage API
+    Other,
+}
+
+/// The usage of an organization over a time range
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Usage {
+    /// The series reported, in the order of the response
+    pub series: Vec<UsageSeries>,
+}
+
+/// The values of one usage measurement, field and set of tags
+#[derive(Clone, Debug, PartialEq)]
+pub struct UsageSeries {
+    /// What the series measures
+    pub metric: UsageMetric,
+    /// The measurement of the series
+    pub measurement: String,
+    /// The field of the series
+    pub field: String,
+    /// The tags of the series, such as the `bucket_id` of storage series
+    pub tags: BTreeMap<String, String>,
+    /// The values, by time
+    pub points: Vec<UsagePoint>,
+}
+
+/// A value of a usage series
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UsagePoint {
+    /// The time of the value
+    pub time: DateTime<FixedOffset>,
+    /// The value
+    pub value: f64,
+}
+
+impl Client {
+    /// The usage of the organization with the ID `org_id` from `start` to
+    /// `stop`, or to now if `None`.
+    pub async fn usage(
+        &self,
+        org_id: &OrgId,
+        start: DateTime<Utc>,
+        stop: Option<DateTime<Utc>>,
+    ) -> Result<Usage, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/orgs/{}/usage", org_id));
+        let mut request = self
+            .request(Method::GET, &url)
+            .query(&[("start", start.to_rfc3339_opts(SecondsFormat::AutoSi, true))]);
+        if let Some(stop) = stop {
+            request = request.query(&[("stop", stop.to_rfc3339_opts(SecondsFormat::AutoSi, true))]);
+        }
+        let response = self.send(request).await?;
+
+        match response.status() {
+            StatusCode::OK => response.parse(parse_records).await.map(Usage::from_records),
+            _ => Err(response.error().await),
+        }
+    }
+}
+
+impl Usage {
+    /// The series measuring `metric`.
+    pub fn series(&self, metric: UsageMetric) -> impl Iterator<Item = &UsageSeries> {
+        self.series
+            .iter()
+            .filter(move |series| series.metric == metric)
+    }
+
+    /// The sum of the values of the series measuring `metric`, e.g. the
+    /// bytes written over the range.
+    pub fn total(&self, metric: UsageMetric) -> f64 {
+        self.series(metric).map(UsageSeries::total).sum()
+    }
+
+    fn from_records(records: Vec<GenericMap>) -> Self {
+        let mut usage = Self::default();
+        for record in records {
+            let text = |column: &str| match record.get(column) {
+                Some(Value::String(text)) => text.clone(),
+                _ => String::new(),
+            };
+            let measurement = text("_measurement");
+            let field = text("_field");
+            let tags: BTreeMap<_, _> = record
+                .iter()
+                .filter(|(column, _)| !NOT_TAGS.contains(&column.as_str()))
+                .filter_map(|(column, value)| match value {
+                    Value::String(tag) => Some((column.clone(), tag.clone())),
+                    _ => None,
+                })
+                .collect();
+            let point = match (record.get("_time"), record.get("_value").and_then(number)) {
+                (Some(Value::TimeRFC(time)), Some(value)) => UsagePoint { time: *time, value },
+                _ => continue,
+            };
+
+            let existing = usage.series.iter_mut().find(|series| {
+                series.measurement == measurement && series.field == field && series.tags == tags
+            });
+            match existing {
+                Some(series) => series.points.push(point),
+                None => usage.series.push(UsageSeries {
+                    metric: UsageMetric::of(&measurement, &field, &tags),
+                    measurement,
+                    field,
+                    tags,
+                    points: vec![point],
+                }),
+            }
+        }
+        for series in &mut usage.series {
+            series.points.sort_by_key(|point| point.time);
+        }
+        usage
+    }
+}
+
+impl UsageSeries {
+    /// The sum of the values.
+    pub fn total(&self) -> f64 {
+        self.points.iter().map(|point| point.value).sum()
+    }
+
+    /// The latest value, e.g. the bytes stored now, or `None` if there is
+    /// none.
+    pub fn latest(&self) -> Option<f64> {
+        self.points.last().map(|point| point.value)
+    }
+}
+
+impl UsageMetric {
+    fn of(measurement: &str, field: &str, tags: &BTreeMap<String, String>) -> Self {
+        match measurement {
+            "http_request"
+                if field == "req_bytes"
+                    && tags.get("endpoint").map(String::as_str) == Some("/api/v2/write") =>
+            {
+                Self::WriteBytes
+            }
+            "query_count" => Self::QueryCount,
+            "storage_usage_bucket_bytes" => Self::StorageBytes,
+            _ => Self::Other,
+        }
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Double(value) => Some(value.into_inner()),
+        Value::Long(value) => Some(*value as f64),
+        Value::UnsignedLong(value) => Some(*value as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn reads_typed_usage_series() {
+        let mock_server = mock("GET", "/api/v2/orgs/0000000000000abc/usage")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("start".into(), "2040-01-01T00:00:00Z".into()),
+                Matcher::UrlEncoded("stop".into(), "2040-01-02T00:00:00Z".into()),
+            ]))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,string,string,string,long
+#group,false,false,false,true,true,true,false
+#default,_result,,,,,,
+,result,table,_time,_measurement,_field,endpoint,_value
+,,0,2040-01-01T01:00:00Z,http_request,req_bytes,/api/v2/write,300
+,,0,2040-01-01T00:00:00Z,http_request,req_bytes,/api/v2/write,200
+,,1,2040-01-01T00:00:00Z,http_request,req_bytes,/api/v2/query,50
+
+#datatype,string,long,dateTime:RFC3339,string,string,long
+#group,false,false,false,true,true,false
+#default,_result,,,,,
+,result,table,_time,_measurement,_field,_value
+,,2,2040-01-01T00:00:00Z,query_count,counter,7
+,,2,2040-01-01T01:00:00Z,query_count,counter,5
+
+#datatype,string,long,dateTime:RFC3339,string,string,string,double
+#group,false,false,false,true,true,true,false
+#default,_result,,,,,,
+,result,table,_time,_measurement,_field,bucket_id,_value
+,,3,2040-01-01T00:00:00Z,storage_usage_bucket_bytes,gauge,b1,1000
+,,3,2040-01-01T01:00:00Z,storage_usage_bucket_bytes,gauge,b1,1500.5
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let usage = client
+            .usage(
+                &OrgId::new("0000000000000abc"),
+                Utc.with_ymd_and_hms(2040, 1, 1, 0, 0, 0).unwrap(),
+                Some(Utc.with_ymd_and_hms(2040, 1, 2, 0, 0, 0).unwrap()),
+            )
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(usage.series.len(), 4);
+        assert_eq!(usage.total(UsageMetric::WriteBytes), 500.0);
+        assert_eq!(usage.total(UsageMetric::QueryCount), 12.0);
+        assert_eq!(usage.total(UsageMetric::Other), 50.0);
+
+        let writes = usage.series(UsageMetric::WriteBytes).next().unwrap();
+        assert_eq!(writes.points[0].value, 200.0);
+        let storage = usage.series(UsageMetric::StorageBytes).next().unwrap();
+        assert_eq!(storage.tags["bucket_id"], "b1");
+        assert_eq!(storage.latest(), Some(1500.5));
+    }
+}
@@ -0,0 +1,139 @@
+//! Users API
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::api::organization::NameCache;
+use crate::models::{User, UserId, Users};
+use crate::{Client, RequestError};
+
+/// Users resolved by name
+pub(crate) type UserCache = NameCache<User>;
+
+impl Client {
+    /// List the users matching `request`.
+    pub async fn list_users(&self, request: ListUsersRequest) -> Result<Users, RequestError> {
+        let qs = serde_qs::to_string(&request).unwrap_or_default();
+        let url = match &qs[..] {
+            "" => self.endpoint_url("/api/v2/users"),
+            _ => self.endpoint_url(&format!("/api/v2/users?{}", qs)),
+        };
+
+        let response = self.send(self.request(Method::GET, &url)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        response.json::<Users>().await
+    }
+
+    /// The user named `name`, or `None` if there is none.
+    ///
+    /// Users aren't cached unless a TTL is set with
+    /// `ClientBuilder::with_user_cache_ttl`, in which case a found user is
+    /// reused for that long by this client and its clones.
+    pub async fn find_user_by_name(&self, name: &str) -> Result<Option<User>, RequestError> {
+        if let Some(user) = self.users.get(name) {
+            return Ok(Some(user));
+        }
+
+        let request = ListUsersRequest {
+            name: Some(name.to_string()),
+            ..ListUsersRequest::default()
+        };
+        let user = self
+            .list_users(request)
+            .await?
+            .users
+            .into_iter()
+            .find(|user| user.name == name);
+        if let Some(user) = &user {
+            self.users.insert(name, user.clone());
+        }
+        Ok(user)
+    }
+
+    /// Forget the cached user named `name`, e.g. after it was renamed.
+    pub fn invalidate_user(&self, name: &str) {
+        self.users.remove(name);
+    }
+
+    /// Forget every cached user.
+    pub fn clear_users(&self) {
+        self.users.clear();
+    }
+}
+
+/// Request for list users API
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListUsersRequest {
+    /// Offset of the users to return from.
+    pub offset: Option<i64>,
+    /// Number of users to return.
+    pub limit: Option<i64>,
+    /// Return the users after this ID.
+    pub after: Option<UserId>,
+    /// Filter by user name.
+    pub name: Option<String>,
+    /// Filter by user ID.
+    pub id: Option<UserId>,
+}
+
+impl ListUsersRequest {
+    /// Create a new request for list users API
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn finds_users_by_name() {
+        let lookup = mock("GET", "/api/v2/users?name=ada")
+            .with_body(r#"{"users":[{"id":"0000000000000001","name":"ada","status":"active"}]}"#)
+            .expect(2)
+            .create();
+        let missing = mock("GET", "/api/v2/users?name=nobody")
+            .with_body(r#"{"users":[]}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let user = client.find_user_by_name("ada").await.unwrap().unwrap();
+        assert_eq!(user.id, Some(UserId::from("0000000000000001")));
+        assert!(client.find_user_by_name("ada").await.unwrap().is_some());
+        assert_eq!(client.find_user_by_name("nobody").await.unwrap(), None);
+
+        lookup.assert();
+        missing.assert();
+    }
+
+    #[tokio::test]
+    async fn caches_users_with_a_ttl() {
+        let lookup = mock("GET", "/api/v2/users?name=grace")
+            .with_body(r#"{"users":[{"id":"0000000000000002","name":"grace"}]}"#)
+            .expect(2)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_user_cache_ttl(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        assert!(client.find_user_by_name("grace").await.unwrap().is_some());
+        assert!(client
+            .clone()
+            .find_user_by_name("grace")
+            .await
+            .unwrap()
+            .is_some());
+        client.invalidate_user("grace");
+        assert!(client.find_user_by_name("grace").await.unwrap().is_some());
+
+        lookup.assert();
+    }
+}
@@ -0,0 +1,174 @@
+//! Variables API
+//!
+//! `evaluate_variable` resolves the choices of a variable in one call,
+//! running the query of a query variable and reading constant and map
+//! variables as they are, e.g. to fill the dropdown of a dashboard:
+//!
+//! ```no_run
+//! use influxdb2::models::VariableId;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let variable = client.find_variable(&VariableId::from("0000000000000001")).await?;
+//! for choice in client.evaluate_variable(&variable).await? {
+//!     println!("{} stands for {}", choice.label, choice.value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use reqwest::{Method, StatusCode};
+
+use crate::api::query::json_value;
+use crate::models::{
+    OrgId, Query, Variable, VariableArguments, VariableChoice, VariableId, Variables,
+};
+use crate::{Client, RequestError};
+
+impl Client {
+    /// List the variables, of the organization `org_id` if given.
+    pub async fn list_variables(&self, org_id: Option<&OrgId>) -> Result<Variables, RequestError> {
+        let url = self.endpoint_url("/api/v2/variables");
+        let mut request = self.request(Method::GET, &url);
+
+        if let Some(id) = org_id {
+            request = request.query(&[("orgID", id.as_str())]);
+        }
+
+        let response = self.send(request).await?;
+        match response.status() {
+            StatusCode::OK => response.json::<Variables>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// Retrieve a variable by ID
+    pub async fn find_variable(&self, id: &VariableId) -> Result<Variable, RequestError> {
+        let url = self.endpoint_url(&format!("/api/v2/variables/{}", id));
+        let response = self.send(self.request(Method::GET, &url)).await?;
+        match response.status() {
+            StatusCode::OK => response.json::<Variable>().await,
+            _ => Err(response.error().await),
+        }
+    }
+
+    /// The choices of `variable`, in order.
+    ///
+    /// A query variable runs its query and offers the distinct values of the
+    /// `_value` column; the query can't refer to other variables. A constant
+    /// variable offers its values and a map variable its keys, each standing
+    /// for the value it maps to.
+    pub async fn evaluate_variable(
+        &self,
+        variable: &Variable,
+    ) -> Result<Vec<VariableChoice>, RequestError> {
+        let query = match &variable.arguments {
+            VariableArguments::Query(query) => query,
+            VariableArguments::Constant(values) => {
+                return Ok(values.iter().cloned().map(VariableChoice::new).collect())
+            }
+            VariableArguments::Map(values) => {
+                return Ok(values
+                    .iter()
+                    .map(|(label, value)| VariableChoice {
+                        label: label.clone(),
+                        value: value.clone(),
+                    })
+                    .collect())
+            }
+        };
+
+        let rows = self.query_records(Query::new(query.query.clone())).await?;
+        let mut values: Vec<String> = Vec::new();
+        for value in rows.iter().filter_map(|row| row.get("_value")) {
+            let value = match json_value(value) {
+                serde_json::Value::Null => continue,
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        Ok(values.into_iter().map(VariableChoice::new).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn variable(arguments: &str) -> Variable {
+        serde_json::from_str(&format!(r#"{{"name":"v","arguments":{}}}"#, arguments)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn evaluates_query_variables() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::PartialJsonString(
+                r#"{"query":"buckets() |> rename(columns: {name: \"_value\"})"}"#.to_string(),
+            ))
+            .with_body(
+                "#datatype,string,long,string
+#group,false,false,false
+#default,_result,,
+,result,table,_value
+,,0,telegraf
+,,0,stocks
+,,1,telegraf
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let choices = client
+            .evaluate_variable(&variable(
+                r#"{"type":"query","values":{
+                    "query":"buckets() |> rename(columns: {name: \"_value\"})","language":"flux"}}"#,
+            ))
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        let values: Vec<_> = choices.iter().map(|choice| choice.value.as_str()).collect();
+        assert_eq!(values, ["telegraf", "stocks"]);
+    }
+
+    #[tokio::test]
+    async fn evaluates_constant_and_map_variables() {
+        let client = Client::new(mockito::server_url(), "org", "");
+
+        let constant = client
+            .evaluate_variable(&variable(r#"{"type":"constant","values":["a","b"]}"#))
+            .await
+            .unwrap();
+        assert_eq!(
+            constant,
+            [
+                VariableChoice::new("a".to_string()),
+                VariableChoice::new("b".to_string())
+            ]
+        );
+
+        let map = client
+            .evaluate_variable(&variable(
+                r#"{"type":"map","values":{"prod":"us-east-1","dev":"local"}}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            map,
+            [
+                VariableChoice {
+                    label: "dev".to_string(),
+                    value: "local".to_string()
+                },
+                VariableChoice {
+                    label: "prod".to_string(),
+                    value: "us-east-1".to_string()
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Windowed aggregation
+//!
+//! Most dashboards and reports need one thing from Flux: a field of a
+//! measurement, aggregated into fixed windows over a time range. These calls
+//! build that query with `aggregateWindow`, run it, and return one typed
+//! [`Series`] per tag set, without writing any Flux:
+//!
+//! ```no_run
+//! use influxdb2::api::window::TimeRange;
+//! use influxdb2::models::FluxDuration;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let every: FluxDuration = "5m".parse()?;
+//! let last_day = TimeRange::last("1d".parse()?);
+//! for series in client.mean_over("telegraf", "cpu", "usage_idle", last_day, every).await? {
+//!     println!("{:?}: {} windows", series.tags, series.points.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Windows without data are left out, and values of integer fields are
+//! converted to `f64`.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use influxdb2_structmap::value::Value;
+
+use crate::models::template::flux_string;
+use crate::models::{FluxDuration, Query};
+use crate::{Client, RequestError};
+
+/// The columns of a record that aren't tags
+pub(crate) const NOT_TAGS: &[&str] = &[
+    "result",
+    "table",
+    "_start",
+    "_stop",
+    "_time",
+    "_value",
+    "_field",
+    "_measurement",
+];
+
+/// The time range a windowed query covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeRange {
+    /// The duration up to now
+    Last(FluxDuration),
+    /// From the first time, included, to the second, excluded
+    Between(DateTime<Utc>, DateTime<Utc>),
+}
+
+impl TimeRange {
+    /// The `duration` up to now.
+    pub fn last(duration: FluxDuration) -> Self {
+        Self::Last(duration)
+    }
+
+    /// From `start`, included, to `stop`, excluded.
+    pub fn between(start: DateTime<Utc>, stop: DateTime<Utc>) -> Self {
+        Self::Between(start, stop)
+    }
+
+    /// The arguments of `range()` covering this range
+    pub(crate) fn flux_arguments(&self) -> String {
+        let time = |t: &DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        match self {
+            Self::Last(duration) => format!("start: -{}", duration),
+            Self::Between(start, stop) => format!("start: {}, stop: {}", time(start), time(stop)),
+        }
+    }
+}
+
+impl From<FluxDuration> for TimeRange {
+    fn from(duration: FluxDuration) -> Self {
+        Self::Last(duration)
+    }
+}
+
+/// The aggregate computed over each window
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    /// The average of the values
+    Mean,
+    /// The middle value
+    Median,
+    /// The total of the values
+    Sum,
+    /// The smallest value
+    Min,
+    /// The largest value
+    Max,
+    /// The number of values
+    Count,
+    /// The earliest value
+    First,
+    /// The latest value
+    Last,
+}
+
+impl Aggregate {
+    /// The name of the Flux function computing the aggregate.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mean => "mean",
+            Self::Median => "median",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Count => "count",
+            Self::First => "first",
+            Self::Last => "last",
+        }
+    }
+}
+
+/// The aggregated windows of one tag set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Series {
+    /// The tags of the series
+    pub tags: BTreeMap<String, String>,
+    /// The windows, in time order
+    pub points: Vec<WindowPoint>,
+}
+
+/// The aggregate of one window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowPoint {
+    /// The end of the window
+    pub time: DateTime<FixedOffset>,
+    /// The aggregate of the values in the window
+    pub value: f64,
+}
+
+impl Client {
+    /// Aggregate `field` of `measurement` in `bucket` with `aggregate` over
+    /// windows of `every` across `range`, one series per tag set.
+    pub async fn aggregate_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+        aggregate: Aggregate,
+    ) -> Result<Vec<Series>, RequestError> {
+        let flux = window_flux(bucket, measurement, field, &range.into(), every, aggregate);
+        let rows = self.query_records(Query::new(flux)).await?;
+
+        let mut series: Vec<(i64, Series)> = Vec::new();
+        for row in rows {
+            let table = match row.get("table") {
+                Some(Value::Long(table)) => *table,
+                _ => 0,
+            };
+            let (time, value) = match (row.get("_time"), row.get("_value")) {
+                (Some(Value::TimeRFC(time)), Some(value)) => match as_f64(value) {
+                    Some(value) => (*time, value),
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let index = match series.iter().position(|(t, _)| *t == table) {
+                Some(index) => index,
+                None => {
+                    let tags = row
+                        .iter()
+                        .filter(|(column, _)| !NOT_TAGS.contains(&column.as_str()))
+                        .filter_map(|(column, value)| match value {
+                            Value::String(value) => Some((column.clone(), value.clone())),
+                            _ => None,
+                        })
+                        .collect();
+                    series.push((table, Series { tags, points: Vec::new() }));
+                    series.len() - 1
+                }
+            };
+            series[index].1.points.push(WindowPoint { time, value });
+        }
+
+        Ok(series.into_iter().map(|(_, series)| series).collect())
+    }
+
+    /// The mean of `field` over windows of `every` across `range`, see
+    /// [`Client::aggregate_over`].
+    pub async fn mean_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.aggregate_over(bucket, measurement, field, range, every, Aggregate::Mean)
+            .await
+    }
+
+    /// The sum of `field` over windows of `every` across `range`, see
+    /// [`Client::aggregate_over`].
+    pub async fn sum_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.aggregate_over(bucket, measurement, field, range, every, Aggregate::Sum)
+            .await
+    }
+
+    /// The minimum of `field` over windows of `every` across `range`, see
+    /// [`Client::aggregate_over`].
+    pub async fn min_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.aggregate_over(bucket, measurement, field, range, every, Aggregate::Min)
+            .await
+    }
+
+    /// The maximum of `field` over windows of `every` across `range`, see
+    /// [`Client::aggregate_over`].
+    pub async fn max_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.aggregate_over(bucket, measurement, field, range, every, Aggregate::Max)
+            .await
+    }
+
+    /// The number of values of `field` in windows of `every` across `range`,
+    /// see [`Client::aggregate_over`].
+    pub async fn count_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.aggregate_over(bucket, measurement, field, range, every, Aggregate::Count)
+            .await
+    }
+}
+
+/// The Flux of `Client::aggregate_over`
+fn window_flux(
+    bucket: &str,
+    measurement: &str,
+    field: &str,
+    range: &TimeRange,
+    every: FluxDuration,
+    aggregate: Aggregate,
+) -> String {
+    format!(
+        "from(bucket: {})\n  \
+         |> range({})\n  \
+         |> filter(fn: (r) => r._measurement == {} and r._field == {})\n  \
+         |> aggregateWindow(every: {}, fn: {}, createEmpty: false)",
+        flux_string(bucket),
+        range.flux_arguments(),
+        flux_string(measurement),
+        flux_string(field),
+        every,
+        aggregate.as_str(),
+    )
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Double(value) => Some(value.into_inner()),
+        Value::Long(value) => Some(*value as f64),
+        Value::UnsignedLong(value) => Some(*value as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn builds_window_queries() {
+        let start = DateTime::parse_from_rfc3339("2040-01-01T00:00:00Z").unwrap();
+        let stop = DateTime::parse_from_rfc3339("2040-01-02T00:00:00Z").unwrap();
+        let range = TimeRange::between(start.into(), stop.into());
+
+        assert_eq!(
+            window_flux("b", "cpu", "usage", &range, FluxDuration::from_secs(300), Aggregate::Max),
+            "from(bucket: \"b\")\n  \
+             |> range(start: 2040-01-01T00:00:00Z, stop: 2040-01-02T00:00:00Z)\n  \
+             |> filter(fn: (r) => r._measurement == \"cpu\" and r._field == \"usage\")\n  \
+             |> aggregateWindow(every: 5m, fn: max, createEmpty: false)"
+        );
+    }
+
+    #[tokio::test]
+    async fn mean_over() {
+        let mock_server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex(r"range\(start: -1h\)".to_string()))
+            .with_body(
+                "#datatype,string,long,dateTime:RFC3339,double,string,string,string
+#group,false,false,false,false,true,true,true
+#default,_result,,,,,,
+,result,table,_time,_value,_field,_measurement,host
+,,0,2040-01-01T00:05:00Z,0.5,usage,cpu,a
+,,0,2040-01-01T00:10:00Z,0.7,usage,cpu,a
+,,1,2040-01-01T00:05:00Z,0.25,usage,cpu,b
+",
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let series = client
+            .mean_over("b", "cpu", "usage", FluxDuration::from_secs(3600), FluxDuration::from_secs(300))
+            .await
+            .unwrap();
+
+        mock_server.assert();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].tags["host"], "a");
+        assert_eq!(series[0].tags.len(), 1);
+        assert_eq!(
+            series[0].points.iter().map(|p| p.value).collect::<Vec<_>>(),
+            vec![0.5, 0.7]
+        );
+        assert_eq!(series[1].points[0].value, 0.25);
+    }
+}
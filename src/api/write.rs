@@ -1,69 +1,311 @@
 //! Write API
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::compression;
+use crate::compression::ContentEncoding;
+use crate::line_protocol::{Precision, TimestampCheck};
 use crate::models::WriteDataPoint;
-use crate::{Client, HttpSnafu, RequestError, ReqwestProcessingSnafu};
+use crate::request::RequestKind;
+use crate::{Client, RequestError, Target};
+#[cfg(not(target_arch = "wasm32"))]
 use bytes::BufMut;
+use bytes::Bytes;
 use futures::{Stream, StreamExt};
-use reqwest::{Body, Method};
-use snafu::ResultExt;
-use std::io::{self, Write};
+use parking_lot::Mutex;
+use reqwest::header::CONTENT_ENCODING;
+use reqwest::{Body, Method, StatusCode};
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+use std::sync::Arc;
 
 impl Client {
+    /// Write line protocol data to the organization and bucket of `target`.
+    ///
+    /// Bodies in memory are encoded as set by
+    /// `ClientBuilder::with_write_compression`. Streamed bodies can't be
+    /// encoded, so they are sent as they are, with a warning if the client
+    /// compresses writes, and only once whatever the retry policy; `write`
+    /// compresses the points it streams.
+    pub async fn write_line_protocol_to(
+        &self,
+        target: impl Into<Target>,
+        body: impl Into<Body> + Send,
+    ) -> Result<(), RequestError> {
+        let target = target.into();
+        let body = body.into();
+        let candidates = self.write_compression.candidates();
+        let compressing = candidates != [ContentEncoding::Identity];
+
+        match body.as_bytes() {
+            Some(bytes) if compressing => {
+                let bytes = Bytes::copy_from_slice(bytes);
+                self.write_encoded(&target, bytes, &candidates).await
+            }
+            None if compressing => {
+                tracing::warn!(
+                    encoding = ?candidates[0],
+                    "writing a streamed body uncompressed, as it can't be encoded"
+                );
+                self.post_write(&target, body, ContentEncoding::Identity)
+                    .await
+            }
+            _ => {
+                self.post_write(&target, body, ContentEncoding::Identity)
+                    .await
+            }
+        }
+    }
+
     /// Write line protocol data to the specified organization and bucket.
+    #[deprecated(note = "use `write_line_protocol_to`, which takes a `Target`")]
     pub async fn write_line_protocol(
         &self,
         org: &str,
         bucket: &str,
         body: impl Into<Body> + Send,
     ) -> Result<(), RequestError> {
-        let body = body.into();
-        let write_url = format!("{}/api/v2/write", self.url);
-
-        let response = self
-            .request(Method::POST, &write_url)
-            .query(&[("bucket", bucket), ("org", org)])
-            .body(body)
-            .send()
+        self.write_line_protocol_to(Target::org(org).with_bucket(bucket), body)
             .await
-            .context(ReqwestProcessingSnafu)?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.context(ReqwestProcessingSnafu)?;
-            HttpSnafu { status, text }.fail()?;
-        }
+    }
 
-        Ok(())
+    /// The encoding of the bodies written, or `None` while
+    /// `WriteCompression::Auto` hasn't negotiated one yet.
+    pub fn write_encoding(&self) -> Option<ContentEncoding> {
+        self.write_compression.encoding()
     }
 
-    /// Write a `Stream` of `DataPoint`s to the specified organization and
-    /// bucket.
+    /// Write a `Stream` of `DataPoint`s to the organization and bucket of
+    /// `target`.
+    ///
+    /// With `ClientBuilder::with_timestamp_check`, the timestamps of the points
+    /// are checked against the precision of the write as they are sent. With
+    /// `ClientBuilder::with_naming`, their names are renamed as they are
+    /// serialized. With `ClientBuilder::with_retry_policy`, they are buffered
+    /// to be sent again.
     pub async fn write(
         &self,
-        bucket: &str,
+        target: impl Into<Target>,
         body: impl Stream<Item = impl WriteDataPoint> + Send + Sync + 'static,
     ) -> Result<(), RequestError> {
-        let mut buffer = bytes::BytesMut::new();
+        let mut checker = TimestampChecker::new(self.timestamp_check, self.write_precision);
+        let rejected = Arc::clone(&checker.rejected);
+        let naming = self.naming.clone();
 
-        let body = body.map(move |point| {
-            let mut w = (&mut buffer).writer();
-            point.write_data_point_to(&mut w)?;
-            w.flush()?;
-            Ok::<_, io::Error>(buffer.split().freeze())
-        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = {
+            let mut buffer = bytes::BytesMut::new();
+            let chunks = body.map(move |point| {
+                checker.check(&point)?;
+                let mut w = (&mut buffer).writer();
+                match &naming {
+                    Some(naming) => point.write_renamed_data_point_to(naming.as_ref(), &mut w)?,
+                    None => point.write_data_point_to(&mut w)?,
+                }
+                w.flush()?;
+                Ok::<_, io::Error>(buffer.split().freeze())
+            });
 
-        let body = Body::wrap_stream(body);
+            let target = target.into();
+            let retrying = self.retry_policy.is_some();
+            match self.write_compression.candidates()[..] {
+                [ContentEncoding::Identity] if !retrying => {
+                    self.post_write(
+                        &target,
+                        Body::wrap_stream(chunks),
+                        ContentEncoding::Identity,
+                    )
+                    .await
+                }
+                [encoding] if !retrying => {
+                    let body = Body::wrap_stream(compression::encode_stream(chunks, encoding));
+                    self.post_write(&target, body, encoding).await
+                }
+                ref candidates => {
+                    // Negotiating or retrying, so the body is buffered to be
+                    // sent again
+                    let mut body = bytes::BytesMut::new();
+                    futures::pin_mut!(chunks);
+                    let mut failed = None;
+                    while let Some(chunk) = chunks.next().await {
+                        match chunk {
+                            Ok(chunk) => body.extend_from_slice(&chunk),
+                            Err(e) => {
+                                failed = Some(e);
+                                break;
+                            }
+                        }
+                    }
+                    let rejected = rejected.lock().take();
+                    match (rejected, failed) {
+                        (Some(e), _) => Err(e),
+                        (None, Some(source)) => Err(RequestError::LineProtocol { source }),
+                        (None, None) => {
+                            self.write_encoded(&target, body.freeze(), candidates).await
+                        }
+                    }
+                }
+            }
+        };
 
-        Ok(self.write_line_protocol(&self.org, bucket, body).await?)
+        // The wasm backend of `reqwest` cannot stream request bodies, so the
+        // points are serialized into a single buffer up front.
+        #[cfg(target_arch = "wasm32")]
+        let result = {
+            let points: Vec<_> = body.collect().await;
+            let mut buffer = Vec::new();
+            let mut failed = None;
+            for point in points {
+                let written = checker.check(&point).and_then(|()| match &naming {
+                    Some(naming) => point.write_renamed_data_point_to(naming.as_ref(), &mut buffer),
+                    None => point.write_data_point_to(&mut buffer),
+                });
+                if let Err(e) = written {
+                    failed = Some(e);
+                    break;
+                }
+            }
+
+            let rejected = rejected.lock().take();
+            match (rejected, failed) {
+                (Some(e), _) => Err(e),
+                (None, Some(source)) => Err(RequestError::LineProtocol { source }),
+                (None, None) => self.write_line_protocol_to(target, buffer).await,
+            }
+        };
+
+        let rejected = rejected.lock().take();
+        match rejected {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+
+    /// Write `body` with the first of `candidates` the server accepts,
+    /// settling the negotiation on it.
+    async fn write_encoded(
+        &self,
+        target: &Target,
+        body: Bytes,
+        candidates: &[ContentEncoding],
+    ) -> Result<(), RequestError> {
+        let mut result = Ok(());
+        for &encoding in candidates {
+            result = self
+                .post_write(target, encoding.encode(&body), encoding)
+                .await;
+            match &result {
+                Ok(()) => {
+                    self.write_compression.settle(encoding);
+                    break;
+                }
+                // Refused, outright or in a bad request naming the encoding;
+                // other bad requests are about the points, whatever their
+                // encoding
+                Err(RequestError::Http { status, text, .. })
+                    if *status == StatusCode::UNSUPPORTED_MEDIA_TYPE
+                        || (*status == StatusCode::BAD_REQUEST
+                            && encoding
+                                .header_value()
+                                .is_some_and(|name| text.to_lowercase().contains(name))) => {}
+                Err(_) => break,
+            }
+        }
+        result
+    }
+
+    /// Send one write of `body`, encoded with `encoding`.
+    async fn post_write(
+        &self,
+        target: &Target,
+        body: impl Into<Body>,
+        encoding: ContentEncoding,
+    ) -> Result<(), RequestError> {
+        let (org, bucket) = self.resolve(target)?;
+        let write_url = self.endpoint_url("/api/v2/write");
+
+        let mut request = self
+            .request_as(RequestKind::Write, Method::POST, &write_url)
+            .query(&[("bucket", bucket), ("org", org)]);
+        if let Some(precision) = self.write_precision {
+            request = request.query(&[("precision", precision.as_str())]);
+        }
+        if let Some(value) = encoding.header_value() {
+            request = request.header(CONTENT_ENCODING, value);
+        }
+        let response = self.send_retrying(request.body(body)).await?;
+
+        if !response.status().is_success() {
+            return Err(response.error().await);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the timestamps of the points of one write
+struct TimestampChecker {
+    check: TimestampCheck,
+    precision: Precision,
+    warned: bool,
+    /// The error of the first rejected point, which aborts the write
+    rejected: Arc<Mutex<Option<RequestError>>>,
+}
+
+impl TimestampChecker {
+    fn new(check: TimestampCheck, precision: Option<Precision>) -> Self {
+        Self {
+            check,
+            precision: precision.unwrap_or_default(),
+            warned: false,
+            rejected: Arc::default(),
+        }
+    }
+
+    fn check(&mut self, point: &impl WriteDataPoint) -> io::Result<()> {
+        let timestamp = match point.timestamp() {
+            Some(timestamp) if self.check != TimestampCheck::Off => timestamp,
+            _ => return Ok(()),
+        };
+        if self.precision.is_plausible(timestamp) {
+            return Ok(());
+        }
+
+        let likely = Precision::infer(timestamp);
+        match self.check {
+            TimestampCheck::Off => Ok(()),
+            TimestampCheck::Warn => {
+                if !self.warned {
+                    self.warned = true;
+                    tracing::warn!(
+                        timestamp,
+                        precision = %self.precision,
+                        likely = %likely.map(|likely| likely.to_string()).unwrap_or_default(),
+                        "writing a timestamp that is implausible in the precision of the write"
+                    );
+                }
+                Ok(())
+            }
+            TimestampCheck::Reject => {
+                *self.rejected.lock() = Some(RequestError::ImplausibleTimestamp {
+                    timestamp,
+                    precision: self.precision,
+                    likely,
+                });
+                Err(io::Error::other("implausible timestamp"))
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compression::WriteCompression;
+    use crate::line_protocol::naming::{CamelCase, MeasurementPrefix};
     use crate::models::DataPoint;
     use futures::stream;
-    use mockito::mock;
+    use mockito::{mock, Matcher};
 
     #[tokio::test]
     async fn writing_points() {
@@ -84,7 +326,7 @@ cpu,host=server01,region=us-west usage=0.87
         )
         .create();
 
-        let client = Client::new(&mockito::server_url(), org, token);
+        let client = Client::new(mockito::server_url(), org, token);
 
         let points = vec![
             DataPoint::builder("cpu")
@@ -109,4 +351,285 @@ cpu,host=server01,region=us-west usage=0.87
 
         mock_server.assert();
     }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn writing_to_org_and_bucket() {
+        let mock_server = mock("POST", "/api/v2/write?bucket=legacy-bucket&org=legacy-org")
+            .match_body("cpu usage=0.5\n")
+            .create();
+
+        let client = Client::new(mockito::server_url(), "some-org", "");
+
+        let _result = client
+            .write_line_protocol("legacy-org", "legacy-bucket", "cpu usage=0.5\n")
+            .await;
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn writing_to_default_bucket_of_other_org() {
+        let mock_server = mock("POST", "/api/v2/write?bucket=default-bucket&org=other-org")
+            .match_body("cpu,host=server01 usage=0.5\n")
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "some-org", "")
+            .with_bucket("default-bucket")
+            .build()
+            .unwrap();
+
+        let _result = client
+            .write_line_protocol_to(Target::org("other-org"), "cpu,host=server01 usage=0.5\n")
+            .await;
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn writing_without_bucket() {
+        let client = Client::new(mockito::server_url(), "some-org", "");
+
+        let result = client.write_line_protocol_to(Target::new(), "cpu usage=0.5\n").await;
+
+        assert!(matches!(result, Err(RequestError::MissingBucket)));
+    }
+
+    #[tokio::test]
+    async fn writing_with_precision() {
+        let mock_server = mock("POST", "/api/v2/write?bucket=b&org=o&precision=s")
+            .match_body("cpu usage=0.5 1700000000\n")
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "o", "")
+            .with_write_precision(Precision::Seconds)
+            .with_timestamp_check(TimestampCheck::Reject)
+            .build()
+            .unwrap();
+        let point = DataPoint::builder("cpu")
+            .field("usage", 0.5)
+            .timestamp(1_700_000_000)
+            .build()
+            .unwrap();
+
+        client.write("b", stream::iter(vec![point])).await.unwrap();
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn rejecting_implausible_timestamps() {
+        let _mock_server = mock("POST", "/api/v2/write?bucket=b&org=o").create();
+
+        let client = Client::builder(mockito::server_url(), "o", "")
+            .with_timestamp_check(TimestampCheck::Reject)
+            .build()
+            .unwrap();
+        let point = DataPoint::builder("cpu")
+            .field("usage", 0.5)
+            .timestamp(1_700_000_000)
+            .build()
+            .unwrap();
+
+        let result = client.write("b", stream::iter(vec![point])).await;
+
+        assert!(matches!(
+            result,
+            Err(RequestError::ImplausibleTimestamp {
+                timestamp: 1_700_000_000,
+                precision: Precision::Nanoseconds,
+                likely: Some(Precision::Seconds),
+            })
+        ));
+        assert_eq!(
+            Precision::infer(1_700_000_000_000_000_000),
+            Some(Precision::Nanoseconds)
+        );
+        assert_eq!(Precision::infer(1_700_000_000_000), Some(Precision::Milliseconds));
+        assert_eq!(Precision::infer(-1), None);
+    }
+
+    #[tokio::test]
+    async fn writes_with_naming() {
+        let mock_server = mock("POST", "/api/v2/write?bucket=named&org=org")
+            .match_body("svc_cpu,hostName=a usageIdle=0.5 1\n")
+            .with_status(204)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_naming((CamelCase, MeasurementPrefix::new("svc_")))
+            .build()
+            .unwrap();
+        let points = vec![DataPoint::builder("cpu")
+            .tag("host_name", "a")
+            .field("usage_idle", 0.5)
+            .timestamp(1)
+            .build()
+            .unwrap()];
+        client.write("named", stream::iter(points)).await.unwrap();
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn writes_gzip() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let mut body = GzEncoder::new(Vec::new(), Compression::default());
+        body.write_all(b"cpu usage=0.5\n").unwrap();
+        let mock_server = mock("POST", "/api/v2/write?bucket=gzipped&org=org")
+            .match_header("Content-Encoding", "gzip")
+            .match_body(body.finish().unwrap())
+            .with_status(204)
+            .expect(2)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_gzip(true)
+            .build()
+            .unwrap();
+        client
+            .write_line_protocol_to("gzipped", "cpu usage=0.5\n")
+            .await
+            .unwrap();
+        let points = vec![DataPoint::builder("cpu").field("usage", 0.5).build().unwrap()];
+        client.write("gzipped", stream::iter(points)).await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(client.write_encoding(), Some(ContentEncoding::Gzip));
+    }
+
+    #[tokio::test]
+    async fn writes_streamed_bodies_uncompressed() {
+        let mock_server = mock("POST", "/api/v2/write?bucket=streamed-gzip&org=org")
+            .match_header("Content-Encoding", Matcher::Missing)
+            .match_body("cpu usage=0.5\ncpu usage=0.7\n")
+            .with_status(204)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_gzip(true)
+            .build()
+            .unwrap();
+        let chunks = stream::iter(vec![
+            Ok::<_, io::Error>("cpu usage=0.5\n"),
+            Ok("cpu usage=0.7\n"),
+        ]);
+        client
+            .write_line_protocol_to("streamed-gzip", Body::wrap_stream(chunks))
+            .await
+            .unwrap();
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn negotiating_write_compression() {
+        #[cfg(feature = "zstd")]
+        let zstd = mock("POST", "/api/v2/write?bucket=negotiated&org=org")
+            .match_header("Content-Encoding", "zstd")
+            .with_status(415)
+            .create();
+        let gzip = mock("POST", "/api/v2/write?bucket=negotiated&org=org")
+            .match_header("Content-Encoding", "gzip")
+            .with_status(204)
+            .expect(2)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_write_compression(WriteCompression::Auto)
+            .build()
+            .unwrap();
+        client
+            .write_line_protocol_to("negotiated", "cpu usage=0.5\n")
+            .await
+            .unwrap();
+        let points = vec![DataPoint::builder("cpu").field("usage", 0.7).build().unwrap()];
+        client.write("negotiated", stream::iter(points)).await.unwrap();
+
+        #[cfg(feature = "zstd")]
+        zstd.assert();
+        gzip.assert();
+        assert_eq!(client.write_encoding(), Some(ContentEncoding::Gzip));
+    }
+
+    #[tokio::test]
+    async fn negotiation_survives_bad_points() {
+        // Bad points are bad in any encoding, so they are sent once
+        let mock_server = mock("POST", "/api/v2/write?bucket=unparsable&org=org")
+            .with_status(400)
+            .with_body(r#"{"code":"invalid","message":"unable to parse 'cpu usage='"}"#)
+            .expect(1)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_write_compression(WriteCompression::Auto)
+            .build()
+            .unwrap();
+        let result = client.write_line_protocol_to("unparsable", "cpu usage=").await;
+
+        mock_server.assert();
+        assert!(matches!(result, Err(RequestError::Http { .. })));
+        assert_eq!(client.write_encoding(), None);
+    }
+
+    #[tokio::test]
+    async fn negotiation_skips_encodings_named_in_bad_requests() {
+        let gzip = mock("POST", "/api/v2/write?bucket=plain&org=org")
+            .match_header("Content-Encoding", "gzip")
+            .with_status(400)
+            .with_body("unsupported Content-Encoding: gzip")
+            .create();
+        let identity = mock("POST", "/api/v2/write?bucket=plain&org=org")
+            .match_header("Content-Encoding", Matcher::Missing)
+            .with_status(204)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_write_compression(WriteCompression::Auto)
+            .build()
+            .unwrap();
+        #[cfg(feature = "zstd")]
+        let zstd = mock("POST", "/api/v2/write?bucket=plain&org=org")
+            .match_header("Content-Encoding", "zstd")
+            .with_status(415)
+            .create();
+        client.write_line_protocol_to("plain", "cpu usage=0.5\n").await.unwrap();
+
+        #[cfg(feature = "zstd")]
+        zstd.assert();
+        gzip.assert();
+        identity.assert();
+        assert_eq!(client.write_encoding(), Some(ContentEncoding::Identity));
+    }
+
+    #[tokio::test]
+    async fn fails_writes_on_serialization_errors() {
+        /// A point failing to serialize when it isn't writable
+        struct Point(bool);
+
+        impl WriteDataPoint for Point {
+            fn write_data_point_to<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+                match self.0 {
+                    true => w.write_all(b"cpu usage=0.5\n"),
+                    false => Err(io::Error::other("unwritable")),
+                }
+            }
+        }
+
+        let mock_server = mock("POST", "/api/v2/write?bucket=failing&org=org")
+            .expect(0)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_write_compression(WriteCompression::Auto)
+            .build()
+            .unwrap();
+        let result = client
+            .write("failing", stream::iter(vec![Point(true), Point(false), Point(true)]))
+            .await;
+
+        mock_server.assert();
+        assert!(matches!(result, Err(RequestError::LineProtocol { .. })));
+    }
 }
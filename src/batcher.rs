@@ -0,0 +1,581 @@
+//! Batched writes
+//!
+//! Every `Client::write` is an HTTP request of its own, which a service
+//! writing points one at a time, many times a second, can't afford. A
+//! [`WriteBatcher`] buffers the points and writes them in batches from a
+//! background task, once a batch holds enough points or bytes, or when the
+//! flush interval has passed since its first point, like the write APIs of
+//! the Go and Java clients. Requires the `write-batcher` feature and a tokio
+//! runtime.
+//!
+//! ```no_run
+//! use influxdb2::models::DataPoint;
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let batcher = client.write_batcher("telemetry");
+//! for reading in 0..100_000 {
+//!     let point = DataPoint::builder("sensor").field("reading", reading).build()?;
+//!     batcher.write(point).await;
+//! }
+//! batcher.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A batch that fails to be written is dropped, reported to the
+//! [`DropSink`] set with [`WriteBatcher::with_drop_sink`], and its error is
+//! returned by the next call to [`WriteBatcher::flush`] or
+//! [`WriteBatcher::close`]. Batches are due on the client clock. The
+//! telemetry sinks write through a `WriteBatcher` too.
+//!
+//! Sources emitting the fields of a point one at a time, such as a poller
+//! reading registers, can set [`BatchOptions::coalesce`] so the points of a
+//! batch sharing a series and a timestamp are written as one line.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::models::{DataPoint, WriteDataPoint};
+use crate::telemetry::dropped::{DropReason, DropSink, Drops, Tally};
+use crate::{Client, RequestError, Target};
+
+/// When a [`WriteBatcher`] writes its points
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// The most points written at once, 5,000 by default
+    pub max_points: usize,
+    /// The most bytes of line protocol written at once, 1 MiB by default;
+    /// a batch reaching it is written with the point that made it reach it
+    pub max_bytes: usize,
+    /// The longest a point waits to be written, a second by default
+    pub flush_interval: Duration,
+    /// The most points waiting for the background task, 10,000 by default
+    pub capacity: usize,
+    /// Whether the points of a batch with the same measurement, tags and
+    /// timestamp are merged into one point with the fields of all of them,
+    /// the value written last winning for a field they share; off by default
+    pub coalesce: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            max_points: 5_000,
+            max_bytes: 1 << 20,
+            flush_interval: Duration::from_secs(1),
+            capacity: 10_000,
+            coalesce: false,
+        }
+    }
+}
+
+enum Command {
+    Write(DataPoint),
+    Flush(oneshot::Sender<Result<(), RequestError>>),
+}
+
+/// Called before each write with whether the batcher is closing, for more
+/// points to write, e.g. the ended windows of an aggregator. A batcher with
+/// one writes every flush interval, even without points of its own.
+pub(crate) type Tick = Box<dyn FnMut(bool) -> Vec<DataPoint> + Send>;
+
+/// A buffer of points written in batches by a background task
+///
+/// The task writes the points left and stops once the batcher is dropped or
+/// closed.
+#[derive(Debug)]
+pub struct WriteBatcher {
+    sender: mpsc::Sender<Command>,
+    task: JoinHandle<Result<(), RequestError>>,
+    drops: Arc<Drops>,
+}
+
+impl WriteBatcher {
+    pub(crate) fn spawn(
+        client: Client,
+        target: Target,
+        options: BatchOptions,
+        drops: Arc<Drops>,
+        tick: Option<Tick>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(options.capacity.max(1));
+        let batch = Batch::new(client, target, options, Arc::clone(&drops), tick);
+        let task = tokio::spawn(batch.run(receiver));
+        Self {
+            sender,
+            task,
+            drops,
+        }
+    }
+
+    /// Report the points of failed batches to `sink` instead of logging
+    /// them as warnings.
+    pub fn with_drop_sink(self, sink: impl DropSink) -> Self {
+        self.drops.set_sink(sink);
+        self
+    }
+
+    /// Buffer `point`, waiting while the buffer is full.
+    pub async fn write(&self, point: DataPoint) {
+        // The task only stops once the batcher is gone
+        let _ = self.sender.send(Command::Write(point)).await;
+    }
+
+    /// Buffer `point` if the buffer isn't full, or give it back.
+    pub fn try_write(&self, point: DataPoint) -> Result<(), DataPoint> {
+        match self.sender.try_send(Command::Write(point)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(Command::Write(point)))
+            | Err(TrySendError::Closed(Command::Write(point))) => Err(point),
+            Err(_) => unreachable!("a point was sent"),
+        }
+    }
+
+    /// Write the points buffered so far, returning the first error of the
+    /// writes since the last flush.
+    pub async fn flush(&self) -> Result<(), RequestError> {
+        let (reply, result) = oneshot::channel();
+        if self.sender.send(Command::Flush(reply)).await.is_err() {
+            return Ok(());
+        }
+        result.await.unwrap_or(Ok(()))
+    }
+
+    /// Write the points left and stop the background task, returning the
+    /// first error of the writes since the last flush.
+    pub async fn close(self) -> Result<(), RequestError> {
+        drop(self.sender);
+        self.task.await.unwrap_or(Ok(()))
+    }
+}
+
+/// The points of the batch being filled and the state of the task
+struct Batch {
+    client: Client,
+    target: Target,
+    options: BatchOptions,
+    points: Vec<DataPoint>,
+    bytes: usize,
+    /// When the batch is due on the client clock, from its first point on
+    deadline: Option<DateTime<Utc>>,
+    /// The first error since the last flush
+    error: Option<RequestError>,
+    /// The line protocol of the last point, kept to measure the next one
+    line: Vec<u8>,
+    drops: Arc<Drops>,
+    tick: Option<Tick>,
+}
+
+impl Batch {
+    fn new(
+        client: Client,
+        target: Target,
+        options: BatchOptions,
+        drops: Arc<Drops>,
+        tick: Option<Tick>,
+    ) -> Self {
+        let mut batch = Self {
+            client,
+            target,
+            options,
+            points: Vec::new(),
+            bytes: 0,
+            deadline: None,
+            error: None,
+            line: Vec::new(),
+            drops,
+            tick,
+        };
+        if batch.tick.is_some() {
+            batch.deadline = Some(batch.due());
+        }
+        batch
+    }
+
+    /// The deadline of a batch started now
+    fn due(&self) -> DateTime<Utc> {
+        let interval = chrono::Duration::from_std(self.options.flush_interval)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+        let now = self.client.clock.now();
+        now.checked_add_signed(interval).unwrap_or(now)
+    }
+
+    /// Whether the deadline of the batch has passed on the client clock
+    fn is_due(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| self.client.clock.now() >= deadline)
+    }
+
+    async fn run(mut self, mut receiver: mpsc::Receiver<Command>) -> Result<(), RequestError> {
+        loop {
+            let command = match self.deadline {
+                Some(deadline) => {
+                    // Waited for on a tokio timer, and checked again on the
+                    // client clock, which a test may hold still
+                    let wait = (deadline - self.client.clock.now())
+                        .to_std()
+                        .unwrap_or_default();
+                    match tokio::time::timeout(wait, receiver.recv()).await {
+                        Ok(command) => command,
+                        Err(_elapsed) => {
+                            if self.is_due() {
+                                self.write(false).await;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                None => receiver.recv().await,
+            };
+
+            match command {
+                Some(Command::Write(point)) => {
+                    self.push(point);
+                    // The timeout above polls the channel first, so a steady
+                    // stream of points never lets it elapse
+                    if self.points.len() >= self.options.max_points
+                        || self.bytes >= self.options.max_bytes
+                        || self.is_due()
+                    {
+                        self.write(false).await;
+                    }
+                }
+                Some(Command::Flush(reply)) => {
+                    self.write(false).await;
+                    let _ = reply.send(self.error.take().map_or(Ok(()), Err));
+                }
+                None => {
+                    self.write(true).await;
+                    return self.error.take().map_or(Ok(()), Err);
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, point: DataPoint) {
+        self.line.clear();
+        if point.write_data_point_to(&mut self.line).is_ok() {
+            self.bytes += self.line.len();
+        }
+        self.points.push(point);
+        if self.deadline.is_none() {
+            self.deadline = Some(self.due());
+        }
+    }
+
+    async fn write(&mut self, closing: bool) {
+        self.bytes = 0;
+        let mut points = std::mem::take(&mut self.points);
+        self.deadline = match &mut self.tick {
+            Some(tick) => {
+                points.extend(tick(closing));
+                Some(self.due())
+            }
+            None => None,
+        };
+        if points.is_empty() {
+            return;
+        }
+
+        if self.options.coalesce {
+            points = coalesce(points);
+        }
+        let tally = Tally::of(&points);
+        let result = self
+            .client
+            .write(self.target.clone(), futures::stream::iter(points))
+            .await;
+        if let Err(e) = result {
+            self.drops.report(tally, DropReason::from_error(&e));
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+/// `points` with those of the same series and timestamp merged into the first
+/// of them, in order; points without a timestamp are left as they are, as
+/// the server gives each the time it receives it.
+fn coalesce(points: Vec<DataPoint>) -> Vec<DataPoint> {
+    let mut merged: Vec<DataPoint> = Vec::with_capacity(points.len());
+    let mut series: HashMap<(String, BTreeMap<String, String>, i64), usize> = HashMap::new();
+    for point in points {
+        let timestamp = match point.timestamp() {
+            Some(timestamp) => timestamp,
+            None => {
+                merged.push(point);
+                continue;
+            }
+        };
+        let key = (
+            point.measurement().to_string(),
+            point.tags().clone(),
+            timestamp,
+        );
+        match series.get(&key) {
+            Some(&index) => merged[index].merge_fields(point),
+            None => {
+                series.insert(key, merged.len());
+                merged.push(point);
+            }
+        }
+    }
+    merged
+}
+
+impl Client {
+    /// Buffer points for `target` and write them in batches with the default
+    /// [`BatchOptions`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn write_batcher(&self, target: impl Into<Target>) -> WriteBatcher {
+        self.write_batcher_with(target, BatchOptions::default())
+    }
+
+    /// Buffer points for `target` and write them in batches as `options`
+    /// say.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn write_batcher_with(
+        &self,
+        target: impl Into<Target>,
+        options: BatchOptions,
+    ) -> WriteBatcher {
+        WriteBatcher::spawn(
+            self.clone(),
+            target.into(),
+            options,
+            Arc::new(Drops::default()),
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::telemetry::dropped::DroppedPoints;
+    use mockito::{mock, Matcher};
+
+    fn point(value: i64) -> DataPoint {
+        DataPoint::builder("cpu")
+            .field("value", value)
+            .timestamp(value)
+            .build()
+            .unwrap()
+    }
+
+    fn client(prefix: &str) -> Client {
+        Client::new(format!("{}/{}", mockito::server_url(), prefix), "org", "")
+    }
+
+    #[tokio::test]
+    async fn writes_full_batches() {
+        let full = mock("POST", "/full/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body("cpu value=1i 1\ncpu value=2i 2\n")
+            .with_status(204)
+            .create();
+        let rest = mock("POST", "/full/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body("cpu value=3i 3\n")
+            .with_status(204)
+            .create();
+
+        let options = BatchOptions {
+            max_points: 2,
+            flush_interval: Duration::from_secs(3600),
+            ..BatchOptions::default()
+        };
+        let batcher = client("full").write_batcher_with("b", options);
+        for value in 1..=3 {
+            batcher.write(point(value)).await;
+        }
+        batcher.flush().await.unwrap();
+
+        full.assert();
+        rest.assert();
+    }
+
+    #[tokio::test]
+    async fn writes_on_the_flush_interval() {
+        let mock_server = mock("POST", "/interval/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body("cpu value=1i 1\n")
+            .with_status(204)
+            .create();
+
+        let options = BatchOptions {
+            flush_interval: Duration::from_millis(10),
+            ..BatchOptions::default()
+        };
+        let batcher = client("interval").write_batcher_with("b", options);
+        batcher.try_write(point(1)).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        mock_server.assert();
+        drop(batcher);
+    }
+
+    #[tokio::test]
+    async fn writes_on_the_flush_interval_while_busy() {
+        let due = mock("POST", "/busy/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body("cpu value=1i 1\ncpu value=2i 2\n")
+            .with_status(204)
+            .create();
+        let rest = mock("POST", "/busy/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body("cpu value=3i 3\ncpu value=4i 4\n")
+            .with_status(204)
+            .create();
+
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let client = Client::builder(format!("{}/busy", mockito::server_url()), "org", "")
+            .with_clock(Arc::clone(&clock))
+            .build()
+            .unwrap();
+        let options = BatchOptions {
+            flush_interval: Duration::from_secs(60),
+            ..BatchOptions::default()
+        };
+        let batcher = client.write_batcher_with("b", options);
+        batcher.try_write(point(1)).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // Points queued past the deadline keep the channel ready, so the
+        // batch is only written if writes check the deadline themselves
+        clock.advance(Duration::from_secs(61));
+        for value in 2..=4 {
+            batcher.try_write(point(value)).unwrap();
+        }
+        for _ in 0..100 {
+            if due.matched() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        due.assert();
+        batcher.close().await.unwrap();
+        rest.assert();
+    }
+
+    #[test]
+    fn coalesces_points_of_a_series() {
+        let field = |host: &str, name: &str, value: i64, timestamp: Option<i64>| {
+            let point = DataPoint::builder("cpu")
+                .tag("host", host)
+                .field(name, value);
+            match timestamp {
+                Some(timestamp) => point.timestamp(timestamp),
+                None => point,
+            }
+            .build()
+            .unwrap()
+        };
+
+        let points = coalesce(vec![
+            field("a", "user", 1, Some(5)),
+            field("b", "user", 2, Some(5)),
+            field("a", "system", 3, Some(5)),
+            field("a", "user", 4, Some(5)),
+            field("a", "user", 5, Some(6)),
+            field("a", "user", 6, None),
+            field("a", "system", 7, None),
+        ]);
+
+        let a = DataPoint::builder("cpu")
+            .tag("host", "a")
+            .field("user", 4)
+            .field("system", 3)
+            .timestamp(5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            points,
+            vec![
+                a,
+                field("b", "user", 2, Some(5)),
+                field("a", "user", 5, Some(6)),
+                field("a", "user", 6, None),
+                field("a", "system", 7, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_coalesced_batches() {
+        let mock_server = mock("POST", "/coalesced/api/v2/write")
+            .match_query(Matcher::Any)
+            .match_body("cpu idle=1i,value=2i 1\ncpu value=2i 2\n")
+            .with_status(204)
+            .create();
+
+        let options = BatchOptions {
+            coalesce: true,
+            ..BatchOptions::default()
+        };
+        let batcher = client("coalesced").write_batcher_with("b", options);
+        let field = |name: &str, value: i64| {
+            DataPoint::builder("cpu")
+                .field(name, value)
+                .timestamp(1)
+                .build()
+                .unwrap()
+        };
+        batcher.write(field("value", 1)).await;
+        batcher.write(field("idle", 1)).await;
+        batcher.write(point(2)).await;
+        batcher.write(field("value", 2)).await;
+        batcher.close().await.unwrap();
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn returns_failures_on_close() {
+        let mock_server = mock("POST", "/failing/api/v2/write")
+            .match_query(Matcher::Any)
+            .with_status(400)
+            .with_body(r#"{"code":"invalid","message":"unable to parse"}"#)
+            .create();
+
+        let options = BatchOptions {
+            max_bytes: 1,
+            ..BatchOptions::default()
+        };
+        let dropped = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let records = Arc::clone(&dropped);
+        let batcher = client("failing")
+            .write_batcher_with("b", options)
+            .with_drop_sink(move |record: &DroppedPoints| records.lock().push(record.clone()));
+        batcher.write(point(1)).await;
+        let error = batcher.close().await.unwrap_err();
+
+        mock_server.assert();
+        assert!(matches!(error, RequestError::Http { .. }), "{:?}", error);
+        assert_eq!(
+            *dropped.lock(),
+            vec![DroppedPoints {
+                measurement: "cpu".into(),
+                count: 1,
+                reason: DropReason::Rejected {
+                    status: 400,
+                    message: r#"{"code":"invalid","message":"unable to parse"}"#.into(),
+                },
+                first_timestamp: Some(1),
+                last_timestamp: Some(1),
+            }]
+        );
+    }
+}
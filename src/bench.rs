@@ -0,0 +1,219 @@
+//! Write benchmarking
+//!
+//! Measure how fast a server takes writes from this client, to pick the
+//! batch size and number of writes in flight of an application. [`run`]
+//! generates synthetic points, writes them in batches and reports the
+//! throughput and the latency of the batches. Requires the `bench` feature;
+//! `examples/write_bench.rs` compares a few settings against a server.
+//!
+//! ```no_run
+//! use influxdb2::bench::{self, BenchConfig};
+//!
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! for batch in [1000, 5000, 20_000] {
+//!     let config = BenchConfig::new(100_000).batch(batch).concurrency(4);
+//!     println!("{}", bench::run(&client, "bench", &config).await?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The points are generated before the clock starts, so the report covers
+//! serializing, sending and the server's answer. They belong to the `bench`
+//! measurement, spread over `series` values of the `series` tag, with
+//! `fields` float fields each and distinct timestamps from the time of the
+//! run, so a dedicated bucket is best.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+use crate::models::DataPoint;
+use crate::{Client, RequestError};
+
+/// What a benchmark writes and how
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// The number of points written
+    pub points: usize,
+    /// The number of points of a write
+    pub batch: usize,
+    /// The number of writes in flight at once
+    pub concurrency: usize,
+    /// The number of series the points are spread over
+    pub series: usize,
+    /// The number of fields of a point
+    pub fields: usize,
+}
+
+/// The outcome of a benchmark
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    /// The settings of the benchmark
+    pub config: BenchConfig,
+    /// The number of writes sent
+    pub batches: usize,
+    /// How long writing every point took
+    pub elapsed: Duration,
+    /// The latency of the writes, fastest first
+    pub latencies: Vec<Duration>,
+}
+
+impl BenchConfig {
+    /// Write `points` points of 4 fields over 100 series, 5000 at a time,
+    /// one write at a time.
+    pub fn new(points: usize) -> Self {
+        Self {
+            points,
+            batch: 5000,
+            concurrency: 1,
+            series: 100,
+            fields: 4,
+        }
+    }
+
+    /// Write `batch` points at a time.
+    pub fn batch(mut self, batch: usize) -> Self {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// Keep up to `concurrency` writes in flight.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Spread the points over `series` series.
+    pub fn series(mut self, series: usize) -> Self {
+        self.series = series.max(1);
+        self
+    }
+
+    /// Give each point `fields` fields.
+    pub fn fields(mut self, fields: usize) -> Self {
+        self.fields = fields.max(1);
+        self
+    }
+
+    /// The synthetic points, in batches
+    fn batches(&self) -> Vec<Vec<DataPoint>> {
+        let start = Utc::now().timestamp_nanos();
+        let points: Vec<_> = (0..self.points)
+            .map(|i| {
+                let mut point = DataPoint::builder("bench")
+                    .tag("series", format!("s{}", i % self.series))
+                    .timestamp(start + i as i64);
+                for field in 0..self.fields {
+                    point = point.field(format!("f{}", field), (i * (field + 1)) as f64 / 7.0);
+                }
+                point.build().expect("the point has fields")
+            })
+            .collect();
+        points
+            .chunks(self.batch)
+            .map(<[DataPoint]>::to_vec)
+            .collect()
+    }
+}
+
+impl BenchReport {
+    /// The number of points written per second.
+    pub fn points_per_second(&self) -> f64 {
+        self.config.points as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// The latency under which `percent` percent of the writes completed,
+    /// e.g. `latency(99.0)`, or zero if nothing was written.
+    pub fn latency(&self, percent: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percent / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "batch {:>6}, {:>2} in flight: {} points in {:.2?}, {:.0} points/s, \
+             latency p50 {:.2?} p90 {:.2?} p99 {:.2?} max {:.2?}",
+            self.config.batch,
+            self.config.concurrency,
+            self.config.points,
+            self.elapsed,
+            self.points_per_second(),
+            self.latency(50.0),
+            self.latency(90.0),
+            self.latency(99.0),
+            self.latency(100.0),
+        )
+    }
+}
+
+/// Write the points of `config` to `bucket` and report how it went,
+/// stopping at the first failed write.
+pub async fn run(
+    client: &Client,
+    bucket: &str,
+    config: &BenchConfig,
+) -> Result<BenchReport, RequestError> {
+    let batches = config.batches();
+    let count = batches.len();
+
+    let started = Instant::now();
+    let mut latencies: Vec<Duration> = stream::iter(batches)
+        .map(|batch| async move {
+            let sent = Instant::now();
+            client.write(bucket, stream::iter(batch)).await?;
+            Ok::<_, RequestError>(sent.elapsed())
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .try_collect()
+        .await?;
+    let elapsed = started.elapsed();
+    latencies.sort();
+
+    Ok(BenchReport {
+        config: *config,
+        batches: count,
+        elapsed,
+        latencies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn writes_every_point_in_batches() {
+        let writes = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "bench".into()))
+            .match_body(Matcher::Regex(
+                "^(bench,series=s[01] f0=[^ ]+ [0-9]+\n){1,3}$".to_string(),
+            ))
+            .with_status(204)
+            .expect(4)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let config = BenchConfig::new(10)
+            .batch(3)
+            .concurrency(2)
+            .series(2)
+            .fields(1);
+        let report = run(&client, "bench", &config).await.unwrap();
+
+        writes.assert();
+        assert_eq!(report.batches, 4);
+        assert_eq!(report.latencies.len(), 4);
+        assert!(report.latency(50.0) <= report.latency(100.0));
+        assert!(report.points_per_second() > 0.0);
+    }
+}
@@ -0,0 +1,1244 @@
+//! Blocking client
+//!
+//! A synchronous facade over the async [`Client`](crate::Client), for
+//! applications and build scripts that don't run an async runtime. Every
+//! call drives the corresponding async method to completion on a private
+//! single-threaded tokio runtime.
+//!
+//! The blocking client must not be used from within an async runtime; doing
+//! so panics, exactly like `reqwest::blocking` does.
+//!
+//! Calls returning streams, such as `query_spooled`, `tail` and the
+//! `stream_*` pagination calls, aren't mirrored; drive them on
+//! [`Client::inner`] from an async runtime instead.
+//!
+//! ```no_run
+//! let client = influxdb2::blocking::Client::new("http://localhost:8086", "org", "my-token");
+//! let health = client.health().unwrap();
+//! println!("{:?}", health.status);
+//! ```
+
+#[cfg(feature = "management")]
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use futures::TryStreamExt;
+use influxdb2_structmap::{FromMap, GenericMap};
+use reqwest::header::{HeaderMap, InvalidHeaderValue};
+use reqwest::Body;
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "management")]
+use crate::api::authorization::ListAuthorizationsRequest;
+#[cfg(feature = "management")]
+use crate::api::buckets::ListBucketsRequest;
+#[cfg(feature = "management")]
+use crate::api::bulk::BulkResults;
+use crate::api::capabilities::Capabilities;
+use crate::api::copy::CopyProgress;
+#[cfg(feature = "management")]
+use crate::api::dbrp::ListDbrpsRequest;
+#[cfg(feature = "management")]
+use crate::api::downsample::DownsampleSpec;
+#[cfg(feature = "management")]
+use crate::api::operation_logs::{ListOperationLogsRequest, LoggedResource};
+#[cfg(feature = "management")]
+use crate::api::organization::ListOrganizationRequest;
+#[cfg(feature = "management")]
+use crate::api::provision::{Plan, ProvisionSpec, Provisioned};
+#[cfg(feature = "management")]
+use crate::api::task::{CreateTaskRequest, ListRunsRequest, ListTasksRequest, UpdateTaskRequest};
+#[cfg(feature = "management")]
+use crate::api::usage::Usage;
+use crate::api::annotations::{Annotation, EventTime};
+use crate::api::export::{ExportError, ExportSink};
+use crate::api::latest::LatestValue;
+use crate::api::monitoring::{CheckStatus, Notification};
+use crate::api::schema::BucketReport;
+use crate::api::window::{Aggregate, Series, TimeRange};
+#[cfg(feature = "management")]
+use crate::api::users::ListUsersRequest;
+use crate::models::{
+    AnalyzeQueryResponse, AstResponse, FluxDuration, FluxSuggestion, FluxSuggestions, HealthCheck,
+    LanguageRequest, Query, WriteDataPoint,
+};
+#[cfg(feature = "management")]
+use crate::models::authorization::Status;
+#[cfg(feature = "management")]
+use crate::models::{
+    Authorization, Authorizations, Bucket, BucketId, Buckets, DashboardId, Dbrp, Dbrps, Label,
+    LabelId, LabelResponse, LabelSpec, LabelsResponse, Logs, OnboardingResponse, OperationLogs, OrgId, Organization, Organizations,
+    PatchBucketRequest, PostBucketRequest, Run, Runs, Task, TaskId, Tasks, Telegraf, TelegrafId,
+    TelegrafRequest, Telegrafs, Template, TemplateExportRequest, User, Users, Variable,
+    VariableChoice, VariableId, Variables,
+};
+#[cfg(feature = "telegraf-toml")]
+use crate::{api::telegraf::TelegrafConfigError, models::TelegrafConfig};
+use crate::{CancellationToken, RequestError, Target};
+
+/// Blocking client to a server supporting the InfluxData 2.0 API.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl Client {
+    /// Create a new blocking client pointing to the URL specified in
+    /// `protocol://server:port` format and using the specified token for
+    /// authorization.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying runtime could not be created.
+    pub fn new(
+        url: impl Into<String>,
+        org: impl Into<String>,
+        auth_token: impl Into<String>,
+    ) -> Self {
+        crate::Client::new(url, org, auth_token).into()
+    }
+
+    /// The async client this facade delegates to.
+    pub fn inner(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// A copy of this client that adds `headers` to every request it sends.
+    pub fn with_headers(&self, headers: HeaderMap) -> Self {
+        Self {
+            inner: self.inner.with_headers(headers),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// A copy of this client that sends `key` as the idempotency key of
+    /// every request, see `Client::with_idempotency_key`.
+    pub fn with_idempotency_key(&self, key: &str) -> Result<Self, InvalidHeaderValue> {
+        Ok(Self {
+            inner: self.inner.with_idempotency_key(key)?,
+            runtime: Arc::clone(&self.runtime),
+        })
+    }
+
+    /// A copy of this client whose default organization and bucket are
+    /// replaced by the parts set in `target`.
+    pub fn with_target(&self, target: impl Into<Target>) -> Self {
+        Self {
+            inner: self.inner.with_target(target),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// A copy of this client whose calls are aborted once `token` is
+    /// cancelled, e.g. from another thread during shutdown.
+    pub fn with_cancellation(&self, token: CancellationToken) -> Self {
+        Self {
+            inner: self.inner.with_cancellation(token),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    #[cfg(feature = "management")]
+    /// List the authorizations matching `request`.
+    pub fn list_authorizations(
+        &self,
+        request: ListAuthorizationsRequest,
+    ) -> Result<Authorizations, RequestError> {
+        self.block_on(self.inner.list_authorizations(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Replace the token with the ID `old_authorization_id` by a new one with
+    /// the same permissions, then deactivate the old one.
+    pub fn rotate_token(
+        &self,
+        old_authorization_id: &str,
+        new_description: &str,
+    ) -> Result<Authorization, RequestError> {
+        self.block_on(self.inner.rotate_token(old_authorization_id, new_description))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the tokens of the user named `user`.
+    pub fn find_tokens_by_user(&self, user: &str) -> Result<Vec<Authorization>, RequestError> {
+        self.block_on(self.inner.find_tokens_by_user(user))
+    }
+
+    #[cfg(feature = "management")]
+    /// The token of the organization named `org` with the description
+    /// `description`, if any.
+    pub fn find_token_by_description(
+        &self,
+        org: &str,
+        description: &str,
+    ) -> Result<Option<Authorization>, RequestError> {
+        self.block_on(self.inner.find_token_by_description(org, description))
+    }
+
+    #[cfg(feature = "management")]
+    /// The token with the ID `authorization_id`.
+    pub fn get_authorization(&self, authorization_id: &str) -> Result<Authorization, RequestError> {
+        self.block_on(self.inner.get_authorization(authorization_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Activate or deactivate the token with the ID `authorization_id`.
+    pub fn set_authorization_status(
+        &self,
+        authorization_id: &str,
+        status: Status,
+    ) -> Result<Authorization, RequestError> {
+        self.block_on(self.inner.set_authorization_status(authorization_id, status))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the token of `authorization`, returning it with its ID and
+    /// token value.
+    pub fn create_authorization(
+        &self,
+        authorization: &Authorization,
+    ) -> Result<Authorization, RequestError> {
+        self.block_on(self.inner.create_authorization(authorization))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete the token with the ID `authorization_id`, revoking it.
+    pub fn delete_authorization(&self, authorization_id: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_authorization(authorization_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the users matching `request`.
+    pub fn list_users(&self, request: ListUsersRequest) -> Result<Users, RequestError> {
+        self.block_on(self.inner.list_users(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// The user named `name`, or `None` if there is none.
+    pub fn find_user_by_name(&self, name: &str) -> Result<Option<User>, RequestError> {
+        self.block_on(self.inner.find_user_by_name(name))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the DBRP mappings matching `request`, of the organization of
+    /// this client if `request` names none.
+    pub fn list_dbrps(&self, request: ListDbrpsRequest) -> Result<Dbrps, RequestError> {
+        self.block_on(self.inner.list_dbrps(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// The names of the databases mapped in the organization of this
+    /// client, sorted.
+    pub fn databases(&self) -> Result<Vec<String>, RequestError> {
+        self.block_on(self.inner.databases())
+    }
+
+    #[cfg(feature = "management")]
+    /// The mappings of the retention policies of the database `db`, the
+    /// default one first.
+    pub fn retention_policies(&self, db: &str) -> Result<Vec<Dbrp>, RequestError> {
+        self.block_on(self.inner.retention_policies(db))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the Telegraf configurations, of the organization `org_id` if
+    /// given.
+    pub fn list_telegrafs(&self, org_id: Option<&OrgId>) -> Result<Telegrafs, RequestError> {
+        self.block_on(self.inner.list_telegrafs(org_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Retrieve a Telegraf configuration by ID
+    pub fn find_telegraf(&self, id: &TelegrafId) -> Result<Telegraf, RequestError> {
+        self.block_on(self.inner.find_telegraf(id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create a Telegraf configuration
+    pub fn create_telegraf(&self, request: TelegrafRequest) -> Result<Telegraf, RequestError> {
+        self.block_on(self.inner.create_telegraf(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Replace the Telegraf configuration `id` with `request`
+    pub fn update_telegraf(
+        &self,
+        id: &TelegrafId,
+        request: TelegrafRequest,
+    ) -> Result<Telegraf, RequestError> {
+        self.block_on(self.inner.update_telegraf(id, request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete a Telegraf configuration
+    pub fn delete_telegraf(&self, id: &TelegrafId) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_telegraf(id))
+    }
+
+    #[cfg(feature = "telegraf-toml")]
+    /// Retrieve a Telegraf configuration by ID, along with its configuration
+    /// parsed as TOML.
+    pub fn find_telegraf_config(
+        &self,
+        id: &TelegrafId,
+    ) -> Result<(Telegraf, TelegrafConfig), TelegrafConfigError> {
+        self.block_on(self.inner.find_telegraf_config(id))
+    }
+
+    #[cfg(feature = "telegraf-toml")]
+    /// Fetch the Telegraf configuration `id`, let `patch` change it as a TOML
+    /// document and write it back, returning the updated configuration.
+    pub fn patch_telegraf_config(
+        &self,
+        id: &TelegrafId,
+        patch: impl FnOnce(&mut TelegrafConfig),
+    ) -> Result<Telegraf, TelegrafConfigError> {
+        self.block_on(self.inner.patch_telegraf_config(id, patch))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the variables, of the organization `org_id` if given.
+    pub fn list_variables(&self, org_id: Option<&OrgId>) -> Result<Variables, RequestError> {
+        self.block_on(self.inner.list_variables(org_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Retrieve a variable by ID
+    pub fn find_variable(&self, id: &VariableId) -> Result<Variable, RequestError> {
+        self.block_on(self.inner.find_variable(id))
+    }
+
+    #[cfg(feature = "management")]
+    /// The choices of `variable`, in order.
+    pub fn evaluate_variable(&self, variable: &Variable) -> Result<Vec<VariableChoice>, RequestError> {
+        self.block_on(self.inner.evaluate_variable(variable))
+    }
+
+    #[cfg(feature = "management")]
+    /// Export the resources of `request` as a template.
+    pub fn export_template(&self, request: TemplateExportRequest) -> Result<Template, RequestError> {
+        self.block_on(self.inner.export_template(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Export the dashboard `id` as a template, along with the labels and
+    /// variables it depends on.
+    pub fn export_dashboard(&self, id: &DashboardId) -> Result<Template, RequestError> {
+        self.block_on(self.inner.export_dashboard(id))
+    }
+
+    #[cfg(feature = "management")]
+    /// List all buckets matching specified parameters
+    pub fn list_buckets(
+        &self,
+        request: Option<ListBucketsRequest>,
+    ) -> Result<Buckets, RequestError> {
+        self.block_on(self.inner.list_buckets(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create a new bucket in the organization specified by the 16-digit
+    /// hexadecimal `org_id` and with the bucket name `bucket`.
+    pub fn create_bucket(
+        &self,
+        post_bucket_request: Option<PostBucketRequest>,
+    ) -> Result<(), RequestError> {
+        self.block_on(self.inner.create_bucket(post_bucket_request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete a bucket specified by bucket id.
+    pub fn delete_bucket(&self, bucket_id: &BucketId) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_bucket(bucket_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete the buckets with the given IDs, `concurrency` at a time.
+    pub fn delete_buckets(
+        &self,
+        ids: impl IntoIterator<Item = BucketId>,
+        concurrency: usize,
+    ) -> BulkResults<BucketId> {
+        self.block_on(self.inner.delete_buckets(ids, concurrency))
+    }
+
+    #[cfg(feature = "management")]
+    /// Update the bucket specified by bucket id, returning it as updated.
+    pub fn update_bucket(
+        &self,
+        bucket_id: &BucketId,
+        request: &PatchBucketRequest,
+    ) -> Result<Bucket, RequestError> {
+        self.block_on(self.inner.update_bucket(bucket_id, request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the bucket `name` of the organization `org` or update its
+    /// retention, like [`Client::ensure_bucket`](crate::Client::ensure_bucket).
+    pub fn ensure_bucket(
+        &self,
+        org: &str,
+        name: &str,
+        retention: FluxDuration,
+    ) -> Result<Bucket, RequestError> {
+        self.block_on(self.inner.ensure_bucket(org, name, retention))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the bucket of `target` or update its retention, like
+    /// [`Client::ensure_bucket_to`](crate::Client::ensure_bucket_to).
+    pub fn ensure_bucket_to(
+        &self,
+        target: impl Into<Target>,
+        retention: FluxDuration,
+    ) -> Result<Bucket, RequestError> {
+        self.block_on(self.inner.ensure_bucket_to(target, retention))
+    }
+
+    /// Delete data points matching specified parameters from the bucket of
+    /// `target`.
+    pub fn delete(
+        &self,
+        target: impl Into<Target>,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        predicate: Option<String>,
+    ) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete(target, start, stop, predicate))
+    }
+
+    /// Copy the points of `source` from `start` to `stop`, both in UTC, to
+    /// `destination`, `batch` points at a time, calling `on_progress` after
+    /// each batch. Returns the progress once the range is copied, or the
+    /// error of the first batch that fails.
+    pub fn copy_bucket_range(
+        &self,
+        source: &str,
+        destination: &str,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        batch: usize,
+        mut on_progress: impl FnMut(CopyProgress),
+    ) -> Result<CopyProgress, RequestError> {
+        self.block_on(async {
+            let progress = self
+                .inner
+                .copy_bucket_range(source, destination, start, stop, batch);
+            futures::pin_mut!(progress);
+            let mut last = CopyProgress {
+                points: 0,
+                copied_until: start.max(stop),
+            };
+            while let Some(update) = progress.try_next().await? {
+                on_progress(update);
+                last = update;
+            }
+            Ok(last)
+        })
+    }
+
+    /// Probe the server for its build, version and the API families it
+    /// offers.
+    pub fn capabilities(&self) -> Result<Capabilities, RequestError> {
+        self.block_on(self.inner.capabilities())
+    }
+
+    /// Get health of an instance
+    pub fn health(&self) -> Result<HealthCheck, RequestError> {
+        self.block_on(self.inner.health())
+    }
+
+    #[cfg(feature = "management")]
+    /// List all Labels
+    pub fn labels(&self) -> Result<LabelsResponse, RequestError> {
+        self.block_on(self.inner.labels())
+    }
+
+    #[cfg(feature = "management")]
+    /// List all Labels by organization ID
+    pub fn labels_by_org(&self, org_id: &OrgId) -> Result<LabelsResponse, RequestError> {
+        self.block_on(self.inner.labels_by_org(org_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Retrieve a label by ID
+    pub fn find_label(&self, label_id: &LabelId) -> Result<LabelResponse, RequestError> {
+        self.block_on(self.inner.find_label(label_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create a Label
+    pub fn create_label(
+        &self,
+        org_id: &OrgId,
+        name: &str,
+        properties: Option<HashMap<String, String>>,
+    ) -> Result<LabelResponse, RequestError> {
+        self.block_on(self.inner.create_label(org_id, name, properties))
+    }
+
+    #[cfg(feature = "management")]
+    /// Update a Label
+    pub fn update_label(
+        &self,
+        name: Option<String>,
+        properties: Option<HashMap<String, String>>,
+        label_id: &LabelId,
+    ) -> Result<LabelResponse, RequestError> {
+        self.block_on(self.inner.update_label(name, properties, label_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete a Label
+    pub fn delete_label(&self, label_id: &LabelId) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_label(label_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete the labels with the given IDs, `concurrency` at a time.
+    pub fn delete_labels(
+        &self,
+        ids: impl IntoIterator<Item = LabelId>,
+        concurrency: usize,
+    ) -> BulkResults<LabelId> {
+        self.block_on(self.inner.delete_labels(ids, concurrency))
+    }
+
+    #[cfg(feature = "management")]
+    /// The ID of the label named `name` in the organization named `org`,
+    /// cached like [`Client::label_id_by_name`](crate::Client::label_id_by_name).
+    pub fn label_id_by_name(&self, org: &str, name: &str) -> Result<LabelId, RequestError> {
+        self.block_on(self.inner.label_id_by_name(org, name))
+    }
+
+    #[cfg(feature = "management")]
+    /// The ID of the label named `name` in the organization of `target`.
+    pub fn label_id_by_name_to(
+        &self,
+        target: impl Into<Target>,
+        name: &str,
+    ) -> Result<LabelId, RequestError> {
+        self.block_on(self.inner.label_id_by_name_to(target, name))
+    }
+
+    #[cfg(feature = "management")]
+    /// Converge the labels of the organization named `org` to `desired`.
+    pub fn ensure_labels(
+        &self,
+        org: &str,
+        desired: Vec<LabelSpec>,
+    ) -> Result<Vec<Label>, RequestError> {
+        self.block_on(self.inner.ensure_labels(org, desired))
+    }
+
+    #[cfg(feature = "management")]
+    /// Converge the labels of the organization of `target` to `desired`.
+    pub fn ensure_labels_to(
+        &self,
+        target: impl Into<Target>,
+        desired: Vec<LabelSpec>,
+    ) -> Result<Vec<Label>, RequestError> {
+        self.block_on(self.inner.ensure_labels_to(target, desired))
+    }
+
+    #[cfg(feature = "management")]
+    /// The changes converging the server to `spec`, see
+    /// [`Client::provision_plan`](crate::Client::provision_plan).
+    pub fn provision_plan(&self, spec: &ProvisionSpec) -> Result<Plan, RequestError> {
+        self.block_on(self.inner.provision_plan(spec))
+    }
+
+    #[cfg(feature = "management")]
+    /// Make the changes of `plan`.
+    pub fn apply_provision(&self, plan: Plan) -> Result<Provisioned, RequestError> {
+        self.block_on(self.inner.apply_provision(plan))
+    }
+
+    #[cfg(feature = "management")]
+    /// Plan and apply `spec`.
+    pub fn provision(&self, spec: &ProvisionSpec) -> Result<Provisioned, RequestError> {
+        self.block_on(self.inner.provision(spec))
+    }
+
+    #[cfg(feature = "management")]
+    /// A page of the operation log of `resource`.
+    pub fn operation_logs(
+        &self,
+        resource: impl Into<LoggedResource>,
+        request: ListOperationLogsRequest,
+    ) -> Result<OperationLogs, RequestError> {
+        self.block_on(self.inner.operation_logs(resource, request))
+    }
+
+    #[cfg(feature = "management")]
+    /// The usage of the organization with the ID `org_id` from `start` to
+    /// `stop`, or to now.
+    pub fn usage(
+        &self,
+        org_id: &OrgId,
+        start: chrono::DateTime<chrono::Utc>,
+        stop: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Usage, RequestError> {
+        self.block_on(self.inner.usage(org_id, start, stop))
+    }
+
+    #[cfg(feature = "management")]
+    /// List all organizations.
+    pub fn list_organizations(
+        &self,
+        request: ListOrganizationRequest,
+    ) -> Result<Organizations, RequestError> {
+        self.block_on(self.inner.list_organizations(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create an organization named `name`, returning it.
+    pub fn create_organization(
+        &self,
+        name: &str,
+        description: Option<String>,
+    ) -> Result<Organization, RequestError> {
+        self.block_on(self.inner.create_organization(name, description))
+    }
+
+    #[cfg(feature = "management")]
+    /// The ID of the organization named `name`, cached like
+    /// [`Client::org_id`](crate::Client::org_id).
+    pub fn org_id(&self, name: &str) -> Result<OrgId, RequestError> {
+        self.block_on(self.inner.org_id(name))
+    }
+
+    /// Get Query Suggestions
+    pub fn query_suggestions(&self) -> Result<FluxSuggestions, RequestError> {
+        self.block_on(self.inner.query_suggestions())
+    }
+
+    /// Query Suggestions with name
+    pub fn query_suggestions_name(&self, name: &str) -> Result<FluxSuggestion, RequestError> {
+        self.block_on(self.inner.query_suggestions_name(name))
+    }
+
+    /// Query
+    pub fn query<T: FromMap>(&self, query: Option<Query>) -> Result<Vec<T>, RequestError> {
+        self.block_on(self.inner.query::<T>(query))
+    }
+
+    /// Query in the organization of `target`
+    pub fn query_to<T: FromMap>(
+        &self,
+        target: impl Into<Target>,
+        query: Option<Query>,
+    ) -> Result<Vec<T>, RequestError> {
+        self.block_on(self.inner.query_to::<T>(target, query))
+    }
+
+    /// Query, returning each row as a `GenericMap` of column name to value
+    pub fn query_raw(&self, query: Option<Query>) -> Result<Vec<GenericMap>, RequestError> {
+        self.block_on(self.inner.query_raw(query))
+    }
+
+    /// Query like `query_raw`, in the organization of `target`
+    pub fn query_raw_to(
+        &self,
+        target: impl Into<Target>,
+        query: Option<Query>,
+    ) -> Result<Vec<GenericMap>, RequestError> {
+        self.block_on(self.inner.query_raw_to(target, query))
+    }
+
+    /// Query, deserializing each row into `T` through serde
+    pub fn query_json<T: DeserializeOwned>(
+        &self,
+        query: Option<Query>,
+    ) -> Result<Vec<T>, RequestError> {
+        self.block_on(self.inner.query_json::<T>(query))
+    }
+
+    /// Query like `query_json`, in the organization of `target`
+    pub fn query_json_to<T: DeserializeOwned>(
+        &self,
+        target: impl Into<Target>,
+        query: Option<Query>,
+    ) -> Result<Vec<T>, RequestError> {
+        self.block_on(self.inner.query_json_to::<T>(target, query))
+    }
+
+    /// Estimate the number of series of `measurement` in `bucket`
+    pub fn cardinality(&self, bucket: &str, measurement: &str) -> Result<i64, RequestError> {
+        self.block_on(self.inner.cardinality(bucket, measurement))
+    }
+
+    /// Estimate the number of series in `bucket`
+    pub fn bucket_cardinality(&self, bucket: &str) -> Result<i64, RequestError> {
+        self.block_on(self.inner.bucket_cardinality(bucket))
+    }
+
+    /// Count the distinct values of a tag of `measurement` in `bucket`
+    pub fn tag_value_count(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        tag: &str,
+    ) -> Result<i64, RequestError> {
+        self.block_on(self.inner.tag_value_count(bucket, measurement, tag))
+    }
+
+    /// Report the series of `bucket`, in all and per measurement, and the
+    /// times of its oldest and newest points
+    pub fn bucket_report(&self, bucket: &str) -> Result<BucketReport, RequestError> {
+        self.block_on(self.inner.bucket_report(bucket))
+    }
+
+    /// Aggregate a field of `measurement` in `bucket` over windows of
+    /// `every` across `range`, one series per tag set
+    pub fn aggregate_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+        aggregate: Aggregate,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.block_on(
+            self.inner
+                .aggregate_over(bucket, measurement, field, range, every, aggregate),
+        )
+    }
+
+    /// The mean of a field of `measurement` in `bucket` over windows of
+    /// `every` across `range`
+    pub fn mean_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.block_on(self.inner.mean_over(bucket, measurement, field, range, every))
+    }
+
+    /// The sum of a field of `measurement` in `bucket` over windows of
+    /// `every` across `range`
+    pub fn sum_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.block_on(self.inner.sum_over(bucket, measurement, field, range, every))
+    }
+
+    /// The minimum of a field of `measurement` in `bucket` over windows of
+    /// `every` across `range`
+    pub fn min_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.block_on(self.inner.min_over(bucket, measurement, field, range, every))
+    }
+
+    /// The maximum of a field of `measurement` in `bucket` over windows of
+    /// `every` across `range`
+    pub fn max_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.block_on(self.inner.max_over(bucket, measurement, field, range, every))
+    }
+
+    /// The number of values of a field of `measurement` in `bucket` in
+    /// windows of `every` across `range`
+    pub fn count_over(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+    ) -> Result<Vec<Series>, RequestError> {
+        self.block_on(self.inner.count_over(bucket, measurement, field, range, every))
+    }
+
+    /// Aggregate every field of `bucket` over windows of `every` across
+    /// `range`, a chunk at a time, and write the rows to `sink`
+    pub fn export<S>(
+        &self,
+        bucket: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+        aggregate: Aggregate,
+        sink: &mut S,
+    ) -> Result<usize, ExportError>
+    where
+        S: ExportSink + ?Sized,
+    {
+        self.block_on(self.inner.export(bucket, range, every, aggregate, sink))
+    }
+
+    /// Like [`Client::export`], querying `chunk` of the range at a time
+    pub fn export_chunked<S>(
+        &self,
+        bucket: &str,
+        range: impl Into<TimeRange>,
+        every: FluxDuration,
+        aggregate: Aggregate,
+        chunk: FluxDuration,
+        sink: &mut S,
+    ) -> Result<usize, ExportError>
+    where
+        S: ExportSink + ?Sized,
+    {
+        self.block_on(
+            self.inner
+                .export_chunked(bucket, range, every, aggregate, chunk, sink),
+        )
+    }
+
+    #[cfg(feature = "arrow")]
+    #[allow(clippy::result_large_err)]
+    /// Query, writing the results to the Arrow IPC file at `path`
+    pub fn query_to_arrow_ipc(
+        &self,
+        query: Option<Query>,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<usize, crate::api::arrow::ExportError> {
+        self.block_on(self.inner.query_to_arrow_ipc(query, path))
+    }
+
+    /// The latest value of a field of each series of `measurement` in
+    /// `bucket` whose tags equal `tag_filters`
+    pub fn last_value(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        tag_filters: &[(&str, &str)],
+    ) -> Result<Vec<LatestValue>, RequestError> {
+        self.block_on(self.inner.last_value(bucket, measurement, field, tag_filters))
+    }
+
+    /// Like [`Client::last_value`], looking back `lookback` for points
+    pub fn last_value_within(
+        &self,
+        bucket: &str,
+        measurement: &str,
+        field: &str,
+        tag_filters: &[(&str, &str)],
+        lookback: FluxDuration,
+    ) -> Result<Vec<LatestValue>, RequestError> {
+        self.block_on(
+            self.inner
+                .last_value_within(bucket, measurement, field, tag_filters, lookback),
+        )
+    }
+
+    /// The statuses written by checks across `range`
+    pub fn check_statuses(
+        &self,
+        range: impl Into<TimeRange>,
+    ) -> Result<Vec<CheckStatus>, RequestError> {
+        self.block_on(self.inner.check_statuses(range))
+    }
+
+    /// The latest status of each check
+    pub fn current_check_statuses(&self) -> Result<Vec<CheckStatus>, RequestError> {
+        self.block_on(self.inner.current_check_statuses())
+    }
+
+    /// The notifications sent by notification rules across `range`
+    pub fn notifications(
+        &self,
+        range: impl Into<TimeRange>,
+    ) -> Result<Vec<Notification>, RequestError> {
+        self.block_on(self.inner.notifications(range))
+    }
+
+    /// Write an annotation titled `title` and tagged with `tags` of the event
+    /// at `time` to `bucket`
+    pub fn annotate(
+        &self,
+        bucket: &str,
+        title: &str,
+        tags: &[&str],
+        time: impl Into<EventTime>,
+    ) -> Result<(), RequestError> {
+        self.block_on(self.inner.annotate(bucket, title, tags, time))
+    }
+
+    /// Write `annotations` to `bucket`
+    pub fn write_annotations(
+        &self,
+        bucket: &str,
+        annotations: &[Annotation],
+    ) -> Result<(), RequestError> {
+        self.block_on(self.inner.write_annotations(bucket, annotations))
+    }
+
+    /// The annotations of `bucket` starting across `range` and tagged with
+    /// every tag of `tags`
+    pub fn annotations(
+        &self,
+        bucket: &str,
+        range: impl Into<TimeRange>,
+        tags: &[&str],
+    ) -> Result<Vec<Annotation>, RequestError> {
+        self.block_on(self.inner.annotations(bucket, range, tags))
+    }
+
+    /// Analyze Query
+    pub fn query_analyze(
+        &self,
+        query: Option<Query>,
+    ) -> Result<AnalyzeQueryResponse, RequestError> {
+        self.block_on(self.inner.query_analyze(query))
+    }
+
+    /// Get Query AST Repsonse
+    pub fn query_ast(
+        &self,
+        language_request: Option<LanguageRequest>,
+    ) -> Result<AstResponse, RequestError> {
+        self.block_on(self.inner.query_ast(language_request))
+    }
+
+    /// Format `query` in the canonical layout, from the AST the server parses
+    /// it into
+    pub fn format_query(&self, query: &str) -> Result<String, RequestError> {
+        self.block_on(self.inner.format_query(query))
+    }
+
+    /// Get the readiness of an instance at startup
+    pub fn ready(&self) -> Result<bool, RequestError> {
+        self.block_on(self.inner.ready())
+    }
+
+    #[cfg(feature = "management")]
+    /// Check if database has default user, org, bucket
+    pub fn is_onboarding_allowed(&self) -> Result<bool, RequestError> {
+        self.block_on(self.inner.is_onboarding_allowed())
+    }
+
+    #[cfg(feature = "management")]
+    /// Set up initial user, org and bucket
+    pub fn onboarding(
+        &self,
+        username: &str,
+        org: &str,
+        bucket: &str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> Result<OnboardingResponse, RequestError> {
+        self.block_on(self.inner.onboarding(
+            username,
+            org,
+            bucket,
+            password,
+            retention_period_hrs,
+            retention_period_seconds,
+        ))
+    }
+
+    #[cfg(feature = "management")]
+    /// Set up a new user, org and bucket
+    pub fn post_setup_user(
+        &self,
+        username: &str,
+        org: &str,
+        bucket: &str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> Result<OnboardingResponse, RequestError> {
+        self.block_on(self.inner.post_setup_user(
+            username,
+            org,
+            bucket,
+            password,
+            retention_period_hrs,
+            retention_period_seconds,
+        ))
+    }
+
+    #[cfg(feature = "management")]
+    /// List all tasks.
+    pub fn list_tasks(&self, request: ListTasksRequest) -> Result<Tasks, RequestError> {
+        self.block_on(self.inner.list_tasks(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the tasks of the organization of `target`.
+    pub fn list_tasks_to(
+        &self,
+        target: impl Into<Target>,
+        request: ListTasksRequest,
+    ) -> Result<Tasks, RequestError> {
+        self.block_on(self.inner.list_tasks_to(target, request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create a new task.
+    pub fn create_task(&self, request: CreateTaskRequest) -> Result<(), RequestError> {
+        self.block_on(self.inner.create_task(request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Update the task specified by task_id, returning it as updated.
+    pub fn update_task(
+        &self,
+        task_id: &TaskId,
+        request: &UpdateTaskRequest,
+    ) -> Result<Task, RequestError> {
+        self.block_on(self.inner.update_task(task_id, request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the task `name` or update it to `spec`, like
+    /// [`Client::ensure_task`](crate::Client::ensure_task).
+    pub fn ensure_task(&self, name: &str, spec: CreateTaskRequest) -> Result<Task, RequestError> {
+        self.block_on(self.inner.ensure_task(name, spec))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the task `name` in the organization of `target` or update it
+    /// to `spec`.
+    pub fn ensure_task_to(
+        &self,
+        target: impl Into<Target>,
+        name: &str,
+        spec: CreateTaskRequest,
+    ) -> Result<Task, RequestError> {
+        self.block_on(self.inner.ensure_task_to(target, name, spec))
+    }
+
+    #[cfg(feature = "management")]
+    /// Resume the scheduled runs of the task specified by task_id.
+    pub fn activate_task(&self, task_id: &TaskId) -> Result<Task, RequestError> {
+        self.block_on(self.inner.activate_task(task_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Pause the scheduled runs of the task specified by task_id.
+    pub fn deactivate_task(&self, task_id: &TaskId) -> Result<Task, RequestError> {
+        self.block_on(self.inner.deactivate_task(task_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete a task specified by task_id.
+    pub fn delete_task(&self, task_id: &TaskId) -> Result<(), RequestError> {
+        self.block_on(self.inner.delete_task(task_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Delete the tasks with the given IDs, `concurrency` at a time.
+    pub fn delete_tasks(
+        &self,
+        ids: impl IntoIterator<Item = TaskId>,
+        concurrency: usize,
+    ) -> BulkResults<TaskId> {
+        self.block_on(self.inner.delete_tasks(ids, concurrency))
+    }
+
+    #[cfg(feature = "management")]
+    /// Activate the tasks with the given IDs, `concurrency` at a time.
+    pub fn activate_tasks(
+        &self,
+        ids: impl IntoIterator<Item = TaskId>,
+        concurrency: usize,
+    ) -> BulkResults<TaskId, Task> {
+        self.block_on(self.inner.activate_tasks(ids, concurrency))
+    }
+
+    #[cfg(feature = "management")]
+    /// Deactivate the tasks with the given IDs, `concurrency` at a time.
+    pub fn deactivate_tasks(
+        &self,
+        ids: impl IntoIterator<Item = TaskId>,
+        concurrency: usize,
+    ) -> BulkResults<TaskId, Task> {
+        self.block_on(self.inner.deactivate_tasks(ids, concurrency))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the downsampling task of `spec` in the organization of this
+    /// client.
+    pub fn create_downsample_task(&self, spec: &DownsampleSpec) -> Result<(), RequestError> {
+        self.block_on(self.inner.create_downsample_task(spec))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the runs of the task specified by task_id.
+    pub fn list_task_runs(
+        &self,
+        task_id: &TaskId,
+        request: ListRunsRequest,
+    ) -> Result<Runs, RequestError> {
+        self.block_on(self.inner.list_task_runs(task_id, request))
+    }
+
+    #[cfg(feature = "management")]
+    /// Start a run of the task specified by task_id now, outside of its
+    /// schedule, returning the scheduled run.
+    pub fn run_task(&self, task_id: &TaskId) -> Result<Run, RequestError> {
+        self.block_on(self.inner.run_task(task_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Get the run specified by run_id of the task specified by task_id.
+    pub fn get_task_run(&self, task_id: &TaskId, run_id: &str) -> Result<Run, RequestError> {
+        self.block_on(self.inner.get_task_run(task_id, run_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the log events of the task specified by task_id, over all its
+    /// runs.
+    pub fn list_task_logs(&self, task_id: &TaskId) -> Result<Logs, RequestError> {
+        self.block_on(self.inner.list_task_logs(task_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// List the log events of the run specified by run_id of the task
+    /// specified by task_id.
+    pub fn list_run_logs(&self, task_id: &TaskId, run_id: &str) -> Result<Logs, RequestError> {
+        self.block_on(self.inner.list_run_logs(task_id, run_id))
+    }
+
+    #[cfg(feature = "management")]
+    /// Create the task equivalent to the InfluxDB 1.x continuous query
+    /// `influxql` in the organization of this client.
+    pub fn migrate_continuous_query(&self, influxql: &str) -> Result<(), RequestError> {
+        self.block_on(self.inner.migrate_continuous_query(influxql))
+    }
+
+    /// Write line protocol data to the organization and bucket of `target`.
+    pub fn write_line_protocol_to(
+        &self,
+        target: impl Into<Target>,
+        body: impl Into<Body> + Send,
+    ) -> Result<(), RequestError> {
+        self.block_on(self.inner.write_line_protocol_to(target, body))
+    }
+
+    /// Write line protocol data to the specified organization and bucket.
+    #[deprecated(note = "use `write_line_protocol_to`, which takes a `Target`")]
+    pub fn write_line_protocol(
+        &self,
+        org: &str,
+        bucket: &str,
+        body: impl Into<Body> + Send,
+    ) -> Result<(), RequestError> {
+        self.write_line_protocol_to(Target::org(org).with_bucket(bucket), body)
+    }
+
+    /// Write `DataPoint`s to the organization and bucket of `target`.
+    pub fn write<T>(
+        &self,
+        target: impl Into<Target>,
+        points: impl IntoIterator<Item = T>,
+    ) -> Result<(), RequestError>
+    where
+        T: WriteDataPoint + Send + Sync + 'static,
+    {
+        let points: Vec<T> = points.into_iter().collect();
+        self.block_on(self.inner.write(target, futures::stream::iter(points)))
+    }
+}
+
+impl From<crate::Client> for Client {
+    /// Wrap an async client into a blocking one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying runtime could not be created.
+    fn from(inner: crate::Client) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to create the runtime of the blocking client");
+
+        Self {
+            inner,
+            runtime: Arc::new(runtime),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DataPoint;
+    use mockito::mock;
+
+    #[test]
+    fn ready() {
+        let mock_server = mock("GET", "/ready").create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+
+        let _result = client.ready();
+
+        mock_server.assert();
+    }
+
+    #[test]
+    fn writing_points() {
+        let org = "some-org";
+        let bucket = "some-bucket";
+        let token = "some-token";
+
+        let mock_server = mock(
+            "POST",
+            format!("/api/v2/write?bucket={}&org={}", bucket, org).as_str(),
+        )
+        .match_header("Authorization", format!("Token {}", token).as_str())
+        .match_body("cpu,host=server01 usage=0.5\n")
+        .create();
+
+        let client = Client::new(mockito::server_url(), org, token);
+
+        let points = vec![DataPoint::builder("cpu")
+            .tag("host", "server01")
+            .field("usage", 0.5)
+            .build()
+            .unwrap()];
+
+        let _result = client.write(bucket, points);
+
+        mock_server.assert();
+    }
+
+    #[cfg(feature = "management")]
+    #[test]
+    fn deleting_labels() {
+        let mock_server = mock("DELETE", "/api/v2/labels/0000000000000b1c")
+            .with_status(204)
+            .expect(1)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+
+        let results = client.delete_labels(vec![LabelId::from("0000000000000b1c")], 2);
+
+        mock_server.assert();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok());
+    }
+}
@@ -0,0 +1,723 @@
+//! Client building
+
+use snafu::{ResultExt, Snafu};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::request::Timeouts;
+#[cfg(feature = "management")]
+use crate::api::organization::{OrgIdCache, DEFAULT_ORG_ID_TTL};
+#[cfg(feature = "management")]
+use crate::api::users::UserCache;
+use crate::clock::{Clock, SystemClock};
+use crate::compression::{ContentEncoding, Negotiation, WriteCompression};
+use crate::line_protocol::naming::Naming;
+use crate::line_protocol::{Precision, TimestampCheck};
+use crate::retry::RetryPolicy;
+#[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+use crate::vcr::Cassette;
+use crate::{Client, RequestError};
+use reqwest::Method;
+
+/// Errors that occur while building a `Client`
+#[derive(Debug, Snafu)]
+pub enum BuildError {
+    /// The underlying `reqwest` client could not be created, e.g. because
+    /// the TLS backend failed to initialize.
+    #[snafu(display("Error while building the HTTP client: {}", source))]
+    Reqwest {
+        /// The underlying error object from `reqwest`.
+        source: reqwest::Error,
+    },
+
+    /// The server URL could not be parsed.
+    #[snafu(display("Invalid server URL `{}`: {}", url, source))]
+    InvalidUrl {
+        /// The URL given to `Client::builder`
+        url: String,
+        /// The underlying error object from `url`.
+        source: url::ParseError,
+    },
+
+    /// The server URL uses a scheme other than `http` or `https`.
+    #[snafu(display("Unsupported scheme `{}` in server URL `{}`", scheme, url))]
+    UnsupportedScheme {
+        /// The URL given to `Client::builder`
+        url: String,
+        /// The scheme of the URL
+        scheme: String,
+    },
+
+    /// No InfluxDB server answered `/ping` at the server URL.
+    #[snafu(display("Could not reach an InfluxDB server at `{}`: {}", url, source))]
+    Unreachable {
+        /// The URL given to `Client::builder`
+        url: String,
+        /// The error of the ping
+        #[snafu(source(from(RequestError, Box::new)))]
+        source: Box<RequestError>,
+    },
+
+    /// The server rejected the token.
+    #[snafu(display("The server rejected the token: {}", source))]
+    Unauthorized {
+        /// The error of the token check
+        #[snafu(source(from(RequestError, Box::new)))]
+        source: Box<RequestError>,
+    },
+}
+
+/// The HTTP versions a `Client` may speak
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// HTTP/1.1, or HTTP/2 when negotiated through TLS ALPN
+    #[default]
+    Negotiate,
+    /// HTTP/1.1 only, never HTTP/2
+    Http1Only,
+    /// HTTP/2 only, without negotiation ("prior knowledge"); the server
+    /// must speak HTTP/2, e.g. InfluxDB behind an h2c proxy
+    Http2PriorKnowledge,
+}
+
+/// Incrementally constructs a `Client`.
+///
+/// Create this via `Client::builder`. Settings left untouched keep the
+/// defaults of `reqwest`.
+///
+/// The connection settings are not available on wasm, where the browser
+/// manages connections.
+#[derive(Debug)]
+pub struct ClientBuilder {
+    url: String,
+    org: String,
+    auth_token: String,
+    bucket: Option<String>,
+    log_requests: bool,
+    write_precision: Option<Precision>,
+    timestamp_check: TimestampCheck,
+    write_compression: WriteCompression,
+    naming: Option<Arc<dyn Naming>>,
+    clock: Arc<dyn Clock>,
+    retry_policy: Option<RetryPolicy>,
+    max_response_size: Option<usize>,
+    endpoint_overrides: BTreeMap<String, String>,
+    #[cfg(feature = "management")]
+    org_id_ttl: std::time::Duration,
+    #[cfg(feature = "management")]
+    user_cache_ttl: std::time::Duration,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeouts: Timeouts,
+    #[cfg(not(target_arch = "wasm32"))]
+    pool_max_idle_per_host: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pool_idle_timeout: Option<Option<Duration>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    tcp_keepalive: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    tcp_nodelay: Option<bool>,
+    #[cfg(not(target_arch = "wasm32"))]
+    http_version: HttpVersion,
+    #[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+    cassette: Option<Cassette>,
+    #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+    danger_accept_invalid_certs: bool,
+    #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+    pinned_certificates: Vec<reqwest::Certificate>,
+}
+
+impl ClientBuilder {
+    pub(crate) fn new(
+        url: impl Into<String>,
+        org: impl Into<String>,
+        auth_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            org: org.into(),
+            auth_token: auth_token.into(),
+            bucket: None,
+            log_requests: false,
+            write_precision: None,
+            timestamp_check: TimestampCheck::Off,
+            write_compression: WriteCompression::default(),
+            naming: None,
+            clock: Arc::new(SystemClock),
+            retry_policy: None,
+            max_response_size: None,
+            endpoint_overrides: BTreeMap::new(),
+            #[cfg(feature = "management")]
+            org_id_ttl: DEFAULT_ORG_ID_TTL,
+            #[cfg(feature = "management")]
+            user_cache_ttl: std::time::Duration::ZERO,
+            #[cfg(not(target_arch = "wasm32"))]
+            timeouts: Timeouts::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pool_max_idle_per_host: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pool_idle_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            tcp_keepalive: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            tcp_nodelay: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            http_version: HttpVersion::default(),
+            #[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+            cassette: None,
+            #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+            danger_accept_invalid_certs: false,
+            #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+            pinned_certificates: Vec::new(),
+        }
+    }
+
+    /// Sets the bucket written to when a call doesn't name one.
+    pub fn with_bucket(mut self, bucket: impl Into<String>) -> Self {
+        self.bucket = Some(bucket.into());
+        self
+    }
+
+    /// Logs the method, URL, headers, status, duration and body of every
+    /// request and response as `tracing` events under the
+    /// `influxdb2::requests` target. The `Authorization` header and token
+    /// query parameters are redacted and bodies are truncated to 1 KiB.
+    pub fn with_request_logging(mut self, enabled: bool) -> Self {
+        self.log_requests = enabled;
+        self
+    }
+
+    /// Records the requests of the client and their responses to
+    /// `cassette`, or answers them from it, see [`crate::vcr`].
+    #[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Sets the precision of the timestamps written, sent as the `precision`
+    /// parameter of every write. InfluxDB reads nanoseconds by default.
+    pub fn with_write_precision(mut self, precision: Precision) -> Self {
+        self.write_precision = Some(precision);
+        self
+    }
+
+    /// Sets whether `Client::write` checks that the timestamps of the points
+    /// are plausible in the precision of the write, catching timestamps in
+    /// the wrong unit before they land in 1970. Off by default.
+    pub fn with_timestamp_check(mut self, check: TimestampCheck) -> Self {
+        self.timestamp_check = check;
+        self
+    }
+
+    /// Sets how write bodies are encoded, see [`crate::compression`]. Writes
+    /// aren't compressed by default.
+    pub fn with_write_compression(mut self, compression: WriteCompression) -> Self {
+        self.write_compression = compression;
+        self
+    }
+
+    /// Sets whether write bodies are compressed with gzip, sent with
+    /// `Content-Encoding: gzip`. Shorthand for `with_write_compression`.
+    pub fn with_gzip(self, enabled: bool) -> Self {
+        self.with_write_compression(WriteCompression::Fixed(if enabled {
+            ContentEncoding::Gzip
+        } else {
+            ContentEncoding::Identity
+        }))
+    }
+
+    /// Sets how `Client::write` renames the measurements, tag keys and field
+    /// keys of the points written, see [`crate::line_protocol::naming`].
+    /// Names are kept as they are by default.
+    pub fn with_naming(mut self, naming: impl Naming + 'static) -> Self {
+        self.naming = Some(Arc::new(naming));
+        self
+    }
+
+    /// Sets the clock the client reads the time from and waits with, see
+    /// [`crate::clock`]. The system clock by default.
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sends writes and queries again as set by `policy` when the server is
+    /// unavailable or limits the rate of requests, see [`crate::retry`]. They
+    /// are sent once by default, and always on `wasm32`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        // Without a timer on wasm32, retries would follow each other at once
+        if cfg!(not(target_arch = "wasm32")) {
+            self.retry_policy = Some(policy);
+        }
+        self
+    }
+
+    /// Sets the largest JSON response read, in bytes; larger ones fail with
+    /// `RequestError::ResponseTooLarge` instead of being buffered. Responses
+    /// aren't limited by default.
+    pub fn with_max_response_size(mut self, bytes: usize) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Sends the requests to `endpoint`, a path of the InfluxDB API such as
+    /// `/api/v2/query`, and to the paths below it, to `target` instead: a
+    /// route under the server URL, such as `/gateway/flux`, or a full URL,
+    /// such as `https://query.example.com/v2/query`. The longest endpoint
+    /// matching a path wins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn example() -> Result<(), influxdb2::BuildError> {
+    /// let client = influxdb2::Client::builder("https://gateway.example.com", "org", "token")
+    ///     .with_endpoint_override("/api/v2/query", "/influx/flux")
+    ///     .with_endpoint_override("/api/v2/write", "https://ingest.example.com/lp")
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_endpoint_override(
+        mut self,
+        endpoint: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Self {
+        let mut target = target.into();
+        if !target.contains("://") && !target.starts_with('/') {
+            target.insert(0, '/');
+        }
+        let endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self.endpoint_overrides.insert(endpoint, target);
+        self
+    }
+
+    /// Sets how long `Client::org_id` reuses a resolved organization ID,
+    /// [`DEFAULT_ORG_ID_TTL`] by default. A zero TTL disables the cache.
+    #[cfg(feature = "management")]
+    pub fn with_org_id_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.org_id_ttl = ttl;
+        self
+    }
+
+    /// Sets how long `Client::find_user_by_name` reuses a found user. Users
+    /// aren't cached by default.
+    #[cfg(feature = "management")]
+    pub fn with_user_cache_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.user_cache_ttl = ttl;
+        self
+    }
+
+    /// Sets the timeout of writes and deletes of data points.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.write = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout of queries, which may legitimately run for minutes.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.query = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout of all other calls, such as health checks and the
+    /// management of buckets, tasks and labels.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_management_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.management = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept open per host.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Sets how long an idle connection is kept open for reuse; `None` keeps
+    /// idle connections open indefinitely.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Enables TCP keep-alive probes on every connection, sent at the given
+    /// interval.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Sets `TCP_NODELAY` on every connection.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.tcp_nodelay = Some(enabled);
+        self
+    }
+
+    /// Sets the HTTP versions the client may speak, e.g. to stay on HTTP/1
+    /// behind proxies that mishandle HTTP/2.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_http_version(mut self, version: HttpVersion) -> Self {
+        self.http_version = version;
+        self
+    }
+
+    /// Skips verification of the server's TLS certificate, accepting invalid
+    /// and self-signed ones.
+    ///
+    /// # Warning
+    ///
+    /// Any server can then impersonate the Influx server and read the token
+    /// of this client. Only use this in lab environments; prefer
+    /// [`with_pinned_certificate`](Self::with_pinned_certificate) for
+    /// appliances with self-signed certificates.
+    #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+    pub fn with_danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.danger_accept_invalid_certs = accept;
+        self
+    }
+
+    /// Trusts only the pinned certificates, instead of the system's root
+    /// certificates, when verifying the server. Call it once per certificate
+    /// to pin several.
+    ///
+    /// ```
+    /// # fn example(pem: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    /// let certificate = reqwest::Certificate::from_pem(pem)?;
+    /// let client = influxdb2::Client::builder("https://appliance:8086", "org", "my-token")
+    ///     .with_pinned_certificate(certificate)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+    pub fn with_pinned_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.pinned_certificates.push(certificate);
+        self
+    }
+
+    /// Constructs the `Client`.
+    pub fn build(self) -> Result<Client, BuildError> {
+        #[allow(unused_mut)]
+        let mut http = reqwest::Client::builder();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(max) = self.pool_max_idle_per_host {
+                http = http.pool_max_idle_per_host(max);
+            }
+            if let Some(timeout) = self.pool_idle_timeout {
+                http = http.pool_idle_timeout(timeout);
+            }
+            if let Some(interval) = self.tcp_keepalive {
+                http = http.tcp_keepalive(interval);
+            }
+            if let Some(enabled) = self.tcp_nodelay {
+                http = http.tcp_nodelay(enabled);
+            }
+            http = match self.http_version {
+                HttpVersion::Negotiate => http,
+                HttpVersion::Http1Only => http.http1_only(),
+                HttpVersion::Http2PriorKnowledge => http.http2_prior_knowledge(),
+            };
+        }
+
+        #[cfg(all(not(target_arch = "wasm32"), any(feature = "native-tls", feature = "rustls")))]
+        {
+            http = http.danger_accept_invalid_certs(self.danger_accept_invalid_certs);
+            if !self.pinned_certificates.is_empty() {
+                http = http.tls_built_in_root_certs(false);
+                for certificate in self.pinned_certificates {
+                    http = http.add_root_certificate(certificate);
+                }
+            }
+        }
+
+        let auth_header = if self.auth_token.is_empty() {
+            None
+        } else {
+            Some(format!("Token {}", self.auth_token))
+        };
+
+        Ok(Client {
+            url: self.url,
+            org: self.org,
+            bucket: self.bucket,
+            auth_header,
+            headers: Default::default(),
+            log_requests: self.log_requests,
+            write_precision: self.write_precision,
+            timestamp_check: self.timestamp_check,
+            write_compression: std::sync::Arc::new(Negotiation::new(self.write_compression)),
+            naming: self.naming,
+            #[cfg(not(target_arch = "wasm32"))]
+            timeouts: self.timeouts,
+            cancellation: None,
+            #[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+            cassette: self.cassette,
+            #[cfg(feature = "management")]
+            org_ids: Arc::new(OrgIdCache::new(self.org_id_ttl, Arc::clone(&self.clock))),
+            #[cfg(feature = "management")]
+            label_ids: Arc::default(),
+            #[cfg(feature = "management")]
+            users: Arc::new(UserCache::new(self.user_cache_ttl, Arc::clone(&self.clock))),
+            clock: self.clock,
+            retry_policy: self.retry_policy,
+            max_response_size: self.max_response_size,
+            endpoint_overrides: Arc::new(self.endpoint_overrides),
+            reqwest: http.build().context(ReqwestSnafu)?,
+        })
+    }
+
+    /// Build the client and check that it works: the URL must parse, an
+    /// InfluxDB server must answer `/ping` there, and the token must be
+    /// accepted by `/api/v2/me`. Misconfigurations are reported right away
+    /// instead of surfacing on the first real call.
+    ///
+    /// `/api/v2/me` requires a token belonging to a user, like the tokens
+    /// created in the UI or by `influx auth create`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), influxdb2::BuildError> {
+    /// let client = influxdb2::Client::builder("http://localhost:8086", "org", "token")
+    ///     .validate()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn validate(self) -> Result<Client, BuildError> {
+        let url = self.url.clone();
+        let parsed = url::Url::parse(&url).context(InvalidUrlSnafu { url: &url })?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return UnsupportedSchemeSnafu {
+                url,
+                scheme: parsed.scheme(),
+            }
+            .fail();
+        }
+
+        let client = self.build()?;
+
+        let ping_url = client.endpoint_url("/ping");
+        let response = client
+            .send(client.request(Method::GET, &ping_url))
+            .await
+            .context(UnreachableSnafu { url: &url })?;
+        if !response.status().is_success() {
+            return Err(response.error().await).context(UnreachableSnafu { url });
+        }
+
+        let me_url = client.endpoint_url("/api/v2/me");
+        let response = client
+            .send(client.request(Method::GET, &me_url))
+            .await
+            .context(UnauthorizedSnafu)?;
+        if !response.status().is_success() {
+            return Err(response.error().await).context(UnauthorizedSnafu);
+        }
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn tuned_client() {
+        let mock_server = mock("GET", "/ready")
+            .match_header("Authorization", "Token some-token")
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "some-token")
+            .with_pool_max_idle_per_host(4)
+            .with_pool_idle_timeout(Some(Duration::from_secs(30)))
+            .with_tcp_keepalive(Duration::from_secs(60))
+            .with_tcp_nodelay(true)
+            .with_http_version(HttpVersion::Http1Only)
+            .build()
+            .unwrap();
+
+        let _result = client.ready().await;
+
+        mock_server.assert();
+    }
+
+    #[test]
+    fn overrides_endpoints() {
+        let client = Client::builder("https://gateway.example.com/influx/", "org", "")
+            .with_endpoint_override("/api/v2/query", "gateway/flux/")
+            .with_endpoint_override("/api/v2/query/ast", "https://ast.example.com/parse")
+            .with_endpoint_override("/api/v2/labels/", "/tags")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            client.endpoint_url("/api/v2/query"),
+            "https://gateway.example.com/influx/gateway/flux"
+        );
+        assert_eq!(
+            client.endpoint_url("/api/v2/query/ast"),
+            "https://ast.example.com/parse"
+        );
+        assert_eq!(
+            client.endpoint_url("/api/v2/query/suggestions?org=a"),
+            "https://gateway.example.com/influx/gateway/flux/suggestions?org=a"
+        );
+        assert_eq!(
+            client.endpoint_url("/api/v2/labels/0a1b"),
+            "https://gateway.example.com/influx/tags/0a1b"
+        );
+        assert_eq!(
+            client.endpoint_url("/api/v2/queryable"),
+            "https://gateway.example.com/influx/api/v2/queryable"
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_overridden_endpoints_to_their_route() {
+        let mock_server = mock("POST", "/gateway/lp")
+            .match_query(mockito::Matcher::UrlEncoded("bucket".into(), "b".into()))
+            .with_status(204)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_endpoint_override("/api/v2/write", "/gateway/lp")
+            .build()
+            .unwrap();
+        client.write_line_protocol_to("b", "cpu usage=0.5").await.unwrap();
+
+        mock_server.assert();
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn request_logging() {
+        let mock_server = mock("GET", "/api/v2/labels/some-label")
+            .with_body(r#"{"label":{"id":"some-label","name":"some-name"}}"#)
+            .create();
+
+        let client = Client::builder(mockito::server_url(), "org", "some-token")
+            .with_request_logging(true)
+            .build()
+            .unwrap();
+
+        let label = client.find_label(&"some-label".into()).await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(label.label.unwrap().name.as_deref(), Some("some-name"));
+    }
+
+    #[tokio::test]
+    async fn validate() {
+        let ping = mock("GET", "/valid/ping").with_status(204).create();
+        let me = mock("GET", "/valid/api/v2/me")
+            .match_header("Authorization", "Token some-token")
+            .with_body(r#"{"name":"some-user"}"#)
+            .create();
+
+        let url = format!("{}/valid", mockito::server_url());
+        let result = Client::builder(url, "org", "some-token").validate().await;
+
+        ping.assert();
+        me.assert();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn validate_rejected_token() {
+        let _ping = mock("GET", "/rejected/ping").with_status(204).create();
+        let _me = mock("GET", "/rejected/api/v2/me").with_status(401).create();
+
+        let url = format!("{}/rejected", mockito::server_url());
+        let result = Client::builder(url, "org", "bad-token").validate().await;
+
+        assert!(matches!(result, Err(BuildError::Unauthorized { .. })));
+    }
+
+    #[tokio::test]
+    async fn validate_bad_urls() {
+        let unparsable = Client::builder("not a url", "org", "").validate().await;
+        // Parses with `localhost` as the scheme
+        let schemeless = Client::builder("localhost:8086", "org", "").validate().await;
+
+        assert!(matches!(unparsable, Err(BuildError::InvalidUrl { .. })));
+        assert!(matches!(schemeless, Err(BuildError::UnsupportedScheme { .. })));
+    }
+
+    /// A self-signed certificate of `localhost`, and its key
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    const CERTIFICATE: &[u8] = include_bytes!("../tests/fixtures/tls/localhost.pem");
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    const KEY: &[u8] = include_bytes!("../tests/fixtures/tls/localhost.key");
+
+    /// The URL of a server answering `/ready` over TLS with [`CERTIFICATE`]
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    async fn tls_server() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let identity = native_tls::Identity::from_pkcs8(CERTIFICATE, KEY).unwrap();
+        let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(acceptor);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("https://localhost:{}", listener.local_addr().unwrap().port());
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    // Clients refusing the certificate fail the handshake
+                    let mut stream = match acceptor.accept(stream).await {
+                        Ok(stream) => stream,
+                        Err(_) => return,
+                    };
+                    let mut request = [0; 1024];
+                    let _ = stream.read(&mut request).await;
+                    let response = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+        url
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    #[tokio::test]
+    async fn verifies_certificates() {
+        let url = tls_server().await;
+
+        let verifying = Client::new(&url, "org", "");
+        let error = verifying.ready().await.unwrap_err();
+        assert!(
+            matches!(error, RequestError::ReqwestProcessing { .. }),
+            "{:?}",
+            error
+        );
+
+        let accepting = Client::builder(&url, "org", "")
+            .with_danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        assert!(accepting.ready().await.unwrap());
+
+        let pinned = Client::builder(&url, "org", "")
+            .with_pinned_certificate(reqwest::Certificate::from_pem(CERTIFICATE).unwrap())
+            .build()
+            .unwrap();
+        assert!(pinned.ready().await.unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+//! Clocks
+//!
+//! The client asks a [`Clock`] for the time of day it works with: to
+//! timestamp the points of the telemetry sinks, to expire cached
+//! organization and user IDs, to pace the probes of a `FailoverClient`, to
+//! decide when a `WriteBatcher` batch is due, and to wait between retries.
+//! Background tasks wait on timers instead, as listed below. [`SystemClock`]
+//! is the default; tests set a [`MockClock`] with `ClientBuilder::with_clock`
+//! to control time, and check the delays waited, without sleeping:
+//!
+//! ```
+//! use std::time::Duration;
+//! use chrono::{TimeZone, Utc};
+//! use influxdb2::clock::{Clock, MockClock};
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let clock = MockClock::new(Utc.timestamp_opt(2_000_000_000, 0).unwrap());
+//! clock.sleep(Duration::from_secs(5)).await;
+//! clock.advance(Duration::from_secs(1));
+//!
+//! assert_eq!(clock.now(), Utc.timestamp_opt(2_000_000_006, 0).unwrap());
+//! assert_eq!(clock.sleeps(), vec![Duration::from_secs(5)]);
+//! # });
+//! ```
+//!
+//! A few readings of the time don't go through a client and so not through
+//! its clock either:
+//!
+//! - `point!(.., ts: now)` reads the time of the system; `ts: now(clock)`
+//!   reads `clock` instead.
+//! - The `bench` module times requests and timestamps its synthetic points
+//!   with the time of the system, as it measures a real server.
+//! - The durations of requests logged with
+//!   `ClientBuilder::with_request_logging` are measured by the system.
+//! - The `fake` server timestamps points and resolves relative times with
+//!   the time of the system, like a real server.
+//! - The intervals of background tasks, such as health monitors and the
+//!   `WriteBatcher` checking whether its batch is due, run on tokio timers,
+//!   which tests control with `tokio::time::pause`. A batch is due by the
+//!   clock, but is only written once such a timer fires or a point arrives.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+
+/// A source of the current time and of delays
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// A future completing once `duration` has passed.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        (**self).sleep(duration)
+    }
+}
+
+/// The time of the system, the default
+///
+/// Sleeps run on a tokio timer with a feature enabling tokio, and otherwise
+/// on a single timer thread, started by the first sleep and shared by all.
+/// On `wasm32`, which has neither, they end at once, so the client doesn't
+/// retry there.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+        return Box::pin(tokio::time::sleep(duration));
+
+        #[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+        return Box::pin(thread_sleep(duration));
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = duration;
+            Box::pin(futures::future::ready(()))
+        }
+    }
+}
+
+/// A sleep waiting on the timer thread: when it ends, and how to wake it
+#[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+type Timer = (std::time::Instant, futures::channel::oneshot::Sender<()>);
+
+/// Sleep for `duration` on the timer thread, so no runtime is needed
+#[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+async fn thread_sleep(duration: Duration) {
+    static TIMERS: std::sync::OnceLock<std::sync::mpsc::Sender<Timer>> =
+        std::sync::OnceLock::new();
+
+    let deadline = match std::time::Instant::now().checked_add(duration) {
+        Some(deadline) => deadline,
+        None => return futures::future::pending().await,
+    };
+    let timers = TIMERS.get_or_init(|| {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        // If the thread can't be spawned, the receiver is dropped with the
+        // closure and sleeps end at once
+        let _ = std::thread::Builder::new()
+            .name("influxdb2-timer".to_string())
+            .spawn(move || run_timers(receiver));
+        sender
+    });
+    let (done, slept) = futures::channel::oneshot::channel();
+    if timers.send((deadline, done)).is_ok() {
+        let _ = slept.await;
+    }
+}
+
+/// Wake the sleeps sent to `receiver` once they end, for as long as the
+/// process runs
+#[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+fn run_timers(receiver: std::sync::mpsc::Receiver<Timer>) {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let mut timers: Vec<Timer> = Vec::new();
+    loop {
+        let now = std::time::Instant::now();
+        let (ended, pending): (Vec<_>, Vec<_>) = timers
+            .into_iter()
+            .filter(|(_, done)| !done.is_canceled())
+            .partition(|(deadline, _)| *deadline <= now);
+        timers = pending;
+        for (_, done) in ended {
+            let _ = done.send(());
+        }
+
+        let timer = match timers.iter().map(|(deadline, _)| *deadline).min() {
+            Some(next) => receiver.recv_timeout(next - now),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match timer {
+            Ok(timer) => timers.push(timer),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// A clock whose time only moves when told to
+///
+/// Sleeping advances its time by the duration at once and records the
+/// duration, so code waiting between retries runs without delay and its
+/// delays can be checked.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+impl MockClock {
+    /// A clock at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+            sleeps: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Move the time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock() += chrono::Duration::from_std(duration).expect("the duration is in range");
+    }
+
+    /// Set the time to `now`.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock() = now;
+    }
+
+    /// The durations slept so far, in order.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().clone()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.sleeps.lock().push(duration);
+        self.advance(duration);
+        Box::pin(futures::future::ready(()))
+    }
+}
+
+#[cfg(all(test, not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn wakes_sleeps_from_one_thread() {
+        let started = Instant::now();
+        let sleep = |millis| async move {
+            SystemClock.sleep(Duration::from_millis(millis)).await;
+            started.elapsed()
+        };
+
+        let (long, short) = futures::future::join(sleep(60), sleep(20)).await;
+
+        assert!(short >= Duration::from_millis(20) && short < long);
+        assert!(long >= Duration::from_millis(60));
+    }
+}
@@ -0,0 +1,283 @@
+//! Write compression
+//!
+//! Line protocol compresses well, and large writes are dominated by transfer
+//! size. `ClientBuilder::with_write_compression` sets how write bodies are
+//! encoded: always with one [`ContentEncoding`], or, with
+//! [`WriteCompression::Auto`], with the best encoding the server accepts, so
+//! the same binary works against InfluxDB OSS, Cloud and older proxies.
+//!
+//! ```no_run
+//! use influxdb2::compression::WriteCompression;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = influxdb2::Client::builder("http://localhost:8086", "org", "my-token")
+//!     .with_write_compression(WriteCompression::Auto)
+//!     .build()?;
+//! client.write_line_protocol_to("bucket", "cpu usage=0.5").await?;
+//! println!("writing with {:?}", client.write_encoding());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Auto negotiation prefers zstd, with the `zstd` feature, then gzip, then
+//! no encoding. The first write is sent with each in turn until the server
+//! accepts it; an encoding counts as refused if the server answers
+//! `415 Unsupported Media Type` or `400 Bad Request`, as servers that ignore
+//! the `Content-Encoding` header fail to parse the compressed body. The
+//! client then sticks to the encoding accepted. Until then the points of
+//! `Client::write` are buffered, so the write can be sent again.
+//!
+//! Bodies given to `Client::write_line_protocol_to` as streams are always sent
+//! as they are.
+
+use std::io::{self, Write};
+
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use parking_lot::Mutex;
+
+/// An encoding of a request body, sent as its `Content-Encoding`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    /// The body as it is
+    Identity,
+    /// gzip, which every InfluxDB 2 server accepts
+    Gzip,
+    /// zstd, smaller and faster than gzip where accepted
+    #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+    Zstd,
+}
+
+/// How write bodies are encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WriteCompression {
+    /// Every write with `encoding`
+    Fixed(ContentEncoding),
+    /// Every write with the best encoding the server accepts, negotiated on
+    /// the first write
+    Auto,
+}
+
+impl Default for WriteCompression {
+    fn default() -> Self {
+        Self::Fixed(ContentEncoding::Identity)
+    }
+}
+
+impl ContentEncoding {
+    /// The encodings tried by auto negotiation, best first
+    pub(crate) const PREFERENCE: &'static [Self] = &[
+        #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+        Self::Zstd,
+        Self::Gzip,
+        Self::Identity,
+    ];
+
+    /// The value of the `Content-Encoding` header, or `None` for the
+    /// identity encoding, which isn't sent.
+    pub fn header_value(self) -> Option<&'static str> {
+        match self {
+            Self::Identity => None,
+            Self::Gzip => Some("gzip"),
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            Self::Zstd => Some("zstd"),
+        }
+    }
+
+    /// Encode all of `body`.
+    pub(crate) fn encode(self, body: &[u8]) -> Bytes {
+        let mut encoder = Encoder::new(self);
+        let mut encoded = encoder
+            .write(body)
+            .expect("encoding into memory cannot fail")
+            .to_vec();
+        encoded.extend_from_slice(&encoder.finish().expect("encoding into memory cannot fail"));
+        encoded.into()
+    }
+}
+
+/// The write compression of a client and the encoding it settled on,
+/// shared by its copies
+#[derive(Debug)]
+pub(crate) struct Negotiation {
+    compression: WriteCompression,
+    settled: Mutex<Option<ContentEncoding>>,
+}
+
+impl Negotiation {
+    pub(crate) fn new(compression: WriteCompression) -> Self {
+        Self {
+            compression,
+            settled: Mutex::new(None),
+        }
+    }
+
+    /// The encoding writes use, or `None` while auto negotiation hasn't
+    /// settled.
+    pub(crate) fn encoding(&self) -> Option<ContentEncoding> {
+        match self.compression {
+            WriteCompression::Fixed(encoding) => Some(encoding),
+            WriteCompression::Auto => *self.settled.lock(),
+        }
+    }
+
+    /// The encodings to try for the next write, best first.
+    pub(crate) fn candidates(&self) -> Vec<ContentEncoding> {
+        match self.encoding() {
+            Some(encoding) => vec![encoding],
+            None => ContentEncoding::PREFERENCE.to_vec(),
+        }
+    }
+
+    /// Remember that the server accepted a write with `encoding`.
+    pub(crate) fn settle(&self, encoding: ContentEncoding) {
+        if self.compression == WriteCompression::Auto {
+            *self.settled.lock() = Some(encoding);
+        }
+    }
+}
+
+/// Encodes a body chunk by chunk
+enum Encoder {
+    Identity(Vec<u8>),
+    Gzip(GzEncoder<Vec<u8>>),
+    #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(encoding: ContentEncoding) -> Self {
+        match encoding {
+            ContentEncoding::Identity => Self::Identity(Vec::new()),
+            ContentEncoding::Gzip => Self::Gzip(GzEncoder::new(Vec::new(), Compression::default())),
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            ContentEncoding::Zstd => Self::Zstd(
+                zstd::stream::write::Encoder::new(Vec::new(), 0)
+                    .expect("the default zstd level is valid"),
+            ),
+        }
+    }
+
+    /// Encode `chunk`, returning the output ready so far
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Self::Identity(output) => {
+                output.extend_from_slice(chunk);
+                output
+            }
+            Self::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            Self::Zstd(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(std::mem::take(output).into())
+    }
+
+    /// End the body, returning the rest of the output
+    fn finish(self) -> io::Result<Bytes> {
+        let output = match self {
+            Self::Identity(output) => output,
+            Self::Gzip(encoder) => encoder.finish()?,
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        Ok(output.into())
+    }
+}
+
+/// Encode the chunks of `body` with `encoding` as they come.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn encode_stream<S>(
+    body: S,
+    encoding: ContentEncoding,
+) -> impl Stream<Item = io::Result<Bytes>> + Send + Sync + 'static
+where
+    S: Stream<Item = io::Result<Bytes>> + Send + Sync + 'static,
+{
+    // A mutex, as the zstd encoder isn't `Sync`
+    let encoder = std::sync::Arc::new(Mutex::new(Some(Encoder::new(encoding))));
+    let finisher = std::sync::Arc::clone(&encoder);
+
+    let chunks = body.map(move |chunk| {
+        let chunk = chunk?;
+        match encoder.lock().as_mut() {
+            Some(encoder) => encoder.write(&chunk),
+            None => Ok(Bytes::new()),
+        }
+    });
+    let end = stream::once(async move {
+        match finisher.lock().take() {
+            Some(encoder) => encoder.finish(),
+            None => Ok(Bytes::new()),
+        }
+    });
+
+    chunks
+        .chain(end)
+        .try_filter(|chunk| futures::future::ready(!chunk.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn encodes_gzip() {
+        let encoded = ContentEncoding::Gzip.encode(b"cpu usage=0.5");
+        let mut decoded = String::new();
+        GzDecoder::new(&encoded[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "cpu usage=0.5");
+        assert_eq!(&ContentEncoding::Identity.encode(b"cpu")[..], b"cpu");
+    }
+
+    #[tokio::test]
+    async fn encodes_streams() {
+        let chunks = stream::iter(vec![
+            Ok(Bytes::from("cpu usage=0.5\n")),
+            Ok(Bytes::from("cpu usage=0.7\n")),
+        ]);
+        let encoded: Vec<Bytes> = encode_stream(chunks, ContentEncoding::Gzip)
+            .try_collect()
+            .await
+            .unwrap();
+        let encoded = encoded.concat();
+
+        let mut decoded = String::new();
+        GzDecoder::new(&encoded[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "cpu usage=0.5\ncpu usage=0.7\n");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn encodes_zstd() {
+        let encoded = ContentEncoding::Zstd.encode(b"cpu usage=0.5");
+        assert_eq!(zstd::decode_all(&encoded[..]).unwrap(), b"cpu usage=0.5");
+    }
+
+    #[test]
+    fn settles_only_when_negotiating() {
+        let auto = Negotiation::new(WriteCompression::Auto);
+        assert_eq!(auto.encoding(), None);
+        assert_eq!(auto.candidates(), ContentEncoding::PREFERENCE);
+        auto.settle(ContentEncoding::Gzip);
+        assert_eq!(auto.candidates(), [ContentEncoding::Gzip]);
+
+        let fixed = Negotiation::new(WriteCompression::Fixed(ContentEncoding::Identity));
+        fixed.settle(ContentEncoding::Gzip);
+        assert_eq!(fixed.encoding(), Some(ContentEncoding::Identity));
+    }
+}
@@ -0,0 +1,532 @@
+//! Failover client
+//!
+//! A [`FailoverClient`] holds clients to several InfluxDB servers, e.g. a
+//! primary and a standby, for highly available OSS deployments without an
+//! external load balancer. Calls go to the active server; when it can't be
+//! reached or answers with a 5xx status, the call is retried on the next
+//! server, which becomes the active one. Writes and creates, which the
+//! server may have applied before failing, are retried only when the active
+//! server couldn't be connected to, unless the client carries an idempotency
+//! key, see `Client::with_idempotency_key`. While a standby is active, the
+//! preferred servers are probed through `/health` at most once per recovery
+//! interval, and the first one passing becomes active again.
+//!
+//! ```no_run
+//! use influxdb2::failover::FailoverClient;
+//! use influxdb2::{Client, InfluxClient};
+//!
+//! # async fn example() -> Result<(), influxdb2::RequestError> {
+//! let client = FailoverClient::new(vec![
+//!     Client::new("http://primary:8086", "org", "my-token"),
+//!     Client::new("http://standby:8086", "org", "my-token"),
+//! ]);
+//!
+//! let health = client.health().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "management")]
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use influxdb2_structmap::GenericMap;
+use parking_lot::Mutex;
+use reqwest::Body;
+
+#[cfg(feature = "management")]
+use crate::api::buckets::ListBucketsRequest;
+use crate::api::capabilities::Capabilities;
+use crate::api::client::{BoxFuture, InfluxClient};
+#[cfg(feature = "management")]
+use crate::api::organization::ListOrganizationRequest;
+#[cfg(feature = "management")]
+use crate::api::task::{CreateTaskRequest, ListTasksRequest};
+use crate::models::{
+    AnalyzeQueryResponse, AstResponse, DataPoint, FluxSuggestion, FluxSuggestions, HealthCheck,
+    LanguageRequest, Query, Status,
+};
+#[cfg(feature = "management")]
+use crate::models::{
+    BucketId, Buckets, FluxDuration, LabelId, LabelResponse, LabelsResponse, OnboardingResponse,
+    OrgId, Organizations, PostBucketRequest, TaskId, Tasks,
+};
+use crate::{Client, RequestError, Target, IDEMPOTENCY_KEY_HEADER};
+
+/// How often the preferred servers are probed by default while a standby is
+/// active.
+const DEFAULT_RECOVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Client failing over between several InfluxDB servers, in order of
+/// preference.
+#[derive(Debug)]
+pub struct FailoverClient {
+    clients: Vec<Client>,
+    active: AtomicUsize,
+    recovery_interval: Duration,
+    last_probe: Mutex<DateTime<Utc>>,
+}
+
+impl FailoverClient {
+    /// Create a failover client over `clients`, the first being the
+    /// preferred one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `clients` is empty.
+    pub fn new(clients: impl IntoIterator<Item = Client>) -> Self {
+        let clients: Vec<Client> = clients.into_iter().collect();
+        assert!(!clients.is_empty(), "a failover client needs at least one client");
+
+        let now = clients[0].clock.now();
+        Self {
+            clients,
+            active: AtomicUsize::new(0),
+            recovery_interval: DEFAULT_RECOVERY_INTERVAL,
+            last_probe: Mutex::new(now),
+        }
+    }
+
+    /// Sets how often the preferred servers are probed while a standby is
+    /// active.
+    pub fn with_recovery_interval(mut self, interval: Duration) -> Self {
+        self.recovery_interval = interval;
+        self
+    }
+
+    /// The client calls currently go to.
+    pub fn active(&self) -> &Client {
+        &self.clients[self.active.load(Ordering::Relaxed)]
+    }
+
+    /// Switch back to the most preferred server passing its health check, if
+    /// a standby is active and the recovery interval has passed.
+    async fn recover(&self) {
+        let active = self.active.load(Ordering::Relaxed);
+        if active == 0 {
+            return;
+        }
+        {
+            let now = self.clients[0].clock.now();
+            let mut last_probe = self.last_probe.lock();
+            let elapsed = now.signed_duration_since(*last_probe).to_std().unwrap_or_default();
+            if elapsed < self.recovery_interval {
+                return;
+            }
+            *last_probe = now;
+        }
+
+        for (index, client) in self.clients[..active].iter().enumerate() {
+            if matches!(client.health().await, Ok(health) if health.status == Status::Pass) {
+                tracing::info!(url = %client.url, "recovered preferred InfluxDB server");
+                self.active.store(index, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    /// Run the idempotent `call` against the active server, failing over to
+    /// the next ones in turn
+    fn call<'a, T, F>(&'a self, call: F) -> BoxFuture<'a, Result<T, RequestError>>
+    where
+        T: Send + 'a,
+        F: Fn(&'a Client) -> BoxFuture<'a, Result<T, RequestError>> + Send + 'a,
+    {
+        self.fail_over(true, call)
+    }
+
+    /// Run `call`, which must not be applied twice, against the active
+    /// server, failing over only if it couldn't connect, or also like an
+    /// idempotent call if the client carries an idempotency key
+    fn call_once<'a, T, F>(&'a self, call: F) -> BoxFuture<'a, Result<T, RequestError>>
+    where
+        T: Send + 'a,
+        F: Fn(&'a Client) -> BoxFuture<'a, Result<T, RequestError>> + Send + 'a,
+    {
+        self.fail_over(false, call)
+    }
+
+    fn fail_over<'a, T, F>(
+        &'a self,
+        idempotent: bool,
+        call: F,
+    ) -> BoxFuture<'a, Result<T, RequestError>>
+    where
+        T: Send + 'a,
+        F: Fn(&'a Client) -> BoxFuture<'a, Result<T, RequestError>> + Send + 'a,
+    {
+        Box::pin(async move {
+            self.recover().await;
+
+            let start = self.active.load(Ordering::Relaxed);
+            let count = self.clients.len();
+            let mut attempt = 0;
+            loop {
+                let index = (start + attempt) % count;
+                let client = &self.clients[index];
+                let idempotent = idempotent || client.headers.contains_key(IDEMPOTENCY_KEY_HEADER);
+                match call(client).await {
+                    Err(e) if should_fail_over(&e, idempotent) && attempt + 1 < count => {
+                        tracing::warn!(url = %client.url, error = %e, "failing over");
+                        attempt += 1;
+                    }
+                    result => {
+                        if index != start && result.is_ok() {
+                            self.active.store(index, Ordering::Relaxed);
+                            *self.last_probe.lock() = self.clients[0].clock.now();
+                        }
+                        return result;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Whether `error` means the server is unavailable, rather than the request
+/// being wrong. Only a request that never reached the server is known not to
+/// have been applied, so others are sent again only if `idempotent`.
+fn should_fail_over(error: &RequestError, idempotent: bool) -> bool {
+    match error {
+        RequestError::ReqwestProcessing { source, .. } => {
+            source.is_connect() || (idempotent && source.is_timeout())
+        }
+        RequestError::Http { status, .. } => idempotent && status.is_server_error(),
+        _ => false,
+    }
+}
+
+impl InfluxClient for FailoverClient {
+    #[cfg(feature = "management")]
+    fn list_buckets(
+        &self,
+        request: Option<ListBucketsRequest>,
+    ) -> BoxFuture<'_, Result<Buckets, RequestError>> {
+        self.call(move |c| InfluxClient::list_buckets(c, request.clone()))
+    }
+
+    #[cfg(feature = "management")]
+    fn create_bucket(
+        &self,
+        post_bucket_request: Option<PostBucketRequest>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.call_once(move |c| InfluxClient::create_bucket(c, post_bucket_request.clone()))
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_bucket<'a>(&'a self, bucket_id: &'a BucketId) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.call(move |c| InfluxClient::delete_bucket(c, bucket_id))
+    }
+
+    fn delete(
+        &self,
+        target: Target,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        predicate: Option<String>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.call(move |c| InfluxClient::delete(c, target.clone(), start, stop, predicate.clone()))
+    }
+
+    fn capabilities(&self) -> BoxFuture<'_, Result<Capabilities, RequestError>> {
+        self.call(InfluxClient::capabilities)
+    }
+
+    fn health(&self) -> BoxFuture<'_, Result<HealthCheck, RequestError>> {
+        self.call(InfluxClient::health)
+    }
+
+    #[cfg(feature = "management")]
+    fn labels(&self) -> BoxFuture<'_, Result<LabelsResponse, RequestError>> {
+        self.call(InfluxClient::labels)
+    }
+
+    #[cfg(feature = "management")]
+    fn labels_by_org<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+    ) -> BoxFuture<'a, Result<LabelsResponse, RequestError>> {
+        self.call(move |c| InfluxClient::labels_by_org(c, org_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn find_label<'a>(
+        &'a self,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        self.call(move |c| InfluxClient::find_label(c, label_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn create_label<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+        name: &'a str,
+        properties: Option<HashMap<String, String>>,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        self.call_once(move |c| InfluxClient::create_label(c, org_id, name, properties.clone()))
+    }
+
+    #[cfg(feature = "management")]
+    fn update_label<'a>(
+        &'a self,
+        name: Option<String>,
+        properties: Option<HashMap<String, String>>,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        self.call(move |c| {
+            InfluxClient::update_label(c, name.clone(), properties.clone(), label_id)
+        })
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_label<'a>(&'a self, label_id: &'a LabelId) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.call(move |c| InfluxClient::delete_label(c, label_id))
+    }
+
+    #[cfg(feature = "management")]
+    fn list_organizations(
+        &self,
+        request: ListOrganizationRequest,
+    ) -> BoxFuture<'_, Result<Organizations, RequestError>> {
+        self.call(move |c| InfluxClient::list_organizations(c, request.clone()))
+    }
+
+    fn query_suggestions(&self) -> BoxFuture<'_, Result<FluxSuggestions, RequestError>> {
+        self.call(InfluxClient::query_suggestions)
+    }
+
+    fn query_suggestions_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<FluxSuggestion, RequestError>> {
+        self.call(move |c| InfluxClient::query_suggestions_name(c, name))
+    }
+
+    fn query_raw(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<Vec<GenericMap>, RequestError>> {
+        self.call(move |c| InfluxClient::query_raw(c, query.clone()))
+    }
+
+    fn query_analyze(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<AnalyzeQueryResponse, RequestError>> {
+        self.call(move |c| InfluxClient::query_analyze(c, query.clone()))
+    }
+
+    fn query_ast(
+        &self,
+        language_request: Option<LanguageRequest>,
+    ) -> BoxFuture<'_, Result<AstResponse, RequestError>> {
+        self.call(move |c| InfluxClient::query_ast(c, language_request.clone()))
+    }
+
+    fn ready(&self) -> BoxFuture<'_, Result<bool, RequestError>> {
+        self.call(InfluxClient::ready)
+    }
+
+    #[cfg(feature = "management")]
+    fn is_onboarding_allowed(&self) -> BoxFuture<'_, Result<bool, RequestError>> {
+        self.call(InfluxClient::is_onboarding_allowed)
+    }
+
+    #[cfg(feature = "management")]
+    fn onboarding<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>> {
+        self.call_once(move |c| {
+            InfluxClient::onboarding(
+                c,
+                username,
+                org,
+                bucket,
+                password.clone(),
+                retention_period_hrs,
+                retention_period_seconds,
+            )
+        })
+    }
+
+    #[cfg(feature = "management")]
+    fn post_setup_user<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>> {
+        self.call_once(move |c| {
+            InfluxClient::post_setup_user(
+                c,
+                username,
+                org,
+                bucket,
+                password.clone(),
+                retention_period_hrs,
+                retention_period_seconds,
+            )
+        })
+    }
+
+    #[cfg(feature = "management")]
+    fn list_tasks(&self, request: ListTasksRequest) -> BoxFuture<'_, Result<Tasks, RequestError>> {
+        self.call(move |c| InfluxClient::list_tasks(c, request.clone()))
+    }
+
+    #[cfg(feature = "management")]
+    fn create_task(&self, request: CreateTaskRequest) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.call_once(move |c| InfluxClient::create_task(c, request.clone()))
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_task<'a>(&'a self, task_id: &'a TaskId) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.call(move |c| InfluxClient::delete_task(c, task_id))
+    }
+
+    /// Streaming bodies can only be sent once, so they go to the active
+    /// server without failing over.
+    fn write_line_protocol_to(
+        &self,
+        target: Target,
+        body: Body,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        match body.as_bytes().map(Bytes::copy_from_slice) {
+            Some(bytes) => self.call_once(move |c| {
+                InfluxClient::write_line_protocol_to(c, target.clone(), Body::from(bytes.clone()))
+            }),
+            None => Box::pin(async move {
+                self.recover().await;
+                InfluxClient::write_line_protocol_to(self.active(), target, body).await
+            }),
+        }
+    }
+
+    fn write(
+        &self,
+        target: Target,
+        points: Vec<DataPoint>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.call_once(move |c| InfluxClient::write(c, target.clone(), points.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    /// A port nothing listens on
+    const UNREACHABLE: &str = "http://127.0.0.1:1";
+
+    fn prefixed(prefix: &str) -> Client {
+        Client::new(format!("{}/{}", mockito::server_url(), prefix), "org", "")
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_connection_errors() {
+        let mock_server = mock("GET", "/standby-a/ready").create();
+
+        let client = FailoverClient::new(vec![
+            Client::new(UNREACHABLE, "org", ""),
+            prefixed("standby-a"),
+        ]);
+
+        let ready = client.ready().await.unwrap();
+
+        mock_server.assert();
+        assert!(ready);
+        assert!(client.active().url.ends_with("standby-a"));
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn fails_over_on_server_errors_only() {
+        let primary = mock("GET", "/primary-b/api/v2/labels/some-label")
+            .with_status(503)
+            .create();
+        let standby = mock("GET", "/standby-b/api/v2/labels/some-label")
+            .with_status(404)
+            .create();
+
+        let client = FailoverClient::new(vec![prefixed("primary-b"), prefixed("standby-b")]);
+
+        let err = client.find_label(&"some-label".into()).await.unwrap_err();
+
+        primary.assert();
+        standby.assert();
+        assert!(matches!(err, RequestError::Http { status, .. } if status == 404));
+        assert!(client.active().url.ends_with("primary-b"));
+    }
+
+    #[tokio::test]
+    async fn fails_over_writes_only_when_unsent() {
+        let primary = mock("POST", "/primary-d/api/v2/write")
+            .match_query(mockito::Matcher::Any)
+            .with_status(503)
+            .expect(2)
+            .create();
+        let standby = mock("POST", "/standby-d/api/v2/write")
+            .match_query(mockito::Matcher::Any)
+            .with_status(204)
+            .expect(1)
+            .create();
+
+        let client = FailoverClient::new(vec![prefixed("primary-d"), prefixed("standby-d")]);
+        fn write(client: &FailoverClient) -> BoxFuture<'_, Result<(), RequestError>> {
+            InfluxClient::write_line_protocol_to(client, "b".into(), Body::from("cpu usage=1"))
+        }
+
+        let err = write(&client).await.unwrap_err();
+        assert!(matches!(err, RequestError::Http { status, .. } if status == 503));
+        assert!(client.active().url.ends_with("primary-d"));
+
+        // With an idempotency key, sending it again is safe
+        let client = FailoverClient::new(vec![
+            prefixed("primary-d").with_idempotency_key("key").unwrap(),
+            prefixed("standby-d").with_idempotency_key("key").unwrap(),
+        ]);
+        write(&client).await.unwrap();
+
+        primary.assert();
+        standby.assert();
+        assert!(client.active().url.ends_with("standby-d"));
+    }
+
+    #[tokio::test]
+    async fn recovers_preferred_server() {
+        let ready = mock("GET", "/standby-c/ready").expect(1).create();
+        let health = mock("GET", "/primary-c/health")
+            .with_body(r#"{"name":"influxdb","status":"pass"}"#)
+            .create();
+        let primary = mock("GET", "/primary-c/ready").with_status(503).expect(1).create();
+
+        let client = FailoverClient::new(vec![prefixed("primary-c"), prefixed("standby-c")])
+            .with_recovery_interval(Duration::from_secs(0));
+
+        client.ready().await.unwrap();
+        assert!(client.active().url.ends_with("standby-c"));
+        drop(primary);
+        let primary = mock("GET", "/primary-c/ready").create();
+
+        client.ready().await.unwrap();
+
+        ready.assert();
+        health.assert();
+        primary.assert();
+        assert!(client.active().url.ends_with("primary-c"));
+    }
+}
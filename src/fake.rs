@@ -0,0 +1,662 @@
+//! In-process fake server
+//!
+//! Serves the write and query endpoints from memory on a local port, so unit
+//! tests can exercise code that writes and queries through a real `Client`
+//! without Docker or mockito body matching:
+//!
+//! ```
+//! use influxdb2::fake::FakeServer;
+//! use influxdb2::models::Query;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let server = FakeServer::start()?;
+//! let client = server.client();
+//! client
+//!     .write_line_protocol_to(server.bucket(), "cpu,host=a usage=0.5 1")
+//!     .await?;
+//!
+//! let flux = r#"from(bucket: "test-bucket")
+//!     |> range(start: 0)
+//!     |> filter(fn: (r) => r._measurement == "cpu" and r.host == "a")"#;
+//! let rows = client.query_raw(Some(Query::new(flux.into()))).await?;
+//! assert_eq!(rows.len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Written points are kept per bucket; the organization is ignored. Queries
+//! are answered from a canned response registered with
+//! `FakeServer::respond` when one matches, and otherwise evaluated if they
+//! only chain `from`, `range`, `filter` and `yield`, with filters comparing
+//! `_measurement`, `_field` or tags to string literals joined by `and`.
+//! Other queries are rejected with `400 Bad Request`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::line_protocol::{self, Precision};
+use crate::models::{DataPoint, ErrorResponse, FieldValue, HealthCheck, Query, Status};
+use crate::Client;
+
+const ORG: &str = "test-org";
+const BUCKET: &str = "test-bucket";
+
+const PRECISIONS: &[Precision] = &[
+    Precision::Nanoseconds,
+    Precision::Microseconds,
+    Precision::Milliseconds,
+    Precision::Seconds,
+];
+
+const UNSUPPORTED: &str = "the fake server can only evaluate from, range, filter and yield; \
+                           register a canned response with FakeServer::respond";
+
+#[derive(Debug, Default)]
+struct State {
+    buckets: HashMap<String, Vec<DataPoint>>,
+    canned: HashMap<String, String>,
+}
+
+impl State {
+    /// Store `points`, timestamping those without a timestamp with the
+    /// current time like the server does
+    fn insert(&mut self, bucket: &str, points: impl IntoIterator<Item = DataPoint>) {
+        let now = Utc::now().timestamp_nanos();
+        let stored = self.buckets.entry(bucket.to_string()).or_default();
+        stored.extend(points.into_iter().map(|point| {
+            if point.timestamp().is_some() {
+                return point;
+            }
+            let mut builder = DataPoint::builder(point.measurement());
+            for (key, value) in point.tags() {
+                builder = builder.tag(key, value);
+            }
+            for (key, value) in point.fields() {
+                builder = builder.field(key, value.clone());
+            }
+            builder
+                .timestamp(now)
+                .build()
+                .expect("the point had fields")
+        }));
+    }
+}
+
+/// An InfluxDB fake listening on a local port
+#[derive(Debug)]
+pub struct FakeServer {
+    state: Arc<Mutex<State>>,
+    client: Client,
+    url: String,
+    task: JoinHandle<()>,
+}
+
+impl FakeServer {
+    /// Start serving on a free port of `127.0.0.1`.
+    ///
+    /// Must be called from within a Tokio runtime, which runs the server
+    /// until the `FakeServer` is dropped.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        listener.set_nonblocking(true)?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let service_state = Arc::clone(&state);
+        let server = Server::from_tcp(listener)
+            .map_err(io::Error::other)?
+            .serve(make_service_fn(move |_| {
+                let state = Arc::clone(&service_state);
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| {
+                        handle(Arc::clone(&state), request)
+                    }))
+                }
+            }));
+        let task = tokio::spawn(async move {
+            let _ = server.await;
+        });
+
+        let client = Client::builder(&url, ORG, "")
+            .with_bucket(BUCKET)
+            .build()
+            .expect("the server URL is valid");
+
+        Ok(Self {
+            state,
+            client,
+            url,
+            task,
+        })
+    }
+
+    /// A client for the server, using its organization and bucket.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The URL of the server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The organization of `client`.
+    pub fn org(&self) -> &str {
+        ORG
+    }
+
+    /// The default bucket of `client`.
+    pub fn bucket(&self) -> &str {
+        BUCKET
+    }
+
+    /// The points written to `bucket` so far, in the order they were
+    /// written.
+    pub fn points(&self, bucket: &str) -> Vec<DataPoint> {
+        self.state
+            .lock()
+            .buckets
+            .get(bucket)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Store `points` in `bucket` as if they had been written.
+    pub fn insert(&self, bucket: &str, points: impl IntoIterator<Item = DataPoint>) {
+        self.state.lock().insert(bucket, points);
+    }
+
+    /// Answer `query` with `csv`, an annotated CSV response, instead of
+    /// evaluating it. Queries match when they are equal up to whitespace.
+    pub fn respond(&self, query: &str, csv: impl Into<String>) {
+        self.state
+            .lock()
+            .canned
+            .insert(normalize(query), csv.into());
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A failed request, as a status and a message
+type Rejection = (StatusCode, String);
+
+fn invalid(message: impl ToString) -> Rejection {
+    (StatusCode::BAD_REQUEST, message.to_string())
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::POST, "/api/v2/write") => write(&state, request).await,
+        (&Method::POST, "/api/v2/query") => query(&state, request).await,
+        (&Method::GET, "/ping") => Ok(respond(StatusCode::NO_CONTENT, None, Body::empty())),
+        (&Method::GET, "/ready") => Ok(json(StatusCode::OK, &health())),
+        (&Method::GET, "/health") => Ok(json(StatusCode::OK, &health())),
+        _ => Err((StatusCode::NOT_FOUND, "path not found".to_string())),
+    };
+
+    Ok(response.unwrap_or_else(|(status, message)| {
+        let code = match status {
+            StatusCode::NOT_FOUND => "not found",
+            _ => "invalid",
+        };
+        let error = ErrorResponse {
+            code: code.to_string(),
+            message,
+            ..ErrorResponse::default()
+        };
+        json(status, &error)
+    }))
+}
+
+async fn write(state: &Mutex<State>, request: Request<Body>) -> Result<Response<Body>, Rejection> {
+    let params: HashMap<String, String> = request
+        .uri()
+        .query()
+        .map(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let bucket = params
+        .get("bucket")
+        .cloned()
+        .ok_or_else(|| invalid("bucket is required"))?;
+    let precision = match params.get("precision") {
+        Some(precision) => PRECISIONS
+            .iter()
+            .copied()
+            .find(|p| p.as_str() == precision)
+            .ok_or_else(|| invalid(format!("invalid precision {}", precision)))?,
+        None => Precision::Nanoseconds,
+    };
+
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .map_err(invalid)?;
+    let text = std::str::from_utf8(&body).map_err(invalid)?;
+    let points = line_protocol::parse(text, precision).map_err(invalid)?;
+
+    state.lock().insert(&bucket, points);
+    Ok(respond(StatusCode::NO_CONTENT, None, Body::empty()))
+}
+
+async fn query(state: &Mutex<State>, request: Request<Body>) -> Result<Response<Body>, Rejection> {
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .map_err(invalid)?;
+    let query: Query = serde_json::from_slice(&body).map_err(invalid)?;
+
+    let state = state.lock();
+    let csv = match state.canned.get(&normalize(&query.query)) {
+        Some(csv) => csv.clone(),
+        None => {
+            let selection = Selection::parse(&query.query).ok_or_else(|| invalid(UNSUPPORTED))?;
+            let points = state.buckets.get(&selection.bucket).ok_or_else(|| {
+                (
+                    StatusCode::NOT_FOUND,
+                    format!("could not find bucket \"{}\"", selection.bucket),
+                )
+            })?;
+            annotated_csv(points, &selection)
+        }
+    };
+
+    Ok(respond(StatusCode::OK, Some("text/csv"), Body::from(csv)))
+}
+
+fn respond(status: StatusCode, content_type: Option<&str>, body: Body) -> Response<Body> {
+    let mut response = Response::builder().status(status);
+    if let Some(content_type) = content_type {
+        response = response.header("Content-Type", content_type);
+    }
+    response.body(body).expect("the response is valid")
+}
+
+fn json(status: StatusCode, body: &impl serde::Serialize) -> Response<Body> {
+    let body = serde_json::to_vec(body).expect("the body serializes");
+    respond(status, Some("application/json"), Body::from(body))
+}
+
+fn health() -> HealthCheck {
+    let mut health = HealthCheck::new("influxdb".to_string(), Status::Pass);
+    health.message = Some("ready for queries and writes".to_string());
+    health
+}
+
+/// Collapse runs of whitespace, so canned queries match regardless of
+/// indentation
+fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What a supported query selects
+#[derive(Debug, PartialEq)]
+struct Selection {
+    bucket: String,
+    /// Inclusive, in nanoseconds
+    start: Option<i64>,
+    /// Exclusive, in nanoseconds
+    stop: Option<i64>,
+    /// Columns and the values they must equal
+    predicates: Vec<(String, String)>,
+}
+
+impl Selection {
+    /// Parse `flux`, or `None` if it does more than the fake evaluates
+    fn parse(flux: &str) -> Option<Self> {
+        let mut stages = flux.split("|>").map(str::trim);
+        let (name, arguments) = call(stages.next()?)?;
+        if name != "from" {
+            return None;
+        }
+        let mut selection = Self {
+            bucket: string_literal(argument(arguments, "bucket")?)?,
+            start: None,
+            stop: None,
+            predicates: Vec::new(),
+        };
+
+        for stage in stages {
+            let (name, arguments) = call(stage)?;
+            match name {
+                "range" => {
+                    selection.start = Some(time(argument(arguments, "start")?)?);
+                    selection.stop = match argument(arguments, "stop") {
+                        Some(stop) => Some(time(stop)?),
+                        None => None,
+                    };
+                }
+                "filter" => {
+                    let body = arguments
+                        .strip_prefix("fn:")?
+                        .trim_start()
+                        .strip_prefix("(r)")?
+                        .trim_start()
+                        .strip_prefix("=>")?;
+                    for term in body.split(" and ") {
+                        selection.predicates.push(predicate(term)?);
+                    }
+                }
+                "yield" => {}
+                _ => return None,
+            }
+        }
+        Some(selection)
+    }
+
+    fn selects(&self, point: &DataPoint, field: &str, timestamp: i64) -> bool {
+        self.start.is_none_or(|start| timestamp >= start)
+            && self.stop.is_none_or(|stop| timestamp < stop)
+            && self
+                .predicates
+                .iter()
+                .all(|(column, value)| match column.as_str() {
+                    "_measurement" => point.measurement() == value,
+                    "_field" => field == value,
+                    tag => point.tags().get(tag) == Some(value),
+                })
+    }
+}
+
+/// Split `name(arguments)`
+fn call(stage: &str) -> Option<(&str, &str)> {
+    let (name, rest) = stage.split_once('(')?;
+    Some((name.trim(), rest.trim_end().strip_suffix(')')?.trim()))
+}
+
+/// The value of the `name: value` argument
+fn argument<'a>(arguments: &'a str, name: &str) -> Option<&'a str> {
+    arguments.split(',').find_map(|argument| {
+        let (key, value) = argument.split_once(':')?;
+        (key.trim() == name).then(|| value.trim())
+    })
+}
+
+fn string_literal(text: &str) -> Option<String> {
+    let inner = text.trim().strip_prefix('"')?.strip_suffix('"')?;
+    (!inner.contains('"')).then(|| inner.to_string())
+}
+
+/// A `range` bound: an RFC 3339 time, Unix seconds, or a duration relative
+/// to now, in nanoseconds
+fn time(text: &str) -> Option<i64> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.timestamp_nanos());
+    }
+    if let Ok(seconds) = text.parse::<i64>() {
+        return seconds.checked_mul(1_000_000_000);
+    }
+    let offset = go_parse_duration::parse_duration(text).ok()?;
+    Utc::now().timestamp_nanos().checked_add(offset)
+}
+
+/// Parse `r.column == "value"` or `r["column"] == "value"`
+fn predicate(term: &str) -> Option<(String, String)> {
+    let mut term = term.trim();
+    while let Some(inner) = term.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        term = inner.trim();
+    }
+    let (column, value) = term.split_once("==")?;
+    let column = column.trim();
+    let column = match column.strip_prefix("r.") {
+        Some(column) => column.to_string(),
+        None => string_literal(column.strip_prefix("r[")?.strip_suffix(']')?)?,
+    };
+    Some((column, string_literal(value)?))
+}
+
+/// One table of the response
+type Series<'a> = (&'a str, &'a BTreeMap<String, String>, &'a str, &'static str);
+
+/// Render the selected values of `points` as annotated CSV, one table per
+/// series and field.
+///
+/// The tables of a response share their columns, as the client's CSV reader
+/// expects, so every table carries the tags of all selected series, empty
+/// where its series lacks them.
+fn annotated_csv(points: &[DataPoint], selection: &Selection) -> String {
+    let mut series: BTreeMap<Series<'_>, Vec<(i64, &FieldValue)>> = BTreeMap::new();
+    for point in points {
+        let timestamp = point.timestamp().expect("stored points are timestamped");
+        for (field, value) in point.fields() {
+            if selection.selects(point, field, timestamp) {
+                let key = (
+                    point.measurement(),
+                    point.tags(),
+                    field.as_str(),
+                    data_type(value),
+                );
+                series.entry(key).or_default().push((timestamp, value));
+            }
+        }
+    }
+
+    let mut tag_keys: Vec<&str> = series
+        .keys()
+        .flat_map(|(_, tags, _, _)| tags.keys().map(String::as_str))
+        .collect();
+    tag_keys.sort_unstable();
+    tag_keys.dedup();
+
+    let mut out = Vec::new();
+    for (table, ((measurement, tags, field, data_type), mut values)) in
+        series.into_iter().enumerate()
+    {
+        values.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let mut writer = csv::Writer::from_writer(&mut out);
+        let mut row = |first: &str, cells: Vec<String>| {
+            let record = std::iter::once(first.to_string()).chain(cells);
+            writer.write_record(record).expect("writing to memory");
+        };
+        let tag_cells = |cell: &str| vec![cell.to_string(); tag_keys.len()];
+
+        let mut datatypes = vec![
+            "string",
+            "long",
+            "dateTime:RFC3339Nano",
+            data_type,
+            "string",
+            "string",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+        datatypes.extend(tag_cells("string"));
+        row("#datatype", datatypes);
+
+        let mut groups = vec!["false"; 4]
+            .into_iter()
+            .chain(vec!["true"; 2])
+            .map(String::from)
+            .collect::<Vec<_>>();
+        groups.extend(tag_cells("true"));
+        row("#group", groups);
+
+        let mut defaults = vec!["_result".to_string()];
+        defaults.extend(vec![String::new(); 5]);
+        defaults.extend(tag_cells(""));
+        row("#default", defaults);
+
+        let names = vec![
+            "result",
+            "table",
+            "_time",
+            "_value",
+            "_field",
+            "_measurement",
+        ]
+        .into_iter()
+        .chain(tag_keys.iter().copied())
+        .map(String::from)
+        .collect();
+        row("", names);
+
+        for (timestamp, value) in values {
+            let mut cells = vec![
+                String::new(),
+                table.to_string(),
+                Utc.timestamp_nanos(timestamp)
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                format_value(value),
+                field.to_string(),
+                measurement.to_string(),
+            ];
+            cells.extend(
+                tag_keys
+                    .iter()
+                    .map(|key| tags.get(*key).cloned().unwrap_or_default()),
+            );
+            row("", cells);
+        }
+
+        writer.flush().expect("writing to memory");
+        drop(writer);
+        out.push(b'\n');
+    }
+
+    String::from_utf8(out).expect("the CSV is UTF-8")
+}
+
+fn data_type(value: &FieldValue) -> &'static str {
+    match value {
+        FieldValue::Bool(_) => "boolean",
+        FieldValue::F64(_) => "double",
+        FieldValue::I64(_) => "long",
+        FieldValue::U64(_) => "unsignedLong",
+        FieldValue::String(_) => "string",
+    }
+}
+
+fn format_value(value: &FieldValue) -> String {
+    match value {
+        FieldValue::Bool(v) => v.to_string(),
+        FieldValue::F64(v) => v.to_string(),
+        FieldValue::I64(v) => v.to_string(),
+        FieldValue::U64(v) => v.to_string(),
+        FieldValue::String(v) => v.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use influxdb2_structmap::value::Value;
+
+    fn query(flux: &str) -> Option<Query> {
+        Some(Query::new(flux.to_string()))
+    }
+
+    #[tokio::test]
+    async fn writes_and_queries() {
+        let server = FakeServer::start().unwrap();
+        let client = server.client();
+
+        client
+            .write_line_protocol_to(
+                server.bucket(),
+                "cpu,host=a usage=0.5,cores=4i 10\n\
+                 cpu,host=b usage=0.7 20\n\
+                 mem used=1.5 30",
+            )
+            .await
+            .unwrap();
+        assert_eq!(server.points(server.bucket()).len(), 3);
+
+        let rows = client
+            .query_raw(query(
+                r#"from(bucket: "test-bucket")
+                     |> range(start: 0)
+                     |> filter(fn: (r) => r._measurement == "cpu" and r["host"] == "a")"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["host"], Value::String("a".into()));
+        assert_eq!(rows[0]["usage"], Value::Double(0.5.into()));
+        assert_eq!(rows[0]["cores"], Value::Long(4));
+
+        let rows = client
+            .query_raw(query(
+                r#"from(bucket: "test-bucket")
+                     |> range(start: 1970-01-01T00:00:00.000000015Z, stop: 0)
+                     |> filter(fn: (r) => r._field == "usage")"#,
+            ))
+            .await
+            .unwrap();
+        assert!(rows.is_empty());
+
+        let rows = client
+            .query_raw(query(
+                r#"from(bucket: "test-bucket") |> filter(fn: (r) => r._field == "usage")"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["host"], Value::String("b".into()));
+    }
+
+    #[tokio::test]
+    async fn answers_canned_queries() {
+        let server = FakeServer::start().unwrap();
+        server.respond(
+            "from(bucket: \"b\") |> mean()",
+            "#datatype,string,long,double\n\
+             #group,false,false,false\n\
+             #default,_result,,\n\
+             ,result,table,_value\n\
+             ,,0,2.5\n",
+        );
+
+        let rows = server
+            .client()
+            .query_raw(query("from(bucket: \"b\")\n  |> mean()"))
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["_value"], Value::Double(2.5.into()));
+    }
+
+    #[tokio::test]
+    async fn rejects_what_it_cannot_answer() {
+        let server = FakeServer::start().unwrap();
+        let client = server.client();
+
+        let err = client
+            .query_raw(query("from(bucket: \"test-bucket\") |> mean()"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("FakeServer::respond"), "{}", err);
+
+        assert!(client
+            .query_raw(query("from(bucket: \"missing\")"))
+            .await
+            .is_err());
+        assert!(client
+            .write_line_protocol_to(server.bucket(), "not line protocol")
+            .await
+            .is_err());
+        assert!(client.ready().await.unwrap());
+    }
+}
@@ -5,9 +5,11 @@
     missing_docs,
     clippy::explicit_iter_loop,
     clippy::use_self,
-    clippy::clone_on_ref_ptr,
-    clippy::future_not_send
+    clippy::clone_on_ref_ptr
 )]
+// Futures are never `Send` on wasm since the browser `fetch` API is single
+// threaded.
+#![cfg_attr(not(target_arch = "wasm32"), warn(clippy::future_not_send))]
 
 //! # influxdb2
 //! 
@@ -100,27 +102,63 @@
 //! ```
 
 
+use reqwest::header::{HeaderMap, HeaderValue, InvalidHeaderValue};
 use reqwest::Method;
-use snafu::Snafu;
+use request::RequestKind;
+use snafu::{OptionExt, Snafu};
 
 /// Errors that occur while making requests to the Influx server.
+///
+/// Errors raised once a request has been sent carry the endpoint it was sent
+/// to, e.g. `POST /api/v2/write`, the ID this client generated for it (sent
+/// as the [`REQUEST_ID_HEADER`] header) and, when the server answered, the ID
+/// it reported in [`INFLUX_REQUEST_ID_HEADER`], so a failure can be matched
+/// with the server's logs. They carry the idempotency key of the request too,
+/// if it had one.
 #[derive(Debug, Snafu)]
 pub enum RequestError {
     /// While making a request to the Influx server, the underlying `reqwest`
     /// library returned an error that was not an HTTP 400 or 500.
-    #[snafu(display("Error while processing the HTTP request: {}", source))]
+    #[snafu(display(
+        "Error while processing the HTTP request{}: {}",
+        to_endpoint(endpoint),
+        source
+    ))]
     ReqwestProcessing {
         /// The underlying error object from `reqwest`.
         source: reqwest::Error,
+        /// The method and path of the request
+        endpoint: Option<String>,
+        /// The ID this client generated for the request
+        request_id: Option<String>,
+        /// The request ID reported by the server
+        influx_request_id: Option<String>,
+        /// The idempotency key the request carried
+        idempotency_key: Option<Box<str>>,
     },
     /// The underlying `reqwest` library returned an HTTP error with code 400
     /// (meaning a client error) or 500 (meaning a server error).
-    #[snafu(display("HTTP request returned an error: {}, `{}`", status, text))]
+    #[snafu(display(
+        "HTTP request{} returned an error: {}, `{}`",
+        to_endpoint(endpoint),
+        status,
+        text
+    ))]
     Http {
         /// The `StatusCode` returned from the request
         status: reqwest::StatusCode,
         /// Any text data returned from the request
         text: String,
+        /// The error body, if the server answered with its JSON error format
+        body: Option<Box<models::ErrorResponse>>,
+        /// The method and path of the request
+        endpoint: Option<String>,
+        /// The ID this client generated for the request
+        request_id: Option<String>,
+        /// The request ID reported by the server
+        influx_request_id: Option<String>,
+        /// The idempotency key the request carried
+        idempotency_key: Option<Box<str>>,
     },
 
     /// While serializing data as JSON to send in a request, the underlying
@@ -133,11 +171,346 @@ pub enum RequestError {
 
     /// While deserializing response from the Influx server, the underlying
     /// parsing library returned an error.
-    #[snafu(display("Error while parsing response: {}", text))]
+    #[snafu(display("Error while parsing response{}: {}", from_endpoint(endpoint), text))]
     Deserializing {
         /// Error description.
         text: String,
+        /// The method and path of the request
+        endpoint: Option<String>,
+        /// The ID this client generated for the request
+        request_id: Option<String>,
+        /// The request ID reported by the server
+        influx_request_id: Option<String>,
+        /// The idempotency key the request carried
+        idempotency_key: Option<Box<str>>,
     },
+
+    /// The schedule of a task is invalid, so it wasn't sent.
+    #[snafu(display("Invalid task schedule: {}", source))]
+    InvalidSchedule {
+        /// The schedule error
+        source: models::ScheduleError,
+    },
+
+    /// A continuous query to migrate has no equivalent task.
+    #[snafu(display("Invalid continuous query: {}", source))]
+    InvalidContinuousQuery {
+        /// The parse error
+        source: models::ContinuousQueryError,
+    },
+
+    /// The call named no bucket and the client has no default bucket.
+    #[snafu(display("No bucket given and the client has no default bucket"))]
+    MissingBucket,
+
+    /// No organization has the name whose ID was asked for.
+    #[snafu(display("No organization named {:?}", name))]
+    OrgNotFound {
+        /// The name of the organization
+        name: String,
+    },
+
+    /// No label of the organization has the name whose ID was asked for.
+    #[snafu(display("No label named {:?} in organization {:?}", name, org))]
+    LabelNotFound {
+        /// The name of the organization
+        org: String,
+        /// The name of the label
+        name: String,
+    },
+
+    /// A point's timestamp isn't plausible in the precision of the write, e.g.
+    /// seconds written as nanoseconds, so the write was aborted.
+    #[snafu(display(
+        "Timestamp {} is implausible in {}{}",
+        timestamp,
+        precision,
+        likely
+            .map(|likely| format!(", it looks like {}", likely))
+            .unwrap_or_default()
+    ))]
+    ImplausibleTimestamp {
+        /// The timestamp of the point
+        timestamp: i64,
+        /// The precision of the write
+        precision: line_protocol::Precision,
+        /// The precision the timestamp looks like it was taken in
+        likely: Option<line_protocol::Precision>,
+    },
+
+    /// The points of a write couldn't be serialized as line protocol, e.g.
+    /// because a name couldn't be renamed, so the write was aborted.
+    #[snafu(display("Error while serializing points as line protocol: {}", source))]
+    LineProtocol {
+        /// The I/O error
+        source: std::io::Error,
+    },
+
+    /// The rows of a spooled query couldn't be written to or read from their
+    /// temporary file.
+    #[snafu(display("Error while spooling query rows: {}", source))]
+    Spooling {
+        /// The I/O error
+        source: std::io::Error,
+    },
+
+    /// A JSON response is larger than the limit set with
+    /// `ClientBuilder::with_max_response_size`, so it wasn't read.
+    #[snafu(display(
+        "Response{} exceeds the limit of {} bytes",
+        from_endpoint(endpoint),
+        limit
+    ))]
+    ResponseTooLarge {
+        /// The largest response read, in bytes
+        limit: usize,
+        /// The method and path of the request
+        endpoint: Option<String>,
+        /// The ID this client generated for the request
+        request_id: Option<String>,
+        /// The request ID reported by the server
+        influx_request_id: Option<String>,
+        /// The idempotency key the request carried
+        idempotency_key: Option<Box<str>>,
+    },
+
+    /// The call was cancelled through the token given to
+    /// `Client::with_cancellation`.
+    #[snafu(display("Request{} cancelled", to_endpoint(endpoint)))]
+    Cancelled {
+        /// The method and path of the request
+        endpoint: Option<String>,
+        /// The ID this client generated for the request
+        request_id: Option<String>,
+        /// The idempotency key the request carried
+        idempotency_key: Option<Box<str>>,
+    },
+
+    /// The cassette the client replays has no recording of the request, see
+    /// `ClientBuilder::with_cassette`.
+    #[snafu(display("No recorded response{}", to_endpoint(endpoint)))]
+    Unrecorded {
+        /// The method and path of the request
+        endpoint: Option<String>,
+        /// The ID this client generated for the request
+        request_id: Option<String>,
+        /// The idempotency key the request carried
+        idempotency_key: Option<Box<str>>,
+    },
+}
+
+fn to_endpoint(endpoint: &Option<String>) -> String {
+    endpoint
+        .as_ref()
+        .map(|endpoint| format!(" to {}", endpoint))
+        .unwrap_or_default()
+}
+
+fn from_endpoint(endpoint: &Option<String>) -> String {
+    endpoint
+        .as_ref()
+        .map(|endpoint| format!(" from {}", endpoint))
+        .unwrap_or_default()
+}
+
+impl RequestError {
+    /// The ID this client generated for the failed request, if it was sent.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::ReqwestProcessing { request_id, .. }
+            | Self::Http { request_id, .. }
+            | Self::Deserializing { request_id, .. }
+            | Self::ResponseTooLarge { request_id, .. }
+            | Self::Cancelled { request_id, .. }
+            | Self::Unrecorded { request_id, .. } => request_id.as_deref(),
+            Self::Serializing { .. }
+            | Self::InvalidSchedule { .. }
+            | Self::InvalidContinuousQuery { .. }
+            | Self::MissingBucket
+            | Self::OrgNotFound { .. }
+            | Self::LabelNotFound { .. }
+            | Self::ImplausibleTimestamp { .. }
+            | Self::LineProtocol { .. }
+            | Self::Spooling { .. } => None,
+        }
+    }
+
+    /// The request ID the server reported for the failed request, if it
+    /// answered.
+    pub fn influx_request_id(&self) -> Option<&str> {
+        match self {
+            Self::ReqwestProcessing {
+                influx_request_id, ..
+            }
+            | Self::Http {
+                influx_request_id, ..
+            }
+            | Self::Deserializing {
+                influx_request_id, ..
+            }
+            | Self::ResponseTooLarge {
+                influx_request_id, ..
+            } => influx_request_id.as_deref(),
+            Self::Serializing { .. }
+            | Self::InvalidSchedule { .. }
+            | Self::InvalidContinuousQuery { .. }
+            | Self::MissingBucket
+            | Self::OrgNotFound { .. }
+            | Self::LabelNotFound { .. }
+            | Self::ImplausibleTimestamp { .. }
+            | Self::LineProtocol { .. }
+            | Self::Spooling { .. }
+            | Self::Cancelled { .. }
+            | Self::Unrecorded { .. } => None,
+        }
+    }
+
+    /// The idempotency key the failed request carried in the
+    /// [`IDEMPOTENCY_KEY_HEADER`] header, see `Client::with_idempotency_key`.
+    pub fn idempotency_key(&self) -> Option<&str> {
+        match self {
+            Self::ReqwestProcessing {
+                idempotency_key, ..
+            }
+            | Self::Http {
+                idempotency_key, ..
+            }
+            | Self::Deserializing {
+                idempotency_key, ..
+            }
+            | Self::ResponseTooLarge {
+                idempotency_key, ..
+            }
+            | Self::Cancelled {
+                idempotency_key, ..
+            }
+            | Self::Unrecorded {
+                idempotency_key, ..
+            } => idempotency_key.as_deref(),
+            Self::Serializing { .. }
+            | Self::InvalidSchedule { .. }
+            | Self::InvalidContinuousQuery { .. }
+            | Self::MissingBucket
+            | Self::OrgNotFound { .. }
+            | Self::LabelNotFound { .. }
+            | Self::ImplausibleTimestamp { .. }
+            | Self::LineProtocol { .. }
+            | Self::Spooling { .. } => None,
+        }
+    }
+
+    /// The method and path of the failed request, e.g. `GET /api/v2/buckets`,
+    /// if it was sent.
+    pub fn endpoint(&self) -> Option<&str> {
+        match self {
+            Self::ReqwestProcessing { endpoint, .. }
+            | Self::Http { endpoint, .. }
+            | Self::Deserializing { endpoint, .. }
+            | Self::ResponseTooLarge { endpoint, .. }
+            | Self::Cancelled { endpoint, .. }
+            | Self::Unrecorded { endpoint, .. } => endpoint.as_deref(),
+            Self::Serializing { .. }
+            | Self::InvalidSchedule { .. }
+            | Self::InvalidContinuousQuery { .. }
+            | Self::MissingBucket
+            | Self::OrgNotFound { .. }
+            | Self::LabelNotFound { .. }
+            | Self::ImplausibleTimestamp { .. }
+            | Self::LineProtocol { .. }
+            | Self::Spooling { .. } => None,
+        }
+    }
+
+    pub(crate) fn deserializing(text: impl Into<String>) -> Self {
+        Self::Deserializing {
+            text: text.into(),
+            endpoint: None,
+            request_id: None,
+            influx_request_id: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[cfg(feature = "management")]
+    /// The error for a resource the server returned without an ID, which
+    /// no call can target
+    pub(crate) fn missing_id(kind: &str, name: &str) -> Self {
+        Self::deserializing(format!("The {} {:?} has no ID", kind, name))
+    }
+
+    /// Fill in the request context of an error that doesn't carry it yet
+    pub(crate) fn with_context(mut self, context: &request::RequestContext) -> Self {
+        let fill = |field: &mut Option<String>, value: Option<&str>| {
+            if field.is_none() {
+                *field = value.map(ToString::to_string);
+            }
+        };
+        let fill_key = |field: &mut Option<Box<str>>, value: Option<&str>| {
+            if field.is_none() {
+                *field = value.map(Box::from);
+            }
+        };
+
+        match &mut self {
+            Self::ReqwestProcessing {
+                endpoint,
+                request_id,
+                influx_request_id,
+                idempotency_key,
+                ..
+            }
+            | Self::Http {
+                endpoint,
+                request_id,
+                influx_request_id,
+                idempotency_key,
+                ..
+            }
+            | Self::Deserializing {
+                endpoint,
+                request_id,
+                influx_request_id,
+                idempotency_key,
+                ..
+            }
+            | Self::ResponseTooLarge {
+                endpoint,
+                request_id,
+                influx_request_id,
+                idempotency_key,
+                ..
+            } => {
+                fill(endpoint, Some(&context.endpoint));
+                fill(request_id, Some(&context.request_id));
+                fill(influx_request_id, context.influx_request_id.as_deref());
+                fill_key(idempotency_key, context.idempotency_key.as_deref());
+            }
+            Self::Cancelled {
+                endpoint,
+                request_id,
+                idempotency_key,
+            }
+            | Self::Unrecorded {
+                endpoint,
+                request_id,
+                idempotency_key,
+            } => {
+                fill(endpoint, Some(&context.endpoint));
+                fill(request_id, Some(&context.request_id));
+                fill_key(idempotency_key, context.idempotency_key.as_deref());
+            }
+            Self::Serializing { .. }
+            | Self::InvalidSchedule { .. }
+            | Self::InvalidContinuousQuery { .. }
+            | Self::MissingBucket
+            | Self::OrgNotFound { .. }
+            | Self::LabelNotFound { .. }
+            | Self::ImplausibleTimestamp { .. }
+            | Self::LineProtocol { .. }
+            | Self::Spooling { .. } => {}
+        }
+        self
+    }
 }
 
 /// Client to a server supporting the InfluxData 2.0 API.
@@ -147,47 +520,248 @@ pub struct Client {
     pub url: String,
     /// The organization tied to this client
     pub org: String,
+    /// The bucket written to when a call doesn't name one
+    pub bucket: Option<String>,
     auth_header: Option<String>,
+    headers: HeaderMap,
+    log_requests: bool,
+    write_precision: Option<line_protocol::Precision>,
+    timestamp_check: line_protocol::TimestampCheck,
+    write_compression: std::sync::Arc<compression::Negotiation>,
+    naming: Option<std::sync::Arc<dyn line_protocol::naming::Naming>>,
+    clock: std::sync::Arc<dyn clock::Clock>,
+    retry_policy: Option<retry::RetryPolicy>,
+    max_response_size: Option<usize>,
+    endpoint_overrides: std::sync::Arc<std::collections::BTreeMap<String, String>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeouts: request::Timeouts,
+    cancellation: Option<CancellationToken>,
+    #[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+    cassette: Option<vcr::Cassette>,
+    #[cfg(feature = "management")]
+    org_ids: std::sync::Arc<api::organization::OrgIdCache>,
+    #[cfg(feature = "management")]
+    label_ids: std::sync::Arc<api::label::LabelIdCache>,
+    #[cfg(feature = "management")]
+    users: std::sync::Arc<api::users::UserCache>,
     reqwest: reqwest::Client,
 }
 
 impl Client {
     /// Create a new client pointing to the URL specified in
     /// `protocol://server:port` format and using the specified token for
-    /// authorization.
+    /// authorization. The URL may carry a path prefix, as in
+    /// `https://gateway.example.com/influx`, which is kept in front of every
+    /// endpoint.
     ///
     /// # Example
     ///
     /// ```
     /// let client = influxdb2::Client::new("http://localhost:8888", "org", "my-token");
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying HTTP client could not be created; use
+    /// [`Client::builder`] to handle that error.
     pub fn new(
         url: impl Into<String>, 
         org: impl Into<String>, 
         auth_token: impl Into<String>
     ) -> Self {
-        let token = auth_token.into();
-        let auth_header = if token.is_empty() {
-            None
-        } else {
-            Some(format!("Token {}", token))
-        };
+        Self::builder(url, org, auth_token)
+            .build()
+            .expect("failed to create the HTTP client")
+    }
+
+    /// Create a builder to configure the connection settings of a new
+    /// client.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), influxdb2::BuildError> {
+    /// let client = influxdb2::Client::builder("http://localhost:8888", "org", "my-token")
+    ///     .with_pool_max_idle_per_host(32)
+    ///     .with_tcp_nodelay(true)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(
+        url: impl Into<String>,
+        org: impl Into<String>,
+        auth_token: impl Into<String>,
+    ) -> ClientBuilder {
+        ClientBuilder::new(url, org, auth_token)
+    }
+
+    /// A copy of this client that adds `headers` to every request it sends,
+    /// for one-off headers such as tenant routing or idempotency keys. The
+    /// copy shares the connection pool of this client.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use reqwest::header::{HeaderMap, HeaderValue};
+    ///
+    /// # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("X-Tenant", HeaderValue::from_static("acme"));
+    /// let health = client.with_headers(headers).health().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_headers(&self, headers: HeaderMap) -> Self {
+        let mut client = self.clone();
+        client.headers.extend(headers);
+        client
+    }
+
+    /// A copy of this client that sends `key` in the
+    /// [`IDEMPOTENCY_KEY_HEADER`] header of every request, and reports it in
+    /// the errors of its calls. Keep one key per batch across its retries, so
+    /// a pipeline delivering writes at least once can have duplicates
+    /// detected downstream; [`new_idempotency_key`] generates one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn example(client: influxdb2::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let points: Vec<influxdb2::models::DataPoint> = Vec::new();
+    /// let key = influxdb2::new_idempotency_key();
+    /// let batch = client.with_idempotency_key(&key)?;
+    /// if let Err(e) = batch.write("bucket", futures::stream::iter(points)).await {
+    ///     eprintln!("batch {:?} failed: {}", e.idempotency_key(), e);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_idempotency_key(&self, key: &str) -> Result<Self, InvalidHeaderValue> {
+        let mut client = self.clone();
+        client
+            .headers
+            .insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(key)?);
+        Ok(client)
+    }
 
-        Self {
-            url: url.into(),
-            org: org.into(),
-            auth_header,
-            reqwest: reqwest::Client::new(),
+    /// A copy of this client whose calls are aborted once `token` is
+    /// cancelled, failing with `RequestError::Cancelled`. Cancelling stops
+    /// in-flight uploads and downloads, so an application can shut down
+    /// within its deadline. The copy shares the connection pool of this
+    /// client.
+    ///
+    /// Dropping the future of a call aborts it as well; the token is for
+    /// cancelling many calls at once, possibly from another task.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use influxdb2::CancellationToken;
+    ///
+    /// # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+    /// let shutdown = CancellationToken::new();
+    /// let client = client.with_cancellation(shutdown.child_token());
+    /// // On shutdown, from anywhere:
+    /// shutdown.cancel();
+    /// assert!(client.ready().await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cancellation(&self, token: CancellationToken) -> Self {
+        let mut client = self.clone();
+        client.cancellation = Some(token);
+        client
+    }
+
+    /// Resolve the organization and bucket of `target` against the defaults
+    /// of this client
+    fn resolve<'a>(&'a self, target: &'a Target) -> Result<(&'a str, &'a str), RequestError> {
+        let org = self.resolve_org(target);
+        let bucket = target
+            .bucket
+            .as_deref()
+            .or(self.bucket.as_deref())
+            .context(MissingBucketSnafu)?;
+        Ok((org, bucket))
+    }
+
+    /// Resolve the organization of `target`, for calls that name no bucket
+    fn resolve_org<'a>(&'a self, target: &'a Target) -> &'a str {
+        target.org.as_deref().unwrap_or(&self.org)
+    }
+
+    /// Compose the URL of the endpoint at `path`, keeping any path prefix of
+    /// the base URL (e.g. `https://gateway.example.com/influx`) so the client
+    /// works behind path-rewriting reverse proxies. An override set with
+    /// `ClientBuilder::with_endpoint_override` for the longest leading part of
+    /// `path` replaces that part.
+    fn endpoint_url(&self, path: &str) -> String {
+        let overridden = self
+            .endpoint_overrides
+            .iter()
+            .filter(|(endpoint, _)| {
+                path.strip_prefix(endpoint.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(&['/', '?'][..]))
+            })
+            .max_by_key(|(endpoint, _)| endpoint.len());
+
+        match overridden {
+            Some((endpoint, url)) if url.contains("://") => {
+                format!("{}{}", url.trim_end_matches('/'), &path[endpoint.len()..])
+            }
+            Some((endpoint, route)) => format!(
+                "{}{}{}",
+                self.url.trim_end_matches('/'),
+                route.trim_end_matches('/'),
+                &path[endpoint.len()..]
+            ),
+            None => format!("{}{}", self.url.trim_end_matches('/'), path),
+        }
+    }
+
+    /// A copy of this client whose default organization and bucket are
+    /// replaced by the parts set in `target`, for calls that don't take a
+    /// target. The copy shares the connection pool of this
+    /// client.
+    pub fn with_target(&self, target: impl Into<Target>) -> Self {
+        let target = target.into();
+        let mut client = self.clone();
+        if let Some(org) = target.org {
+            client.org = org;
+        }
+        if let Some(bucket) = target.bucket {
+            client.bucket = Some(bucket);
         }
+        client
     }
 
     /// Consolidate common request building code
     fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        self.request_as(RequestKind::Management, method, url)
+    }
+
+    /// Build a request of the given class, which selects its timeout
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn request_as(
+        &self,
+        kind: RequestKind,
+        method: Method,
+        url: &str,
+    ) -> reqwest::RequestBuilder {
         let mut req = self.reqwest.request(method, url);
 
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.timeouts.get(kind) {
+            req = req.timeout(timeout);
+        }
+
         if let Some(auth) = &self.auth_header {
             req = req.header("Authorization", auth);
         }
+        if !self.headers.is_empty() {
+            req = req.headers(self.headers.clone());
+        }
 
         req
     }
@@ -196,8 +770,57 @@ impl Client {
 pub mod common;
 
 pub mod api;
+#[cfg(all(feature = "write-batcher", not(target_arch = "wasm32")))]
+pub mod batcher;
+#[cfg(all(feature = "bench", not(target_arch = "wasm32")))]
+pub mod bench;
+mod builder;
+pub mod clock;
+pub mod compression;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod failover;
+#[cfg(all(feature = "fake-server", not(target_arch = "wasm32")))]
+pub mod fake;
+mod logging;
+#[cfg(all(feature = "health-monitor", not(target_arch = "wasm32")))]
+pub mod monitor;
+mod request;
+pub mod retry;
+mod shared;
+mod target;
+#[cfg(all(
+    any(
+        feature = "tracing-layer",
+        feature = "log-appender",
+        feature = "metrics-exporter",
+        feature = "opentelemetry",
+        feature = "write-batcher"
+    ),
+    not(target_arch = "wasm32")
+))]
+pub mod telemetry;
+pub mod mock;
+pub mod line_protocol;
 pub mod models;
+pub mod prometheus;
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+#[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+pub mod vcr;
 
 // Re-exports
+pub use api::client::InfluxClient;
+pub use builder::{BuildError, ClientBuilder};
+#[cfg(not(target_arch = "wasm32"))]
+pub use builder::HttpVersion;
+pub use shared::SharedClient;
+pub use target::Target;
+pub use tokio_util::sync::CancellationToken;
+pub use request::{
+    new_idempotency_key, IDEMPOTENCY_KEY_HEADER, INFLUX_REQUEST_ID_HEADER, REQUEST_ID_HEADER,
+};
 pub use influxdb2_structmap::FromMap;
 pub use influxdb2_derive::FromDataPoint;
@@ -0,0 +1,366 @@
+//! Line protocol literals
+//!
+//! The `lp!` macro checks a line protocol string literal while the crate
+//! using it compiles, following the rules of [`parse`](super::parse), and
+//! yields a [`LineProtocol`] body for `Client::write_line_protocol_to`:
+//!
+//! ```
+//! use influxdb2::lp;
+//!
+//! let body = lp!("cpu,host=server\\ 01 usage=0.5,cores=4i 1556813561000000000");
+//! assert_eq!(body.as_str(), "cpu,host=server\\ 01 usage=0.5,cores=4i 1556813561000000000");
+//! ```
+//!
+//! Text that doesn't parse doesn't compile:
+//!
+//! ```compile_fail
+//! let body = influxdb2::lp!("cpu,host=a usage=0.5x");
+//! ```
+//!
+//! Timestamps are only checked to be 64-bit integers, since their precision
+//! is the client's.
+
+use std::fmt;
+
+use reqwest::Body;
+
+/// Line protocol text that was checked when it was compiled, see `lp!`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LineProtocol(&'static str);
+
+impl LineProtocol {
+    #[doc(hidden)]
+    pub const fn __checked(text: &'static str) -> Self {
+        check(text);
+        Self(text)
+    }
+
+    /// The text.
+    pub const fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Display for LineProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl From<LineProtocol> for Body {
+    fn from(text: LineProtocol) -> Self {
+        Self::from(text.0)
+    }
+}
+
+/// Check line protocol text at compile time, yielding a
+/// [`LineProtocol`](crate::line_protocol::LineProtocol).
+///
+/// See the [`literal`](crate::line_protocol::literal) module.
+#[macro_export]
+macro_rules! lp {
+    ($text:literal) => {{
+        const BODY: $crate::line_protocol::LineProtocol =
+            $crate::line_protocol::LineProtocol::__checked($text);
+        BODY
+    }};
+}
+
+/// Panic, failing the build when evaluated at compile time, unless `text`
+/// is valid line protocol
+const fn check(text: &str) {
+    let text = text.as_bytes();
+    let mut i = 0;
+    while i < text.len() {
+        i = skip_spaces(text, i);
+        if at_line_end(text, i) {
+            i = next_line(text, i);
+        } else if text[i] == b'#' {
+            while i < text.len() && text[i] != b'\n' {
+                i += 1;
+            }
+        } else {
+            i = check_line(text, i);
+        }
+    }
+}
+
+/// Check the line starting at `i`, returning where the next one starts
+const fn check_line(text: &[u8], mut i: usize) -> usize {
+    let start = i;
+    i = key_end(text, i, false);
+    if i == start {
+        panic!("invalid line protocol: missing measurement");
+    }
+
+    while i < text.len() && text[i] == b',' {
+        let key = i + 1;
+        i = key_end(text, key, true);
+        if i == key || i >= text.len() || text[i] != b'=' {
+            panic!("invalid line protocol: tag is not key=value");
+        }
+        let value = i + 1;
+        i = key_end(text, value, true);
+        if i == value {
+            panic!("invalid line protocol: tag is not key=value");
+        }
+    }
+
+    if i >= text.len() || text[i] != b' ' {
+        panic!("invalid line protocol: missing fields");
+    }
+    i = skip_spaces(text, i);
+    if at_line_end(text, i) {
+        panic!("invalid line protocol: missing fields");
+    }
+    loop {
+        let key = i;
+        i = key_end(text, key, true);
+        if i == key || i >= text.len() || text[i] != b'=' {
+            panic!("invalid line protocol: field is not key=value");
+        }
+        i = field_value_end(text, i + 1);
+        if i < text.len() && text[i] == b',' {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    i = skip_spaces(text, i);
+    if !at_line_end(text, i) {
+        i = timestamp_end(text, i);
+        i = skip_spaces(text, i);
+        if !at_line_end(text, i) {
+            panic!("invalid line protocol: unexpected text after the timestamp");
+        }
+    }
+    next_line(text, i)
+}
+
+/// The end of the measurement, tag key or value, or field key at `i`
+const fn key_end(text: &[u8], mut i: usize, stop_at_equals: bool) -> usize {
+    while i < text.len() {
+        match text[i] {
+            b'\\' if i + 1 < text.len() && text[i + 1] != b'\n' => i += 2,
+            b',' | b' ' | b'\n' => return i,
+            b'=' if stop_at_equals => return i,
+            b'\r' if i + 1 < text.len() && text[i + 1] == b'\n' => return i,
+            _ => i += 1,
+        }
+    }
+    i
+}
+
+/// The end of the field value at `i`
+const fn field_value_end(text: &[u8], mut i: usize) -> usize {
+    if i < text.len() && text[i] == b'"' {
+        i += 1;
+        while i < text.len() {
+            match text[i] {
+                b'\\' if i + 1 < text.len() => i += 2,
+                b'"' => return i + 1,
+                _ => i += 1,
+            }
+        }
+        panic!("invalid line protocol: unterminated string field");
+    }
+
+    let start = i;
+    while i < text.len() && !matches!(text[i], b',' | b' ' | b'\n') && !at_line_end(text, i) {
+        i += 1;
+    }
+    if !is_field_value(text, start, i) {
+        panic!("invalid line protocol: field value is not a float, integer, boolean or string");
+    }
+    i
+}
+
+const BOOLEANS: &[&str] = &[
+    "t", "T", "true", "True", "TRUE", "f", "F", "false", "False", "FALSE",
+];
+
+/// Whether `text[start..end]` is an unquoted field value
+const fn is_field_value(text: &[u8], start: usize, end: usize) -> bool {
+    let mut b = 0;
+    while b < BOOLEANS.len() {
+        if equals(text, start, end, BOOLEANS[b].as_bytes()) {
+            return true;
+        }
+        b += 1;
+    }
+    if end == start {
+        return false;
+    }
+
+    match text[end - 1] {
+        b'i' => is_integer(text, start, end - 1, true),
+        b'u' => is_integer(text, start, end - 1, false),
+        _ => is_float(text, start, end),
+    }
+}
+
+const fn equals(text: &[u8], start: usize, end: usize, word: &[u8]) -> bool {
+    if end - start != word.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < word.len() {
+        if text[start + i] != word[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Whether `text[start..end]` is an integer fitting in an `i64`, negative
+/// only if `signed`
+const fn is_integer(text: &[u8], mut start: usize, end: usize, signed: bool) -> bool {
+    let mut negative = false;
+    if signed && start < end && text[start] == b'-' {
+        negative = true;
+        start += 1;
+    }
+    if start == end {
+        return false;
+    }
+    // Unsigned values are read into an `i64` too
+    let limit = match negative {
+        true => i64::MAX as u128 + 1,
+        false => i64::MAX as u128,
+    };
+    let mut value: u128 = 0;
+    while start < end {
+        if !text[start].is_ascii_digit() {
+            return false;
+        }
+        value = value * 10 + (text[start] - b'0') as u128;
+        if value > limit {
+            return false;
+        }
+        start += 1;
+    }
+    true
+}
+
+/// Whether `text[start..end]` is a finite float such as `-1.5e3`
+const fn is_float(text: &[u8], mut i: usize, end: usize) -> bool {
+    if i < end && (text[i] == b'-' || text[i] == b'+') {
+        i += 1;
+    }
+    let mut digits = 0;
+    while i < end && text[i].is_ascii_digit() {
+        i += 1;
+        digits += 1;
+    }
+    if i < end && text[i] == b'.' {
+        i += 1;
+        while i < end && text[i].is_ascii_digit() {
+            i += 1;
+            digits += 1;
+        }
+    }
+    if digits == 0 {
+        return false;
+    }
+    if i < end && (text[i] == b'e' || text[i] == b'E') {
+        i += 1;
+        if i < end && (text[i] == b'-' || text[i] == b'+') {
+            i += 1;
+        }
+        let (digits, mut exponent) = (i, 0);
+        while i < end && text[i].is_ascii_digit() {
+            exponent = exponent * 10 + (text[i] - b'0') as u32;
+            if exponent > 308 {
+                return false;
+            }
+            i += 1;
+        }
+        if i == digits {
+            return false;
+        }
+    }
+    i == end
+}
+
+/// The end of the timestamp at `i`
+const fn timestamp_end(text: &[u8], i: usize) -> usize {
+    let mut end = i;
+    while end < text.len() && !matches!(text[end], b' ' | b'\t' | b'\n') && !at_line_end(text, end)
+    {
+        end += 1;
+    }
+    if !is_integer(text, i, end, true) {
+        panic!("invalid line protocol: timestamp is not a 64-bit integer");
+    }
+    end
+}
+
+const fn skip_spaces(text: &[u8], mut i: usize) -> usize {
+    while i < text.len() && (text[i] == b' ' || text[i] == b'\t') {
+        i += 1;
+    }
+    i
+}
+
+const fn at_line_end(text: &[u8], i: usize) -> bool {
+    i >= text.len()
+        || text[i] == b'\n'
+        || (text[i] == b'\r' && i + 1 < text.len() && text[i + 1] == b'\n')
+}
+
+const fn next_line(text: &[u8], mut i: usize) -> usize {
+    while i < text.len() && text[i] != b'\n' {
+        i += 1;
+    }
+    i + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_protocol::{parse, Precision};
+
+    #[test]
+    fn accepts_what_parses() {
+        let body = lp!("# a comment\n\
+                        \n\
+                        m,t=v\\ 1 a=1,b=-2i,c=3u,d=t,e=FALSE,f=\"x,\\\"y\\\"\nz\",g=-1e3 10\r\n\
+                        m x=.5 -9223372036854775808\n");
+
+        let points = parse(body.as_str(), Precision::Nanoseconds).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].timestamp(), Some(i64::MIN));
+    }
+
+    #[test]
+    #[should_panic(expected = "missing fields")]
+    fn rejects_lines_without_fields() {
+        check("m,t=v \n");
+    }
+
+    #[test]
+    #[should_panic(expected = "field value")]
+    fn rejects_invalid_field_values() {
+        check("m a=9223372036854775808i");
+    }
+
+    #[test]
+    #[should_panic(expected = "tag is not key=value")]
+    fn rejects_invalid_tags() {
+        check("m,t a=1");
+    }
+
+    #[test]
+    #[should_panic(expected = "timestamp")]
+    fn rejects_invalid_timestamps() {
+        check("m a=1 1.5");
+    }
+
+    #[test]
+    #[should_panic(expected = "after the timestamp")]
+    fn rejects_trailing_text() {
+        check("m a=1 1 2");
+    }
+}
@@ -0,0 +1,102 @@
+//! Line protocol
+//!
+//! Conversions between Rust values and InfluxDB line protocol that don't go
+//! through the `FromDataPoint` derive.
+
+use std::fmt;
+
+pub mod literal;
+pub mod naming;
+mod parse;
+mod ser;
+
+pub use literal::LineProtocol;
+pub use parse::{parse, ParseError};
+pub use ser::{to_data_point, to_line_protocol, Mapping, SerializeError};
+
+/// The unit of line protocol timestamps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Precision {
+    /// Nanoseconds, the default of InfluxDB
+    #[default]
+    Nanoseconds,
+    /// Microseconds
+    Microseconds,
+    /// Milliseconds
+    Milliseconds,
+    /// Seconds
+    Seconds,
+}
+
+impl Precision {
+    /// The value of the `precision` parameter of the write API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Nanoseconds => "ns",
+            Self::Microseconds => "us",
+            Self::Milliseconds => "ms",
+            Self::Seconds => "s",
+        }
+    }
+
+    /// Convert `timestamp` in this precision to nanoseconds, `None` on
+    /// overflow.
+    pub fn to_nanos(self, timestamp: i64) -> Option<i64> {
+        let factor = match self {
+            Self::Nanoseconds => 1,
+            Self::Microseconds => 1_000,
+            Self::Milliseconds => 1_000_000,
+            Self::Seconds => 1_000_000_000,
+        };
+        timestamp.checked_mul(factor)
+    }
+
+    /// Whether `timestamp` read in this precision falls after 1970, when
+    /// InfluxDB can represent it. Timestamps in a finer unit than they are
+    /// read in overflow, and those in a coarser unit land in January 1970.
+    pub fn is_plausible(self, timestamp: i64) -> bool {
+        self.to_nanos(timestamp)
+            .is_some_and(|nanos| nanos >= PLAUSIBLE_SINCE_NANOS)
+    }
+
+    /// The precision `timestamp` looks like it was taken in, if any.
+    pub fn infer(timestamp: i64) -> Option<Self> {
+        [
+            Self::Nanoseconds,
+            Self::Microseconds,
+            Self::Milliseconds,
+            Self::Seconds,
+        ]
+        .iter()
+        .copied()
+        .find(|precision| precision.is_plausible(timestamp))
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Nanoseconds => "nanoseconds",
+            Self::Microseconds => "microseconds",
+            Self::Milliseconds => "milliseconds",
+            Self::Seconds => "seconds",
+        })
+    }
+}
+
+/// 1971-01-01T00:00:00Z in nanoseconds
+const PLAUSIBLE_SINCE_NANOS: i64 = 365 * 86_400 * 1_000_000_000;
+
+/// What `Client::write` does with a point whose timestamp isn't plausible in
+/// the precision of the write, see [`Precision::is_plausible`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum TimestampCheck {
+    /// Write the point without checking its timestamp
+    #[default]
+    Off,
+    /// Write the point, logging a `tracing` warning for the first such point
+    /// of each write
+    Warn,
+    /// Abort the write with `RequestError::ImplausibleTimestamp`
+    Reject,
+}
@@ -0,0 +1,226 @@
+//! Naming conventions
+//!
+//! A [`Naming`] renames the measurement, tag keys and field keys of every
+//! point `Client::write` sends, set with `ClientBuilder::with_naming`, so a
+//! convention such as camelCase field keys or a per-service measurement
+//! prefix is enforced in one place instead of at every call site. It applies
+//! to `DataPoint`s and `FromDataPoint` types alike; line protocol given to
+//! `Client::write_line_protocol_to` is sent as it is.
+//!
+//! ```
+//! use influxdb2::line_protocol::naming::{CamelCase, MeasurementPrefix};
+//!
+//! let client = influxdb2::Client::builder("http://localhost:8086", "org", "my-token")
+//!     .with_naming((CamelCase, MeasurementPrefix::new("billing_")))
+//!     .build()
+//!     .unwrap();
+//! // `cpu_load,host_name=a load_avg=1` is written as
+//! // `billing_cpuLoad,hostName=a loadAvg=1`
+//! ```
+//!
+//! Two names mapping to the same name collide, and the value written last
+//! wins.
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// Renames the parts of the points written
+///
+/// Every method keeps names as they are by default. A pair `(A, B)` applies
+/// `A`, then `B`.
+pub trait Naming: fmt::Debug + Send + Sync {
+    /// The name `measurement` is written as.
+    fn measurement<'a>(&self, measurement: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(measurement)
+    }
+
+    /// The name the tag `key` is written as.
+    fn tag_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(key)
+    }
+
+    /// The name the field `key` is written as.
+    fn field_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(key)
+    }
+}
+
+impl<A: Naming, B: Naming> Naming for (A, B) {
+    fn measurement<'a>(&self, measurement: &'a str) -> Cow<'a, str> {
+        match self.0.measurement(measurement) {
+            Cow::Borrowed(name) => self.1.measurement(name),
+            Cow::Owned(name) => Cow::Owned(self.1.measurement(&name).into_owned()),
+        }
+    }
+
+    fn tag_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match self.0.tag_key(key) {
+            Cow::Borrowed(name) => self.1.tag_key(name),
+            Cow::Owned(name) => Cow::Owned(self.1.tag_key(&name).into_owned()),
+        }
+    }
+
+    fn field_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match self.0.field_key(key) {
+            Cow::Borrowed(name) => self.1.field_key(name),
+            Cow::Owned(name) => Cow::Owned(self.1.field_key(&name).into_owned()),
+        }
+    }
+}
+
+/// Writes every name in camelCase, e.g. `cpu_usage` as `cpuUsage`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CamelCase;
+
+impl Naming for CamelCase {
+    fn measurement<'a>(&self, measurement: &'a str) -> Cow<'a, str> {
+        camel_case(measurement)
+    }
+
+    fn tag_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        camel_case(key)
+    }
+
+    fn field_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        camel_case(key)
+    }
+}
+
+/// Writes every name in snake_case, e.g. `cpuUsage` or `HTTPStatus` as
+/// `cpu_usage` and `http_status`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnakeCase;
+
+impl Naming for SnakeCase {
+    fn measurement<'a>(&self, measurement: &'a str) -> Cow<'a, str> {
+        snake_case(measurement)
+    }
+
+    fn tag_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        snake_case(key)
+    }
+
+    fn field_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        snake_case(key)
+    }
+}
+
+/// Prefixes every measurement, e.g. with the name of the service writing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MeasurementPrefix(String);
+
+impl MeasurementPrefix {
+    /// Prefix measurements with `prefix`.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self(prefix.into())
+    }
+}
+
+impl Naming for MeasurementPrefix {
+    fn measurement<'a>(&self, measurement: &'a str) -> Cow<'a, str> {
+        Cow::Owned(format!("{}{}", self.0, measurement))
+    }
+}
+
+/// `name` with the letter after each inner `_` uppercased and the `_`
+/// removed, keeping leading and trailing underscores
+fn camel_case(name: &str) -> Cow<'_, str> {
+    let inner = name.trim_matches('_');
+    if !inner.contains('_') {
+        return Cow::Borrowed(name);
+    }
+
+    let start = name.len() - name.trim_start_matches('_').len();
+    let mut camel = String::with_capacity(name.len());
+    camel.push_str(&name[..start]);
+    let mut upper = false;
+    for c in inner.chars() {
+        match c {
+            '_' => upper = !camel[start..].is_empty(),
+            _ if upper => {
+                camel.extend(c.to_uppercase());
+                upper = false;
+            }
+            _ => camel.push(c),
+        }
+    }
+    camel.push_str(&name[start + inner.len()..]);
+    Cow::Owned(camel)
+}
+
+/// `name` lowercased, with a `_` where a word starts with an uppercase letter
+fn snake_case(name: &str) -> Cow<'_, str> {
+    if !name.chars().any(char::is_uppercase) {
+        return Cow::Borrowed(name);
+    }
+
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            let starts_word = previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower);
+            if starts_word && !snake.ends_with('_') {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    Cow::Owned(snake)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DataPoint, WriteDataPoint};
+    use std::io;
+
+    #[test]
+    fn converts_cases() {
+        assert_eq!(camel_case("cpu_usage_idle"), "cpuUsageIdle");
+        assert_eq!(camel_case("_start"), "_start");
+        assert_eq!(camel_case("__a__b_"), "__aB_");
+        assert_eq!(snake_case("cpuUsageIdle"), "cpu_usage_idle");
+        assert_eq!(snake_case("HTTPStatus"), "http_status");
+        assert_eq!(snake_case("ioWait2Ms"), "io_wait2_ms");
+        assert_eq!(snake_case("already_snake"), "already_snake");
+    }
+
+    #[test]
+    fn renames_points() {
+        let point = DataPoint::builder("cpu_load")
+            .tag("host_name", "a b")
+            .field("load_avg", 1.5)
+            .timestamp(7)
+            .build()
+            .unwrap();
+        let naming = (CamelCase, MeasurementPrefix::new("billing_"));
+
+        let mut line = Vec::new();
+        point.write_renamed_data_point_to(&naming, &mut line).unwrap();
+
+        assert_eq!(
+            String::from_utf8(line).unwrap(),
+            "billing_cpuLoad,hostName=a\\ b loadAvg=1.5 7\n"
+        );
+    }
+
+    #[test]
+    fn renames_other_points_through_their_line_protocol() {
+        struct Point;
+
+        impl WriteDataPoint for Point {
+            fn write_data_point_to<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+                w.write_all(b"cpu_load z_a=1i,zA=2i\n")
+            }
+        }
+
+        let mut line = Vec::new();
+        Point.write_renamed_data_point_to(&CamelCase, &mut line).unwrap();
+
+        assert_eq!(String::from_utf8(line).unwrap(), "cpuLoad zA=1i\n");
+    }
+}
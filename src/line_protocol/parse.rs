@@ -0,0 +1,361 @@
+//! Line protocol parsing
+//!
+//! Reads line protocol text back into `DataPoint`s, undoing the escaping of
+//! `WriteDataPoint`, so points can be inspected, modified and written again.
+//! Blank lines and comments, lines starting with `#` and a space, are
+//! skipped; `#cpu usage=1` is a point of the `#cpu` measurement.
+//!
+//! ```
+//! use influxdb2::line_protocol::{parse, Precision};
+//! use influxdb2::models::FieldValue;
+//!
+//! let points = parse("cpu,host=server\\ 01 usage=0.5,cores=4i 1556813561", Precision::Seconds)
+//!     .unwrap();
+//! assert_eq!(points[0].tags()["host"], "server 01");
+//! assert_eq!(points[0].fields()["cores"], FieldValue::I64(4));
+//! assert_eq!(points[0].timestamp(), Some(1_556_813_561_000_000_000));
+//! ```
+
+use snafu::{ensure, OptionExt, Snafu};
+
+use super::Precision;
+use crate::models::{DataPoint, FieldValue};
+
+/// Errors that occur while parsing line protocol
+#[derive(Debug, Snafu)]
+pub enum ParseError {
+    /// A line without a measurement.
+    #[snafu(display("Missing measurement on line {}", line))]
+    MissingMeasurement {
+        /// The line number, starting at 1
+        line: usize,
+    },
+
+    /// A tag that isn't `key=value`.
+    #[snafu(display("Invalid tag on line {}: {}", line, text))]
+    InvalidTag {
+        /// The line number, starting at 1
+        line: usize,
+        /// The tag
+        text: String,
+    },
+
+    /// A line without fields.
+    #[snafu(display("Missing fields on line {}", line))]
+    MissingFields {
+        /// The line number, starting at 1
+        line: usize,
+    },
+
+    /// A field that isn't `key=value`, or whose value has no valid type.
+    #[snafu(display("Invalid field on line {}: {}", line, text))]
+    InvalidField {
+        /// The line number, starting at 1
+        line: usize,
+        /// The field
+        text: String,
+    },
+
+    /// A timestamp that isn't an integer, or overflows in nanoseconds.
+    #[snafu(display("Invalid timestamp on line {}: {}", line, text))]
+    InvalidTimestamp {
+        /// The line number, starting at 1
+        line: usize,
+        /// The timestamp
+        text: String,
+    },
+
+    /// Text after the timestamp.
+    #[snafu(display("Unexpected text after the timestamp on line {}", line))]
+    TrailingText {
+        /// The line number, starting at 1
+        line: usize,
+    },
+}
+
+const MEASUREMENT_DELIMITERS: &[char] = &[',', ' '];
+const KEY_DELIMITERS: &[char] = &[',', '=', ' '];
+const STRING_DELIMITERS: &[char] = &['"', '\\'];
+
+/// Parse `text` into points, converting timestamps from `precision` to
+/// nanoseconds.
+pub fn parse(text: &str, precision: Precision) -> Result<Vec<DataPoint>, ParseError> {
+    let mut points = Vec::new();
+    for (number, line) in lines(text) {
+        let line = line.trim_start();
+        if line.trim_end().is_empty() || is_comment(line) {
+            continue;
+        }
+        points.push(parse_line(line, number, precision)?);
+    }
+    Ok(points)
+}
+
+/// Whether `line`, without its leading whitespace, is a comment
+fn is_comment(line: &str) -> bool {
+    line.strip_prefix('#')
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// The lines of `text` with their numbers, keeping newlines inside quoted
+/// string field values
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut rest = text;
+    let mut number = 1;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        // Quotes only start a string right after the `=` of a field, so
+        // quotes in measurements, tags and comments don't join lines
+        let start = rest.len() - rest.trim_start_matches(&[' ', '\t'][..]).len();
+        let comment = is_comment(&rest[start..]);
+        let (mut fields, mut quoted, mut escaped, mut after_equals) = (false, false, false, false);
+        let end = rest[start..]
+            .char_indices()
+            .find(|&(_, c)| {
+                let unescaped = !escaped;
+                match c {
+                    '\n' if !quoted => return true,
+                    _ if comment => {}
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    ' ' if !quoted => fields = true,
+                    '"' if quoted => quoted = false,
+                    '"' if fields && after_equals => quoted = true,
+                    _ => {}
+                }
+                after_equals = c == '=' && unescaped && !quoted;
+                false
+            })
+            .map_or(rest.len(), |(i, _)| start + i);
+
+        let line = rest[..end].strip_suffix('\r').unwrap_or(&rest[..end]);
+        let line_number = number;
+        number += 1 + line.matches('\n').count();
+        rest = rest.get(end + 1..).unwrap_or("");
+        Some((line_number, line))
+    })
+}
+
+fn parse_line(line: &str, number: usize, precision: Precision) -> Result<DataPoint, ParseError> {
+    let (measurement, mut rest) = unescape_until(line, MEASUREMENT_DELIMITERS);
+    ensure!(
+        !measurement.is_empty(),
+        MissingMeasurementSnafu { line: number }
+    );
+    let mut point = DataPoint::builder(measurement);
+
+    while let Some(tag) = rest.strip_prefix(',') {
+        let (key, after_key) = unescape_until(tag, KEY_DELIMITERS);
+        let after_equals = after_key
+            .strip_prefix('=')
+            .filter(|_| !key.is_empty())
+            .context(InvalidTagSnafu {
+                line: number,
+                text: tag,
+            })?;
+        let (value, after_value) = unescape_until(after_equals, KEY_DELIMITERS);
+        ensure!(
+            !value.is_empty(),
+            InvalidTagSnafu {
+                line: number,
+                text: tag,
+            }
+        );
+        point = point.tag(key, value);
+        rest = after_value;
+    }
+
+    rest = rest
+        .strip_prefix(' ')
+        .map(str::trim_start)
+        .filter(|fields| !fields.is_empty())
+        .context(MissingFieldsSnafu { line: number })?;
+    loop {
+        let invalid = InvalidFieldSnafu {
+            line: number,
+            text: rest,
+        };
+        let (key, after_key) = unescape_until(rest, KEY_DELIMITERS);
+        let after_equals = after_key
+            .strip_prefix('=')
+            .filter(|_| !key.is_empty())
+            .context(invalid)?;
+        let (value, after_value) = field_value(after_equals).context(invalid)?;
+        point = point.field(key, value);
+        rest = after_value;
+        match rest.strip_prefix(',') {
+            Some(next) => rest = next,
+            None => break,
+        }
+    }
+
+    let mut parts = rest.split_whitespace();
+    if let Some(timestamp) = parts.next() {
+        let nanos = timestamp
+            .parse::<i64>()
+            .ok()
+            .and_then(|timestamp| precision.to_nanos(timestamp))
+            .context(InvalidTimestampSnafu {
+                line: number,
+                text: timestamp,
+            })?;
+        point = point.timestamp(nanos);
+    }
+    ensure!(parts.next().is_none(), TrailingTextSnafu { line: number });
+
+    Ok(point.build().expect("a field was parsed"))
+}
+
+/// Read up to the first unescaped delimiter, unescaping `\` followed by a
+/// delimiter
+fn unescape_until<'a>(text: &'a str, delimiters: &[char]) -> (String, &'a str) {
+    let mut value = String::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, next)) if delimiters.contains(&next) => {
+                    value.push(next);
+                    chars.next();
+                }
+                _ => value.push('\\'),
+            },
+            _ if delimiters.contains(&c) => return (value, &text[i..]),
+            _ => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+/// Read a field value, returning what follows it
+fn field_value(text: &str) -> Option<(FieldValue, &str)> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let (value, rest) = unescape_until(quoted, STRING_DELIMITERS);
+        let rest = rest.strip_prefix('"')?;
+        return Some((FieldValue::String(value), rest));
+    }
+
+    let end = text.find(&[',', ' '][..]).unwrap_or(text.len());
+    let (raw, rest) = text.split_at(end);
+    let value = match raw {
+        "t" | "T" | "true" | "True" | "TRUE" => FieldValue::Bool(true),
+        "f" | "F" | "false" | "False" | "FALSE" => FieldValue::Bool(false),
+        _ => {
+            if let Some(integer) = raw.strip_suffix('i') {
+                FieldValue::I64(integer.parse().ok()?)
+            } else if let Some(unsigned) = raw.strip_suffix('u') {
+                FieldValue::U64(unsigned.parse().ok()?)
+            } else {
+                let float: f64 = raw.parse().ok()?;
+                if !float.is_finite() {
+                    return None;
+                }
+                FieldValue::F64(float)
+            }
+        }
+    };
+    Some((value, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WriteDataPoint;
+
+    #[test]
+    fn round_trips_escaping() {
+        let point = DataPoint::builder("m e,a")
+            .tag("t k=", "v, a=l")
+            .field("f k", "say \"hi\"\non two lines")
+            .field("n", 1.5)
+            .field("i", -3_i64)
+            .field("b", true)
+            .timestamp(-1)
+            .build()
+            .unwrap();
+        let mut line = Vec::new();
+        point.write_data_point_to(&mut line).unwrap();
+        let line = String::from_utf8(line).unwrap();
+
+        assert_eq!(parse(&line, Precision::Nanoseconds).unwrap(), vec![point]);
+    }
+
+    #[test]
+    fn parses_every_value_type() {
+        let text = "# a comment\n\
+                    \n\
+                    m a=1,b=2i,c=3u,d=t,e=FALSE,f=\"x\",g=-1e3 10\r\n\
+                    m,t=v x=1";
+
+        let points = parse(text, Precision::Milliseconds).unwrap();
+
+        assert_eq!(points.len(), 2);
+        let fields = points[0].fields();
+        assert_eq!(fields["a"], FieldValue::F64(1.0));
+        assert_eq!(fields["b"], FieldValue::I64(2));
+        assert_eq!(fields["c"], FieldValue::U64(3));
+        assert_eq!(fields["d"], FieldValue::Bool(true));
+        assert_eq!(fields["e"], FieldValue::Bool(false));
+        assert_eq!(fields["f"], FieldValue::String("x".into()));
+        assert_eq!(fields["g"], FieldValue::F64(-1000.0));
+        assert_eq!(points[0].timestamp(), Some(10_000_000));
+        assert_eq!(points[1].tags()["t"], "v");
+        assert_eq!(points[1].timestamp(), None);
+    }
+
+    #[test]
+    fn parses_unsigned_integers() {
+        let points = parse("m a=18446744073709551615u", Precision::Nanoseconds).unwrap();
+        assert_eq!(points[0].fields()["a"], FieldValue::U64(u64::MAX));
+
+        assert!(matches!(
+            parse("m a=18446744073709551616u", Precision::Nanoseconds),
+            Err(ParseError::InvalidField { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn splits_lines_on_quotes_outside_string_fields() {
+        let text = "# a \"quoted comment\n\
+                    #m,t=a\"b x=1,f\"=\"a\nb\"\n\
+                    m x=2";
+
+        let points = parse(text, Precision::Nanoseconds).unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].measurement(), "#m");
+        assert_eq!(points[0].tags()["t"], "a\"b");
+        assert_eq!(points[0].fields()["f\""], FieldValue::String("a\nb".into()));
+        assert_eq!(points[1].fields()["x"], FieldValue::F64(2.0));
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let parse = |text| parse(text, Precision::Nanoseconds).unwrap_err();
+
+        assert!(matches!(
+            parse("m a=1\n,t=v a=1"),
+            ParseError::MissingMeasurement { line: 2 }
+        ));
+        assert!(matches!(
+            parse("m,t a=1"),
+            ParseError::InvalidTag { line: 1, .. }
+        ));
+        assert!(matches!(parse("m"), ParseError::MissingFields { line: 1 }));
+        assert!(matches!(
+            parse("m s=\"a\nb\" 1\nm a=x"),
+            ParseError::InvalidField { line: 3, .. }
+        ));
+        assert!(matches!(
+            parse("m a=1 soon"),
+            ParseError::InvalidTimestamp { line: 1, .. }
+        ));
+        assert!(matches!(
+            parse("m a=1 1 2"),
+            ParseError::TrailingText { line: 1 }
+        ));
+    }
+}
@@ -0,0 +1,755 @@
+//! serde serialization into line protocol
+//!
+//! A dynamic alternative to deriving: any `Serialize` struct or map becomes a
+//! point, given a [`Mapping`] that names its measurement and says which of
+//! its members are tags, fields and the timestamp.
+//!
+//! ```
+//! use influxdb2::line_protocol::{to_line_protocol, Mapping};
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Reading {
+//!     sensor: String,
+//!     celsius: f64,
+//!     time: i64,
+//! }
+//!
+//! let mapping = Mapping::new("temperature").tag("sensor").timestamp("time");
+//! let reading = Reading { sensor: "kitchen".into(), celsius: 21.5, time: 1 };
+//! assert_eq!(
+//!     to_line_protocol(&reading, &mapping).unwrap(),
+//!     "temperature,sensor=kitchen celsius=21.5 1\n"
+//! );
+//! ```
+
+use serde::ser::{self, Impossible, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+use crate::models::data_point::{DataPointBuilder, DataPointError};
+use crate::models::{DataPoint, FieldValue, WriteDataPoint};
+
+/// Errors that occur while serializing into line protocol
+#[derive(Debug, Snafu)]
+pub enum SerializeError {
+    /// A custom error from a `Serialize` implementation.
+    #[snafu(display("{}", message))]
+    Custom {
+        /// The message of the implementation
+        message: String,
+    },
+
+    /// The value isn't a struct or a map.
+    #[snafu(display("Only structs and maps can be serialized as points"))]
+    NotAStruct,
+
+    /// A map key isn't a string.
+    #[snafu(display("Map keys must be strings"))]
+    InvalidKey,
+
+    /// A member has a type line protocol can't carry, such as a sequence or a
+    /// nested struct.
+    #[snafu(display("Unsupported {} value for `{}`", kind, name))]
+    Unsupported {
+        /// The member
+        name: String,
+        /// The kind of value
+        kind: &'static str,
+    },
+
+    /// An unsigned integer is too large for the signed integer field it is
+    /// written as; it isn't turned into a float, whose field type would
+    /// depend on the magnitude of the value.
+    #[snafu(display("Value {} of `{}` is out of range of an integer field", value, name))]
+    OutOfRange {
+        /// The member
+        name: String,
+        /// The value
+        value: u64,
+    },
+
+    /// The timestamp member isn't an integer or an RFC 3339 date.
+    #[snafu(display("Invalid timestamp in `{}`", name))]
+    InvalidTimestamp {
+        /// The member
+        name: String,
+    },
+
+    /// The value has no fields.
+    #[snafu(display("Unable to build the point: {}", source))]
+    Building {
+        /// The builder error
+        source: DataPointError,
+    },
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom {
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// How the members of a value map onto a point
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mapping {
+    measurement: String,
+    tags: Vec<String>,
+    fields: Option<Vec<String>>,
+    timestamp: Option<String>,
+}
+
+impl Mapping {
+    /// Map values onto points of `measurement`, with every member as a field.
+    pub fn new(measurement: impl Into<String>) -> Self {
+        Self {
+            measurement: measurement.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Write member `name` as a tag.
+    pub fn tag(mut self, name: impl Into<String>) -> Self {
+        self.tags.push(name.into());
+        self
+    }
+
+    /// Write member `name` as a field. Once a field is named, members that
+    /// aren't named as tags, fields or the timestamp are skipped.
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.fields.get_or_insert_with(Vec::new).push(name.into());
+        self
+    }
+
+    /// Take the timestamp from member `name`, either an integer in the
+    /// precision of the write or an RFC 3339 date, written in nanoseconds.
+    pub fn timestamp(mut self, name: impl Into<String>) -> Self {
+        self.timestamp = Some(name.into());
+        self
+    }
+
+    fn role(&self, name: &str) -> Option<Role> {
+        if self.timestamp.as_deref() == Some(name) {
+            Some(Role::Timestamp)
+        } else if self.tags.iter().any(|tag| tag == name) {
+            Some(Role::Tag)
+        } else {
+            match &self.fields {
+                Some(fields) if !fields.iter().any(|field| field == name) => None,
+                _ => Some(Role::Field),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    Tag,
+    Field,
+    Timestamp,
+}
+
+/// Serialize `value` into a point according to `mapping`.
+///
+/// `None` members are skipped, like missing ones.
+pub fn to_data_point<T>(value: &T, mapping: &Mapping) -> Result<DataPoint, SerializeError>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = PointSerializer {
+        mapping,
+        point: Some(DataPoint::builder(mapping.measurement.as_str())),
+        key: None,
+    };
+    value.serialize(&mut serializer)?;
+    serializer
+        .point
+        .expect("the point is only taken while serializing a member")
+        .build()
+        .context(BuildingSnafu)
+}
+
+/// Serialize `value` into a line of line protocol according to `mapping`.
+pub fn to_line_protocol<T>(value: &T, mapping: &Mapping) -> Result<String, SerializeError>
+where
+    T: Serialize + ?Sized,
+{
+    let point = to_data_point(value, mapping)?;
+    let mut line = Vec::new();
+    point
+        .write_data_point_to(&mut line)
+        .expect("writing to a Vec never fails");
+    Ok(String::from_utf8(line).expect("line protocol of strings is UTF-8"))
+}
+
+/// Serializes the top-level struct or map, routing its members
+#[derive(Debug)]
+struct PointSerializer<'a> {
+    mapping: &'a Mapping,
+    point: Option<DataPointBuilder>,
+    /// The key of the map entry being serialized
+    key: Option<String>,
+}
+
+impl PointSerializer<'_> {
+    fn member<T>(&mut self, name: &str, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let role = match self.mapping.role(name) {
+            Some(role) => role,
+            None => return Ok(()),
+        };
+        let value = match value.serialize(ValueSerializer { name })? {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let point = self
+            .point
+            .take()
+            .expect("members are serialized one at a time");
+        self.point = Some(match role {
+            Role::Tag => point.tag(name, tag_value(value)),
+            Role::Field => point.field(name, value),
+            Role::Timestamp => point.timestamp(timestamp(name, value)?),
+        });
+        Ok(())
+    }
+}
+
+fn tag_value(value: FieldValue) -> String {
+    match value {
+        FieldValue::Bool(v) => v.to_string(),
+        FieldValue::F64(v) => v.to_string(),
+        FieldValue::I64(v) => v.to_string(),
+        FieldValue::U64(v) => v.to_string(),
+        FieldValue::String(v) => v,
+    }
+}
+
+fn timestamp(name: &str, value: FieldValue) -> Result<i64, SerializeError> {
+    match value {
+        FieldValue::I64(v) => Ok(v),
+        FieldValue::String(v) => chrono::DateTime::parse_from_rfc3339(&v)
+            .map(|date| date.timestamp_nanos())
+            .map_err(|_| SerializeError::InvalidTimestamp { name: name.into() }),
+        _ => InvalidTimestampSnafu { name }.fail(),
+    }
+}
+
+fn not_a_struct<T>() -> Result<T, SerializeError> {
+    NotAStructSnafu.fail()
+}
+
+impl<'a, 'b> ser::Serializer for &'a mut PointSerializer<'b> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<(), SerializeError>;
+    type SerializeTuple = Impossible<(), SerializeError>;
+    type SerializeTupleStruct = Impossible<(), SerializeError>;
+    type SerializeTupleVariant = Impossible<(), SerializeError>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), SerializeError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_char(self, _v: char) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        not_a_struct()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        not_a_struct()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, SerializeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerializeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        not_a_struct()
+    }
+}
+
+impl ser::SerializeStruct for &mut PointSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.member(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut PointSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        match key.serialize(ValueSerializer { name: "" }) {
+            Ok(Some(FieldValue::String(key))) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => InvalidKeySnafu.fail(),
+        }
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self.key.take().expect("serde calls serialize_key first");
+        self.member(&key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+/// Serializes a member into a field value, `None` if it is absent
+#[derive(Debug)]
+struct ValueSerializer<'a> {
+    name: &'a str,
+}
+
+impl ValueSerializer<'_> {
+    fn unsupported<T>(&self, kind: &'static str) -> Result<T, SerializeError> {
+        UnsupportedSnafu {
+            name: self.name,
+            kind,
+        }
+        .fail()
+    }
+}
+
+type ValueResult = Result<Option<FieldValue>, SerializeError>;
+
+impl ser::Serializer for ValueSerializer<'_> {
+    type Ok = Option<FieldValue>;
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<Option<FieldValue>, SerializeError>;
+    type SerializeTuple = Impossible<Option<FieldValue>, SerializeError>;
+    type SerializeTupleStruct = Impossible<Option<FieldValue>, SerializeError>;
+    type SerializeTupleVariant = Impossible<Option<FieldValue>, SerializeError>;
+    type SerializeMap = Impossible<Option<FieldValue>, SerializeError>;
+    type SerializeStruct = Impossible<Option<FieldValue>, SerializeError>;
+    type SerializeStructVariant = Impossible<Option<FieldValue>, SerializeError>;
+
+    fn serialize_bool(self, v: bool) -> ValueResult {
+        Ok(Some(v.into()))
+    }
+
+    fn serialize_i8(self, v: i8) -> ValueResult {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> ValueResult {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> ValueResult {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> ValueResult {
+        Ok(Some(v.into()))
+    }
+
+    fn serialize_u8(self, v: u8) -> ValueResult {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> ValueResult {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> ValueResult {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> ValueResult {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => OutOfRangeSnafu {
+                name: self.name,
+                value: v,
+            }
+            .fail(),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> ValueResult {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> ValueResult {
+        Ok(Some(v.into()))
+    }
+
+    fn serialize_char(self, v: char) -> ValueResult {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> ValueResult {
+        Ok(Some(v.into()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> ValueResult {
+        self.unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> ValueResult {
+        Ok(None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> ValueResult
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ValueResult {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> ValueResult {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> ValueResult {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> ValueResult
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> ValueResult
+    where
+        T: Serialize + ?Sized,
+    {
+        self.unsupported("enum")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        self.unsupported("sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        self.unsupported("tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        self.unsupported("tuple")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        self.unsupported("enum")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        self.unsupported("map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        self.unsupported("struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        self.unsupported("enum")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Unit {
+        Celsius,
+    }
+
+    #[derive(Serialize)]
+    struct Reading {
+        sensor: &'static str,
+        room: Option<&'static str>,
+        value: f64,
+        count: u32,
+        unit: Unit,
+        ok: bool,
+        time: &'static str,
+    }
+
+    #[test]
+    fn serializes_structs() {
+        let reading = Reading {
+            sensor: "a b",
+            room: None,
+            value: 21.5,
+            count: 3,
+            unit: Unit::Celsius,
+            ok: true,
+            time: "1970-01-01T00:00:01Z",
+        };
+        let mapping = Mapping::new("temperature")
+            .tag("sensor")
+            .tag("room")
+            .timestamp("time");
+
+        assert_eq!(
+            to_line_protocol(&reading, &mapping).unwrap(),
+            "temperature,sensor=a\\ b count=3i,ok=t,unit=\"celsius\",value=21.5 1000000000\n"
+        );
+
+        let only_value = mapping.field("value");
+        assert_eq!(
+            to_line_protocol(&reading, &only_value).unwrap(),
+            "temperature,sensor=a\\ b value=21.5 1000000000\n"
+        );
+    }
+
+    #[test]
+    fn serializes_maps() {
+        let mut values = BTreeMap::new();
+        values.insert("host", "server01");
+        values.insert("status", "up");
+
+        let mapping = Mapping::new("hosts").tag("host");
+
+        assert_eq!(
+            to_line_protocol(&values, &mapping).unwrap(),
+            "hosts,host=server01 status=\"up\"\n"
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_values() {
+        #[derive(Serialize)]
+        struct Nested {
+            values: Vec<i64>,
+        }
+
+        let mapping = Mapping::new("m");
+
+        assert!(matches!(
+            to_line_protocol(&Nested { values: vec![1] }, &mapping),
+            Err(SerializeError::Unsupported {
+                kind: "sequence",
+                ..
+            })
+        ));
+        assert!(matches!(
+            to_line_protocol(&1, &mapping),
+            Err(SerializeError::NotAStruct)
+        ));
+        assert!(matches!(
+            to_line_protocol(&BTreeMap::<&str, i64>::new(), &mapping),
+            Err(SerializeError::Building { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_unsigned_integers_out_of_range() {
+        let mut values = BTreeMap::new();
+        values.insert("bytes", i64::MAX as u64);
+        let mapping = Mapping::new("m");
+        assert_eq!(
+            to_line_protocol(&values, &mapping).unwrap(),
+            format!("m bytes={}i\n", i64::MAX)
+        );
+
+        values.insert("bytes", i64::MAX as u64 + 1);
+        assert!(matches!(
+            to_line_protocol(&values, &mapping),
+            Err(SerializeError::OutOfRange { name, value })
+                if name == "bytes" && value == i64::MAX as u64 + 1
+        ));
+    }
+}
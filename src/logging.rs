@@ -0,0 +1,319 @@
+//! Request logging
+//!
+//! Opt-in logging of every request and response, enabled with
+//! `ClientBuilder::with_request_logging`. Credentials never reach the log:
+//! the `Authorization` header, token-like query parameters and the values of
+//! secret JSON keys in bodies, such as the password of onboarding or the
+//! token of a new authorization, are redacted, and bodies are truncated.
+//! Only the logged part of a response body is buffered; the rest is streamed
+//! to the caller as it arrives.
+
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use std::fmt::Write;
+
+/// Bodies longer than this many bytes are truncated in the log.
+const MAX_LOGGED_BODY: usize = 1024;
+
+/// Query parameters that may carry credentials.
+const SECRET_PARAMS: &[&str] = &["token", "authorization", "password", "p"];
+
+/// JSON keys whose values are credentials, e.g. in onboarding requests and
+/// in the authorizations the server returns.
+const SECRET_KEYS: &[&str] = &["password", "token", "secret"];
+
+const REDACTED: &str = "REDACTED";
+
+pub(crate) fn log_request(request: &reqwest::Request) {
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(|body| truncate_body(body, body.len()));
+
+    tracing::info!(
+        target: "influxdb2::requests",
+        method = %request.method(),
+        url = %redact_url(request.url()),
+        headers = %redact_headers(request.headers()),
+        body = body.as_deref().unwrap_or("<streamed>"),
+        "sending request"
+    );
+}
+
+/// Log `response`, buffering the logged part of its body so the body can
+/// still be read afterwards
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn log_response(
+    mut response: reqwest::Response,
+    elapsed_ms: Option<u128>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    use futures::{stream, StreamExt};
+
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let length = response.content_length();
+
+    let mut head = bytes::BytesMut::new();
+    let mut complete = false;
+    while head.len() <= MAX_LOGGED_BODY {
+        match response.chunk().await? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => {
+                complete = true;
+                break;
+            }
+        }
+    }
+    let head = head.freeze();
+
+    let total = match (complete, length) {
+        (true, _) => head.len(),
+        (false, Some(length)) => std::convert::TryFrom::try_from(length).unwrap_or(usize::MAX),
+        (false, None) => usize::MAX,
+    };
+    let logged = truncate_body(&head, total);
+    log_response_parts(status, &headers, elapsed_ms, Some(&logged));
+
+    let body = if complete {
+        reqwest::Body::from(head)
+    } else {
+        let rest = response.bytes_stream();
+        reqwest::Body::wrap_stream(stream::once(async { Ok(head) }).chain(rest))
+    };
+    let mut buffered = http::Response::new(body);
+    *buffered.status_mut() = status;
+    *buffered.version_mut() = version;
+    *buffered.headers_mut() = headers;
+    Ok(buffered.into())
+}
+
+/// Log `response`; its body can't be rebuilt once read on wasm, so it isn't
+/// logged there
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn log_response(
+    response: reqwest::Response,
+    elapsed_ms: Option<u128>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    log_response_parts(response.status(), response.headers(), elapsed_ms, None);
+    Ok(response)
+}
+
+fn log_response_parts(
+    status: reqwest::StatusCode,
+    headers: &HeaderMap,
+    elapsed_ms: Option<u128>,
+    body: Option<&str>,
+) {
+    tracing::info!(
+        target: "influxdb2::requests",
+        status = status.as_u16(),
+        duration_ms = elapsed_ms.map(|ms| ms as u64),
+        headers = %redact_headers(headers),
+        body = body.unwrap_or("<not buffered>"),
+        "received response"
+    );
+}
+
+/// Render `url` with the values of credential-carrying query parameters
+/// replaced
+pub(crate) fn redact_url(url: &url::Url) -> url::Url {
+    if !url.query_pairs().any(|(name, _)| is_secret(&name)) {
+        return url.clone();
+    }
+
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if is_secret(&name) {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+
+    let mut redacted = url.clone();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted
+}
+
+fn is_secret(name: &str) -> bool {
+    SECRET_PARAMS.iter().any(|secret| name.eq_ignore_ascii_case(secret))
+}
+
+fn redact_headers(headers: &HeaderMap) -> String {
+    let mut out = String::new();
+    for (name, value) in headers {
+        if !out.is_empty() {
+            out.push_str(", ");
+        }
+        let value = if name == AUTHORIZATION {
+            REDACTED
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+        let _ = write!(out, "{}: {}", name, value);
+    }
+    out
+}
+
+/// Render the first bytes of a body of `total` bytes, `usize::MAX` when
+/// unknown, with the values of secret JSON keys redacted
+fn truncate_body(body: &[u8], total: usize) -> String {
+    let shown = &body[..body.len().min(MAX_LOGGED_BODY)];
+    let text = redact_json_secrets(&String::from_utf8_lossy(shown));
+    match total {
+        total if total <= MAX_LOGGED_BODY && shown.len() == body.len() => text,
+        usize::MAX => format!("{}... (truncated)", text),
+        total => format!("{}... ({} bytes)", text, total),
+    }
+}
+
+/// Replace the string values of the [`SECRET_KEYS`] in `text`, which may be
+/// cut off, so it is scanned rather than parsed
+pub(crate) fn redact_json_secrets(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('"') {
+        out.push_str(&rest[..start]);
+        let (string, after) = split_string(&rest[start..]);
+        out.push_str(string);
+        rest = after;
+
+        let key = string.trim_matches('"');
+        if !SECRET_KEYS.iter().any(|secret| key.eq_ignore_ascii_case(secret)) {
+            continue;
+        }
+        let value = rest.trim_start();
+        let value = match value.strip_prefix(':') {
+            Some(value) => value.trim_start(),
+            None => continue,
+        };
+        if value.starts_with('"') {
+            out.push_str(&rest[..rest.len() - value.len()]);
+            out.push('"');
+            out.push_str(REDACTED);
+            out.push('"');
+            rest = split_string(value).1;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Split `text`, which starts with a `"`, after the end of the JSON string
+/// it starts with, or at its end if the string is cut off
+fn split_string(text: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return text.split_at(i + 1),
+            _ => {}
+        }
+    }
+    (text, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn redacts_token_query_params() {
+        let url = url::Url::parse("http://localhost:8086/api/v2/write?org=o&Token=secret&p=pw")
+            .unwrap();
+
+        assert_eq!(
+            redact_url(&url).as_str(),
+            "http://localhost:8086/api/v2/write?org=o&Token=REDACTED&p=REDACTED"
+        );
+    }
+
+    #[test]
+    fn redacts_authorization_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Token secret"));
+        headers.insert("x-tenant", HeaderValue::from_static("acme"));
+
+        let rendered = redact_headers(&headers);
+
+        assert!(!rendered.contains("secret"));
+        assert!(rendered.contains("authorization: REDACTED"));
+        assert!(rendered.contains("x-tenant: acme"));
+    }
+
+    #[test]
+    fn truncates_long_bodies() {
+        let body = vec![b'a'; MAX_LOGGED_BODY + 10];
+
+        let logged = truncate_body(&body, body.len());
+
+        assert!(logged.ends_with(&format!("... ({} bytes)", MAX_LOGGED_BODY + 10)));
+        assert!(truncate_body(&body[..MAX_LOGGED_BODY + 1], usize::MAX).ends_with("(truncated)"));
+    }
+
+    #[test]
+    fn redacts_secret_json_keys() {
+        let onboarding = r#"{"username":"admin", "password" : "hunter2","org":"o","token":"s3cr\"et"}"#;
+        assert_eq!(
+            redact_json_secrets(onboarding),
+            r#"{"username":"admin", "password" : "REDACTED","org":"o","token":"REDACTED"}"#
+        );
+
+        // Cut off by truncation in the middle of the secret
+        assert_eq!(
+            redact_json_secrets(r#"{"authorizations":[{"token":"abcdef"#),
+            r#"{"authorizations":[{"token":"REDACTED""#
+        );
+        assert_eq!(redact_json_secrets(r#"{"token":null}"#), r#"{"token":null}"#);
+    }
+
+    #[tokio::test]
+    async fn logs_responses_without_secrets() {
+        use mockito::mock;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let body = format!(
+            r#"{{"token":"secret-token","name":"influxdb","message":"{}","status":"pass"}}"#,
+            "a".repeat(2 * MAX_LOGGED_BODY)
+        );
+        let mock_server = mock("GET", "/health").with_body(&body).create();
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = crate::Client::builder(mockito::server_url(), "", "")
+            .with_request_logging(true)
+            .build()
+            .unwrap();
+        let health = client.health().await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(health.message.map(|m| m.len()), Some(2 * MAX_LOGGED_BODY));
+        let logged = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(logged.contains("received response"));
+        assert!(!logged.contains("secret-token"));
+    }
+}
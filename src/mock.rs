@@ -0,0 +1,507 @@
+//! Mock client
+//!
+//! An in-memory [`InfluxClient`] for unit tests of code built on this crate.
+//! [`MockClient`] records every call it receives and answers with responses
+//! queued up front, so no server (nor mockito) is needed.
+//!
+//! ```
+//! use influxdb2::api::client::InfluxClient;
+//! use influxdb2::mock::MockClient;
+//! use influxdb2::models::{HealthCheck, Status};
+//!
+//! async fn is_healthy(client: &dyn InfluxClient) -> bool {
+//!     matches!(client.health().await, Ok(h) if h.status == Status::Pass)
+//! }
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let mock = MockClient::new();
+//! mock.respond("health", Ok(HealthCheck::new("influxdb".into(), Status::Pass)));
+//!
+//! assert!(is_healthy(&mock).await);
+//! assert_eq!(mock.calls_to("health").len(), 1);
+//! # });
+//! ```
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use influxdb2_structmap::GenericMap;
+use parking_lot::Mutex;
+use reqwest::Body;
+
+#[cfg(feature = "management")]
+use crate::api::buckets::ListBucketsRequest;
+use crate::api::capabilities::Capabilities;
+use crate::api::client::{BoxFuture, InfluxClient};
+#[cfg(feature = "management")]
+use crate::api::organization::ListOrganizationRequest;
+#[cfg(feature = "management")]
+use crate::api::task::{CreateTaskRequest, ListTasksRequest};
+use crate::models::{
+    AnalyzeQueryResponse, AstResponse, DataPoint, FluxSuggestion, FluxSuggestions, HealthCheck,
+    LanguageRequest, Query, WriteDataPoint,
+};
+#[cfg(feature = "management")]
+use crate::models::{
+    BucketId, Buckets, FluxDuration, LabelId, LabelResponse, LabelsResponse, OnboardingResponse,
+    OrgId, Organizations, PostBucketRequest, TaskId, Tasks,
+};
+use crate::{RequestError, Target};
+
+/// A call received by a [`MockClient`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Call {
+    /// Name of the `InfluxClient` method that was called
+    pub method: &'static str,
+    /// Arguments of the call formatted with `Debug`, except for written data
+    /// which is recorded as line protocol text.
+    pub args: Vec<String>,
+}
+
+type Response = Box<dyn Any + Send>;
+
+/// An [`InfluxClient`] that records calls and returns canned responses.
+///
+/// Responses are queued per method name with [`respond`](Self::respond) and
+/// consumed in order. Calls to methods returning `()` succeed when nothing is
+/// queued; any other method panics, since there is no sensible value to
+/// return.
+#[derive(Default)]
+pub struct MockClient {
+    calls: Mutex<Vec<Call>>,
+    responses: Mutex<HashMap<&'static str, VecDeque<Response>>>,
+}
+
+impl fmt::Debug for MockClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockClient")
+            .field("calls", &*self.calls.lock())
+            .finish()
+    }
+}
+
+impl MockClient {
+    /// Create a mock client without any queued responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the response of the next call to `method`.
+    ///
+    /// The type of `response` must match the return type of `method`, e.g.
+    /// `Result<HealthCheck, RequestError>` for `health`. Use `GenericMap` rows
+    /// for both `query` and `query_raw`.
+    pub fn respond<T: Send + 'static>(
+        &self,
+        method: &'static str,
+        response: Result<T, RequestError>,
+    ) -> &Self {
+        self.responses
+            .lock()
+            .entry(method)
+            .or_default()
+            .push_back(Box::new(response));
+        self
+    }
+
+    /// All calls received so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().clone()
+    }
+
+    /// The calls received so far to `method`, in order.
+    pub fn calls_to(&self, method: &str) -> Vec<Call> {
+        self.calls
+            .lock()
+            .iter()
+            .filter(|call| call.method == method)
+            .cloned()
+            .collect()
+    }
+
+    /// Forget all recorded calls and queued responses.
+    pub fn reset(&self) {
+        self.calls.lock().clear();
+        self.responses.lock().clear();
+    }
+
+    fn record(&self, method: &'static str, args: Vec<String>) {
+        self.calls.lock().push(Call { method, args });
+    }
+
+    fn next_response<T: 'static>(&self, method: &'static str) -> Option<Result<T, RequestError>> {
+        let response = self.responses.lock().get_mut(method)?.pop_front()?;
+        match response.downcast::<Result<T, RequestError>>() {
+            Ok(response) => Some(*response),
+            Err(_) => panic!(
+                "MockClient: the response queued for `{}` has the wrong type, expected `Result<{}, RequestError>`",
+                method,
+                std::any::type_name::<T>(),
+            ),
+        }
+    }
+
+    fn answer<T: 'static>(&self, method: &'static str) -> Result<T, RequestError> {
+        self.next_response(method)
+            .unwrap_or_else(|| panic!("MockClient: no response queued for `{}`", method))
+    }
+
+    fn answer_unit(&self, method: &'static str) -> Result<(), RequestError> {
+        self.next_response(method).unwrap_or(Ok(()))
+    }
+
+    fn reply<T: Send + 'static>(
+        &self,
+        method: &'static str,
+        args: Vec<String>,
+    ) -> BoxFuture<'_, Result<T, RequestError>> {
+        self.record(method, args);
+        let response = self.answer(method);
+        Box::pin(async move { response })
+    }
+
+    fn reply_unit(
+        &self,
+        method: &'static str,
+        args: Vec<String>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.record(method, args);
+        let response = self.answer_unit(method);
+        Box::pin(async move { response })
+    }
+}
+
+macro_rules! args {
+    ($($arg:expr),* $(,)?) => {
+        vec![$(format!("{:?}", $arg)),*]
+    };
+}
+
+impl InfluxClient for MockClient {
+    #[cfg(feature = "management")]
+    fn list_buckets(
+        &self,
+        request: Option<ListBucketsRequest>,
+    ) -> BoxFuture<'_, Result<Buckets, RequestError>> {
+        self.reply("list_buckets", args![request])
+    }
+
+    #[cfg(feature = "management")]
+    fn create_bucket(
+        &self,
+        post_bucket_request: Option<PostBucketRequest>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.reply_unit("create_bucket", args![post_bucket_request])
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_bucket<'a>(&'a self, bucket_id: &'a BucketId) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.reply_unit("delete_bucket", args![bucket_id])
+    }
+
+    fn delete(
+        &self,
+        target: Target,
+        start: NaiveDateTime,
+        stop: NaiveDateTime,
+        predicate: Option<String>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.reply_unit("delete", args![target, start, stop, predicate])
+    }
+
+    fn capabilities(&self) -> BoxFuture<'_, Result<Capabilities, RequestError>> {
+        self.reply("capabilities", args![])
+    }
+
+    fn health(&self) -> BoxFuture<'_, Result<HealthCheck, RequestError>> {
+        self.reply("health", args![])
+    }
+
+    #[cfg(feature = "management")]
+    fn labels(&self) -> BoxFuture<'_, Result<LabelsResponse, RequestError>> {
+        self.reply("labels", args![])
+    }
+
+    #[cfg(feature = "management")]
+    fn labels_by_org<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+    ) -> BoxFuture<'a, Result<LabelsResponse, RequestError>> {
+        self.reply("labels_by_org", args![org_id])
+    }
+
+    #[cfg(feature = "management")]
+    fn find_label<'a>(
+        &'a self,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        self.reply("find_label", args![label_id])
+    }
+
+    #[cfg(feature = "management")]
+    fn create_label<'a>(
+        &'a self,
+        org_id: &'a OrgId,
+        name: &'a str,
+        properties: Option<HashMap<String, String>>,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        self.reply("create_label", args![org_id, name, properties])
+    }
+
+    #[cfg(feature = "management")]
+    fn update_label<'a>(
+        &'a self,
+        name: Option<String>,
+        properties: Option<HashMap<String, String>>,
+        label_id: &'a LabelId,
+    ) -> BoxFuture<'a, Result<LabelResponse, RequestError>> {
+        self.reply("update_label", args![name, properties, label_id])
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_label<'a>(&'a self, label_id: &'a LabelId) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.reply_unit("delete_label", args![label_id])
+    }
+
+    #[cfg(feature = "management")]
+    fn list_organizations(
+        &self,
+        request: ListOrganizationRequest,
+    ) -> BoxFuture<'_, Result<Organizations, RequestError>> {
+        self.reply("list_organizations", args![request])
+    }
+
+    fn query_suggestions(&self) -> BoxFuture<'_, Result<FluxSuggestions, RequestError>> {
+        self.reply("query_suggestions", args![])
+    }
+
+    fn query_suggestions_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<FluxSuggestion, RequestError>> {
+        self.reply("query_suggestions_name", args![name])
+    }
+
+    fn query_raw(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<Vec<GenericMap>, RequestError>> {
+        // `query` goes through `query_raw` as well, so both share one queue.
+        self.reply("query", args![query])
+    }
+
+    fn query_analyze(
+        &self,
+        query: Option<Query>,
+    ) -> BoxFuture<'_, Result<AnalyzeQueryResponse, RequestError>> {
+        self.reply("query_analyze", args![query])
+    }
+
+    fn query_ast(
+        &self,
+        language_request: Option<LanguageRequest>,
+    ) -> BoxFuture<'_, Result<AstResponse, RequestError>> {
+        self.reply("query_ast", args![language_request])
+    }
+
+    fn ready(&self) -> BoxFuture<'_, Result<bool, RequestError>> {
+        self.reply("ready", args![])
+    }
+
+    #[cfg(feature = "management")]
+    fn is_onboarding_allowed(&self) -> BoxFuture<'_, Result<bool, RequestError>> {
+        self.reply("is_onboarding_allowed", args![])
+    }
+
+    #[cfg(feature = "management")]
+    fn onboarding<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>> {
+        self.reply(
+            "onboarding",
+            args![
+                username,
+                org,
+                bucket,
+                password,
+                retention_period_hrs,
+                retention_period_seconds
+            ],
+        )
+    }
+
+    #[cfg(feature = "management")]
+    fn post_setup_user<'a>(
+        &'a self,
+        username: &'a str,
+        org: &'a str,
+        bucket: &'a str,
+        password: Option<String>,
+        retention_period_hrs: Option<i32>,
+        retention_period_seconds: Option<FluxDuration>,
+    ) -> BoxFuture<'a, Result<OnboardingResponse, RequestError>> {
+        self.reply(
+            "post_setup_user",
+            args![
+                username,
+                org,
+                bucket,
+                password,
+                retention_period_hrs,
+                retention_period_seconds
+            ],
+        )
+    }
+
+    #[cfg(feature = "management")]
+    fn list_tasks(&self, request: ListTasksRequest) -> BoxFuture<'_, Result<Tasks, RequestError>> {
+        self.reply("list_tasks", args![request])
+    }
+
+    #[cfg(feature = "management")]
+    fn create_task(&self, request: CreateTaskRequest) -> BoxFuture<'_, Result<(), RequestError>> {
+        self.reply_unit("create_task", args![request])
+    }
+
+    #[cfg(feature = "management")]
+    fn delete_task<'a>(&'a self, task_id: &'a TaskId) -> BoxFuture<'a, Result<(), RequestError>> {
+        self.reply_unit("delete_task", args![task_id])
+    }
+
+    fn write_line_protocol_to(
+        &self,
+        target: Target,
+        body: Body,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        let body = body
+            .as_bytes()
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_else(|| String::from("<streaming body>"));
+        let mut args = args![target];
+        args.push(body);
+        self.reply_unit("write_line_protocol_to", args)
+    }
+
+    fn write(
+        &self,
+        target: Target,
+        points: Vec<DataPoint>,
+    ) -> BoxFuture<'_, Result<(), RequestError>> {
+        let mut lines = Vec::new();
+        for point in &points {
+            point
+                .write_data_point_to(&mut lines)
+                .expect("writing to an in-memory buffer cannot fail");
+        }
+        let mut args = args![target];
+        args.push(String::from_utf8_lossy(&lines).into_owned());
+        self.reply_unit("write", args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Status;
+    use influxdb2_structmap::value::Value;
+
+    #[derive(Debug, Default, PartialEq, crate::FromDataPoint)]
+    struct Temperature {
+        room: String,
+        value: f64,
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn records_calls_and_returns_queued_responses() {
+        let mock = MockClient::new();
+        mock.respond("ready", Ok(true)).respond("ready", Ok(false));
+
+        assert!(mock.ready().await.unwrap());
+        assert!(!mock.ready().await.unwrap());
+        mock.delete_bucket(&"0000111100001111".parse().unwrap()).await.unwrap();
+
+        assert_eq!(
+            mock.calls(),
+            vec![
+                Call { method: "ready", args: vec![] },
+                Call { method: "ready", args: vec![] },
+                Call {
+                    method: "delete_bucket",
+                    args: vec![String::from("BucketId(\"0000111100001111\")")],
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn records_written_points_as_line_protocol() {
+        let mock = MockClient::new();
+        let point = DataPoint::builder("cpu")
+            .tag("host", "server01")
+            .field("usage", 0.5)
+            .build()
+            .unwrap();
+
+        mock.write("some-bucket".into(), vec![point]).await.unwrap();
+
+        assert_eq!(
+            mock.calls_to("write")[0].args,
+            vec![
+                String::from("Target { org: None, bucket: Some(\"some-bucket\") }"),
+                String::from("cpu,host=server01 usage=0.5\n"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn query_deserializes_canned_rows() {
+        let mock = MockClient::new();
+        let row: GenericMap = vec![
+            (String::from("room"), Value::String(String::from("kitchen"))),
+            (String::from("_value"), Value::Double(21.5.into())),
+        ]
+        .into_iter()
+        .collect();
+        mock.respond("query", Ok(vec![row]));
+
+        let res = mock.query::<Temperature>(None).await.unwrap();
+
+        assert_eq!(
+            res,
+            vec![Temperature {
+                room: String::from("kitchen"),
+                value: 21.5,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn works_as_trait_object() {
+        let mock = MockClient::new();
+        mock.respond(
+            "health",
+            Ok(HealthCheck::new(String::from("influxdb"), Status::Fail)),
+        );
+        let client: &dyn InfluxClient = &mock;
+
+        let health = client.health().await.unwrap();
+
+        assert_eq!(health.status, Status::Fail);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "no response queued for `health`")]
+    async fn panics_without_queued_response() {
+        let mock = MockClient::new();
+        mock.health().await.ok();
+    }
+}
@@ -12,7 +12,11 @@ pub struct CallExpression {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub callee: Option<Box<crate::models::ast::Expression>>,
     /// Function arguments
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub arguments: Vec<crate::models::ast::Expression>,
 }
 
@@ -1,6 +1,6 @@
 //! Expression
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 /// Expression AST
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
@@ -8,11 +8,19 @@ pub struct Expression {
     /// Type of AST node
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub r#type: Option<String>,
-    /// Elements of the dictionary
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub elements: Vec<crate::models::ast::DictItem>,
+    /// Elements of the array, or items of the dictionary
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub elements: Vec<Self>,
     /// Function parameters
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub params: Vec<crate::models::ast::Property>,
     /// Node
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -22,54 +30,86 @@ pub struct Expression {
     pub operator: Option<String>,
     /// Left leaf
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub left: Option<Box<crate::models::ast::Expression>>,
+    pub left: Option<Box<Self>>,
     /// Right leaf
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub right: Option<Box<crate::models::ast::Expression>>,
+    pub right: Option<Box<Self>>,
     /// Parent Expression
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub callee: Option<Box<crate::models::ast::Expression>>,
+    pub callee: Option<Box<Self>>,
     /// Function arguments
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub arguments: Vec<crate::models::ast::Expression>,
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub arguments: Vec<Self>,
     /// Test Expr
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub test: Option<Box<crate::models::ast::Expression>>,
+    pub test: Option<Box<Self>>,
     /// Alternate Expr
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub alternate: Option<Box<crate::models::ast::Expression>>,
+    pub alternate: Option<Box<Self>>,
     /// Consequent Expr
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub consequent: Option<Box<crate::models::ast::Expression>>,
+    pub consequent: Option<Box<Self>>,
     /// Object Expr
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub object: Option<Box<crate::models::ast::Expression>>,
+    pub object: Option<Box<Self>>,
     /// PropertyKey Expr
     #[serde(skip_serializing_if = "Option::is_none")]
     pub property: Option<Box<crate::models::ast::PropertyKey>>,
     /// Array Expr
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub array: Option<Box<crate::models::ast::Expression>>,
+    pub array: Option<Box<Self>>,
     /// Index Expr
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub index: Option<Box<crate::models::ast::Expression>>,
+    pub index: Option<Box<Self>>,
     /// Properties
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub properties: Vec<crate::models::ast::Property>,
     /// Expression
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub expression: Option<Box<crate::models::ast::Expression>>,
+    pub expression: Option<Box<Self>>,
     /// Argument
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub argument: Option<Box<crate::models::ast::Expression>>,
+    pub argument: Option<Box<Self>>,
+    /// Key of a dictionary item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<Box<Self>>,
+    /// Value of a dictionary item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub val: Option<Box<Self>>,
+    /// Object extended by the properties, as in `{r with _value: 0}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub with: Option<crate::models::ast::Identifier>,
+    /// Parts of an interpolated string
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub parts: Vec<crate::models::ast::StringExpressionPart>,
     /// Call Expr
     #[serde(skip_serializing_if = "Option::is_none")]
     pub call: Option<crate::models::ast::CallExpression>,
     /// Expression Value
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::literal_value",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub value: Option<String>,
     /// Duration values
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub values: Vec<crate::models::ast::Duration>,
     /// Expression Name
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -82,3 +122,25 @@ impl Expression {
         Self::default()
     }
 }
+
+/// The value of a literal, which the server sends as a string, or as a
+/// number or boolean for float and boolean literals
+pub(crate) fn literal_value<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(
+        Option::<serde_json::Value>::deserialize(deserializer)?.map(|value| match value {
+            serde_json::Value::String(value) => value,
+            value => value.to_string(),
+        }),
+    )
+}
+
+/// A list the server may send as `null` when it is empty
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,714 @@
+//! Flux formatting
+//!
+//! Renders the AST returned by `Client::query_ast` back into Flux source in
+//! a canonical layout, so that scripts differing only in whitespace or
+//! redundant parentheses format to the same text:
+//!
+//! - every `|>` of a pipeline starts a new, indented line
+//! - binary operators and `=>` are surrounded by single spaces, and commas
+//!   and colons are followed by one
+//! - parentheses are only kept where precedence requires them
+//! - statements are separated by a line, or by an empty line where the kind
+//!   of statement changes
+//!
+//! ```no_run
+//! # async fn example(client: influxdb2::Client) -> Result<(), influxdb2::RequestError> {
+//! let source = client
+//!     .format_query("from(bucket:\"b\")|>range(start:-1h)")
+//!     .await?;
+//! assert_eq!(source, "from(bucket: \"b\")\n    |> range(start: -1h)");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::models::ast::{
+    CallExpression, Expression, ImportDeclaration, Node, Package, Property, PropertyKey,
+    Statement, VariableAssignment,
+};
+use crate::models::template::flux_string;
+use crate::models::File;
+
+const INDENT: &str = "    ";
+
+/// Format the files of `package`, separated by an empty line.
+pub fn format_package(package: &Package) -> String {
+    package
+        .files
+        .iter()
+        .map(format_file)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Format the package clause, imports and statements of `file`.
+pub fn format_file(file: &File) -> String {
+    let mut formatter = Formatter::default();
+    formatter.file(file);
+    formatter.out
+}
+
+/// Format a single expression.
+pub fn format_expression(expression: &Expression) -> String {
+    let mut formatter = Formatter::default();
+    formatter.expression(expression);
+    formatter.out
+}
+
+/// How tightly an expression binds, from the loosest to the tightest
+fn precedence(expression: &Expression) -> u8 {
+    let operator = expression.operator.as_deref().unwrap_or_default();
+    match kind(expression) {
+        "ConditionalExpression" | "FunctionExpression" => 0,
+        "LogicalExpression" if operator == "or" => 1,
+        "LogicalExpression" => 2,
+        "UnaryExpression" if operator == "not" || operator == "exists" => 3,
+        "BinaryExpression" => match operator {
+            "^" => 7,
+            "*" | "/" | "%" => 6,
+            "+" | "-" => 5,
+            _ => 4,
+        },
+        "UnaryExpression" => 8,
+        "PipeExpression" => 9,
+        "CallExpression" | "MemberExpression" | "IndexExpression" => 10,
+        "ParenExpression" => expression.expression.as_deref().map_or(11, precedence),
+        _ => 11,
+    }
+}
+
+fn kind(expression: &Expression) -> &str {
+    expression.r#type.as_deref().unwrap_or_default()
+}
+
+/// The expression of `node`, a function body that isn't a block
+fn node_expression(node: &Node) -> Expression {
+    Expression {
+        r#type: node.r#type.clone(),
+        elements: node.elements.clone(),
+        params: node.params.clone(),
+        body: None,
+        operator: node.operator.clone(),
+        left: node.left.clone(),
+        right: node.right.clone(),
+        callee: node.callee.clone(),
+        arguments: node.arguments.clone(),
+        test: node.test.clone(),
+        alternate: node.alternate.clone(),
+        consequent: node.consequent.clone(),
+        object: node.object.clone(),
+        property: node.property.clone().map(Box::new),
+        array: node.array.clone(),
+        index: node.index.clone(),
+        properties: node.properties.clone(),
+        expression: node.expression.clone(),
+        argument: node.argument.clone(),
+        key: node.key.clone(),
+        val: node.val.clone(),
+        with: node.with.clone(),
+        parts: node.parts.clone(),
+        call: node.call.clone(),
+        value: node.value.clone(),
+        values: node.values.clone(),
+        name: node.name.clone(),
+    }
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    indent: usize,
+}
+
+impl Formatter {
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn file(&mut self, file: &File) {
+        let name = file
+            .package
+            .as_ref()
+            .and_then(|package| package.name.as_ref())
+            .and_then(|name| name.name.as_deref());
+        let mut sections = 0;
+        if let Some(name) = name {
+            self.push("package ");
+            self.push(name);
+            sections += 1;
+        }
+        if !file.imports.is_empty() {
+            if sections > 0 {
+                self.push("\n\n");
+            }
+            for (i, import) in file.imports.iter().enumerate() {
+                if i > 0 {
+                    self.newline();
+                }
+                self.import(import);
+            }
+            sections += 1;
+        }
+        if !file.body.is_empty() {
+            if sections > 0 {
+                self.push("\n\n");
+            }
+            self.statements(&file.body);
+        }
+    }
+
+    fn import(&mut self, import: &ImportDeclaration) {
+        self.push("import ");
+        if let Some(alias) = import.r#as.as_ref().and_then(|alias| alias.name.as_deref()) {
+            self.push(alias);
+            self.push(" ");
+        }
+        let path = import
+            .path
+            .as_ref()
+            .and_then(|path| path.value.as_deref())
+            .unwrap_or_default();
+        self.push(&flux_string(path));
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for (i, statement) in statements.iter().enumerate() {
+            if i > 0 {
+                if statements[i - 1].r#type != statement.r#type {
+                    self.out.push('\n');
+                }
+                self.newline();
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement.r#type.as_deref().unwrap_or_default() {
+            "OptionStatement" => {
+                self.push("option ");
+                self.assignment(statement.assignment.as_ref());
+            }
+            "TestStatement" => {
+                self.push("test ");
+                self.assignment(statement.assignment.as_ref());
+            }
+            "ReturnStatement" => {
+                self.push("return ");
+                self.optional(statement.argument.as_ref());
+            }
+            "BuiltinStatement" => {
+                self.push("builtin ");
+                if let Some(id) = statement.id.as_ref().and_then(|id| id.name.as_deref()) {
+                    self.push(id);
+                }
+            }
+            "MemberAssignment" => {
+                if let Some(member) = &statement.member {
+                    if let Some(object) = &member.object {
+                        self.operand(object, 10, false);
+                    }
+                    self.property(member.property.as_ref());
+                }
+                self.push(" = ");
+                self.optional(statement.init.as_ref());
+            }
+            "VariableAssignment" => {
+                if let Some(id) = statement.id.as_ref().and_then(|id| id.name.as_deref()) {
+                    self.push(id);
+                }
+                self.push(" = ");
+                self.optional(statement.init.as_ref());
+            }
+            "BadStatement" => {
+                if let Some(text) = &statement.text {
+                    self.push(text);
+                }
+            }
+            _ => self.optional(statement.expression.as_ref()),
+        }
+    }
+
+    fn assignment(&mut self, assignment: Option<&VariableAssignment>) {
+        if let Some(assignment) = assignment {
+            if let Some(id) = assignment.id.as_ref().and_then(|id| id.name.as_deref()) {
+                self.push(id);
+            }
+            self.push(" = ");
+            self.optional(assignment.init.as_ref());
+        }
+    }
+
+    fn optional(&mut self, expression: Option<&Expression>) {
+        if let Some(expression) = expression {
+            self.expression(expression);
+        }
+    }
+
+    /// Format `expression` as an operand binding at least as tightly as
+    /// `min`, or strictly more tightly if `strict`
+    fn operand(&mut self, expression: &Expression, min: u8, strict: bool) {
+        let inner = precedence(expression);
+        if inner < min || (strict && inner == min) {
+            self.push("(");
+            self.expression(expression);
+            self.push(")");
+        } else {
+            self.expression(expression);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        match kind(expression) {
+            "Identifier" => self.push(&text(&expression.name)),
+            "PipeLiteral" => self.push("<-"),
+            "StringLiteral" => self.push(&flux_string(&text(&expression.value))),
+            "FloatLiteral" => {
+                let value = text(&expression.value);
+                self.push(&value);
+                if !value.contains(['.', 'e', 'E']) {
+                    self.push(".0");
+                }
+            }
+            "RegexpLiteral" => {
+                self.push("/");
+                self.push(&text(&expression.value).replace('/', "\\/"));
+                self.push("/");
+            }
+            "DurationLiteral" => {
+                for duration in &expression.values {
+                    if let Some(magnitude) = duration.magnitude {
+                        self.push(&magnitude.to_string());
+                    }
+                    self.push(duration.unit.as_deref().unwrap_or_default());
+                }
+            }
+            "StringExpression" => {
+                self.push("\"");
+                for part in &expression.parts {
+                    match (&part.value, &part.expression) {
+                        (_, Some(interpolated)) => {
+                            self.push("${");
+                            self.expression(interpolated);
+                            self.push("}");
+                        }
+                        (Some(value), None) => {
+                            let quoted = flux_string(value);
+                            self.push(&quoted[1..quoted.len() - 1]);
+                        }
+                        (None, None) => {}
+                    }
+                }
+                self.push("\"");
+            }
+            "ArrayExpression" => {
+                self.push("[");
+                for (i, element) in expression.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.expression(element);
+                }
+                self.push("]");
+            }
+            "DictExpression" => {
+                self.push("[");
+                if expression.elements.is_empty() {
+                    self.push(":");
+                }
+                for (i, item) in expression.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.optional(item.key.as_deref());
+                    self.push(": ");
+                    self.optional(item.val.as_deref());
+                }
+                self.push("]");
+            }
+            "ObjectExpression" => {
+                self.push("{");
+                if let Some(with) = expression.with.as_ref().and_then(|with| with.name.as_deref())
+                {
+                    self.push(with);
+                    self.push(" with ");
+                }
+                self.properties(&expression.properties);
+                self.push("}");
+            }
+            "FunctionExpression" => {
+                self.push("(");
+                for (i, param) in expression.params.iter().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.property_key(param.key.as_ref());
+                    if let Some(default) = &param.value {
+                        self.push("=");
+                        self.expression(default);
+                    }
+                }
+                self.push(") => ");
+                if let Some(body) = &expression.body {
+                    self.function_body(body);
+                }
+            }
+            "CallExpression" => self.call(expression.callee.as_deref(), &expression.arguments),
+            "PipeExpression" => {
+                if let Some(argument) = &expression.argument {
+                    self.operand(argument, 9, false);
+                }
+                self.indent += 1;
+                self.newline();
+                self.push("|> ");
+                if let Some(CallExpression {
+                    callee, arguments, ..
+                }) = &expression.call
+                {
+                    self.call(callee.as_deref(), arguments);
+                }
+                self.indent -= 1;
+            }
+            "MemberExpression" => {
+                if let Some(object) = &expression.object {
+                    self.operand(object, 10, false);
+                }
+                self.property(expression.property.as_deref());
+            }
+            "IndexExpression" => {
+                if let Some(array) = &expression.array {
+                    self.operand(array, 10, false);
+                }
+                self.push("[");
+                self.optional(expression.index.as_deref());
+                self.push("]");
+            }
+            "BinaryExpression" | "LogicalExpression" => {
+                let outer = precedence(expression);
+                if let Some(left) = &expression.left {
+                    self.operand(left, outer, false);
+                }
+                self.push(" ");
+                self.push(&text(&expression.operator));
+                self.push(" ");
+                if let Some(right) = &expression.right {
+                    self.operand(right, outer, true);
+                }
+            }
+            "UnaryExpression" => {
+                let operator = text(&expression.operator);
+                self.push(&operator);
+                if operator.chars().all(char::is_alphabetic) {
+                    self.push(" ");
+                }
+                if let Some(argument) = &expression.argument {
+                    self.operand(argument, precedence(expression), false);
+                }
+            }
+            "ConditionalExpression" => {
+                self.push("if ");
+                self.optional(expression.test.as_deref());
+                self.push(" then ");
+                self.optional(expression.consequent.as_deref());
+                self.push(" else ");
+                self.optional(expression.alternate.as_deref());
+            }
+            "ParenExpression" => self.optional(expression.expression.as_deref()),
+            // Integer, unsigned integer, boolean and date-time literals
+            _ => self.push(&text(&expression.value)),
+        }
+    }
+
+    fn call(&mut self, callee: Option<&Expression>, arguments: &[Expression]) {
+        if let Some(callee) = callee {
+            self.operand(callee, 10, false);
+        }
+        self.push("(");
+        for argument in arguments {
+            // The arguments of a call are the properties of a single object
+            if kind(argument) == "ObjectExpression" && argument.with.is_none() {
+                self.properties(&argument.properties);
+            } else {
+                self.expression(argument);
+            }
+        }
+        self.push(")");
+    }
+
+    fn properties(&mut self, properties: &[Property]) {
+        for (i, property) in properties.iter().enumerate() {
+            if i > 0 {
+                self.push(", ");
+            }
+            self.property_key(property.key.as_ref());
+            if let Some(value) = &property.value {
+                self.push(": ");
+                self.expression(value);
+            }
+        }
+    }
+
+    fn property_key(&mut self, key: Option<&PropertyKey>) {
+        match key {
+            Some(PropertyKey {
+                name: Some(name), ..
+            }) => self.push(name),
+            Some(PropertyKey {
+                value: Some(value),
+                ..
+            }) => self.push(&flux_string(value)),
+            _ => {}
+        }
+    }
+
+    /// The `.name` or `["key"]` accessing a member
+    fn property(&mut self, key: Option<&PropertyKey>) {
+        match key {
+            Some(PropertyKey {
+                name: Some(name), ..
+            }) => {
+                self.push(".");
+                self.push(name);
+            }
+            Some(PropertyKey {
+                value: Some(value),
+                ..
+            }) => {
+                self.push("[");
+                self.push(&flux_string(value));
+                self.push("]");
+            }
+            _ => {}
+        }
+    }
+
+    fn function_body(&mut self, body: &Node) {
+        if body.r#type.as_deref() == Some("Block") {
+            self.push("{");
+            self.indent += 1;
+            self.newline();
+            self.statements(&body.body);
+            self.indent -= 1;
+            self.newline();
+            self.push("}");
+            return;
+        }
+
+        let expression = node_expression(body);
+        // A body starting with `{` would be read as a block
+        if kind(&expression) == "ObjectExpression" {
+            self.push("(");
+            self.expression(&expression);
+            self.push(")");
+        } else {
+            self.expression(&expression);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The AST the server returns for `source`, trimmed of locations
+    fn file(json: serde_json::Value) -> File {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn ident(name: &str) -> serde_json::Value {
+        serde_json::json!({"type": "Identifier", "name": name})
+    }
+
+    fn call(name: &str, properties: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "type": "CallExpression",
+            "callee": ident(name),
+            "arguments": [{"type": "ObjectExpression", "properties": properties}],
+        })
+    }
+
+    fn property(key: &str, value: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({"type": "Property", "key": ident(key), "value": value})
+    }
+
+    #[test]
+    fn formats_pipelines() {
+        let r_value = serde_json::json!({
+            "type": "MemberExpression",
+            "object": ident("r"),
+            "property": ident("_value"),
+        });
+        let predicate = serde_json::json!({
+            "type": "FunctionExpression",
+            "params": [{"type": "Property", "key": ident("r")}],
+            "body": {
+                "type": "LogicalExpression",
+                "operator": "and",
+                "left": {
+                    "type": "BinaryExpression",
+                    "operator": ">",
+                    "left": r_value,
+                    "right": {"type": "FloatLiteral", "value": 1},
+                },
+                "right": {
+                    "type": "BinaryExpression",
+                    "operator": "==",
+                    "left": {
+                        "type": "MemberExpression",
+                        "object": ident("r"),
+                        "property": {"type": "StringLiteral", "value": "host name"},
+                    },
+                    "right": {
+                        "type": "StringExpression",
+                        "parts": [
+                            {"type": "TextPart", "value": "a\""},
+                            {"type": "InterpolatedPart", "expression": ident("x")},
+                        ],
+                    },
+                },
+            },
+        });
+        let pipeline = serde_json::json!({
+            "type": "PipeExpression",
+            "argument": {
+                "type": "PipeExpression",
+                "argument": call("from", serde_json::json!([
+                    property("bucket", serde_json::json!({"type": "StringLiteral", "value": "b"})),
+                ])),
+                "call": call("range", serde_json::json!([
+                    property("start", serde_json::json!({
+                        "type": "UnaryExpression",
+                        "operator": "-",
+                        "argument": {
+                            "type": "DurationLiteral",
+                            "values": [{"magnitude": 1, "unit": "h"}, {"magnitude": 30, "unit": "m"}],
+                        },
+                    })),
+                ])),
+            },
+            "call": call("filter", serde_json::json!([property("fn", predicate)])),
+        });
+        let file = file(serde_json::json!({
+            "type": "File",
+            "imports": [{
+                "type": "ImportDeclaration",
+                "path": {"type": "StringLiteral", "value": "strings"},
+            }],
+            "body": [
+                {
+                    "type": "OptionStatement",
+                    "assignment": {
+                        "type": "VariableAssignment",
+                        "id": ident("now"),
+                        "init": {
+                            "type": "FunctionExpression",
+                            "body": {"type": "DateTimeLiteral", "value": "2023-01-01T00:00:00Z"},
+                        },
+                    },
+                },
+                {
+                    "type": "VariableAssignment",
+                    "id": ident("hosts"),
+                    "init": {
+                        "type": "ArrayExpression",
+                        "elements": [
+                            {"type": "StringLiteral", "value": "a"},
+                            {"type": "IntegerLiteral", "value": "2"},
+                        ],
+                    },
+                },
+                {"type": "ExpressionStatement", "expression": pipeline},
+            ],
+        }));
+
+        assert_eq!(
+            format_file(&file),
+            "import \"strings\"\n\
+             \n\
+             option now = () => 2023-01-01T00:00:00Z\n\
+             \n\
+             hosts = [\"a\", 2]\n\
+             \n\
+             from(bucket: \"b\")\n    \
+                 |> range(start: -1h30m)\n    \
+                 |> filter(fn: (r) => r._value > 1.0 and r[\"host name\"] == \"a\\\"${x}\")"
+        );
+    }
+
+    #[test]
+    fn keeps_required_parentheses() {
+        let binary = |operator: &str, left, right| {
+            serde_json::json!({
+                "type": "BinaryExpression",
+                "operator": operator,
+                "left": left,
+                "right": right,
+            })
+        };
+        let int = |value: &str| serde_json::json!({"type": "IntegerLiteral", "value": value});
+        let expression: Expression = serde_json::from_value(binary(
+            "*",
+            serde_json::json!({
+                "type": "ParenExpression",
+                "expression": binary("+", int("1"), int("2")),
+            }),
+            binary("-", int("3"), binary("-", int("4"), int("5"))),
+        ))
+        .unwrap();
+        assert_eq!(format_expression(&expression), "(1 + 2) * (3 - (4 - 5))");
+
+        let function: Expression = serde_json::from_value(serde_json::json!({
+            "type": "FunctionExpression",
+            "params": [
+                {"type": "Property", "key": ident("tables"), "value": {"type": "PipeLiteral"}},
+                {"type": "Property", "key": ident("r")},
+            ],
+            "body": {
+                "type": "ObjectExpression",
+                "with": ident("r"),
+                "properties": [property("_value", serde_json::json!({
+                    "type": "DictExpression",
+                    "elements": [],
+                }))],
+            },
+        }))
+        .unwrap();
+        assert_eq!(
+            format_expression(&function),
+            "(tables=<-, r) => ({r with _value: [:]})"
+        );
+    }
+
+    #[test]
+    fn formats_blocks() {
+        let function: Expression = serde_json::from_value(serde_json::json!({
+            "type": "FunctionExpression",
+            "params": [{"type": "Property", "key": ident("x")}],
+            "body": {
+                "type": "Block",
+                "body": [
+                    {"type": "VariableAssignment", "id": ident("y"), "init": ident("x")},
+                    {"type": "ReturnStatement", "argument": {
+                        "type": "ConditionalExpression",
+                        "test": {"type": "UnaryExpression", "operator": "exists", "argument": ident("y")},
+                        "consequent": ident("y"),
+                        "alternate": {"type": "RegexpLiteral", "value": "a/b"},
+                    }},
+                ],
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            format_expression(&function),
+            "(x) => {\n    y = x\n\n    return if exists y then y else /a\\/b/\n}"
+        );
+    }
+}
@@ -12,6 +12,8 @@ pub mod member_expression;
 pub use self::member_expression::MemberExpression;
 pub mod string_literal;
 pub use self::string_literal::StringLiteral;
+pub mod string_expression_part;
+pub use self::string_expression_part::StringExpressionPart;
 pub mod dict_item;
 pub use self::dict_item::DictItem;
 pub mod variable_assignment;
@@ -32,3 +34,4 @@ pub mod package_clause;
 pub use self::package_clause::PackageClause;
 pub mod duration;
 pub use self::duration::Duration;
+pub mod format;
@@ -8,14 +8,26 @@ pub struct Node {
     /// Type of AST node
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub r#type: Option<String>,
-    /// Elements of the dictionary
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub elements: Vec<crate::models::ast::DictItem>,
+    /// Elements of the array, or items of the dictionary
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub elements: Vec<crate::models::ast::Expression>,
     /// Function parameters
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub params: Vec<crate::models::ast::Property>,
     /// Block body
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub body: Vec<crate::models::ast::Statement>,
     /// Node Operator
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -30,7 +42,11 @@ pub struct Node {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub callee: Option<Box<crate::models::ast::Expression>>,
     /// Function arguments
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub arguments: Vec<crate::models::ast::Expression>,
     /// Test Expr
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -54,7 +70,11 @@ pub struct Node {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub index: Option<Box<crate::models::ast::Expression>>,
     /// Object properties
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub properties: Vec<crate::models::ast::Property>,
     /// Expression
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -62,14 +82,38 @@ pub struct Node {
     /// Node arguments
     #[serde(skip_serializing_if = "Option::is_none")]
     pub argument: Option<Box<crate::models::ast::Expression>>,
+    /// Key of a dictionary item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<Box<crate::models::ast::Expression>>,
+    /// Value of a dictionary item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub val: Option<Box<crate::models::ast::Expression>>,
+    /// Object extended by the properties, as in `{r with _value: 0}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub with: Option<crate::models::ast::Identifier>,
+    /// Parts of an interpolated string
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub parts: Vec<crate::models::ast::StringExpressionPart>,
     /// Call Expr
     #[serde(skip_serializing_if = "Option::is_none")]
     pub call: Option<crate::models::ast::CallExpression>,
     /// Node Value
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::literal_value",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub value: Option<String>,
     /// Duration values
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub values: Vec<crate::models::ast::Duration>,
     /// Node name
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -16,7 +16,11 @@ pub struct Package {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package: Option<String>,
     /// Package files
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub files: Vec<File>,
 }
 
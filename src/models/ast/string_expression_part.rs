@@ -0,0 +1,24 @@
+//! StringExpressionPart
+
+use serde::{Deserialize, Serialize};
+
+/// A part of an interpolated string: text, or an expression in `${}`
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct StringExpressionPart {
+    /// Type of AST node, `TextPart` or `InterpolatedPart`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    /// The text of a text part
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// The expression of an interpolated part
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expression: Option<crate::models::ast::Expression>,
+}
+
+impl StringExpressionPart {
+    /// Return instance of StringExpressionPart
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -23,7 +23,7 @@ pub struct Authorization {
     pub updated_at: Option<String>,
     /// ID of org that authorization is scoped to.
     #[serde(rename = "orgID")]
-    pub org_id: String,
+    pub org_id: crate::models::OrgId,
     /// List of permissions for an auth. An auth must have at least one
     /// Permission.
     pub permissions: Vec<crate::models::Permission>,
@@ -35,7 +35,7 @@ pub struct Authorization {
     pub token: Option<String>,
     /// ID of user that created and owns the token.
     #[serde(rename = "userID", skip_serializing_if = "Option::is_none")]
-    pub user_id: Option<String>,
+    pub user_id: Option<crate::models::UserId>,
     /// Name of user that created and owns the token.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
@@ -49,7 +49,7 @@ pub struct Authorization {
 
 impl Authorization {
     /// Returns an Authorization with the given orgID and permissions
-    pub fn new(org_id: String, permissions: Vec<crate::models::Permission>) -> Self {
+    pub fn new(org_id: crate::models::OrgId, permissions: Vec<crate::models::Permission>) -> Self {
         Self {
             org_id,
             permissions,
@@ -58,6 +58,17 @@ impl Authorization {
     }
 }
 
+/// List of authorizations
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Authorizations {
+    /// Links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<crate::models::Links>,
+    /// Authorizations
+    #[serde(default)]
+    pub authorizations: Vec<Authorization>,
+}
+
 /// If inactive the token is inactive and requests using the token will be
 /// rejected.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
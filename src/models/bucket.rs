@@ -11,7 +11,7 @@ pub struct Bucket {
     pub links: Option<crate::models::BucketLinks>,
     /// Bucket ID
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<String>,
+    pub id: Option<crate::models::BucketId>,
     /// Bucket Type
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub r#type: Option<Type>,
@@ -22,7 +22,7 @@ pub struct Bucket {
     pub description: Option<String>,
     /// Organization ID of bucket
     #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
-    pub org_id: Option<String>,
+    pub org_id: Option<crate::models::OrgId>,
     /// RP
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rp: Option<String>,
@@ -116,7 +116,7 @@ impl Buckets {
 pub struct PostBucketRequest {
     /// Organization ID
     #[serde(rename = "orgID")]
-    pub org_id: String,
+    pub org_id: crate::models::OrgId,
     /// Bucket name
     pub name: String,
     /// Bucket Description
@@ -132,11 +132,59 @@ pub struct PostBucketRequest {
 
 impl PostBucketRequest {
     /// Returns instance of PostBucketRequest
-    pub fn new(org_id: String, name: String) -> Self {
+    pub fn new(org_id: crate::models::OrgId, name: String) -> Self {
         Self {
             org_id,
             name,
             ..Default::default()
         }
     }
+
+    /// Sets the retention period from a string such as `72h`, `30d` or
+    /// `inf`, and optionally the shard group duration, e.g. `1d`.
+    ///
+    /// ```
+    /// use influxdb2::models::PostBucketRequest;
+    ///
+    /// let request = PostBucketRequest::new("0000000000000001".into(), "b".into())
+    ///     .with_retention("30d", Some("1d"))
+    ///     .unwrap();
+    /// assert_eq!(request.retention_rules[0].every_seconds.as_secs(), 30 * 86_400);
+    /// ```
+    pub fn with_retention(
+        mut self,
+        retention: &str,
+        shard_group_duration: Option<&str>,
+    ) -> Result<Self, crate::models::ParseDurationError> {
+        let mut rule: crate::models::RetentionRule = retention.parse()?;
+        if let Some(duration) = shard_group_duration {
+            rule = rule.with_shard_group_duration(duration.parse()?);
+        }
+        self.retention_rules = vec![rule];
+        Ok(self)
+    }
+}
+
+/// PatchBucketRequest
+///
+/// The fields left `None` are kept as they are.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchBucketRequest {
+    /// Bucket name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Bucket Description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Rules to expire or retain data, replacing the current ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_rules: Option<Vec<crate::models::RetentionRule>>,
+}
+
+impl PatchBucketRequest {
+    /// Returns instance of PatchBucketRequest
+    pub fn new() -> Self {
+        Self::default()
+    }
 }
@@ -0,0 +1,803 @@
+//! InfluxDB 1.x continuous queries
+//!
+//! Parse the `CREATE CONTINUOUS QUERY` statement of a 1.x database and
+//! generate the Flux task doing the same on 2.x, for migrations. Databases
+//! and retention policies map to the `db/rp` buckets of the 1.x
+//! compatibility API, `autogen` being the retention policy of measurements
+//! that don't name one.
+//!
+//! ```
+//! use influxdb2::models::ContinuousQuery;
+//!
+//! let cq: ContinuousQuery = r#"CREATE CONTINUOUS QUERY "cq_30m" ON "food_data" BEGIN
+//!     SELECT mean("website") AS "mean_website" INTO "a_year"."downsampled_orders"
+//!     FROM "orders" GROUP BY time(30m)
+//! END"#
+//!     .parse()
+//!     .unwrap();
+//! assert_eq!(
+//!     cq.flux(),
+//!     "data = from(bucket: \"food_data/autogen\")\n    \
+//!      |> range(start: -30m)\n    \
+//!      |> filter(fn: (r) => r._measurement == \"orders\")\n    \
+//!      |> group(columns: [\"_measurement\", \"_field\"])\n\n\
+//!      data\n    \
+//!      |> filter(fn: (r) => r._field == \"website\")\n    \
+//!      |> aggregateWindow(every: 30m, fn: mean, timeSrc: \"_start\", createEmpty: false)\n    \
+//!      |> set(key: \"_field\", value: \"mean_website\")\n    \
+//!      |> set(key: \"_measurement\", value: \"downsampled_orders\")\n    \
+//!      |> to(bucket: \"food_data/a_year\")\n"
+//! );
+//! ```
+//!
+//! Only the statements a task can express are accepted: aggregates of a
+//! field or of `*`, `WHERE` clauses comparing columns to literals joined by
+//! `AND`, and `GROUP BY time(...)` with tags or `*`.
+
+use std::str::FromStr;
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+
+use crate::models::template::flux_string;
+use crate::models::{FluxDuration, ParseDurationError};
+
+/// The aggregates InfluxQL and Flux share a name for
+const AGGREGATES: &[&str] = &[
+    "count", "first", "last", "max", "mean", "median", "min", "spread", "stddev", "sum",
+];
+
+/// Errors that occur while parsing a continuous query
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum ContinuousQueryError {
+    /// The statement doesn't follow the InfluxQL syntax.
+    #[snafu(display("Expected {} but found {:?}", expected, found))]
+    Syntax {
+        /// What the statement should have had
+        expected: String,
+        /// What it had, or an empty string at its end
+        found: String,
+    },
+
+    /// A duration that isn't an InfluxQL duration literal.
+    #[snafu(display("Invalid duration {:?}: {}", text, source))]
+    Duration {
+        /// The duration
+        text: String,
+        /// The parse error
+        source: ParseDurationError,
+    },
+
+    /// A part of the statement that has no equivalent task.
+    #[snafu(display("Unsupported in a continuous query migration: {}", feature))]
+    Unsupported {
+        /// What isn't supported
+        feature: String,
+    },
+}
+
+/// A measurement, optionally qualified by its database and retention
+/// policy
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeasurementRef {
+    /// The database, or the database of the query if `None`
+    pub database: Option<String>,
+    /// The retention policy, or `autogen` if `None`
+    pub retention_policy: Option<String>,
+    /// The measurement, or the source measurement for the `:MEASUREMENT`
+    /// back-reference if `None`
+    pub measurement: Option<String>,
+}
+
+/// An aggregate of the `SELECT` clause
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectedField {
+    /// The aggregate function, such as `mean`
+    pub function: String,
+    /// The field aggregated, or every field for `*` if `None`
+    pub field: Option<String>,
+    /// The name of the field written
+    pub alias: Option<String>,
+}
+
+/// A comparison of the `WHERE` clause
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    /// The tag or field compared
+    pub column: String,
+    /// The Flux comparison operator, such as `==`
+    pub operator: String,
+    /// The Flux literal compared with
+    pub value: String,
+}
+
+/// The tags of the `GROUP BY` clause
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupTags {
+    /// Group by these tags, merging the series that differ in others
+    Tags(Vec<String>),
+    /// Keep every series apart, for `GROUP BY *`
+    All,
+}
+
+/// A parsed `CREATE CONTINUOUS QUERY` statement
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContinuousQuery {
+    /// Name of the continuous query, which becomes the name of the task
+    pub name: String,
+    /// The database the query runs on
+    pub database: String,
+    /// The aggregates selected
+    pub fields: Vec<SelectedField>,
+    /// Where the results are written
+    pub into: MeasurementRef,
+    /// Where the points are read from
+    pub from: MeasurementRef,
+    /// The comparisons of the `WHERE` clause, all of which must hold
+    pub conditions: Vec<Condition>,
+    /// The interval of `GROUP BY time(...)`
+    pub interval: FluxDuration,
+    /// The tags grouped by
+    pub group_by: GroupTags,
+    /// How often the query runs, the interval by default
+    pub resample_every: Option<FluxDuration>,
+    /// How far back each run looks, the interval by default
+    pub resample_for: Option<FluxDuration>,
+}
+
+impl FromStr for ContinuousQuery {
+    type Err = ContinuousQueryError;
+
+    fn from_str(influxql: &str) -> Result<Self, Self::Err> {
+        Parser {
+            tokens: tokenize(influxql)?,
+            position: 0,
+        }
+        .continuous_query()
+    }
+}
+
+impl ContinuousQuery {
+    /// The bucket the query reads from.
+    pub fn source_bucket(&self) -> String {
+        self.bucket(&self.from)
+    }
+
+    /// The bucket the query writes to.
+    pub fn target_bucket(&self) -> String {
+        self.bucket(&self.into)
+    }
+
+    fn bucket(&self, measurement: &MeasurementRef) -> String {
+        format!(
+            "{}/{}",
+            measurement.database.as_deref().unwrap_or(&self.database),
+            measurement.retention_policy.as_deref().unwrap_or("autogen")
+        )
+    }
+
+    /// How often the task runs.
+    pub fn every(&self) -> FluxDuration {
+        self.resample_every.unwrap_or(self.interval)
+    }
+
+    /// The body of the task script, which follows the `option task` block.
+    pub fn flux(&self) -> String {
+        let source = self.from.measurement.as_deref().unwrap_or_default();
+        let mut data = vec![
+            format!("from(bucket: {})", flux_string(&self.source_bucket())),
+            format!(
+                "range(start: -{})",
+                self.resample_for.unwrap_or(self.interval)
+            ),
+            format!(
+                "filter(fn: (r) => r._measurement == {})",
+                flux_string(source)
+            ),
+        ];
+        if !self.conditions.is_empty() {
+            let conditions: Vec<_> = self
+                .conditions
+                .iter()
+                .map(|c| format!("r[{}] {} {}", flux_string(&c.column), c.operator, c.value))
+                .collect();
+            data.push(format!("filter(fn: (r) => {})", conditions.join(" and ")));
+        }
+        if let GroupTags::Tags(tags) = &self.group_by {
+            let columns: Vec<_> = ["_measurement", "_field"]
+                .iter()
+                .map(|column| flux_string(column))
+                .chain(tags.iter().map(|tag| flux_string(tag)))
+                .collect();
+            data.push(format!("group(columns: [{}])", columns.join(", ")));
+        }
+        let mut flux = format!("data = {}\n", data.join("\n    |> "));
+
+        let target = self.into.measurement.as_deref().unwrap_or(source);
+        for (selected, name) in self.fields.iter().zip(self.field_names()) {
+            let mut steps = vec!["data".to_string()];
+            if let Some(field) = &selected.field {
+                steps.push(format!(
+                    "filter(fn: (r) => r._field == {})",
+                    flux_string(field)
+                ));
+            }
+            steps.push(format!(
+                "aggregateWindow(every: {}, fn: {}, timeSrc: \"_start\", createEmpty: false)",
+                self.interval, selected.function
+            ));
+            steps.push(match &selected.field {
+                Some(_) => format!("set(key: \"_field\", value: {})", flux_string(&name)),
+                None => format!(
+                    "map(fn: (r) => ({{r with _field: {} + r._field}}))",
+                    flux_string(&format!("{}_", name))
+                ),
+            });
+            steps.push(format!(
+                "set(key: \"_measurement\", value: {})",
+                flux_string(target)
+            ));
+            steps.push(format!(
+                "to(bucket: {})",
+                flux_string(&self.target_bucket())
+            ));
+            flux.push('\n');
+            flux.push_str(&steps.join("\n    |> "));
+            flux.push('\n');
+        }
+        flux
+    }
+
+    /// The names of the fields written, or their prefix for `*`, numbered
+    /// like InfluxQL when an unaliased function repeats.
+    fn field_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for selected in &self.fields {
+            let name = match &selected.alias {
+                Some(alias) => alias.clone(),
+                None => {
+                    let base = &selected.function;
+                    let mut name = base.clone();
+                    let mut n = 0;
+                    while names.contains(&name) {
+                        n += 1;
+                        name = format!("{}_{}", base, n);
+                    }
+                    name
+                }
+            };
+            names.push(name);
+        }
+        names
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A bare or double-quoted identifier
+    Ident { name: String, quoted: bool },
+    /// A single-quoted string
+    Str(String),
+    /// A number or a duration literal
+    Number(String),
+    /// Punctuation or an operator
+    Punct(String),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Self::Ident { name, .. }
+            | Self::Str(name)
+            | Self::Number(name)
+            | Self::Punct(name) => name.clone(),
+        }
+    }
+}
+
+fn tokenize(influxql: &str) -> Result<Vec<Token>, ContinuousQueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = influxql.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => text.extend(chars.next()),
+                    Some(end) if end == c => break,
+                    Some(other) => text.push(other),
+                    None => {
+                        return SyntaxSnafu {
+                            expected: format!("closing {}", c),
+                            found: "",
+                        }
+                        .fail()
+                    }
+                }
+            }
+            tokens.push(match c {
+                '"' => Token::Ident {
+                    name: text,
+                    quoted: true,
+                },
+                _ => Token::Str(text),
+            });
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars
+                .peek()
+                .filter(|c| c.is_ascii_alphanumeric() || **c == '_')
+            {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Ident {
+                name,
+                quoted: false,
+            });
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&c) = chars
+                .peek()
+                .filter(|c| c.is_ascii_alphanumeric() || **c == '.' || **c == 'µ')
+            {
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(number));
+        } else {
+            chars.next();
+            let punct = match (c, chars.peek()) {
+                ('!', Some('=')) | ('<', Some('>')) | ('<', Some('=')) | ('>', Some('=')) => {
+                    let second = chars.next().unwrap_or_default();
+                    format!("{}{}", c, second)
+                }
+                _ => c.to_string(),
+            };
+            tokens.push(Token::Punct(punct));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn found(&self) -> String {
+        self.peek().map(Token::text).unwrap_or_default()
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Ident { name, quoted: false }) if name.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if p == punct)
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), ContinuousQueryError> {
+        ensure!(
+            self.is_keyword(keyword),
+            SyntaxSnafu {
+                expected: keyword,
+                found: self.found()
+            }
+        );
+        self.position += 1;
+        Ok(())
+    }
+
+    fn punct(&mut self, punct: &str) -> Result<(), ContinuousQueryError> {
+        ensure!(
+            self.is_punct(punct),
+            SyntaxSnafu {
+                expected: format!("{:?}", punct),
+                found: self.found()
+            }
+        );
+        self.position += 1;
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String, ContinuousQueryError> {
+        match self.peek() {
+            Some(Token::Ident { name, .. }) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => SyntaxSnafu {
+                expected: "an identifier",
+                found: self.found(),
+            }
+            .fail(),
+        }
+    }
+
+    fn duration(&mut self) -> Result<FluxDuration, ContinuousQueryError> {
+        let text = match self.peek() {
+            Some(Token::Number(text)) => text.clone(),
+            _ => {
+                return SyntaxSnafu {
+                    expected: "a duration",
+                    found: self.found(),
+                }
+                .fail()
+            }
+        };
+        self.position += 1;
+        // InfluxQL writes microseconds as `u`, Flux as `us`
+        let flux = match text.strip_suffix('u') {
+            Some(number) => format!("{}us", number),
+            None => text.clone(),
+        };
+        flux.parse().context(DurationSnafu { text })
+    }
+
+    fn continuous_query(mut self) -> Result<ContinuousQuery, ContinuousQueryError> {
+        self.keyword("CREATE")?;
+        self.keyword("CONTINUOUS")?;
+        self.keyword("QUERY")?;
+        let name = self.ident()?;
+        self.keyword("ON")?;
+        let database = self.ident()?;
+
+        let (mut resample_every, mut resample_for) = (None, None);
+        if self.is_keyword("RESAMPLE") {
+            self.position += 1;
+            if self.is_keyword("EVERY") {
+                self.position += 1;
+                resample_every = Some(self.duration()?);
+            }
+            if self.is_keyword("FOR") {
+                self.position += 1;
+                resample_for = Some(self.duration()?);
+            }
+        }
+
+        self.keyword("BEGIN")?;
+        self.keyword("SELECT")?;
+        let mut fields = vec![self.selected_field()?];
+        while self.is_punct(",") {
+            self.position += 1;
+            fields.push(self.selected_field()?);
+        }
+        self.keyword("INTO")?;
+        let into = self.measurement()?;
+        self.keyword("FROM")?;
+        let from = self.measurement()?;
+        ensure!(
+            from.measurement.is_some(),
+            SyntaxSnafu {
+                expected: "a source measurement",
+                found: ":MEASUREMENT"
+            }
+        );
+
+        let mut conditions = Vec::new();
+        if self.is_keyword("WHERE") {
+            self.position += 1;
+            conditions.push(self.condition()?);
+            while self.is_keyword("AND") {
+                self.position += 1;
+                conditions.push(self.condition()?);
+            }
+        }
+
+        self.keyword("GROUP")?;
+        self.keyword("BY")?;
+        let (interval, group_by) = self.group_by()?;
+
+        if self.is_keyword("fill") {
+            self.position += 1;
+            self.punct("(")?;
+            let fill = self.next().map(|token| token.text()).unwrap_or_default();
+            ensure!(
+                fill.eq_ignore_ascii_case("none") || fill.eq_ignore_ascii_case("null"),
+                UnsupportedSnafu {
+                    feature: format!("fill({})", fill)
+                }
+            );
+            self.punct(")")?;
+        }
+        self.keyword("END")?;
+        if self.is_punct(";") {
+            self.position += 1;
+        }
+        ensure!(
+            self.peek().is_none(),
+            SyntaxSnafu {
+                expected: "the end of the statement",
+                found: self.found()
+            }
+        );
+
+        Ok(ContinuousQuery {
+            name,
+            database,
+            fields,
+            into,
+            from,
+            conditions,
+            interval,
+            group_by,
+            resample_every,
+            resample_for,
+        })
+    }
+
+    fn selected_field(&mut self) -> Result<SelectedField, ContinuousQueryError> {
+        let function = self.ident()?.to_ascii_lowercase();
+        ensure!(
+            AGGREGATES.contains(&function.as_str()),
+            UnsupportedSnafu {
+                feature: format!("the function {}()", function)
+            }
+        );
+        self.punct("(")?;
+        let field = match self.is_punct("*") {
+            true => {
+                self.position += 1;
+                None
+            }
+            false => Some(self.ident()?),
+        };
+        self.punct(")")?;
+
+        let alias = match self.is_keyword("AS") {
+            true => {
+                self.position += 1;
+                Some(self.ident()?)
+            }
+            false => None,
+        };
+        Ok(SelectedField {
+            function,
+            field,
+            alias,
+        })
+    }
+
+    fn measurement(&mut self) -> Result<MeasurementRef, ContinuousQueryError> {
+        let mut parts = Vec::new();
+        loop {
+            if self.is_punct(":") {
+                self.position += 1;
+                self.keyword("MEASUREMENT")?;
+                parts.push(None);
+                break;
+            }
+            parts.push(Some(self.ident()?));
+            if !self.is_punct(".") {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let mut parts = parts.into_iter().rev();
+        let measurement = parts.next().flatten();
+        let retention_policy = parts.next().flatten();
+        let database = parts.next().flatten();
+        ensure!(
+            parts.next().is_none(),
+            SyntaxSnafu {
+                expected: "a measurement",
+                found: self.found()
+            }
+        );
+        Ok(MeasurementRef {
+            database,
+            retention_policy,
+            measurement,
+        })
+    }
+
+    fn condition(&mut self) -> Result<Condition, ContinuousQueryError> {
+        ensure!(
+            !self.is_punct("("),
+            UnsupportedSnafu {
+                feature: "parentheses in WHERE"
+            }
+        );
+        let column = self.ident()?;
+        ensure!(
+            !column.eq_ignore_ascii_case("time"),
+            UnsupportedSnafu {
+                feature: "conditions on time"
+            }
+        );
+
+        let operator = match self.next() {
+            Some(Token::Punct(p)) => match p.as_str() {
+                "=" => "==",
+                "!=" | "<>" => "!=",
+                "<" => "<",
+                "<=" => "<=",
+                ">" => ">",
+                ">=" => ">=",
+                _ => {
+                    return UnsupportedSnafu {
+                        feature: format!("the operator {}", p),
+                    }
+                    .fail()
+                }
+            },
+            other => {
+                return SyntaxSnafu {
+                    expected: "a comparison",
+                    found: other.map(|token| token.text()).unwrap_or_default(),
+                }
+                .fail()
+            }
+        };
+
+        let negative = self.is_punct("-");
+        if negative {
+            self.position += 1;
+        }
+        let value = match self.next() {
+            Some(Token::Str(value)) if !negative => flux_string(&value),
+            Some(Token::Number(number)) if number.parse::<f64>().is_ok() => match negative {
+                true => format!("-{}", number),
+                false => number,
+            },
+            Some(Token::Ident {
+                name,
+                quoted: false,
+            }) if !negative && (name == "true" || name == "false") => name,
+            other => {
+                return SyntaxSnafu {
+                    expected: "a string, number or boolean",
+                    found: other.map(|token| token.text()).unwrap_or_default(),
+                }
+                .fail()
+            }
+        };
+        ensure!(
+            !self.is_keyword("OR"),
+            UnsupportedSnafu {
+                feature: "OR in WHERE"
+            }
+        );
+
+        Ok(Condition {
+            column,
+            operator: operator.to_string(),
+            value,
+        })
+    }
+
+    fn group_by(&mut self) -> Result<(FluxDuration, GroupTags), ContinuousQueryError> {
+        let mut interval = None;
+        let mut tags = Some(Vec::new());
+        loop {
+            if self.is_keyword("time") {
+                self.position += 1;
+                self.punct("(")?;
+                interval = Some(self.duration()?);
+                ensure!(
+                    !self.is_punct(","),
+                    UnsupportedSnafu {
+                        feature: "offsets of GROUP BY time()"
+                    }
+                );
+                self.punct(")")?;
+            } else if self.is_punct("*") {
+                self.position += 1;
+                tags = None;
+            } else {
+                let tag = self.ident()?;
+                if let Some(tags) = &mut tags {
+                    tags.push(tag);
+                }
+            }
+            if !self.is_punct(",") {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let interval = interval.context(SyntaxSnafu {
+            expected: "GROUP BY time(...)",
+            found: self.found(),
+        })?;
+        Ok((interval, tags.map_or(GroupTags::All, GroupTags::Tags)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_resampled_queries_with_tags_and_conditions() {
+        let cq: ContinuousQuery =
+            "CREATE CONTINUOUS QUERY cq ON db RESAMPLE EVERY 1h FOR 2h BEGIN \
+             SELECT max(*), mean(value), mean(other) INTO \"db\".\"rp\".:MEASUREMENT FROM cpu \
+             WHERE host != 'a''s' AND value > -1.5 GROUP BY time(1h), region fill(none) END;"
+                .replace("''", "\\'")
+                .parse()
+                .unwrap();
+
+        assert_eq!(cq.every(), FluxDuration::from_secs(3600));
+        assert_eq!(cq.target_bucket(), "db/rp");
+        assert_eq!(
+            cq.flux(),
+            r#"data = from(bucket: "db/autogen")
+    |> range(start: -2h)
+    |> filter(fn: (r) => r._measurement == "cpu")
+    |> filter(fn: (r) => r["host"] != "a's" and r["value"] > -1.5)
+    |> group(columns: ["_measurement", "_field", "region"])
+
+data
+    |> aggregateWindow(every: 1h, fn: max, timeSrc: "_start", createEmpty: false)
+    |> map(fn: (r) => ({r with _field: "max_" + r._field}))
+    |> set(key: "_measurement", value: "cpu")
+    |> to(bucket: "db/rp")
+
+data
+    |> filter(fn: (r) => r._field == "value")
+    |> aggregateWindow(every: 1h, fn: mean, timeSrc: "_start", createEmpty: false)
+    |> set(key: "_field", value: "mean")
+    |> set(key: "_measurement", value: "cpu")
+    |> to(bucket: "db/rp")
+
+data
+    |> filter(fn: (r) => r._field == "other")
+    |> aggregateWindow(every: 1h, fn: mean, timeSrc: "_start", createEmpty: false)
+    |> set(key: "_field", value: "mean_1")
+    |> set(key: "_measurement", value: "cpu")
+    |> to(bucket: "db/rp")
+"#
+        );
+    }
+
+    #[test]
+    fn rejects_what_tasks_cannot_express() {
+        let parse = |influxql: &str| influxql.parse::<ContinuousQuery>().unwrap_err();
+
+        assert_eq!(
+            parse(
+                "CREATE CONTINUOUS QUERY cq ON db BEGIN SELECT percentile(v, 95) INTO x \
+                 FROM y GROUP BY time(1m) END"
+            ),
+            ContinuousQueryError::Unsupported {
+                feature: "the function percentile()".to_string()
+            }
+        );
+        assert_eq!(
+            parse(
+                "CREATE CONTINUOUS QUERY cq ON db BEGIN SELECT mean(v) INTO x FROM y \
+                 WHERE a = 'b' OR c = 'd' GROUP BY time(1m) END"
+            ),
+            ContinuousQueryError::Unsupported {
+                feature: "OR in WHERE".to_string()
+            }
+        );
+        assert_eq!(
+            parse("CREATE CONTINUOUS QUERY cq ON db BEGIN SELECT mean(v) INTO x FROM y GROUP BY host END"),
+            ContinuousQueryError::Syntax {
+                expected: "GROUP BY time(...)".to_string(),
+                found: "END".to_string()
+            }
+        );
+    }
+}
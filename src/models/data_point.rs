@@ -1,7 +1,10 @@
 //! Data point building and writing
 
 use snafu::{ensure, Snafu};
-use std::{collections::BTreeMap, io};
+use std::{borrow::Cow, collections::BTreeMap, io};
+
+use crate::line_protocol::naming::Naming;
+use crate::line_protocol::{parse, Precision};
 
 /// Errors that occur while building `DataPoint`s
 #[derive(Debug, Snafu)]
@@ -91,7 +94,7 @@ impl DataPointBuilder {
 // to be `Vec<u8>` instead, the API for creating a `DataPoint` would need some more consideration,
 // and there would need to be more `Write*` trait implementations. Because the `Write*` traits work
 // on a writer of bytes, that part of the design supports non-UTF-8 data now.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DataPoint {
     measurement: String,
     tags: BTreeMap<String, String>,
@@ -104,39 +107,167 @@ impl DataPoint {
     pub fn builder(measurement: impl Into<String>) -> DataPointBuilder {
         DataPointBuilder::new(measurement)
     }
+
+    /// The measurement of this point.
+    pub fn measurement(&self) -> &str {
+        &self.measurement
+    }
+
+    /// The tags of this point, sorted by name.
+    pub fn tags(&self) -> &BTreeMap<String, String> {
+        &self.tags
+    }
+
+    /// The fields of this point, sorted by name.
+    pub fn fields(&self) -> &BTreeMap<String, FieldValue> {
+        &self.fields
+    }
+
+    /// The timestamp of this point, in nanoseconds since the UNIX epoch.
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    /// Add the fields of `other`, replacing those of the same name.
+    #[cfg(all(feature = "write-batcher", not(target_arch = "wasm32")))]
+    pub(crate) fn merge_fields(&mut self, other: Self) {
+        self.fields.extend(other.fields);
+    }
 }
 
-impl WriteDataPoint for DataPoint {
-    fn write_data_point_to<W>(&self, mut w: W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
-        self.measurement.write_measurement_to(&mut w)?;
+/// Construct a `DataPoint` in one expression.
+///
+/// The measurement comes first, then the optional `tags`, the `fields` and
+/// the optional timestamp `ts`, in nanoseconds since the UNIX epoch, `now`
+/// for the time of the system, or `now(clock)` for the time of a
+/// [`Clock`](crate::clock::Clock). Keys are identifiers or string literals;
+/// values are any expression the builder takes. A measurement that isn't a
+/// literal or an identifier goes in parentheses.
+///
+/// ```
+/// use influxdb2::point;
+///
+/// let host = "server01";
+/// let point = point!("cpu", tags: { host: host, "cpu-id": "0" }, fields: { usage: 0.3 }, ts: now);
+/// assert_eq!(point.tags()["cpu-id"], "0");
+/// ```
+///
+/// Points without fields and empty measurement literals don't compile:
+///
+/// ```compile_fail
+/// let point = influxdb2::point!("cpu", tags: { host: "a" }, fields: {});
+/// ```
+///
+/// ```compile_fail
+/// let point = influxdb2::point!("", fields: { usage: 0.3 });
+/// ```
+#[macro_export]
+macro_rules! point {
+    (
+        $measurement:tt
+        $(, tags: { $($tag:tt : $tag_value:expr),* $(,)? })?
+        , fields: { $($field:tt : $field_value:expr),+ $(,)? }
+        $(, ts: $($ts:tt)+)?
+    ) => {
+        $crate::models::DataPoint::builder($crate::__point_measurement!($measurement))
+            $($(.tag($crate::__point_key!($tag), $tag_value))*)?
+            $(.field($crate::__point_key!($field), $field_value))+
+            $(.timestamp($crate::__point_ts!($($ts)+)))?
+            .build()
+            .expect("point! always sets a field")
+    };
+}
 
-        for (k, v) in &self.tags {
-            w.write_all(b",")?;
-            k.write_tag_key_to(&mut w)?;
-            w.write_all(b"=")?;
-            v.write_tag_value_to(&mut w)?;
-        }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __point_measurement {
+    ($measurement:literal) => {{
+        const _: () = assert!(!$measurement.is_empty(), "the measurement is empty");
+        $measurement
+    }};
+    ($measurement:expr) => {
+        $measurement
+    };
+}
 
-        for (i, (k, v)) in self.fields.iter().enumerate() {
-            let d = if i == 0 { b" " } else { b"," };
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __point_key {
+    ($key:ident) => {
+        stringify!($key)
+    };
+    ($key:literal) => {
+        $key
+    };
+}
 
-            w.write_all(d)?;
-            k.write_field_key_to(&mut w)?;
-            w.write_all(b"=")?;
-            v.write_field_value_to(&mut w)?;
-        }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __point_ts {
+    (now $(,)?) => {
+        $crate::models::data_point::now_nanos()
+    };
+    (now($clock:expr) $(,)?) => {
+        $crate::models::data_point::clock_nanos(&$clock)
+    };
+    ($ts:expr $(,)?) => {
+        $ts
+    };
+}
 
-        if let Some(ts) = self.timestamp {
-            w.write_all(b" ")?;
-            ts.write_timestamp_to(&mut w)?;
-        }
+/// The current time in nanoseconds since the UNIX epoch, for `point!`
+#[doc(hidden)]
+pub fn now_nanos() -> i64 {
+    chrono::Utc::now().timestamp_nanos()
+}
 
-        w.write_all(b"\n")?;
+/// The time of `clock` in nanoseconds since the UNIX epoch, for `point!`
+#[doc(hidden)]
+pub fn clock_nanos<C: crate::clock::Clock + ?Sized>(clock: &C) -> i64 {
+    clock.now().timestamp_nanos()
+}
 
-        Ok(())
+impl WriteDataPoint for DataPoint {
+    fn write_data_point_to<W>(&self, w: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        write_line(
+            &self.measurement,
+            self.tags.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+            self.fields.iter().map(|(k, v)| (k.as_str(), v)),
+            self.timestamp,
+            w,
+        )
+    }
+
+    fn write_renamed_data_point_to<W>(&self, naming: &dyn Naming, w: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        // Collected again so renamed keys stay sorted, and colliding ones
+        // keep the value written last
+        let tags: BTreeMap<Cow<'_, str>, &str> = self
+            .tags
+            .iter()
+            .map(|(k, v)| (naming.tag_key(k), v.as_str()))
+            .collect();
+        let fields: BTreeMap<Cow<'_, str>, &FieldValue> = self
+            .fields
+            .iter()
+            .map(|(k, v)| (naming.field_key(k), v))
+            .collect();
+        write_line(
+            &naming.measurement(&self.measurement),
+            tags.iter().map(|(k, v)| (k.as_ref(), *v)),
+            fields.iter().map(|(k, v)| (k.as_ref(), *v)),
+            self.timestamp,
+            w,
+        )
+    }
+
+    fn timestamp(&self) -> Option<i64> {
+        self.timestamp
     }
 }
 
@@ -149,6 +280,8 @@ pub enum FieldValue {
     F64(f64),
     /// A 64-bit signed integer number
     I64(i64),
+    /// A 64-bit unsigned integer number, written with the `u` suffix
+    U64(u64),
     /// A string value
     String(String),
 }
@@ -197,6 +330,31 @@ pub trait WriteDataPoint {
     fn write_data_point_to<W>(&self, w: W) -> io::Result<()>
     where
         W: io::Write;
+
+    /// Write this data point as line protocol with the names `naming` gives,
+    /// for `ClientBuilder::with_naming`. By default the line protocol of
+    /// `write_data_point_to` is parsed back to rename its points.
+    fn write_renamed_data_point_to<W>(&self, naming: &dyn Naming, mut w: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let mut line = Vec::new();
+        self.write_data_point_to(&mut line)?;
+        let text =
+            std::str::from_utf8(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let points = parse(text, Precision::Nanoseconds)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for point in points {
+            point.write_renamed_data_point_to(naming, &mut w)?;
+        }
+        Ok(())
+    }
+
+    /// The timestamp this data point is written with, which
+    /// `ClientBuilder::with_timestamp_check` checks. `None` by default.
+    fn timestamp(&self) -> Option<i64> {
+        None
+    }
 }
 
 // The following are traits rather than free functions so that we can limit
@@ -281,6 +439,7 @@ impl WriteFieldValue for FieldValue {
             Bool(v) => write!(w, "{}", if *v { "t" } else { "f" }),
             F64(v) => write!(w, "{}", v),
             I64(v) => write!(w, "{}i", v),
+            U64(v) => write!(w, "{}u", v),
             String(v) => {
                 w.write_all(br#"""#)?;
                 escape_and_write_value(v, FIELD_VALUE_STRING_DELIMITERS, &mut w)?;
@@ -305,6 +464,44 @@ impl WriteTimestamp for i64 {
     }
 }
 
+fn write_line<'a, W>(
+    measurement: &str,
+    tags: impl Iterator<Item = (&'a str, &'a str)>,
+    fields: impl Iterator<Item = (&'a str, &'a FieldValue)>,
+    timestamp: Option<i64>,
+    mut w: W,
+) -> io::Result<()>
+where
+    W: io::Write,
+{
+    measurement.write_measurement_to(&mut w)?;
+
+    for (k, v) in tags {
+        w.write_all(b",")?;
+        k.write_tag_key_to(&mut w)?;
+        w.write_all(b"=")?;
+        v.write_tag_value_to(&mut w)?;
+    }
+
+    for (i, (k, v)) in fields.enumerate() {
+        let d = if i == 0 { b" " } else { b"," };
+
+        w.write_all(d)?;
+        k.write_field_key_to(&mut w)?;
+        w.write_all(b"=")?;
+        v.write_field_value_to(&mut w)?;
+    }
+
+    if let Some(ts) = timestamp {
+        w.write_all(b" ")?;
+        ts.write_timestamp_to(&mut w)?;
+    }
+
+    w.write_all(b"\n")?;
+
+    Ok(())
+}
+
 const MEASUREMENT_DELIMITERS: &[char] = &[',', ' '];
 const TAG_KEY_DELIMITERS: &[char] = &[',', '=', ' '];
 const TAG_VALUE_DELIMITERS: &[char] = TAG_KEY_DELIMITERS;
@@ -327,7 +524,7 @@ where
         last = idx + delim.len();
     }
 
-    w.write_all(value[last..].as_bytes())
+    w.write_all(&value.as_bytes()[last..])
 }
 
 #[cfg(test)]
@@ -345,6 +542,42 @@ mod tests {
         );
     }
 
+
+    #[test]
+    fn point_macro_expands_to_the_builder() {
+        let region = String::from("us-west");
+        let point = point!(
+            "cpu",
+            tags: { host: "server01", "region name": region },
+            fields: { usage: 0.5, count: 3_i64, "is-up": true },
+            ts: 1_600_000_000_000_000_000
+        );
+
+        assert_eq!(
+            point,
+            DataPoint::builder("cpu")
+                .tag("host", "server01")
+                .tag("region name", "us-west")
+                .field("usage", 0.5)
+                .field("count", 3_i64)
+                .field("is-up", true)
+                .timestamp(1_600_000_000_000_000_000)
+                .build()
+                .unwrap()
+        );
+
+        let measurement = "mem";
+        let now = point!(measurement, fields: { used: 1_i64 }, ts: now,);
+        assert_eq!(now.measurement(), "mem");
+        assert!(now.timestamp().unwrap() > 1_600_000_000_000_000_000);
+        assert_eq!(point!("disk", fields: { free: 2.0 }).timestamp(), None);
+
+        use chrono::{TimeZone, Utc};
+        let clock = crate::clock::MockClock::new(Utc.timestamp_opt(1_700_000_000, 5).unwrap());
+        let then = point!("mem", fields: { used: 1_i64 }, ts: now(clock));
+        assert_eq!(then.timestamp(), Some(1_700_000_000_000_000_005));
+    }
+
     #[test]
     fn point_builder_allows_setting_tags_and_fields() {
         let point = DataPoint::builder("swap")
@@ -463,6 +696,12 @@ mod tests {
         assert_utf8_strings_eq(&e.field_value_to_vec().unwrap(), b"42i");
     }
 
+    #[test]
+    fn field_value_of_unsigned_integer() {
+        let e = FieldValue::U64(u64::MAX);
+        assert_utf8_strings_eq(&e.field_value_to_vec().unwrap(), b"18446744073709551615u");
+    }
+
     #[test]
     fn field_value_of_string() {
         let e = FieldValue::from("hello");
@@ -0,0 +1,37 @@
+//! DBRP mappings
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{BucketId, DbrpId, OrgId};
+
+/// A mapping of an InfluxDB 1.x database and retention policy to a bucket,
+/// used by the 1.x compatibility API
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dbrp {
+    /// Mapping ID
+    pub id: DbrpId,
+    /// ID of the organization owning the mapping
+    #[serde(rename = "orgID")]
+    pub org_id: OrgId,
+    /// ID of the bucket the mapping points to
+    #[serde(rename = "bucketID")]
+    pub bucket_id: BucketId,
+    /// Name of the database
+    pub database: String,
+    /// Name of the retention policy
+    pub retention_policy: String,
+    /// Whether this is the retention policy of the database used when a
+    /// request names none
+    pub default: bool,
+    /// Whether the server made the mapping up from the name of the bucket
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+}
+
+/// List of DBRP mappings
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Dbrps {
+    /// The mappings
+    #[serde(default)]
+    pub content: Vec<Dbrp>,
+}
@@ -0,0 +1,236 @@
+//! Durations
+//!
+//! A non-negative duration written the way Flux and the `influx` CLI write
+//! them, such as `30d` or `1h30m`, and sent to the API as whole seconds.
+//!
+//! ```
+//! use influxdb2::models::FluxDuration;
+//!
+//! let retention: FluxDuration = "1h30m".parse().unwrap();
+//! assert_eq!(retention.as_secs(), 5400);
+//! assert_eq!(retention.to_string(), "1h30m");
+//! assert_eq!(FluxDuration::from_secs(30 * 86_400).to_string(), "30d");
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use snafu::{ensure, OptionExt, Snafu};
+
+/// Errors that occur while parsing a duration
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum ParseDurationError {
+    /// The text is empty.
+    #[snafu(display("Empty duration"))]
+    Empty,
+
+    /// A unit without a number before it.
+    #[snafu(display("Missing number before {:?} in duration {:?}", unit, text))]
+    MissingNumber {
+        /// The unit
+        unit: String,
+        /// The duration
+        text: String,
+    },
+
+    /// A unit that isn't `ns`, `us`, `µs`, `ms`, `s`, `m`, `h`, `d` or `w`.
+    ///
+    /// Months and years aren't accepted, since their length varies.
+    #[snafu(display("Unknown unit {:?} in duration {:?}", unit, text))]
+    UnknownUnit {
+        /// The unit
+        unit: String,
+        /// The duration
+        text: String,
+    },
+
+    /// The duration doesn't fit in 64 bits of seconds.
+    #[snafu(display("Duration {:?} is too long", text))]
+    Overflow {
+        /// The duration
+        text: String,
+    },
+}
+
+/// The units of a duration with their length in nanoseconds, longest first
+const UNITS: &[(&str, u128)] = &[
+    ("w", 7 * 86_400 * NANOS_PER_SECOND),
+    ("d", 86_400 * NANOS_PER_SECOND),
+    ("h", 3_600 * NANOS_PER_SECOND),
+    ("m", 60 * NANOS_PER_SECOND),
+    ("s", NANOS_PER_SECOND),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ns", 1),
+];
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// A duration such as `30d` or `1h30m`
+///
+/// Parses from and displays as Flux duration literals. Serializes as whole
+/// seconds, the unit of the retention fields of the API, so sub-second parts
+/// are dropped on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FluxDuration(Duration);
+
+impl FluxDuration {
+    /// The empty duration, which as a retention period means forever.
+    pub const ZERO: Self = Self(Duration::from_secs(0));
+
+    /// A duration of `secs` seconds.
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+
+    /// The number of whole seconds.
+    pub const fn as_secs(&self) -> u64 {
+        self.0.as_secs()
+    }
+
+    /// The duration as a `std::time::Duration`.
+    pub const fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<Duration> for FluxDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<FluxDuration> for Duration {
+    fn from(duration: FluxDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for FluxDuration {
+    type Err = ParseDurationError;
+
+    fn from_str(text: &str) -> Result<Self, ParseDurationError> {
+        ensure!(!text.is_empty(), EmptySnafu);
+
+        let mut nanos: u128 = 0;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (number, after_number) = rest.split_at(digits);
+            let units = after_number
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(after_number.len());
+            let (unit, after_unit) = after_number.split_at(units);
+
+            ensure!(!number.is_empty(), MissingNumberSnafu { unit, text });
+            let (_, length) = UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .context(UnknownUnitSnafu { unit, text })?;
+            nanos = number
+                .parse::<u128>()
+                .ok()
+                .and_then(|number| number.checked_mul(*length))
+                .and_then(|part| nanos.checked_add(part))
+                .context(OverflowSnafu { text })?;
+            rest = after_unit;
+        }
+
+        let secs = u64::try_from(nanos / NANOS_PER_SECOND)
+            .ok()
+            .context(OverflowSnafu { text })?;
+        let subsec = (nanos % NANOS_PER_SECOND) as u32;
+        Ok(Self(Duration::new(secs, subsec)))
+    }
+}
+
+impl fmt::Display for FluxDuration {
+    /// Writes the shortest literal, with days as the longest unit, or `0s`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return f.write_str("0s");
+        }
+        for (unit, length) in UNITS.iter().skip(1).filter(|(unit, _)| *unit != "µs") {
+            if nanos >= *length {
+                write!(f, "{}{}", nanos / length, unit)?;
+                nanos %= length;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for FluxDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_secs())
+    }
+}
+
+impl<'de> Deserialize<'de> for FluxDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(Self::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<FluxDuration, ParseDurationError> {
+        text.parse()
+    }
+
+    #[test]
+    fn parses_and_formats() {
+        assert_eq!(parse("30d").unwrap().as_secs(), 30 * 86_400);
+        assert_eq!(parse("1h30m").unwrap().as_secs(), 5_400);
+        assert_eq!(parse("2w").unwrap().to_string(), "14d");
+        assert_eq!(parse("90m").unwrap().to_string(), "1h30m");
+        assert_eq!(parse("1500ms").unwrap().to_string(), "1s500ms");
+        assert_eq!(parse("3µs").unwrap().to_string(), "3us");
+        assert_eq!(parse("0s").unwrap(), FluxDuration::ZERO);
+        assert_eq!(FluxDuration::ZERO.to_string(), "0s");
+        assert_eq!(
+            FluxDuration::from(Duration::from_nanos(86_400_000_000_001)).to_string(),
+            "1d1ns"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert_eq!(parse(""), Err(ParseDurationError::Empty));
+        assert!(matches!(
+            parse("h"),
+            Err(ParseDurationError::MissingNumber { unit, .. }) if unit == "h"
+        ));
+        assert!(matches!(
+            parse("1mo"),
+            Err(ParseDurationError::UnknownUnit { unit, .. }) if unit == "mo"
+        ));
+        assert!(matches!(
+            parse("10"),
+            Err(ParseDurationError::UnknownUnit { .. })
+        ));
+        assert!(matches!(
+            parse("99999999999999999999999w"),
+            Err(ParseDurationError::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn serializes_as_seconds() {
+        let retention = parse("1h").unwrap();
+        assert_eq!(serde_json::to_string(&retention).unwrap(), "3600");
+        assert_eq!(
+            serde_json::from_str::<FluxDuration>("3600").unwrap(),
+            retention
+        );
+    }
+}
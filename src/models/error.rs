@@ -0,0 +1,18 @@
+//! Errors
+
+use serde::{Deserialize, Serialize};
+
+/// The JSON body the server answers a failed request with
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// Machine-readable code, e.g. `not found` or `unauthorized`
+    pub code: String,
+    /// Human-readable message
+    pub message: String,
+    /// The operation that failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub op: Option<String>,
+    /// The error that caused the failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub err: Option<String>,
+}
@@ -15,10 +15,18 @@ pub struct File {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package: Option<crate::models::ast::PackageClause>,
     /// A list of package imports
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub imports: Vec<crate::models::ast::ImportDeclaration>,
     /// List of Flux statements
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(
+        default,
+        deserialize_with = "crate::models::ast::expression::nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub body: Vec<crate::models::ast::Statement>,
 }
 
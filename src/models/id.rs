@@ -0,0 +1,164 @@
+//! Resource IDs
+//!
+//! Distinct types for the IDs of organizations, buckets, tasks, labels and
+//! users, so one can't be passed where another is expected:
+//!
+//! ```
+//! use influxdb2::models::{BucketId, OrgId};
+//!
+//! let org: OrgId = "0123456789abcdef".parse().unwrap();
+//! assert_eq!(org.to_string(), "0123456789abcdef");
+//! assert!("not-an-id".parse::<BucketId>().is_err());
+//! ```
+//!
+//! Parsing checks for the 16 hexadecimal digits of InfluxDB IDs, whereas
+//! `From<String>` and deserializing take the ID as it is, for IDs that come
+//! from the server or from configuration.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, Snafu};
+
+/// The error returned when parsing an ID that isn't 16 hexadecimal digits
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(display("Invalid ID {:?}: expected 16 hexadecimal digits", id))]
+pub struct ParseIdError {
+    /// The text that was parsed
+    pub id: String,
+}
+
+const ID_LENGTH: usize = 16;
+
+macro_rules! id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            /// Wrap `id` without checking it.
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            /// The ID as sent to the server.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIdError;
+
+            fn from_str(id: &str) -> Result<Self, ParseIdError> {
+                ensure!(
+                    id.len() == ID_LENGTH && id.bytes().all(|b| b.is_ascii_hexdigit()),
+                    ParseIdSnafu { id }
+                );
+                Ok(Self(id.to_string()))
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+    };
+}
+
+id!(
+    /// The ID of an organization
+    OrgId
+);
+id!(
+    /// The ID of a bucket
+    BucketId
+);
+id!(
+    /// The ID of a task
+    TaskId
+);
+id!(
+    /// The ID of a label
+    LabelId
+);
+id!(
+    /// The ID of a user
+    UserId
+);
+id!(
+    /// The ID of a Telegraf configuration
+    TelegrafId
+);
+id!(
+    /// The ID of a variable
+    VariableId
+);
+id!(
+    /// The ID of a dashboard
+    DashboardId
+);
+id!(
+    /// The ID of a DBRP mapping
+    DbrpId
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_serializes() {
+        let id: TaskId = "0a1B2c3D4e5F6a7b".parse().unwrap();
+        assert_eq!(id.as_str(), "0a1B2c3D4e5F6a7b");
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"0a1B2c3D4e5F6a7b\"");
+        assert_eq!(
+            serde_json::from_str::<TaskId>("\"0a1B2c3D4e5F6a7b\"").unwrap(),
+            id
+        );
+
+        for invalid in &[
+            "",
+            "0a1b2c3d4e5f6a7",
+            "0a1b2c3d4e5f6a7bc",
+            "0a1b2c3d4e5f6a7g",
+        ] {
+            assert_eq!(
+                invalid.parse::<LabelId>(),
+                Err(ParseIdError {
+                    id: invalid.to_string()
+                })
+            );
+        }
+    }
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 pub struct LabelCreateRequest {
     /// Organisation ID
     #[serde(rename = "orgID")]
-    pub org_id: String,
+    pub org_id: crate::models::OrgId,
     /// Label name
     pub name: String,
     /// Key/Value pairs associated with this label.
@@ -18,7 +18,7 @@ pub struct LabelCreateRequest {
 
 impl LabelCreateRequest {
     /// Return instance of LabelCreateRequest
-    pub fn new(org_id: String, name: String) -> Self {
+    pub fn new(org_id: crate::models::OrgId, name: String) -> Self {
         Self {
             org_id,
             name,
@@ -90,10 +90,10 @@ impl LabelUpdate {
 pub struct Label {
     /// Label ID
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<String>,
+    pub id: Option<crate::models::LabelId>,
     /// Org ID
     #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
-    pub org_id: Option<String>,
+    pub org_id: Option<crate::models::OrgId>,
     /// Label name
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -109,3 +109,29 @@ impl Label {
         Self::default()
     }
 }
+
+/// The desired state of a label, for `Client::ensure_labels`
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LabelSpec {
+    /// Label name
+    pub name: String,
+    /// Key/Value pairs associated with this label.
+    #[serde(default)]
+    pub properties: ::std::collections::HashMap<String, String>,
+}
+
+impl LabelSpec {
+    /// Returns an instance of LabelSpec without properties
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets a property, such as the `color` the UI shows the label in.
+    pub fn property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.insert(key.into(), value.into());
+        self
+    }
+}
@@ -4,37 +4,101 @@
 
 pub mod ast;
 
+#[cfg(feature = "management")]
 pub mod user;
+#[cfg(feature = "management")]
 pub use self::user::{User, UserLinks, Users, UsersLinks};
+#[cfg(feature = "management")]
 pub mod organization;
+#[cfg(feature = "management")]
 pub use self::organization::{Organization, OrganizationLinks, Organizations};
+#[cfg(feature = "management")]
 pub mod bucket;
-pub use self::bucket::{Bucket, BucketLinks, Buckets, PostBucketRequest};
+#[cfg(feature = "management")]
+pub use self::bucket::{Bucket, BucketLinks, Buckets, PatchBucketRequest, PostBucketRequest};
+#[cfg(feature = "management")]
 pub mod onboarding;
+#[cfg(feature = "management")]
 pub use self::onboarding::{IsOnboarding, OnboardingRequest, OnboardingResponse};
+#[cfg(feature = "management")]
 pub mod links;
+#[cfg(feature = "management")]
 pub use self::links::Links;
+#[cfg(feature = "management")]
 pub mod permission;
+#[cfg(feature = "management")]
 pub use self::permission::Permission;
+#[cfg(feature = "management")]
 pub mod label;
-pub use self::label::{Label, LabelCreateRequest, LabelResponse, LabelUpdate, LabelsResponse};
+#[cfg(feature = "management")]
+pub use self::label::{
+    Label, LabelCreateRequest, LabelResponse, LabelSpec, LabelUpdate, LabelsResponse,
+};
+#[cfg(feature = "management")]
 pub mod authorization;
-pub use self::authorization::{Authorization, AuthorizationAllOfLinks};
+#[cfg(feature = "management")]
+pub use self::authorization::{Authorization, AuthorizationAllOfLinks, Authorizations};
+#[cfg(feature = "management")]
 pub mod resource;
+#[cfg(feature = "management")]
 pub use self::resource::Resource;
+#[cfg(feature = "management")]
+pub mod id;
+#[cfg(feature = "management")]
+pub use self::id::{
+    BucketId, DashboardId, DbrpId, LabelId, OrgId, ParseIdError, TaskId, TelegrafId, UserId, VariableId,
+};
+#[cfg(feature = "management")]
+pub mod operation_log;
+#[cfg(feature = "management")]
+pub use self::operation_log::{OperationLog, OperationLogLinks, OperationLogs};
+#[cfg(feature = "management")]
+pub mod telegraf;
+#[cfg(feature = "management")]
+pub use self::telegraf::{Telegraf, TelegrafMetadata, TelegrafRequest, Telegrafs};
+#[cfg(feature = "telegraf-toml")]
+pub use self::telegraf::TelegrafConfig;
+#[cfg(feature = "management")]
+pub mod dbrp;
+#[cfg(feature = "management")]
+pub use self::dbrp::{Dbrp, Dbrps};
+#[cfg(feature = "management")]
+pub mod variable;
+#[cfg(feature = "management")]
+pub use self::variable::{Variable, VariableArguments, VariableChoice, VariableQuery, Variables};
+#[cfg(feature = "management")]
+pub mod templates;
+#[cfg(feature = "management")]
+pub use self::templates::{
+    Template, TemplateExportRequest, TemplateExportResource, TemplateMetadata, TemplateObject,
+};
+#[cfg(feature = "management")]
 pub mod retention_rule;
+#[cfg(feature = "management")]
 pub use self::retention_rule::RetentionRule;
 pub mod query;
 pub use self::query::{
     AnalyzeQueryResponse, AnalyzeQueryResponseErrors, AstResponse, 
-    FluxSuggestion, FluxSuggestions, LanguageRequest, Query,
+    FluxSuggestion, FluxSuggestions, LanguageRequest, Profiler, Query,
 };
 pub mod file;
 pub use self::file::File;
+pub mod error;
+pub use self::error::ErrorResponse;
 pub mod health;
 pub use self::health::{HealthCheck, Status};
+pub mod duration;
+pub use self::duration::{FluxDuration, ParseDurationError};
+pub mod schedule;
+pub use self::schedule::ScheduleError;
+pub mod continuous_query;
+pub use self::continuous_query::{ContinuousQuery, ContinuousQueryError};
+pub mod template;
+pub use self::template::{QueryTemplate, TemplateError, TemplateValue};
 pub mod data_point;
 pub use data_point::{DataPoint, FieldValue, WriteDataPoint};
+#[cfg(feature = "management")]
 pub mod task;
-pub use task::{Task, Tasks, TaskStatusType};
+#[cfg(feature = "management")]
+pub use task::{LogEvent, Logs, Run, RunStatus, Runs, Task, Tasks, TaskStatusType};
 
@@ -33,9 +33,9 @@ pub struct OnboardingRequest {
     /// Initial password of user
     #[serde(skip_serializing_if = "Option::is_none")]
     pub password: Option<String>,
-    /// Retention period in nanoseconds
+    /// Retention period, sent in seconds
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub retention_period_seconds: Option<i32>,
+    pub retention_period_seconds: Option<crate::models::FluxDuration>,
     /// Retention period *in nanoseconds* for the new bucket. This key's name
     /// has been misleading since OSS 2.0 GA, please transition to use
     /// `retentionPeriodSeconds`
@@ -0,0 +1,38 @@
+//! Operation logs
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A change made to a resource
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OperationLog {
+    /// A description of the change
+    pub description: String,
+    /// When the change was made
+    pub time: DateTime<Utc>,
+    /// The ID of the user who made the change
+    #[serde(rename = "userID", skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<crate::models::UserId>,
+    /// Operation log links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<OperationLogLinks>,
+}
+
+/// OperationLogLinks
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OperationLogLinks {
+    /// The user who made the change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// A page of the operation log of a resource
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OperationLogs {
+    /// The changes, oldest first unless requested in descending order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<OperationLog>,
+    /// Links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<crate::models::Links>,
+}
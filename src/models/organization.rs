@@ -11,7 +11,7 @@ pub struct Organization {
     pub links: Option<crate::models::OrganizationLinks>,
     /// Organization ID
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<String>,
+    pub id: Option<crate::models::OrgId>,
     /// Organization Name
     pub name: String,
     /// Organization description
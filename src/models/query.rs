@@ -3,10 +3,26 @@
 use crate::models::ast::Package;
 use crate::models::ast::dialect::Annotations;
 use crate::models::File;
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 
 /// Query influx using the Flux language
+///
+/// Besides the script, a query carries the options of its request, set with
+/// the `with_*` methods:
+///
+/// ```
+/// use influxdb2::models::{Profiler, Query};
+/// use std::time::Duration;
+///
+/// let query = Query::new(r#"from(bucket: params.bucket) |> range(start: -1h)"#.to_string())
+///     .with_param("bucket", "telegraf")
+///     .with_timeout(Duration::from_secs(30))
+///     .with_profilers(&[Profiler::Query]);
+/// ```
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Query {
     /// Query Script
@@ -24,6 +40,16 @@ pub struct Query {
     /// Default is the server's now time.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub now: Option<String>,
+    /// Values the script reads as `params.<name>`, supported by InfluxDB
+    /// Cloud
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, serde_json::Value>,
+    /// Timeout of the request, replacing the query timeout of the client
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+    /// Flux profilers to enable, whose results come as extra tables
+    #[serde(skip)]
+    pub profilers: Vec<Profiler>,
 }
 
 impl Query {
@@ -34,8 +60,55 @@ impl Query {
             ..Default::default()
         }
     }
+
+    /// Bind `value` to `params.<name>`, replacing any earlier value.
+    pub fn with_param(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.params.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the dialect of the CSV response.
+    pub fn with_dialect(mut self, dialect: crate::models::ast::Dialect) -> Self {
+        self.dialect = Some(dialect);
+        self
+    }
+
+    /// Run the query as if it were `now`.
+    pub fn with_now(mut self, now: impl Into<DateTime<Utc>>) -> Self {
+        self.now = Some(now.into().to_rfc3339_opts(SecondsFormat::AutoSi, true));
+        self
+    }
+
+    /// Give up on the request after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Enable the Flux `profilers`.
+    pub fn with_profilers(mut self, profilers: &[Profiler]) -> Self {
+        self.profilers = profilers.to_vec();
+        self
+    }
+
+    /// The script sent, with the option enabling the profilers prepended.
+    pub(crate) fn script(&self) -> Cow<'_, str> {
+        if self.profilers.is_empty() {
+            return Cow::Borrowed(&self.query);
+        }
+        let profilers: Vec<_> = self
+            .profilers
+            .iter()
+            .map(|profiler| format!("\"{}\"", profiler.as_str()))
+            .collect();
+        Cow::Owned(format!(
+            "import \"profiler\"\n\noption profiler.enabledProfilers = [{}]\n\n{}",
+            profilers.join(", "),
+            self.query
+        ))
+    }
 }
-    
+
 impl Default for Query {
     fn default() -> Self {
         Self {
@@ -47,6 +120,28 @@ impl Default for Query {
                 ..Default::default()
             }),
             now: None,
+            params: BTreeMap::new(),
+            timeout: None,
+            profilers: Vec::new(),
+        }
+    }
+}
+
+/// A Flux profiler, see the `profiler` package
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Profiler {
+    /// Statistics of the whole query, in the `profiler/query` table
+    Query,
+    /// Statistics of every operation, in the `profiler/operator` table
+    Operator,
+}
+
+impl Profiler {
+    /// The name Flux knows the profiler by.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Operator => "operator",
         }
     }
 }
@@ -20,7 +20,7 @@ pub struct Resource {
     /// org. if it is not set it is a permission for all resources of that
     /// resource type.
     #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
-    pub org_id: Option<String>,
+    pub org_id: Option<crate::models::OrgId>,
     /// Optional name of the organization of the organization with orgID.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub org: Option<String>,
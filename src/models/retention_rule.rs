@@ -1,7 +1,11 @@
 //! Retention Rules
 
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
+use crate::models::{FluxDuration, ParseDurationError};
+
 /// RetentionRule
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -9,23 +13,43 @@ pub struct RetentionRule {
     /// Expiry
     #[serde(rename = "type")]
     pub r#type: Type,
-    /// Duration in seconds for how long data will be kept in the database. 0
+    /// How long data will be kept in the database, sent in seconds. Zero
     /// means infinite.
-    pub every_seconds: i32,
-    /// Shard duration measured in seconds.
+    pub every_seconds: FluxDuration,
+    /// Shard duration, sent in seconds.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub shard_group_duration_seconds: Option<i64>,
+    pub shard_group_duration_seconds: Option<FluxDuration>,
 }
 
 impl RetentionRule {
     /// Returns instance of RetentionRule
-    pub fn new(r#type: Type, every_seconds: i32) -> Self {
+    pub fn new(r#type: Type, every_seconds: FluxDuration) -> Self {
         Self {
             r#type,
             every_seconds,
             shard_group_duration_seconds: None,
         }
     }
+
+    /// Sets the shard group duration.
+    pub fn with_shard_group_duration(mut self, duration: FluxDuration) -> Self {
+        self.shard_group_duration_seconds = Some(duration);
+        self
+    }
+}
+
+impl FromStr for RetentionRule {
+    type Err = ParseDurationError;
+
+    /// Parses a retention period such as `72h` or `30d`, or `inf` to keep
+    /// data forever.
+    fn from_str(retention: &str) -> Result<Self, ParseDurationError> {
+        let every = match retention {
+            "inf" => FluxDuration::ZERO,
+            _ => retention.parse()?,
+        };
+        Ok(Self::new(Type::Expire, every))
+    }
 }
 
 /// Set Retention Rule expired or not
@@ -35,3 +59,20 @@ pub enum Type {
     /// RetentionRule Expired
     Expire,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_retention_periods() {
+        let rule: RetentionRule = "72h".parse().unwrap();
+        assert_eq!(rule.every_seconds, FluxDuration::from_secs(72 * 3600));
+        assert_eq!(rule.shard_group_duration_seconds, None);
+
+        let rule: RetentionRule = "inf".parse().unwrap();
+        assert_eq!(rule.every_seconds, FluxDuration::ZERO);
+
+        assert!("30 days".parse::<RetentionRule>().is_err());
+    }
+}
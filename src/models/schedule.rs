@@ -0,0 +1,319 @@
+//! Task schedules
+//!
+//! Client-side checks of the `every`, `offset` and `cron` options of a
+//! task, so a malformed schedule is reported with the offending token
+//! before the task is sent.
+//!
+//! ```
+//! use influxdb2::models::schedule::{validate_cron, ScheduleError};
+//!
+//! assert!(validate_cron("0 */2 * * MON-FRI").is_ok());
+//! assert!(matches!(
+//!     validate_cron("0 25 * * *"),
+//!     Err(ScheduleError::InvalidCron { token, .. }) if token == "25"
+//! ));
+//! ```
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+
+use crate::models::{FluxDuration, ParseDurationError};
+
+/// Errors in the schedule of a task
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// An `every` or `offset` option that isn't a duration literal.
+    #[snafu(display("Invalid {} duration {:?}: {}", option, token, source))]
+    InvalidDuration {
+        /// The option, `every` or `offset`
+        option: String,
+        /// The value of the option
+        token: String,
+        /// The parse error
+        source: ParseDurationError,
+    },
+
+    /// A `cron` option that isn't a valid cron expression.
+    #[snafu(display("Invalid token {:?} in cron expression {:?}", token, expression))]
+    InvalidCron {
+        /// The expression
+        expression: String,
+        /// The field or part of a field that is invalid
+        token: String,
+    },
+
+    /// A task scheduled with both `every` and `cron`.
+    #[snafu(display("A task can't set both every and cron"))]
+    ConflictingSchedule,
+}
+
+/// The fields of a cron expression with their bounds and value names
+struct Field {
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+    /// Whether `?` may stand for "no specific value"
+    any: bool,
+}
+
+const SECOND: Field = Field {
+    min: 0,
+    max: 59,
+    names: &[],
+    any: false,
+};
+const MINUTE: Field = SECOND;
+const HOUR: Field = Field {
+    min: 0,
+    max: 23,
+    names: &[],
+    any: false,
+};
+const DAY_OF_MONTH: Field = Field {
+    min: 1,
+    max: 31,
+    names: &[],
+    any: true,
+};
+const MONTH: Field = Field {
+    min: 1,
+    max: 12,
+    names: &[
+        "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+    ],
+    any: false,
+};
+const DAY_OF_WEEK: Field = Field {
+    min: 0,
+    max: 7,
+    names: &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"],
+    any: true,
+};
+const YEAR: Field = Field {
+    min: 1970,
+    max: 2099,
+    names: &[],
+    any: false,
+};
+
+const MACROS: &[&str] = &[
+    "@yearly",
+    "@annually",
+    "@monthly",
+    "@weekly",
+    "@daily",
+    "@midnight",
+    "@hourly",
+];
+
+/// Check `expression` the way the task scheduler of InfluxDB parses it:
+/// five fields from minutes to days of the week, optionally preceded by
+/// seconds and followed by years, or a macro such as `@daily` or
+/// `@every 1h`.
+pub fn validate_cron(expression: &str) -> Result<(), ScheduleError> {
+    let invalid = |token| InvalidCronSnafu { expression, token };
+
+    let trimmed = expression.trim();
+    if trimmed.starts_with('@') {
+        if MACROS.iter().any(|m| m.eq_ignore_ascii_case(trimmed)) {
+            return Ok(());
+        }
+        let duration = trimmed.strip_prefix("@every").context(invalid(trimmed))?;
+        let duration = duration.trim();
+        return duration
+            .parse::<FluxDuration>()
+            .map(|_| ())
+            .ok()
+            .context(invalid(duration));
+    }
+
+    let tokens: Vec<&str> = trimmed.split_whitespace().collect();
+    let fields: &[&Field] = match tokens.len() {
+        5 => &[&MINUTE, &HOUR, &DAY_OF_MONTH, &MONTH, &DAY_OF_WEEK],
+        6 => &[&SECOND, &MINUTE, &HOUR, &DAY_OF_MONTH, &MONTH, &DAY_OF_WEEK],
+        7 => &[
+            &SECOND,
+            &MINUTE,
+            &HOUR,
+            &DAY_OF_MONTH,
+            &MONTH,
+            &DAY_OF_WEEK,
+            &YEAR,
+        ],
+        _ => return invalid(trimmed).fail(),
+    };
+
+    for (token, field) in tokens.iter().zip(fields) {
+        for item in token.split(',') {
+            ensure!(valid_item(item, field), invalid(item));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `item` is `*`, `?`, a value or a range, with an optional step
+fn valid_item(item: &str, field: &Field) -> bool {
+    let (range, step) = match item.split_once('/') {
+        Some((range, step)) => (range, Some(step)),
+        None => (item, None),
+    };
+    if let Some(step) = step {
+        match step.parse::<u32>() {
+            Ok(step) if step > 0 => {}
+            _ => return false,
+        }
+    }
+
+    match range {
+        "*" => true,
+        "?" => field.any && step.is_none(),
+        _ => match range.split_once('-') {
+            Some((low, high)) => match (value(low, field), value(high, field)) {
+                (Some(low), Some(high)) => low <= high,
+                _ => false,
+            },
+            None => value(range, field).is_some(),
+        },
+    }
+}
+
+fn value(text: &str, field: &Field) -> Option<u32> {
+    if let Some(index) = field
+        .names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(text))
+    {
+        return Some(field.min + index as u32);
+    }
+    text.parse()
+        .ok()
+        .filter(|value| (field.min..=field.max).contains(value))
+}
+
+/// Check the schedule in the `option task = {...}` block of `flux`, if it
+/// has one.
+pub fn validate_task_options(flux: &str) -> Result<(), ScheduleError> {
+    let options = match task_options(flux) {
+        Some(options) => options,
+        None => return Ok(()),
+    };
+
+    let (mut every, mut cron) = (false, false);
+    for (key, value) in options {
+        match key {
+            "every" | "offset" => {
+                value
+                    .parse::<FluxDuration>()
+                    .context(InvalidDurationSnafu {
+                        option: key,
+                        token: value,
+                    })?;
+                every |= key == "every";
+            }
+            "cron" => {
+                let expression = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .context(InvalidCronSnafu {
+                        expression: value,
+                        token: value,
+                    })?;
+                validate_cron(expression)?;
+                cron = true;
+            }
+            _ => {}
+        }
+    }
+    ensure!(!(every && cron), ConflictingScheduleSnafu);
+    Ok(())
+}
+
+/// The `key: value` pairs of the `option task` record of `flux`
+fn task_options(flux: &str) -> Option<Vec<(&str, &str)>> {
+    let start = flux.find("option task")?;
+    let record = flux[start + "option task".len()..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start()
+        .strip_prefix('{')?;
+
+    let mut options = Vec::new();
+    let (mut quoted, mut escaped) = (false, false);
+    let mut item_start = 0;
+    for (i, c) in record.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' | '}' if !quoted => {
+                if let Some((key, value)) = record[item_start..i].split_once(':') {
+                    options.push((key.trim(), value.trim()));
+                }
+                if c == '}' {
+                    return Some(options);
+                }
+                item_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad_token(expression: &str) -> String {
+        match validate_cron(expression) {
+            Err(ScheduleError::InvalidCron { token, .. }) => token,
+            other => panic!("{:?} for {:?}", other, expression),
+        }
+    }
+
+    #[test]
+    fn validates_cron_expressions() {
+        for expression in &[
+            "0 * * * *",
+            "*/15 0-6,18-23 ? jan-MAR 1-5",
+            "30 0 12 1 * SUN-SAT",
+            "0 0 0 1 1 ? 2030",
+            "@daily",
+            "@every 1h30m",
+        ] {
+            assert_eq!(validate_cron(expression), Ok(()), "{}", expression);
+        }
+
+        assert_eq!(bad_token("* * * *"), "* * * *");
+        assert_eq!(bad_token("60 * * * *"), "60");
+        assert_eq!(bad_token("0 * 0 * *"), "0");
+        assert_eq!(bad_token("*/0 * * * *"), "*/0");
+        assert_eq!(bad_token("0 5-2 * * *"), "5-2");
+        assert_eq!(bad_token("0 0 * FOO *"), "FOO");
+        assert_eq!(bad_token("? * * * *"), "?");
+        assert_eq!(bad_token("@every soon"), "soon");
+        assert_eq!(bad_token("@often"), "@often");
+    }
+
+    #[test]
+    fn validates_task_options() {
+        let valid = "option task = {name: \"a, b}\", every: 1h, offset: 5m}\n\nx";
+        assert_eq!(validate_task_options(valid), Ok(()));
+        assert_eq!(validate_task_options("from(bucket: \"b\")"), Ok(()));
+
+        let err = validate_task_options("option task = {name: \"t\", every: 1hour}").unwrap_err();
+        assert!(
+            matches!(&err, ScheduleError::InvalidDuration { option, token, .. }
+                if option == "every" && token == "1hour"),
+            "{:?}",
+            err
+        );
+
+        let err = validate_task_options("option task = {name: \"t\", cron: \"0 0 32 * *\"}");
+        assert!(matches!(err, Err(ScheduleError::InvalidCron { token, .. }) if token == "32"));
+
+        assert_eq!(
+            validate_task_options("option task = {name: \"t\", every: 1h, cron: \"@daily\"}"),
+            Err(ScheduleError::ConflictingSchedule)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 
 //! Task
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Task status
@@ -19,17 +20,17 @@ pub enum TaskStatusType {
 #[serde(rename_all = "camelCase")]
 pub struct Task {
     /// Task ID
-    pub id: String,
+    pub id: crate::models::TaskId,
     /// Task name
     pub name: String,
     /// The ID of the organization that owns this task
     #[serde(rename = "orgID")]
-    pub org_id: String,
+    pub org_id: crate::models::OrgId,
     /// The FLUX script to run this task
     pub flux: String,
     /// The ID of the user who owns this task
     #[serde(rename = "ownerID")]
-    pub owner_id: Option<String>,
+    pub owner_id: Option<crate::models::UserId>,
     /// The name of the organization that owns this task
     pub org: Option<String>,
     /// Task status
@@ -50,9 +51,9 @@ pub struct Task {
     /// Task error on last run
     pub last_run_error: Option<String>,
     /// Status of task on last run
-    pub last_run_status: Option<String>,
-    /// Timestamp of latest scheduled, completed run, RFC3339
-    pub latest_completed: Option<String>,
+    pub last_run_status: Option<RunStatus>,
+    /// Timestamp of latest scheduled, completed run
+    pub latest_completed: Option<DateTime<Utc>>,
     /// Duration to delay after the schedule, before executing the task; 
     /// parsed from flux
     pub offset: Option<String>,
@@ -62,9 +63,9 @@ pub struct Task {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub labels: Vec<crate::models::Label>,
     /// Task created timestamp
-    pub created_at: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
     /// Task updated timestamp
-    pub updated_at: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// Task Links
@@ -101,6 +102,81 @@ pub struct Tasks {
     pub tasks: Vec<Task>,
 }
 
+/// Run status
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// The run is scheduled
+    Scheduled,
+    /// The run is running
+    Started,
+    /// The run failed
+    Failed,
+    /// The run succeeded
+    Success,
+    /// The run was canceled
+    Canceled,
+}
+
+impl RunStatus {
+    /// Whether the run is over, whatever its outcome.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Failed | Self::Success | Self::Canceled)
+    }
+}
+
+/// A run of a task
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Run {
+    /// Run ID
+    pub id: Option<String>,
+    /// The ID of the task of the run
+    #[serde(rename = "taskID")]
+    pub task_id: Option<crate::models::TaskId>,
+    /// Run status
+    pub status: Option<RunStatus>,
+    /// The time the run is scheduled for
+    pub scheduled_for: Option<DateTime<Utc>>,
+    /// The time the run started
+    pub started_at: Option<DateTime<Utc>>,
+    /// The time the run finished
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The time the run was manually requested
+    pub requested_at: Option<DateTime<Utc>>,
+    /// Log events of the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log: Vec<LogEvent>,
+}
+
+/// A log event of a task or of one of its runs
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LogEvent {
+    /// The ID of the run
+    #[serde(rename = "runID")]
+    pub run_id: Option<String>,
+    /// The time of the event
+    pub time: Option<DateTime<Utc>>,
+    /// The message of the event
+    pub message: Option<String>,
+}
+
+/// Log events
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Logs {
+    /// List of log events
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<LogEvent>,
+}
+
+/// Runs
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Runs {
+    /// List of runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<Run>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,5 +188,25 @@ mod tests {
         let v = serde_json::to_string(&TaskStatusType::Inactive).unwrap();
         assert_eq!(v, "\"inactive\"");
     }
+
+    #[test]
+    fn deserialize_run() {
+        let run: Run = serde_json::from_str(
+            r#"{"id":"0000000000000001","taskID":"0000000000000002","status":"failed",
+                "scheduledFor":"2023-01-02T03:04:05Z","startedAt":"2023-01-02T03:04:05.5Z",
+                "log":[{"runID":"0000000000000001","time":"2023-01-02T05:04:06+02:00",
+                        "message":"Started task from script"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(run.status, Some(RunStatus::Failed));
+        let scheduled_for = DateTime::parse_from_rfc3339("2023-01-02T03:04:05Z").unwrap();
+        assert_eq!(run.scheduled_for, Some(scheduled_for.into()));
+        assert_eq!(run.finished_at, None);
+        assert_eq!(
+            run.log[0].time,
+            Some(DateTime::parse_from_rfc3339("2023-01-02T03:04:06Z").unwrap().into())
+        );
+    }
 }
 
@@ -0,0 +1,236 @@
+//! Telegraf configurations
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Label, OrgId, TelegrafId};
+
+/// A Telegraf configuration stored in InfluxDB
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Telegraf {
+    /// Telegraf configuration ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<TelegrafId>,
+    /// ID of the organization owning the configuration
+    #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+    /// Name of the configuration
+    pub name: String,
+    /// Description of the configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Metadata of the configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<TelegrafMetadata>,
+    /// The configuration, in Telegraf's TOML format
+    #[serde(default)]
+    pub config: String,
+    /// Labels of the configuration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<Label>,
+}
+
+/// Metadata of a Telegraf configuration
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TelegrafMetadata {
+    /// The buckets the configuration writes to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buckets: Vec<String>,
+}
+
+/// List of Telegraf configurations
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Telegrafs {
+    /// Telegraf configurations
+    #[serde(default)]
+    pub configurations: Vec<Telegraf>,
+}
+
+/// Request to create or replace a Telegraf configuration
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TelegrafRequest {
+    /// Name of the configuration
+    pub name: String,
+    /// Description of the configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Metadata of the configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<TelegrafMetadata>,
+    /// The configuration, in Telegraf's TOML format
+    pub config: String,
+    /// ID of the organization owning the configuration
+    #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+}
+
+impl TelegrafRequest {
+    /// Returns a request for the configuration `config` named `name` in the
+    /// organization `org_id`
+    pub fn new(org_id: OrgId, name: String, config: String) -> Self {
+        Self {
+            name,
+            config,
+            org_id: Some(org_id),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Telegraf> for TelegrafRequest {
+    fn from(telegraf: Telegraf) -> Self {
+        Self {
+            name: telegraf.name,
+            description: telegraf.description,
+            metadata: telegraf.metadata,
+            config: telegraf.config,
+            org_id: telegraf.org_id,
+        }
+    }
+}
+
+#[cfg(feature = "telegraf-toml")]
+pub use self::config::TelegrafConfig;
+
+#[cfg(feature = "telegraf-toml")]
+mod config {
+    use std::str::FromStr;
+
+    use toml::value::{Table, Value};
+
+    use super::Telegraf;
+
+    /// A Telegraf configuration parsed as a TOML document
+    ///
+    /// Plugins are arrays of tables under their kind, as in
+    /// `[[inputs.cpu]]`, and can be added, changed or removed before the
+    /// configuration is written back with `to_toml`. Comments and the order
+    /// of the keys are not kept.
+    #[derive(Clone, Debug, PartialEq, Default)]
+    pub struct TelegrafConfig {
+        document: Table,
+    }
+
+    impl FromStr for TelegrafConfig {
+        type Err = toml::de::Error;
+
+        fn from_str(config: &str) -> Result<Self, Self::Err> {
+            Ok(Self {
+                document: toml::from_str(config)?,
+            })
+        }
+    }
+
+    impl TelegrafConfig {
+        /// The TOML document.
+        pub fn document(&self) -> &Table {
+            &self.document
+        }
+
+        /// The TOML document, to change it.
+        pub fn document_mut(&mut self) -> &mut Table {
+            &mut self.document
+        }
+
+        /// The plugins of `kind`, such as `inputs` or `outputs`, named `name`.
+        pub fn plugins(&self, kind: &str, name: &str) -> &[Value] {
+            self.document
+                .get(kind)
+                .and_then(|plugins| plugins.get(name))
+                .and_then(Value::as_array)
+                .map_or(&[], Vec::as_slice)
+        }
+
+        /// The plugins of `kind` named `name`, to add, change or remove some.
+        ///
+        /// The kind and the plugin array are created if they are missing, and
+        /// replace any value of another type in their place.
+        pub fn plugins_mut(&mut self, kind: &str, name: &str) -> &mut Vec<Value> {
+            let kind = self
+                .document
+                .entry(kind)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !kind.is_table() {
+                *kind = Value::Table(Table::new());
+            }
+            let plugins = match kind {
+                Value::Table(kind) => kind.entry(name).or_insert_with(|| Value::Array(Vec::new())),
+                _ => unreachable!(),
+            };
+            if !plugins.is_array() {
+                *plugins = Value::Array(Vec::new());
+            }
+            match plugins {
+                Value::Array(plugins) => plugins,
+                _ => unreachable!(),
+            }
+        }
+
+        /// The configuration in Telegraf's TOML format.
+        pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+            toml::to_string(&self.document)
+        }
+    }
+
+    impl Telegraf {
+        /// Parse the configuration as a TOML document.
+        pub fn parse_config(&self) -> Result<TelegrafConfig, toml::de::Error> {
+            self.config.parse()
+        }
+
+        /// Replace the configuration with `config`.
+        pub fn set_config(&mut self, config: &TelegrafConfig) -> Result<(), toml::ser::Error> {
+            self.config = config.to_toml()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "telegraf-toml"))]
+mod tests {
+    use super::*;
+    use toml::Value;
+
+    #[test]
+    fn patches_plugins() {
+        let mut telegraf = Telegraf {
+            config: r#"
+                [agent]
+                interval = "10s"
+
+                [[inputs.cpu]]
+                percpu = true
+
+                [[outputs.influxdb_v2]]
+                urls = ["http://localhost:8086"]
+            "#
+            .to_string(),
+            ..Telegraf::default()
+        };
+
+        let mut config = telegraf.parse_config().unwrap();
+        assert_eq!(config.plugins("inputs", "cpu").len(), 1);
+        assert!(config.plugins("inputs", "disk").is_empty());
+
+        config.plugins_mut("inputs", "cpu")[0]
+            .as_table_mut()
+            .unwrap()
+            .insert("percpu".to_string(), Value::Boolean(false));
+        config
+            .plugins_mut("inputs", "mem")
+            .push(Value::Table(Default::default()));
+        config.plugins_mut("outputs", "influxdb_v2").clear();
+        telegraf.set_config(&config).unwrap();
+
+        let patched = telegraf.parse_config().unwrap();
+        assert_eq!(
+            patched.document()["agent"]["interval"].as_str(),
+            Some("10s")
+        );
+        assert_eq!(
+            patched.plugins("inputs", "cpu")[0]["percpu"].as_bool(),
+            Some(false)
+        );
+        assert_eq!(patched.plugins("inputs", "mem").len(), 1);
+        assert!(patched.plugins("outputs", "influxdb_v2").is_empty());
+    }
+}
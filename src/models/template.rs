@@ -0,0 +1,347 @@
+//! Query templates
+//!
+//! A Flux script with named placeholders such as `{bucket}` or `{start}`,
+//! whose values are turned into Flux literals of their type when the
+//! template is rendered, so user input can't change the script around them:
+//!
+//! ```
+//! use influxdb2::models::{FluxDuration, QueryTemplate};
+//!
+//! let query = QueryTemplate::new(
+//!     "from(bucket: {bucket}) |> range(start: -{window}) |> filter(fn: (r) => r.ticker == {ticker})",
+//! )
+//! .bind("bucket", "stock-prices")
+//! .bind("window", FluxDuration::from_secs(7 * 86_400))
+//! .bind("ticker", "AA\"PL")
+//! .render()
+//! .unwrap();
+//! assert_eq!(
+//!     query,
+//!     "from(bucket: \"stock-prices\") |> range(start: -7d) |> filter(fn: (r) => r.ticker == \"AA\\\"PL\")"
+//! );
+//! ```
+//!
+//! Only braces around a bare identifier are placeholders; records such as
+//! `{name: "t"}` are kept as they are.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use snafu::{ensure, Snafu};
+
+use crate::models::{FluxDuration, Query};
+
+/// Errors that occur while rendering a query template
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A placeholder of the template has no value.
+    #[snafu(display("No value bound to the placeholder {{{}}}", name))]
+    Unbound {
+        /// The name of the placeholder
+        name: String,
+    },
+
+    /// A value is bound to a name that isn't a placeholder of the template.
+    #[snafu(display("The template has no placeholder {{{}}}", name))]
+    UnknownPlaceholder {
+        /// The name the value is bound to
+        name: String,
+    },
+
+    /// A float that is NaN or infinite, which Flux has no literal for.
+    #[snafu(display("The value of {{{}}} is not a finite number", name))]
+    NonFinite {
+        /// The name of the placeholder
+        name: String,
+    },
+}
+
+/// A value bound to a placeholder, rendered as a Flux literal of its type
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateValue {
+    /// A string literal, quoted and escaped
+    String(String),
+    /// An integer literal
+    Integer(i64),
+    /// A float literal, always with a decimal point
+    Float(f64),
+    /// `true` or `false`
+    Boolean(bool),
+    /// A duration literal such as `1h30m`
+    Duration(FluxDuration),
+    /// A time literal in RFC3339 format
+    Time(DateTime<FixedOffset>),
+}
+
+impl From<&str> for TemplateValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for TemplateValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for TemplateValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<i32> for TemplateValue {
+    fn from(value: i32) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<f64> for TemplateValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for TemplateValue {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+impl From<FluxDuration> for TemplateValue {
+    fn from(value: FluxDuration) -> Self {
+        Self::Duration(value)
+    }
+}
+
+impl From<std::time::Duration> for TemplateValue {
+    fn from(value: std::time::Duration) -> Self {
+        Self::Duration(value.into())
+    }
+}
+
+impl From<DateTime<FixedOffset>> for TemplateValue {
+    fn from(value: DateTime<FixedOffset>) -> Self {
+        Self::Time(value)
+    }
+}
+
+impl From<DateTime<Utc>> for TemplateValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self::Time(value.into())
+    }
+}
+
+/// A Flux script with named placeholders
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryTemplate {
+    template: String,
+    values: BTreeMap<String, TemplateValue>,
+}
+
+impl QueryTemplate {
+    /// Create a template from a script with `{name}` placeholders.
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Bind `value` to the placeholder `name`, replacing any earlier value.
+    pub fn bind(mut self, name: impl Into<String>, value: impl Into<TemplateValue>) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    /// The names of the placeholders, in the order they appear.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for part in parts(&self.template) {
+            if let Part::Placeholder(name) = part {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Render the script, failing if a placeholder has no value or a value
+    /// has no placeholder.
+    pub fn render(&self) -> Result<String, TemplateError> {
+        let placeholders = self.placeholders();
+        if let Some(name) = self
+            .values
+            .keys()
+            .find(|name| !placeholders.contains(&name.as_str()))
+        {
+            return UnknownPlaceholderSnafu { name }.fail();
+        }
+
+        let mut script = String::with_capacity(self.template.len());
+        for part in parts(&self.template) {
+            match part {
+                Part::Text(text) => script.push_str(text),
+                Part::Placeholder(name) => match self.values.get(name) {
+                    Some(value) => write_literal(&mut script, name, value)?,
+                    None => return UnboundSnafu { name }.fail(),
+                },
+            }
+        }
+        Ok(script)
+    }
+
+    /// Render the script into a `Query`.
+    pub fn query(&self) -> Result<Query, TemplateError> {
+        self.render().map(Query::new)
+    }
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Split `template` into text and the names of the `{name}` placeholders
+fn parts(template: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let name = rest[open + 1..]
+            .find('}')
+            .map(|close| &rest[open + 1..open + 1 + close])
+            .filter(|name| is_identifier(name));
+        match name {
+            Some(name) => {
+                parts.push(Part::Text(&rest[..open]));
+                parts.push(Part::Placeholder(name));
+                rest = &rest[open + name.len() + 2..];
+            }
+            None => {
+                parts.push(Part::Text(&rest[..=open]));
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    parts.push(Part::Text(rest));
+    parts
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn write_literal(
+    script: &mut String,
+    name: &str,
+    value: &TemplateValue,
+) -> Result<(), TemplateError> {
+    match value {
+        TemplateValue::String(value) => script.push_str(&flux_string(value)),
+        TemplateValue::Integer(value) => script.push_str(&value.to_string()),
+        TemplateValue::Float(value) => {
+            ensure!(value.is_finite(), NonFiniteSnafu { name });
+            let literal = value.to_string();
+            script.push_str(&literal);
+            if !literal.contains('.') {
+                script.push_str(".0");
+            }
+        }
+        TemplateValue::Boolean(value) => script.push_str(if *value { "true" } else { "false" }),
+        TemplateValue::Duration(value) => {
+            let _ = write!(script, "{}", value);
+        }
+        TemplateValue::Time(value) => {
+            script.push_str(&value.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+    }
+    Ok(())
+}
+
+/// Quote `value` as a Flux string literal
+pub(crate) fn flux_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_literals_by_type() {
+        let time = DateTime::parse_from_rfc3339("2023-01-02T03:04:05+02:00").unwrap();
+        let script = QueryTemplate::new(
+            "option task = {name: {name}}\n{s} {i} {f} {g} {b} {d} {t} {s} {} { s }",
+        )
+        .bind("name", "${x}")
+        .bind("s", "a\nb")
+        .bind("i", -3)
+        .bind("f", 2.0)
+        .bind("g", 0.25)
+        .bind("b", true)
+        .bind("d", std::time::Duration::from_secs(90))
+        .bind("t", time)
+        .render()
+        .unwrap();
+
+        assert_eq!(
+            script,
+            "option task = {name: \"\\${x}\"}\n\"a\\nb\" -3 2.0 0.25 true 1m30s \
+             2023-01-02T03:04:05+02:00 \"a\\nb\" {} { s }"
+        );
+    }
+
+    #[test]
+    fn validates_bindings() {
+        let template = QueryTemplate::new("from(bucket: {bucket}) |> range(start: {start})");
+        assert_eq!(template.placeholders(), ["bucket", "start"]);
+
+        assert_eq!(
+            template.clone().bind("bucket", "b").render(),
+            Err(TemplateError::Unbound {
+                name: "start".to_string()
+            })
+        );
+        assert_eq!(
+            template
+                .clone()
+                .bind("bucket", "b")
+                .bind("start", 0)
+                .bind("stop", 1)
+                .render(),
+            Err(TemplateError::UnknownPlaceholder {
+                name: "stop".to_string()
+            })
+        );
+        assert_eq!(
+            template
+                .bind("bucket", "b")
+                .bind("start", f64::NAN)
+                .render(),
+            Err(TemplateError::NonFinite {
+                name: "start".to_string()
+            })
+        );
+    }
+}
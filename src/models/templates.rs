@@ -0,0 +1,84 @@
+//! Templates of resources
+//!
+//! Not to be confused with the query templates of `models::template`: these
+//! are the resource manifests exported and applied by the templates API.
+
+use serde::{Deserialize, Serialize};
+
+/// A template: the manifests of a set of resources, in the order the server
+/// exported them
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Template {
+    /// The manifests of the resources
+    pub objects: Vec<TemplateObject>,
+}
+
+impl Template {
+    /// The manifests of `kind`, such as `Dashboard`, `Label` or `Variable`.
+    pub fn objects_of_kind<'a>(
+        &'a self,
+        kind: &'a str,
+    ) -> impl Iterator<Item = &'a TemplateObject> {
+        self.objects
+            .iter()
+            .filter(move |object| object.kind == kind)
+    }
+}
+
+/// The manifest of a resource in a template
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TemplateObject {
+    /// Version of the manifest format, such as `influxdata.com/v2alpha1`
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    /// Kind of the resource, such as `Dashboard`
+    pub kind: String,
+    /// Metadata of the manifest
+    pub metadata: TemplateMetadata,
+    /// The resource, in the format of its kind
+    #[serde(default)]
+    pub spec: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Metadata of a manifest
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TemplateMetadata {
+    /// Name of the manifest, referred to by the manifests depending on it
+    pub name: String,
+}
+
+/// Request to export resources as a template
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TemplateExportRequest {
+    /// ID of a stack whose resources to export
+    #[serde(rename = "stackID", skip_serializing_if = "Option::is_none")]
+    pub stack_id: Option<String>,
+    /// Resources to export
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<TemplateExportResource>,
+}
+
+/// A resource to export
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TemplateExportResource {
+    /// Kind of the resource, such as `Dashboard`
+    pub kind: String,
+    /// ID of the resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Name of the resource, when exporting by name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl TemplateExportResource {
+    /// Returns the resource of `kind` with ID `id`
+    pub fn new(kind: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            id: Some(id.into()),
+            name: None,
+        }
+    }
+}
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 pub struct User {
     /// User ID
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<String>,
+    pub id: Option<crate::models::UserId>,
     /// User oauth token id
     #[serde(rename = "oauthID", skip_serializing_if = "Option::is_none")]
     pub oauth_id: Option<String>,
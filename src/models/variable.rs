@@ -0,0 +1,79 @@
+//! Variables
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Label, OrgId, VariableId};
+
+/// A dashboard variable
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Variable {
+    /// Variable ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<VariableId>,
+    /// ID of the organization owning the variable
+    #[serde(rename = "orgID", skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+    /// Name of the variable, as used in `v.<name>`
+    pub name: String,
+    /// Description of the variable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The selected values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selected: Vec<String>,
+    /// Labels of the variable
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<Label>,
+    /// Where the values of the variable come from
+    pub arguments: VariableArguments,
+}
+
+/// Where the values of a variable come from
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "values", rename_all = "lowercase")]
+pub enum VariableArguments {
+    /// The values of the `_value` column of a query
+    Query(VariableQuery),
+    /// A fixed list of values
+    Constant(Vec<String>),
+    /// Keys shown to the user, standing for the values they map to
+    Map(BTreeMap<String, String>),
+}
+
+/// The query of a query variable
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct VariableQuery {
+    /// The query
+    pub query: String,
+    /// The language of the query, `flux`
+    pub language: String,
+}
+
+/// List of variables
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Variables {
+    /// Variables
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+}
+
+/// A value a variable can take
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariableChoice {
+    /// What a dropdown shows: the key of a map variable, the value otherwise
+    pub label: String,
+    /// What is substituted for the variable in queries
+    pub value: String,
+}
+
+impl VariableChoice {
+    /// Returns a choice showing and substituting `value`
+    pub fn new(value: String) -> Self {
+        Self {
+            label: value.clone(),
+            value,
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Health monitor
+//!
+//! A [`HealthMonitor`] polls `/health` from a background task and publishes
+//! the outcome through a `tokio::sync::watch` channel, so an application can
+//! gate its writes or switch to buffering while the server is degraded.
+//! Requires the `health-monitor` feature and a tokio runtime.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use influxdb2::monitor::HealthStatus;
+//!
+//! # async fn example(client: influxdb2::Client) {
+//! let monitor = client.monitor_health(Duration::from_secs(10));
+//! let mut status = monitor.subscribe();
+//! while status.changed().await.is_ok() {
+//!     if !status.borrow().is_healthy() {
+//!         // Buffer writes until the server recovers
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! Without a background task, [`Client::health_stream`] runs the same checks
+//! as the stream is polled and yields the transitions.
+
+use std::time::Duration;
+
+use futures::stream::{self, Stream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::models::{HealthCheck, Status};
+use crate::{Client, RequestError};
+
+/// The health of a server, as seen by the last check of a [`HealthMonitor`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// No check has completed yet
+    Unknown,
+    /// The server reports itself healthy
+    Healthy,
+    /// The server answered, but reports itself unhealthy
+    Degraded {
+        /// The reason given by the server
+        message: Option<String>,
+    },
+    /// The check failed, e.g. because the server can't be reached
+    Unreachable {
+        /// The error of the check
+        error: String,
+    },
+}
+
+impl HealthStatus {
+    /// Whether the server reported itself healthy.
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, Self::Healthy)
+    }
+}
+
+impl From<Result<HealthCheck, RequestError>> for HealthStatus {
+    fn from(check: Result<HealthCheck, RequestError>) -> Self {
+        match check {
+            Ok(check) => match check.status {
+                Status::Pass => Self::Healthy,
+                Status::Fail => Self::Degraded {
+                    message: check.message,
+                },
+            },
+            Err(error) => Self::Unreachable {
+                error: error.to_string(),
+            },
+        }
+    }
+}
+
+/// A background task checking the health of a server on an interval.
+///
+/// The task stops when the monitor is dropped.
+#[derive(Debug)]
+pub struct HealthMonitor {
+    status: watch::Receiver<HealthStatus>,
+    task: JoinHandle<()>,
+}
+
+impl HealthMonitor {
+    fn spawn(client: Client, interval: Duration) -> Self {
+        let (sender, status) = watch::channel(HealthStatus::Unknown);
+
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                let checked = HealthStatus::from(client.health().await);
+                sender.send_if_modified(|status| {
+                    let changed = *status != checked;
+                    *status = checked;
+                    changed
+                });
+            }
+        });
+
+        Self { status, task }
+    }
+
+    /// The outcome of the last check.
+    pub fn status(&self) -> HealthStatus {
+        self.status.borrow().clone()
+    }
+
+    /// A receiver notified whenever the status changes.
+    pub fn subscribe(&self) -> watch::Receiver<HealthStatus> {
+        self.status.clone()
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Client {
+    /// Check the health of the server every `interval` from a background
+    /// task. A check that doesn't answer within the management timeout of
+    /// the client counts as [`HealthStatus::Unreachable`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn monitor_health(&self, interval: Duration) -> HealthMonitor {
+        HealthMonitor::spawn(self.clone(), interval)
+    }
+
+    /// Check the health of the server every `interval` while the stream is
+    /// polled, yielding the first status and then every change, e.g. from
+    /// [`HealthStatus::Healthy`] to [`HealthStatus::Degraded`] with the
+    /// reason given by the server.
+    ///
+    /// Unlike [`Client::monitor_health`], nothing runs in the background:
+    /// the checks stop when the stream is no longer polled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn health_stream(&self, interval: Duration) -> impl Stream<Item = HealthStatus> {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        stream::unfold(
+            (self.clone(), ticks, HealthStatus::Unknown),
+            |(client, mut ticks, last)| async move {
+                loop {
+                    ticks.tick().await;
+                    let checked = HealthStatus::from(client.health().await);
+                    if checked != last {
+                        return Some((checked.clone(), (client, ticks, checked)));
+                    }
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use mockito::mock;
+
+    fn prefixed(prefix: &str) -> Client {
+        Client::new(format!("{}/{}", mockito::server_url(), prefix), "org", "")
+    }
+
+    #[tokio::test]
+    async fn reports_healthy_server() {
+        let mock_server = mock("GET", "/healthy/health")
+            .with_body(r#"{"name":"influxdb","status":"pass"}"#)
+            .create();
+
+        let monitor = prefixed("healthy").monitor_health(Duration::from_millis(10));
+        let mut status = monitor.subscribe();
+        status.changed().await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(monitor.status(), HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn streams_transitions() {
+        let healthy = mock("GET", "/transitions/health")
+            .with_body(r#"{"name":"influxdb","status":"pass"}"#)
+            .expect(2)
+            .create();
+        let degraded = mock("GET", "/transitions/health")
+            .with_status(503)
+            .with_body(r#"{"name":"influxdb","status":"fail","message":"disk full"}"#)
+            .create();
+
+        let statuses: Vec<_> = prefixed("transitions")
+            .health_stream(Duration::from_millis(10))
+            .take(2)
+            .collect()
+            .await;
+
+        healthy.assert();
+        degraded.assert();
+        assert_eq!(
+            statuses,
+            [
+                HealthStatus::Healthy,
+                HealthStatus::Degraded {
+                    message: Some("disk full".to_string())
+                }
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_degraded_server() {
+        let mock_server = mock("GET", "/degraded/health")
+            .with_status(503)
+            .with_body(r#"{"name":"influxdb","status":"fail","message":"disk full"}"#)
+            .create();
+
+        let monitor = prefixed("degraded").monitor_health(Duration::from_millis(10));
+        let mut status = monitor.subscribe();
+        status.changed().await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(
+            monitor.status(),
+            HealthStatus::Degraded {
+                message: Some("disk full".to_string())
+            }
+        );
+    }
+}
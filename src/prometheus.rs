@@ -0,0 +1,376 @@
+//! Prometheus exposition format
+//!
+//! Parses the Prometheus text exposition format into `DataPoint`s, so a
+//! scraper can push metrics into InfluxDB without Telegraf. The mapping
+//! follows Telegraf's Prometheus parser: each metric family becomes a
+//! measurement, its labels become tags, and the value lands in a field named
+//! after the family's type:
+//!
+//! - counters in `counter`, gauges in `gauge`, untyped metrics in `value`,
+//! - histograms in `count`, `sum` and one field per bucket, named after its
+//!   `le` bound,
+//! - summaries in `count`, `sum` and one field per quantile.
+//!
+//! Samples whose value is `NaN` or infinite are skipped, since line protocol
+//! can't carry them. Timestamps are converted from milliseconds; samples
+//! without one are timestamped by the server.
+//!
+//! ```
+//! use influxdb2::prometheus;
+//!
+//! let points = prometheus::parse(
+//!     "# TYPE http_requests_total counter\n\
+//!      http_requests_total{method=\"post\",code=\"200\"} 1027 1395066363000\n",
+//! )
+//! .unwrap();
+//! assert_eq!(points.len(), 1);
+//! ```
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::models::DataPoint;
+
+/// Errors that occur while parsing the exposition format
+#[derive(Debug, Snafu)]
+pub enum ParseError {
+    /// A sample line that isn't `name{labels} value [timestamp]`.
+    #[snafu(display("Invalid sample on line {}: {}", line, text))]
+    InvalidSample {
+        /// The line number, starting at 1
+        line: usize,
+        /// The line
+        text: String,
+    },
+
+    /// A sample whose value isn't a number.
+    #[snafu(display("Invalid value on line {}: {}", line, value))]
+    InvalidValue {
+        /// The line number, starting at 1
+        line: usize,
+        /// The value
+        value: String,
+        /// The parse error
+        source: std::num::ParseFloatError,
+    },
+
+    /// A sample whose timestamp isn't an integer number of milliseconds.
+    #[snafu(display("Invalid timestamp on line {}: {}", line, timestamp))]
+    InvalidTimestamp {
+        /// The line number, starting at 1
+        line: usize,
+        /// The timestamp
+        timestamp: String,
+        /// The parse error
+        source: std::num::ParseIntError,
+    },
+}
+
+/// The type of a metric family, from its `# TYPE` line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+    Summary,
+    Untyped,
+}
+
+impl Kind {
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "counter" => Self::Counter,
+            "gauge" => Self::Gauge,
+            "histogram" => Self::Histogram,
+            "summary" => Self::Summary,
+            _ => Self::Untyped,
+        }
+    }
+}
+
+/// The fields of one point under construction
+#[derive(Debug)]
+struct Group {
+    measurement: String,
+    tags: BTreeMap<String, String>,
+    fields: Vec<(String, f64)>,
+    timestamp: Option<i64>,
+}
+
+/// Parse `text`, in the Prometheus text exposition format, into points.
+///
+/// The samples of a histogram or summary that share their labels and
+/// timestamp are gathered into a single point.
+pub fn parse(text: &str) -> Result<Vec<DataPoint>, ParseError> {
+    let mut kinds = HashMap::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index = HashMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut words = comment.split_whitespace();
+            if let (Some("TYPE"), Some(name), Some(kind)) =
+                (words.next(), words.next(), words.next())
+            {
+                kinds.insert(name.to_string(), Kind::parse(kind));
+            }
+            continue;
+        }
+
+        let Sample {
+            name,
+            labels: mut tags,
+            value,
+            timestamp,
+        } = parse_sample(line, number)?;
+        if !value.is_finite() {
+            continue;
+        }
+
+        let (family, kind) = family(&name, &kinds);
+        let field = match kind {
+            Kind::Counter => "counter".to_string(),
+            Kind::Gauge => "gauge".to_string(),
+            Kind::Untyped => "value".to_string(),
+            Kind::Histogram | Kind::Summary => match &name[family.len()..] {
+                "_sum" => "sum".to_string(),
+                "_count" => "count".to_string(),
+                _ => {
+                    let bound = if kind == Kind::Histogram {
+                        "le"
+                    } else {
+                        "quantile"
+                    };
+                    match tags.remove(bound) {
+                        Some(bound) => bound,
+                        None => "value".to_string(),
+                    }
+                }
+            },
+        };
+
+        let key = (family.to_string(), tags.clone(), timestamp);
+        let group = *index.entry(key).or_insert_with(|| {
+            groups.push(Group {
+                measurement: family.to_string(),
+                tags,
+                fields: Vec::new(),
+                timestamp,
+            });
+            groups.len() - 1
+        });
+        groups[group].fields.push((field, value));
+    }
+
+    Ok(groups
+        .into_iter()
+        .filter_map(|group| {
+            let mut point = DataPoint::builder(group.measurement);
+            for (key, value) in group.tags {
+                point = point.tag(key, value);
+            }
+            for (key, value) in group.fields {
+                point = point.field(key, value);
+            }
+            if let Some(timestamp) = group.timestamp {
+                point = point.timestamp(timestamp);
+            }
+            point.build().ok()
+        })
+        .collect())
+}
+
+/// The family `name` belongs to, with its type
+fn family<'a>(name: &'a str, kinds: &HashMap<String, Kind>) -> (&'a str, Kind) {
+    if let Some(kind) = kinds.get(name) {
+        return (name, *kind);
+    }
+    for suffix in &["_bucket", "_sum", "_count"] {
+        if let Some(family) = name.strip_suffix(suffix) {
+            match kinds.get(family) {
+                Some(Kind::Histogram) => return (family, Kind::Histogram),
+                Some(Kind::Summary) if *suffix != "_bucket" => return (family, Kind::Summary),
+                _ => {}
+            }
+        }
+    }
+    (name, Kind::Untyped)
+}
+
+#[derive(Debug)]
+struct Sample {
+    name: String,
+    labels: BTreeMap<String, String>,
+    value: f64,
+    timestamp: Option<i64>,
+}
+
+fn parse_sample(line: &str, number: usize) -> Result<Sample, ParseError> {
+    let invalid = || InvalidSampleSnafu {
+        line: number,
+        text: line,
+    };
+
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .context(invalid())?;
+    let name = &line[..name_end];
+    ensure!(!name.is_empty(), invalid());
+
+    let mut rest = &line[name_end..];
+    let mut labels = BTreeMap::new();
+    if let Some(after_brace) = rest.strip_prefix('{') {
+        rest = parse_labels(after_brace, &mut labels).context(invalid())?;
+    }
+
+    let mut parts = rest.split_whitespace();
+    let value = parts.next().context(invalid())?;
+    let timestamp = parts.next();
+    ensure!(parts.next().is_none(), invalid());
+
+    let value = parse_value(value).context(InvalidValueSnafu {
+        line: number,
+        value,
+    })?;
+    let timestamp = timestamp
+        .map(|timestamp| {
+            timestamp
+                .parse::<i64>()
+                .map(|millis| millis.saturating_mul(1_000_000))
+                .context(InvalidTimestampSnafu {
+                    line: number,
+                    timestamp,
+                })
+        })
+        .transpose()?;
+
+    Ok(Sample {
+        name: name.to_string(),
+        labels,
+        value,
+        timestamp,
+    })
+}
+
+/// Parse the labels after the opening brace into `labels`, returning what
+/// follows the closing brace
+fn parse_labels<'a>(mut rest: &'a str, labels: &mut BTreeMap<String, String>) -> Option<&'a str> {
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some(after);
+        }
+
+        let (name, after_name) = rest.split_at(rest.find('=')?);
+        let mut chars = after_name[1..]
+            .trim_start()
+            .strip_prefix('"')?
+            .char_indices();
+        let mut value = String::new();
+        loop {
+            match chars.next()? {
+                (_, '"') => break,
+                (_, '\\') => match chars.next()? {
+                    (_, 'n') => value.push('\n'),
+                    (_, c) => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        }
+        labels.insert(name.trim().to_string(), value);
+
+        rest = chars.as_str().trim_start();
+        if let Some(after_comma) = rest.strip_prefix(',') {
+            rest = after_comma;
+        } else if !rest.starts_with('}') {
+            return None;
+        }
+    }
+}
+
+fn parse_value(value: &str) -> Result<f64, std::num::ParseFloatError> {
+    match value {
+        "+Inf" => Ok(f64::INFINITY),
+        "-Inf" => Ok(f64::NEG_INFINITY),
+        _ => value.parse(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WriteDataPoint;
+
+    fn lines(points: &[DataPoint]) -> String {
+        let mut out = Vec::new();
+        for point in points {
+            point.write_data_point_to(&mut out).unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn counters_and_gauges() {
+        let text = r#"
+# HELP http_requests_total The total number of HTTP requests.
+# TYPE http_requests_total counter
+http_requests_total{method="post",code="200"} 1027 1395066363000
+http_requests_total{method="post",code="400"}    3 1395066363000
+
+# TYPE temperature gauge
+temperature{room="a \"b\"\\c"} 21.5
+untyped_metric NaN
+other 1e3
+"#;
+
+        let points = parse(text).unwrap();
+
+        assert_eq!(
+            lines(&points),
+            "http_requests_total,code=200,method=post counter=1027 1395066363000000000\n\
+             http_requests_total,code=400,method=post counter=3 1395066363000000000\n\
+             temperature,room=a\\ \"b\"\\c gauge=21.5\n\
+             other value=1000\n"
+        );
+    }
+
+    #[test]
+    fn histograms_and_summaries() {
+        let text = r#"
+# TYPE request_duration_seconds histogram
+request_duration_seconds_bucket{le="0.1"} 24054
+request_duration_seconds_bucket{le="+Inf"} 144320
+request_duration_seconds_sum 53423
+request_duration_seconds_count 144320
+# TYPE rpc_duration_seconds summary
+rpc_duration_seconds{quantile="0.5"} 4773
+rpc_duration_seconds_sum 1.7560473e+07
+rpc_duration_seconds_count 2693
+"#;
+
+        let points = parse(text).unwrap();
+
+        assert_eq!(
+            lines(&points),
+            "request_duration_seconds +Inf=144320,0.1=24054,count=144320,sum=53423\n\
+             rpc_duration_seconds 0.5=4773,count=2693,sum=17560473\n"
+        );
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let err = parse("up 1\nup{job=\"a\" 1\n").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidSample { line: 2, .. }));
+
+        let err = parse("up one").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidValue { line: 1, .. }));
+
+        let err = parse("up 1 soon").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidTimestamp { line: 1, .. }));
+    }
+}
@@ -0,0 +1,622 @@
+//! Sending requests
+//!
+//! Every request gets a fresh UUID, sent in the [`REQUEST_ID_HEADER`] header
+//! and recorded on the tracing span of the request, so a call can be followed
+//! from the application logs to the logs of the server.
+//!
+//! A request may carry an idempotency key as well, set with
+//! `Client::with_idempotency_key`. Unlike the request ID it stays the same
+//! when a batch is sent again, so a proxy or a pipeline downstream of the
+//! server can tell a retried write from a new one. The Influx server
+//! ignores it.
+
+use futures::future::{select, Either};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::{logging, retry, CancellationToken, Client, RequestError};
+
+/// Header carrying the ID this client generates for every request.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Header carrying the ID the Influx server assigns to every request.
+pub const INFLUX_REQUEST_ID_HEADER: &str = "X-Influxdb-Request-Id";
+
+/// Header carrying the idempotency key of a request, see
+/// `Client::with_idempotency_key`.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// A fresh idempotency key, a random UUID.
+pub fn new_idempotency_key() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// The class of a request, selecting its timeout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RequestKind {
+    /// Writes and deletes of data points
+    Write,
+    /// Flux queries
+    Query,
+    /// Everything else
+    Management,
+}
+
+/// Timeouts per class of request; `None` waits indefinitely
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Timeouts {
+    pub(crate) write: Option<Duration>,
+    pub(crate) query: Option<Duration>,
+    pub(crate) management: Option<Duration>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Timeouts {
+    pub(crate) fn get(&self, kind: RequestKind) -> Option<Duration> {
+        match kind {
+            RequestKind::Write => self.write,
+            RequestKind::Query => self.query,
+            RequestKind::Management => self.management,
+        }
+    }
+}
+
+impl Client {
+    /// Send a request built by `request`, tagged with a fresh request ID
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Response, RequestError> {
+        let request_id = Uuid::new_v4().to_string();
+
+        let mut request = request
+            .build()
+            .map_err(|source| RequestError::ReqwestProcessing {
+                source,
+                endpoint: None,
+                request_id: Some(request_id.clone()),
+                influx_request_id: None,
+                idempotency_key: None,
+            })?;
+        request.headers_mut().insert(
+            REQUEST_ID_HEADER,
+            HeaderValue::from_str(&request_id).expect("a UUID is a valid header value"),
+        );
+        let mut context = RequestContext {
+            endpoint: format!("{} {}", request.method(), request.url().path()),
+            request_id,
+            influx_request_id: None,
+            idempotency_key: request
+                .headers()
+                .get(IDEMPOTENCY_KEY_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+        };
+
+        let span = tracing::debug_span!(
+            "influxdb2_request",
+            request_id = %context.request_id,
+            method = %request.method(),
+            url = %logging::redact_url(request.url()),
+            status = tracing::field::Empty,
+            influx_request_id = tracing::field::Empty,
+            idempotency_key = tracing::field::Empty,
+        );
+        if let Some(key) = &context.idempotency_key {
+            span.record("idempotency_key", key.as_str());
+        }
+
+        if self.log_requests {
+            logging::log_request(&request);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let started = std::time::Instant::now();
+
+        let exchange = async {
+            let fail = |e| context.reqwest_error(e);
+            #[cfg(all(feature = "vcr", not(target_arch = "wasm32")))]
+            let mut response = match &self.cassette {
+                Some(cassette) => cassette
+                    .exchange(&self.reqwest, request)
+                    .await
+                    .map_err(|e| context.attach(e))?,
+                None => self.reqwest.execute(request).await.map_err(fail)?,
+            };
+            #[cfg(not(all(feature = "vcr", not(target_arch = "wasm32"))))]
+            let mut response = self.reqwest.execute(request).await.map_err(fail)?;
+
+            if self.log_requests {
+                #[cfg(not(target_arch = "wasm32"))]
+                let elapsed_ms = Some(started.elapsed().as_millis());
+                #[cfg(target_arch = "wasm32")]
+                let elapsed_ms = None;
+
+                response = logging::log_response(response, elapsed_ms)
+                    .await
+                    .map_err(fail)?;
+            }
+            Ok(response)
+        };
+        let response = context
+            .cancellable(
+                self.cancellation.as_ref(),
+                exchange.instrument(span.clone()),
+            )
+            .await?;
+
+        context.influx_request_id = influx_request_id(response.headers());
+        span.record("status", response.status().as_u16());
+        if let Some(id) = &context.influx_request_id {
+            span.record("influx_request_id", id.as_str());
+        }
+
+        Ok(Response {
+            inner: response,
+            context,
+            cancellation: self.cancellation.clone(),
+            max_size: self.max_response_size,
+        })
+    }
+
+    /// Send a request built by `request` as [`Client::send`] does, again
+    /// while the retry policy of the client allows it, see [`crate::retry`]
+    pub(crate) async fn send_retrying(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Response, RequestError> {
+        let policy = match &self.retry_policy {
+            Some(policy) => policy,
+            None => return self.send(request).await,
+        };
+
+        let mut attempt = 1;
+        loop {
+            // Streamed bodies can't be cloned, so they are sent only once
+            let retry = match request.try_clone() {
+                Some(retry) if attempt < policy.max_attempts => retry,
+                _ => return self.send(request).await,
+            };
+            let result = self.send(retry).await;
+            let retry_after = match &result {
+                Ok(response) if retry::is_retryable_status(response.status()) => {
+                    retry::retry_after(response.headers(), self.clock.now())
+                }
+                Err(e) if retry::is_retryable_error(e) => None,
+                _ => return result,
+            };
+            let delay = match policy.delay(attempt, retry_after) {
+                Some(delay) => delay,
+                None => return result,
+            };
+            drop(result);
+
+            tracing::debug!(attempt, delay_ms = delay.as_millis() as u64, "retrying request");
+            match &self.cancellation {
+                // A cancelled token fails the next attempt before it's sent
+                Some(cancellation) => {
+                    select(Box::pin(cancellation.cancelled()), self.clock.sleep(delay)).await;
+                }
+                None => self.clock.sleep(delay).await,
+            }
+            attempt += 1;
+        }
+    }
+}
+
+fn influx_request_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(INFLUX_REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string)
+}
+
+/// What identifies a request on both ends
+#[derive(Debug)]
+pub(crate) struct RequestContext {
+    /// The method and path of the request
+    pub(crate) endpoint: String,
+    pub(crate) request_id: String,
+    pub(crate) influx_request_id: Option<String>,
+    pub(crate) idempotency_key: Option<String>,
+}
+
+impl RequestContext {
+    fn attach(&self, error: RequestError) -> RequestError {
+        error.with_context(self)
+    }
+
+    fn reqwest_error(&self, source: reqwest::Error) -> RequestError {
+        self.attach(RequestError::ReqwestProcessing {
+            source,
+            endpoint: None,
+            request_id: None,
+            influx_request_id: None,
+            idempotency_key: None,
+        })
+    }
+
+    /// Run `future` to completion, unless `cancellation` is cancelled first
+    async fn cancellable<T>(
+        &self,
+        cancellation: Option<&CancellationToken>,
+        future: impl Future<Output = Result<T, RequestError>>,
+    ) -> Result<T, RequestError> {
+        let cancellation = match cancellation {
+            Some(cancellation) => cancellation,
+            None => return future.await,
+        };
+
+        // The token goes first, so an already cancelled call doesn't start
+        match select(Box::pin(cancellation.cancelled()), Box::pin(future)).await {
+            Either::Left(((), _)) => Err(self.attach(RequestError::Cancelled {
+                endpoint: None,
+                request_id: None,
+                idempotency_key: None,
+            })),
+            Either::Right((result, _)) => result,
+        }
+    }
+}
+
+/// A response to a request sent with [`Client::send`], which attaches the
+/// request context to every error raised while reading it.
+#[derive(Debug)]
+pub(crate) struct Response {
+    inner: reqwest::Response,
+    context: RequestContext,
+    cancellation: Option<CancellationToken>,
+    /// The largest JSON body read, see `ClientBuilder::with_max_response_size`
+    max_size: Option<usize>,
+}
+
+impl Response {
+    pub(crate) fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    pub(crate) fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// Deserialize the body as JSON, unless it's larger than the limit
+    pub(crate) async fn json<T: DeserializeOwned>(self) -> Result<T, RequestError> {
+        let Self {
+            mut inner,
+            context,
+            cancellation,
+            max_size,
+        } = self;
+        let limit = match max_size {
+            Some(limit) => limit,
+            None => {
+                let json = async {
+                    inner
+                        .json::<T>()
+                        .await
+                        .map_err(|e| context.reqwest_error(e))
+                };
+                return context.cancellable(cancellation.as_ref(), json).await;
+            }
+        };
+
+        let too_large = || {
+            context.attach(RequestError::ResponseTooLarge {
+                limit,
+                endpoint: None,
+                request_id: None,
+                influx_request_id: None,
+                idempotency_key: None,
+            })
+        };
+        let json = async {
+            if inner.content_length().is_some_and(|length| length > limit as u64) {
+                return Err(too_large());
+            }
+            let mut body = Vec::new();
+            while let Some(chunk) = inner.chunk().await.map_err(|e| context.reqwest_error(e))? {
+                if body.len() + chunk.len() > limit {
+                    return Err(too_large());
+                }
+                body.extend_from_slice(&chunk);
+            }
+            serde_json::from_slice::<T>(&body)
+                .map_err(|e| context.attach(RequestError::deserializing(e.to_string())))
+        };
+        context.cancellable(cancellation.as_ref(), json).await
+    }
+
+    /// Read the next chunk of the body, or `None` at its end
+    #[cfg(all(feature = "spool", not(target_arch = "wasm32")))]
+    pub(crate) async fn chunk(&mut self) -> Result<Option<bytes::Bytes>, RequestError> {
+        let Self {
+            inner,
+            context,
+            cancellation,
+            ..
+        } = self;
+        let chunk = async { inner.chunk().await.map_err(|e| context.reqwest_error(e)) };
+        context.cancellable(cancellation.as_ref(), chunk).await
+    }
+
+    /// Read the body as text and parse it with `parse`
+    pub(crate) async fn parse<T>(
+        self,
+        parse: impl FnOnce(&str) -> Result<T, RequestError>,
+    ) -> Result<T, RequestError> {
+        let Self {
+            inner,
+            context,
+            cancellation,
+            ..
+        } = self;
+        let text = async { inner.text().await.map_err(|e| context.reqwest_error(e)) };
+        let text = context.cancellable(cancellation.as_ref(), text).await?;
+        parse(&text).map_err(|e| context.attach(e))
+    }
+
+    /// Turn an unexpected response into an `Http` error carrying its body,
+    /// parsed if the server answered with its JSON error format
+    pub(crate) async fn error(self) -> RequestError {
+        let Self {
+            inner,
+            context,
+            cancellation,
+            ..
+        } = self;
+        let status = inner.status();
+        let text = async { inner.text().await.map_err(|e| context.reqwest_error(e)) };
+        match context.cancellable(cancellation.as_ref(), text).await {
+            Ok(text) => context.attach(RequestError::Http {
+                status,
+                body: serde_json::from_str(&text).ok(),
+                text,
+                endpoint: None,
+                request_id: None,
+                influx_request_id: None,
+                idempotency_key: None,
+            }),
+            Err(e) => e,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn sends_request_id() {
+        let mock_server = mock("GET", "/ready")
+            .match_header(
+                REQUEST_ID_HEADER,
+                Matcher::Regex("^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-".to_string()),
+            )
+            .create();
+
+        let client = Client::new(mockito::server_url(), "", "");
+
+        let _result = client.ready().await;
+
+        mock_server.assert();
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn base_path_prefix() {
+        let mock_server = mock("GET", "/influx/api/v2/labels/some-label")
+            .expect(2)
+            .create();
+
+        for url in &["/influx", "/influx/"] {
+            let client = Client::new(format!("{}{}", mockito::server_url(), url), "", "");
+
+            let _result = client.find_label(&"some-label".into()).await;
+        }
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn extra_headers() {
+        let mock_server = mock("GET", "/ready")
+            .match_header("X-Tenant", "acme")
+            .match_header("Authorization", "Token some-token")
+            .create();
+
+        let client = Client::new(mockito::server_url(), "", "some-token");
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Tenant", HeaderValue::from_static("acme"));
+
+        let _result = client.with_headers(headers).ready().await;
+
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn errors_carry_idempotency_keys() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "idempotent".into()))
+            .match_header(IDEMPOTENCY_KEY_HEADER, "batch-7")
+            .with_status(503)
+            .expect(2)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "")
+            .with_idempotency_key("batch-7")
+            .unwrap();
+        let point = || {
+            crate::models::DataPoint::builder("cpu")
+                .field("usage", 1.0)
+                .build()
+                .unwrap()
+        };
+
+        for _ in 0..2 {
+            let err = client
+                .write("idempotent", futures::stream::iter(vec![point()]))
+                .await
+                .unwrap_err();
+            assert_eq!(err.idempotency_key(), Some("batch-7"));
+        }
+        mock_server.assert();
+        assert!(Client::new("http://localhost", "", "")
+            .with_idempotency_key("not\nvalid")
+            .is_err());
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn errors_carry_request_ids() {
+        let mock_server = mock("GET", "/api/v2/labels/some-label")
+            .with_status(404)
+            .with_header(INFLUX_REQUEST_ID_HEADER, "0a1b2c3d")
+            .with_body("label not found")
+            .create();
+
+        let client = Client::new(mockito::server_url(), "", "");
+
+        let err = client.find_label(&"some-label".into()).await.unwrap_err();
+
+        mock_server.assert();
+        assert!(matches!(err, RequestError::Http { ref text, .. } if text == "label not found"));
+        assert_eq!(err.request_id().map(str::len), Some(36));
+        assert_eq!(err.influx_request_id(), Some("0a1b2c3d"));
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn errors_carry_context() {
+        let mock_server = mock("GET", "/api/v2/labels/some-label")
+            .with_status(404)
+            .with_body(r#"{"code":"not found","message":"label not found"}"#)
+            .create();
+
+        let client = Client::new(mockito::server_url(), "", "");
+
+        let err = client.find_label(&"some-label".into()).await.unwrap_err();
+
+        mock_server.assert();
+        assert_eq!(err.endpoint(), Some("GET /api/v2/labels/some-label"));
+        assert!(err.to_string().contains("GET /api/v2/labels/some-label"));
+        match err {
+            RequestError::Http { body, .. } => assert_eq!(
+                body.map(|body| *body),
+                Some(crate::models::ErrorResponse {
+                    code: "not found".to_string(),
+                    message: "label not found".to_string(),
+                    op: None,
+                    err: None,
+                })
+            ),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn limits_json_responses() {
+        let body = r#"{"name":"influxdb","message":"ready for queries and writes","status":"pass","checks":[]}"#;
+        let mock_server = mock("GET", "/health").with_body(body).expect(2).create();
+
+        let client = |limit| {
+            Client::builder(mockito::server_url(), "", "")
+                .with_max_response_size(limit)
+                .build()
+                .unwrap()
+        };
+
+        let health = client(body.len()).health().await.unwrap();
+        let err = client(body.len() - 1).health().await.unwrap_err();
+
+        mock_server.assert();
+        assert_eq!(health.name, "influxdb");
+        assert!(matches!(err, RequestError::ResponseTooLarge { limit, .. } if limit == body.len() - 1));
+        assert_eq!(err.endpoint(), Some("GET /health"));
+    }
+
+    #[tokio::test]
+    async fn timeout_per_request_kind() {
+        // The kernel accepts the connection, but nobody ever answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let client = Client::builder(url, "org", "")
+            .with_query_timeout(Duration::from_secs(60))
+            .with_management_timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let err = client.ready().await.unwrap_err();
+
+        assert!(
+            matches!(err, RequestError::ReqwestProcessing { ref source, .. } if source.is_timeout())
+        );
+        assert_eq!(err.endpoint(), Some("GET /ready"));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[tokio::test]
+    async fn cancels_in_flight_requests() {
+        // The kernel accepts the connection, but nobody ever answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let token = CancellationToken::new();
+
+        let client = Client::new(url, "org", "").with_cancellation(token.clone());
+        let cancel = async {
+            tokio::task::yield_now().await;
+            token.cancel();
+        };
+
+        let (result, ()) = futures::join!(client.ready(), cancel);
+
+        let err = result.unwrap_err();
+        assert!(matches!(err, RequestError::Cancelled { .. }));
+        assert_eq!(err.request_id().map(str::len), Some(36));
+    }
+
+    #[tokio::test]
+    async fn cancelled_calls_are_not_sent() {
+        let mock_server = mock("GET", "/ready").expect(0).create();
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let client = Client::new(mockito::server_url(), "", "").with_cancellation(token);
+
+        let result = client.ready().await;
+
+        mock_server.assert();
+        assert!(matches!(result, Err(RequestError::Cancelled { .. })));
+    }
+
+    #[cfg(all(feature = "gzip", feature = "management"))]
+    #[tokio::test]
+    async fn decompresses_responses() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut body = GzEncoder::new(Vec::new(), Compression::default());
+        body.write_all(br#"{"label":{"id":"some-label","name":"some-name"}}"#)
+            .unwrap();
+
+        let mock_server = mock("GET", "/api/v2/labels/some-label")
+            .match_header("Accept-Encoding", Matcher::Regex("gzip".to_string()))
+            .with_header("Content-Encoding", "gzip")
+            .with_body(body.finish().unwrap())
+            .create();
+
+        let client = Client::new(mockito::server_url(), "", "");
+
+        let label = client.find_label(&"some-label".into()).await.unwrap();
+
+        mock_server.assert();
+        assert_eq!(label.label.unwrap().name.as_deref(), Some("some-name"));
+    }
+}
@@ -0,0 +1,271 @@
+//! Retries
+//!
+//! InfluxDB Cloud limits the rate of writes and queries, answering with
+//! `429 Too Many Requests` and a `Retry-After` header, and any server may be
+//! briefly unavailable. A [`RetryPolicy`] set with
+//! `ClientBuilder::with_retry_policy` sends writes and queries again when
+//! the server can't be reached, times out, or answers with 429 or a 5xx
+//! status, waiting between attempts on the client clock:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use influxdb2::retry::RetryPolicy;
+//!
+//! # fn example() -> Result<(), influxdb2::BuildError> {
+//! let client = influxdb2::Client::builder("http://localhost:8086", "org", "my-token")
+//!     .with_retry_policy(RetryPolicy {
+//!         max_attempts: 5,
+//!         base_delay: Duration::from_millis(200),
+//!         ..RetryPolicy::default()
+//!     })
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The delays double from `base_delay` up to `max_delay`, with jitter so
+//! clients failing together don't retry together. A `Retry-After` header
+//! replaces the delay; when it asks for more than `max_delay`, the error is
+//! returned at once instead. Bodies streamed into a request can't be sent
+//! again, so `Client::write` buffers its points while a policy is set, and
+//! streamed bodies passed to `Client::write_line_protocol_to` are sent once.
+//!
+//! On `wasm32`, which has no timer to wait with, requests are always sent
+//! once and the policy is ignored.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+use crate::RequestError;
+
+/// When and how often to send a request again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The most times a request is sent, the first one included
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled before each next one
+    pub base_delay: Duration,
+    /// The longest delay between two attempts
+    pub max_delay: Duration,
+    /// Whether to wait a random delay between half the delay and the delay
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay after the failed `attempt`, counted from 1, or `None` if
+    /// the `retry_after` the server asked for is longer than the policy
+    /// allows.
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if let Some(retry_after) = retry_after {
+            return Some(retry_after).filter(|delay| *delay <= self.max_delay);
+        }
+
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        if !self.jitter {
+            return Some(delay);
+        }
+        let half = delay / 2;
+        // A v4 UUID is random enough to spread retries apart
+        let nanos = u64::try_from(half.as_nanos()).unwrap_or(u64::MAX);
+        let random = Uuid::new_v4().as_u128() as u64;
+        Some(half + Duration::from_nanos(random % nanos.saturating_add(1)))
+    }
+}
+
+/// Whether a response with `status` is worth sending the request again for
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Whether `error` may not happen again on a retry
+pub(crate) fn is_retryable_error(error: &RequestError) -> bool {
+    match error {
+        RequestError::ReqwestProcessing { source, .. } => {
+            source.is_connect() || source.is_timeout()
+        }
+        _ => false,
+    }
+}
+
+/// How long the `Retry-After` header of `headers` asks to wait from `now`,
+/// given in seconds or as an HTTP date
+pub(crate) fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        date.with_timezone(&Utc)
+            .signed_duration_since(now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::models::Query;
+    use crate::Client;
+    use chrono::TimeZone;
+    use mockito::{mock, Matcher};
+    use reqwest::header::HeaderValue;
+    use std::sync::Arc;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(3),
+            jitter: false,
+        }
+    }
+
+    fn client(policy: RetryPolicy) -> (Client, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new(Utc.timestamp_opt(2_000_000_000, 0).unwrap()));
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_retry_policy(policy)
+            .with_clock(Arc::clone(&clock))
+            .build()
+            .unwrap();
+        (client, clock)
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = policy();
+        let delays: Vec<_> = (1..=4).map(|attempt| policy.delay(attempt, None)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(3)),
+                Some(Duration::from_secs(3)),
+            ]
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(4))), None);
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        for _ in 0..100 {
+            let delay = jittered.delay(2, None).unwrap();
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let now = Utc.timestamp_opt(1_445_412_480, 0).unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(120)));
+
+        // Five seconds after `now`
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:05 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn retries_writes() {
+        let unavailable = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "retried".into()))
+            .match_body("cpu usage=1i\n")
+            .with_status(503)
+            .expect(2)
+            .create();
+        let written = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "retried".into()))
+            .match_body("cpu usage=1i\n")
+            .with_status(204)
+            .create();
+
+        let (client, clock) = client(policy());
+        let point = crate::models::DataPoint::builder("cpu")
+            .field("usage", 1)
+            .build()
+            .unwrap();
+        client
+            .write("retried", futures::stream::iter(vec![point]))
+            .await
+            .unwrap();
+
+        unavailable.assert();
+        written.assert();
+        assert_eq!(
+            clock.sleeps(),
+            vec![Duration::from_secs(1), Duration::from_secs(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn honors_retry_after() {
+        let limited = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex("rate-limited".to_string()))
+            .with_status(429)
+            .with_header("Retry-After", "2")
+            .expect(4)
+            .create();
+
+        let (client, clock) = client(policy());
+        let err = client
+            .query_raw(Some(Query::new("rate-limited".to_string())))
+            .await
+            .unwrap_err();
+
+        limited.assert();
+        assert!(
+            matches!(err, RequestError::Http { status, .. } if status == StatusCode::TOO_MANY_REQUESTS)
+        );
+        assert_eq!(clock.sleeps(), vec![Duration::from_secs(2); 3]);
+    }
+
+    #[tokio::test]
+    async fn leaves_client_errors_alone() {
+        let rejected = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "rejected".into()))
+            .with_status(400)
+            .expect(1)
+            .create();
+
+        let (client, clock) = client(policy());
+        let result = client.write_line_protocol_to("rejected", "cpu usage=").await;
+
+        rejected.assert();
+        assert!(result.is_err());
+        assert!(clock.sleeps().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! Shared client handle
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::Client;
+
+/// A handle to a [`Client`] that is cheap to clone: clones share one client,
+/// with its connection pool and everything else it keeps, instead of copying
+/// its configuration. Store one in the state of a web framework and clone it
+/// per request.
+///
+/// The handle dereferences to the client, so every API method is available
+/// on it directly.
+///
+/// ```
+/// let client = influxdb2::Client::new("http://localhost:8086", "org", "token").into_shared();
+/// let per_request = client.clone();
+/// assert_eq!(per_request.org, "org");
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedClient {
+    inner: Arc<Client>,
+}
+
+impl Client {
+    /// Move this client behind a [`SharedClient`] handle.
+    pub fn into_shared(self) -> SharedClient {
+        SharedClient {
+            inner: Arc::new(self),
+        }
+    }
+}
+
+impl From<Client> for SharedClient {
+    fn from(client: Client) -> Self {
+        client.into_shared()
+    }
+}
+
+impl Deref for SharedClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.inner
+    }
+}
+
+impl AsRef<Client> for SharedClient {
+    fn as_ref(&self) -> &Client {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn clones_share_the_client() {
+        let mock_server = mock("GET", "/ready").expect_at_least(2).create();
+
+        let shared = Client::new(mockito::server_url(), "org", "").into_shared();
+        let clone = shared.clone();
+
+        let _result = shared.ready().await;
+        let _result = clone.ready().await;
+
+        mock_server.assert();
+        assert!(std::ptr::eq(shared.as_ref(), clone.as_ref()));
+    }
+}
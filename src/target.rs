@@ -0,0 +1,78 @@
+//! Call targets
+
+/// The organization and bucket a call operates on.
+///
+/// Unset parts fall back to the defaults of the client: its organization and
+/// the bucket given to `ClientBuilder::with_bucket`. A bucket name converts
+/// into a `Target` directly, so `client.write("bucket", points)` overrides
+/// only the bucket.
+///
+/// The calls on the data of a bucket take a target: `write`,
+/// `write_line_protocol_to` and `delete`. So do the `_to` variants of the
+/// queries, such as `query_to`, which use only its organization, and of the
+/// management calls naming an organization or bucket: `ensure_bucket_to`,
+/// `list_tasks_to`, `ensure_task_to`, `label_id_by_name_to` and
+/// `ensure_labels_to`. Other calls, which name buckets and organizations by
+/// ID or in their request, use the defaults of `Client::with_target`.
+///
+/// ```
+/// use influxdb2::Target;
+///
+/// let bucket_only: Target = "metrics".into();
+/// let elsewhere = Target::org("other-org").with_bucket("metrics");
+/// assert_eq!(bucket_only.bucket, elsewhere.bucket);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Target {
+    /// The organization, instead of the client's
+    pub org: Option<String>,
+    /// The bucket, instead of the client's default bucket
+    pub bucket: Option<String>,
+}
+
+impl Target {
+    /// A target using the defaults of the client.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A target overriding the organization.
+    pub fn org(org: impl Into<String>) -> Self {
+        Self::new().with_org(org)
+    }
+
+    /// A target overriding the bucket.
+    pub fn bucket(bucket: impl Into<String>) -> Self {
+        Self::new().with_bucket(bucket)
+    }
+
+    /// Sets the organization.
+    pub fn with_org(mut self, org: impl Into<String>) -> Self {
+        self.org = Some(org.into());
+        self
+    }
+
+    /// Sets the bucket.
+    pub fn with_bucket(mut self, bucket: impl Into<String>) -> Self {
+        self.bucket = Some(bucket.into());
+        self
+    }
+}
+
+impl From<&str> for Target {
+    fn from(bucket: &str) -> Self {
+        Self::bucket(bucket)
+    }
+}
+
+impl From<String> for Target {
+    fn from(bucket: String) -> Self {
+        Self::bucket(bucket)
+    }
+}
+
+impl From<&String> for Target {
+    fn from(bucket: &String) -> Self {
+        Self::bucket(bucket.as_str())
+    }
+}
@@ -0,0 +1,307 @@
+//! Pre-aggregation
+//!
+//! Chatty sources can emit far more points than anyone reads. An
+//! [`Aggregation`] collapses the points of chosen measurements into one point
+//! per series and window before they are written, e.g. the mean and maximum
+//! of every 10 seconds. Hand it to the `with_aggregation` method of a sink:
+//!
+//! ```
+//! use std::time::Duration;
+//! use influxdb2::telemetry::aggregate::{Aggregate, Aggregation};
+//!
+//! let aggregation = Aggregation::new()
+//!     .measurement("my_app::http", Duration::from_secs(10), [Aggregate::Mean, Aggregate::Max]);
+//! ```
+//!
+//! Each numeric field `f` of a window becomes the fields `f_mean`, `f_max`
+//! and so on, as floats but for `f_count`. Other fields keep their last
+//! value under their own name. The point of a window is timestamped at its
+//! start and written with the first batch after it ends; a point arriving
+//! once its window was written starts the window over, and the new point
+//! overwrites the first one. Measurements without a rule are written as
+//! they are.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::models::{DataPoint, FieldValue};
+
+/// A function collapsing the values of a field in a window
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    /// The average value, written as `<field>_mean`
+    Mean,
+    /// The smallest value, written as `<field>_min`
+    Min,
+    /// The largest value, written as `<field>_max`
+    Max,
+    /// The sum of the values, written as `<field>_sum`
+    Sum,
+    /// The number of values, written as `<field>_count`
+    Count,
+    /// The latest value, written as `<field>_last`
+    Last,
+}
+
+impl Aggregate {
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Mean => "mean",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Sum => "sum",
+            Self::Count => "count",
+            Self::Last => "last",
+        }
+    }
+}
+
+/// Which measurements to aggregate, over which windows and how
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aggregation {
+    rules: HashMap<String, Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    /// The length of a window in nanoseconds
+    window: i64,
+    aggregates: Vec<Aggregate>,
+}
+
+impl Aggregation {
+    /// Aggregate nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collapse the points of `measurement` into windows of `window` with
+    /// `aggregates`, replacing any rule it had.
+    pub fn measurement(
+        mut self,
+        measurement: impl Into<String>,
+        window: Duration,
+        aggregates: impl IntoIterator<Item = Aggregate>,
+    ) -> Self {
+        let window = i64::try_from(window.as_nanos()).unwrap_or(i64::MAX).max(1);
+        let rule = Rule {
+            window,
+            aggregates: aggregates.into_iter().collect(),
+        };
+        self.rules.insert(measurement.into(), rule);
+        self
+    }
+}
+
+/// The windows being aggregated by a sink
+#[derive(Debug, Default)]
+pub(crate) struct Aggregator {
+    aggregation: Aggregation,
+    windows: HashMap<WindowKey, Window>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct WindowKey {
+    measurement: String,
+    tags: Vec<(String, String)>,
+    start: i64,
+}
+
+#[derive(Debug)]
+struct Window {
+    end: i64,
+    aggregates: Vec<Aggregate>,
+    fields: BTreeMap<String, FieldState>,
+}
+
+#[derive(Debug)]
+enum FieldState {
+    Numbers {
+        count: i64,
+        sum: f64,
+        min: f64,
+        max: f64,
+        last: f64,
+    },
+    Other(FieldValue),
+}
+
+impl Aggregator {
+    pub(crate) fn set_aggregation(&mut self, aggregation: Aggregation) {
+        self.aggregation = aggregation;
+    }
+
+    /// Fold `point` into its window, or give it back if its measurement
+    /// isn't aggregated. Points without a timestamp fall in the window of
+    /// `now`.
+    pub(crate) fn push(&mut self, point: DataPoint, now: i64) -> Option<DataPoint> {
+        let rule = match self.aggregation.rules.get(point.measurement()) {
+            Some(rule) => rule,
+            None => return Some(point),
+        };
+        let timestamp = point.timestamp().unwrap_or(now);
+        let start = timestamp - timestamp.rem_euclid(rule.window);
+        let key = WindowKey {
+            measurement: point.measurement().to_string(),
+            tags: point
+                .tags()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            start,
+        };
+        let window = self.windows.entry(key).or_insert_with(|| Window {
+            end: start.saturating_add(rule.window),
+            aggregates: rule.aggregates.clone(),
+            fields: BTreeMap::new(),
+        });
+
+        for (name, value) in point.fields() {
+            let number = match value {
+                FieldValue::F64(value) => *value,
+                FieldValue::I64(value) => *value as f64,
+                FieldValue::U64(value) => *value as f64,
+                other => {
+                    window
+                        .fields
+                        .insert(name.clone(), FieldState::Other(other.clone()));
+                    continue;
+                }
+            };
+            match window.fields.get_mut(name) {
+                Some(FieldState::Numbers {
+                    count,
+                    sum,
+                    min,
+                    max,
+                    last,
+                }) => {
+                    *count += 1;
+                    *sum += number;
+                    *min = min.min(number);
+                    *max = max.max(number);
+                    *last = number;
+                }
+                _ => {
+                    window.fields.insert(
+                        name.clone(),
+                        FieldState::Numbers {
+                            count: 1,
+                            sum: number,
+                            min: number,
+                            max: number,
+                            last: number,
+                        },
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    /// The points of the windows that ended by `until`, or of every window
+    /// if `None`, forgetting those windows.
+    pub(crate) fn drain(&mut self, until: Option<i64>) -> Vec<DataPoint> {
+        let (ended, open): (HashMap<_, _>, _) = std::mem::take(&mut self.windows)
+            .into_iter()
+            .partition(|(_, window)| until.is_none_or(|until| window.end <= until));
+        self.windows = open;
+
+        let mut points: Vec<_> = ended
+            .into_iter()
+            .filter_map(|(key, window)| window.point(key))
+            .collect();
+        points.sort_by_key(|point| point.timestamp());
+        points
+    }
+}
+
+impl Window {
+    fn point(self, key: WindowKey) -> Option<DataPoint> {
+        let mut point = DataPoint::builder(key.measurement).timestamp(key.start);
+        for (name, value) in key.tags {
+            point = point.tag(name, value);
+        }
+        for (name, state) in self.fields {
+            match state {
+                FieldState::Numbers {
+                    count,
+                    sum,
+                    min,
+                    max,
+                    last,
+                } => {
+                    for aggregate in &self.aggregates {
+                        let value = match aggregate {
+                            Aggregate::Mean => FieldValue::F64(sum / count as f64),
+                            Aggregate::Min => FieldValue::F64(min),
+                            Aggregate::Max => FieldValue::F64(max),
+                            Aggregate::Sum => FieldValue::F64(sum),
+                            Aggregate::Count => FieldValue::I64(count),
+                            Aggregate::Last => FieldValue::F64(last),
+                        };
+                        point = point.field(format!("{}_{}", name, aggregate.suffix()), value);
+                    }
+                }
+                FieldState::Other(value) => point = point.field(name, value),
+            }
+        }
+        point.build().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(measurement: &str, host: &str, value: i64, timestamp: i64) -> DataPoint {
+        DataPoint::builder(measurement)
+            .tag("host", host)
+            .field("latency", value)
+            .field("route", "/health")
+            .timestamp(timestamp)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn collapses_series_into_windows() {
+        let mut aggregator = Aggregator::default();
+        aggregator.set_aggregation(Aggregation::new().measurement(
+            "http",
+            Duration::from_nanos(10),
+            [Aggregate::Mean, Aggregate::Max, Aggregate::Count],
+        ));
+
+        assert_eq!(aggregator.push(point("http", "a", 1, 21), 0), None);
+        assert_eq!(aggregator.push(point("http", "a", 4, 29), 0), None);
+        assert_eq!(aggregator.push(point("http", "b", 7, 25), 0), None);
+        assert_eq!(aggregator.push(point("http", "a", 9, 31), 0), None);
+        let passed = point("other", "a", 1, 22);
+        assert_eq!(aggregator.push(passed.clone(), 0), Some(passed));
+
+        let mut ended = aggregator.drain(Some(30));
+        ended.sort_by(|a, b| a.tags().cmp(b.tags()));
+        let expected = |host: &str, mean: f64, max: f64, count: i64| {
+            DataPoint::builder("http")
+                .tag("host", host)
+                .field("latency_mean", mean)
+                .field("latency_max", max)
+                .field("latency_count", count)
+                .field("route", "/health")
+                .timestamp(20)
+                .build()
+                .unwrap()
+        };
+        assert_eq!(
+            ended,
+            [expected("a", 2.5, 4.0, 2), expected("b", 7.0, 7.0, 1)]
+        );
+
+        assert_eq!(aggregator.drain(Some(30)), []);
+        let rest = aggregator.drain(None);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].timestamp(), Some(30));
+    }
+}
@@ -0,0 +1,339 @@
+//! Dropped points
+//!
+//! The sinks drop points when their buffer is full or a write fails, as
+//! telemetry mustn't slow down or fail the application. So that lost data is
+//! at least observable, every drop is reported to a [`DropSink`], one
+//! [`DroppedPoints`] record per measurement: by default as a `tracing`
+//! warning, or as JSON lines with [`JsonDrops`], e.g. into an audit file.
+//! Points dropped because the buffer was full are counted and reported once
+//! per flush interval. A [`WriteBatcher`](crate::batcher::WriteBatcher)
+//! reports the points of its failed batches the same way.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::time::Duration;
+//! use influxdb2::telemetry::dropped::JsonDrops;
+//! use influxdb2::telemetry::logger::InfluxLogger;
+//!
+//! # async fn example(client: influxdb2::Client) -> std::io::Result<()> {
+//! let audit = File::create("dropped.jsonl")?;
+//! InfluxLogger::new(client, "logs", Duration::from_secs(1))
+//!     .with_drop_sink(JsonDrops::new(audit))
+//!     .init(log::LevelFilter::Info)
+//!     .expect("no other logger is installed");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::TARGET;
+use crate::models::DataPoint;
+use crate::RequestError;
+
+/// Points of one measurement dropped at once
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DroppedPoints {
+    /// The measurement of the points
+    pub measurement: String,
+    /// How many points were dropped
+    pub count: usize,
+    /// Why they were dropped
+    pub reason: DropReason,
+    /// The earliest timestamp of the points, in nanoseconds
+    pub first_timestamp: Option<i64>,
+    /// The latest timestamp of the points, in nanoseconds
+    pub last_timestamp: Option<i64>,
+}
+
+/// Why points were dropped
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DropReason {
+    /// The buffer of the sink was full
+    BufferFull,
+    /// The server refused the write for good, with a 4xx status
+    Rejected {
+        /// The status of the response
+        status: u16,
+        /// The body of the response
+        message: String,
+    },
+    /// The write failed otherwise, e.g. the server was unreachable
+    WriteFailed {
+        /// The error of the write
+        message: String,
+    },
+}
+
+impl DropReason {
+    /// The reason for dropping the points of a write failing with `error`.
+    pub(crate) fn from_error(error: &RequestError) -> Self {
+        match error {
+            RequestError::Http { status, text, .. }
+                if status.is_client_error()
+                    && *status != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                Self::Rejected {
+                    status: status.as_u16(),
+                    message: text.clone(),
+                }
+            }
+            _ => Self::WriteFailed {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferFull => f.write_str("buffer full"),
+            Self::Rejected { status, message } => write!(f, "rejected with {status}: {message}"),
+            Self::WriteFailed { message } => write!(f, "write failed: {message}"),
+        }
+    }
+}
+
+/// Where dropped points are reported
+///
+/// Implemented for closures taking a `&DroppedPoints`.
+pub trait DropSink: Send + Sync + 'static {
+    /// Report that the points of `record` were dropped.
+    fn dropped(&self, record: &DroppedPoints);
+}
+
+impl<F> DropSink for F
+where
+    F: Fn(&DroppedPoints) + Send + Sync + 'static,
+{
+    fn dropped(&self, record: &DroppedPoints) {
+        self(record)
+    }
+}
+
+/// Reports dropped points as `tracing` warnings with the target
+/// `influxdb2::telemetry`, the default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingDrops;
+
+impl DropSink for TracingDrops {
+    fn dropped(&self, record: &DroppedPoints) {
+        tracing::warn!(
+            target: TARGET,
+            measurement = %record.measurement,
+            count = record.count,
+            reason = %record.reason,
+            first_timestamp = record.first_timestamp,
+            last_timestamp = record.last_timestamp,
+            "dropping telemetry points"
+        );
+    }
+}
+
+/// Reports dropped points as JSON objects, one per line
+#[derive(Debug)]
+pub struct JsonDrops<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send + 'static> JsonDrops<W> {
+    /// Write the records to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> DropSink for JsonDrops<W> {
+    fn dropped(&self, record: &DroppedPoints) {
+        let mut writer = self.writer.lock();
+        // Reporting must not fail the sink, so write errors are ignored
+        let _ = serde_json::to_writer(&mut *writer, record);
+        let _ = writer.write_all(b"\n").and_then(|()| writer.flush());
+    }
+}
+
+/// The drop sink of a telemetry sink or write batcher and the points dropped for a full
+/// buffer since the last report
+pub(crate) struct Drops {
+    sink: Mutex<Arc<dyn DropSink>>,
+    overflow: Mutex<Tally>,
+}
+
+impl Default for Drops {
+    fn default() -> Self {
+        Self {
+            sink: Mutex::new(Arc::new(TracingDrops)),
+            overflow: Mutex::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Drops {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Drops")
+            .field("overflow", &self.overflow)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drops {
+    pub(crate) fn set_sink(&self, sink: impl DropSink) {
+        *self.sink.lock() = Arc::new(sink);
+    }
+
+    #[cfg(any(
+        feature = "tracing-layer",
+        feature = "log-appender",
+        feature = "opentelemetry"
+    ))]
+    /// Count `point` as dropped for a full buffer.
+    pub(crate) fn overflowed(&self, point: &DataPoint) {
+        self.overflow.lock().add(point);
+    }
+
+    #[cfg(any(
+        feature = "tracing-layer",
+        feature = "log-appender",
+        feature = "opentelemetry"
+    ))]
+    /// Report the points dropped for a full buffer since the last call.
+    pub(crate) fn report_overflow(&self) {
+        let overflow = std::mem::take(&mut *self.overflow.lock());
+        self.report(overflow, DropReason::BufferFull);
+    }
+
+    /// Report the points of `tally` as dropped for `reason`.
+    pub(crate) fn report(&self, tally: Tally, reason: DropReason) {
+        let sink = Arc::clone(&self.sink.lock());
+        for (measurement, counts) in tally.0 {
+            sink.dropped(&DroppedPoints {
+                measurement,
+                count: counts.count,
+                reason: reason.clone(),
+                first_timestamp: counts.first_timestamp,
+                last_timestamp: counts.last_timestamp,
+            });
+        }
+    }
+}
+
+/// The number of points and their timestamp range per measurement, kept
+/// until they are known to be dropped
+#[derive(Debug, Default)]
+pub(crate) struct Tally(BTreeMap<String, Counts>);
+
+#[derive(Debug, Default)]
+struct Counts {
+    count: usize,
+    first_timestamp: Option<i64>,
+    last_timestamp: Option<i64>,
+}
+
+impl Tally {
+    pub(crate) fn of(points: &[DataPoint]) -> Self {
+        let mut tally = Self::default();
+        for point in points {
+            tally.add(point);
+        }
+        tally
+    }
+
+    fn add(&mut self, point: &DataPoint) {
+        let counts = match self.0.get_mut(point.measurement()) {
+            Some(counts) => counts,
+            None => self.0.entry(point.measurement().to_string()).or_default(),
+        };
+        counts.count += 1;
+        if let Some(timestamp) = point.timestamp() {
+            counts.first_timestamp = Some(counts.first_timestamp.map_or(timestamp, |t| t.min(timestamp)));
+            counts.last_timestamp = Some(counts.last_timestamp.map_or(timestamp, |t| t.max(timestamp)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(
+        feature = "tracing-layer",
+        feature = "log-appender",
+        feature = "opentelemetry"
+    ))]
+    fn point(measurement: &str, timestamp: i64) -> DataPoint {
+        DataPoint::builder(measurement)
+            .field("value", 1)
+            .timestamp(timestamp)
+            .build()
+            .unwrap()
+    }
+
+    #[cfg(any(
+        feature = "tracing-layer",
+        feature = "log-appender",
+        feature = "opentelemetry"
+    ))]
+    #[test]
+    fn reports_one_record_per_measurement() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let drops = Drops::default();
+        let sink = Arc::clone(&reported);
+        drops.set_sink(move |record: &DroppedPoints| sink.lock().push(record.clone()));
+
+        for timestamp in [20, 10, 30] {
+            drops.overflowed(&point("cpu", timestamp));
+        }
+        drops.overflowed(&point("mem", 5));
+        drops.report_overflow();
+        drops.report_overflow();
+
+        let reported = reported.lock();
+        assert_eq!(reported.len(), 2);
+        assert_eq!(
+            reported[0],
+            DroppedPoints {
+                measurement: "cpu".to_string(),
+                count: 3,
+                reason: DropReason::BufferFull,
+                first_timestamp: Some(10),
+                last_timestamp: Some(30),
+            }
+        );
+        assert_eq!(reported[1].measurement, "mem");
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let drops = JsonDrops::new(Vec::new());
+        drops.dropped(&DroppedPoints {
+            measurement: "cpu".to_string(),
+            count: 2,
+            reason: DropReason::Rejected {
+                status: 400,
+                message: "unable to parse".to_string(),
+            },
+            first_timestamp: Some(1),
+            last_timestamp: Some(2),
+        });
+
+        let line = String::from_utf8(drops.writer.into_inner()).unwrap();
+        assert_eq!(
+            line,
+            concat!(
+                r#"{"measurement":"cpu","count":2,"reason":{"kind":"rejected","status":400,"#,
+                r#""message":"unable to parse"},"first_timestamp":1,"last_timestamp":2}"#,
+                "\n"
+            )
+        );
+    }
+}
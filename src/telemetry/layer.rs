@@ -0,0 +1,254 @@
+//! tracing layer
+//!
+//! An [`InfluxLayer`] writes every `tracing` event as a point:
+//!
+//! - the measurement is the target of the event, e.g. `my_app::db`,
+//! - the fields are the values recorded by the event, with its message in
+//!   `message`,
+//! - the tags are the level of the event, the name of the span it occurred
+//!   in, and the values recorded by that span and its parents.
+//!
+//! Events of this crate and of the HTTP stack it uses are ignored, so writing
+//! the points doesn't emit more of them.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use influxdb2::telemetry::layer::InfluxLayer;
+//! use tracing_subscriber::prelude::*;
+//!
+//! # async fn example(client: influxdb2::Client) {
+//! let layer = InfluxLayer::new(client, "telemetry", Duration::from_secs(1));
+//! tracing_subscriber::registry().with(layer).init();
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::aggregate::Aggregation;
+use super::dropped::DropSink;
+use super::{is_internal, PointSink};
+use crate::models::{DataPoint, FieldValue};
+use crate::{Client, Target};
+
+/// A `tracing-subscriber` layer writing events to InfluxDB.
+#[derive(Debug)]
+pub struct InfluxLayer {
+    sink: PointSink,
+}
+
+impl InfluxLayer {
+    /// Write events to `target` through `client`, in batches at least every
+    /// `flush_interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(client: Client, target: impl Into<Target>, flush_interval: Duration) -> Self {
+        Self {
+            sink: PointSink::spawn(client, target.into(), flush_interval),
+        }
+    }
+
+    /// Collapse the events of the measurements of `aggregation` into windows
+    /// before writing them.
+    pub fn with_aggregation(self, aggregation: Aggregation) -> Self {
+        self.sink.set_aggregation(aggregation);
+        self
+    }
+
+    /// Report the points dropped for a full buffer or a failed write to
+    /// `sink` instead of logging them as warnings.
+    pub fn with_drop_sink(self, sink: impl DropSink) -> Self {
+        self.sink.set_drop_sink(sink);
+        self
+    }
+}
+
+/// The values recorded by a span, kept as tags of the events inside it
+#[derive(Default)]
+struct SpanTags(BTreeMap<String, String>);
+
+impl<S> Layer<S> for InfluxLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut tags = TagVisitor::default();
+            attrs.record(&mut tags);
+            span.extensions_mut().insert(SpanTags(tags.0));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if let Some(SpanTags(tags)) = extensions.get_mut::<SpanTags>() {
+                let mut visitor = TagVisitor(std::mem::take(tags));
+                values.record(&mut visitor);
+                *tags = visitor.0;
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if is_internal(metadata.target()) {
+            return;
+        }
+
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        if fields.0.is_empty() {
+            return;
+        }
+
+        let mut point = DataPoint::builder(metadata.target())
+            .tag("level", metadata.level().as_str())
+            .timestamp(self.sink.now());
+        if let Some(scope) = ctx.event_scope(event) {
+            let mut spans = scope.peekable();
+            if let Some(span) = spans.peek() {
+                point = point.tag("span", span.name());
+            }
+            // Outer spans first, so inner spans override their tags
+            for span in spans.collect::<Vec<_>>().into_iter().rev() {
+                if let Some(SpanTags(tags)) = span.extensions().get::<SpanTags>() {
+                    for (name, value) in tags {
+                        point = point.tag(name.as_str(), value.as_str());
+                    }
+                }
+            }
+        }
+        for (name, value) in fields.0 {
+            point = point.field(name, value);
+        }
+
+        if let Ok(point) = point.build() {
+            self.sink.push(point);
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor(Vec<(String, FieldValue)>);
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.push((field.name().to_string(), value.into()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.push((field.name().to_string(), value.into()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        let value = match i64::try_from(value) {
+            Ok(value) => value.into(),
+            Err(_) => FieldValue::U64(value),
+        };
+        self.0.push((field.name().to_string(), value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.push((field.name().to_string(), value.into()));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_string(), value.into()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((field.name().to_string(), format!("{:?}", value).into()));
+    }
+}
+
+#[derive(Default)]
+struct TagVisitor(BTreeMap<String, String>);
+
+impl Visit for TagVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+    use tracing_subscriber::prelude::*;
+
+    #[tokio::test]
+    async fn writes_events() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "telemetry".into()))
+            .match_body(Matcher::Regex(
+                r#"^my_app::db,level=INFO,span=query,table=users message="done",rows=3i \d+\n$"#
+                    .to_string(),
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let layer = InfluxLayer::new(client, "telemetry", Duration::from_secs(60));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("query", table = "users");
+            let _entered = span.enter();
+            tracing::info!(target: "my_app::db", rows = 3, "done");
+            tracing::info!(target: "hyper::client", "ignored");
+        });
+
+        // Dropping the subscriber flushes the layer
+        for _ in 0..100 {
+            if mock_server.matched() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock_server.assert();
+    }
+
+    #[tokio::test]
+    async fn writes_u64_beyond_i64_as_unsigned() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "unsigned".into()))
+            .match_body(Matcher::Regex(
+                r#"^my_app,level=INFO bytes=18446744073709551615u,message="sent",rows=3i \d+\n$"#
+                    .to_string(),
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let layer = InfluxLayer::new(client, "unsigned", Duration::from_secs(60));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "my_app", bytes = u64::MAX, rows = 3u64, "sent");
+        });
+
+        for _ in 0..100 {
+            if mock_server.matched() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock_server.assert();
+    }
+}
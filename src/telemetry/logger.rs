@@ -0,0 +1,148 @@
+//! log appender
+//!
+//! An [`InfluxLogger`] writes every record of the `log` crate as a point of
+//! the `app_logs` measurement, tagged with its level and target, with the
+//! message in the `message` field. Records of this crate and of the HTTP
+//! stack it uses are ignored, so writing the points doesn't log more of them.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use influxdb2::telemetry::logger::InfluxLogger;
+//!
+//! # async fn example(client: influxdb2::Client) {
+//! InfluxLogger::new(client, "logs", Duration::from_secs(1))
+//!     .init(log::LevelFilter::Info)
+//!     .expect("no other logger is installed");
+//! log::info!("started");
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use super::aggregate::Aggregation;
+use super::dropped::DropSink;
+use super::{is_internal, PointSink};
+use crate::models::DataPoint;
+use crate::{Client, Target};
+
+/// The measurement log records are written to
+pub const MEASUREMENT: &str = "app_logs";
+
+/// A `log::Log` implementation writing records to InfluxDB.
+#[derive(Debug)]
+pub struct InfluxLogger {
+    sink: PointSink,
+}
+
+impl InfluxLogger {
+    /// Write records to `target` through `client`, in batches at least every
+    /// `flush_interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(client: Client, target: impl Into<Target>, flush_interval: Duration) -> Self {
+        Self {
+            sink: PointSink::spawn(client, target.into(), flush_interval),
+        }
+    }
+
+    /// Collapse the records of the measurements of `aggregation` into windows
+    /// before writing them.
+    pub fn with_aggregation(self, aggregation: Aggregation) -> Self {
+        self.sink.set_aggregation(aggregation);
+        self
+    }
+
+    /// Report the points dropped for a full buffer or a failed write to
+    /// `sink` instead of logging them as warnings.
+    pub fn with_drop_sink(self, sink: impl DropSink) -> Self {
+        self.sink.set_drop_sink(sink);
+        self
+    }
+
+    /// Install this logger as the global logger, recording up to `level`.
+    pub fn init(self, level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for InfluxLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        !is_internal(metadata.target())
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut point = DataPoint::builder(MEASUREMENT)
+            .tag("level", record.level().as_str())
+            .tag("target", record.target())
+            .field("message", record.args().to_string())
+            .timestamp(self.sink.now());
+        if let Some(file) = record.file() {
+            point = point.field("file", file);
+        }
+        if let Some(line) = record.line() {
+            point = point.field("line", i64::from(line));
+        }
+
+        if let Ok(point) = point.build() {
+            self.sink.push(point);
+        }
+    }
+
+    /// Points are written from a background task, which writes what is left
+    /// once the logger is dropped.
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn writes_records() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "logs".into()))
+            .match_body(Matcher::Regex(
+                r#"^app_logs,level=WARN,target=my_app message="disk \\"data\\" almost full" \d+\n$"#
+                    .to_string(),
+            ))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let logger = InfluxLogger::new(client, "logs", Duration::from_secs(60));
+
+        logger.log(
+            &Record::builder()
+                .level(log::Level::Warn)
+                .target("my_app")
+                .args(format_args!("disk {:?} almost full", "data"))
+                .build(),
+        );
+        logger.log(
+            &Record::builder()
+                .target("hyper::proto")
+                .args(format_args!("ignored"))
+                .build(),
+        );
+        // Dropping the logger flushes it
+        drop(logger);
+
+        for _ in 0..100 {
+            if mock_server.matched() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock_server.assert();
+    }
+}
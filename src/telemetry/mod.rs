@@ -0,0 +1,89 @@
+//! Telemetry sinks
+//!
+//! Adapters that turn the telemetry of an application into points and write
+//! them to InfluxDB in batches from a background task, so a service can ship
+//! its telemetry without running Telegraf. Each adapter sits behind its own
+//! feature and requires a tokio runtime:
+//!
+//! - `tracing-layer`: [`InfluxLayer`](layer::InfluxLayer), a
+//!   `tracing-subscriber` layer writing events
+//! - `log-appender`: [`InfluxLogger`](logger::InfluxLogger), a `log` logger
+//!   writing records
+//! - `metrics-exporter`: [`InfluxRecorder`](recorder::InfluxRecorder), a
+//!   `metrics` recorder writing snapshots
+//! - `opentelemetry`: [`InfluxExporter`](otel::InfluxExporter), an
+//!   OpenTelemetry metrics exporter
+//!
+//! The [`dropped`] module is also built with the `write-batcher` feature,
+//! for the drop sink of a `WriteBatcher`.
+//!
+//! The layer, logger and exporter can collapse the points of chatty
+//! measurements into windows before writing them, see [`aggregate`].
+//!
+//! The adapters write through a [`WriteBatcher`](crate::batcher::WriteBatcher)
+//! each. Points dropped because a buffer is full or a write fails are
+//! reported to a pluggable sink, see [`dropped`].
+
+#[cfg(any(
+    feature = "tracing-layer",
+    feature = "log-appender",
+    feature = "opentelemetry"
+))]
+pub mod aggregate;
+pub mod dropped;
+#[cfg(feature = "tracing-layer")]
+pub mod layer;
+#[cfg(feature = "log-appender")]
+pub mod logger;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+#[cfg(feature = "metrics-exporter")]
+pub mod recorder;
+#[cfg(any(
+    feature = "tracing-layer",
+    feature = "log-appender",
+    feature = "opentelemetry"
+))]
+mod sink;
+
+#[cfg(any(
+    feature = "tracing-layer",
+    feature = "log-appender",
+    feature = "opentelemetry"
+))]
+pub(crate) use sink::PointSink;
+
+/// Events of the sinks themselves go to this target, which the sinks ignore,
+/// so a failing write doesn't feed back into more writes.
+const TARGET: &str = "influxdb2::telemetry";
+
+#[cfg(any(feature = "tracing-layer", feature = "log-appender"))]
+/// This crate and the HTTP stack it writes through
+const INTERNAL_TARGETS: &[&str] = &[
+    "influxdb2",
+    "reqwest",
+    "hyper",
+    "h2",
+    "want",
+    "mio",
+    "tokio",
+];
+
+#[cfg(any(feature = "tracing-layer", feature = "log-appender"))]
+/// Whether events of `target` must not be written, because writing would
+/// emit more of them
+pub(crate) fn is_internal(target: &str) -> bool {
+    let krate = target.split("::").next().unwrap_or(target);
+    INTERNAL_TARGETS.contains(&krate)
+}
+
+#[cfg(any(
+    feature = "tracing-layer",
+    feature = "log-appender",
+    feature = "metrics-exporter",
+    feature = "opentelemetry"
+))]
+/// The time of `clock` in nanoseconds since the UNIX epoch
+pub(crate) fn now(clock: &dyn crate::clock::Clock) -> i64 {
+    clock.now().timestamp_nanos()
+}
@@ -0,0 +1,245 @@
+//! OpenTelemetry metrics exporter
+//!
+//! An [`InfluxExporter`] is a push exporter for the OpenTelemetry metrics
+//! SDK. It maps each data point onto line protocol the way InfluxData's
+//! OpenTelemetry bridges do with their Prometheus-compatible schema: the
+//! measurement is named after the metric and tagged with the resource, the
+//! instrumentation library and the point's attributes.
+//!
+//! - sums write their value in the `counter` field,
+//! - last values write theirs in the `gauge` field,
+//! - histograms write the `count` and `sum` fields and one field per bucket,
+//!   named after its upper bound, with the cumulative count of the values up
+//!   to it, `+Inf` included.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use influxdb2::telemetry::otel::InfluxExporter;
+//! use opentelemetry::sdk::metrics::{controllers, processors, selectors};
+//! use opentelemetry::{global, runtime, Context};
+//!
+//! # async fn example(client: influxdb2::Client) {
+//! let exporter = InfluxExporter::new(client, "metrics", Duration::from_secs(1));
+//! let controller = controllers::basic(processors::factory(
+//!     selectors::simple::inexpensive(),
+//!     exporter.temporality_selector(),
+//! ))
+//! .with_exporter(exporter)
+//! .build();
+//! controller.start(&Context::current(), runtime::Tokio).unwrap();
+//! global::set_meter_provider(controller);
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+
+use opentelemetry::metrics::Result;
+use opentelemetry::sdk::export::metrics::aggregation::{
+    cumulative_temporality_selector, AggregationKind, Count, Histogram, LastValue, Sum,
+    Temporality, TemporalitySelector,
+};
+use opentelemetry::sdk::export::metrics::{InstrumentationLibraryReader, MetricsExporter, Record};
+use opentelemetry::sdk::metrics::aggregators::{
+    HistogramAggregator, LastValueAggregator, SumAggregator,
+};
+use opentelemetry::sdk::metrics::sdk_api::Descriptor;
+use opentelemetry::sdk::Resource;
+use opentelemetry::{Context, InstrumentationLibrary};
+
+use super::aggregate::Aggregation;
+use super::dropped::DropSink;
+use super::PointSink;
+use crate::models::data_point::DataPointBuilder;
+use crate::models::DataPoint;
+use crate::{Client, Target};
+
+/// An OpenTelemetry metrics exporter writing data points to InfluxDB.
+///
+/// Sums and histograms are exported cumulatively, like Prometheus counters.
+#[derive(Debug)]
+pub struct InfluxExporter {
+    sink: PointSink,
+}
+
+impl InfluxExporter {
+    /// Write data points to `target` through `client`, in batches at least
+    /// every `flush_interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(client: Client, target: impl Into<Target>, flush_interval: Duration) -> Self {
+        Self {
+            sink: PointSink::spawn(client, target.into(), flush_interval),
+        }
+    }
+
+    /// Collapse the data points of the measurements of `aggregation` into windows
+    /// before writing them.
+    pub fn with_aggregation(self, aggregation: Aggregation) -> Self {
+        self.sink.set_aggregation(aggregation);
+        self
+    }
+
+    /// Report the points dropped for a full buffer or a failed write to
+    /// `sink` instead of logging them as warnings.
+    pub fn with_drop_sink(self, sink: impl DropSink) -> Self {
+        self.sink.set_drop_sink(sink);
+        self
+    }
+
+    /// The temporality selector for the processor feeding this exporter.
+    pub fn temporality_selector(&self) -> impl TemporalitySelector + Clone {
+        cumulative_temporality_selector()
+    }
+}
+
+impl TemporalitySelector for InfluxExporter {
+    fn temporality_for(&self, descriptor: &Descriptor, kind: &AggregationKind) -> Temporality {
+        cumulative_temporality_selector().temporality_for(descriptor, kind)
+    }
+}
+
+impl MetricsExporter for InfluxExporter {
+    fn export(
+        &self,
+        _cx: &Context,
+        resource: &Resource,
+        reader: &dyn InstrumentationLibraryReader,
+    ) -> Result<()> {
+        reader.try_for_each(&mut |library, records| {
+            records.try_for_each(self, &mut |record| {
+                if let Some(point) = to_point(resource, library, record)? {
+                    self.sink.push(point);
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+/// The point of `record`, if its aggregation has a mapping
+fn to_point(
+    resource: &Resource,
+    library: &InstrumentationLibrary,
+    record: &Record<'_>,
+) -> Result<Option<DataPoint>> {
+    let aggregator = match record.aggregator() {
+        Some(aggregator) => aggregator.as_any(),
+        None => return Ok(None),
+    };
+    let kind = record.descriptor().number_kind();
+    let mut point = DataPoint::builder(record.descriptor().name());
+
+    if let Some(sum) = aggregator.downcast_ref::<SumAggregator>() {
+        point = point.field("counter", sum.sum()?.to_f64(kind));
+    } else if let Some(last_value) = aggregator.downcast_ref::<LastValueAggregator>() {
+        let (value, _) = last_value.last_value()?;
+        point = point.field("gauge", value.to_f64(kind));
+    } else if let Some(histogram) = aggregator.downcast_ref::<HistogramAggregator>() {
+        let count = histogram.count()?;
+        let buckets = histogram.histogram()?;
+        let mut cumulative = 0.0;
+        for (bound, bucket) in buckets.boundaries().iter().zip(buckets.counts()) {
+            cumulative += bucket;
+            point = point.field(bound.to_string(), cumulative);
+        }
+        point = point
+            .field("+Inf", count as f64)
+            .field("count", count as f64)
+            .field("sum", histogram.sum()?.to_f64(kind));
+    } else {
+        return Ok(None);
+    }
+
+    point = tags(point, resource, library, record).timestamp(nanos(*record.end_time()));
+    Ok(point.build().ok())
+}
+
+fn tags(
+    mut point: DataPointBuilder,
+    resource: &Resource,
+    library: &InstrumentationLibrary,
+    record: &Record<'_>,
+) -> DataPointBuilder {
+    for (key, value) in resource {
+        point = point.tag(key.as_str(), value.as_str());
+    }
+    if !library.name.is_empty() {
+        point = point.tag("otel.library.name", library.name.as_ref());
+    }
+    if let Some(version) = &library.version {
+        point = point.tag("otel.library.version", version.as_ref());
+    }
+    for (key, value) in record.attributes() {
+        point = point.tag(key.as_str(), value.as_str());
+    }
+    point
+}
+
+/// Nanoseconds since the UNIX epoch
+fn nanos(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .and_then(|since| i64::try_from(since.as_nanos()).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::sdk::metrics::{controllers, processors, selectors};
+    use opentelemetry::KeyValue;
+
+    #[tokio::test]
+    async fn exports_data_points() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "metrics".into()))
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(
+                    r#"(?m)^requests,otel.library.name=app,route=/health counter=3 \d+$"#
+                        .to_string(),
+                ),
+                Matcher::Regex(
+                    r#"(?m)^latency,otel.library.name=app \+Inf=2,1=1,5=2,count=2,sum=3 \d+$"#
+                        .to_string(),
+                ),
+            ]))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let exporter = InfluxExporter::new(client, "metrics", Duration::from_millis(20));
+        let controller = controllers::basic(processors::factory(
+            selectors::simple::histogram([1.0, 5.0]),
+            exporter.temporality_selector(),
+        ))
+        .with_resource(Resource::empty())
+        .build();
+
+        let cx = Context::new();
+        let meter = controller.versioned_meter("app", None, None);
+        meter
+            .u64_counter("requests")
+            .init()
+            .add(&cx, 3, &[KeyValue::new("route", "/health")]);
+        let latency = meter.f64_histogram("latency").init();
+        latency.record(&cx, 0.5, &[]);
+        latency.record(&cx, 2.5, &[]);
+
+        controller.collect(&cx).unwrap();
+        exporter
+            .export(&cx, controller.resource(), &controller)
+            .unwrap();
+
+        for _ in 0..100 {
+            if mock_server.matched() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock_server.assert();
+    }
+}
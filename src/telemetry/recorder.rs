@@ -0,0 +1,234 @@
+//! metrics recorder
+//!
+//! An [`InfluxRecorder`] collects the metrics of the `metrics` crate and
+//! writes a snapshot of them every interval. Each metric becomes a point of
+//! the measurement named after it, tagged with its labels:
+//!
+//! - counters write their total in the `value` field,
+//! - gauges write their current value in the `value` field,
+//! - histograms write the `count`, `sum`, `min`, `max` and `mean` of the
+//!   values recorded since the last snapshot, and nothing when there were
+//!   none.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use influxdb2::telemetry::recorder::InfluxRecorder;
+//!
+//! # async fn example(client: influxdb2::Client) {
+//! InfluxRecorder::new(client, "metrics", Duration::from_secs(10))
+//!     .install()
+//!     .expect("no other recorder is installed");
+//! metrics::counter!("requests", "route" => "/health").increment(1);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use metrics::atomics::AtomicU64;
+use metrics::{
+    Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SetRecorderError,
+    SharedString, Unit,
+};
+use parking_lot::Mutex;
+
+use super::dropped::{DropSink, Drops};
+use super::now;
+use crate::batcher::{BatchOptions, WriteBatcher};
+use crate::models::data_point::DataPointBuilder;
+use crate::models::DataPoint;
+use crate::{Client, Target};
+
+/// A `metrics` recorder writing snapshots of the metrics to InfluxDB.
+#[derive(Debug)]
+pub struct InfluxRecorder {
+    registry: Arc<Registry>,
+    drops: Arc<Drops>,
+    // Writes the snapshots, until dropped with the recorder
+    _batcher: WriteBatcher,
+}
+
+impl InfluxRecorder {
+    /// Write a snapshot of the metrics to `target` through `client` every
+    /// `interval`, until the recorder is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(client: Client, target: impl Into<Target>, interval: Duration) -> Self {
+        let registry = Arc::new(Registry::default());
+        let drops = Arc::new(Drops::default());
+
+        let metrics = Arc::clone(&registry);
+        let clock = Arc::clone(&client.clock);
+        let tick = Box::new(move |_closing: bool| metrics.snapshot(now(&*clock)));
+        let options = BatchOptions {
+            flush_interval: interval,
+            ..BatchOptions::default()
+        };
+        let batcher = WriteBatcher::spawn(
+            client,
+            target.into(),
+            options,
+            Arc::clone(&drops),
+            Some(tick),
+        );
+
+        Self {
+            registry,
+            drops,
+            _batcher: batcher,
+        }
+    }
+
+    /// Report the points of failed writes to `sink` instead of logging them
+    /// as warnings.
+    pub fn with_drop_sink(self, sink: impl DropSink) -> Self {
+        self.drops.set_sink(sink);
+        self
+    }
+
+    /// Install this recorder as the global recorder.
+    pub fn install(self) -> Result<(), SetRecorderError<Self>> {
+        metrics::set_global_recorder(self)
+    }
+}
+
+impl Recorder for InfluxRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.registry.counters.lock().entry(key))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.registry.gauges.lock().entry(key))
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.registry.histograms.lock().entry(key))
+    }
+}
+
+/// The metrics registered so far
+#[derive(Debug, Default)]
+struct Registry {
+    counters: Mutex<Handles<AtomicU64>>,
+    gauges: Mutex<Handles<AtomicU64>>,
+    histograms: Mutex<Handles<Samples>>,
+}
+
+impl Registry {
+    fn snapshot(&self, timestamp: i64) -> Vec<DataPoint> {
+        let mut points = Vec::new();
+
+        for (key, counter) in &self.counters.lock().0 {
+            let total = counter.load(Ordering::Acquire);
+            let total = i64::try_from(total).unwrap_or(i64::MAX);
+            points.push(point(key, timestamp).field("value", total).build());
+        }
+        for (key, gauge) in &self.gauges.lock().0 {
+            let value = f64::from_bits(gauge.load(Ordering::Acquire));
+            points.push(point(key, timestamp).field("value", value).build());
+        }
+        for (key, samples) in &self.histograms.lock().0 {
+            let samples = std::mem::take(&mut *samples.0.lock());
+            if samples.is_empty() {
+                continue;
+            }
+            let sum: f64 = samples.iter().sum();
+            let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            points.push(
+                point(key, timestamp)
+                    .field("count", samples.len() as i64)
+                    .field("sum", sum)
+                    .field("min", min)
+                    .field("max", max)
+                    .field("mean", sum / samples.len() as f64)
+                    .build(),
+            );
+        }
+
+        // Every point has a field, so building never fails
+        points.into_iter().filter_map(Result::ok).collect()
+    }
+}
+
+fn point(key: &Key, timestamp: i64) -> DataPointBuilder {
+    key.labels().fold(
+        DataPoint::builder(key.name()).timestamp(timestamp),
+        |point, label| point.tag(label.key(), label.value()),
+    )
+}
+
+/// The handles of one kind of metric, by key
+#[derive(Debug)]
+struct Handles<T>(HashMap<Key, Arc<T>>);
+
+impl<T> Default for Handles<T> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<T: Default> Handles<T> {
+    fn entry(&mut self, key: &Key) -> Arc<T> {
+        Arc::clone(self.0.entry(key.clone()).or_default())
+    }
+}
+
+/// The values recorded by a histogram since the last snapshot
+#[derive(Debug, Default)]
+struct Samples(Mutex<Vec<f64>>);
+
+impl HistogramFn for Samples {
+    fn record(&self, value: f64) {
+        self.0.lock().push(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn writes_snapshots() {
+        let mock_server = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "metrics".into()))
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(r#"(?m)^requests,route=/health value=3i \d+$"#.to_string()),
+                Matcher::Regex(r#"(?m)^queue_depth value=7 \d+$"#.to_string()),
+                Matcher::Regex(
+                    r#"(?m)^latency count=2i,max=3,mean=2,min=1,sum=4 \d+$"#.to_string(),
+                ),
+            ]))
+            .create();
+
+        let client = Client::new(mockito::server_url(), "org", "");
+        let recorder = InfluxRecorder::new(client, "metrics", Duration::from_millis(20));
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("requests", "route" => "/health").increment(3);
+            metrics::gauge!("queue_depth").set(7.0);
+            metrics::histogram!("latency").record(1.0);
+            metrics::histogram!("latency").record(3.0);
+        });
+
+        for _ in 0..100 {
+            if mock_server.matched() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock_server.assert();
+    }
+}
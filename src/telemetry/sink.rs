@@ -0,0 +1,84 @@
+//! Batching of points
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use super::aggregate::{Aggregation, Aggregator};
+use super::dropped::{DropSink, Drops};
+use super::now;
+use crate::batcher::{BatchOptions, WriteBatcher};
+use crate::clock::Clock;
+use crate::models::DataPoint;
+use crate::{Client, Target};
+
+/// A [`WriteBatcher`] with the default batch size and capacity that never
+/// blocks.
+///
+/// When the buffer is full, points are dropped and reported to the
+/// `DropSink` with each write, as are the points of failed writes. The task
+/// writes what is left and stops once the sink is dropped.
+///
+/// Points of the measurements of its `Aggregation` go to the aggregator
+/// instead, whose ended windows join the batch written every flush interval.
+#[derive(Debug)]
+pub(crate) struct PointSink {
+    batcher: WriteBatcher,
+    aggregator: Arc<Mutex<Aggregator>>,
+    drops: Arc<Drops>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PointSink {
+    pub(crate) fn spawn(client: Client, target: Target, flush_interval: Duration) -> Self {
+        let aggregator = Arc::new(Mutex::new(Aggregator::default()));
+        let drops = Arc::new(Drops::default());
+        let clock = Arc::clone(&client.clock);
+
+        let windows = Arc::clone(&aggregator);
+        let dropped = Arc::clone(&drops);
+        let ticks = Arc::clone(&clock);
+        let tick = Box::new(move |closing: bool| {
+            let until = if closing { None } else { Some(now(&*ticks)) };
+            dropped.report_overflow();
+            windows.lock().drain(until)
+        });
+        let options = BatchOptions {
+            flush_interval,
+            ..BatchOptions::default()
+        };
+        let batcher = WriteBatcher::spawn(client, target, options, Arc::clone(&drops), Some(tick));
+
+        Self {
+            batcher,
+            aggregator,
+            drops,
+            clock,
+        }
+    }
+
+    /// The time of the client clock in nanoseconds, to timestamp points with
+    pub(crate) fn now(&self) -> i64 {
+        now(&*self.clock)
+    }
+
+    pub(crate) fn set_aggregation(&self, aggregation: Aggregation) {
+        self.aggregator.lock().set_aggregation(aggregation);
+    }
+
+    pub(crate) fn set_drop_sink(&self, sink: impl DropSink) {
+        self.drops.set_sink(sink);
+    }
+
+    pub(crate) fn push(&self, point: DataPoint) {
+        let now = self.now();
+        let point = match self.aggregator.lock().push(point, now) {
+            Some(point) => point,
+            None => return,
+        };
+        if let Err(point) = self.batcher.try_write(point) {
+            self.drops.overflowed(&point);
+        }
+    }
+}
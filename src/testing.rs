@@ -0,0 +1,176 @@
+//! Integration testing
+//!
+//! Starts a throwaway InfluxDB 2.x server in a Docker container through
+//! `testcontainers`, onboards it, and hands back a client configured for it,
+//! so downstream crates can test against a real server:
+//!
+//! ```no_run
+//! use influxdb2::testing::InfluxContainer;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let influx = InfluxContainer::start().await?;
+//! let client = influx.client();
+//! client.write_line_protocol_to(influx.bucket(), "cpu usage=0.5").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The container is removed when the `InfluxContainer` is dropped.
+
+use std::time::Duration;
+
+use snafu::{OptionExt, ResultExt, Snafu};
+use testcontainers::core::IntoContainerPort;
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, TestcontainersError};
+
+use crate::{BuildError, Client, RequestError};
+
+/// The image tag started by `InfluxContainer::start`
+pub const DEFAULT_TAG: &str = "2.7";
+
+const PORT: u16 = 8086;
+const USERNAME: &str = "admin";
+const PASSWORD: &str = "admin-password";
+const ORG: &str = "test-org";
+const BUCKET: &str = "test-bucket";
+
+/// How long the server may take to answer after the container starts
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Errors that occur while starting a test server
+#[derive(Debug, Snafu)]
+pub enum TestingError {
+    /// The container couldn't be started.
+    #[snafu(display("Unable to start the container: {}", source))]
+    Container {
+        /// The testcontainers error
+        source: TestcontainersError,
+    },
+
+    /// The server didn't become ready in time.
+    #[snafu(display("InfluxDB at {} wasn't ready after {:?}", url, STARTUP_TIMEOUT))]
+    NotReady {
+        /// The URL of the server
+        url: String,
+    },
+
+    /// Onboarding failed.
+    #[snafu(display("Unable to onboard the server: {}", source))]
+    Onboarding {
+        /// The request error
+        source: RequestError,
+    },
+
+    /// Onboarding didn't return a token.
+    #[snafu(display("Onboarding returned no token"))]
+    MissingToken,
+
+    /// The client couldn't be built.
+    #[snafu(display("Unable to build the client: {}", source))]
+    Client {
+        /// The builder error
+        source: BuildError,
+    },
+}
+
+/// A running, onboarded InfluxDB server in a container
+#[derive(Debug)]
+pub struct InfluxContainer {
+    // Keeps the container alive; dropping it removes the container
+    _container: ContainerAsync<GenericImage>,
+    client: Client,
+    url: String,
+    token: String,
+}
+
+impl InfluxContainer {
+    /// Start the `influxdb:2.7` image.
+    pub async fn start() -> Result<Self, TestingError> {
+        Self::start_with_tag(DEFAULT_TAG).await
+    }
+
+    /// Start the `influxdb` image of `tag`, which must be a 2.x release.
+    pub async fn start_with_tag(tag: &str) -> Result<Self, TestingError> {
+        let container = GenericImage::new("influxdb", tag)
+            .with_exposed_port(PORT.tcp())
+            .start()
+            .await
+            .context(ContainerSnafu)?;
+        let host = container.get_host().await.context(ContainerSnafu)?;
+        let port = container
+            .get_host_port_ipv4(PORT)
+            .await
+            .context(ContainerSnafu)?;
+        let url = format!("http://{}:{}", host, port);
+
+        let anonymous = Client::new(&url, ORG, "");
+        wait_until_ready(&anonymous, &url).await?;
+
+        let onboarding = anonymous
+            .onboarding(
+                USERNAME,
+                ORG,
+                BUCKET,
+                Some(PASSWORD.to_string()),
+                Some(0),
+                None,
+            )
+            .await
+            .context(OnboardingSnafu)?;
+        let token = onboarding
+            .auth
+            .and_then(|auth| auth.token)
+            .context(MissingTokenSnafu)?;
+
+        let client = Client::builder(&url, ORG, &token)
+            .with_bucket(BUCKET)
+            .build()
+            .context(ClientSnafu)?;
+
+        Ok(Self {
+            _container: container,
+            client,
+            url,
+            token,
+        })
+    }
+
+    /// A client for the server, using the onboarded organization, bucket
+    /// and token.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The URL of the server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The onboarded organization.
+    pub fn org(&self) -> &str {
+        ORG
+    }
+
+    /// The onboarded bucket, the default bucket of `client`.
+    pub fn bucket(&self) -> &str {
+        BUCKET
+    }
+
+    /// The operator token created by onboarding.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+async fn wait_until_ready(client: &Client, url: &str) -> Result<(), TestingError> {
+    let ready = async {
+        while !matches!(client.ready().await, Ok(true)) {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    };
+    tokio::time::timeout(STARTUP_TIMEOUT, ready)
+        .await
+        .ok()
+        .context(NotReadySnafu { url })
+}
@@ -0,0 +1,422 @@
+//! Record and replay
+//!
+//! A [`Cassette`] attached with `ClientBuilder::with_cassette` captures the
+//! requests a client sends and the responses it gets into a JSON fixture
+//! file, and answers the same requests from that file later, so tests run
+//! against realistic payloads without a live server:
+//!
+//! ```no_run
+//! use influxdb2::vcr::Cassette;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Records against the server while the fixture is missing, replays it
+//! // once it exists
+//! let cassette = Cassette::open("tests/fixtures/health.json")?;
+//! let client = influxdb2::Client::builder("http://localhost:8086", "org", "token")
+//!     .with_cassette(cassette.clone())
+//!     .build()?;
+//!
+//! client.health().await?;
+//! cassette.save()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A request is answered by the first unplayed interaction recorded with
+//! the same method, path, query and body, so a request sent twice needs two
+//! recordings. Requests without one fail with `RequestError::Unrecorded`
+//! instead of reaching the network. The cassette takes the place of the
+//! HTTP exchange of the client, after retries and before response logging.
+//!
+//! Request headers, and so the token, are never recorded. Query parameters
+//! carrying credentials and the string values of the `password`, `token` and
+//! `secret` keys of JSON bodies are redacted, in requests and responses
+//! alike, so a replayed response carries `REDACTED` where the server sent a
+//! secret. Other secrets in bodies are recorded as sent, so review fixtures
+//! before committing them. Bodies that aren't UTF-8, such as compressed
+//! writes, are recorded in base64. Streamed bodies, such as the points of
+//! `Client::write`, aren't recorded and match any body. Requires the `vcr`
+//! feature.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_LENGTH, SET_COOKIE, TRANSFER_ENCODING};
+use serde::{Deserialize, Serialize};
+
+use crate::{logging, RequestError};
+
+/// A fixture file of recorded interactions, shared by its clones
+#[derive(Clone, Debug)]
+pub struct Cassette {
+    tape: Arc<Mutex<Tape>>,
+}
+
+#[derive(Debug)]
+struct Tape {
+    path: PathBuf,
+    recording: bool,
+    interactions: Vec<Interaction>,
+    /// Whether each interaction answered a request, when replaying
+    played: Vec<bool>,
+    unsaved: bool,
+}
+
+/// A request and the response it got
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request
+    pub request: RecordedRequest,
+    /// The response
+    pub response: RecordedResponse,
+}
+
+/// What identifies a recorded request
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The method, e.g. `POST`
+    pub method: String,
+    /// The path and query, e.g. `/api/v2/query?org=org`
+    pub path: String,
+    /// The body, if it wasn't streamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<RecordedBody>,
+}
+
+/// A recorded response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The status code
+    pub status: u16,
+    /// The headers, in the order they were received
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// The body
+    #[serde(default)]
+    pub body: RecordedBody,
+}
+
+/// A recorded body, as text with its secrets redacted, or in base64 when it
+/// isn't UTF-8
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecordedBody {
+    /// A UTF-8 body
+    Text(String),
+    /// Any other body
+    Base64 {
+        /// The body in base64
+        base64: String,
+    },
+}
+
+impl Default for RecordedBody {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl Cassette {
+    /// Record to `path`, replacing the fixture there once saved.
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Self::new(path.as_ref(), true, Vec::new())
+    }
+
+    /// Replay the fixture at `path`.
+    pub fn replay(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let interactions = serde_json::from_slice(&fs::read(path)?).map_err(io::Error::other)?;
+        Ok(Self::new(path, false, interactions))
+    }
+
+    /// Replay the fixture at `path` if it exists, or record it otherwise.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path))
+        }
+    }
+
+    fn new(path: &Path, recording: bool, interactions: Vec<Interaction>) -> Self {
+        let tape = Tape {
+            path: path.to_path_buf(),
+            recording,
+            played: vec![false; interactions.len()],
+            interactions,
+            unsaved: false,
+        };
+        Self {
+            tape: Arc::new(Mutex::new(tape)),
+        }
+    }
+
+    /// Whether requests go to the server and are recorded, rather than
+    /// being replayed.
+    pub fn is_recording(&self) -> bool {
+        self.tape.lock().recording
+    }
+
+    /// The interactions recorded or loaded so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.tape.lock().interactions.clone()
+    }
+
+    /// Write the recorded interactions to the fixture file, creating its
+    /// directory if needed. Does nothing when replaying.
+    ///
+    /// A recording cassette is saved when its last clone is dropped too,
+    /// ignoring errors.
+    pub fn save(&self) -> io::Result<()> {
+        self.tape.lock().save()
+    }
+
+    /// Answer `request`, from the server while recording
+    pub(crate) async fn exchange(
+        &self,
+        http: &reqwest::Client,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, RequestError> {
+        let recorded = RecordedRequest::of(&request);
+        if !self.is_recording() {
+            return self.tape.lock().play(&recorded).ok_or(RequestError::Unrecorded {
+                endpoint: None,
+                request_id: None,
+                idempotency_key: None,
+            });
+        }
+
+        let fail = |source| RequestError::ReqwestProcessing {
+            source,
+            endpoint: None,
+            request_id: None,
+            influx_request_id: None,
+            idempotency_key: None,
+        };
+        let response = http.execute(request).await.map_err(fail)?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| {
+                ![CONTENT_ENCODING, CONTENT_LENGTH, SET_COOKIE, TRANSFER_ENCODING].contains(name)
+            })
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await.map_err(fail)?;
+
+        let response = RecordedResponse {
+            status,
+            headers,
+            body: RecordedBody::of(&body),
+        };
+        let mut tape = self.tape.lock();
+        tape.interactions.push(Interaction {
+            request: recorded,
+            response: response.clone(),
+        });
+        tape.unsaved = true;
+        Ok(response.into_response())
+    }
+}
+
+impl Tape {
+    fn save(&mut self) -> io::Result<()> {
+        if !self.recording {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(&self.interactions).map_err(io::Error::other)?;
+        fs::write(&self.path, json)?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// The response of the first unplayed interaction matching `request`
+    fn play(&mut self, request: &RecordedRequest) -> Option<reqwest::Response> {
+        let position = self
+            .interactions
+            .iter()
+            .zip(&self.played)
+            .position(|(interaction, played)| !played && interaction.request.matches(request))?;
+        self.played[position] = true;
+        Some(self.interactions[position].response.clone().into_response())
+    }
+}
+
+impl Drop for Tape {
+    fn drop(&mut self) {
+        if self.unsaved {
+            let _ = self.save();
+        }
+    }
+}
+
+impl RecordedRequest {
+    fn of(request: &reqwest::Request) -> Self {
+        let url = logging::redact_url(request.url());
+        Self {
+            method: request.method().to_string(),
+            path: url[url::Position::BeforePath..].to_string(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(RecordedBody::of),
+        }
+    }
+
+    /// Whether a request like `request` gets the response of this one
+    fn matches(&self, request: &Self) -> bool {
+        self.method == request.method
+            && self.path == request.path
+            && (request.body.is_none() || self.body == request.body)
+    }
+}
+
+impl RecordedResponse {
+    fn into_response(self) -> reqwest::Response {
+        let mut response = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            response = response.header(name.as_str(), value.as_str());
+        }
+        response
+            .body(self.body.into_bytes())
+            .unwrap_or_else(|_| http::Response::new(Vec::new()))
+            .into()
+    }
+}
+
+impl RecordedBody {
+    fn of(body: &[u8]) -> Self {
+        match std::str::from_utf8(body) {
+            Ok(text) => Self::Text(logging::redact_json_secrets(text)),
+            Err(_) => Self::Base64 {
+                base64: base64::encode(body),
+            },
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            // A fixture edited by hand may hold invalid base64
+            Self::Base64 { base64 } => base64::decode(base64).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Query;
+    use crate::Client;
+    use mockito::{mock, Matcher};
+
+    const CSV: &str = "#datatype,string,long,string,double
+#group,false,false,true,false
+#default,_result,,,
+,result,table,_measurement,_value
+,,0,vcr,1.5
+";
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixtures").join("query.json");
+        let query = || Some(Query::new("from(bucket: \"vcr\")".to_string()));
+
+        let server = mock("POST", "/api/v2/query")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::Regex("vcr".to_string()))
+            .with_header("content-type", "text/csv")
+            .with_body(CSV)
+            .expect(1)
+            .create();
+        let cassette = Cassette::open(&path).unwrap();
+        assert!(cassette.is_recording());
+        let client = Client::builder(mockito::server_url(), "org", "secret-token")
+            .with_cassette(cassette.clone())
+            .build()
+            .unwrap();
+        let recorded = client.query_raw(query()).await.unwrap();
+        cassette.save().unwrap();
+        server.assert();
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret-token"));
+
+        let cassette = Cassette::open(&path).unwrap();
+        assert!(!cassette.is_recording());
+        let client = Client::builder("http://127.0.0.1:1", "org", "secret-token")
+            .with_cassette(cassette)
+            .build()
+            .unwrap();
+        assert_eq!(client.query_raw(query()).await.unwrap(), recorded);
+
+        // The only recording was played
+        let error = client.query_raw(query()).await.unwrap_err();
+        assert!(matches!(error, RequestError::Unrecorded { .. }));
+        assert_eq!(error.endpoint(), Some("POST /api/v2/query"));
+    }
+
+    #[cfg(feature = "management")]
+    #[tokio::test]
+    async fn redacts_secrets_in_bodies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("setup.json");
+        let onboard = |client: Client| async move {
+            client
+                .onboarding("admin", "org", "b", Some("hunter2".into()), None, None)
+                .await
+                .unwrap_err()
+        };
+
+        let server = mock("POST", "/api/v2/setup")
+            .match_body(Matcher::Regex("hunter2".to_string()))
+            .with_status(422)
+            .with_body(r#"{"code":"conflict","message":"taken","token":"new-token"}"#)
+            .expect(1)
+            .create();
+        let cassette = Cassette::record(&path);
+        let client = Client::builder(mockito::server_url(), "org", "")
+            .with_cassette(cassette.clone())
+            .build()
+            .unwrap();
+        onboard(client).await;
+        cassette.save().unwrap();
+        server.assert();
+        let fixture = fs::read_to_string(&path).unwrap();
+        assert!(!fixture.contains("hunter2"));
+        assert!(!fixture.contains("new-token"));
+
+        // Redacted alike, the request matches its recording
+        let client = Client::builder("http://127.0.0.1:1", "org", "")
+            .with_cassette(Cassette::replay(&path).unwrap())
+            .build()
+            .unwrap();
+        let error = onboard(client).await;
+        assert!(matches!(error, RequestError::Http { .. }), "{:?}", error);
+    }
+
+    #[test]
+    fn records_binary_bodies_in_base64() {
+        let body = RecordedBody::of(&[0x28, 0xb5, 0x2f, 0xfd, 0xff]);
+        assert_eq!(
+            body,
+            RecordedBody::Base64 {
+                base64: "KLUv/f8=".to_string()
+            }
+        );
+        let json = serde_json::to_string(&body).unwrap();
+        assert_eq!(json, r#"{"base64":"KLUv/f8="}"#);
+        let body: RecordedBody = serde_json::from_str(&json).unwrap();
+        assert_eq!(body.into_bytes(), vec![0x28, 0xb5, 0x2f, 0xfd, 0xff]);
+
+        let text: RecordedBody = serde_json::from_str(r#""cpu usage=1""#).unwrap();
+        assert_eq!(text, RecordedBody::of(b"cpu usage=1"));
+    }
+}
@@ -64,6 +64,7 @@ impl ServerFixture {
     ///
     /// This is currently implemented as a singleton so all tests *must*
     /// use a new database and not interfere with the existing database.
+    #[allow(clippy::await_holding_lock)]
     pub async fn create_shared() -> Self {
         // Try and reuse the same shared server, if there is already
         // one present
@@ -213,6 +214,7 @@ impl TestServer {
             let ci_image = "quay.io/influxdb/rust:ci";
             let container_name = format!("influxdb2_{}", http_port);
 
+            #[allow(clippy::needless_borrows_for_generic_args)]
             Command::new("docker")
                 .arg("run")
                 .arg("--name")
@@ -0,0 +1,21 @@
+pub mod common;
+
+type Result<T = (), E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+
+#[tokio::test]
+async fn write_and_query_in_a_container() -> Result {
+    let influx = maybe_skip_integration!(influxdb2::testing::InfluxContainer::start()).await?;
+    let client = influx.client();
+
+    client
+        .write_line_protocol_to(influx.bucket(), "cpu,host=a usage=0.5")
+        .await?;
+
+    let query = format!("from(bucket: \"{}\") |> range(start: -1h)", influx.bucket());
+    let rows = client
+        .query_raw(Some(influxdb2::models::Query::new(query)))
+        .await?;
+    assert_eq!(rows.len(), 1);
+
+    Ok(())
+}